@@ -2,15 +2,18 @@
 
 import { useState, useEffect, useCallback } from "react";
 
-// QR scan: URL = http://host:port/?token=xxx — parse both server URL and token from same URL
+// QR scan: URL = http://host:port/?code=xxx (single-use pairing code, exchanged for the token)
+// or the older http://host:port/?token=xxx — parse both server URL and code/token from same URL
 function getInitialUrl() {
   if (typeof window === "undefined") return null;
   const href = window.location.href;
   const params = new URLSearchParams(window.location.search);
   let token = params.get("token");
-  if (!token && window.location.hash) {
+  let code = params.get("code");
+  if (!token && !code && window.location.hash) {
     const hashParams = new URLSearchParams(window.location.hash.replace(/^#/, "").replace(/^\?/, ""));
     token = hashParams.get("token");
+    code = hashParams.get("code");
   }
   let origin = "";
   // 1. Meta tag (reliable, in DOM before script runs)
@@ -39,7 +42,7 @@ function getInitialUrl() {
       origin = window.location.origin || "";
     }
   }
-  return { origin: origin || "", token };
+  return { origin: origin || "", token, code };
 }
 const INITIAL_URL = getInitialUrl();
 import { httpApi } from "../src/lib/http-api";
@@ -88,6 +91,7 @@ export function MobileApp() {
 
       try {
         httpApi.configure(trimmedUrl, trimmedToken);
+        await httpApi.loadCapabilities();
         const status = await httpApi.getStatus();
         setServerName(status.name);
         setTrackCount(status.track_count);
@@ -135,6 +139,29 @@ export function MobileApp() {
       window.history.replaceState({}, "", window.location.pathname);
     }
 
+    // Pairing code from the QR: exchange it for the token, then connect
+    if (INITIAL_URL?.code && !INITIAL_URL.token) {
+      const { code, origin } = INITIAL_URL;
+      window.history.replaceState({}, "", window.location.pathname);
+      fetch("/api/pair", {
+        method: "POST",
+        headers: { "content-type": "application/json" },
+        body: JSON.stringify({ code }),
+      })
+        .then((r) => (r.ok ? r.json() : Promise.reject(new Error("Pairing code invalid or expired — scan the QR again"))))
+        .then((data: { token: string }) => {
+          if (cancelled) return;
+          setToken(data.token);
+          connect(origin, data.token);
+        })
+        .catch((err) => {
+          if (cancelled) return;
+          setConnectionState("error");
+          setErrorMessage(err instanceof Error ? err.message : "Pairing failed");
+        });
+      return () => { cancelled = true; };
+    }
+
     // Server URL: prefer INITIAL_URL, then meta tag, then current page, then localStorage
     let fallback = INITIAL_URL?.origin || localStorage.getItem("companion_url");
     if (!fallback) {
@@ -206,13 +233,25 @@ export function MobileApp() {
     localStorage.removeItem("companion_token");
   }
 
+  // List rows only carry summary fields; fill in album, genre etc. for the
+  // player and Media Session once the full track arrives
+  const showTrack = useCallback((track: Track) => {
+    setCurrentTrack(track);
+    httpApi
+      .getTrack(track.id)
+      .then((full) =>
+        setCurrentTrack((current) => (current?.id === full.id ? full : current))
+      )
+      .catch((err) => console.error("Failed to load track details:", err));
+  }, []);
+
   const handlePlayTrack = useCallback(
     async (track: Track, tracks: Track[], index: number) => {
       try {
         const streamUrl = await httpApi.getStreamUrl(track.id);
         audio.src = streamUrl;
         audio.play();
-        setCurrentTrack(track);
+        showTrack(track);
         setQueue(tracks);
         setQueueIndex(index);
         setIsPlaying(true);
@@ -220,7 +259,7 @@ export function MobileApp() {
         console.error("Failed to play track:", err);
       }
     },
-    [audio]
+    [audio, showTrack]
   );
 
   const handleNext = useCallback(async () => {
@@ -231,14 +270,14 @@ export function MobileApp() {
         const streamUrl = await httpApi.getStreamUrl(nextTrack.id);
         audio.src = streamUrl;
         audio.play();
-        setCurrentTrack(nextTrack);
+        showTrack(nextTrack);
         setQueueIndex(nextIndex);
         setIsPlaying(true);
       } catch (err) {
         console.error("Failed to play next:", err);
       }
     }
-  }, [audio, queue, queueIndex]);
+  }, [audio, queue, queueIndex, showTrack]);
 
   const handlePrevious = useCallback(async () => {
     if (queueIndex > 0) {
@@ -248,14 +287,14 @@ export function MobileApp() {
         const streamUrl = await httpApi.getStreamUrl(prevTrack.id);
         audio.src = streamUrl;
         audio.play();
-        setCurrentTrack(prevTrack);
+        showTrack(prevTrack);
         setQueueIndex(prevIndex);
         setIsPlaying(true);
       } catch (err) {
         console.error("Failed to play previous:", err);
       }
     }
-  }, [audio, queue, queueIndex]);
+  }, [audio, queue, queueIndex, showTrack]);
 
   const handlePlayPause = useCallback(() => {
     if (audio.paused) {
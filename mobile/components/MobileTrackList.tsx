@@ -30,6 +30,26 @@ export function MobileTrackList({ onPlayTrack }: MobileTrackListProps) {
     loadTracks(true);
   }, []);
 
+  // Refresh a row's BPM and key when the desktop finishes analyzing it
+  useEffect(() => {
+    return httpApi.watchEvents((event) => {
+      if (event.type === "track_analyzed" && !event.error) {
+        httpApi
+          .getTrack(event.track_id)
+          .then((analyzed) =>
+            setTracks((prev) =>
+              prev.map((t) =>
+                t.id === analyzed.id
+                  ? { ...t, bpm: analyzed.bpm, musical_key: analyzed.musical_key }
+                  : t
+              )
+            )
+          )
+          .catch((err) => console.error("Failed to refresh track:", err));
+      }
+    });
+  }, []);
+
   // Search debounce
   useEffect(() => {
     const timer = setTimeout(() => {
@@ -47,7 +67,7 @@ export function MobileTrackList({ onPlayTrack }: MobileTrackListProps) {
     setLoading(true);
     try {
       const newOffset = reset ? 0 : offset;
-      const result = await httpApi.getTracksPaginated(PAGE_SIZE, newOffset);
+      const result = await httpApi.getTrackSummaries(PAGE_SIZE, newOffset);
       if (reset) {
         setTracks(result);
         setOffset(PAGE_SIZE);
@@ -131,10 +151,12 @@ export function MobileTrackList({ onPlayTrack }: MobileTrackListProps) {
               </span>
               <span className="mobile-track-artist">
                 {track.artist || "Unknown Artist"}
-                {track.album ? ` — ${track.album}` : ""}
               </span>
             </div>
             <div className="mobile-track-meta">
+              {track.musical_key ? (
+                <span className="mobile-track-key">{track.musical_key}</span>
+              ) : null}
               {track.bpm ? (
                 <span className="mobile-track-bpm">
                   {Math.round(track.bpm)}
@@ -2,18 +2,70 @@
 //
 // Implements communication with Anthropic's Claude API for:
 // - Chat completions with streaming
-// - Playlist generation
-// - Rate limiting and error handling
+// - Playlist generation and refinement
+// - Rate limiting and error handling: 429 (rate limited) and 529 (overloaded) responses
+//   are retried with exponential backoff, and every client holds off meanwhile
+// - Token accounting: a client adds up the usage the API reports, for the caller to
+//   record (Database::record_ai_usage)
 
-use reqwest::{Client, header};
+use reqwest::{Client, StatusCode, header};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 const CLAUDE_API_URL: &str = "https://api.anthropic.com/v1/messages";
 const CLAUDE_MODEL: &str = "claude-sonnet-4-5-20250929";
 const CLAUDE_VERSION: &str = "2023-06-01";
 const MAX_TOKENS: u32 = 4096;
 
+/// List prices of CLAUDE_MODEL, USD per million tokens
+const INPUT_PRICE_PER_MTOK: f64 = 3.0;
+const OUTPUT_PRICE_PER_MTOK: f64 = 15.0;
+
+/// Retries of a rate-limited or overloaded request before giving up
+const MAX_RETRIES: u32 = 4;
+/// Wait before the first retry; doubled for each further one
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Overloaded (Anthropic-specific status)
+const STATUS_OVERLOADED: u16 = 529;
+
+/// Until when no request is sent, after the API asked to slow down. Shared by all clients,
+/// so chat, playlist generation and session naming don't keep hitting the limit in turn.
+static BACKOFF_UNTIL: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Tokens used, as reported by the API
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub struct Usage {
+    /// Requests the usage was summed over
+    #[serde(skip)]
+    pub requests: u64,
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+}
+
+/// What tokens cost at CLAUDE_MODEL's list prices (USD)
+pub fn estimated_cost_usd(input_tokens: u64, output_tokens: u64) -> f64 {
+    (input_tokens as f64 * INPUT_PRICE_PER_MTOK + output_tokens as f64 * OUTPUT_PRICE_PER_MTOK) / 1_000_000.0
+}
+
+/// Wait before retry `attempt` (0-based): exponential, or what the server's Retry-After
+/// asks if longer, up to MAX_BACKOFF
+fn backoff_delay(attempt: u32, retry_after: Option<Duration>) -> Duration {
+    let exponential = INITIAL_BACKOFF.saturating_mul(2u32.saturating_pow(attempt));
+    exponential.max(retry_after.unwrap_or_default()).min(MAX_BACKOFF)
+}
+
+/// Sleep while the shared backoff lasts
+async fn wait_for_backoff() {
+    let until = *BACKOFF_UNTIL.lock().unwrap();
+    if let Some(wait) = until.and_then(|until| until.checked_duration_since(Instant::now())) {
+        tokio::time::sleep(wait).await;
+    }
+}
+
 /// Message in a conversation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
@@ -43,6 +95,8 @@ struct ClaudeResponse {
     content: Vec<ContentBlock>,
     model: String,
     stop_reason: Option<String>,
+    #[serde(default)]
+    usage: Usage,
 }
 
 #[derive(Debug, Deserialize)]
@@ -84,9 +138,31 @@ pub struct PlaylistResponse {
     pub reasoning: String,
 }
 
+/// Where an added or moved track goes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlacedTrack {
+    pub track_id: i64,
+    /// 0-based index in the refined list; missing = at the end
+    #[serde(default)]
+    pub position: Option<usize>,
+}
+
+/// Changes to an existing playlist, as proposed by the model
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlaylistRefinement {
+    pub remove: Vec<i64>,
+    #[serde(rename = "move")]
+    pub moves: Vec<PlacedTrack>,
+    pub add: Vec<PlacedTrack>,
+    pub reasoning: String,
+}
+
 pub struct ClaudeClient {
     api_key: String,
     client: Client,
+    /// Summed over this client's requests
+    usage: Mutex<Usage>,
 }
 
 impl ClaudeClient {
@@ -97,7 +173,51 @@ impl ClaudeClient {
             .build()
             .expect("Failed to create HTTP client");
 
-        Self { api_key, client }
+        Self { api_key, client, usage: Mutex::new(Usage::default()) }
+    }
+
+    /// Tokens used by this client's requests so far (also by those whose answer couldn't be used)
+    pub fn usage(&self) -> Usage {
+        *self.usage.lock().unwrap()
+    }
+
+    /// POST a request, retrying while the API is rate limited or overloaded
+    async fn send(&self, request: &ClaudeRequest) -> Result<reqwest::Response, String> {
+        let mut attempt = 0;
+        loop {
+            wait_for_backoff().await;
+            let response = self
+                .client
+                .post(CLAUDE_API_URL)
+                .header(header::CONTENT_TYPE, "application/json")
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", CLAUDE_VERSION)
+                .json(request)
+                .send()
+                .await
+                .map_err(|e| format!("API request failed: {}", e))?;
+
+            let status = response.status();
+            let retryable = status == StatusCode::TOO_MANY_REQUESTS || status.as_u16() == STATUS_OVERLOADED;
+            if !retryable || attempt >= MAX_RETRIES {
+                return Ok(response);
+            }
+            let retry_after = response
+                .headers()
+                .get(header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .filter(|secs| secs.is_finite() && *secs >= 0.0)
+                .map(Duration::from_secs_f64);
+            let delay = backoff_delay(attempt, retry_after) + Duration::from_millis(rand::random::<u64>() % 250);
+            eprintln!("[ai] API returned {}, retrying in {:.1}s", status, delay.as_secs_f64());
+            {
+                let mut until = BACKOFF_UNTIL.lock().unwrap();
+                let resume_at = Instant::now() + delay;
+                *until = Some(until.map_or(resume_at, |u| u.max(resume_at)));
+            }
+            attempt += 1;
+        }
     }
 
     /// Send a chat message and get a complete response (no streaming)
@@ -114,16 +234,7 @@ impl ClaudeClient {
             stream: None,
         };
 
-        let response = self
-            .client
-            .post(CLAUDE_API_URL)
-            .header(header::CONTENT_TYPE, "application/json")
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", CLAUDE_VERSION)
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| format!("API request failed: {}", e))?;
+        let response = self.send(&request).await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -135,6 +246,12 @@ impl ClaudeClient {
             .json()
             .await
             .map_err(|e| format!("Failed to parse response: {}", e))?;
+        {
+            let mut usage = self.usage.lock().unwrap();
+            usage.requests += 1;
+            usage.input_tokens += claude_response.usage.input_tokens;
+            usage.output_tokens += claude_response.usage.output_tokens;
+        }
 
         // Extract text from content blocks
         let text = claude_response
@@ -177,6 +294,37 @@ impl ClaudeClient {
             .map_err(|e| format!("Failed to parse playlist response: {}", e))
     }
 
+    /// Ask for changes to an existing playlist instead of a new one
+    pub async fn refine_playlist(
+        &self,
+        instruction: String,
+        playlist_tracks: String,
+        track_context: String,
+        system_prompt: String,
+    ) -> Result<PlaylistRefinement, String> {
+        let user_message = format!(
+            "Here is my music library:\n\n{}\n\nHere is the current playlist, in order:\n\n{}\n\n\
+             Change it as follows: {}\n\n\
+             Do not rebuild the playlist. Respond with a JSON object describing only the changes: \
+             remove (array of track IDs to take out), move (array of {{track_id, position}} for \
+             tracks to reorder), add (array of {{track_id, position}} for library tracks to put in) \
+             and reasoning. Positions are 0-based indexes in the refined playlist; leave position \
+             out to append. Moves are applied after removals, adds after moves.",
+            track_context, playlist_tracks, instruction
+        );
+
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: user_message,
+        }];
+
+        let response_text = self.chat(messages, Some(system_prompt)).await?;
+        let json_text = Self::extract_json(&response_text)?;
+
+        serde_json::from_str::<PlaylistRefinement>(&json_text)
+            .map_err(|e| format!("Failed to parse playlist changes: {}", e))
+    }
+
     /// Extract JSON from response text (handles markdown code blocks)
     fn extract_json(text: &str) -> Result<String, String> {
         // Try to find JSON in markdown code block
@@ -231,6 +379,26 @@ Hope you enjoy!"#;
         assert!(json.contains("\"name\": \"Test\""));
     }
 
+    #[test]
+    fn test_backoff_delay() {
+        assert_eq!(backoff_delay(0, None), Duration::from_secs(1));
+        assert_eq!(backoff_delay(2, None), Duration::from_secs(4));
+        assert_eq!(backoff_delay(1, Some(Duration::from_secs(10))), Duration::from_secs(10));
+        assert_eq!(backoff_delay(1, Some(Duration::from_millis(100))), Duration::from_secs(2));
+        assert_eq!(backoff_delay(40, None), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_usage_parsing() {
+        let response: ClaudeResponse = serde_json::from_str(
+            r#"{"id": "msg_1", "type": "message", "role": "assistant", "model": "m",
+                "content": [{"type": "text", "text": "Hi"}], "stop_reason": "end_turn",
+                "usage": {"input_tokens": 1200, "output_tokens": 80, "cache_read_input_tokens": 0}}"#,
+        ).unwrap();
+        assert_eq!(response.usage, Usage { requests: 0, input_tokens: 1200, output_tokens: 80 });
+        assert!((estimated_cost_usd(1_000_000, 100_000) - 4.5).abs() < 1e-9);
+    }
+
     #[test]
     fn test_extract_json_raw() {
         let text = r#"{"name": "Test", "track_ids": [1, 2]}"#;
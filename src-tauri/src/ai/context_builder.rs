@@ -2,10 +2,33 @@
 //
 // Prepares optimized JSON representation of the music library
 // for Claude API with intelligent filtering and token optimization
+//
+// Libraries bigger than CONTEXT_TRACK_BUDGET are sampled: tracks are grouped by genre,
+// BPM band and how recently they were added, and each group gets its share of the budget.
+// Tracks matching the prompt's keywords are sent on top of the sample (see prompt_keywords).
+// Each track carries its listening history (plays, skips, days since last played, cooldown)
+// so requests like "stuff I haven't played in months" can be answered.
 
-use crate::db::{Track, TrackAnalysis};
+use crate::db::{GenreProfile, PlayStats, Track, TrackAnalysis};
 use serde::{Deserialize, Serialize};
 use serde_json;
+use std::collections::{HashMap, HashSet};
+
+/// Most tracks sent as library context (~30 tokens each)
+pub const CONTEXT_TRACK_BUDGET: usize = 4000;
+/// Most prompt-matched tracks sent on top of the sample
+pub const MAX_MATCHED_TRACKS: usize = 500;
+/// Width of the BPM bands used for sampling
+const SAMPLE_BPM_BAND: f64 = 10.0;
+/// Number of date-added bands (newest quarter, ..., oldest quarter)
+const SAMPLE_RECENCY_BANDS: usize = 4;
+
+/// Words left out of prompt keyword searches
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "with", "some", "from", "that", "this", "into", "like", "make", "give",
+    "playlist", "tracks", "track", "songs", "song", "music", "set", "mix", "minutes", "hour",
+    "hours", "bpm", "please", "more", "less", "only", "about", "around", "between", "without",
+];
 
 /// Condensed track representation for AI context
 #[derive(Debug, Serialize, Deserialize)]
@@ -27,6 +50,16 @@ pub struct TrackContext {
     pub key: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub duration_s: Option<i32>,
+    /// Lifetime plays (left out if never played)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plays: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skips: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub days_since_played: Option<i64>,
+    /// Not to be used in playlists before this date ('YYYY-MM-DD')
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cooldown_until: Option<String>,
 }
 
 /// Library statistics for AI context
@@ -37,6 +70,9 @@ pub struct LibraryStats {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bpm_range: Option<(i32, i32)>,
     pub common_keys: Vec<String>,
+    /// Set when only a sample of the library is listed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampled_tracks: Option<usize>,
 }
 
 /// Complete context sent to AI
@@ -46,16 +82,66 @@ pub struct AIContext {
     pub tracks: Vec<TrackContext>,
 }
 
+/// Library context plus what it covers
+#[derive(Debug, Clone)]
+pub struct SampledContext {
+    pub context: String,
+    /// IDs of the tracks listed in `context`
+    pub track_ids: HashSet<i64>,
+    pub total_tracks: usize,
+    /// Play history the context was built from (Database::get_play_history_stamp)
+    pub history_stamp: Option<String>,
+}
+
+impl SampledContext {
+    /// Share of the library listed, 0-100
+    pub fn coverage_pct(&self, extra_tracks: usize) -> f64 {
+        if self.total_tracks == 0 {
+            return 100.0;
+        }
+        ((self.track_ids.len() + extra_tracks) as f64 * 100.0 / self.total_tracks as f64).min(100.0)
+    }
+}
+
 pub struct TrackContextBuilder;
 
 impl TrackContextBuilder {
-    /// Build full context from all tracks and their analysis
+    /// Typical BPM range and keys of the genres named in `prompt` (learned genre profiles),
+    /// so "a techno set" gets the tempos and keys this library's techno actually has.
+    /// Empty if the prompt names none.
+    pub fn genre_profile_notes(profiles: &[GenreProfile], prompt: &str) -> String {
+        let prompt_lower = prompt.to_lowercase();
+        let lines: Vec<String> = profiles
+            .iter()
+            .filter(|p| prompt_lower.contains(&p.genre.to_lowercase()))
+            .map(|p| {
+                let mut line = format!("- {} ({} tracks)", p.genre, p.track_count);
+                if let (Some(min), Some(max)) = (p.min_bpm, p.max_bpm) {
+                    line.push_str(&format!(": typically {:.0}-{:.0} BPM", min, max));
+                    if let Some(median) = p.median_bpm {
+                        line.push_str(&format!(" (median {})", median));
+                    }
+                }
+                if !p.common_keys.is_empty() {
+                    line.push_str(&format!(", common keys {}", p.common_keys.join(", ")));
+                }
+                line
+            })
+            .collect();
+        if lines.is_empty() {
+            return String::new();
+        }
+        format!("\nGenres in the request, as they are in this library:\n{}\n", lines.join("\n"))
+    }
+
+    /// Build full context from all tracks, their analysis and play stats
     pub fn build_full_context(
-        tracks: &[(Track, Option<TrackAnalysis>)]
+        tracks: &[(Track, Option<TrackAnalysis>)],
+        play_stats: &HashMap<i64, PlayStats>,
     ) -> Result<String, String> {
         let track_contexts: Vec<TrackContext> = tracks
             .iter()
-            .map(|(track, analysis)| Self::track_to_context(track, analysis.as_ref()))
+            .map(|(track, analysis)| Self::track_to_context(track, analysis.as_ref(), play_stats))
             .collect();
 
         let stats = Self::calculate_stats(tracks);
@@ -69,11 +155,122 @@ impl TrackContextBuilder {
             .map_err(|e| format!("Failed to serialize context: {}", e))
     }
 
+    /// Library context within `budget` tracks: everything if it fits, a stratified sample
+    /// (see sample_tracks) otherwise. Stats always describe the whole library.
+    pub fn build_sampled_context(
+        tracks: &[(Track, Option<TrackAnalysis>)],
+        budget: usize,
+        play_stats: &HashMap<i64, PlayStats>,
+    ) -> Result<SampledContext, String> {
+        let mut stats = Self::calculate_stats(tracks);
+        let selected: Vec<&(Track, Option<TrackAnalysis>)> = if tracks.len() > budget {
+            stats.sampled_tracks = Some(budget);
+            Self::sample_tracks(tracks, budget).into_iter().map(|i| &tracks[i]).collect()
+        } else {
+            tracks.iter().collect()
+        };
+
+        let context = AIContext {
+            library_stats: stats,
+            tracks: selected
+                .iter()
+                .map(|(track, analysis)| Self::track_to_context(track, analysis.as_ref(), play_stats))
+                .collect(),
+        };
+        let mut json = serde_json::to_string_pretty(&context)
+            .map_err(|e| format!("Failed to serialize context: {}", e))?;
+        if tracks.len() > budget {
+            json.push_str(&format!(
+                "\n\nThis is a sample of {} of the library's {} tracks, spread across genres, \
+                 tempos and when they were added.\n",
+                budget,
+                tracks.len()
+            ));
+        }
+
+        Ok(SampledContext {
+            context: json,
+            track_ids: selected.iter().filter_map(|(track, _)| track.id).collect(),
+            total_tracks: tracks.len(),
+            history_stamp: None,
+        })
+    }
+
+    /// Pick `budget` tracks (indexes into `tracks`) so every genre / BPM band / date-added
+    /// band keeps its share of the library: each group gets its proportional number of
+    /// slots, leftover slots go to the biggest groups. Newest tracks first within a group.
+    pub fn sample_tracks(tracks: &[(Track, Option<TrackAnalysis>)], budget: usize) -> Vec<usize> {
+        if tracks.len() <= budget {
+            return (0..tracks.len()).collect();
+        }
+
+        // Newest first; the rank decides the date-added band
+        let mut by_recency: Vec<usize> = (0..tracks.len()).collect();
+        by_recency.sort_by(|&a, &b| tracks[b].0.date_added.cmp(&tracks[a].0.date_added));
+
+        let mut groups: HashMap<(String, i64, usize), Vec<usize>> = HashMap::new();
+        for (rank, &index) in by_recency.iter().enumerate() {
+            let (track, analysis) = &tracks[index];
+            let genre = track.genre.as_deref().unwrap_or("").trim().to_lowercase();
+            let bpm_band = analysis
+                .as_ref()
+                .and_then(|a| a.bpm)
+                .map(|bpm| (bpm / SAMPLE_BPM_BAND).floor() as i64)
+                .unwrap_or(-1);
+            let recency_band = rank * SAMPLE_RECENCY_BANDS / tracks.len();
+            groups.entry((genre, bpm_band, recency_band)).or_default().push(index);
+        }
+
+        // Biggest groups first (ties by key, so the sample is stable between rebuilds)
+        let mut groups: Vec<((String, i64, usize), Vec<usize>)> = groups.into_iter().collect();
+        groups.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+
+        let mut taken: Vec<usize> = groups
+            .iter()
+            .map(|(_, members)| members.len() * budget / tracks.len())
+            .collect();
+        let mut left = budget - taken.iter().sum::<usize>();
+        while left > 0 {
+            for (i, (_, members)) in groups.iter().enumerate() {
+                if left > 0 && taken[i] < members.len() {
+                    taken[i] += 1;
+                    left -= 1;
+                }
+            }
+        }
+
+        let mut selected: Vec<usize> = groups
+            .iter()
+            .zip(taken)
+            .flat_map(|((_, members), n)| members[..n].iter().copied())
+            .collect();
+        selected.sort_unstable();
+        selected
+    }
+
+    /// Words of a prompt worth searching the library for (artists, labels, genres...)
+    pub fn prompt_keywords(prompt: &str) -> Vec<String> {
+        let mut keywords: Vec<String> = Vec::new();
+        for word in prompt.split(|c: char| !c.is_alphanumeric() && c != '-' && c != '\'') {
+            let word = word.trim_matches(|c| c == '-' || c == '\'').to_lowercase();
+            if word.chars().count() < 3
+                || word.chars().all(|c| c.is_ascii_digit())
+                || STOP_WORDS.contains(&word.as_str())
+                || keywords.contains(&word)
+            {
+                continue;
+            }
+            keywords.push(word);
+        }
+        keywords
+    }
+
     /// Build smart context with filtering based on prompt keywords
     /// For large libraries (>5K tracks), this intelligently filters tracks
     pub fn build_smart_context(
         tracks: &[(Track, Option<TrackAnalysis>)],
         prompt: &str,
+        play_stats: &HashMap<i64, PlayStats>,
     ) -> Result<String, String> {
         let prompt_lower = prompt.to_lowercase();
 
@@ -101,11 +298,30 @@ impl TrackContextBuilder {
             .cloned()
             .collect();
 
-        Self::build_full_context(&limited_tracks)
+        Self::build_full_context(&limited_tracks, play_stats)
     }
 
-    /// Convert Track + TrackAnalysis to condensed TrackContext
-    fn track_to_context(track: &Track, analysis: Option<&TrackAnalysis>) -> TrackContext {
+    /// Condensed list of some tracks (e.g. a playlist), order kept
+    pub fn build_track_list(
+        tracks: &[(Track, Option<TrackAnalysis>)],
+        play_stats: &HashMap<i64, PlayStats>,
+    ) -> Result<String, String> {
+        let track_contexts: Vec<TrackContext> = tracks
+            .iter()
+            .map(|(track, analysis)| Self::track_to_context(track, analysis.as_ref(), play_stats))
+            .collect();
+
+        serde_json::to_string_pretty(&track_contexts)
+            .map_err(|e| format!("Failed to serialize tracks: {}", e))
+    }
+
+    /// Convert Track + TrackAnalysis + play stats to condensed TrackContext
+    fn track_to_context(
+        track: &Track,
+        analysis: Option<&TrackAnalysis>,
+        play_stats: &HashMap<i64, PlayStats>,
+    ) -> TrackContext {
+        let stats = track.id.and_then(|id| play_stats.get(&id));
         TrackContext {
             id: track.id.unwrap_or(0),
             title: track.title.clone(),
@@ -116,6 +332,10 @@ impl TrackContextBuilder {
             bpm: analysis.and_then(|a| a.bpm).map(|b| (b * 10.0).round() / 10.0), // Round to 1 decimal
             key: analysis.and_then(|a| a.musical_key.clone()),
             duration_s: track.duration_ms.map(|ms| ms / 1000),
+            plays: Some(track.play_count).filter(|&plays| plays > 0),
+            skips: stats.map(|s| s.skips).filter(|&skips| skips > 0),
+            days_since_played: stats.and_then(|s| s.days_since_played),
+            cooldown_until: stats.and_then(|s| s.cooldown_until.clone()),
         }
     }
 
@@ -155,6 +375,7 @@ impl TrackContextBuilder {
             analyzed_tracks: analyzed,
             bpm_range,
             common_keys,
+            sampled_tracks: None,
         }
     }
 
@@ -219,6 +440,7 @@ impl TrackContextBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::create_test_track;
 
     #[test]
     fn test_track_context_serialization() {
@@ -232,10 +454,104 @@ mod tests {
             bpm: Some(128.5),
             key: Some("8A".to_string()),
             duration_s: Some(300),
+            plays: None,
+            skips: None,
+            days_since_played: None,
+            cooldown_until: None,
         };
 
         let json = serde_json::to_string(&context).unwrap();
         assert!(json.contains("Test Track"));
         assert!(json.contains("128.5"));
+        assert!(!json.contains("plays"));
+    }
+
+    #[test]
+    fn test_track_list_play_stats() {
+        let (mut played, analysis) = track(1, "House", 122.0, 1);
+        played.play_count = 4;
+        let tracks = vec![(played, analysis), track(2, "House", 123.0, 2), track(3, "House", 124.0, 3)];
+        let play_stats = HashMap::from([
+            (1, PlayStats { skips: 1, days_since_played: Some(190), cooldown_until: None }),
+            (3, PlayStats { skips: 0, days_since_played: None, cooldown_until: Some("2999-01-01".to_string()) }),
+        ]);
+
+        let list = TrackContextBuilder::build_track_list(&tracks, &play_stats).unwrap();
+        let list: Vec<serde_json::Value> = serde_json::from_str(&list).unwrap();
+        assert_eq!(list[0]["plays"], 4);
+        assert_eq!(list[0]["skips"], 1);
+        assert_eq!(list[0]["days_since_played"], 190);
+        assert!(list[1].get("plays").is_none() && list[1].get("days_since_played").is_none());
+        assert!(list[2].get("skips").is_none());
+        assert_eq!(list[2]["cooldown_until"], "2999-01-01");
+    }
+
+    fn track(id: i64, genre: &str, bpm: f64, day: usize) -> (Track, Option<TrackAnalysis>) {
+        let track = Track {
+            id: Some(id),
+            file_path: format!("/music/{}.mp3", id),
+            file_hash: id.to_string(),
+            date_added: Some(format!("2024-01-{:02} 00:00:00", day)),
+            genre: Some(genre.to_string()),
+            ..create_test_track()
+        };
+        let analysis = TrackAnalysis {
+            track_id: id,
+            bpm: Some(bpm),
+            bpm_confidence: None,
+            musical_key: None,
+            key_confidence: None,
+            loudness_lufs: None,
+            dynamic_range: None,
+            spectral_centroid: None,
+            analyzed_at: None,
+            silence_lead_ms: None,
+            silence_tail_ms: None,
+            tempo_variable: None,
+        };
+        (track, Some(analysis))
+    }
+
+    #[test]
+    fn test_sample_tracks_keeps_every_group() {
+        // 90 techno tracks at 130 BPM, 10 house tracks at 122
+        let mut tracks: Vec<_> = (0..90).map(|i| track(i, "Techno", 130.0, 1 + (i % 28) as usize)).collect();
+        tracks.extend((90..100).map(|i| track(i, "House", 122.0, 1 + (i % 28) as usize)));
+
+        let sample = TrackContextBuilder::sample_tracks(&tracks, 20);
+        assert_eq!(sample.len(), 20);
+        let house = sample.iter().filter(|&&i| tracks[i].0.genre.as_deref() == Some("House")).count();
+        assert!((1..=4).contains(&house), "house tracks in sample: {}", house);
+
+        let context = TrackContextBuilder::build_sampled_context(&tracks, 20, &HashMap::new()).unwrap();
+        assert_eq!(context.track_ids.len(), 20);
+        assert_eq!(context.coverage_pct(5), 25.0);
+        assert!(context.context.contains("\"sampled_tracks\": 20"));
+
+        let all = TrackContextBuilder::build_sampled_context(&tracks, 500, &HashMap::new()).unwrap();
+        assert_eq!(all.coverage_pct(0), 100.0);
+    }
+
+    #[test]
+    fn test_genre_profile_notes() {
+        let profiles = vec![GenreProfile {
+            genre: "Techno".to_string(),
+            track_count: 40,
+            min_bpm: Some(128.0),
+            median_bpm: Some(132.5),
+            max_bpm: Some(138.0),
+            common_keys: vec!["8A".to_string(), "5A".to_string()],
+        }];
+        let notes = TrackContextBuilder::genre_profile_notes(&profiles, "Dark TECHNO for peak time");
+        assert!(notes.contains("- Techno (40 tracks): typically 128-138 BPM (median 132.5), common keys 8A, 5A"));
+        assert_eq!(TrackContextBuilder::genre_profile_notes(&profiles, "Deep house warm-up"), "");
+    }
+
+    #[test]
+    fn test_prompt_keywords() {
+        assert_eq!(
+            TrackContextBuilder::prompt_keywords("Make a 60 minute Dixon-style deep house set, 122 BPM"),
+            vec!["minute", "dixon-style", "deep", "house"]
+        );
     }
 }
@@ -4,70 +4,146 @@
 // - macOS: Keychain
 // - Windows: Credential Manager
 // - Linux: Secret Service (GNOME/KDE)
+//
+// One keychain entry per provider. Keys can be checked with a cheap
+// authenticated request (validate_key).
 
 use keyring::Entry;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 const SERVICE_NAME: &str = "com.recodeck.app";
-const API_KEY_NAME: &str = "claude_api_key";
+/// Timeout for key validation requests
+const VALIDATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A service RecoDeck holds an API key for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiProvider {
+    /// Claude (AI playlists and chat)
+    Anthropic,
+    Openai,
+    /// AcoustID audio fingerprint lookups
+    Acoustid,
+    Discogs,
+}
 
-pub struct CredentialManager;
+impl ApiProvider {
+    pub const ALL: [ApiProvider; 4] = [
+        ApiProvider::Anthropic,
+        ApiProvider::Openai,
+        ApiProvider::Acoustid,
+        ApiProvider::Discogs,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiProvider::Anthropic => "anthropic",
+            ApiProvider::Openai => "openai",
+            ApiProvider::Acoustid => "acoustid",
+            ApiProvider::Discogs => "discogs",
+        }
+    }
 
-impl CredentialManager {
-    /// Store the Claude API key in the OS keychain
-    pub fn store_api_key(key: &str) -> Result<(), String> {
-        // Validate key format (Claude keys start with "sk-ant-")
-        // More permissive validation to accept various Claude API key formats
-        if !key.starts_with("sk-ant-") {
-            return Err("Invalid API key format. Claude API keys should start with 'sk-ant-'".to_string());
+    /// Keychain entry name (the Claude key keeps its original name)
+    fn key_name(&self) -> &'static str {
+        match self {
+            ApiProvider::Anthropic => "claude_api_key",
+            ApiProvider::Openai => "openai_api_key",
+            ApiProvider::Acoustid => "acoustid_api_key",
+            ApiProvider::Discogs => "discogs_token",
         }
+    }
 
-        // Additional length check (Claude keys are typically quite long)
-        if key.len() < 20 {
-            return Err("API key appears too short. Please check and try again.".to_string());
+    /// Offline sanity check of a key's shape, before it is stored
+    pub fn check_format(&self, key: &str) -> Result<(), String> {
+        match self {
+            // Claude keys start with "sk-ant-" and are typically quite long
+            ApiProvider::Anthropic if !key.starts_with("sk-ant-") => {
+                Err("Invalid API key format. Claude API keys should start with 'sk-ant-'".to_string())
+            }
+            ApiProvider::Openai if !key.starts_with("sk-") => {
+                Err("Invalid API key format. OpenAI API keys should start with 'sk-'".to_string())
+            }
+            ApiProvider::Anthropic | ApiProvider::Openai if key.len() < 20 => {
+                Err("API key appears too short. Please check and try again.".to_string())
+            }
+            _ if key.trim().is_empty() || key.chars().any(char::is_whitespace) => {
+                Err("API key cannot be empty or contain spaces".to_string())
+            }
+            _ => Ok(()),
         }
+    }
+}
 
-        let entry = Entry::new(SERVICE_NAME, API_KEY_NAME)
-            .map_err(|e| format!("Failed to access keychain: {}", e))?;
+/// Result of a validation request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyValidation {
+    Valid,
+    /// The service rejected the key
+    Rejected(String),
+}
+
+pub struct CredentialManager;
+
+impl CredentialManager {
+    fn entry(provider: ApiProvider) -> Result<Entry, String> {
+        Entry::new(SERVICE_NAME, provider.key_name())
+            .map_err(|e| format!("Failed to access keychain: {}", e))
+    }
 
-        entry
+    /// Store a provider's API key in the OS keychain
+    pub fn store_key(provider: ApiProvider, key: &str) -> Result<(), String> {
+        provider.check_format(key)?;
+
+        Self::entry(provider)?
             .set_password(key)
             .map_err(|e| format!("Failed to store API key: {}", e))?;
 
-        println!("✓ API key stored successfully in keychain");
+        println!("✓ {} API key stored successfully in keychain", provider.as_str());
         Ok(())
     }
 
-    /// Retrieve the Claude API key from the OS keychain
-    pub fn retrieve_api_key() -> Result<Option<String>, String> {
-        let entry = Entry::new(SERVICE_NAME, API_KEY_NAME)
-            .map_err(|e| format!("Failed to access keychain: {}", e))?;
-
-        match entry.get_password() {
+    /// Retrieve a provider's API key from the OS keychain
+    pub fn retrieve_key(provider: ApiProvider) -> Result<Option<String>, String> {
+        match Self::entry(provider)?.get_password() {
             Ok(key) => {
-                println!("✓ API key retrieved from keychain (length: {})", key.len());
+                println!("✓ {} API key retrieved from keychain (length: {})", provider.as_str(), key.len());
                 Ok(Some(key))
             },
             Err(keyring::Error::NoEntry) => {
-                println!("⚠ No API key found in keychain");
+                println!("⚠ No {} API key found in keychain", provider.as_str());
                 Ok(None)
             },
             Err(e) => {
-                println!("✗ Failed to retrieve API key: {}", e);
+                println!("✗ Failed to retrieve {} API key: {}", provider.as_str(), e);
                 Err(format!("Failed to retrieve API key: {}", e))
             },
         }
     }
 
-    /// Delete the Claude API key from the OS keychain
-    pub fn delete_api_key() -> Result<(), String> {
-        let entry = Entry::new(SERVICE_NAME, API_KEY_NAME)
-            .map_err(|e| format!("Failed to access keychain: {}", e))?;
+    /// Delete a provider's API key from the OS keychain (no-op if there is none)
+    pub fn delete_key(provider: ApiProvider) -> Result<(), String> {
+        match Self::entry(provider)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!("Failed to delete API key: {}", e)),
+        }
+    }
 
-        entry
-            .delete_credential()
-            .map_err(|e| format!("Failed to delete API key: {}", e))?;
+    /// Store the Claude API key in the OS keychain
+    pub fn store_api_key(key: &str) -> Result<(), String> {
+        Self::store_key(ApiProvider::Anthropic, key)
+    }
 
-        Ok(())
+    /// Retrieve the Claude API key from the OS keychain
+    pub fn retrieve_api_key() -> Result<Option<String>, String> {
+        Self::retrieve_key(ApiProvider::Anthropic)
+    }
+
+    /// Delete the Claude API key from the OS keychain
+    pub fn delete_api_key() -> Result<(), String> {
+        Self::delete_key(ApiProvider::Anthropic)
     }
 
     /// Check if an API key is stored (without retrieving it)
@@ -87,6 +163,59 @@ impl CredentialManager {
             },
         }
     }
+
+    /// Check a key against the provider with a cheap authenticated request
+    /// (model list / minimal lookup). Err means the check itself failed (e.g. offline).
+    pub async fn validate_key(provider: ApiProvider, key: &str) -> Result<KeyValidation, String> {
+        let client = Client::builder()
+            .timeout(VALIDATION_TIMEOUT)
+            .user_agent(concat!("RecoDeck/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+        let request = match provider {
+            ApiProvider::Anthropic => client
+                .get("https://api.anthropic.com/v1/models")
+                .header("x-api-key", key)
+                .header("anthropic-version", "2023-06-01"),
+            ApiProvider::Openai => client
+                .get("https://api.openai.com/v1/models")
+                .bearer_auth(key),
+            // AcoustID answers 200 or 400 either way; the error code says whether the key was the problem
+            ApiProvider::Acoustid => client
+                .get("https://api.acoustid.org/v2/lookup")
+                .query(&[("client", key), ("duration", "1"), ("fingerprint", "x")]),
+            ApiProvider::Discogs => client
+                .get("https://api.discogs.com/oauth/identity")
+                .header("Authorization", format!("Discogs token={}", key)),
+        };
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach {}: {}", provider.as_str(), e))?;
+        let status = response.status();
+
+        if provider == ApiProvider::Acoustid {
+            let body: serde_json::Value = response
+                .json()
+                .await
+                .map_err(|e| format!("Invalid response from acoustid: {}", e))?;
+            // Error code 4 = invalid API key
+            return Ok(match body["error"]["code"].as_i64() {
+                Some(4) => KeyValidation::Rejected("Invalid API key".to_string()),
+                _ => KeyValidation::Valid,
+            });
+        }
+
+        match status {
+            s if s.is_success() => Ok(KeyValidation::Valid),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Ok(KeyValidation::Rejected(format!("Key rejected ({})", status)))
+            }
+            _ => Err(format!("Unexpected response from {}: {}", provider.as_str(), status)),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -101,4 +230,14 @@ mod tests {
         // Invalid key
         assert!(CredentialManager::store_api_key("invalid-key").is_err());
     }
+
+    #[test]
+    fn test_provider_key_format() {
+        assert!(ApiProvider::Openai.check_format("sk-proj-0123456789abcdef").is_ok());
+        assert!(ApiProvider::Openai.check_format("sk-ant-api03-test123").is_ok());
+        assert!(ApiProvider::Openai.check_format("pk-0123456789abcdefghij").is_err());
+        assert!(ApiProvider::Acoustid.check_format("AbCdEf12").is_ok());
+        assert!(ApiProvider::Discogs.check_format("two words").is_err());
+        assert_eq!(serde_json::to_string(&ApiProvider::Acoustid).unwrap(), "\"acoustid\"");
+    }
 }
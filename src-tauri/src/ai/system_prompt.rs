@@ -18,6 +18,8 @@ You have access to the user's complete track library with metadata including:
 - Musical Key (Camelot notation: 1A-12A, 1B-12B)
 - Duration, Year, File Format
 - Genre/Style tags (when available)
+- Listening history: plays, skips, days_since_played (missing = never played) and
+  cooldown_until (a date; don't put the track in playlists before it)
 - Energy, Mood, Danceability (future ML features)
 
 When creating playlists:
@@ -0,0 +1,154 @@
+// Artwork thumbnails
+// Track lists show covers at 64px and detail views at 256px; decoding a 3000px cover for
+// every row is slow. So each size is rendered once to a small JPEG in the thumbnail cache
+// (a folder next to the database) and served from there. The original comes from the
+// track's cover file (artwork_path) or else the picture embedded in the audio file.
+// A thumbnail older than its source file is rendered again.
+
+use crate::db::Database;
+use image::codecs::jpeg::JpegEncoder;
+use lofty::picture::PictureType;
+use lofty::prelude::*;
+use lofty::read_from_path;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Thumbnail edge lengths in pixels (64: track lists, 256: detail views)
+pub const THUMBNAIL_SIZES: [u32; 2] = [64, 256];
+const THUMBNAIL_DIR: &str = "artwork_thumbnails";
+const JPEG_QUALITY: u8 = 85;
+
+pub fn check_size(size: u32) -> Result<(), String> {
+    if THUMBNAIL_SIZES.contains(&size) {
+        Ok(())
+    } else {
+        Err(format!("Unsupported thumbnail size {} (use 64 or 256)", size))
+    }
+}
+
+/// Thumbnail cache folder, next to the database file. None for an in-memory database.
+pub fn thumbnail_dir(db: &Database) -> Option<PathBuf> {
+    Some(db.file_path()?.parent()?.join(THUMBNAIL_DIR))
+}
+
+fn thumbnail_path(dir: &Path, track_id: i64, size: u32) -> PathBuf {
+    dir.join(format!("{}_{}.jpg", track_id, size))
+}
+
+/// The track's original artwork: its cover file, or else the picture embedded in the audio
+/// file (the front cover if there are several). None if it has neither.
+pub fn read_artwork(file_path: &str, artwork_path: Option<&str>) -> Result<Option<Vec<u8>>, String> {
+    if let Some(artwork_path) = artwork_path {
+        return fs::read(artwork_path)
+            .map(Some)
+            .map_err(|e| format!("Failed to read artwork {}: {}", artwork_path, e));
+    }
+    let tagged_file = read_from_path(file_path)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let pictures: Vec<_> = tagged_file.tags().iter().flat_map(|tag| tag.pictures()).collect();
+    let picture = pictures
+        .iter()
+        .find(|p| p.pic_type() == PictureType::CoverFront)
+        .or_else(|| pictures.first());
+    Ok(picture.map(|p| p.data().to_vec()))
+}
+
+/// Content-Type of an image, from its first bytes
+pub fn image_mime_type(data: &[u8]) -> &'static str {
+    image::guess_format(data)
+        .map(|format| format.to_mime_type())
+        .unwrap_or("application/octet-stream")
+}
+
+/// Scale an image down to fit `size` x `size` (never up) and encode it as JPEG
+fn render_thumbnail(data: &[u8], size: u32) -> Result<Vec<u8>, String> {
+    let image = image::load_from_memory(data)
+        .map_err(|e| format!("Failed to decode artwork: {}", e))?;
+    let image = if image.width() > size || image.height() > size {
+        image.thumbnail(size, size)
+    } else {
+        image
+    };
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY)
+        .encode_image(&image.to_rgb8())
+        .map_err(|e| format!("Failed to encode thumbnail: {}", e))?;
+    Ok(jpeg)
+}
+
+/// Path of a track's thumbnail at `size` in `dir`, rendered first if missing or older than
+/// the artwork. None if the track has no artwork; that is cached too (as an empty file),
+/// so rows without covers don't read tags every time.
+pub fn thumbnail(
+    dir: &Path,
+    track_id: i64,
+    file_path: &str,
+    artwork_path: Option<&str>,
+    size: u32,
+) -> Result<Option<PathBuf>, String> {
+    check_size(size)?;
+    let path = thumbnail_path(dir, track_id, size);
+    let source_modified = fs::metadata(artwork_path.unwrap_or(file_path))
+        .and_then(|m| m.modified())
+        .ok();
+    if let Ok(cached) = fs::metadata(&path) {
+        let fresh = match (cached.modified(), source_modified) {
+            (Ok(cached_at), Some(source_at)) => cached_at >= source_at,
+            _ => true,
+        };
+        if fresh {
+            return Ok((cached.len() > 0).then_some(path));
+        }
+    }
+
+    let jpeg = match read_artwork(file_path, artwork_path)? {
+        Some(data) => render_thumbnail(&data, size)?,
+        None => Vec::new(),
+    };
+    fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create thumbnail folder: {}", e))?;
+    // Via a temporary file: a thumbnail being written may be requested at the same time
+    let temp = path.with_extension(format!("{}.tmp", rand::random::<u32>()));
+    fs::write(&temp, &jpeg)
+        .and_then(|_| fs::rename(&temp, &path))
+        .map_err(|e| format!("Failed to write thumbnail: {}", e))?;
+    Ok((!jpeg.is_empty()).then_some(path))
+}
+
+/// Drop a track's cached thumbnails (e.g. after its artwork changed)
+pub fn remove_thumbnails(dir: &Path, track_id: i64) {
+    for size in THUMBNAIL_SIZES {
+        let _ = fs::remove_file(thumbnail_path(dir, track_id, size));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_thumbnail() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = temp_dir.path().join("cache");
+        let cover = temp_dir.path().join("cover.png");
+        image::RgbImage::from_pixel(600, 300, image::Rgb([200, 40, 40])).save(&cover).unwrap();
+        let cover = cover.to_str().unwrap();
+
+        let path = thumbnail(&cache, 1, "/missing.mp3", Some(cover), 64).unwrap().unwrap();
+        let thumb = image::open(&path).unwrap();
+        assert_eq!((thumb.width(), thumb.height()), (64, 32));
+        assert_eq!(image_mime_type(&fs::read(&path).unwrap()), "image/jpeg");
+        assert_eq!(thumbnail(&cache, 1, "/missing.mp3", Some(cover), 64).unwrap(), Some(path.clone()));
+        assert!(thumbnail(&cache, 1, "/missing.mp3", Some(cover), 100).is_err());
+
+        // A track without artwork is remembered as such
+        let none = temp_dir.path().join("plain.mp3");
+        fs::write(&none, b"not really audio").unwrap();
+        fs::write(thumbnail_path(&cache, 2, 256), b"").unwrap();
+        assert_eq!(thumbnail(&cache, 2, none.to_str().unwrap(), None, 256).unwrap(), None);
+
+        remove_thumbnails(&cache, 1);
+        assert!(!path.exists());
+    }
+}
@@ -11,8 +11,13 @@
 // 4. Returns BPM estimate and confidence score
 //
 // The BPM result is stored in the track_analysis table alongside other DSP data.
+//
+// For tracks that weren't produced to a grid (live recordings, older disco), the same
+// detector also runs over consecutive windows to build a tempo curve; a wide spread
+// between windows marks the track as "variable tempo".
 
 use bliss_audio_aubio_rs::{OnsetMode, Tempo};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
 use super::decoder::{decode_to_mono, MonoAudio};
@@ -34,6 +39,65 @@ const BUF_SIZE: usize = 1024;
 /// 512 samples = 50% overlap, which gives good temporal resolution for beat tracking.
 const HOP_SIZE: usize = 512;
 
+/// Length of each tempo-curve window
+pub const TEMPO_WINDOW_SECS: u32 = 20;
+/// Step between tempo-curve windows (50% overlap)
+const TEMPO_STEP_SECS: u32 = 10;
+/// Spread (BPM between the 10th and 90th percentile window) above which a
+/// track counts as variable tempo. Quantized tracks stay well below this.
+pub const VARIABLE_TEMPO_SPREAD: f64 = 1.5;
+
+/// BPM measured over one window of a track
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TempoPoint {
+    /// Middle of the window
+    pub time_ms: u64,
+    pub bpm: f64,
+}
+
+/// Tempo over the course of a track
+#[derive(Debug, Clone, PartialEq)]
+pub struct TempoCurve {
+    pub points: Vec<TempoPoint>,
+    pub min_bpm: f64,
+    pub max_bpm: f64,
+    /// BPM between the 10th and 90th percentile window (ignores odd outliers)
+    pub spread: f64,
+    pub variable: bool,
+}
+
+impl TempoCurve {
+    /// Summarize per-window BPMs. Windows that locked onto half/double tempo
+    /// are folded back to the octave of the median first.
+    pub fn from_points(mut points: Vec<TempoPoint>) -> Self {
+        points.retain(|p| p.bpm > 0.0);
+        if points.is_empty() {
+            return TempoCurve { points, min_bpm: 0.0, max_bpm: 0.0, spread: 0.0, variable: false };
+        }
+
+        let mut sorted: Vec<f64> = points.iter().map(|p| p.bpm).collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let median = sorted[sorted.len() / 2];
+        for point in &mut points {
+            point.bpm = normalize_octave(point.bpm, median / std::f64::consts::SQRT_2, median * std::f64::consts::SQRT_2);
+        }
+
+        let mut sorted: Vec<f64> = points.iter().map(|p| p.bpm).collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let percentile = |q: f64| sorted[((sorted.len() - 1) as f64 * q).round() as usize];
+        let spread = percentile(0.9) - percentile(0.1);
+
+        TempoCurve {
+            min_bpm: sorted[0],
+            max_bpm: sorted[sorted.len() - 1],
+            spread,
+            // A couple of windows can't tell drift from a breakdown
+            variable: points.len() >= 3 && spread > VARIABLE_TEMPO_SPREAD,
+            points,
+        }
+    }
+}
+
 /// Detect the BPM (tempo) of an audio file.
 ///
 /// Uses aubio's Tempo tracker to analyze the full audio file.
@@ -58,7 +122,39 @@ pub fn detect_bpm(path: &Path) -> Result<BpmResult, String> {
 /// This is separated from file I/O to allow testing with synthetic signals
 /// and to enable reuse when audio is already decoded (e.g., from a shared pipeline).
 pub fn detect_bpm_from_samples(audio: &MonoAudio) -> Result<BpmResult, String> {
-    if audio.samples.is_empty() {
+    detect_bpm_from_slice(&audio.samples, audio.sample_rate)
+}
+
+/// Measure BPM over consecutive windows of the track (see TempoCurve).
+/// Tracks shorter than one window yield a single point.
+pub fn detect_tempo_curve_from_samples(audio: &MonoAudio) -> Result<TempoCurve, String> {
+    let rate = audio.sample_rate as usize;
+    let window = rate * TEMPO_WINDOW_SECS as usize;
+    let step = rate * TEMPO_STEP_SECS as usize;
+    if audio.samples.is_empty() || window == 0 {
+        return Err("No audio samples to analyze".to_string());
+    }
+
+    let mut points = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = (start + window).min(audio.samples.len());
+        let result = detect_bpm_from_slice(&audio.samples[start..end], audio.sample_rate)?;
+        points.push(TempoPoint {
+            time_ms: ((start + end) / 2) as u64 * 1000 / rate as u64,
+            bpm: result.bpm,
+        });
+        if end == audio.samples.len() || start + step + window > audio.samples.len() {
+            break;
+        }
+        start += step;
+    }
+
+    Ok(TempoCurve::from_points(points))
+}
+
+fn detect_bpm_from_slice(samples: &[f32], sample_rate: u32) -> Result<BpmResult, String> {
+    if samples.is_empty() {
         return Err("No audio samples to analyze".to_string());
     }
     
@@ -70,12 +166,11 @@ pub fn detect_bpm_from_samples(audio: &MonoAudio) -> Result<BpmResult, String> {
     //   buf_size: FFT window size for onset detection (1024)
     //   hop_size: advance between frames (512 = 50% overlap)
     //   sample_rate: match the audio's native sample rate
-    let mut tempo = Tempo::new(OnsetMode::SpecFlux, BUF_SIZE, HOP_SIZE, audio.sample_rate)
+    let mut tempo = Tempo::new(OnsetMode::SpecFlux, BUF_SIZE, HOP_SIZE, sample_rate)
         .map_err(|e| format!("Failed to create aubio Tempo detector: {:?}", e))?;
     
     // Feed audio in hop-sized chunks to the tempo tracker.
     // Each call processes one frame and updates the internal beat tracking state.
-    let samples = &audio.samples;
     let total_hops = samples.len() / HOP_SIZE;
     for i in 0..total_hops {
         let start = i * HOP_SIZE;
@@ -120,11 +215,60 @@ pub fn detect_bpm_from_samples(audio: &MonoAudio) -> Result<BpmResult, String> {
     Ok(BpmResult { bpm, confidence })
 }
 
+/// Fix a half/double (or quarter/quadruple) tempo error by moving `bpm` into
+/// `min..=max` by whole octaves. Returns `bpm` unchanged if it's already in range
+/// or no octave lands in it (range narrower than an octave).
+pub fn normalize_octave(bpm: f64, min: f64, max: f64) -> f64 {
+    if bpm <= 0.0 || (min..=max).contains(&bpm) {
+        return bpm;
+    }
+    [2.0, 0.5, 4.0, 0.25]
+        .iter()
+        .map(|factor| bpm * factor)
+        .find(|candidate| (min..=max).contains(candidate))
+        .unwrap_or(bpm)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::f32::consts::PI;
 
+    #[test]
+    fn test_tempo_curve_summary() {
+        let point = |secs: u64, bpm: f64| TempoPoint { time_ms: secs * 1000, bpm };
+
+        // Steady track with one half-tempo window and one failed window
+        let steady = TempoCurve::from_points(vec![
+            point(10, 120.0),
+            point(20, 60.1),
+            point(30, 120.2),
+            point(40, 0.0),
+            point(50, 119.9),
+        ]);
+        assert_eq!(steady.points.len(), 4);
+        assert!((steady.points[1].bpm - 120.2).abs() < 1e-9);
+        assert!(!steady.variable, "spread {}", steady.spread);
+
+        // Live drummer speeding up
+        let drifting = TempoCurve::from_points((1..=8).map(|i| point(i * 10, 116.0 + i as f64)).collect());
+        assert_eq!(drifting.min_bpm, 117.0);
+        assert_eq!(drifting.max_bpm, 124.0);
+        assert!(drifting.variable);
+
+        assert!(!TempoCurve::from_points(vec![point(10, 100.0), point(20, 110.0)]).variable);
+    }
+
+    #[test]
+    fn test_normalize_octave() {
+        assert_eq!(normalize_octave(87.0, 160.0, 180.0), 174.0);
+        assert_eq!(normalize_octave(180.0, 80.0, 100.0), 90.0);
+        assert_eq!(normalize_octave(43.0, 160.0, 180.0), 172.0);
+        assert_eq!(normalize_octave(170.0, 160.0, 180.0), 170.0);
+        // No octave lands in range
+        assert_eq!(normalize_octave(130.0, 160.0, 180.0), 130.0);
+    }
+
     /// Generate a synthetic click track at a known BPM for testing.
     /// Creates short impulses (clicks) at regular intervals corresponding to the target BPM.
     fn generate_click_track(bpm: f64, sample_rate: u32, duration_seconds: f64) -> MonoAudio {
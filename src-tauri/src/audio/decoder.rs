@@ -24,6 +24,35 @@ pub struct AudioChunk {
     pub is_end: bool,
 }
 
+/// The same chunk as 16-bit samples: about half the IPC payload of f32.
+/// `sample_format` ("i16") tells the frontend to scale by 1/32767.
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioChunkI16 {
+    pub samples: Vec<i16>,
+    pub sample_rate: u32,
+    pub position_ms: u64,
+    pub duration_ms: u64,
+    pub is_end: bool,
+    pub sample_format: &'static str,
+}
+
+impl AudioChunk {
+    pub fn to_i16(&self) -> AudioChunkI16 {
+        AudioChunkI16 {
+            samples: self
+                .samples
+                .iter()
+                .map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16)
+                .collect(),
+            sample_rate: self.sample_rate,
+            position_ms: self.position_ms,
+            duration_ms: self.duration_ms,
+            is_end: self.is_end,
+            sample_format: "i16",
+        }
+    }
+}
+
 /// Audio decoder for streaming playback
 pub struct AudioDecoder {
     format_reader: Box<dyn FormatReader>,
@@ -0,0 +1,258 @@
+// Acoustic sub-fingerprints for finding library tracks inside a recorded DJ set
+// Philips-style (Haitsma & Kalker): the audio is reduced to ~5.5 kHz mono, cut into
+// 0.37 s frames every 46 ms, and each frame's energy in 33 log-spaced bands between
+// 300 and 2000 Hz becomes a 32-bit hash: bit m is set when the energy difference between
+// bands m and m+1 grew since the previous frame. The hashes survive EQ, compression,
+// lossy encoding and the overlap of a transition reasonably well, but not pitch shifts,
+// so tracks played well off their original tempo without key lock won't be found.
+// (Unrelated to the Chromaprint strings in track_fingerprints.)
+
+use super::decoder::AudioDecoder;
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Rate the audio is reduced to before hashing
+pub const SAMPLE_RATE: u32 = 5512;
+const FRAME_SIZE: usize = 2048;
+const HOP_SIZE: usize = 256;
+/// Time between consecutive hashes
+pub const HOP_MS: f64 = HOP_SIZE as f64 * 1000.0 / SAMPLE_RATE as f64;
+const BANDS: usize = 33;
+const MIN_FREQ: f32 = 300.0;
+const MAX_FREQ: f32 = 2000.0;
+/// Least reliable bits remembered per hash (see Fingerprinter::with_weak_bits)
+pub const WEAK_BITS: usize = 4;
+/// Blob format version
+const BLOB_VERSION: u8 = 1;
+
+/// Hashes, and per hash its weak bits if they were requested
+pub type Prints = (Vec<u32>, Option<Vec<[u8; WEAK_BITS]>>);
+
+/// Streaming sub-fingerprint extractor: push mono samples at any rate, then finish()
+pub struct Fingerprinter {
+    input_rate: u32,
+    /// Input samples seen, for resampling
+    input_pos: u64,
+    /// Output sample currently being averaged, and its running sum and count
+    output_pos: u64,
+    acc: f32,
+    acc_count: u32,
+    buffer: Vec<f32>,
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    /// FFT bin range [start, end) of each band
+    band_bins: Vec<(usize, usize)>,
+    prev_diffs: Option<Vec<f32>>,
+    hashes: Vec<u32>,
+    /// Per hash, the WEAK_BITS bit positions closest to flipping (only when requested)
+    weak_bits: Option<Vec<[u8; WEAK_BITS]>>,
+}
+
+impl Fingerprinter {
+    pub fn new(input_rate: u32) -> Self {
+        let fft = FftPlanner::new().plan_fft_forward(FRAME_SIZE);
+        let window = (0..FRAME_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / FRAME_SIZE as f32).cos())
+            .collect();
+        let bin_of = |freq: f32| (freq * FRAME_SIZE as f32 / SAMPLE_RATE as f32).round() as usize;
+        let ratio = (MAX_FREQ / MIN_FREQ).powf(1.0 / BANDS as f32);
+        let band_bins = (0..BANDS)
+            .map(|b| {
+                let start = bin_of(MIN_FREQ * ratio.powi(b as i32));
+                let end = bin_of(MIN_FREQ * ratio.powi(b as i32 + 1)).max(start + 1);
+                (start, end)
+            })
+            .collect();
+
+        Fingerprinter {
+            input_rate: input_rate.max(SAMPLE_RATE),
+            input_pos: 0,
+            output_pos: 0,
+            acc: 0.0,
+            acc_count: 0,
+            buffer: Vec::with_capacity(FRAME_SIZE * 2),
+            fft,
+            window,
+            band_bins,
+            prev_diffs: None,
+            hashes: Vec::new(),
+            weak_bits: None,
+        }
+    }
+
+    /// Also record each hash's least reliable bits, so a lookup can try the variants
+    /// most likely to match a degraded copy
+    pub fn with_weak_bits(mut self) -> Self {
+        self.weak_bits = Some(Vec::new());
+        self
+    }
+
+    /// Feed mono samples at the input rate
+    pub fn push(&mut self, samples: &[f32]) {
+        for &sample in samples {
+            // Box-filter resampling: average the input samples that fall on each output sample
+            let target = self.input_pos * SAMPLE_RATE as u64 / self.input_rate as u64;
+            if target != self.output_pos && self.acc_count > 0 {
+                self.buffer.push(self.acc / self.acc_count as f32);
+                self.acc = 0.0;
+                self.acc_count = 0;
+                self.output_pos = target;
+                if self.buffer.len() >= FRAME_SIZE {
+                    self.process_frame();
+                }
+            }
+            self.acc += sample;
+            self.acc_count += 1;
+            self.input_pos += 1;
+        }
+    }
+
+    fn process_frame(&mut self) {
+        let mut spectrum: Vec<Complex<f32>> = self.buffer[..FRAME_SIZE]
+            .iter()
+            .zip(&self.window)
+            .map(|(s, w)| Complex::new(s * w, 0.0))
+            .collect();
+        self.fft.process(&mut spectrum);
+        self.buffer.drain(..HOP_SIZE);
+
+        let energies: Vec<f32> = self
+            .band_bins
+            .iter()
+            .map(|&(start, end)| spectrum[start..end].iter().map(|c| c.norm_sqr()).sum())
+            .collect();
+        let diffs: Vec<f32> = energies.windows(2).map(|pair| pair[0] - pair[1]).collect();
+
+        if let Some(prev) = &self.prev_diffs {
+            let deltas: Vec<f32> = diffs.iter().zip(prev).map(|(d, p)| d - p).collect();
+            let hash = deltas
+                .iter()
+                .enumerate()
+                .fold(0u32, |hash, (bit, &delta)| if delta > 0.0 { hash | 1 << bit } else { hash });
+            self.hashes.push(hash);
+
+            if let Some(weak_bits) = &mut self.weak_bits {
+                let mut order: Vec<usize> = (0..deltas.len()).collect();
+                order.sort_by(|&a, &b| deltas[a].abs().total_cmp(&deltas[b].abs()));
+                let mut weakest = [0u8; WEAK_BITS];
+                for (slot, &bit) in weakest.iter_mut().zip(&order) {
+                    *slot = bit as u8;
+                }
+                weak_bits.push(weakest);
+            }
+        }
+        self.prev_diffs = Some(diffs);
+    }
+
+    /// The hashes, one per HOP_MS, and the weak bits if requested
+    pub fn finish(self) -> Prints {
+        (self.hashes, self.weak_bits)
+    }
+}
+
+/// Sub-fingerprints of a whole file, decoded in chunks (recordings can be hours long)
+pub fn fingerprint_file(path: &Path, weak_bits: bool) -> Result<Prints, String> {
+    let mut decoder = AudioDecoder::new(path)?;
+    let mut printer = Fingerprinter::new(decoder.sample_rate());
+    if weak_bits {
+        printer = printer.with_weak_bits();
+    }
+
+    let mut mono = Vec::new();
+    while let Some(chunk) = decoder.decode_next_chunk()? {
+        if chunk.is_end {
+            break;
+        }
+        mono.clear();
+        mono.extend(chunk.samples.chunks_exact(2).map(|lr| (lr[0] + lr[1]) * 0.5));
+        printer.push(&mono);
+    }
+    Ok(printer.finish())
+}
+
+/// Serialize hashes for database storage: [version:u8, hashes as u32 LE...]
+pub fn to_blob(hashes: &[u32]) -> Vec<u8> {
+    let mut blob = Vec::with_capacity(1 + hashes.len() * 4);
+    blob.push(BLOB_VERSION);
+    for hash in hashes {
+        blob.extend_from_slice(&hash.to_le_bytes());
+    }
+    blob
+}
+
+/// Hashes from a blob written by to_blob (None for another version or a torn blob)
+pub fn from_blob(blob: &[u8]) -> Option<Vec<u32>> {
+    let data = blob.strip_prefix(&[BLOB_VERSION])?;
+    if data.len() % 4 != 0 {
+        return None;
+    }
+    Some(
+        data.chunks_exact(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+    )
+}
+
+/// Fraction of differing bits between two equally long hash runs (1.0 when empty)
+pub fn bit_error_rate(a: &[u32], b: &[u32]) -> f64 {
+    let len = a.len().min(b.len());
+    if len == 0 {
+        return 1.0;
+    }
+    let errors: u32 = a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum();
+    errors as f64 / (len * 32) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A few seconds of chords changing every half second
+    fn music(rate: u32, seconds: f32) -> Vec<f32> {
+        let notes = [330.0, 440.0, 523.0, 392.0, 587.0, 349.0, 494.0, 659.0];
+        (0..(rate as f32 * seconds) as usize)
+            .map(|i| {
+                let t = i as f32 / rate as f32;
+                let step = (t * 2.0) as usize;
+                let a = notes[step % notes.len()];
+                let b = notes[(step * 3 + 1) % notes.len()] * 1.5;
+                (2.0 * std::f32::consts::PI * a * t).sin() * 0.4 + (2.0 * std::f32::consts::PI * b * t).sin() * 0.3
+            })
+            .collect()
+    }
+
+    fn prints(samples: &[f32], rate: u32) -> Vec<u32> {
+        let mut printer = Fingerprinter::new(rate);
+        // Uneven chunk sizes, as a decoder delivers them
+        for chunk in samples.chunks(1151) {
+            printer.push(chunk);
+        }
+        printer.finish().0
+    }
+
+    #[test]
+    fn test_fingerprint_robustness() {
+        let original = music(44100, 8.0);
+        let hashes = prints(&original, 44100);
+        // (8 s * 5512 - 2048) / 256 frames, minus the first (no previous frame)
+        assert!((160..=165).contains(&hashes.len()), "{} hashes", hashes.len());
+
+        // Quieter and with noise: still close
+        let noisy: Vec<f32> = original
+            .iter()
+            .enumerate()
+            .map(|(i, s)| s * 0.5 + ((i * 7919 % 1000) as f32 / 1000.0 - 0.5) * 0.02)
+            .collect();
+        let ber = bit_error_rate(&hashes, &prints(&noisy, 44100));
+        assert!(ber < 0.2, "noisy copy BER {}", ber);
+
+        // Other music: unrelated
+        let other: Vec<f32> = music(44100, 8.5)[22050..].to_vec();
+        let ber = bit_error_rate(&hashes, &prints(&other, 44100));
+        assert!(ber > 0.3, "other music BER {}", ber);
+
+        assert_eq!(from_blob(&to_blob(&hashes)), Some(hashes));
+        assert_eq!(from_blob(&[9, 0, 0, 0, 0]), None);
+    }
+}
@@ -64,7 +64,7 @@ const SHAATH_MINOR: [f64; 12] = [
     6.5, 2.7, 3.5, 5.4, 2.6, 3.5, 2.5, 4.7, 4.0, 2.7, 3.4, 3.2,
 ];
 
-/// Temperley's key profiles (alternative, good for classical music)
+/// Temperley's key profiles (alternative, good for classical music; selectable for benchmarking)
 const TEMPERLEY_MAJOR: [f64; 12] = [
     5.0, 2.0, 3.5, 2.0, 4.5, 4.0, 2.0, 4.5, 2.0, 3.5, 1.5, 4.0,
 ];
@@ -73,7 +73,7 @@ const TEMPERLEY_MINOR: [f64; 12] = [
     5.0, 2.0, 3.5, 4.5, 2.0, 4.0, 2.0, 4.5, 3.5, 2.0, 1.5, 4.0,
 ];
 
-/// Krumhansl-Schmuckler profiles (original, kept for reference)
+/// Krumhansl-Schmuckler profiles (original, kept for reference and benchmarking)
 const KS_MAJOR: [f64; 12] = [
     6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
 ];
@@ -82,6 +82,64 @@ const KS_MINOR: [f64; 12] = [
     6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
 ];
 
+/// Key profile set matched against the chromagram
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyProfile {
+    #[default]
+    Shaath,
+    Temperley,
+    KrumhanslSchmuckler,
+}
+
+impl KeyProfile {
+    pub const ALL: [KeyProfile; 3] = [
+        KeyProfile::Shaath,
+        KeyProfile::Temperley,
+        KeyProfile::KrumhanslSchmuckler,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyProfile::Shaath => "shaath",
+            KeyProfile::Temperley => "temperley",
+            KeyProfile::KrumhanslSchmuckler => "ks",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name().eq_ignore_ascii_case(name))
+    }
+
+    fn profiles(self) -> (&'static [f64; 12], &'static [f64; 12]) {
+        match self {
+            KeyProfile::Shaath => (&SHAATH_MAJOR, &SHAATH_MINOR),
+            KeyProfile::Temperley => (&TEMPERLEY_MAJOR, &TEMPERLEY_MINOR),
+            KeyProfile::KrumhanslSchmuckler => (&KS_MAJOR, &KS_MINOR),
+        }
+    }
+}
+
+/// Tunable parameters of the detector. The app always uses the defaults;
+/// the key_bench binary varies them to evaluate algorithm changes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyOptions {
+    pub profile: KeyProfile,
+    /// FFT window size (power of two)
+    pub fft_size: usize,
+    /// Hop between consecutive frames
+    pub hop_size: usize,
+}
+
+impl Default for KeyOptions {
+    fn default() -> Self {
+        KeyOptions {
+            profile: KeyProfile::default(),
+            fft_size: FFT_SIZE,
+            hop_size: HOP_SIZE,
+        }
+    }
+}
+
 /// Musical key names for major keys (indexed by pitch class: 0=C, 1=C#/Db, ..., 11=B)
 const MAJOR_NAMES: [&str; 12] = [
     "C", "Db", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B",
@@ -143,25 +201,33 @@ pub fn detect_key(path: &Path) -> Result<KeyResult, String> {
 /// Separated from file I/O to allow testing with synthetic signals
 /// and reuse when audio is already decoded (e.g., from a shared analysis pipeline).
 pub fn detect_key_from_samples(audio: &MonoAudio) -> Result<KeyResult, String> {
+    detect_key_with_options(audio, &KeyOptions::default())
+}
+
+/// Detect key with non-default detector parameters (see KeyOptions)
+pub fn detect_key_with_options(audio: &MonoAudio, options: &KeyOptions) -> Result<KeyResult, String> {
+    if options.fft_size < 2 || options.hop_size == 0 {
+        return Err("Invalid key detection window".to_string());
+    }
     if audio.samples.is_empty() {
         return Err("No audio samples to analyze".to_string());
     }
 
     // Need at least one full FFT frame
-    if audio.samples.len() < FFT_SIZE {
+    if audio.samples.len() < options.fft_size {
         return Err(format!(
             "Audio too short for key detection: {} samples (need at least {})",
             audio.samples.len(),
-            FFT_SIZE
+            options.fft_size
         ));
     }
 
     // Step 1: Compute chromagram from FFT analysis
-    let chromagram = compute_chromagram(&audio.samples, audio.sample_rate)?;
+    let chromagram = compute_chromagram(&audio.samples, audio.sample_rate, options.fft_size, options.hop_size)?;
 
     // Step 2: Correlate with all 24 key profiles and find the best match
     let (best_key_index, best_is_minor, best_corr, second_best_corr) =
-        match_key_profiles(&chromagram);
+        match_key_profiles(&chromagram, options.profile);
 
     // Step 3: Convert to Camelot, Open Key, and musical notation
     let camelot = if best_is_minor {
@@ -218,14 +284,19 @@ pub fn detect_key_from_samples(audio: &MonoAudio) -> Result<KeyResult, String> {
 /// 3. Map each FFT bin's frequency to a pitch class (using 12-TET tuning, A=440Hz)
 /// 4. Sum the power (magnitude squared) for each pitch class
 /// 5. Normalize so the chromagram sums to 1.0
-fn compute_chromagram(samples: &[f32], sample_rate: u32) -> Result<[f64; 12], String> {
+fn compute_chromagram(
+    samples: &[f32],
+    sample_rate: u32,
+    fft_size: usize,
+    hop_size: usize,
+) -> Result<[f64; 12], String> {
     let mut chromagram = [0.0f64; 12];
     let mut planner = FftPlanner::new();
-    let fft = planner.plan_fft_forward(FFT_SIZE);
+    let fft = planner.plan_fft_forward(fft_size);
 
     // Precompute Hanning window coefficients
-    let window: Vec<f64> = (0..FFT_SIZE)
-        .map(|i| 0.5 * (1.0 - (2.0 * PI * i as f64 / (FFT_SIZE - 1) as f64).cos()))
+    let window: Vec<f64> = (0..fft_size)
+        .map(|i| 0.5 * (1.0 - (2.0 * PI * i as f64 / (fft_size - 1) as f64).cos()))
         .collect();
 
     // Precompute frequency-to-pitch-class mapping for each FFT bin.
@@ -233,9 +304,9 @@ fn compute_chromagram(samples: &[f32], sample_rate: u32) -> Result<[f64; 12], St
     //   semitones_from_A = 12 * log2(freq / 440)
     //   pitch_class = (round(semitones_from_A) + 9) mod 12
     // Where +9 shifts from A-based to C-based indexing (C=0, C#=1, ..., A=9, ..., B=11)
-    let bin_to_pitch_class: Vec<Option<usize>> = (0..FFT_SIZE / 2 + 1)
+    let bin_to_pitch_class: Vec<Option<usize>> = (0..fft_size / 2 + 1)
         .map(|bin| {
-            let freq = bin as f64 * sample_rate as f64 / FFT_SIZE as f64;
+            let freq = bin as f64 * sample_rate as f64 / fft_size as f64;
             if freq < MIN_FREQ || freq > MAX_FREQ {
                 None // Outside musical range
             } else {
@@ -248,11 +319,11 @@ fn compute_chromagram(samples: &[f32], sample_rate: u32) -> Result<[f64; 12], St
         .collect();
 
     // Process audio in overlapping frames
-    let num_frames = (samples.len().saturating_sub(FFT_SIZE)) / HOP_SIZE + 1;
+    let num_frames = (samples.len().saturating_sub(fft_size)) / hop_size + 1;
 
     for frame_idx in 0..num_frames {
-        let start = frame_idx * HOP_SIZE;
-        let end = start + FFT_SIZE;
+        let start = frame_idx * hop_size;
+        let end = start + fft_size;
         if end > samples.len() {
             break;
         }
@@ -288,10 +359,11 @@ fn compute_chromagram(samples: &[f32], sample_rate: u32) -> Result<[f64; 12], St
 }
 
 /// Match the computed chromagram against all 24 key profiles using Pearson correlation.
-/// Uses Shaath's custom profiles which work better for popular/electronic music.
+/// The app uses Shaath's custom profiles, which work better for popular/electronic music.
 ///
 /// Returns (pitch_class_index, is_minor, best_correlation, second_best_correlation)
-fn match_key_profiles(chromagram: &[f64; 12]) -> (usize, bool, f64, f64) {
+fn match_key_profiles(chromagram: &[f64; 12], profile: KeyProfile) -> (usize, bool, f64, f64) {
+    let (major_profile, minor_profile) = profile.profiles();
     let mut best_key = 0;
     let mut best_is_minor = false;
     let mut best_correlation = f64::NEG_INFINITY;
@@ -301,8 +373,8 @@ fn match_key_profiles(chromagram: &[f64; 12]) -> (usize, bool, f64, f64) {
     for root in 0..12 {
         // Rotate the key profile so index 0 aligns with the root note.
         // e.g., for D major (root=2): the profile's "tonic" entry aligns with D in the chromagram.
-        let major_corr = pearson_correlation(chromagram, &SHAATH_MAJOR, root);
-        let minor_corr = pearson_correlation(chromagram, &SHAATH_MINOR, root);
+        let major_corr = pearson_correlation(chromagram, major_profile, root);
+        let minor_corr = pearson_correlation(chromagram, minor_profile, root);
 
         // Track best and second-best correlations
         for (corr, is_minor) in [(major_corr, false), (minor_corr, true)] {
@@ -358,6 +430,120 @@ fn pearson_correlation(chromagram: &[f64; 12], profile: &[f64; 12], root: usize)
     }
 }
 
+/// Parse a Camelot key string (e.g. "8A", "11b") into (number 1-12, is_minor).
+/// Returns None for anything that isn't a valid Camelot key.
+pub fn parse_camelot(camelot: &str) -> Option<(u8, bool)> {
+    let camelot = camelot.trim();
+    if camelot.len() < 2 {
+        return None;
+    }
+    let letter = camelot.chars().last()?;
+    let number: u8 = camelot[..camelot.len() - letter.len_utf8()].parse().ok()?;
+    if !(1..=12).contains(&number) {
+        return None;
+    }
+    match letter {
+        'A' | 'a' => Some((number, true)),
+        'B' | 'b' => Some((number, false)),
+        _ => None,
+    }
+}
+
+/// Convert a key as written by other software into Camelot notation.
+/// Accepts Camelot ("8A"), Open Key ("1m", "6d") and musical notation
+/// ("Am", "F#m", "Dbmaj", "A minor", "E♭"). Compound values like "8A/Am"
+/// are tried part by part. Returns None if nothing parses.
+pub fn key_to_camelot(key: &str) -> Option<String> {
+    let key = key.trim();
+    if key.is_empty() {
+        return None;
+    }
+    parse_single_key(key).or_else(|| {
+        key.split(['/', ',', '-', ' '])
+            .filter(|part| !part.is_empty() && *part != key)
+            .find_map(parse_single_key)
+    })
+}
+
+fn parse_single_key(key: &str) -> Option<String> {
+    let key = key.trim();
+
+    // Camelot (also with a leading zero, e.g. "08A")
+    if let Some((number, is_minor)) = parse_camelot(key.trim_start_matches('0')) {
+        return Some(format!("{}{}", number, if is_minor { 'A' } else { 'B' }));
+    }
+
+    // Open Key as written by Traktor (1m = Am = 8A): same wheel, rotated by 7
+    let lower = key.to_lowercase();
+    if let Some(mode) = lower.chars().last().filter(|c| *c == 'm' || *c == 'd') {
+        if let Ok(number) = lower[..lower.len() - 1].parse::<u8>() {
+            if (1..=12).contains(&number) {
+                let camelot = (number + 6) % 12 + 1;
+                return Some(format!("{}{}", camelot, if mode == 'm' { 'A' } else { 'B' }));
+            }
+        }
+    }
+
+    // Musical notation: note letter, optional accidental, optional mode
+    let mut chars = key.chars();
+    let mut pitch_class: i32 = match chars.next()?.to_ascii_uppercase() {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+    let mut rest: &str = chars.as_str();
+    if let Some(r) = rest.strip_prefix(['#', '♯']) {
+        pitch_class += 1;
+        rest = r;
+    } else if let Some(r) = rest.strip_prefix(['b', '♭']) {
+        pitch_class -= 1;
+        rest = r;
+    }
+    let is_minor = match rest.trim().to_lowercase().as_str() {
+        "" | "maj" | "major" | "dur" => false,
+        "m" | "min" | "minor" | "moll" => true,
+        _ => return None,
+    };
+    let pc = pitch_class.rem_euclid(12) as usize;
+    Some(if is_minor { CAMELOT_MINOR[pc] } else { CAMELOT_MAJOR[pc] }.to_string())
+}
+
+/// The keys that mix harmonically with `camelot` on the Camelot wheel:
+/// the key itself, ±1 on the same ring, and the relative major/minor.
+/// Returns an empty list for unparseable keys.
+pub fn camelot_neighbors(camelot: &str) -> Vec<String> {
+    let (number, is_minor) = match parse_camelot(camelot) {
+        Some(k) => k,
+        None => return Vec::new(),
+    };
+    let letter = if is_minor { 'A' } else { 'B' };
+    let other = if is_minor { 'B' } else { 'A' };
+    let up = number % 12 + 1;
+    let down = (number + 10) % 12 + 1;
+    vec![
+        format!("{}{}", number, letter),
+        format!("{}{}", up, letter),
+        format!("{}{}", down, letter),
+        format!("{}{}", number, other),
+    ]
+}
+
+/// Whether two Camelot keys are harmonically compatible
+pub fn camelot_compatible(a: &str, b: &str) -> bool {
+    match parse_camelot(b) {
+        Some((number, is_minor)) => {
+            let normalized = format!("{}{}", number, if is_minor { 'A' } else { 'B' });
+            camelot_neighbors(a).contains(&normalized)
+        }
+        None => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -639,4 +825,50 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_parse_camelot() {
+        assert_eq!(parse_camelot("8A"), Some((8, true)));
+        assert_eq!(parse_camelot("12b"), Some((12, false)));
+        assert_eq!(parse_camelot("13A"), None);
+        assert_eq!(parse_camelot("Am"), None);
+        assert_eq!(parse_camelot(""), None);
+    }
+
+    #[test]
+    fn test_camelot_neighbors_wrap_around() {
+        let neighbors = camelot_neighbors("12A");
+        assert!(neighbors.contains(&"1A".to_string()));
+        assert!(neighbors.contains(&"11A".to_string()));
+        assert!(neighbors.contains(&"12B".to_string()));
+        assert_eq!(neighbors.len(), 4);
+    }
+
+    #[test]
+    fn test_camelot_compatible() {
+        assert!(camelot_compatible("8A", "9A"));
+        assert!(camelot_compatible("8A", "8b"));
+        assert!(!camelot_compatible("8A", "9B"));
+        assert!(!camelot_compatible("8A", "unknown"));
+    }
+
+    #[test]
+    fn test_key_to_camelot() {
+        assert_eq!(key_to_camelot("8A").as_deref(), Some("8A"));
+        assert_eq!(key_to_camelot("08b").as_deref(), Some("8B"));
+        assert_eq!(key_to_camelot("Am").as_deref(), Some("8A"));
+        assert_eq!(key_to_camelot("A minor").as_deref(), Some("8A"));
+        assert_eq!(key_to_camelot("C").as_deref(), Some("8B"));
+        assert_eq!(key_to_camelot("F#m").as_deref(), Some("11A"));
+        assert_eq!(key_to_camelot("Gbm").as_deref(), Some("11A"));
+        assert_eq!(key_to_camelot("E♭").as_deref(), Some("5B"));
+        assert_eq!(key_to_camelot("Cb").as_deref(), Some("1B"));
+        // Open Key: 1m = Am = 8A, 1d = C = 8B
+        assert_eq!(key_to_camelot("1m").as_deref(), Some("8A"));
+        assert_eq!(key_to_camelot("1d").as_deref(), Some("8B"));
+        assert_eq!(key_to_camelot("8A/Am").as_deref(), Some("8A"));
+        assert_eq!(key_to_camelot("o").as_deref(), None);
+        assert_eq!(key_to_camelot("").as_deref(), None);
+        assert_eq!(key_to_camelot("H").as_deref(), None);
+    }
 }
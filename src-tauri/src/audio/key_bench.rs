@@ -0,0 +1,237 @@
+// Scoring for the key detection benchmark (src/bin/key_bench.rs)
+// Ground truth comes from file names, detected keys are compared MIREX-style:
+// exact = 1.0, perfect fifth = 0.5, relative major/minor = 0.3, parallel major/minor = 0.2.
+
+use std::fmt::Write;
+
+use super::key::{key_to_camelot, parse_camelot};
+
+/// Read the labeled key from a file name (without directory). Accepted layouts:
+/// a bracketed key anywhere ("Artist - Title [8A].mp3", "Title (F#m).flac")
+/// or a key prefix ("08A - Artist - Title.mp3", "Am_Title.wav").
+/// Returns the key in Camelot notation.
+pub fn ground_truth_from_filename(file_name: &str) -> Option<String> {
+    let stem = match file_name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => file_name,
+    };
+
+    let bracketed = stem
+        .split(['[', '('])
+        .skip(1)
+        .filter_map(|part| part.split_once([']', ')']).map(|(inside, _)| inside))
+        .find_map(key_to_camelot);
+    if bracketed.is_some() {
+        return bracketed;
+    }
+
+    let (prefix, rest) = stem.split_once([' ', '_', '-'])?;
+    if rest.trim().is_empty() {
+        return None;
+    }
+    key_to_camelot(prefix)
+}
+
+/// How a detected key relates to the labeled one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyMatch {
+    Exact,
+    /// ±1 on the same Camelot ring
+    Fifth,
+    /// Same Camelot number, other ring (Am ↔ C)
+    Relative,
+    /// Same tonic, other mode (Cm ↔ C)
+    Parallel,
+    Other,
+}
+
+impl KeyMatch {
+    pub const ALL: [KeyMatch; 5] = [
+        KeyMatch::Exact,
+        KeyMatch::Fifth,
+        KeyMatch::Relative,
+        KeyMatch::Parallel,
+        KeyMatch::Other,
+    ];
+
+    /// Classify two Camelot keys (None if either doesn't parse)
+    pub fn classify(expected: &str, detected: &str) -> Option<Self> {
+        let (exp_num, exp_minor) = parse_camelot(expected)?;
+        let (det_num, det_minor) = parse_camelot(detected)?;
+        let step = (det_num as i32 - exp_num as i32).rem_euclid(12);
+
+        Some(if exp_minor == det_minor {
+            match step {
+                0 => KeyMatch::Exact,
+                1 | 11 => KeyMatch::Fifth,
+                _ => KeyMatch::Other,
+            }
+        } else if step == 0 {
+            KeyMatch::Relative
+        } else if (exp_minor && step == 3) || (!exp_minor && step == 9) {
+            // Cm (5A) -> C (8B), C (8B) -> Cm (5A)
+            KeyMatch::Parallel
+        } else {
+            KeyMatch::Other
+        })
+    }
+
+    pub fn weight(self) -> f64 {
+        match self {
+            KeyMatch::Exact => 1.0,
+            KeyMatch::Fifth => 0.5,
+            KeyMatch::Relative => 0.3,
+            KeyMatch::Parallel => 0.2,
+            KeyMatch::Other => 0.0,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            KeyMatch::Exact => "exact",
+            KeyMatch::Fifth => "fifth",
+            KeyMatch::Relative => "relative",
+            KeyMatch::Parallel => "parallel",
+            KeyMatch::Other => "other",
+        }
+    }
+}
+
+/// Row/column of a Camelot key in the confusion matrix: 1A, 1B, 2A, ... 12B
+fn camelot_index(camelot: &str) -> Option<usize> {
+    let (number, is_minor) = parse_camelot(camelot)?;
+    Some((number as usize - 1) * 2 + if is_minor { 0 } else { 1 })
+}
+
+fn camelot_label(index: usize) -> String {
+    format!("{}{}", index / 2 + 1, if index.is_multiple_of(2) { 'A' } else { 'B' })
+}
+
+/// Accumulated results for one detector configuration
+#[derive(Debug, Clone, Default)]
+pub struct BenchReport {
+    /// Files that were scored
+    pub scored: usize,
+    /// Files where detection failed
+    pub failed: usize,
+    /// Count per KeyMatch (indexed like KeyMatch::ALL)
+    pub matches: [usize; 5],
+    /// confusion[expected][detected], indexed by camelot_index
+    pub confusion: [[usize; 24]; 24],
+}
+
+impl BenchReport {
+    /// Score one file; returns how it matched
+    pub fn add(&mut self, expected: &str, detected: &str) -> Option<KeyMatch> {
+        let key_match = KeyMatch::classify(expected, detected)?;
+        let (row, col) = (camelot_index(expected)?, camelot_index(detected)?);
+        self.scored += 1;
+        self.matches[KeyMatch::ALL.iter().position(|m| *m == key_match).unwrap_or(4)] += 1;
+        self.confusion[row][col] += 1;
+        Some(key_match)
+    }
+
+    pub fn add_failure(&mut self) {
+        self.failed += 1;
+    }
+
+    /// Share of exact matches (0.0–1.0)
+    pub fn accuracy(&self) -> f64 {
+        if self.scored == 0 {
+            return 0.0;
+        }
+        self.matches[0] as f64 / self.scored as f64
+    }
+
+    /// MIREX weighted score (0.0–1.0)
+    pub fn weighted_score(&self) -> f64 {
+        if self.scored == 0 {
+            return 0.0;
+        }
+        let total: f64 = KeyMatch::ALL
+            .iter()
+            .zip(self.matches)
+            .map(|(m, count)| m.weight() * count as f64)
+            .sum();
+        total / self.scored as f64
+    }
+
+    /// Human-readable summary (and optionally the confusion matrix)
+    pub fn render(&self, with_matrix: bool) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "  scored {} files ({} failed)\n  accuracy {:.1}%  weighted {:.1}%",
+            self.scored,
+            self.failed,
+            self.accuracy() * 100.0,
+            self.weighted_score() * 100.0
+        );
+        for (key_match, count) in KeyMatch::ALL.iter().zip(self.matches) {
+            let share = if self.scored > 0 { count as f64 * 100.0 / self.scored as f64 } else { 0.0 };
+            let _ = writeln!(out, "  {:<9} {:>5}  {:>5.1}%", key_match.label(), count, share);
+        }
+
+        if with_matrix {
+            let _ = write!(out, "\n  expected \\ detected\n      ");
+            for col in 0..24 {
+                let _ = write!(out, "{:>4}", camelot_label(col));
+            }
+            out.push('\n');
+            for (row, counts) in self.confusion.iter().enumerate() {
+                if counts.iter().all(|c| *c == 0) {
+                    continue;
+                }
+                let _ = write!(out, "  {:>4}", camelot_label(row));
+                for count in counts {
+                    if *count == 0 {
+                        out.push_str("   .");
+                    } else {
+                        let _ = write!(out, "{:>4}", count);
+                    }
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ground_truth_from_filename() {
+        assert_eq!(ground_truth_from_filename("Artist - Title [8A].mp3").as_deref(), Some("8A"));
+        assert_eq!(ground_truth_from_filename("Title (F#m).flac").as_deref(), Some("11A"));
+        assert_eq!(ground_truth_from_filename("08A - Artist - Title.mp3").as_deref(), Some("8A"));
+        assert_eq!(ground_truth_from_filename("Am_Title.wav").as_deref(), Some("8A"));
+        // Bracketed non-key text is skipped
+        assert_eq!(ground_truth_from_filename("Title (Original Mix) [1B].mp3").as_deref(), Some("1B"));
+        assert_eq!(ground_truth_from_filename("Artist - Title.mp3"), None);
+        assert_eq!(ground_truth_from_filename("8A.mp3"), None);
+    }
+
+    #[test]
+    fn test_classify_and_score() {
+        assert_eq!(KeyMatch::classify("8A", "8A"), Some(KeyMatch::Exact));
+        assert_eq!(KeyMatch::classify("12B", "1B"), Some(KeyMatch::Fifth));
+        assert_eq!(KeyMatch::classify("8A", "8B"), Some(KeyMatch::Relative));
+        assert_eq!(KeyMatch::classify("5A", "8B"), Some(KeyMatch::Parallel));
+        assert_eq!(KeyMatch::classify("8B", "5A"), Some(KeyMatch::Parallel));
+        assert_eq!(KeyMatch::classify("8A", "2B"), Some(KeyMatch::Other));
+
+        let mut report = BenchReport::default();
+        report.add("8A", "8A");
+        report.add("8A", "9A");
+        report.add("8A", "8B");
+        report.add("8A", "2A");
+        report.add_failure();
+        assert_eq!(report.scored, 4);
+        assert_eq!(report.confusion[14][14], 1);
+        assert!((report.accuracy() - 0.25).abs() < 1e-9);
+        assert!((report.weighted_score() - 1.8 / 4.0).abs() < 1e-9);
+        assert!(report.render(true).contains("  8A   ."));
+    }
+}
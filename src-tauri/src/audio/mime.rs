@@ -0,0 +1,226 @@
+// Content-Type for served audio (stream:// protocol and companion streaming/downloads)
+// Extensions lie: AAC in .mp3, FLAC in .wav... and the webview's player refuses a stream
+// whose Content-Type doesn't match what's inside. So the first bytes are probed (container
+// magic, then symphonia for raw MPEG/ADTS streams); the extension is only the fallback.
+// Per-extension overrides from the settings win over both.
+
+use crate::db::Database;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::RwLock;
+use symphonia::core::codecs::{
+    CODEC_TYPE_AAC, CODEC_TYPE_ALAC, CODEC_TYPE_FLAC, CODEC_TYPE_MP1, CODEC_TYPE_MP2, CODEC_TYPE_MP3,
+};
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+pub const STREAM_MIME_SETTING: &str = "stream_mime";
+
+/// Bytes after any ID3v2 tag handed to symphonia; enough to find the first frames
+const PROBE_BYTES: usize = 16 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamMimeConfig {
+    /// Probe file contents instead of trusting the extension
+    pub probe: bool,
+    /// Extension (lowercase, no dot) -> Content-Type to send, e.g. "m4a" -> "audio/x-m4a"
+    pub overrides: HashMap<String, String>,
+}
+
+impl Default for StreamMimeConfig {
+    fn default() -> Self {
+        StreamMimeConfig {
+            probe: true,
+            overrides: HashMap::new(),
+        }
+    }
+}
+
+impl StreamMimeConfig {
+    /// Load the saved config, falling back to the default
+    pub fn from_settings(db: &Database) -> Self {
+        db.get_setting(STREAM_MIME_SETTING)
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Normalize extensions (".MP3" -> "mp3") and check the MIME types look like "type/subtype"
+    pub fn validate(self) -> Result<Self, String> {
+        let mut overrides = HashMap::new();
+        for (extension, mime) in self.overrides {
+            let extension = extension.trim().trim_start_matches('.').to_lowercase();
+            if extension.is_empty() || !extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Err(format!("Invalid file extension '{}'", extension));
+            }
+            let mime = mime.trim().to_string();
+            let valid = mime.split_once('/').is_some_and(|(kind, subtype)| {
+                !kind.is_empty() && !subtype.is_empty()
+                    && mime.chars().all(|c| c.is_ascii_graphic())
+            });
+            if !valid {
+                return Err(format!("Invalid MIME type '{}' for .{}", mime, extension));
+            }
+            overrides.insert(extension, mime);
+        }
+        Ok(StreamMimeConfig { probe: self.probe, overrides })
+    }
+}
+
+/// Active config, shared by the stream:// handler and the companion server (which has its
+/// own database connection). Set at startup and whenever the setting changes.
+static CONFIG: RwLock<Option<StreamMimeConfig>> = RwLock::new(None);
+
+pub fn configure(config: StreamMimeConfig) {
+    *CONFIG.write().unwrap() = Some(config);
+}
+
+/// Content-Type for a file on disk (reads its first bytes when probing is on)
+pub fn file_mime_type(path: &Path) -> String {
+    resolve(path, || probe_file(path))
+}
+
+/// Content-Type for a file already read into memory
+pub fn data_mime_type(path: &Path, data: &[u8]) -> String {
+    resolve(path, || probe_bytes(skip_id3(data)))
+}
+
+fn resolve(path: &Path, probe: impl FnOnce() -> Option<&'static str>) -> String {
+    let extension = path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase());
+    let config = CONFIG.read().unwrap();
+    let config = config.as_ref();
+    if let Some(mime) = extension.as_ref().and_then(|ext| config?.overrides.get(ext)) {
+        return mime.clone();
+    }
+    let probed = if config.is_none_or(|c| c.probe) { probe() } else { None };
+    probed
+        .or_else(|| extension_mime_type(extension.as_deref()))
+        .unwrap_or("application/octet-stream")
+        .to_string()
+}
+
+fn extension_mime_type(extension: Option<&str>) -> Option<&'static str> {
+    match extension? {
+        "mp3" => Some("audio/mpeg"),
+        "flac" => Some("audio/flac"),
+        "wav" => Some("audio/wav"),
+        "ogg" => Some("audio/ogg"),
+        "m4a" => Some("audio/mp4"),
+        "aac" => Some("audio/aac"),
+        "aiff" | "aif" => Some("audio/aiff"),
+        _ => None,
+    }
+}
+
+/// Length of a leading ID3v2 tag (which may hold megabytes of artwork), 0 without one.
+/// Needs the 10-byte tag header.
+fn id3_len(data: &[u8]) -> usize {
+    match data {
+        [b'I', b'D', b'3', _, _, flags, s0, s1, s2, s3, ..] => {
+            let size = [s0, s1, s2, s3].iter().fold(0usize, |acc, b| (acc << 7) | (**b & 0x7f) as usize);
+            let footer = if flags & 0x10 != 0 { 10 } else { 0 };
+            10 + size + footer
+        }
+        _ => 0,
+    }
+}
+
+fn skip_id3(data: &[u8]) -> &[u8] {
+    data.get(id3_len(data)..).unwrap_or(&[])
+}
+
+fn probe_file(path: &Path) -> Option<&'static str> {
+    let mut file = File::open(path).ok()?;
+    let mut header = [0u8; 10];
+    file.read_exact(&mut header).ok()?;
+    file.seek(SeekFrom::Start(id3_len(&header) as u64)).ok()?;
+    let mut head = Vec::with_capacity(PROBE_BYTES);
+    file.take(PROBE_BYTES as u64).read_to_end(&mut head).ok()?;
+    probe_bytes(&head)
+}
+
+/// Containers recognizable by their magic bytes
+fn container_mime_type(head: &[u8]) -> Option<&'static str> {
+    match head {
+        [b'f', b'L', b'a', b'C', ..] => Some("audio/flac"),
+        [b'O', b'g', b'g', b'S', ..] => Some("audio/ogg"),
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some("audio/mp4"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some("audio/wav"),
+        [b'F', b'O', b'R', b'M', _, _, _, _, b'A', b'I', b'F', b'F' | b'C', ..] => Some("audio/aiff"),
+        _ => None,
+    }
+}
+
+/// MIME type from the start of a file (after any ID3v2 tag). Raw streams have no magic
+/// to go by, so symphonia finds the first frame and says which codec it is.
+fn probe_bytes(head: &[u8]) -> Option<&'static str> {
+    if let Some(mime) = container_mime_type(head) {
+        return Some(mime);
+    }
+    let head = head[..head.len().min(PROBE_BYTES)].to_vec();
+    let mss = MediaSourceStream::new(Box::new(Cursor::new(head)), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(&Hint::new(), mss, &FormatOptions::default(), &MetadataOptions::default())
+        .ok()?;
+    match probed.format.default_track()?.codec_params.codec {
+        CODEC_TYPE_MP1 | CODEC_TYPE_MP2 | CODEC_TYPE_MP3 => Some("audio/mpeg"),
+        CODEC_TYPE_AAC => Some("audio/aac"),
+        CODEC_TYPE_FLAC => Some("audio/flac"),
+        CODEC_TYPE_ALAC => Some("audio/mp4"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// MPEG-1 Layer III frames, 128 kbps / 44.1 kHz (417 bytes each)
+    fn mp3_frames(count: usize) -> Vec<u8> {
+        let mut frame = vec![0u8; 417];
+        frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+        frame.repeat(count)
+    }
+
+    #[test]
+    fn test_probe_bytes() {
+        let mut wav = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+        wav.resize(64, 0);
+        assert_eq!(probe_bytes(&wav), Some("audio/wav"));
+        assert_eq!(probe_bytes(b"\0\0\0\x20ftypM4A \0\0\0\0"), Some("audio/mp4"));
+        assert_eq!(probe_bytes(&mp3_frames(8)), Some("audio/mpeg"));
+        assert_eq!(probe_bytes(b"not audio at all"), None);
+
+        // A big ID3 tag in front of FLAC data
+        let mut tagged = b"ID3\x04\0\0\0\0\x01\0".to_vec();
+        tagged.resize(10 + 128, 0);
+        tagged.extend_from_slice(b"fLaC\0\0\0\x22");
+        assert_eq!(id3_len(&tagged), 138);
+        assert_eq!(probe_bytes(skip_id3(&tagged)), Some("audio/flac"));
+    }
+
+    #[test]
+    fn test_stream_mime_config() {
+        let config = StreamMimeConfig {
+            probe: true,
+            overrides: HashMap::from([(" .M4A".to_string(), "audio/x-m4a".to_string())]),
+        };
+        let config = config.validate().unwrap();
+        assert_eq!(config.overrides.get("m4a").map(String::as_str), Some("audio/x-m4a"));
+
+        let bad = |ext: &str, mime: &str| StreamMimeConfig {
+            probe: true,
+            overrides: HashMap::from([(ext.to_string(), mime.to_string())]),
+        };
+        assert!(bad("mp3", "audio").validate().is_err());
+        assert!(bad("mp3", "audio/mp 3").validate().is_err());
+        assert!(bad("", "audio/mpeg").validate().is_err());
+    }
+}
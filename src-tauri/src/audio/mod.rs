@@ -1,7 +1,15 @@
 // Audio processing (DSP)
-// Modules: decoder, bpm, key, waveform, spectrogram, loudness, fingerprint
+// Modules: decoder (+ prefetch for playback), bpm, key (+ key_bench scoring), waveform, spectrogram, loudness, fingerprint, quality,
+// mime (Content-Type of served files), stream_access (which files stream:// serves)
 
 pub mod decoder;
+pub mod prefetch;
 pub mod bpm;
 pub mod key;
+pub mod key_bench;
 pub mod waveform;
+pub mod fingerprint;
+pub mod quality;
+pub mod loudness;
+pub mod mime;
+pub mod stream_access;
@@ -0,0 +1,120 @@
+// Prefetch buffer between the playback decoder thread and the chunk emitter
+// The decoder thread pushes decoded items until a fixed amount of audio is buffered, then
+// waits for room; the emitter takes items without blocking. A decoding hiccup then eats
+// into the buffer instead of starving the webview. close() ends both sides.
+
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+struct Inner<T> {
+    /// Items with the length of audio each carries (ms)
+    items: VecDeque<(T, f64)>,
+    buffered_ms: f64,
+    closed: bool,
+}
+
+/// Bounded FIFO measured in milliseconds of audio
+pub struct PrefetchBuffer<T> {
+    inner: Mutex<Inner<T>>,
+    room: Condvar,
+    capacity_ms: f64,
+}
+
+impl<T> PrefetchBuffer<T> {
+    pub fn new(capacity_ms: f64) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                items: VecDeque::new(),
+                buffered_ms: 0.0,
+                closed: false,
+            }),
+            room: Condvar::new(),
+            capacity_ms,
+        }
+    }
+
+    /// Add an item carrying `length_ms` of audio, waiting up to `timeout` for room.
+    /// Returns the item back if the buffer stayed full (so the caller can check whether
+    /// to give up) or was closed.
+    pub fn push(&self, item: T, length_ms: f64, timeout: Duration) -> Result<(), T> {
+        let inner = self.inner.lock().unwrap();
+        let (mut inner, _) = self
+            .room
+            .wait_timeout_while(inner, timeout, |inner| {
+                !inner.closed && inner.buffered_ms >= self.capacity_ms
+            })
+            .unwrap();
+        if inner.closed || inner.buffered_ms >= self.capacity_ms {
+            return Err(item);
+        }
+        inner.buffered_ms += length_ms;
+        inner.items.push_back((item, length_ms));
+        Ok(())
+    }
+
+    /// Take the oldest item, if any (never blocks)
+    pub fn pop(&self) -> Option<T> {
+        let mut inner = self.inner.lock().unwrap();
+        let (item, length_ms) = inner.items.pop_front()?;
+        inner.buffered_ms = (inner.buffered_ms - length_ms).max(0.0);
+        self.room.notify_one();
+        Some(item)
+    }
+
+    /// Audio waiting in the buffer (ms)
+    pub fn buffered_ms(&self) -> f64 {
+        self.inner.lock().unwrap().buffered_ms
+    }
+
+    /// Stop accepting items and wake a waiting producer
+    pub fn close(&self) {
+        self.inner.lock().unwrap().closed = true;
+        self.room.notify_all();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.lock().unwrap().closed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_prefetch_buffer() {
+        let buffer = Arc::new(PrefetchBuffer::new(100.0));
+        let wait = Duration::from_millis(10);
+
+        assert!(buffer.push(1, 60.0, wait).is_ok());
+        assert!(buffer.push(2, 60.0, wait).is_ok()); // 120 ms: over capacity now
+        assert_eq!(buffer.push(3, 60.0, wait), Err(3));
+        assert_eq!(buffer.buffered_ms(), 120.0);
+
+        // A waiting producer gets in as soon as the consumer makes room
+        let producer = {
+            let buffer = buffer.clone();
+            thread::spawn(move || buffer.push(3, 60.0, Duration::from_secs(5)))
+        };
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(buffer.pop(), Some(1));
+        assert!(producer.join().unwrap().is_ok());
+        assert_eq!(buffer.pop(), Some(2));
+        assert_eq!(buffer.pop(), Some(3));
+        assert_eq!(buffer.pop(), None);
+
+        // Closing wakes a waiting producer and refuses further items
+        assert!(buffer.push(4, 200.0, wait).is_ok());
+        let producer = {
+            let buffer = buffer.clone();
+            thread::spawn(move || buffer.push(5, 10.0, Duration::from_secs(5)))
+        };
+        thread::sleep(Duration::from_millis(20));
+        buffer.close();
+        assert_eq!(producer.join().unwrap(), Err(5));
+        assert!(buffer.is_closed());
+    }
+}
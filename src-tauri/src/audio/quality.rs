@@ -0,0 +1,345 @@
+// Technical quality checks: spectral ceiling, DC offset and mains hum
+// Only the audible band (20 Hz - 20 kHz) is looked at, so ultrasonic noise in hi-res files
+// doesn't count as content and a 22 kHz filter in a 44.1 kHz file doesn't count as a cut.
+//
+// - Low-pass ceiling: MP3/AAC encoders throw away everything above ~16 kHz (128 kbps) to
+//   ~19-20 kHz (256-320 kbps). A "lossless" file whose averaged spectrum falls off a cliff
+//   there was almost certainly transcoded from a lossy source.
+// - DC offset: a constant shift of the waveform (bad converter or edit), which eats
+//   headroom and clicks on cuts.
+// - Mains hum: a steady 50/60 Hz peak (plus its first harmonic) in the quiet passages,
+//   where the music doesn't mask it (vinyl rips, bad grounding).
+
+use super::decoder::AudioDecoder;
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Bits of the stored quality_flags
+pub const FLAG_LOSSY_CUTOFF: u32 = 1;
+pub const FLAG_LOW_BANDWIDTH: u32 = 2;
+pub const FLAG_DC_OFFSET: u32 = 4;
+pub const FLAG_HUM: u32 = 8;
+/// Flag names, as used by the frontend
+pub const FLAG_NAMES: [(u32, &str); 4] = [
+    (FLAG_LOSSY_CUTOFF, "lossy_cutoff"),
+    (FLAG_LOW_BANDWIDTH, "low_bandwidth"),
+    (FLAG_DC_OFFSET, "dc_offset"),
+    (FLAG_HUM, "hum"),
+];
+
+/// ~0.37 s at 44.1 kHz: fine enough (2.7 Hz bins) to separate hum from bass
+const FRAME_SIZE: usize = 16384;
+const AUDIBLE_MIN_HZ: f32 = 20.0;
+const AUDIBLE_MAX_HZ: f32 = 20_000.0;
+/// Frames quieter than this (RMS) are left out of the averaged spectrum
+const SPECTRUM_MIN_RMS: f32 = 0.001;
+/// Frames quieter than this are digital silence (no hum either)
+const SILENCE_RMS: f32 = 1e-5;
+/// Ceiling search: band width, lowest ceiling considered, and the drop that makes a cliff
+const CUTOFF_BAND_HZ: f32 = 250.0;
+const CUTOFF_MIN_HZ: f32 = 10_000.0;
+const CUTOFF_DROP_DB: f32 = 30.0;
+/// Lossless files with a ceiling below this were made from a lossy source
+const LOSSY_CUTOFF_HZ: f32 = 19_500.0;
+/// Any file with a ceiling below this came from a 128 kbps (or worse) source
+const LOW_BANDWIDTH_HZ: f32 = 16_500.0;
+/// Mean of either channel above this is a DC offset (about -40 dBFS)
+const DC_OFFSET_THRESHOLD: f32 = 0.01;
+/// Hum: mains frequencies, and how far the peak must stand above the bins next to it
+const MAINS_HZ: [f32; 2] = [50.0, 60.0];
+const HUM_MIN_DB: f32 = 10.0;
+
+/// What the analyzer found
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityReport {
+    /// Frequency the spectrum stops at, if it stops inside the audible band
+    pub cutoff_hz: Option<f32>,
+    /// Largest channel mean (-1 to 1)
+    pub dc_offset: f32,
+    /// Mains frequency of the hum, if any
+    pub hum_hz: Option<f32>,
+}
+
+impl QualityReport {
+    /// The quality_flags bitfield; the lossy-cutoff check only applies to lossless files
+    pub fn flags(&self, lossless: bool) -> u32 {
+        let mut flags = 0;
+        if let Some(cutoff) = self.cutoff_hz {
+            if lossless && cutoff < LOSSY_CUTOFF_HZ {
+                flags |= FLAG_LOSSY_CUTOFF;
+            }
+            if cutoff < LOW_BANDWIDTH_HZ {
+                flags |= FLAG_LOW_BANDWIDTH;
+            }
+        }
+        if self.dc_offset.abs() > DC_OFFSET_THRESHOLD {
+            flags |= FLAG_DC_OFFSET;
+        }
+        if self.hum_hz.is_some() {
+            flags |= FLAG_HUM;
+        }
+        flags
+    }
+}
+
+/// Names of the bits set in `flags`
+pub fn flag_names(flags: u32) -> Vec<&'static str> {
+    FLAG_NAMES.iter().filter(|(bit, _)| flags & bit != 0).map(|(_, name)| *name).collect()
+}
+
+/// Streaming analyzer: push interleaved stereo samples, then finish()
+pub struct QualityAnalyzer {
+    sample_rate: u32,
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    buffer: Vec<f32>,
+    /// Per channel sums, for the DC offset
+    channel_sums: [f64; 2],
+    frames_seen: u64,
+    /// Summed power per bin up to the top of the audible band, and frames summed
+    power_sum: Vec<f64>,
+    spectrum_frames: u32,
+    /// Per non-silent frame: RMS and the hum score at each mains frequency
+    hum_scores: Vec<(f32, [f32; 2])>,
+}
+
+impl QualityAnalyzer {
+    pub fn new(sample_rate: u32) -> Self {
+        let fft = FftPlanner::new().plan_fft_forward(FRAME_SIZE);
+        let window = (0..FRAME_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / FRAME_SIZE as f32).cos())
+            .collect();
+        let analyzer = QualityAnalyzer {
+            sample_rate,
+            fft,
+            window,
+            buffer: Vec::with_capacity(FRAME_SIZE),
+            channel_sums: [0.0; 2],
+            frames_seen: 0,
+            power_sum: Vec::new(),
+            spectrum_frames: 0,
+            hum_scores: Vec::new(),
+        };
+        let top_bin = analyzer.bin_of(analyzer.top_hz());
+        QualityAnalyzer { power_sum: vec![0.0; top_bin + 1], ..analyzer }
+    }
+
+    fn bin_width(&self) -> f32 {
+        self.sample_rate as f32 / FRAME_SIZE as f32
+    }
+
+    fn bin_of(&self, freq: f32) -> usize {
+        ((freq / self.bin_width()).round() as usize).min(FRAME_SIZE / 2)
+    }
+
+    /// Top of the audible band, or of the file's bandwidth if lower
+    fn top_hz(&self) -> f32 {
+        AUDIBLE_MAX_HZ.min(self.sample_rate as f32 / 2.0)
+    }
+
+    /// Feed interleaved stereo samples
+    pub fn push(&mut self, samples: &[f32]) {
+        for lr in samples.chunks_exact(2) {
+            self.channel_sums[0] += lr[0] as f64;
+            self.channel_sums[1] += lr[1] as f64;
+            self.frames_seen += 1;
+            self.buffer.push((lr[0] + lr[1]) * 0.5);
+            if self.buffer.len() == FRAME_SIZE {
+                self.process_frame();
+                self.buffer.clear();
+            }
+        }
+    }
+
+    fn process_frame(&mut self) {
+        let rms = (self.buffer.iter().map(|s| s * s).sum::<f32>() / FRAME_SIZE as f32).sqrt();
+        if rms < SILENCE_RMS {
+            return;
+        }
+        let mut spectrum: Vec<Complex<f32>> = self
+            .buffer
+            .iter()
+            .zip(&self.window)
+            .map(|(s, w)| Complex::new(s * w, 0.0))
+            .collect();
+        self.fft.process(&mut spectrum);
+        let power: Vec<f32> = spectrum[..=FRAME_SIZE / 2].iter().map(|c| c.norm_sqr()).collect();
+
+        if rms >= SPECTRUM_MIN_RMS {
+            let first = self.bin_of(AUDIBLE_MIN_HZ);
+            for (sum, p) in self.power_sum.iter_mut().zip(&power).skip(first) {
+                *sum += *p as f64;
+            }
+            self.spectrum_frames += 1;
+        }
+
+        let scores = MAINS_HZ.map(|hz| self.peak_db(&power, hz).min(self.peak_db(&power, hz * 2.0)));
+        self.hum_scores.push((rms, scores));
+    }
+
+    /// How far the strongest bin within 1.5 Hz of `freq` stands above the median of the
+    /// bins 5-20 Hz away (dB)
+    fn peak_db(&self, power: &[f32], freq: f32) -> f32 {
+        let width = self.bin_width();
+        let peak_bins = self.bin_of(freq - 1.5)..=self.bin_of(freq + 1.5);
+        let peak = peak_bins.map(|b| power[b]).fold(0.0f32, f32::max);
+        let mut around: Vec<f32> = (self.bin_of(freq - 20.0)..=self.bin_of(freq + 20.0))
+            .filter(|&b| (b as f32 * width - freq).abs() >= 5.0)
+            .map(|b| power[b])
+            .collect();
+        if around.is_empty() {
+            return 0.0;
+        }
+        around.sort_by(f32::total_cmp);
+        let median = around[around.len() / 2];
+        10.0 * ((peak + 1e-12) / (median + 1e-12)).log10()
+    }
+
+    /// Where the averaged spectrum falls off a cliff: the band edge with the largest drop
+    /// between the kilohertz below it and everything above it, if the drop is big enough
+    fn find_cutoff(&self) -> Option<f32> {
+        if self.spectrum_frames == 0 {
+            return None;
+        }
+        let frames = self.spectrum_frames as f64;
+        let bins_per_band = ((CUTOFF_BAND_HZ / self.bin_width()).round() as usize).max(1);
+        let first_band = self.bin_of(CUTOFF_MIN_HZ - 1_000.0) / bins_per_band;
+        let last_band = self.bin_of(self.top_hz()) / bins_per_band;
+        if last_band <= first_band + 4 {
+            return None;
+        }
+        let levels: Vec<f32> = (first_band..last_band)
+            .map(|band| {
+                let bins = &self.power_sum[band * bins_per_band..(band + 1) * bins_per_band];
+                let mean = bins.iter().sum::<f64>() / (bins.len() as f64 * frames);
+                (10.0 * (mean + 1e-20).log10()) as f32
+            })
+            .collect();
+
+        let below_bands = (1_000.0 / CUTOFF_BAND_HZ) as usize;
+        (below_bands..levels.len())
+            .map(|edge| {
+                let below = levels[edge - below_bands..edge].iter().sum::<f32>() / below_bands as f32;
+                let above = levels[edge..].iter().copied().fold(f32::MIN, f32::max);
+                (edge, below - above)
+            })
+            .filter(|&(edge, drop)| {
+                drop >= CUTOFF_DROP_DB
+                    && (first_band + edge) as f32 * bins_per_band as f32 * self.bin_width() >= CUTOFF_MIN_HZ
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(edge, _)| (first_band + edge) as f32 * bins_per_band as f32 * self.bin_width())
+    }
+
+    /// Mains frequency whose hum stands out in the quietest quarter of the (non-silent) frames
+    fn find_hum(&self) -> Option<f32> {
+        let mut frames = self.hum_scores.clone();
+        if frames.is_empty() {
+            return None;
+        }
+        frames.sort_by(|a, b| a.0.total_cmp(&b.0));
+        frames.truncate((frames.len() / 4).max(1));
+        MAINS_HZ
+            .iter()
+            .enumerate()
+            .map(|(i, &hz)| {
+                let mut scores: Vec<f32> = frames.iter().map(|(_, s)| s[i]).collect();
+                scores.sort_by(f32::total_cmp);
+                (hz, scores[scores.len() / 2])
+            })
+            .filter(|&(_, score)| score >= HUM_MIN_DB)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(hz, _)| hz)
+    }
+
+    pub fn finish(self) -> QualityReport {
+        let frames = self.frames_seen.max(1) as f64;
+        let dc_offset = self
+            .channel_sums
+            .iter()
+            .map(|sum| (sum / frames) as f32)
+            .max_by(|a, b| a.abs().total_cmp(&b.abs()))
+            .unwrap_or(0.0);
+        QualityReport {
+            cutoff_hz: self.find_cutoff(),
+            dc_offset,
+            hum_hz: self.find_hum(),
+        }
+    }
+}
+
+/// Analyze a whole file, decoded in chunks
+pub fn analyze_file(path: &Path) -> Result<QualityReport, String> {
+    let mut decoder = AudioDecoder::new(path)?;
+    let mut analyzer = QualityAnalyzer::new(decoder.sample_rate());
+    while let Some(chunk) = decoder.decode_next_chunk()? {
+        if chunk.is_end {
+            break;
+        }
+        analyzer.push(&chunk.samples);
+    }
+    Ok(analyzer.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 44100;
+
+    /// Stereo test signal: tones every 100 Hz up to `top_hz`, plus `extra` per sample
+    fn signal(seconds: f32, top_hz: f32, extra: impl Fn(f32) -> f32) -> Vec<f32> {
+        let tones: Vec<(f32, f32)> = (1..)
+            .map(|i| i as f32 * 100.0 + 37.0)
+            .take_while(|&f| f < top_hz)
+            .enumerate()
+            .map(|(i, f)| (f, i as f32 * 2.39))
+            .collect();
+        (0..(RATE as f32 * seconds) as usize)
+            .flat_map(|i| {
+                let t = i as f32 / RATE as f32;
+                let music: f32 = tones
+                    .iter()
+                    .map(|&(f, phase)| (2.0 * std::f32::consts::PI * f * t + phase).sin())
+                    .sum::<f32>()
+                    * 0.003;
+                let s = music + extra(t);
+                [s, s]
+            })
+            .collect()
+    }
+
+    fn analyze(samples: &[f32]) -> QualityReport {
+        let mut analyzer = QualityAnalyzer::new(RATE);
+        for chunk in samples.chunks(2304) {
+            analyzer.push(chunk);
+        }
+        analyzer.finish()
+    }
+
+    #[test]
+    fn test_quality_analyzer() {
+        // Full band, clean
+        let clean = analyze(&signal(2.0, 21_000.0, |_| 0.0));
+        assert_eq!(clean.cutoff_hz, None);
+        assert_eq!(clean.flags(true), 0);
+
+        // Cut at 16 kHz like a 128 kbps MP3
+        let transcoded = analyze(&signal(2.0, 16_000.0, |_| 0.0));
+        let cutoff = transcoded.cutoff_hz.expect("cutoff");
+        assert!((15_500.0..16_500.0).contains(&cutoff), "cutoff {}", cutoff);
+        assert_eq!(transcoded.flags(true), FLAG_LOSSY_CUTOFF | FLAG_LOW_BANDWIDTH);
+        assert_eq!(transcoded.flags(false), FLAG_LOW_BANDWIDTH);
+
+        // Offset waveform with 60 Hz hum (and its harmonic) under the music
+        let hum = |t: f32| {
+            0.02 + 0.01 * (2.0 * std::f32::consts::PI * 60.0 * t).sin()
+                + 0.005 * (2.0 * std::f32::consts::PI * 120.0 * t).sin()
+        };
+        let humming = analyze(&signal(2.0, 21_000.0, hum));
+        assert_eq!(humming.hum_hz, Some(60.0));
+        assert!((humming.dc_offset - 0.02).abs() < 1e-3);
+        assert_eq!(flag_names(humming.flags(true)), vec!["dc_offset", "hum"]);
+    }
+}
@@ -0,0 +1,165 @@
+// Which files the stream:// protocol serves
+// The webview asks for files by absolute path, so unchecked, anything running in it could
+// read any file the app can. By default only files under the library folders and the
+// artwork thumbnail cache are served. Paths are canonicalized first, so ".." and links
+// leading out of a folder don't get around it; the exception is a link inside a library
+// folder that follows symlinks (scanner::SymlinkMode), since its tracks are played
+// through the link. The setting can turn the check off (e.g. to play files outside the
+// library); rejected requests are logged.
+
+use crate::artwork::thumbnail_dir;
+use crate::db::Database;
+use crate::scanner::{collapse_roots, load_symlink_folders, normalize_root, SymlinkMode};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+
+/// Setting key: "false" serves any path; anything else (or unset) restricts to the library
+pub const STREAM_RESTRICT_SETTING: &str = "stream_restrict_to_library";
+
+/// A folder files may be served from
+#[derive(Debug, Clone, PartialEq)]
+struct AllowedRoot {
+    /// As configured (what paths reached through a followed link start with)
+    path: PathBuf,
+    /// None while the folder doesn't exist (e.g. an unplugged drive)
+    canonical: Option<PathBuf>,
+    follows_links: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamAccess {
+    enforced: bool,
+    roots: Vec<AllowedRoot>,
+}
+
+impl StreamAccess {
+    /// `folders`: (folder, whether its symlinks are followed)
+    pub fn new(enforced: bool, folders: &[(PathBuf, bool)]) -> Self {
+        let roots = folders
+            .iter()
+            .map(|(path, follows_links)| AllowedRoot {
+                path: path.clone(),
+                canonical: fs::canonicalize(path).ok(),
+                follows_links: *follows_links,
+            })
+            .collect();
+        StreamAccess { enforced, roots }
+    }
+
+    /// The library folders and the thumbnail cache, as configured in the settings
+    pub fn from_settings(db: &Database) -> Self {
+        let enforced = db.get_setting(STREAM_RESTRICT_SETTING).ok().flatten().as_deref() != Some("false");
+        let library_folders: Vec<String> = db.get_setting("library_folders")
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        let symlink_modes = load_symlink_folders(db);
+
+        let mut folders: Vec<(PathBuf, bool)> = collapse_roots(&library_folders)
+            .into_iter()
+            .map(|folder| {
+                let mode = symlink_modes.get(&normalize_root(&folder)).copied().unwrap_or_default();
+                (PathBuf::from(folder), mode == SymlinkMode::Follow)
+            })
+            .collect();
+        if let Some(dir) = thumbnail_dir(db) {
+            folders.push((dir, false));
+        }
+        StreamAccess::new(enforced, &folders)
+    }
+
+    /// Ok if `path` may be served. Errors: the file's own (e.g. NotFound, so callers can
+    /// try other spellings of the path) or PermissionDenied when it's outside the roots.
+    pub fn check(&self, path: &Path) -> io::Result<()> {
+        if !self.enforced {
+            return Ok(());
+        }
+        let denied = || io::Error::new(io::ErrorKind::PermissionDenied, "outside the library folders");
+        if !path.is_absolute() || path.components().any(|c| c == Component::ParentDir) {
+            return Err(denied());
+        }
+        let canonical = fs::canonicalize(path)?;
+        let allowed = self.roots.iter().any(|root| {
+            root.canonical.as_ref().is_some_and(|dir| canonical.starts_with(dir))
+                || (root.follows_links && path.starts_with(&root.path))
+        });
+        if allowed {
+            Ok(())
+        } else {
+            Err(denied())
+        }
+    }
+}
+
+/// Active access rules for the stream:// handler. Set when the database opens and whenever
+/// the library folders or the setting change; until then nothing is served.
+static ACCESS: RwLock<Option<StreamAccess>> = RwLock::new(None);
+
+pub fn configure(access: StreamAccess) {
+    *ACCESS.write().unwrap() = Some(access);
+}
+
+/// Reload the rules from the settings (after library folders or symlink modes changed)
+pub fn refresh(db: &Database) {
+    configure(StreamAccess::from_settings(db));
+}
+
+/// Check `path` against the active rules (see StreamAccess::check)
+pub fn check(path: &Path) -> io::Result<()> {
+    match ACCESS.read().unwrap().as_ref() {
+        Some(access) => access.check(path),
+        None => Err(io::Error::new(io::ErrorKind::PermissionDenied, "library not loaded yet")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_only_library_files_are_served() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        let library = base.join("library");
+        let outside = base.join("outside");
+        fs::create_dir_all(library.join("house")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(library.join("house/a.mp3"), b"a").unwrap();
+        fs::write(outside.join("secret.txt"), b"s").unwrap();
+
+        let access = StreamAccess::new(true, &[(library.clone(), false)]);
+        assert!(access.check(&library.join("house/a.mp3")).is_ok());
+        let denied = access.check(&outside.join("secret.txt")).unwrap_err();
+        assert_eq!(denied.kind(), io::ErrorKind::PermissionDenied);
+        // ".." can't climb out, even when it would land back inside
+        let climbing = library.join("house/../../outside/secret.txt");
+        assert_eq!(access.check(&climbing).unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+        assert!(access.check(&library.join("house/../house/a.mp3")).is_err());
+        assert!(access.check(Path::new("house/a.mp3")).is_err());
+        // Missing files report NotFound (the stream handler then tries other spellings)
+        assert_eq!(access.check(&library.join("gone.mp3")).unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        assert!(StreamAccess::new(false, &[]).check(&outside.join("secret.txt")).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_follow_the_folder_mode() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        let library = base.join("library");
+        let outside = base.join("outside");
+        fs::create_dir_all(&library).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("b.mp3"), b"b").unwrap();
+        std::os::unix::fs::symlink(&outside, library.join("linked")).unwrap();
+
+        let linked = library.join("linked/b.mp3");
+        assert!(StreamAccess::new(true, &[(library.clone(), true)]).check(&linked).is_ok());
+        assert!(StreamAccess::new(true, &[(library.clone(), false)]).check(&linked).is_err());
+    }
+}
@@ -18,6 +18,16 @@ pub struct WaveformPoint {
     pub high: u8,
 }
 
+/// Peaks at or below this (about -60 dBFS) count as silence
+const SILENCE_PEAK: f32 = 0.001;
+
+/// Dead air at the start and end of a track
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Silence {
+    pub lead_ms: u64,
+    pub tail_ms: u64,
+}
+
 /// Waveform data with metadata
 #[derive(Debug, Clone)]
 pub struct WaveformData {
@@ -50,6 +60,22 @@ impl WaveformData {
         blob
     }
     
+    /// Leading/trailing silence, to the resolution of one waveform point.
+    /// A track that is silent throughout reports no silence (nothing sensible to trim).
+    pub fn silence(&self) -> Silence {
+        let first = self.points.iter().position(|p| p.peak > SILENCE_PEAK);
+        let last = self.points.iter().rposition(|p| p.peak > SILENCE_PEAK);
+        let (Some(first), Some(last)) = (first, last) else {
+            return Silence::default();
+        };
+
+        let ms_per_point = self.duration_ms as f64 / self.points.len() as f64;
+        Silence {
+            lead_ms: (first as f64 * ms_per_point) as u64,
+            tail_ms: ((self.points.len() - 1 - last) as f64 * ms_per_point) as u64,
+        }
+    }
+
     /// Deserialize from binary BLOB
     pub fn from_blob(blob: &[u8]) -> Result<Self, String> {
         if blob.len() < 17 {
@@ -243,4 +269,17 @@ mod tests {
         assert_eq!(restored.points[0].low, 100);
         assert_eq!(restored.points[1].high, 150);
     }
+
+    #[test]
+    fn test_silence_detection() {
+        let point = |peak| WaveformPoint { peak, low: 0, mid: 0, high: 0 };
+        let mut points = vec![point(0.0); 10];
+        points[3] = point(0.4);
+        points[7] = point(0.6);
+        let data = WaveformData { points, sample_rate: 44100, duration_ms: 10_000 };
+        assert_eq!(data.silence(), Silence { lead_ms: 3000, tail_ms: 2000 });
+
+        let silent = WaveformData { points: vec![point(0.0); 4], sample_rate: 44100, duration_ms: 4000 };
+        assert_eq!(silent.silence(), Silence::default());
+    }
 }
@@ -0,0 +1,166 @@
+// Key detection benchmark: runs detect_key over a directory of labeled audio files and
+// reports accuracy per detector configuration, so changes to profiles or windows can be
+// compared objectively.
+//
+//   cargo run --release --bin key_bench -- <dir> [--profile shaath|temperley|ks|all]
+//       [--fft 4096] [--hop 2048] [--matrix] [--verbose]
+//
+// The labeled key is read from each file name, e.g. "Artist - Title [8A].mp3" or
+// "Am - Artist - Title.flac" (see audio::key_bench::ground_truth_from_filename).
+// Files without a recognizable label are skipped.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+
+use recodeck_lib::audio::decoder::decode_to_mono;
+use recodeck_lib::audio::key::{detect_key_with_options, KeyOptions, KeyProfile};
+use recodeck_lib::audio::key_bench::{ground_truth_from_filename, BenchReport, KeyMatch};
+use recodeck_lib::scanner::Scanner;
+
+const USAGE: &str = "usage: key_bench <dir> [--profile shaath|temperley|ks|all] [--fft N] [--hop N] [--matrix] [--verbose]";
+
+struct Args {
+    dir: PathBuf,
+    profiles: Vec<KeyProfile>,
+    fft_size: usize,
+    hop_size: usize,
+    matrix: bool,
+    verbose: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let defaults = KeyOptions::default();
+    let mut dir = None;
+    let mut profiles = vec![defaults.profile];
+    let mut fft_size = defaults.fft_size;
+    let mut hop_size = defaults.hop_size;
+    let mut matrix = false;
+    let mut verbose = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--profile" => {
+                let name = args.next().ok_or("--profile needs a value")?;
+                profiles = if name == "all" {
+                    KeyProfile::ALL.to_vec()
+                } else {
+                    vec![KeyProfile::from_name(&name).ok_or(format!("Unknown profile: {}", name))?]
+                };
+            }
+            "--fft" | "--hop" => {
+                let value: usize = args
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or(format!("{} needs a number", arg))?;
+                if arg == "--fft" {
+                    fft_size = value;
+                } else {
+                    hop_size = value;
+                }
+            }
+            "--matrix" => matrix = true,
+            "--verbose" | "-v" => verbose = true,
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ if dir.is_none() && !arg.starts_with('-') => dir = Some(PathBuf::from(arg)),
+            _ => return Err(format!("Unexpected argument: {}\n{}", arg, USAGE)),
+        }
+    }
+
+    Ok(Args {
+        dir: dir.ok_or(USAGE)?,
+        profiles,
+        fft_size,
+        hop_size,
+        matrix,
+        verbose,
+    })
+}
+
+fn labeled_files(dir: &Path) -> Vec<(PathBuf, String)> {
+    let mut files: Vec<(PathBuf, String)> = Scanner::scan_directory(dir)
+        .into_iter()
+        .filter_map(|path| {
+            let label = ground_truth_from_filename(&path.file_name()?.to_string_lossy())?;
+            Some((path, label))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let files = labeled_files(&args.dir);
+    if files.is_empty() {
+        eprintln!("No labeled audio files found in {}", args.dir.display());
+        return ExitCode::FAILURE;
+    }
+    println!(
+        "{} labeled files, fft {} / hop {}",
+        files.len(),
+        args.fft_size,
+        args.hop_size
+    );
+
+    let options: Vec<KeyOptions> = args
+        .profiles
+        .iter()
+        .map(|&profile| KeyOptions {
+            profile,
+            fft_size: args.fft_size,
+            hop_size: args.hop_size,
+        })
+        .collect();
+    let mut reports = vec![BenchReport::default(); options.len()];
+    let started = Instant::now();
+
+    // Decoding dominates, so each file is decoded once and run through every configuration
+    for (path, expected) in &files {
+        let audio = match decode_to_mono(path) {
+            Ok(audio) => audio,
+            Err(e) => {
+                eprintln!("[key_bench] {}: {}", path.display(), e);
+                reports.iter_mut().for_each(BenchReport::add_failure);
+                continue;
+            }
+        };
+        for (opts, report) in options.iter().zip(reports.iter_mut()) {
+            match detect_key_with_options(&audio, opts) {
+                Ok(result) => {
+                    let key_match = report.add(expected, &result.camelot);
+                    if args.verbose && key_match != Some(KeyMatch::Exact) {
+                        println!(
+                            "  [{}] {}: expected {}, got {} ({}, confidence {:.2})",
+                            opts.profile.name(),
+                            path.display(),
+                            expected,
+                            result.camelot,
+                            key_match.map(KeyMatch::label).unwrap_or("?"),
+                            result.confidence
+                        );
+                    }
+                }
+                Err(e) => {
+                    eprintln!("[key_bench] {} ({}): {}", path.display(), opts.profile.name(), e);
+                    report.add_failure();
+                }
+            }
+        }
+    }
+
+    for (opts, report) in options.iter().zip(&reports) {
+        println!("\nprofile {}", opts.profile.name());
+        print!("{}", report.render(args.matrix));
+    }
+    println!("\ndone in {:.1}s", started.elapsed().as_secs_f64());
+    ExitCode::SUCCESS
+}
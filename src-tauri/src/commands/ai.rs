@@ -1,16 +1,22 @@
 // Tauri commands for AI features
 //
 // Provides commands for:
-// - API key management (stored in settings DB)
+// - API key management (Claude key in settings DB; per-provider keys in the OS keychain)
 // - Pre-cached library context for instant AI responses
-// - Playlist generation
+// - Playlist generation and refinement
 // - Chat interaction
+// - Token usage per month and feature (get_ai_usage)
 
-use crate::ai::{ClaudeClient, TrackContextBuilder, SYSTEM_PROMPT};
+use crate::ai::claude_client::{estimated_cost_usd, PlaylistRefinement};
+use crate::ai::context_builder::{SampledContext, CONTEXT_TRACK_BUDGET, MAX_MATCHED_TRACKS};
+use crate::ai::credentials::{ApiProvider, KeyValidation};
+use crate::ai::{ClaudeClient, CredentialManager, TrackContextBuilder, SYSTEM_PROMPT};
 use crate::commands::library::AppState;
-use crate::db::{Track, TrackAnalysis};
+use crate::commands::playlists::{ensure_editable, notify_playlists_changed};
+use crate::db::{AiUsage, Track, TrackAnalysis};
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use std::collections::{HashMap, HashSet};
+use tauri::{AppHandle, State};
 
 /// Generated playlist from AI
 #[derive(Debug, Serialize, Deserialize)]
@@ -19,6 +25,24 @@ pub struct GeneratedPlaylist {
     pub description: String,
     pub track_ids: Vec<i64>,
     pub reasoning: String,
+    /// Share of the library the model was shown (0-100)
+    pub context_coverage: f64,
+}
+
+/// A playlist after ai_refine_playlist applied the model's changes
+#[derive(Debug, Serialize)]
+pub struct RefinedPlaylist {
+    pub playlist_id: i64,
+    /// The playlist's tracks after the changes, in order
+    pub track_ids: Vec<i64>,
+    pub added: Vec<i64>,
+    pub removed: Vec<i64>,
+    pub moved: Vec<i64>,
+    /// Proposed changes that were dropped (unknown track, already in the playlist, ...)
+    pub rejected: Vec<String>,
+    pub reasoning: String,
+    /// Share of the library the model was shown (0-100)
+    pub context_coverage: f64,
 }
 
 /// Chat message for conversation history
@@ -30,20 +54,111 @@ pub struct ChatMessage {
 }
 
 const AI_API_KEY_SETTING: &str = "ai_api_key";
+/// Last validation result per provider (JSON map provider -> ApiKeyCheck)
+const API_KEY_STATUS_SETTING: &str = "api_key_status";
+
+/// Helper: get the Claude API key from settings DB, falling back to the keychain
+pub(crate) fn get_api_key_from_db(state: &State<'_, AppState>) -> Result<Option<String>, String> {
+    {
+        let db_guard = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        let db = db_guard.as_ref().ok_or_else(|| "Database not initialized".to_string())?;
+        if let Ok(Some(val)) = db.get_setting(AI_API_KEY_SETTING) {
+            if !val.is_empty() {
+                return Ok(Some(val));
+            }
+        }
+    }
+    Ok(CredentialManager::retrieve_key(ApiProvider::Anthropic).unwrap_or(None))
+}
+
+/// Add the tokens `client` used to this month's tally for `purpose`. Failures are only
+/// logged: the request itself went through.
+pub(crate) fn record_usage(state: &State<'_, AppState>, client: &ClaudeClient, purpose: &str) {
+    let usage = client.usage();
+    if usage.requests == 0 {
+        return;
+    }
+    let Ok(db_guard) = state.db.lock() else {
+        return;
+    };
+    if let Some(db) = db_guard.as_ref() {
+        let recorded = db.record_ai_usage(
+            purpose,
+            usage.requests as i64,
+            usage.input_tokens as i64,
+            usage.output_tokens as i64,
+        );
+        if let Err(e) = recorded {
+            eprintln!("[ai] Failed to record token usage: {}", e);
+        }
+    }
+}
+
+/// Helper: a provider's key (the Claude key may also live in the settings DB)
+fn get_provider_key(state: &State<'_, AppState>, provider: ApiProvider) -> Result<Option<String>, String> {
+    match provider {
+        ApiProvider::Anthropic => get_api_key_from_db(state),
+        _ => CredentialManager::retrieve_key(provider),
+    }
+}
+
+/// Outcome of the last validate_api_key call for a provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ApiKeyCheck {
+    valid: bool,
+    message: Option<String>,
+    /// Unix seconds
+    checked_at: i64,
+}
+
+fn load_key_checks(state: &State<'_, AppState>) -> Result<HashMap<ApiProvider, ApiKeyCheck>, String> {
+    let db_guard = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    let db = db_guard.as_ref().ok_or_else(|| "Database not initialized".to_string())?;
+    Ok(db.get_setting(API_KEY_STATUS_SETTING)
+        .map_err(|e| format!("Failed to load API key status: {}", e))?
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default())
+}
+
+fn save_key_check(state: &State<'_, AppState>, provider: ApiProvider, check: Option<ApiKeyCheck>) -> Result<(), String> {
+    let mut checks = load_key_checks(state)?;
+    match check {
+        Some(check) => checks.insert(provider, check),
+        None => checks.remove(&provider),
+    };
+    let json = serde_json::to_string(&checks)
+        .map_err(|e| format!("Failed to serialize API key status: {}", e))?;
 
-/// Helper: get API key from settings DB
-fn get_api_key_from_db(state: &State<'_, AppState>) -> Result<Option<String>, String> {
     let db_guard = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
     let db = db_guard.as_ref().ok_or_else(|| "Database not initialized".to_string())?;
-    match db.get_setting(AI_API_KEY_SETTING) {
-        Ok(Some(val)) if !val.is_empty() => Ok(Some(val)),
-        Ok(_) => Ok(None),
-        Err(_) => Ok(None),
+    db.set_setting(API_KEY_STATUS_SETTING, &json)
+        .map_err(|e| format!("Failed to save API key status: {}", e))
+}
+
+/// Key status for one provider
+#[derive(Debug, Serialize)]
+pub struct ApiKeyStatusDTO {
+    pub provider: ApiProvider,
+    pub configured: bool,
+    /// Result of the last validation; None if never validated since the key was set
+    pub valid: Option<bool>,
+    pub message: Option<String>,
+    /// Unix seconds
+    pub checked_at: Option<i64>,
+}
+
+fn key_status(provider: ApiProvider, configured: bool, check: Option<&ApiKeyCheck>) -> ApiKeyStatusDTO {
+    ApiKeyStatusDTO {
+        provider,
+        configured,
+        valid: check.map(|c| c.valid),
+        message: check.and_then(|c| c.message.clone()),
+        checked_at: check.map(|c| c.checked_at),
     }
 }
 
-/// Helper: build and cache AI context from current library
-fn rebuild_context_cache(state: &State<'_, AppState>) -> Result<String, String> {
+/// Helper: build and cache AI context from current library (sampled if it's too big)
+fn rebuild_context_cache(state: &State<'_, AppState>) -> Result<SampledContext, String> {
     let context = {
         let db_guard = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
         let db = db_guard.as_ref().ok_or_else(|| "Database not initialized".to_string())?;
@@ -59,9 +174,26 @@ fn rebuild_context_cache(state: &State<'_, AppState>) -> Result<String, String>
                 (track, analysis)
             })
             .collect();
-
-        TrackContextBuilder::build_full_context(&tracks_with_analysis)?
+        let play_stats = db.get_play_stats()
+            .map_err(|e| format!("Failed to get play stats: {}", e))?;
+        let history_stamp = db.get_play_history_stamp()
+            .map_err(|e| format!("Failed to get play history: {}", e))?;
+
+        let mut context = TrackContextBuilder::build_sampled_context(
+            &tracks_with_analysis,
+            CONTEXT_TRACK_BUDGET,
+            &play_stats,
+        )?;
+        context.history_stamp = Some(history_stamp);
+        context
     };
+    if context.track_ids.len() < context.total_tracks {
+        eprintln!(
+            "[ai] Library context samples {} of {} tracks",
+            context.track_ids.len(),
+            context.total_tracks
+        );
+    }
 
     // Store in cache
     let mut cache = state.ai_context_cache.lock().map_err(|e| format!("Cache lock failed: {}", e))?;
@@ -70,19 +202,84 @@ fn rebuild_context_cache(state: &State<'_, AppState>) -> Result<String, String>
     Ok(context)
 }
 
-/// Helper: get cached context or rebuild it
-fn get_or_build_context(state: &State<'_, AppState>) -> Result<String, String> {
+/// Helper: get cached context or rebuild it (also when plays were recorded since, or the
+/// day changed, so play stats stay current)
+fn get_or_build_context(state: &State<'_, AppState>) -> Result<SampledContext, String> {
+    let history_stamp = {
+        let db_guard = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        let db = db_guard.as_ref().ok_or_else(|| "Database not initialized".to_string())?;
+        db.get_play_history_stamp()
+            .map_err(|e| format!("Failed to get play history: {}", e))?
+    };
     // Try cache first
     {
         let cache = state.ai_context_cache.lock().map_err(|e| format!("Cache lock failed: {}", e))?;
         if let Some(ref cached) = *cache {
-            return Ok(cached.clone());
+            if cached.history_stamp.as_ref() == Some(&history_stamp) {
+                return Ok(cached.clone());
+            }
         }
     }
     // Cache miss - rebuild
     rebuild_context_cache(state)
 }
 
+/// Helper: library context for a prompt. When the cached context is only a sample, tracks
+/// matching the prompt's keywords (library search) that the sample left out are listed
+/// after it. Profiles of the genres the prompt names are added at the end.
+/// Returns the context and its coverage of the library (0-100).
+fn get_prompt_context(state: &State<'_, AppState>, prompt: &str) -> Result<(String, f64), String> {
+    let sampled = get_or_build_context(state)?;
+    let genre_notes = {
+        let db_guard = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        let db = db_guard.as_ref().ok_or_else(|| "Database not initialized".to_string())?;
+        let profiles = db.get_genre_profiles()
+            .map_err(|e| format!("Failed to get genre profiles: {}", e))?;
+        TrackContextBuilder::genre_profile_notes(&profiles, prompt)
+    };
+    if sampled.track_ids.len() >= sampled.total_tracks {
+        return Ok((sampled.context + &genre_notes, 100.0));
+    }
+
+    let (matched, play_stats) = {
+        let db_guard = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        let db = db_guard.as_ref().ok_or_else(|| "Database not initialized".to_string())?;
+        let play_stats = db.get_play_stats()
+            .map_err(|e| format!("Failed to get play stats: {}", e))?;
+
+        let mut seen: HashSet<i64> = HashSet::new();
+        let mut matched: Vec<(Track, Option<TrackAnalysis>)> = Vec::new();
+        'keywords: for keyword in TrackContextBuilder::prompt_keywords(prompt) {
+            let tracks = db.search_tracks(&keyword)
+                .map_err(|e| format!("Failed to search tracks: {}", e))?;
+            for track in tracks {
+                let Some(id) = track.id else { continue };
+                if sampled.track_ids.contains(&id) || !seen.insert(id) {
+                    continue;
+                }
+                let analysis = db.get_track_analysis(id).ok().flatten();
+                matched.push((track, analysis));
+                if matched.len() >= MAX_MATCHED_TRACKS {
+                    break 'keywords;
+                }
+            }
+        }
+        (matched, play_stats)
+    };
+
+    let coverage = sampled.coverage_pct(matched.len());
+    let mut context = sampled.context;
+    if !matched.is_empty() {
+        context.push_str(&format!(
+            "\nMore tracks matching the request (not in the sample above):\n{}\n",
+            TrackContextBuilder::build_track_list(&matched, &play_stats)?
+        ));
+    }
+    context.push_str(&genre_notes);
+    eprintln!("[ai] Context covers {:.1}% of the library ({} matched tracks added)", coverage, matched.len());
+    Ok((context, coverage))
+}
+
 // ─── Tauri Commands ───
 
 /// Set the Claude API key (stores in settings DB)
@@ -96,8 +293,9 @@ pub async fn set_ai_api_key(state: State<'_, AppState>, api_key: String) -> Resu
     let db = db_guard.as_ref().ok_or_else(|| "Database not initialized".to_string())?;
     db.set_setting(AI_API_KEY_SETTING, &api_key)
         .map_err(|e| format!("Failed to save API key: {}", e))?;
+    drop(db_guard);
 
-    Ok(())
+    save_key_check(&state, ApiProvider::Anthropic, None)
 }
 
 /// Get API key status (whether one is configured)
@@ -123,6 +321,62 @@ pub async fn delete_ai_api_key(state: State<'_, AppState>) -> Result<(), String>
     Ok(())
 }
 
+/// Store an API key for a provider in the OS keychain. Clears its last validation result.
+#[tauri::command]
+pub async fn set_provider_api_key(
+    state: State<'_, AppState>,
+    provider: ApiProvider,
+    api_key: String,
+) -> Result<(), String> {
+    CredentialManager::store_key(provider, api_key.trim())?;
+    save_key_check(&state, provider, None)
+}
+
+/// Delete a provider's API key from the keychain
+#[tauri::command]
+pub async fn delete_provider_api_key(state: State<'_, AppState>, provider: ApiProvider) -> Result<(), String> {
+    CredentialManager::delete_key(provider)?;
+    save_key_check(&state, provider, None)
+}
+
+/// Key status for every provider: configured or not, and the last validation result
+#[tauri::command]
+pub async fn get_api_key_statuses(state: State<'_, AppState>) -> Result<Vec<ApiKeyStatusDTO>, String> {
+    let checks = load_key_checks(&state)?;
+    ApiProvider::ALL
+        .iter()
+        .map(|&provider| {
+            let configured = get_provider_key(&state, provider)?.is_some();
+            Ok(key_status(provider, configured, checks.get(&provider)))
+        })
+        .collect()
+}
+
+/// Check a provider's stored key with a cheap test call and remember the result.
+/// Errors (e.g. offline) are returned without changing the stored status.
+#[tauri::command]
+pub async fn validate_api_key(state: State<'_, AppState>, provider: ApiProvider) -> Result<ApiKeyStatusDTO, String> {
+    let key = get_provider_key(&state, provider)?
+        .ok_or_else(|| format!("No {} API key configured", provider.as_str()))?;
+
+    let (valid, message) = match CredentialManager::validate_key(provider, &key).await? {
+        KeyValidation::Valid => (true, None),
+        KeyValidation::Rejected(message) => (false, Some(message)),
+    };
+    let check = ApiKeyCheck {
+        valid,
+        message,
+        checked_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0),
+    };
+    eprintln!("[ai] {} API key valid: {}", provider.as_str(), check.valid);
+
+    save_key_check(&state, provider, Some(check.clone()))?;
+    Ok(key_status(provider, true, Some(&check)))
+}
+
 /// Rebuild the AI context cache (call after scan/analysis/library changes)
 #[tauri::command]
 pub async fn rebuild_ai_context(state: State<'_, AppState>) -> Result<(), String> {
@@ -139,23 +393,197 @@ pub async fn ai_generate_playlist(
     let api_key = get_api_key_from_db(&state)?
         .ok_or_else(|| "No API key configured. Please set your Claude API key in Settings.".to_string())?;
 
-    // Use cached context (instant)
-    let track_context = get_or_build_context(&state)?;
+    // Use cached context (instant), plus prompt matches on big libraries
+    let (mut track_context, context_coverage) = get_prompt_context(&state, &prompt)?;
+
+    // Tracks in cooldown stay in the shared context (chat can still talk about them), but
+    // are ruled out for generated playlists; anything the model picks anyway is dropped
+    let cooled_down = {
+        let db_guard = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        let db = db_guard.as_ref().ok_or_else(|| "Database not initialized".to_string())?;
+        db.get_active_cooldowns()
+            .map_err(|e| format!("Failed to get cooldowns: {}", e))?
+    };
+    if !cooled_down.is_empty() {
+        let mut ids: Vec<i64> = cooled_down.keys().copied().collect();
+        ids.sort_unstable();
+        let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+        track_context.push_str(&format!(
+            "\n\nDo not use these track IDs (played recently, in cooldown): {}\n",
+            ids.join(", ")
+        ));
+    }
 
     // Create Claude client and generate playlist
     let client = ClaudeClient::new(api_key);
     let response = client
         .generate_playlist(prompt, track_context, SYSTEM_PROMPT.to_string())
-        .await?;
+        .await;
+    record_usage(&state, &client, "playlist_generation");
+    let response = response?;
+
+    let track_ids: Vec<i64> = response
+        .track_ids
+        .into_iter()
+        .filter(|id| !cooled_down.contains_key(id))
+        .collect();
 
     Ok(GeneratedPlaylist {
         name: response.name,
         description: response.description,
-        track_ids: response.track_ids,
+        track_ids,
         reasoning: response.reasoning,
+        context_coverage,
     })
 }
 
+/// Apply the model's changes to a playlist's `current` tracks: removals, then moves, then
+/// adds. Each change is checked on its own and dropped (with a note in `rejected`) if it
+/// doesn't fit the playlist; `can_add` says why a library track can't be added, if so.
+fn apply_refinement(
+    playlist_id: i64,
+    current: &[i64],
+    refinement: &PlaylistRefinement,
+    can_add: impl Fn(i64) -> Result<(), String>,
+) -> RefinedPlaylist {
+    let mut track_ids = current.to_vec();
+    let mut result = RefinedPlaylist {
+        playlist_id,
+        track_ids: Vec::new(),
+        added: Vec::new(),
+        removed: Vec::new(),
+        moved: Vec::new(),
+        rejected: Vec::new(),
+        reasoning: refinement.reasoning.clone(),
+        context_coverage: 100.0,
+    };
+
+    for &id in &refinement.remove {
+        match track_ids.iter().position(|&t| t == id) {
+            Some(index) => {
+                track_ids.remove(index);
+                result.removed.push(id);
+            }
+            None => result.rejected.push(format!("Remove {}: not in the playlist", id)),
+        }
+    }
+
+    let mut touched: HashSet<i64> = HashSet::new();
+    for placed in &refinement.moves {
+        let id = placed.track_id;
+        let Some(index) = track_ids.iter().position(|&t| t == id) else {
+            result.rejected.push(format!("Move {}: not in the playlist", id));
+            continue;
+        };
+        if !touched.insert(id) {
+            result.rejected.push(format!("Move {}: moved more than once", id));
+            continue;
+        }
+        track_ids.remove(index);
+        let position = placed.position.unwrap_or(track_ids.len()).min(track_ids.len());
+        track_ids.insert(position, id);
+        if position != index {
+            result.moved.push(id);
+        }
+    }
+
+    for placed in &refinement.add {
+        let id = placed.track_id;
+        if track_ids.contains(&id) {
+            result.rejected.push(format!("Add {}: already in the playlist", id));
+            continue;
+        }
+        if let Err(reason) = can_add(id) {
+            result.rejected.push(format!("Add {}: {}", id, reason));
+            continue;
+        }
+        let position = placed.position.unwrap_or(track_ids.len()).min(track_ids.len());
+        track_ids.insert(position, id);
+        result.added.push(id);
+    }
+
+    result.track_ids = track_ids;
+    result
+}
+
+/// Refine an existing playlist with an instruction ("make the middle third harder",
+/// "remove vocal-heavy tracks"). The model proposes adds, removes and moves against the
+/// current track list; the valid ones are applied. The playlist is snapshotted first, so
+/// the refinement can be undone from its history.
+#[tauri::command]
+pub async fn ai_refine_playlist(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    playlist_id: i64,
+    instruction: String,
+) -> Result<RefinedPlaylist, String> {
+    if instruction.trim().is_empty() {
+        return Err("Instruction cannot be empty".to_string());
+    }
+    let api_key = get_api_key_from_db(&state)?
+        .ok_or_else(|| "No API key configured. Please set your Claude API key in Settings.".to_string())?;
+
+    let (current, playlist_tracks) = {
+        let db_guard = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        let db = db_guard.as_ref().ok_or_else(|| "Database not initialized".to_string())?;
+        ensure_editable(db, playlist_id)?;
+        let current = db.get_playlist_track_ids(playlist_id)
+            .map_err(|e| format!("Failed to get playlist tracks: {}", e))?;
+        let tracks: Vec<(Track, Option<TrackAnalysis>)> = db
+            .get_playlist_tracks(playlist_id)
+            .map_err(|e| format!("Failed to get playlist tracks: {}", e))?
+            .into_iter()
+            .map(|(track, ..)| {
+                let analysis = track.id.and_then(|id| db.get_track_analysis(id).ok().flatten());
+                (track, analysis)
+            })
+            .collect();
+        let play_stats = db.get_play_stats()
+            .map_err(|e| format!("Failed to get play stats: {}", e))?;
+        (current, TrackContextBuilder::build_track_list(&tracks, &play_stats)?)
+    };
+
+    let (track_context, context_coverage) = get_prompt_context(&state, &instruction)?;
+    let client = ClaudeClient::new(api_key);
+    let refinement = client
+        .refine_playlist(instruction, playlist_tracks, track_context, SYSTEM_PROMPT.to_string())
+        .await;
+    record_usage(&state, &client, "playlist_refinement");
+    let refinement = refinement?;
+
+    let db_guard = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    let db = db_guard.as_ref().ok_or_else(|| "Database not initialized".to_string())?;
+    // Checked again: the playlist may have changed while the model was thinking
+    let latest = db.get_playlist_track_ids(playlist_id)
+        .map_err(|e| format!("Failed to get playlist tracks: {}", e))?;
+    if latest != current {
+        return Err("The playlist changed while it was being refined. Please try again.".to_string());
+    }
+    let cooled_down = db.get_active_cooldowns()
+        .map_err(|e| format!("Failed to get cooldowns: {}", e))?;
+
+    let mut refined = apply_refinement(playlist_id, &current, &refinement, |id| {
+        if cooled_down.contains_key(&id) {
+            return Err("in cooldown".to_string());
+        }
+        db.get_track(id).map(|_| ()).map_err(|_| "not in the library".to_string())
+    });
+    refined.context_coverage = context_coverage;
+    for rejected in &refined.rejected {
+        eprintln!("[ai] Refining playlist {}: dropped change: {}", playlist_id, rejected);
+    }
+
+    if refined.track_ids != current {
+        let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        db.set_playlist_tracks(playlist_id, &refined.track_ids, "ai_refine")
+            .map_err(|e| format!("Failed to update playlist: {}", e))?;
+        tx.commit().map_err(|e| format!("Failed to commit: {}", e))?;
+        notify_playlists_changed(&app);
+    }
+
+    Ok(refined)
+}
+
 /// Send a chat message to AI (simple, non-streaming)
 #[tauri::command]
 pub async fn ai_chat(
@@ -181,9 +609,9 @@ pub async fn ai_chat(
         || msg_lower.contains("library")
         || msg_lower.contains("music");
 
-    // Use cached context (instant, no DB query)
+    // Use cached context (instant), plus message matches on big libraries
     let track_context = if needs_library_context {
-        Some(get_or_build_context(&state)?)
+        Some(get_prompt_context(&state, &message)?.0)
     } else {
         None
     };
@@ -209,9 +637,78 @@ pub async fn ai_chat(
     });
 
     let client = ClaudeClient::new(api_key);
-    let response = client.chat(messages, Some(SYSTEM_PROMPT.to_string())).await?;
+    let response = client.chat(messages, Some(SYSTEM_PROMPT.to_string())).await;
+    record_usage(&state, &client, "chat");
+
+    response
+}
+
+/// Token usage by one feature in a month
+#[derive(Debug, Serialize)]
+pub struct AiUsagePurposeDTO {
+    /// "playlist_generation", "playlist_refinement", "chat" or "session_naming"
+    pub purpose: String,
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub estimated_cost_usd: f64,
+}
 
-    Ok(response)
+/// Token usage in a month
+#[derive(Debug, Serialize)]
+pub struct AiUsageMonthDTO {
+    /// "YYYY-MM" (UTC, as Anthropic bills)
+    pub month: String,
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    /// At the model's list prices; the Anthropic console has the exact figure
+    pub estimated_cost_usd: f64,
+    pub by_purpose: Vec<AiUsagePurposeDTO>,
+}
+
+/// Group usage rows (newest month first) by month
+fn usage_by_month(rows: Vec<AiUsage>) -> Vec<AiUsageMonthDTO> {
+    let cost = |input: i64, output: i64| {
+        (estimated_cost_usd(input.max(0) as u64, output.max(0) as u64) * 10_000.0).round() / 10_000.0
+    };
+    let mut months: Vec<AiUsageMonthDTO> = Vec::new();
+    for row in rows {
+        if months.last().is_none_or(|m| m.month != row.month) {
+            months.push(AiUsageMonthDTO {
+                month: row.month.clone(),
+                requests: 0,
+                input_tokens: 0,
+                output_tokens: 0,
+                estimated_cost_usd: 0.0,
+                by_purpose: Vec::new(),
+            });
+        }
+        let month = months.last_mut().unwrap();
+        month.requests += row.requests;
+        month.input_tokens += row.input_tokens;
+        month.output_tokens += row.output_tokens;
+        month.estimated_cost_usd = cost(month.input_tokens, month.output_tokens);
+        month.by_purpose.push(AiUsagePurposeDTO {
+            estimated_cost_usd: cost(row.input_tokens, row.output_tokens),
+            purpose: row.purpose,
+            requests: row.requests,
+            input_tokens: row.input_tokens,
+            output_tokens: row.output_tokens,
+        });
+    }
+    months
+}
+
+/// Claude tokens used per month (the last `months`, default 12, newest first), with what
+/// each feature used
+#[tauri::command]
+pub fn get_ai_usage(state: State<'_, AppState>, months: Option<i64>) -> Result<Vec<AiUsageMonthDTO>, String> {
+    let db_guard = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    let db = db_guard.as_ref().ok_or_else(|| "Database not initialized".to_string())?;
+    let rows = db.get_ai_usage(months.unwrap_or(12).max(1))
+        .map_err(|e| format!("Failed to get AI usage: {}", e))?;
+    Ok(usage_by_month(rows))
 }
 
 #[cfg(test)]
@@ -229,4 +726,50 @@ mod tests {
         let json = serde_json::to_string(&msg).unwrap();
         assert!(json.contains("Test message"));
     }
+
+    #[test]
+    fn test_usage_by_month() {
+        let row = |month: &str, purpose: &str, input_tokens: i64| AiUsage {
+            month: month.to_string(),
+            purpose: purpose.to_string(),
+            requests: 2,
+            input_tokens,
+            output_tokens: 100_000,
+        };
+        let months = usage_by_month(vec![
+            row("2026-10", "chat", 500_000),
+            row("2026-10", "playlist_generation", 1_000_000),
+            row("2026-09", "chat", 0),
+        ]);
+        assert_eq!(months.len(), 2);
+        assert_eq!((months[0].requests, months[0].input_tokens), (4, 1_500_000));
+        assert_eq!(months[0].by_purpose.len(), 2);
+        assert_eq!(months[0].by_purpose[1].estimated_cost_usd, 4.5);
+        assert_eq!(months[0].estimated_cost_usd, 7.5);
+        assert_eq!(months[1].estimated_cost_usd, 1.5);
+    }
+
+    #[test]
+    fn test_apply_refinement() {
+        let refinement: PlaylistRefinement = serde_json::from_str(
+            r#"{
+                "remove": [2, 9],
+                "move": [{"track_id": 4, "position": 0}, {"track_id": 4, "position": 2}],
+                "add": [{"track_id": 5, "position": 1}, {"track_id": 6}, {"track_id": 1}, {"track_id": 7}],
+                "reasoning": "Harder in the middle"
+            }"#,
+        )
+        .unwrap();
+        let refined = apply_refinement(1, &[1, 2, 3, 4], &refinement, |id| {
+            if id == 7 { Err("not in the library".to_string()) } else { Ok(()) }
+        });
+
+        assert_eq!(refined.track_ids, vec![4, 5, 1, 3, 6]);
+        assert_eq!(refined.removed, vec![2]);
+        assert_eq!(refined.moved, vec![4]);
+        assert_eq!(refined.added, vec![5, 6]);
+        assert_eq!(refined.rejected.len(), 4);
+        assert!(refined.rejected.iter().any(|r| r.starts_with("Add 7")));
+        assert_eq!(refined.reasoning, "Harder in the middle");
+    }
 }
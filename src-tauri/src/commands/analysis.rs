@@ -7,13 +7,198 @@
 // 3. Stores results back in the track_analysis table
 // 4. Returns the result to the frontend
 
-use crate::audio::bpm;
+use crate::audio::bpm::{self, TempoCurve, TempoPoint};
+use crate::audio::decoder::decode_to_mono;
 use crate::audio::key;
+use crate::audio::waveform::Silence;
+use crate::commands::analysis_errors;
+use crate::commands::genre::genre_merge_key;
 use crate::commands::library::AppState;
+use crate::db::Database;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use tauri::State;
 
+/// Setting key: expected BPM range per genre (JSON array of GenreBpmRange)
+pub const GENRE_BPM_RANGES_SETTING: &str = "genre_bpm_ranges";
+/// Setting key: how analyzed BPMs are rounded (JSON BpmRounding)
+pub const BPM_ROUNDING_SETTING: &str = "bpm_rounding";
+/// Setting key: "true" to also measure a tempo curve whenever a track's BPM is analyzed
+pub const TEMPO_CURVE_SETTING: &str = "analyze_tempo_curve";
+
+/// The BPM range a genre is expected to fall in (e.g. Drum & Bass 160-180).
+/// Detected BPMs outside it are moved in by octaves, fixing half/double tempo errors.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenreBpmRange {
+    pub genre: String,
+    pub min_bpm: f64,
+    pub max_bpm: f64,
+}
+
+fn load_genre_bpm_ranges(db: &Database) -> Vec<GenreBpmRange> {
+    db.get_setting(GENRE_BPM_RANGES_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// `bpm` moved into the configured range for `genre` (matched like genre merges:
+/// case, spacing and punctuation ignored); unchanged if the genre has no range
+fn normalize_bpm_for_genre(ranges: &[GenreBpmRange], genre: Option<&str>, bpm: f64) -> f64 {
+    let Some(key) = genre.map(genre_merge_key).filter(|k| !k.is_empty()) else {
+        return bpm;
+    };
+    ranges
+        .iter()
+        .find(|r| genre_merge_key(&r.genre) == key)
+        .map(|r| bpm::normalize_octave(bpm, r.min_bpm, r.max_bpm))
+        .unwrap_or(bpm)
+}
+
+/// Steps analyzed BPMs can be rounded to (0 = as detected)
+const BPM_ROUNDING_STEPS: [f64; 4] = [0.0, 0.01, 0.1, 1.0];
+
+/// How analyzed BPMs are stored: a detected 125.98 can be kept, rounded (126.0 for a
+/// whole-number step) or pulled onto a common tempo (125 or 128) like other DJ software
+/// shows it. Only confident results are touched; BPMs read from tags never are.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BpmRounding {
+    /// 0.01, 0.1 or 1; 0 keeps the detected value
+    pub step: f64,
+    /// Tempos a BPM within snap_tolerance is pulled onto (e.g. 125, 128)
+    pub snap_to: Vec<f64>,
+    pub snap_tolerance: f64,
+    /// Results less confident than this (0-1) are stored as detected
+    pub min_confidence: f64,
+}
+
+impl Default for BpmRounding {
+    fn default() -> Self {
+        BpmRounding {
+            step: 0.0,
+            snap_to: Vec::new(),
+            snap_tolerance: 0.1,
+            min_confidence: 0.5,
+        }
+    }
+}
+
+impl BpmRounding {
+    pub fn apply(&self, bpm: f64, confidence: f64) -> f64 {
+        if confidence < self.min_confidence {
+            return bpm;
+        }
+        let snapped = self.snap_to
+            .iter()
+            .copied()
+            .filter(|target| (bpm - target).abs() <= self.snap_tolerance)
+            .min_by(|a, b| (bpm - a).abs().total_cmp(&(bpm - b).abs()));
+        if let Some(target) = snapped {
+            return target;
+        }
+        if self.step > 0.0 {
+            // Scale up rather than divide by the step: 125.98 stays 125.98, not 125.98000000000002
+            let scale = (1.0 / self.step).round();
+            return (bpm * scale).round() / scale;
+        }
+        bpm
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !BPM_ROUNDING_STEPS.contains(&self.step) {
+            return Err(format!("Invalid BPM rounding step {}. Must be 0, 0.01, 0.1 or 1", self.step));
+        }
+        if !(0.0..=1.0).contains(&self.snap_tolerance) {
+            return Err(format!("Snap tolerance must be 0-1 BPM, got {}", self.snap_tolerance));
+        }
+        if !(0.0..=1.0).contains(&self.min_confidence) {
+            return Err(format!("Minimum confidence must be 0-1, got {}", self.min_confidence));
+        }
+        if let Some(bpm) = self.snap_to.iter().find(|bpm| !(**bpm > 0.0 && bpm.is_finite())) {
+            return Err(format!("Invalid snap tempo: {}", bpm));
+        }
+        Ok(())
+    }
+}
+
+fn load_bpm_rounding(db: &Database) -> BpmRounding {
+    db.get_setting(BPM_ROUNDING_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// A freshly detected BPM, corrected for the track's genre range (if any) and rounded
+/// as configured (BpmRounding)
+pub(crate) fn normalize_detected_bpm(db: &Database, track_id: i64, bpm: f64, confidence: f64) -> f64 {
+    let genre = db.get_track(track_id).ok().and_then(|t| t.genre);
+    let bpm = normalize_bpm_for_genre(&load_genre_bpm_ranges(db), genre.as_deref(), bpm);
+    load_bpm_rounding(db).apply(bpm, confidence)
+}
+
+/// Whether BPM analysis also measures the tempo curve (off by default: it roughly
+/// doubles the cost of BPM detection)
+pub fn tempo_curve_enabled(db: &Database) -> bool {
+    db.get_setting(TEMPO_CURVE_SETTING)
+        .ok()
+        .flatten()
+        .map(|v| v == "true")
+        .unwrap_or(false)
+}
+
+/// Detect a file's BPM, plus its tempo curve if `with_curve` (decoding only once)
+pub(crate) fn detect_bpm_and_curve(
+    path: &Path,
+    with_curve: bool,
+) -> Result<(bpm::BpmResult, Option<TempoCurve>), String> {
+    let audio = decode_to_mono(path)?;
+    let result = bpm::detect_bpm_from_samples(&audio)?;
+    let curve = if with_curve {
+        Some(bpm::detect_tempo_curve_from_samples(&audio)?)
+    } else {
+        None
+    };
+    Ok((result, curve))
+}
+
+pub(crate) fn save_tempo_curve(db: &Database, track_id: i64, curve: &TempoCurve) -> rusqlite::Result<()> {
+    let json = serde_json::to_string(&curve.points)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    db.save_tempo_curve(track_id, &json, curve.variable)
+}
+
+/// DTO for a track's tempo curve
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TempoCurveDTO {
+    pub track_id: i64,
+    /// Window length the points were measured over
+    pub window_secs: u32,
+    pub points: Vec<TempoPoint>,
+    pub min_bpm: f64,
+    pub max_bpm: f64,
+    /// BPM between the 10th and 90th percentile window
+    pub spread: f64,
+    /// Tempo drifts enough to need manual beatmatching
+    pub variable: bool,
+}
+
+impl TempoCurveDTO {
+    fn new(track_id: i64, curve: TempoCurve) -> Self {
+        TempoCurveDTO {
+            track_id,
+            window_secs: bpm::TEMPO_WINDOW_SECS,
+            points: curve.points,
+            min_bpm: curve.min_bpm,
+            max_bpm: curve.max_bpm,
+            spread: curve.spread,
+            variable: curve.variable,
+        }
+    }
+}
+
 /// DTO for BPM analysis result sent to frontend
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BpmResultDTO {
@@ -47,6 +232,10 @@ pub struct TrackAnalysisDTO {
     pub dynamic_range: Option<f64>,
     pub spectral_centroid: Option<f64>,
     pub analyzed_at: Option<String>,
+    pub silence_lead_ms: Option<i64>,
+    pub silence_tail_ms: Option<i64>,
+    /// Null until a tempo curve has been analyzed
+    pub tempo_variable: Option<bool>,
 }
 
 /// Analyze a single track's BPM.
@@ -59,12 +248,12 @@ pub struct TrackAnalysisDTO {
 #[tauri::command]
 pub fn analyze_bpm(state: State<AppState>, track_id: i64) -> Result<BpmResultDTO, String> {
     // Get the track's file path from the database
-    let file_path = {
+    let (file_path, with_curve) = {
         let db_lock = state.db.lock().unwrap();
         let db = db_lock.as_ref().ok_or("Database not initialized")?;
         let track = db.get_track(track_id)
             .map_err(|e| format!("Failed to get track {}: {}", track_id, e))?;
-        track.file_path
+        (track.file_path, tempo_curve_enabled(db))
     };
 
     // Run BPM detection on the audio file
@@ -75,7 +264,7 @@ pub fn analyze_bpm(state: State<AppState>, track_id: i64) -> Result<BpmResultDTO
 
     eprintln!("[analyze_bpm] Analyzing track {} at: {}", track_id, file_path);
 
-    let bpm_result = bpm::detect_bpm(path)
+    let (bpm_result, curve) = detect_bpm_and_curve(path, with_curve)
         .map_err(|e| format!("BPM detection failed for track {}: {}", track_id, e))?;
 
     eprintln!(
@@ -84,20 +273,81 @@ pub fn analyze_bpm(state: State<AppState>, track_id: i64) -> Result<BpmResultDTO
     );
 
     // Save the result to the database
-    {
+    let bpm = {
         let db_lock = state.db.lock().unwrap();
         let db = db_lock.as_ref().ok_or("Database not initialized")?;
-        db.save_bpm_analysis(track_id, bpm_result.bpm, bpm_result.confidence)
+        let bpm = normalize_detected_bpm(db, track_id, bpm_result.bpm, bpm_result.confidence);
+        db.save_bpm_analysis(track_id, bpm, bpm_result.confidence)
             .map_err(|e| format!("Failed to save BPM analysis: {}", e))?;
-    }
+        if let Some(curve) = &curve {
+            save_tempo_curve(db, track_id, curve)
+                .map_err(|e| format!("Failed to save tempo curve: {}", e))?;
+        }
+        bpm
+    };
 
     Ok(BpmResultDTO {
         track_id,
-        bpm: bpm_result.bpm,
+        bpm,
         confidence: bpm_result.confidence,
     })
 }
 
+/// Measure a track's tempo over consecutive windows and store the curve.
+/// Flags the track as variable tempo if the BPM drifts (live recordings, older disco).
+#[tauri::command]
+pub fn analyze_tempo_curve(state: State<AppState>, track_id: i64) -> Result<TempoCurveDTO, String> {
+    let file_path = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        db.get_track(track_id)
+            .map_err(|e| format!("Failed to get track {}: {}", track_id, e))?
+            .file_path
+    };
+
+    let path = Path::new(&file_path);
+    if !path.exists() {
+        return Err(format!("Audio file not found: {}", file_path));
+    }
+
+    // Heavy DSP work — no lock held
+    let audio = decode_to_mono(path)?;
+    let curve = bpm::detect_tempo_curve_from_samples(&audio)
+        .map_err(|e| format!("Tempo curve analysis failed for track {}: {}", track_id, e))?;
+
+    eprintln!(
+        "[analyze_tempo_curve] Track {}: {} windows, {:.1}-{:.1} BPM{}",
+        track_id,
+        curve.points.len(),
+        curve.min_bpm,
+        curve.max_bpm,
+        if curve.variable { " (variable tempo)" } else { "" }
+    );
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    save_tempo_curve(db, track_id, &curve)
+        .map_err(|e| format!("Failed to save tempo curve: {}", e))?;
+
+    Ok(TempoCurveDTO::new(track_id, curve))
+}
+
+/// Get a track's stored tempo curve. Returns null if it hasn't been analyzed.
+#[tauri::command]
+pub fn get_tempo_curve(state: State<AppState>, track_id: i64) -> Result<Option<TempoCurveDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let Some(json) = db.get_tempo_curve(track_id)
+        .map_err(|e| format!("Failed to get tempo curve for track {}: {}", track_id, e))?
+    else {
+        return Ok(None);
+    };
+    let points: Vec<TempoPoint> = serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse tempo curve JSON: {}", e))?;
+    Ok(Some(TempoCurveDTO::new(track_id, TempoCurve::from_points(points))))
+}
+
 /// Get the analysis data for a track (returns whatever analysis has been done so far)
 #[tauri::command]
 pub fn get_track_analysis(state: State<AppState>, track_id: i64) -> Result<Option<TrackAnalysisDTO>, String> {
@@ -117,6 +367,9 @@ pub fn get_track_analysis(state: State<AppState>, track_id: i64) -> Result<Optio
         dynamic_range: a.dynamic_range,
         spectral_centroid: a.spectral_centroid,
         analyzed_at: a.analyzed_at,
+        silence_lead_ms: a.silence_lead_ms,
+        silence_tail_ms: a.silence_tail_ms,
+        tempo_variable: a.tempo_variable,
     }))
 }
 
@@ -175,6 +428,7 @@ pub fn analyze_key(state: State<AppState>, track_id: i64) -> Result<KeyResultDTO
 /// Analyze key for all tracks that haven't had key analysis yet.
 /// Returns the list of results.
 /// Releases the DB mutex during heavy DSP work so other commands aren't blocked.
+/// Results are committed in transactions of ANALYSIS_COMMIT_EVERY tracks.
 #[tauri::command]
 pub fn analyze_all_keys(state: State<AppState>) -> Result<Vec<KeyResultDTO>, String> {
     // Get all tracks that need key analysis (brief lock)
@@ -183,25 +437,30 @@ pub fn analyze_all_keys(state: State<AppState>) -> Result<Vec<KeyResultDTO>, Str
         let db = db_lock.as_ref().ok_or("Database not initialized")?;
         let all_tracks = db.get_all_tracks()
             .map_err(|e| format!("Failed to get tracks: {}", e))?;
+        let skipped = analysis_errors::skipped_tracks(db);
 
         all_tracks
             .into_iter()
             .filter_map(|t| {
-                let id = t.id?;
-                let has_key = db.has_key_analysis(id).unwrap_or(false);
-                if has_key { None } else { Some((id, t.file_path)) }
+                let id = t.id.filter(|id| !skipped.contains(id))?;
+                // Tag keys count as analyzed unless analyzed values are preferred
+                let (_, needs_key) = db.needs_analysis(id).unwrap_or((true, true));
+                if needs_key { Some((id, t.file_path)) } else { None }
             })
             .collect()
     }; // lock released
 
     eprintln!("[analyze_all_keys] {} tracks need key analysis", tracks_to_analyze.len());
 
-    let mut results = Vec::new();
+    let mut results: Vec<KeyResultDTO> = Vec::new();
+    let mut saved = 0;
+    start_marker(&state, ANALYZE_KEYS_JOB, tracks_to_analyze.len())?;
 
     for (track_id, file_path) in &tracks_to_analyze {
         let path = Path::new(file_path);
         if !path.exists() {
             eprintln!("[analyze_all_keys] Skipping missing file: {}", file_path);
+            record_failure(&state, *track_id, &format!("Audio file not found: {}", file_path));
             continue;
         }
 
@@ -213,14 +472,6 @@ pub fn analyze_all_keys(state: State<AppState>) -> Result<Vec<KeyResultDTO>, Str
                     track_id, key_result.camelot, key_result.musical_key, key_result.confidence
                 );
 
-                // Brief lock to save result
-                {
-                    let db_lock = state.db.lock().unwrap();
-                    let db = db_lock.as_ref().ok_or("Database not initialized")?;
-                    db.save_key_analysis(*track_id, &key_result.camelot, key_result.confidence)
-                        .map_err(|e| format!("Failed to save key analysis: {}", e))?;
-                }
-
                 results.push(KeyResultDTO {
                     track_id: *track_id,
                     camelot: key_result.camelot,
@@ -228,13 +479,22 @@ pub fn analyze_all_keys(state: State<AppState>) -> Result<Vec<KeyResultDTO>, Str
                     musical_key: key_result.musical_key,
                     confidence: key_result.confidence,
                 });
+
+                // Periodic commit: brief lock, one transaction per batch of results
+                if results.len() - saved >= ANALYSIS_COMMIT_EVERY {
+                    saved = save_key_results(&state, &results[saved..], results.len())?;
+                }
             }
             Err(e) => {
                 eprintln!("[analyze_all_keys] Error analyzing track {}: {}", track_id, e);
+                record_failure(&state, *track_id, &format!("Key detection failed: {}", e));
             }
         }
     }
 
+    save_key_results(&state, &results[saved..], results.len())?;
+    finish_marker(&state, ANALYZE_KEYS_JOB)?;
+
     eprintln!("[analyze_all_keys] Completed: {} tracks analyzed", results.len());
 
     Ok(results)
@@ -243,6 +503,7 @@ pub fn analyze_all_keys(state: State<AppState>) -> Result<Vec<KeyResultDTO>, Str
 /// Analyze BPM for all tracks that haven't been analyzed yet.
 /// Returns the number of tracks analyzed.
 /// Releases the DB mutex during heavy DSP work so other commands aren't blocked.
+/// Results are committed in transactions of ANALYSIS_COMMIT_EVERY tracks.
 #[tauri::command]
 pub fn analyze_all_bpm(state: State<AppState>) -> Result<Vec<BpmResultDTO>, String> {
     // Get all tracks that need BPM analysis (brief lock)
@@ -251,25 +512,30 @@ pub fn analyze_all_bpm(state: State<AppState>) -> Result<Vec<BpmResultDTO>, Stri
         let db = db_lock.as_ref().ok_or("Database not initialized")?;
         let all_tracks = db.get_all_tracks()
             .map_err(|e| format!("Failed to get tracks: {}", e))?;
+        let skipped = analysis_errors::skipped_tracks(db);
 
         all_tracks
             .into_iter()
             .filter_map(|t| {
-                let id = t.id?;
-                let has_bpm = db.has_bpm_analysis(id).unwrap_or(false);
-                if has_bpm { None } else { Some((id, t.file_path)) }
+                let id = t.id.filter(|id| !skipped.contains(id))?;
+                // Tag BPMs count as analyzed unless analyzed values are preferred
+                let (needs_bpm, _) = db.needs_analysis(id).unwrap_or((true, true));
+                if needs_bpm { Some((id, t.file_path)) } else { None }
             })
             .collect()
     }; // lock released
 
     eprintln!("[analyze_all_bpm] {} tracks need BPM analysis", tracks_to_analyze.len());
 
-    let mut results = Vec::new();
+    let mut results: Vec<BpmResultDTO> = Vec::new();
+    let mut saved = 0;
+    start_marker(&state, ANALYZE_BPM_JOB, tracks_to_analyze.len())?;
 
     for (track_id, file_path) in &tracks_to_analyze {
         let path = Path::new(file_path);
         if !path.exists() {
             eprintln!("[analyze_all_bpm] Skipping missing file: {}", file_path);
+            record_failure(&state, *track_id, &format!("Audio file not found: {}", file_path));
             continue;
         }
 
@@ -281,31 +547,95 @@ pub fn analyze_all_bpm(state: State<AppState>) -> Result<Vec<BpmResultDTO>, Stri
                     track_id, bpm_result.bpm, bpm_result.confidence
                 );
 
-                // Brief lock to save result
-                {
-                    let db_lock = state.db.lock().unwrap();
-                    let db = db_lock.as_ref().ok_or("Database not initialized")?;
-                    db.save_bpm_analysis(*track_id, bpm_result.bpm, bpm_result.confidence)
-                        .map_err(|e| format!("Failed to save BPM analysis: {}", e))?;
-                }
-
                 results.push(BpmResultDTO {
                     track_id: *track_id,
                     bpm: bpm_result.bpm,
                     confidence: bpm_result.confidence,
                 });
+
+                // Periodic commit: brief lock, one transaction per batch of results
+                if results.len() - saved >= ANALYSIS_COMMIT_EVERY {
+                    let total = results.len();
+                    saved = save_bpm_results(&state, &mut results[saved..], total)?;
+                }
             }
             Err(e) => {
                 eprintln!("[analyze_all_bpm] Error analyzing track {}: {}", track_id, e);
+                record_failure(&state, *track_id, &format!("BPM detection failed: {}", e));
             }
         }
     }
 
+    let total = results.len();
+    save_bpm_results(&state, &mut results[saved..], total)?;
+    finish_marker(&state, ANALYZE_BPM_JOB)?;
+
     eprintln!("[analyze_all_bpm] Completed: {} tracks analyzed", results.len());
 
     Ok(results)
 }
 
+/// Batch analysis results written per transaction. Small, because each result
+/// costs seconds of DSP — a crash loses at most this many analyses.
+const ANALYSIS_COMMIT_EVERY: usize = 10;
+/// Recovery marker job names (see db::BatchMarker)
+const ANALYZE_BPM_JOB: &str = "analyze_bpm";
+const ANALYZE_KEYS_JOB: &str = "analyze_keys";
+
+/// Note a batch failure in analysis_errors (brief lock)
+fn record_failure(state: &State<AppState>, track_id: i64, message: &str) {
+    let db_lock = state.db.lock().unwrap();
+    if let Some(db) = db_lock.as_ref() {
+        analysis_errors::record_failure(db, track_id, message);
+    }
+}
+
+fn start_marker(state: &State<AppState>, job: &str, total: usize) -> Result<(), String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.start_batch_marker(job, None, Some(total as i64))
+        .map_err(|e| format!("Failed to write batch marker: {}", e))
+}
+
+fn finish_marker(state: &State<AppState>, job: &str) -> Result<(), String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.finish_batch_marker(job)
+        .map_err(|e| format!("Failed to clear batch marker: {}", e))
+}
+
+/// Save a batch of BPM results in one transaction (correcting each for its genre's
+/// BPM range). Returns the new saved count.
+fn save_bpm_results(state: &State<AppState>, batch: &mut [BpmResultDTO], total_saved: usize) -> Result<usize, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+    for r in batch {
+        r.bpm = normalize_detected_bpm(db, r.track_id, r.bpm, r.confidence);
+        db.save_bpm_analysis(r.track_id, r.bpm, r.confidence)
+            .map_err(|e| format!("Failed to save BPM analysis: {}", e))?;
+    }
+    db.update_batch_marker(ANALYZE_BPM_JOB, total_saved as i64)
+        .map_err(|e| format!("Failed to update batch marker: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit BPM analysis: {}", e))?;
+    Ok(total_saved)
+}
+
+/// Save a batch of key results in one transaction. Returns the new saved count.
+fn save_key_results(state: &State<AppState>, batch: &[KeyResultDTO], total_saved: usize) -> Result<usize, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+    for r in batch {
+        db.save_key_analysis(r.track_id, &r.camelot, r.confidence)
+            .map_err(|e| format!("Failed to save key analysis: {}", e))?;
+    }
+    db.update_batch_marker(ANALYZE_KEYS_JOB, total_saved as i64)
+        .map_err(|e| format!("Failed to update batch marker: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit key analysis: {}", e))?;
+    Ok(total_saved)
+}
+
 /// DTO for waveform data sent to frontend
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WaveformDTO {
@@ -318,8 +648,6 @@ pub struct WaveformDTO {
 /// This is idempotent - if waveform already exists, it will be regenerated.
 #[tauri::command]
 pub fn analyze_waveform(state: State<AppState>, track_id: i64) -> Result<(), String> {
-    use crate::audio::waveform::generate_waveform;
-    
     // Get the track's file path from the database
     let file_path = {
         let db_lock = state.db.lock().unwrap();
@@ -336,34 +664,181 @@ pub fn analyze_waveform(state: State<AppState>, track_id: i64) -> Result<(), Str
 
     eprintln!("[analyze_waveform] Analyzing track {} at: {}", track_id, file_path);
 
-    // Generate overview waveform (2500 points - full track view)
-    let overview = generate_waveform(path, 2500)
-        .map_err(|e| format!("Failed to generate overview waveform: {}", e))?;
-    let overview_blob = overview.to_blob();
-
-    // Generate detail waveform (10000 points - for zoom)
-    let detail = generate_waveform(path, 10000)
-        .map_err(|e| format!("Failed to generate detail waveform: {}", e))?;
-    let detail_blob = detail.to_blob();
+    let waveforms = generate_waveform_blobs(path)?;
 
     eprintln!(
-        "[analyze_waveform] Track {}: overview={} bytes, detail={} bytes",
+        "[analyze_waveform] Track {}: overview={} bytes, detail={} bytes, silence lead={}ms tail={}ms",
         track_id,
-        overview_blob.len(),
-        detail_blob.len()
+        waveforms.overview.len(),
+        waveforms.detail.len(),
+        waveforms.silence.lead_ms,
+        waveforms.silence.tail_ms
     );
 
     // Save to database
     {
         let db_lock = state.db.lock().unwrap();
         let db = db_lock.as_ref().ok_or("Database not initialized")?;
-        db.save_waveform(track_id, &overview_blob, &detail_blob)
+        db.with_busy_retry(|db| waveforms.save(db, track_id))
             .map_err(|e| format!("Failed to save waveform: {}", e))?;
     }
 
     Ok(())
 }
 
+/// Get the expected BPM range per genre
+#[tauri::command]
+pub fn get_genre_bpm_ranges(state: State<AppState>) -> Result<Vec<GenreBpmRange>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    Ok(load_genre_bpm_ranges(db))
+}
+
+/// Replace the expected BPM ranges per genre. New BPM analysis is corrected into them;
+/// run normalize_bpm_octaves to correct BPMs already stored.
+#[tauri::command]
+pub fn set_genre_bpm_ranges(state: State<AppState>, ranges: Vec<GenreBpmRange>) -> Result<(), String> {
+    for r in &ranges {
+        if r.genre.trim().is_empty() {
+            return Err("Genre name is required".to_string());
+        }
+        if !(r.min_bpm > 0.0 && r.min_bpm < r.max_bpm) {
+            return Err(format!(
+                "Invalid BPM range for {}: {}-{}",
+                r.genre, r.min_bpm, r.max_bpm
+            ));
+        }
+    }
+
+    let json = serde_json::to_string(&ranges)
+        .map_err(|e| format!("Failed to serialize BPM ranges: {}", e))?;
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.set_setting(GENRE_BPM_RANGES_SETTING, &json)
+        .map_err(|e| format!("Failed to save BPM ranges: {}", e))
+}
+
+/// A stored BPM moved into its genre's range
+#[derive(Debug, Serialize)]
+pub struct BpmCorrectionDTO {
+    pub track_id: i64,
+    pub old_bpm: f64,
+    pub new_bpm: f64,
+}
+
+/// Move every stored BPM (analyzed or from tags) into its genre's configured range
+/// by octaves. Tracks without a range, or with no octave inside it, are left alone.
+#[tauri::command]
+pub fn normalize_bpm_octaves(state: State<AppState>) -> Result<Vec<BpmCorrectionDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let ranges = load_genre_bpm_ranges(db);
+    if ranges.is_empty() {
+        return Ok(Vec::new());
+    }
+    let rows = db.get_bpms_with_genre()
+        .map_err(|e| format!("Failed to get BPMs: {}", e))?;
+
+    let corrections: Vec<BpmCorrectionDTO> = rows
+        .into_iter()
+        .filter_map(|(track_id, genre, old_bpm)| {
+            let new_bpm = normalize_bpm_for_genre(&ranges, genre.as_deref(), old_bpm);
+            (new_bpm != old_bpm).then_some(BpmCorrectionDTO { track_id, old_bpm, new_bpm })
+        })
+        .collect();
+
+    let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+    for c in &corrections {
+        db.set_bpm(c.track_id, c.new_bpm)
+            .map_err(|e| format!("Failed to update BPM: {}", e))?;
+    }
+    tx.commit().map_err(|e| format!("Failed to commit BPM corrections: {}", e))?;
+
+    eprintln!("[normalize_bpm_octaves] {} BPMs corrected", corrections.len());
+    Ok(corrections)
+}
+
+#[tauri::command]
+pub fn get_bpm_rounding(state: State<AppState>) -> Result<BpmRounding, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    Ok(load_bpm_rounding(db))
+}
+
+/// Set how new BPM analysis is rounded; run requantize_bpms to apply it to stored BPMs
+#[tauri::command]
+pub fn set_bpm_rounding(state: State<AppState>, rounding: BpmRounding) -> Result<(), String> {
+    rounding.validate()?;
+    let json = serde_json::to_string(&rounding)
+        .map_err(|e| format!("Failed to serialize BPM rounding: {}", e))?;
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.set_setting(BPM_ROUNDING_SETTING, &json)
+        .map_err(|e| format!("Failed to save BPM rounding: {}", e))
+}
+
+/// Round every analyzed BPM as currently configured (BPMs from tags are left alone).
+/// Rounding works on the stored values, so going back to a finer step needs re-analysis.
+#[tauri::command]
+pub fn requantize_bpms(state: State<AppState>) -> Result<Vec<BpmCorrectionDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let rounding = load_bpm_rounding(db);
+    let rows = db.get_analyzed_bpms()
+        .map_err(|e| format!("Failed to get BPMs: {}", e))?;
+
+    let corrections: Vec<BpmCorrectionDTO> = rows
+        .into_iter()
+        .filter_map(|(track_id, old_bpm, confidence)| {
+            let new_bpm = rounding.apply(old_bpm, confidence);
+            (new_bpm != old_bpm).then_some(BpmCorrectionDTO { track_id, old_bpm, new_bpm })
+        })
+        .collect();
+
+    let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+    for c in &corrections {
+        db.set_bpm(c.track_id, c.new_bpm)
+            .map_err(|e| format!("Failed to update BPM: {}", e))?;
+    }
+    tx.commit().map_err(|e| format!("Failed to commit BPM rounding: {}", e))?;
+
+    eprintln!("[requantize_bpms] {} BPMs rounded", corrections.len());
+    Ok(corrections)
+}
+
+/// Waveform blobs plus the silence detected from them
+pub(crate) struct WaveformAnalysis {
+    pub overview: Vec<u8>,
+    pub detail: Vec<u8>,
+    pub silence: Silence,
+}
+
+impl WaveformAnalysis {
+    /// Store the waveforms and silence (call inside the caller's transaction, if any)
+    pub fn save(&self, db: &crate::db::Database, track_id: i64) -> rusqlite::Result<()> {
+        db.save_waveform(track_id, &self.overview, &self.detail)?;
+        db.save_silence(track_id, self.silence.lead_ms as i64, self.silence.tail_ms as i64)
+    }
+}
+
+/// Generate the overview (2500 points - full track view) and detail (10000 points - for zoom)
+/// waveform blobs for a file, and detect leading/trailing silence from the detail peaks
+pub(crate) fn generate_waveform_blobs(path: &Path) -> Result<WaveformAnalysis, String> {
+    use crate::audio::waveform::generate_waveform;
+
+    let overview = generate_waveform(path, 2500)
+        .map_err(|e| format!("Failed to generate overview waveform: {}", e))?;
+    let detail = generate_waveform(path, 10000)
+        .map_err(|e| format!("Failed to generate detail waveform: {}", e))?;
+    Ok(WaveformAnalysis {
+        overview: overview.to_blob(),
+        detail: detail.to_blob(),
+        silence: detail.silence(),
+    })
+}
+
 /// Get waveform data for a track.
 /// Level: "overview" or "detail"
 /// Returns binary BLOB that frontend will deserialize.
@@ -375,3 +850,55 @@ pub fn get_waveform(state: State<AppState>, track_id: i64, level: String) -> Res
     db.get_waveform(track_id, &level)
         .map_err(|e| format!("Failed to get waveform: {}", e))
 }
+
+/// Number of analyses kept for deleted tracks (restored when the same file is re-imported)
+#[tauri::command]
+pub fn get_analysis_cache_count(state: State<AppState>) -> Result<i64, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.count_analysis_cache()
+        .map_err(|e| format!("Failed to count cached analyses: {}", e))
+}
+
+/// Drop the analyses kept for deleted tracks. Returns the number dropped.
+#[tauri::command]
+pub fn clear_analysis_cache(state: State<AppState>) -> Result<usize, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.clear_analysis_cache()
+        .map_err(|e| format!("Failed to clear analysis cache: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_bpm_for_genre() {
+        let ranges = vec![GenreBpmRange { genre: "Drum & Bass".to_string(), min_bpm: 160.0, max_bpm: 180.0 }];
+        assert_eq!(normalize_bpm_for_genre(&ranges, Some("drum and bass"), 87.0), 174.0);
+        assert_eq!(normalize_bpm_for_genre(&ranges, Some("Techno"), 87.0), 87.0);
+        assert_eq!(normalize_bpm_for_genre(&ranges, None, 87.0), 87.0);
+    }
+
+    #[test]
+    fn test_bpm_rounding() {
+        let as_detected = BpmRounding::default();
+        assert_eq!(as_detected.apply(125.984, 0.9), 125.984);
+
+        let hundredths = BpmRounding { step: 0.01, ..Default::default() };
+        assert_eq!(hundredths.apply(125.984, 0.9), 125.98);
+        let whole = BpmRounding { step: 1.0, ..Default::default() };
+        assert_eq!(whole.apply(125.6, 0.9), 126.0);
+        assert_eq!(whole.apply(125.6, 0.2), 125.6); // not confident enough
+
+        let snapping = BpmRounding { step: 0.1, snap_to: vec![125.0, 128.0], snap_tolerance: 0.1, ..Default::default() };
+        assert_eq!(snapping.apply(127.94, 0.9), 128.0);
+        assert_eq!(snapping.apply(127.5, 0.9), 127.5);
+        assert_eq!(snapping.apply(124.96, 0.9), 125.0);
+
+        assert!(BpmRounding { step: 0.5, ..Default::default() }.validate().is_err());
+        assert!(BpmRounding { snap_to: vec![-1.0], ..Default::default() }.validate().is_err());
+        assert!(snapping.validate().is_ok());
+    }
+}
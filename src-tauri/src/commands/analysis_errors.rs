@@ -0,0 +1,165 @@
+// Failed analyses: recorded per track with a class and a count of failures in a row,
+// so files that can't be analyzed (gone, undecodable) aren't retried on every batch
+// run and every start. After MAX_ANALYSIS_ATTEMPTS failures a file is skipped until
+// it changes or retry_failed_analyses is called. Errors that aren't the file's fault
+// (database busy, ...) aren't recorded.
+
+use crate::commands::analysis_queue::AnalysisQueueState;
+use crate::commands::library::AppState;
+use crate::db::{AnalysisFailure, Database};
+use serde::Serialize;
+use std::collections::HashSet;
+use tauri::{AppHandle, State};
+
+/// Failures in a row after which a file is skipped
+pub const MAX_ANALYSIS_ATTEMPTS: i64 = 3;
+
+/// Decoder errors (audio::decoder): the file can't be read as audio
+const DECODE_ERRORS: [&str; 6] = [
+    "Failed to open audio file",
+    "Failed to probe audio format",
+    "No audio tracks found",
+    "Failed to create decoder",
+    "Error reading packet",
+    "Decode error",
+];
+/// Errors from the database side of an analysis: not the file's fault
+const DATABASE_ERRORS: [&str; 6] = [
+    "Database not initialized",
+    "Failed to get track",
+    "Failed to check analysis",
+    "Failed to check waveform",
+    "Failed to save analysis",
+    "Failed to save waveform",
+];
+
+/// Why an analysis failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalysisErrorClass {
+    MissingFile,
+    Decode,
+    /// The audio decoded, a detector failed on it
+    Analysis,
+}
+
+impl AnalysisErrorClass {
+    /// Class of an analysis error message; None for errors that aren't the file's fault
+    pub fn of(message: &str) -> Option<Self> {
+        if message.starts_with("Audio file not found") {
+            Some(AnalysisErrorClass::MissingFile)
+        } else if DECODE_ERRORS.iter().any(|e| message.contains(e)) {
+            Some(AnalysisErrorClass::Decode)
+        } else if DATABASE_ERRORS.iter().any(|e| message.starts_with(e)) {
+            None
+        } else {
+            Some(AnalysisErrorClass::Analysis)
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnalysisErrorClass::MissingFile => "missing_file",
+            AnalysisErrorClass::Decode => "decode",
+            AnalysisErrorClass::Analysis => "analysis",
+        }
+    }
+}
+
+/// Record a failed analysis (see AnalysisErrorClass::of for what isn't recorded)
+pub fn record_failure(db: &Database, track_id: i64, message: &str) {
+    let Some(class) = AnalysisErrorClass::of(message) else {
+        return;
+    };
+    match db.record_analysis_failure(track_id, class.as_str(), message) {
+        Ok(attempts) if attempts >= MAX_ANALYSIS_ATTEMPTS => eprintln!(
+            "[analysis_errors] Track {} failed {} times ({}), skipping it from now on",
+            track_id, attempts, class.as_str()
+        ),
+        Ok(_) => {}
+        Err(e) => eprintln!("[analysis_errors] Failed to record failure of track {}: {}", track_id, e),
+    }
+}
+
+/// Tracks batch and background analysis leave alone
+pub fn skipped_tracks(db: &Database) -> HashSet<i64> {
+    db.get_analysis_skipped_tracks(MAX_ANALYSIS_ATTEMPTS).unwrap_or_default()
+}
+
+/// A track whose analysis failed
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalysisFailureDTO {
+    pub track_id: i64,
+    pub file_path: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// "missing_file", "decode" or "analysis"
+    pub error_class: String,
+    pub message: String,
+    pub attempts: i64,
+    pub last_failed_at: String,
+    /// Failed often enough to be left out of analysis until retried
+    pub skipped: bool,
+}
+
+impl From<AnalysisFailure> for AnalysisFailureDTO {
+    fn from(f: AnalysisFailure) -> Self {
+        AnalysisFailureDTO {
+            skipped: f.attempts >= MAX_ANALYSIS_ATTEMPTS,
+            track_id: f.track_id,
+            file_path: f.file_path,
+            title: f.title,
+            artist: f.artist,
+            error_class: f.error_class,
+            message: f.message,
+            attempts: f.attempts,
+            last_failed_at: f.last_failed_at,
+        }
+    }
+}
+
+/// Tracks whose last analysis failed, most recent first
+#[tauri::command]
+pub fn get_analysis_failures(state: State<AppState>) -> Result<Vec<AnalysisFailureDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let failures = db.get_analysis_failures()
+        .map_err(|e| format!("Failed to get analysis failures: {}", e))?;
+    Ok(failures.into_iter().map(AnalysisFailureDTO::from).collect())
+}
+
+/// Forget the failures of `track_ids` (every failed track if None) and queue them for
+/// analysis again. Returns how many were queued.
+#[tauri::command]
+pub fn retry_failed_analyses(
+    app: AppHandle,
+    state: State<AppState>,
+    queue: State<AnalysisQueueState>,
+    track_ids: Option<Vec<i64>>,
+) -> Result<usize, String> {
+    let failed = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        db.reset_analysis_failures(track_ids.as_deref())
+            .map_err(|e| format!("Failed to reset analysis failures: {}", e))?
+    };
+    let queued = queue.enqueue(&app, failed);
+    eprintln!("[analysis_errors] {} failed tracks queued for another try", queued);
+    Ok(queued)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_classes() {
+        assert_eq!(AnalysisErrorClass::of("Audio file not found: /a.mp3"), Some(AnalysisErrorClass::MissingFile));
+        assert_eq!(
+            AnalysisErrorClass::of("BPM detection failed: Failed to probe audio format: unsupported"),
+            Some(AnalysisErrorClass::Decode)
+        );
+        assert_eq!(AnalysisErrorClass::of("Key detection failed: too short"), Some(AnalysisErrorClass::Analysis));
+        assert_eq!(AnalysisErrorClass::of("Failed to save analysis: database is locked"), None);
+        assert_eq!(AnalysisErrorClass::of("Database not initialized"), None);
+    }
+}
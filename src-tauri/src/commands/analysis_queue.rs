@@ -0,0 +1,656 @@
+// Auto-analysis queue — analyzes newly imported tracks (BPM, key, waveform) in the
+// background so they're ready by the time they're browsed.
+// Enabled by the `auto_analyze` setting. scan_directory enqueues the tracks it imports;
+// the file watcher's "library-changed" event triggers those scans for new files.
+// One worker thread handles one track at a time and pauses between tracks, holding the
+// DB lock only to read and save, so it stays out of the way of interactive work.
+// The same worker serves request_waveforms: waveforms for the rows currently on screen,
+// which jump ahead of the auto-analysis backlog and are replaced on every request.
+// Companion clients asking for a missing waveform are queued separately (after the
+// visible rows, before the backlog) so desktop scrolling doesn't drop their requests.
+// analyze_playlist queues one playlist's tracks (with loudness too, if asked) ahead of
+// the backlog, for prepping a set without waiting on the whole library.
+// Failures are recorded in analysis_errors; tracks that keep failing are skipped.
+
+use crate::audio::{key, loudness};
+use crate::commands::analysis::{
+    detect_bpm_and_curve, generate_waveform_blobs, normalize_detected_bpm, save_tempo_curve,
+    tempo_curve_enabled,
+};
+use crate::commands::analysis_errors;
+use crate::commands::library::AppState;
+use crate::commands::sidecars::write_sidecar_after_analysis;
+use crate::db::Database;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Setting key for auto-analysis of new imports ("true"/"false", off by default)
+pub const AUTO_ANALYZE_SETTING: &str = "auto_analyze";
+/// Pause between queued tracks, leaving CPU for playback and the UI
+const QUEUE_PAUSE: Duration = Duration::from_millis(500);
+
+/// Something the analysis worker can compute for a track
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnalysisKind {
+    Bpm,
+    Key,
+    Waveform,
+    /// Integrated loudness (LUFS), for gain hints and loudness reports
+    Loudness,
+}
+
+/// What auto-analysis computes for new imports
+const AUTO_ANALYSIS_KINDS: &[AnalysisKind] = &[AnalysisKind::Bpm, AnalysisKind::Key, AnalysisKind::Waveform];
+/// What analyze_playlist computes when no kinds are given
+const ALL_ANALYSIS_KINDS: &[AnalysisKind] =
+    &[AnalysisKind::Bpm, AnalysisKind::Key, AnalysisKind::Waveform, AnalysisKind::Loudness];
+
+/// Managed state: the pending track IDs and the worker that drains them
+pub struct AnalysisQueueState {
+    inner: Arc<QueueInner>,
+}
+
+struct QueueInner {
+    pending: Mutex<Pending>,
+    wakeup: Condvar,
+    /// Track being analyzed right now
+    current: Mutex<Option<i64>>,
+    /// Companion waveform requests whose generation failed, with the error
+    remote_failures: Mutex<HashMap<i64, String>>,
+    worker_started: AtomicBool,
+}
+
+#[derive(Default)]
+struct Pending {
+    /// Auto-analysis: BPM, key and waveform
+    tracks: VecDeque<i64>,
+    /// Waveforms for visible rows; served first
+    waveforms: VecDeque<i64>,
+    /// Waveforms requested by companion clients
+    remote_waveforms: VecDeque<i64>,
+    /// Tracks of playlists being prepped, with what to compute; ahead of the backlog
+    playlist: VecDeque<(i64, Vec<AnalysisKind>)>,
+}
+
+impl Pending {
+    /// Tracks waiting for analysis (backlog and playlists; not waveform requests)
+    fn analysis_len(&self) -> usize {
+        self.tracks.len() + self.playlist.len()
+    }
+}
+
+enum Job {
+    Analyze(i64),
+    Playlist(i64, Vec<AnalysisKind>),
+    Waveform(i64),
+    RemoteWaveform(i64),
+}
+
+impl AnalysisQueueState {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(QueueInner {
+                pending: Mutex::new(Pending::default()),
+                wakeup: Condvar::new(),
+                current: Mutex::new(None),
+                remote_failures: Mutex::new(HashMap::new()),
+                worker_started: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Queue tracks (skipping ones already queued) and start the worker on first use.
+    /// Returns how many were added.
+    pub fn enqueue(&self, app: &AppHandle, track_ids: impl IntoIterator<Item = i64>) -> usize {
+        let added = {
+            let mut pending = self.inner.pending.lock().unwrap();
+            let before = pending.tracks.len();
+            for id in track_ids {
+                if !pending.tracks.contains(&id) {
+                    pending.tracks.push_back(id);
+                }
+            }
+            pending.tracks.len() - before
+        };
+        if added == 0 {
+            return 0;
+        }
+
+        self.wake_worker(app);
+        added
+    }
+
+    /// Queue a playlist's tracks ahead of the backlog. A track already queued this way
+    /// gets `kinds` added to what it was queued for. Returns how many were added.
+    pub fn enqueue_playlist(
+        &self,
+        app: &AppHandle,
+        track_ids: impl IntoIterator<Item = i64>,
+        kinds: &[AnalysisKind],
+    ) -> usize {
+        let mut added = 0;
+        {
+            let mut pending = self.inner.pending.lock().unwrap();
+            for id in track_ids {
+                match pending.playlist.iter_mut().find(|(queued, _)| *queued == id) {
+                    Some((_, queued_kinds)) => {
+                        for kind in kinds {
+                            if !queued_kinds.contains(kind) {
+                                queued_kinds.push(*kind);
+                            }
+                        }
+                    }
+                    None => {
+                        pending.playlist.push_back((id, kinds.to_vec()));
+                        added += 1;
+                    }
+                }
+            }
+        }
+        if added > 0 {
+            self.wake_worker(app);
+        }
+        added
+    }
+
+    /// Replace the visible-rows waveform requests; rows that scrolled away are dropped
+    fn request_waveforms(&self, app: &AppHandle, track_ids: Vec<i64>) {
+        let empty = track_ids.is_empty();
+        self.inner.pending.lock().unwrap().waveforms = track_ids.into();
+        if !empty {
+            self.wake_worker(app);
+        }
+    }
+
+    /// Queue a waveform a companion client asked for (no-op if already queued). If the
+    /// previous attempt for this track failed, returns that error instead and forgets it,
+    /// so asking again retries.
+    pub fn request_remote_waveform(&self, app: &AppHandle, track_id: i64) -> Result<(), String> {
+        if let Some(error) = self.inner.remote_failures.lock().unwrap().remove(&track_id) {
+            return Err(error);
+        }
+        {
+            let mut pending = self.inner.pending.lock().unwrap();
+            if pending.remote_waveforms.contains(&track_id) {
+                return Ok(());
+            }
+            pending.remote_waveforms.push_back(track_id);
+        }
+        self.wake_worker(app);
+        Ok(())
+    }
+
+    fn wake_worker(&self, app: &AppHandle) {
+        if !self.inner.worker_started.swap(true, Ordering::SeqCst) {
+            let inner = self.inner.clone();
+            let app = app.clone();
+            std::thread::spawn(move || run_worker(app, inner));
+        }
+        self.inner.wakeup.notify_one();
+    }
+}
+
+/// Whether new imports should be queued for analysis
+pub fn auto_analyze_enabled(db: &Database) -> bool {
+    db.get_setting(AUTO_ANALYZE_SETTING)
+        .ok()
+        .flatten()
+        .map(|v| v == "true")
+        .unwrap_or(false)
+}
+
+/// Payload of the "track-analyzed" event, emitted after each queued track
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackAnalyzedEvent {
+    pub track_id: i64,
+    pub error: Option<String>,
+    /// Tracks still waiting
+    pub pending: usize,
+}
+
+/// Payload of the "waveform-ready" event, emitted for each requested waveform
+#[derive(Debug, Clone, Serialize)]
+pub struct WaveformReadyEvent {
+    pub track_id: i64,
+    pub error: Option<String>,
+}
+
+fn run_worker(app: AppHandle, inner: Arc<QueueInner>) {
+    eprintln!("[analysis_queue] Worker started");
+    loop {
+        let job = {
+            let mut pending = inner.pending.lock().unwrap();
+            loop {
+                if let Some(id) = pending.waveforms.pop_front() {
+                    break Job::Waveform(id);
+                }
+                if let Some(id) = pending.remote_waveforms.pop_front() {
+                    break Job::RemoteWaveform(id);
+                }
+                if let Some((id, kinds)) = pending.playlist.pop_front() {
+                    break Job::Playlist(id, kinds);
+                }
+                if let Some(id) = pending.tracks.pop_front() {
+                    break Job::Analyze(id);
+                }
+                pending = inner.wakeup.wait(pending).unwrap();
+            }
+        };
+        let state = app.state::<AppState>();
+
+        let (track_id, kinds) = match job {
+            Job::Analyze(id) => (id, AUTO_ANALYSIS_KINDS.to_vec()),
+            Job::Playlist(id, kinds) => (id, kinds),
+            Job::Waveform(track_id) => {
+                // No pause: the user is looking at these rows
+                let outcome = generate_missing_waveform(&state, track_id);
+                record_outcome(&state, track_id, &outcome, false);
+                match outcome {
+                    Ok(false) => {}
+                    outcome => {
+                        let _ = app.emit(
+                            "waveform-ready",
+                            WaveformReadyEvent { track_id, error: outcome.err() },
+                        );
+                    }
+                }
+                continue;
+            }
+            Job::RemoteWaveform(track_id) => {
+                // No pause either: a phone is polling for it (or listening on /events)
+                let outcome = generate_missing_waveform(&state, track_id);
+                record_outcome(&state, track_id, &outcome, false);
+                match outcome {
+                    Ok(false) => {}
+                    Ok(true) => {
+                        let _ = app.emit("waveform-ready", WaveformReadyEvent { track_id, error: None });
+                    }
+                    Err(e) => {
+                        eprintln!("[analysis_queue] Waveform for track {}: {}", track_id, e);
+                        inner.remote_failures.lock().unwrap().insert(track_id, e.clone());
+                        let _ = app.emit("waveform-ready", WaveformReadyEvent { track_id, error: Some(e) });
+                    }
+                }
+                continue;
+            }
+        };
+        *inner.current.lock().unwrap() = Some(track_id);
+
+        let outcome = analyze_queued_track(&state, track_id, &kinds);
+        record_outcome(&state, track_id, &outcome, true);
+        match &outcome {
+            Ok(true) => eprintln!("[analysis_queue] Track {} analyzed", track_id),
+            Ok(false) => {}
+            Err(e) => eprintln!("[analysis_queue] Track {}: {}", track_id, e),
+        }
+
+        *inner.current.lock().unwrap() = None;
+        if !matches!(outcome, Ok(false)) {
+            let _ = app.emit(
+                "track-analyzed",
+                TrackAnalyzedEvent {
+                    track_id,
+                    error: outcome.err(),
+                    pending: inner.pending.lock().unwrap().analysis_len(),
+                },
+            );
+            std::thread::sleep(QUEUE_PAUSE);
+        }
+    }
+}
+
+/// Record a failed job in analysis_errors; a successful analysis (`clears`) forgets
+/// the track's earlier failures
+fn record_outcome(state: &AppState, track_id: i64, outcome: &Result<bool, String>, clears: bool) {
+    let db_lock = state.db.lock().unwrap();
+    let Some(db) = db_lock.as_ref() else {
+        return;
+    };
+    match outcome {
+        Err(e) => analysis_errors::record_failure(db, track_id, e),
+        Ok(true) if clears => {
+            let _ = db.clear_analysis_failure(track_id);
+        }
+        Ok(_) => {}
+    }
+}
+
+/// Which of the requested kinds a track is still missing
+#[derive(Debug, Default, PartialEq)]
+struct MissingAnalysis {
+    bpm: bool,
+    key: bool,
+    waveform: bool,
+    loudness: bool,
+}
+
+impl MissingAnalysis {
+    fn any(&self) -> bool {
+        self.bpm || self.key || self.waveform || self.loudness
+    }
+}
+
+fn missing_analysis(db: &Database, track_id: i64, kinds: &[AnalysisKind]) -> rusqlite::Result<MissingAnalysis> {
+    let wants = |kind| kinds.contains(&kind);
+    let (needs_bpm, needs_key) = db.needs_analysis(track_id)?;
+    Ok(MissingAnalysis {
+        bpm: wants(AnalysisKind::Bpm) && needs_bpm,
+        key: wants(AnalysisKind::Key) && needs_key,
+        waveform: wants(AnalysisKind::Waveform) && !db.has_waveform(track_id)?,
+        loudness: wants(AnalysisKind::Loudness) && db.get_track_loudness(&[track_id])?.is_empty(),
+    })
+}
+
+/// Compute whichever of `kinds` a track is still missing. Returns false if there was
+/// nothing to do (e.g. a manual batch analysis got to it first) or the track failed too
+/// often to try again.
+fn analyze_queued_track(state: &AppState, track_id: i64, kinds: &[AnalysisKind]) -> Result<bool, String> {
+    // Brief lock: what's missing?
+    let (file_path, missing, with_curve) = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        if analysis_errors::skipped_tracks(db).contains(&track_id) {
+            return Ok(false);
+        }
+        let track = db.get_track(track_id)
+            .map_err(|e| format!("Failed to get track: {}", e))?;
+        let missing = missing_analysis(db, track_id, kinds)
+            .map_err(|e| format!("Failed to check analysis: {}", e))?;
+        (track.file_path, missing, tempo_curve_enabled(db))
+    };
+    if !missing.any() {
+        return Ok(false);
+    }
+
+    let path = Path::new(&file_path);
+    if !path.exists() {
+        return Err(format!("Audio file not found: {}", file_path));
+    }
+
+    // Heavy DSP work — no lock held
+    let bpm_result = if missing.bpm {
+        Some(detect_bpm_and_curve(path, with_curve).map_err(|e| format!("BPM detection failed: {}", e))?)
+    } else {
+        None
+    };
+    let key_result = if missing.key {
+        Some(key::detect_key(path).map_err(|e| format!("Key detection failed: {}", e))?)
+    } else {
+        None
+    };
+    let waveforms = if missing.waveform {
+        Some(generate_waveform_blobs(path)?)
+    } else {
+        None
+    };
+    // None for silence: nothing to store
+    let loudness_lufs = if missing.loudness {
+        loudness::measure_file(path).map_err(|e| format!("Loudness measurement failed: {}", e))?
+    } else {
+        None
+    };
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let (bpm_result, curve) = bpm_result.unzip();
+    let curve = curve.flatten();
+    let bpm = bpm_result.map(|r| (normalize_detected_bpm(db, track_id, r.bpm, r.confidence), r.confidence));
+    db.with_busy_retry(|db| {
+        let tx = db.transaction()?;
+        if let Some((bpm, confidence)) = bpm {
+            db.save_bpm_analysis(track_id, bpm, confidence)?;
+        }
+        if let Some(curve) = &curve {
+            save_tempo_curve(db, track_id, curve)?;
+        }
+        if let Some(r) = &key_result {
+            db.save_key_analysis(track_id, &r.camelot, r.confidence)?;
+        }
+        if let Some(waveforms) = &waveforms {
+            waveforms.save(db, track_id)?;
+        }
+        if let Some(lufs) = loudness_lufs {
+            db.save_loudness_analysis(track_id, lufs)?;
+        }
+        tx.commit()
+    })
+    .map_err(|e| format!("Failed to save analysis: {}", e))?;
+    write_sidecar_after_analysis(db, track_id);
+
+    Ok(true)
+}
+
+/// Generate a track's waveforms if it has none. Returns false if it already had them.
+fn generate_missing_waveform(state: &AppState, track_id: i64) -> Result<bool, String> {
+    let file_path = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        if db.has_waveform(track_id).map_err(|e| format!("Failed to check waveform: {}", e))? {
+            return Ok(false);
+        }
+        db.get_track(track_id)
+            .map_err(|e| format!("Failed to get track: {}", e))?
+            .file_path
+    };
+
+    let path = Path::new(&file_path);
+    if !path.exists() {
+        return Err(format!("Audio file not found: {}", file_path));
+    }
+    let waveforms = generate_waveform_blobs(path)?;
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.with_busy_retry(|db| {
+        let tx = db.transaction()?;
+        waveforms.save(db, track_id)?;
+        tx.commit()
+    })
+    .map_err(|e| format!("Failed to save waveform: {}", e))?;
+    Ok(true)
+}
+
+/// IDs of tracks missing BPM, key or waveform (except ones that keep failing)
+fn tracks_missing_analysis(db: &Database) -> Result<Vec<i64>, String> {
+    let tracks = db.get_all_tracks()
+        .map_err(|e| format!("Failed to get tracks: {}", e))?;
+    let skipped = analysis_errors::skipped_tracks(db);
+    Ok(tracks
+        .into_iter()
+        .filter_map(|t| t.id)
+        .filter(|id| !skipped.contains(id))
+        .filter(|&id| {
+            missing_analysis(db, id, AUTO_ANALYSIS_KINDS).map_or(true, |missing| missing.any())
+        })
+        .collect())
+}
+
+/// If auto-analysis is on, queue every track still missing analysis (e.g. imports
+/// left over from the previous session). Called from init_database.
+pub fn queue_backlog(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let backlog = {
+        let db_lock = state.db.lock().unwrap();
+        match db_lock.as_ref() {
+            Some(db) if auto_analyze_enabled(db) => tracks_missing_analysis(db),
+            _ => return,
+        }
+    };
+    match backlog {
+        Ok(ids) => {
+            let added = app.state::<AnalysisQueueState>().enqueue(app, ids);
+            eprintln!("[analysis_queue] {} tracks queued from backlog", added);
+        }
+        Err(e) => eprintln!("[analysis_queue] Failed to load backlog: {}", e),
+    }
+}
+
+/// Auto-analysis setting plus what the queue is doing
+#[derive(Debug, Serialize)]
+pub struct AnalysisQueueStatusDTO {
+    pub enabled: bool,
+    pub pending: usize,
+    pub current: Option<i64>,
+    /// Visible-row waveforms still to generate
+    pub waveforms_pending: usize,
+    /// Of `pending`: tracks queued by analyze_playlist
+    pub playlist_pending: usize,
+}
+
+/// Get the auto-analysis setting and queue progress
+#[tauri::command]
+pub fn get_analysis_queue_status(
+    state: State<AppState>,
+    queue: State<AnalysisQueueState>,
+) -> Result<AnalysisQueueStatusDTO, String> {
+    let enabled = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        auto_analyze_enabled(db)
+    };
+
+    let pending = queue.inner.pending.lock().unwrap();
+    Ok(AnalysisQueueStatusDTO {
+        enabled,
+        pending: pending.analysis_len(),
+        current: *queue.inner.current.lock().unwrap(),
+        waveforms_pending: pending.waveforms.len(),
+        playlist_pending: pending.playlist.len(),
+    })
+}
+
+/// Turn auto-analysis of new imports on or off. Turning it on also queues every track
+/// that is still missing analysis; turning it off clears the queue (the track being
+/// analyzed finishes). Returns the number of tracks queued.
+#[tauri::command]
+pub fn set_auto_analysis(
+    app: AppHandle,
+    state: State<AppState>,
+    queue: State<AnalysisQueueState>,
+    enabled: bool,
+) -> Result<usize, String> {
+    let backlog = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        db.set_setting(AUTO_ANALYZE_SETTING, if enabled { "true" } else { "false" })
+            .map_err(|e| format!("Failed to save auto-analysis setting: {}", e))?;
+        if enabled {
+            tracks_missing_analysis(db)?
+        } else {
+            Vec::new()
+        }
+    };
+
+    if !enabled {
+        queue.inner.pending.lock().unwrap().tracks.clear();
+        return Ok(0);
+    }
+
+    let added = queue.enqueue(&app, backlog);
+    eprintln!("[analysis_queue] Auto-analysis enabled, {} tracks queued", added);
+    Ok(added)
+}
+
+/// Generate waveforms for the rows currently on screen, ahead of any auto-analysis.
+/// Each call replaces the previous request, so rows scrolled out of view are dropped
+/// (pass an empty list to cancel). Tracks that already have waveforms are skipped.
+/// Emits "waveform-ready" per track; returns how many were queued.
+#[tauri::command]
+pub fn request_waveforms(
+    app: AppHandle,
+    state: State<AppState>,
+    queue: State<AnalysisQueueState>,
+    track_ids: Vec<i64>,
+) -> Result<usize, String> {
+    let missing: Vec<i64> = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        track_ids
+            .into_iter()
+            .filter(|&id| !db.has_waveform(id).unwrap_or(true))
+            .collect()
+    };
+
+    let count = missing.len();
+    queue.request_waveforms(&app, missing);
+    Ok(count)
+}
+
+/// Analyze one playlist's tracks ahead of the auto-analysis backlog (whether or not
+/// auto-analysis is on). `kinds` picks from "bpm", "key", "waveform" and "loudness"
+/// (all of them if left out); only what a track is missing is computed, and tracks that
+/// keep failing are left out.
+/// Emits "track-analyzed" per track; returns how many tracks were queued.
+#[tauri::command]
+pub fn analyze_playlist(
+    app: AppHandle,
+    state: State<AppState>,
+    queue: State<AnalysisQueueState>,
+    playlist_id: i64,
+    kinds: Option<Vec<AnalysisKind>>,
+) -> Result<usize, String> {
+    let kinds = kinds.unwrap_or_else(|| ALL_ANALYSIS_KINDS.to_vec());
+    let missing: Vec<i64> = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let track_ids = db.get_playlist_track_ids(playlist_id)
+            .map_err(|e| format!("Failed to get playlist tracks: {}", e))?;
+        // Tracks that keep failing count as seen, so they're left out too
+        let mut seen = analysis_errors::skipped_tracks(db);
+        track_ids
+            .into_iter()
+            .filter(|&id| seen.insert(id))
+            .filter(|&id| missing_analysis(db, id, &kinds).map_or(true, |missing| missing.any()))
+            .collect()
+    };
+
+    let added = queue.enqueue_playlist(&app, missing, &kinds);
+    eprintln!("[analysis_queue] Playlist {}: {} tracks queued", playlist_id, added);
+    Ok(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{create_test_track, Track};
+
+    fn add_track(db: &Database) -> i64 {
+        let track = Track {
+            file_path: "/Music/a.mp3".to_string(),
+            file_hash: "a".to_string(),
+            ..create_test_track()
+        };
+        db.create_track(&track).unwrap()
+    }
+
+    #[test]
+    fn test_missing_analysis_follows_kinds() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let track_id = add_track(&db);
+
+        let missing = missing_analysis(&db, track_id, AUTO_ANALYSIS_KINDS).unwrap();
+        assert_eq!(missing, MissingAnalysis { bpm: true, key: true, waveform: true, loudness: false });
+        let missing = missing_analysis(&db, track_id, &[AnalysisKind::Loudness]).unwrap();
+        assert_eq!(missing, MissingAnalysis { loudness: true, ..Default::default() });
+
+        db.save_bpm_analysis(track_id, 124.0, 0.9).unwrap();
+        db.save_loudness_analysis(track_id, -8.5).unwrap();
+        let missing = missing_analysis(&db, track_id, &[AnalysisKind::Bpm, AnalysisKind::Loudness]).unwrap();
+        assert!(!missing.any());
+        assert!(missing_analysis(&db, track_id, ALL_ANALYSIS_KINDS).unwrap().key);
+    }
+
+    #[test]
+    fn test_analysis_kind_names() {
+        let kinds: Vec<AnalysisKind> = serde_json::from_str(r#"["bpm", "loudness"]"#).unwrap();
+        assert_eq!(kinds, [AnalysisKind::Bpm, AnalysisKind::Loudness]);
+        assert!(serde_json::from_str::<AnalysisKind>(r#""lufs""#).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+// Batch actions — run an ordered list of primitive actions over many tracks
+// (the building blocks for user-defined "new track workflow" macros in the UI).
+// Each track's database changes are applied in one transaction: if any action
+// fails, that track is rolled back and the next track is processed.
+
+use crate::audio::{bpm, key};
+use crate::commands::analysis::normalize_detected_bpm;
+use crate::commands::library::{validate_color, AppState};
+use crate::commands::playlists::{ensure_editable, notify_playlists_changed};
+use crate::db::Database;
+use crate::scanner::{Scanner, TagValues, WriteConflictMode};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::{AppHandle, State};
+
+/// One step of a batch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BatchAction {
+    /// Set the genre (source 'user')
+    SetGenre { genre: String },
+    /// Set or clear the track color ("#RRGGBB")
+    SetColor { color: Option<String> },
+    AddToPlaylist { playlist_id: i64 },
+    /// Detect BPM and key (DSP runs before the track's transaction, outside the DB lock)
+    Analyze,
+    /// Write BPM, key and genre to the file's tags. Runs after the track's changes
+    /// are committed, so it writes the values the earlier actions produced.
+    /// Files modified by another program since they were read are refused or re-read
+    /// first (see WriteConflictMode).
+    WriteTags,
+}
+
+impl BatchAction {
+    fn name(&self) -> &'static str {
+        match self {
+            BatchAction::SetGenre { .. } => "set_genre",
+            BatchAction::SetColor { .. } => "set_color",
+            BatchAction::AddToPlaylist { .. } => "add_to_playlist",
+            BatchAction::Analyze => "analyze",
+            BatchAction::WriteTags => "write_tags",
+        }
+    }
+}
+
+/// Outcome for one track
+#[derive(Debug, Serialize)]
+pub struct BatchTrackResultDTO {
+    pub track_id: i64,
+    pub success: bool,
+    /// Names of the actions that were applied, in order
+    pub applied: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// Analysis computed for a track ahead of its transaction
+struct AnalysisResults {
+    bpm: (f64, f64),
+    key: (String, f64),
+}
+
+/// Run `actions` in order on every track in `track_ids` and report per track.
+/// Invalid actions (bad color, unknown or read-only playlist) reject the whole batch
+/// up front. A failing track doesn't stop the batch; its database changes are rolled
+/// back. A failed tag write is reported, but the database changes before it are kept.
+#[tauri::command]
+pub fn run_batch_actions(
+    app: AppHandle,
+    state: State<AppState>,
+    track_ids: Vec<i64>,
+    actions: Vec<BatchAction>,
+) -> Result<Vec<BatchTrackResultDTO>, String> {
+    if actions.is_empty() {
+        return Err("No actions given".to_string());
+    }
+
+    // 1. Validate the actions once (brief lock)
+    let conflict_mode = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        for action in &actions {
+            match action {
+                BatchAction::SetColor { color: Some(color) } => validate_color(color)?,
+                BatchAction::AddToPlaylist { playlist_id } => ensure_editable(db, *playlist_id)?,
+                _ => {}
+            }
+        }
+        WriteConflictMode::from_settings(db)
+    };
+
+    let wants_analysis = actions.contains(&BatchAction::Analyze);
+    let wants_tags = actions.contains(&BatchAction::WriteTags);
+    let mut results = Vec::with_capacity(track_ids.len());
+
+    for track_id in track_ids {
+        let mut result = BatchTrackResultDTO {
+            track_id,
+            success: false,
+            applied: Vec::new(),
+            error: None,
+        };
+
+        let file_path = {
+            let db_lock = state.db.lock().unwrap();
+            let db = db_lock.as_ref().ok_or("Database not initialized")?;
+            match db.get_track(track_id) {
+                Ok(track) => track.file_path,
+                Err(e) => {
+                    result.error = Some(format!("Failed to get track: {}", e));
+                    results.push(result);
+                    continue;
+                }
+            }
+        };
+
+        // 2. Heavy DSP without the lock
+        let analysis = if wants_analysis {
+            match analyze(Path::new(&file_path)) {
+                Ok(a) => Some(a),
+                Err(e) => {
+                    result.error = Some(e);
+                    results.push(result);
+                    continue;
+                }
+            }
+        } else {
+            None
+        };
+
+        // 3. All database actions for this track in one transaction
+        let committed = {
+            let db_lock = state.db.lock().unwrap();
+            let db = db_lock.as_ref().ok_or("Database not initialized")?;
+            apply_actions(db, track_id, &actions, analysis.as_ref(), &mut result.applied)
+        };
+        if let Err(e) = committed {
+            result.applied.clear();
+            result.error = Some(e);
+            results.push(result);
+            continue;
+        }
+
+        // 4. Tag writing touches the file, so it runs after the commit. The file must
+        // not have been edited elsewhere since it was read; the write is then recorded
+        // so it doesn't look like an outside change itself.
+        if wants_tags {
+            let prepared = {
+                let db_lock = state.db.lock().unwrap();
+                let db = db_lock.as_ref().ok_or("Database not initialized")?;
+                Scanner::ensure_fresh(db, track_id, conflict_mode)
+                    .and_then(|path| Ok((path, tag_values_for(db, track_id)?)))
+            };
+            let written = prepared.and_then(|(path, values)| {
+                Scanner::write_tags(Path::new(&path), &values)?;
+                let db_lock = state.db.lock().unwrap();
+                let db = db_lock.as_ref().ok_or("Database not initialized")?;
+                Scanner::record_file_write(db, track_id, Path::new(&path))
+            });
+            match written {
+                Ok(()) => {
+                    result.applied.push(BatchAction::WriteTags.name().to_string());
+                    result.success = true;
+                }
+                Err(e) => result.error = Some(e),
+            }
+        } else {
+            result.success = true;
+        }
+
+        results.push(result);
+    }
+
+    let failed = results.iter().filter(|r| !r.success).count();
+    eprintln!(
+        "[batch] Ran {} actions on {} tracks ({} failed)",
+        actions.len(),
+        results.len(),
+        failed
+    );
+    if actions.iter().any(|a| matches!(a, BatchAction::AddToPlaylist { .. })) {
+        notify_playlists_changed(&app);
+    }
+
+    Ok(results)
+}
+
+fn analyze(path: &Path) -> Result<AnalysisResults, String> {
+    if !path.exists() {
+        return Err(format!("Audio file not found: {}", path.display()));
+    }
+    let bpm_result = bpm::detect_bpm(path).map_err(|e| format!("BPM detection failed: {}", e))?;
+    let key_result = key::detect_key(path).map_err(|e| format!("Key detection failed: {}", e))?;
+    Ok(AnalysisResults {
+        bpm: (bpm_result.bpm, bpm_result.confidence),
+        key: (key_result.camelot, key_result.confidence),
+    })
+}
+
+/// Apply the database actions for one track in a transaction (dropped = rolled back on error)
+fn apply_actions(
+    db: &Database,
+    track_id: i64,
+    actions: &[BatchAction],
+    analysis: Option<&AnalysisResults>,
+    applied: &mut Vec<String>,
+) -> Result<(), String> {
+    let tx = db.begin_write().map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    for action in actions {
+        let outcome = match action {
+            BatchAction::SetGenre { genre } => db.save_track_genre(track_id, genre, "user"),
+            BatchAction::SetColor { color } => db.set_track_color(track_id, color.as_deref()),
+            BatchAction::AddToPlaylist { playlist_id } => db.add_track_to_playlist(*playlist_id, track_id),
+            BatchAction::Analyze => match analysis {
+                Some(a) => db
+                    .save_bpm_analysis(track_id, normalize_detected_bpm(db, track_id, a.bpm.0, a.bpm.1), a.bpm.1)
+                    .and_then(|_| db.save_key_analysis(track_id, &a.key.0, a.key.1)),
+                None => Ok(()),
+            },
+            // Runs after commit
+            BatchAction::WriteTags => continue,
+        };
+        outcome.map_err(|e| format!("{} failed: {}", action.name(), e))?;
+        applied.push(action.name().to_string());
+    }
+
+    tx.commit().map_err(|e| format!("Failed to commit: {}", e))
+}
+
+/// Current BPM, key and genre of a track, as they should be written to its tags
+fn tag_values_for(db: &Database, track_id: i64) -> Result<TagValues, String> {
+    let track = db.get_track(track_id).map_err(|e| format!("Failed to get track: {}", e))?;
+    let bpm = db.get_bpm_analysis(track_id).map_err(|e| format!("Failed to get BPM: {}", e))?;
+    let key = db.get_key_analysis(track_id).map_err(|e| format!("Failed to get key: {}", e))?;
+    Ok(TagValues {
+        bpm: bpm.map(|(bpm, _)| bpm),
+        key: key.map(|(key, _)| key),
+        genre: track.genre,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,236 @@
+// Beat marker export for VJ software
+// export_beat_markers writes a track's beat and bar timestamps so visuals can be synced
+// to a prepared track: as CSV (one row per beat) or as Resolume-style marker XML (tempo,
+// phase offset of the first downbeat and one marker per bar, the values a Resolume clip
+// needs for BPM sync). Markers come from the track's beat grid (imported from
+// Rekordbox); without one, a constant grid is estimated from the analyzed BPM, starting
+// where the leading silence ends, and the export says so.
+
+use crate::commands::library::AppState;
+use crate::db::{BeatGrid, Track};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tauri::State;
+
+/// Beats per bar assumed when the grid doesn't number them
+const BEATS_PER_BAR: u8 = 4;
+
+/// Output format for export_beat_markers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BeatMarkerFormat {
+    Csv,
+    Resolume,
+}
+
+/// What export_beat_markers wrote
+#[derive(Debug, Clone, Serialize)]
+pub struct BeatMarkerExportDTO {
+    pub path: String,
+    pub beats: usize,
+    pub bars: usize,
+    pub bpm: Option<f64>,
+    /// "rekordbox", "analysis" or "estimated" (constant grid from the analyzed BPM)
+    pub source: String,
+}
+
+/// One beat: position and place in its bar (1 = downbeat)
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Beat {
+    position_ms: f64,
+    beat_in_bar: u8,
+}
+
+/// Beats every 60000/bpm ms from `first_ms` to `duration_ms`, numbered from `first_number`
+fn constant_grid(bpm: f64, first_ms: f64, first_number: u8, duration_ms: f64) -> Vec<Beat> {
+    let interval = 60_000.0 / bpm;
+    let count = ((duration_ms - first_ms) / interval).floor().max(-1.0) as i64 + 1;
+    (0..count)
+        .map(|i| Beat {
+            position_ms: first_ms + i as f64 * interval,
+            beat_in_bar: ((first_number.max(1) as i64 - 1 + i) % BEATS_PER_BAR as i64) as u8 + 1,
+        })
+        .collect()
+}
+
+/// The track's beats and where they came from. A grid with every beat is used as is;
+/// a grid with only its first beat and BPM is extended to the end of the track.
+fn track_beats(
+    grid: Option<&BeatGrid>,
+    analyzed_bpm: Option<f64>,
+    silence_lead_ms: Option<i64>,
+    duration_ms: Option<i32>,
+) -> Option<(Vec<Beat>, Option<f64>, String)> {
+    let duration_ms = duration_ms.unwrap_or(0) as f64;
+
+    if let Some(grid) = grid {
+        if grid.beats.len() >= 2 {
+            let beats = grid
+                .beats
+                .iter()
+                .map(|&(position_ms, number)| Beat { position_ms: position_ms as f64, beat_in_bar: number })
+                .collect();
+            return Some((beats, grid.bpm, grid.source.clone()));
+        }
+        if let Some(bpm) = grid.bpm.filter(|b| *b > 0.0) {
+            let first_ms = grid.first_beat_ms.or(grid.beats.first().map(|b| b.0)).unwrap_or(0) as f64;
+            let first_number = grid.beats.first().map_or(1, |b| b.1);
+            return Some((constant_grid(bpm, first_ms, first_number, duration_ms), Some(bpm), grid.source.clone()));
+        }
+    }
+
+    let bpm = analyzed_bpm.filter(|b| *b > 0.0)?;
+    let first_ms = silence_lead_ms.unwrap_or(0) as f64;
+    Some((constant_grid(bpm, first_ms, 1, duration_ms), Some(bpm), "estimated".to_string()))
+}
+
+/// Bar number of each beat: bar 1 starts at the first downbeat, beats before it are bar 0
+fn bar_numbers(beats: &[Beat]) -> Vec<usize> {
+    let mut bar = 0;
+    beats
+        .iter()
+        .map(|beat| {
+            if beat.beat_in_bar == 1 {
+                bar += 1;
+            }
+            bar
+        })
+        .collect()
+}
+
+fn render_csv(beats: &[Beat]) -> String {
+    let mut csv = String::from("Beat,Bar,Beat In Bar,Time (s),Time (ms)\r\n");
+    for (i, (beat, bar)) in beats.iter().zip(bar_numbers(beats)).enumerate() {
+        csv.push_str(&format!(
+            "{},{},{},{:.3},{}\r\n",
+            i + 1,
+            bar,
+            beat.beat_in_bar,
+            beat.position_ms / 1000.0,
+            beat.position_ms.round() as i64
+        ));
+    }
+    csv
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_resolume(track: &Track, beats: &[Beat], bpm: Option<f64>) -> String {
+    let name = match (&track.artist, &track.title) {
+        (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+        (_, Some(title)) => title.clone(),
+        _ => track.file_path.clone(),
+    };
+    let first_downbeat = beats.iter().find(|b| b.beat_in_bar == 1).or(beats.first());
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str(&format!(
+        "<BeatMarkers name=\"{}\" bpm=\"{:.3}\" phaseOffsetMs=\"{}\" durationMs=\"{}\" beatsPerBar=\"{}\">\n",
+        xml_escape(&name),
+        bpm.unwrap_or(0.0),
+        first_downbeat.map_or(0, |b| b.position_ms.round() as i64),
+        track.duration_ms.unwrap_or(0),
+        BEATS_PER_BAR
+    ));
+    for (beat, bar) in beats.iter().zip(bar_numbers(beats)) {
+        if beat.beat_in_bar == 1 {
+            xml.push_str(&format!(
+                "  <Marker bar=\"{}\" timeMs=\"{}\" />\n",
+                bar,
+                beat.position_ms.round() as i64
+            ));
+        }
+    }
+    xml.push_str("</BeatMarkers>\n");
+    xml
+}
+
+/// Write a track's beat/bar timestamps to `path` as CSV or Resolume marker XML
+#[tauri::command]
+pub fn export_beat_markers(
+    state: State<AppState>,
+    track_id: i64,
+    format: BeatMarkerFormat,
+    path: String,
+) -> Result<BeatMarkerExportDTO, String> {
+    let (track, grid, analysis) = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let track = db.get_track(track_id)
+            .map_err(|e| format!("Failed to get track: {}", e))?;
+        let grid = db.get_beat_grid(track_id)
+            .map_err(|e| format!("Failed to get beat grid: {}", e))?;
+        let analysis = db.get_track_analysis(track_id)
+            .map_err(|e| format!("Failed to get analysis: {}", e))?;
+        (track, grid, analysis)
+    };
+
+    let (beats, bpm, source) = track_beats(
+        grid.as_ref(),
+        analysis.as_ref().and_then(|a| a.bpm),
+        analysis.as_ref().and_then(|a| a.silence_lead_ms),
+        track.duration_ms,
+    )
+    .filter(|(beats, ..)| !beats.is_empty())
+    .ok_or("Track has no beat grid or BPM. Analyze it or import its grid first.")?;
+
+    let contents = match format {
+        BeatMarkerFormat::Csv => render_csv(&beats),
+        BeatMarkerFormat::Resolume => render_resolume(&track, &beats, bpm),
+    };
+    fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+    let bars = bar_numbers(&beats).last().copied().unwrap_or(0);
+    eprintln!("[beat_markers] Wrote {} beats ({} bars, {}) to {}", beats.len(), bars, source, path);
+
+    Ok(BeatMarkerExportDTO {
+        path,
+        beats: beats.len(),
+        bars,
+        bpm,
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_track_beats() {
+        // Estimated: 120 BPM from the end of a 250 ms lead silence, 3 s long
+        let (beats, bpm, source) = track_beats(None, Some(120.0), Some(250), Some(3_000)).unwrap();
+        assert_eq!((bpm, source.as_str()), (Some(120.0), "estimated"));
+        let positions: Vec<f64> = beats.iter().map(|b| b.position_ms).collect();
+        assert_eq!(positions, vec![250.0, 750.0, 1250.0, 1750.0, 2250.0, 2750.0]);
+        assert_eq!(bar_numbers(&beats), vec![1, 1, 1, 1, 2, 2]);
+        assert!(track_beats(None, None, None, Some(3_000)).is_none());
+
+        // A full grid is used as is, starting on beat 3 (bar 0 until the downbeat)
+        let grid = BeatGrid {
+            track_id: 1,
+            bpm: Some(128.0),
+            first_beat_ms: Some(100),
+            beats: vec![(100, 3), (569, 4), (1038, 1), (1506, 2)],
+            source: "rekordbox".to_string(),
+        };
+        let (beats, _, source) = track_beats(Some(&grid), Some(127.5), None, Some(2_000)).unwrap();
+        assert_eq!(source, "rekordbox");
+        assert_eq!(bar_numbers(&beats), vec![0, 0, 1, 1]);
+
+        let csv = render_csv(&beats);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "Beat,Bar,Beat In Bar,Time (s),Time (ms)");
+        assert_eq!(lines[3], "3,1,1,1.038,1038");
+
+        // Only the first beat: extended from the grid's BPM, keeping its numbering
+        let sparse = BeatGrid { beats: vec![(100, 4)], ..grid };
+        let (beats, ..) = track_beats(Some(&sparse), None, None, Some(1_100)).unwrap();
+        assert_eq!(beats.iter().map(|b| b.beat_in_bar).collect::<Vec<_>>(), vec![4, 1, 2]);
+    }
+}
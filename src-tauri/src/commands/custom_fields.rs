@@ -0,0 +1,230 @@
+// User-defined track fields ("Vinyl owned", "Stems available", "Crate"...)
+// Definitions (name + type) and per-track values live in their own tables, so adding a
+// column needs no schema change. Values are searchable (search_tracks) and can be filtered
+// on by seed filters (CustomFieldFilter).
+
+use crate::commands::library::{validate_date, AppState};
+use crate::db::CustomFieldDefinition;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::State;
+
+const FIELD_TYPES: [&str; 4] = ["text", "number", "boolean", "date"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CustomFieldDTO {
+    pub id: i64,
+    pub name: String,
+    /// "text", "number", "boolean" or "date"
+    pub field_type: String,
+    pub sort_order: i32,
+}
+
+impl From<CustomFieldDefinition> for CustomFieldDTO {
+    fn from(field: CustomFieldDefinition) -> Self {
+        CustomFieldDTO {
+            id: field.id,
+            name: field.name,
+            field_type: field.field_type,
+            sort_order: field.sort_order,
+        }
+    }
+}
+
+/// Condition on a custom field. Tracks without a value for the field never match.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CustomFieldFilter {
+    pub field_id: i64,
+    /// Exact value (ignoring case); missing = any value
+    pub value: Option<String>,
+    /// Bounds for number fields
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl CustomFieldFilter {
+    /// `values`: the track's custom values (field ID -> value)
+    pub fn matches(&self, values: Option<&HashMap<i64, String>>) -> bool {
+        let Some(value) = values.and_then(|v| v.get(&self.field_id)) else {
+            return false;
+        };
+        if self.value.as_ref().is_some_and(|wanted| !wanted.eq_ignore_ascii_case(value)) {
+            return false;
+        }
+        if self.min.is_some() || self.max.is_some() {
+            let Ok(number) = value.parse::<f64>() else {
+                return false;
+            };
+            if self.min.is_some_and(|min| number < min) || self.max.is_some_and(|max| number > max) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Check and normalize a value for a field of `field_type`. Blank means "no value".
+fn normalize_value(field_type: &str, value: &str) -> Result<Option<String>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let normalized = match field_type {
+        "number" => {
+            value.parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .ok_or_else(|| format!("'{}' is not a number", value))?;
+            value.to_string()
+        }
+        "boolean" => match value.to_lowercase().as_str() {
+            "true" | "yes" | "1" => "true".to_string(),
+            "false" | "no" | "0" => "false".to_string(),
+            _ => return Err(format!("'{}' is not true or false", value)),
+        },
+        "date" => {
+            validate_date(value)?;
+            value.to_string()
+        }
+        _ => value.to_string(),
+    };
+    Ok(Some(normalized))
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Field name cannot be empty".to_string());
+    }
+    Ok(name.to_string())
+}
+
+/// All custom fields in display order
+#[tauri::command]
+pub fn get_custom_fields(state: State<AppState>) -> Result<Vec<CustomFieldDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let fields = db.get_custom_field_definitions()
+        .map_err(|e| format!("Failed to get custom fields: {}", e))?;
+    Ok(fields.into_iter().map(CustomFieldDTO::from).collect())
+}
+
+#[tauri::command]
+pub fn create_custom_field(
+    state: State<AppState>,
+    name: String,
+    field_type: String,
+) -> Result<CustomFieldDTO, String> {
+    let name = validate_name(&name)?;
+    if !FIELD_TYPES.contains(&field_type.as_str()) {
+        return Err(format!("Invalid field type '{}'. Must be one of: {}", field_type, FIELD_TYPES.join(", ")));
+    }
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let id = db.create_custom_field_definition(&name, &field_type)
+        .map_err(|e| format!("Failed to create field '{}' (name already used?): {}", name, e))?;
+    db.get_custom_field_definition(id)
+        .map(CustomFieldDTO::from)
+        .map_err(|e| format!("Failed to get custom field: {}", e))
+}
+
+/// Rename or reorder a field. The type is fixed once created.
+#[tauri::command]
+pub fn update_custom_field(
+    state: State<AppState>,
+    id: i64,
+    name: String,
+    sort_order: i32,
+) -> Result<CustomFieldDTO, String> {
+    let name = validate_name(&name)?;
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.update_custom_field_definition(id, &name, sort_order)
+        .map_err(|e| format!("Failed to update field '{}' (name already used?): {}", name, e))?;
+    db.get_custom_field_definition(id)
+        .map(CustomFieldDTO::from)
+        .map_err(|e| format!("Failed to get custom field: {}", e))
+}
+
+/// Delete a field and all its values
+#[tauri::command]
+pub fn delete_custom_field(state: State<AppState>, id: i64) -> Result<(), String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+    db.delete_custom_field_definition(id)
+        .map_err(|e| format!("Failed to delete custom field: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit: {}", e))
+}
+
+/// A track's custom values as field ID -> value
+#[tauri::command]
+pub fn get_track_custom_fields(state: State<AppState>, track_id: i64) -> Result<HashMap<i64, String>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.get_track_custom_fields(track_id)
+        .map_err(|e| format!("Failed to get custom fields: {}", e))
+}
+
+/// Every track's custom values (track ID -> field ID -> value), for the track table columns
+#[tauri::command]
+pub fn get_all_custom_field_values(
+    state: State<AppState>,
+) -> Result<HashMap<i64, HashMap<i64, String>>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.get_all_custom_field_values()
+        .map_err(|e| format!("Failed to get custom field values: {}", e))
+}
+
+/// Set a track's value for a field (checked against the field's type); null or blank clears it.
+/// Returns the stored value.
+#[tauri::command]
+pub fn set_track_custom_field(
+    state: State<AppState>,
+    track_id: i64,
+    field_id: i64,
+    value: Option<String>,
+) -> Result<Option<String>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let field = db.get_custom_field_definition(field_id)
+        .map_err(|e| format!("Custom field {} not found: {}", field_id, e))?;
+    let value = match value {
+        Some(value) => normalize_value(&field.field_type, &value)
+            .map_err(|e| format!("Invalid value for '{}': {}", field.name, e))?,
+        None => None,
+    };
+    db.set_track_custom_field(track_id, field_id, value.as_deref())
+        .map_err(|e| format!("Failed to set custom field: {}", e))?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_value_and_filter() {
+        assert_eq!(normalize_value("boolean", " Yes ").unwrap().as_deref(), Some("true"));
+        assert!(normalize_value("boolean", "maybe").is_err());
+        assert!(normalize_value("number", "12a").is_err());
+        assert!(normalize_value("date", "2024-02-30").is_err());
+        assert_eq!(normalize_value("text", "  ").unwrap(), None);
+
+        let values = HashMap::from([(1, "true".to_string()), (2, "4".to_string())]);
+        let filter = |field_id, value: Option<&str>, min| CustomFieldFilter {
+            field_id,
+            value: value.map(String::from),
+            min,
+            max: None,
+        };
+        assert!(filter(1, Some("TRUE"), None).matches(Some(&values)));
+        assert!(!filter(1, Some("false"), None).matches(Some(&values)));
+        assert!(filter(2, None, Some(3.0)).matches(Some(&values)));
+        assert!(!filter(2, None, Some(5.0)).matches(Some(&values)));
+        assert!(!filter(3, None, None).matches(Some(&values)));
+        assert!(!filter(1, None, None).matches(None));
+    }
+}
@@ -0,0 +1,98 @@
+// "Show in Finder/Explorer" for the track context menu
+// Paths are resolved (symlinks, "..") and must lie inside a library folder before they are
+// handed to the opener plugin, so the frontend can't use these to open arbitrary locations.
+
+use crate::commands::library::AppState;
+use crate::db::Database;
+use crate::scanner::collapse_roots;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+/// Library folders as resolved paths; folders that no longer exist are left out
+fn library_roots(db: &Database) -> Result<Vec<PathBuf>, String> {
+    let folders = match db.get_setting("library_folders")
+        .map_err(|e| format!("Failed to get library folders: {}", e))?
+    {
+        Some(json) => serde_json::from_str::<Vec<String>>(&json)
+            .map_err(|e| format!("Failed to parse library folders JSON: {}", e))?,
+        None => Vec::new(),
+    };
+    Ok(collapse_roots(&folders)
+        .iter()
+        .filter_map(|folder| Path::new(folder).canonicalize().ok())
+        .collect())
+}
+
+/// Resolve `path` and check it lies inside one of `roots` (resolved paths)
+fn resolve_in_library(path: &str, roots: &[PathBuf]) -> Result<PathBuf, String> {
+    let resolved = Path::new(path)
+        .canonicalize()
+        .map_err(|e| format!("Path not found: {} ({})", path, e))?;
+    if !roots.iter().any(|root| resolved.starts_with(root)) {
+        return Err(format!("Path is not inside a library folder: {}", path));
+    }
+    Ok(resolved)
+}
+
+/// Show a track's file selected in the system file manager
+#[tauri::command]
+pub fn reveal_track_in_file_manager(state: State<AppState>, track_id: i64) -> Result<(), String> {
+    let (file_path, roots) = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let track = db.get_track(track_id)
+            .map_err(|e| format!("Failed to get track {}: {}", track_id, e))?;
+        (track.file_path, library_roots(db)?)
+    };
+
+    let path = resolve_in_library(&file_path, &roots)?;
+    tauri_plugin_opener::reveal_item_in_dir(&path)
+        .map_err(|e| format!("Failed to reveal {}: {}", path.display(), e))
+}
+
+/// Open a library folder in the system file manager; for a file, the folder containing it
+#[tauri::command]
+pub fn open_containing_folder(state: State<AppState>, path: String) -> Result<(), String> {
+    let roots = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        library_roots(db)?
+    };
+
+    let resolved = resolve_in_library(&path, &roots)?;
+    let folder = if resolved.is_dir() {
+        resolved
+    } else {
+        resolved.parent().map(Path::to_path_buf).ok_or_else(|| format!("No parent folder: {}", path))?
+    };
+    tauri_plugin_opener::open_path(&folder, None::<&str>)
+        .map_err(|e| format!("Failed to open {}: {}", folder.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resolve_in_library() {
+        let temp_dir = TempDir::new().unwrap();
+        let library = temp_dir.path().join("Music");
+        let outside = temp_dir.path().join("Private");
+        fs::create_dir_all(library.join("House")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(library.join("House/track.mp3"), b"x").unwrap();
+        fs::write(outside.join("notes.txt"), b"x").unwrap();
+        let roots = vec![library.canonicalize().unwrap()];
+
+        let track = library.join("House/track.mp3");
+        assert!(resolve_in_library(track.to_str().unwrap(), &roots).is_ok());
+        assert!(resolve_in_library(library.to_str().unwrap(), &roots).is_ok());
+        assert!(resolve_in_library(outside.join("notes.txt").to_str().unwrap(), &roots).is_err());
+        // ".." can't climb out of the library
+        let escaped = library.join("House/../../Private/notes.txt");
+        assert!(resolve_in_library(escaped.to_str().unwrap(), &roots).is_err());
+        assert!(resolve_in_library(library.join("missing.mp3").to_str().unwrap(), &roots).is_err());
+    }
+}
@@ -1,10 +1,20 @@
 // Tauri commands for genre operations
 
+use crate::audio::key::parse_camelot;
+use crate::commands::key_colors::KeyColors;
 use crate::commands::library::{AppState, TrackDTO};
-use crate::db::GenreDefinition;
+use crate::db::{GenreDefinition, GenreProfile, GenreSample};
 use serde::Serialize;
+use std::collections::HashMap;
 use tauri::State;
 
+/// Genres with fewer tracks get no profile
+const MIN_PROFILE_TRACKS: usize = 5;
+/// Share of BPMs cut off at each end of a profile's typical range
+const PROFILE_BPM_TRIM: f64 = 0.1;
+/// Keys kept per profile
+const PROFILE_KEYS: usize = 3;
+
 /// DTO for genre counts (for sidebar display)
 #[derive(Debug, Clone, Serialize)]
 pub struct GenreCountDTO {
@@ -21,6 +31,41 @@ pub struct GenreDefinitionDTO {
     pub sort_order: i32,
 }
 
+/// A group of genre spellings that look like the same genre
+#[derive(Debug, Clone, Serialize)]
+pub struct GenreMergeSuggestionDTO {
+    /// Spellings in the group with their track counts (most used first)
+    pub variants: Vec<GenreCountDTO>,
+    /// Proposed name to merge into (the most used spelling)
+    pub suggested_name: String,
+    pub total_tracks: i64,
+}
+
+/// Typical BPM range and keys of a genre in the library
+#[derive(Debug, Clone, Serialize)]
+pub struct GenreProfileDTO {
+    pub genre: String,
+    pub track_count: i64,
+    pub min_bpm: Option<f64>,
+    pub median_bpm: Option<f64>,
+    pub max_bpm: Option<f64>,
+    /// Camelot keys, most common first
+    pub common_keys: Vec<String>,
+}
+
+impl From<GenreProfile> for GenreProfileDTO {
+    fn from(profile: GenreProfile) -> Self {
+        GenreProfileDTO {
+            genre: profile.genre,
+            track_count: profile.track_count,
+            min_bpm: profile.min_bpm,
+            median_bpm: profile.median_bpm,
+            max_bpm: profile.max_bpm,
+            common_keys: profile.common_keys,
+        }
+    }
+}
+
 impl From<GenreDefinition> for GenreDefinitionDTO {
     fn from(def: GenreDefinition) -> Self {
         GenreDefinitionDTO {
@@ -73,12 +118,14 @@ pub fn get_tracks_by_genre(genre: String, state: State<AppState>) -> Result<Vec<
     let rows = db.get_tracks_by_genre(&genre)
         .map_err(|e| format!("Failed to get tracks by genre: {}", e))?;
 
+    let key_colors = KeyColors::from_settings(db);
     Ok(rows.into_iter().map(|(track, bpm, bpm_conf, key, key_conf)| {
         let mut dto = TrackDTO::from(track);
         dto.bpm = bpm;
         dto.bpm_confidence = bpm_conf;
         dto.musical_key = key;
         dto.key_confidence = key_conf;
+        dto.key_color = key_colors.resolve(dto.musical_key.as_deref());
         dto
     }).collect())
 }
@@ -136,3 +183,220 @@ pub fn bulk_set_genre(track_ids: Vec<i64>, genre: String, state: State<AppState>
 
     Ok(count as i64)
 }
+
+/// Merge several genre names into one (e.g. "Tech-House" + "tech house" -> "Tech House").
+/// Returns the number of tracks updated.
+#[tauri::command]
+pub fn merge_genres(from_names: Vec<String>, to_name: String, state: State<AppState>) -> Result<i64, String> {
+    let to_name = to_name.trim().to_string();
+    if to_name.is_empty() {
+        return Err("Target genre name cannot be empty".to_string());
+    }
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let count = db.merge_genres(&from_names, &to_name)
+        .map_err(|e| format!("Failed to merge genres: {}", e))?;
+
+    Ok(count as i64)
+}
+
+/// Suggest genre merges by clustering names that differ only in case,
+/// punctuation or spacing ("Tech-House", "tech house", "TECH HOUSE").
+#[tauri::command]
+pub fn suggest_genre_merges(state: State<AppState>) -> Result<Vec<GenreMergeSuggestionDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let counts = db.get_all_genres_with_counts()
+        .map_err(|e| format!("Failed to get genres: {}", e))?;
+
+    Ok(cluster_genres(counts))
+}
+
+/// Comparison key for a genre name: lowercase alphanumerics only, "&" read as "and"
+pub(crate) fn genre_merge_key(name: &str) -> String {
+    name.to_lowercase()
+        .replace('&', "and")
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+fn cluster_genres(counts: Vec<(String, i64)>) -> Vec<GenreMergeSuggestionDTO> {
+    let mut groups: HashMap<String, Vec<GenreCountDTO>> = HashMap::new();
+    for (genre, count) in counts {
+        let key = genre_merge_key(&genre);
+        if key.is_empty() {
+            continue;
+        }
+        groups.entry(key).or_default().push(GenreCountDTO { genre, count });
+    }
+
+    let mut suggestions: Vec<GenreMergeSuggestionDTO> = groups
+        .into_values()
+        .filter(|variants| variants.len() > 1)
+        .map(|mut variants| {
+            variants.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.genre.cmp(&b.genre)));
+            GenreMergeSuggestionDTO {
+                suggested_name: variants[0].genre.clone(),
+                total_tracks: variants.iter().map(|v| v.count).sum(),
+                variants,
+            }
+        })
+        .collect();
+
+    suggestions.sort_by_key(|s| std::cmp::Reverse(s.total_tracks));
+    suggestions
+}
+
+/// Learn a profile per genre from (genre, BPM, key) rows. Spellings of a genre are grouped
+/// like merge suggestions and the profile takes the most used one. The BPM range leaves
+/// out the slowest and fastest PROFILE_BPM_TRIM of tracks (half/double-time mistakes).
+fn learn_profiles(rows: Vec<GenreSample>) -> Vec<GenreProfile> {
+    struct Group {
+        spellings: HashMap<String, usize>,
+        tracks: usize,
+        bpms: Vec<f64>,
+        keys: HashMap<String, usize>,
+    }
+
+    let mut groups: HashMap<String, Group> = HashMap::new();
+    for (genre, bpm, key) in rows {
+        let merge_key = genre_merge_key(&genre);
+        if merge_key.is_empty() {
+            continue;
+        }
+        let group = groups.entry(merge_key).or_insert_with(|| Group {
+            spellings: HashMap::new(),
+            tracks: 0,
+            bpms: Vec::new(),
+            keys: HashMap::new(),
+        });
+        *group.spellings.entry(genre).or_default() += 1;
+        group.tracks += 1;
+        if let Some(bpm) = bpm.filter(|b| *b > 0.0) {
+            group.bpms.push(bpm);
+        }
+        if let Some((number, minor)) = key.as_deref().and_then(parse_camelot) {
+            *group.keys.entry(format!("{}{}", number, if minor { 'A' } else { 'B' })).or_default() += 1;
+        }
+    }
+
+    let mut profiles: Vec<GenreProfile> = groups
+        .into_values()
+        .filter(|g| g.tracks >= MIN_PROFILE_TRACKS)
+        .map(|mut g| {
+            let genre = g.spellings
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+                .map(|(name, _)| name)
+                .unwrap_or_default();
+
+            g.bpms.sort_by(f64::total_cmp);
+            let at = |share: f64| g.bpms[((g.bpms.len() - 1) as f64 * share).round() as usize];
+            let (min_bpm, median_bpm, max_bpm) = if g.bpms.is_empty() {
+                (None, None, None)
+            } else {
+                (
+                    Some(at(PROFILE_BPM_TRIM).floor()),
+                    Some((at(0.5) * 10.0).round() / 10.0),
+                    Some(at(1.0 - PROFILE_BPM_TRIM).ceil()),
+                )
+            };
+
+            let mut keys: Vec<(String, usize)> = g.keys.into_iter().collect();
+            keys.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+            GenreProfile {
+                genre,
+                track_count: g.tracks as i64,
+                min_bpm,
+                median_bpm,
+                max_bpm,
+                common_keys: keys.into_iter().take(PROFILE_KEYS).map(|(k, _)| k).collect(),
+            }
+        })
+        .collect();
+
+    profiles.sort_by(|a, b| b.track_count.cmp(&a.track_count).then_with(|| a.genre.cmp(&b.genre)));
+    profiles
+}
+
+/// Learn each genre's typical BPM range and keys from the library and store them
+/// (replacing the previous profiles). Genres with fewer than MIN_PROFILE_TRACKS tracks
+/// are skipped.
+#[tauri::command]
+pub fn learn_genre_profiles(state: State<AppState>) -> Result<Vec<GenreProfileDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let rows = db.get_genre_bpm_keys()
+        .map_err(|e| format!("Failed to get genres: {}", e))?;
+    let profiles = learn_profiles(rows);
+
+    let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+    db.replace_genre_profiles(&profiles)
+        .map_err(|e| format!("Failed to save genre profiles: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit genre profiles: {}", e))?;
+
+    eprintln!("[genre] Learned {} genre profiles", profiles.len());
+    Ok(profiles.into_iter().map(GenreProfileDTO::from).collect())
+}
+
+/// Stored genre profiles (from the last learn_genre_profiles), biggest genres first
+#[tauri::command]
+pub fn get_genre_profiles(state: State<AppState>) -> Result<Vec<GenreProfileDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let profiles = db.get_genre_profiles()
+        .map_err(|e| format!("Failed to get genre profiles: {}", e))?;
+    Ok(profiles.into_iter().map(GenreProfileDTO::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_genre_merge_key() {
+        assert_eq!(genre_merge_key("Tech-House"), "techhouse");
+        assert_eq!(genre_merge_key("TECH HOUSE"), "techhouse");
+        assert_eq!(genre_merge_key("Drum & Bass"), genre_merge_key("drum and bass"));
+    }
+
+    #[test]
+    fn test_cluster_genres() {
+        let suggestions = cluster_genres(vec![
+            ("Tech-House".to_string(), 2),
+            ("tech house".to_string(), 10),
+            ("TECH HOUSE".to_string(), 1),
+            ("Techno".to_string(), 5),
+        ]);
+
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].suggested_name, "tech house");
+        assert_eq!(suggestions[0].variants.len(), 3);
+        assert_eq!(suggestions[0].total_tracks, 13);
+    }
+
+    #[test]
+    fn test_learn_profiles() {
+        let mut rows: Vec<GenreSample> = (0..10)
+            .map(|i| ("Techno".to_string(), Some(128.0 + i as f64), Some(if i < 6 { "8a" } else { "5A" }.to_string())))
+            .collect();
+        rows.push(("techno".to_string(), Some(65.0), None)); // half-time detection, trimmed
+        rows.push(("Techno".to_string(), None, Some("Am".to_string()))); // not Camelot
+        rows.extend((0..4).map(|_| ("Ambient".to_string(), Some(90.0), None)));
+
+        let profiles = learn_profiles(rows);
+        assert_eq!(profiles.len(), 1); // Ambient has too few tracks
+        let techno = &profiles[0];
+        assert_eq!(techno.genre, "Techno");
+        assert_eq!(techno.track_count, 12);
+        assert_eq!((techno.min_bpm, techno.max_bpm), (Some(128.0), Some(136.0)));
+        assert_eq!(techno.median_bpm, Some(132.0));
+        assert_eq!(techno.common_keys, vec!["8A", "5A"]);
+    }
+}
@@ -0,0 +1,312 @@
+// Gigs and set preparation
+// A gig records a date, venue and slot length; playlists prepared for it (main set,
+// backups, an encore folder...) are linked to it. get_gig_overview summarizes how ready
+// the gig is: how each playlist's length compares with the slot, and how many of the
+// linked tracks are analyzed (BPM and key) and still present on disk.
+
+use crate::commands::library::{validate_date, AppState};
+use crate::db::{Database, Gig};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use tauri::State;
+
+/// Longest slot accepted
+const MAX_SET_LENGTH_MINUTES: i64 = 24 * 60;
+
+/// A gig as sent to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct GigDTO {
+    pub id: i64,
+    pub name: String,
+    pub venue: Option<String>,
+    /// 'YYYY-MM-DD'
+    pub date: String,
+    pub set_length_minutes: i64,
+    pub notes: Option<String>,
+    /// Linked playlists, in order
+    pub playlist_ids: Vec<i64>,
+}
+
+/// One linked playlist measured against the slot
+#[derive(Debug, Clone, Serialize)]
+pub struct GigPlaylistSummaryDTO {
+    pub playlist_id: i64,
+    pub name: String,
+    pub track_count: usize,
+    pub duration_ms: i64,
+    /// Tracks with both BPM and key
+    pub analyzed_tracks: usize,
+    /// Long enough to fill the slot on its own
+    pub covers_slot: bool,
+}
+
+/// Preparedness of a gig
+#[derive(Debug, Clone, Serialize)]
+pub struct GigOverviewDTO {
+    pub gig: GigDTO,
+    pub set_length_ms: i64,
+    pub playlists: Vec<GigPlaylistSummaryDTO>,
+    /// Distinct tracks across the linked playlists
+    pub total_tracks: usize,
+    pub total_duration_ms: i64,
+    pub analyzed_tracks: usize,
+    /// analyzed_tracks / total_tracks (0 when there are no tracks)
+    pub analysis_coverage: f64,
+    /// Linked tracks whose file is no longer on disk
+    pub missing_files: usize,
+    /// A playlist covers the slot, every track is analyzed and no file is missing
+    pub ready: bool,
+}
+
+/// Blank strings from the form mean "not set"
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+fn validate_gig(name: &str, date: &str, set_length_minutes: i64) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Gig name cannot be empty".to_string());
+    }
+    validate_date(date)?;
+    if !(1..=MAX_SET_LENGTH_MINUTES).contains(&set_length_minutes) {
+        return Err(format!("Set length must be 1-{} minutes", MAX_SET_LENGTH_MINUTES));
+    }
+    Ok(())
+}
+
+fn gig_dto(db: &Database, gig: Gig) -> rusqlite::Result<GigDTO> {
+    Ok(GigDTO {
+        playlist_ids: db.get_gig_playlist_ids(gig.id)?,
+        id: gig.id,
+        name: gig.name,
+        venue: gig.venue,
+        date: gig.date,
+        set_length_minutes: gig.set_length_minutes,
+        notes: gig.notes,
+    })
+}
+
+fn build_overview(db: &Database, gig: Gig) -> rusqlite::Result<GigOverviewDTO> {
+    let gig = gig_dto(db, gig)?;
+    let set_length_ms = gig.set_length_minutes * 60_000;
+
+    let mut playlists = Vec::with_capacity(gig.playlist_ids.len());
+    let mut seen = HashSet::new();
+    let (mut total_duration_ms, mut analyzed_tracks, mut missing_files) = (0, 0, 0);
+
+    for &playlist_id in &gig.playlist_ids {
+        let playlist = db.get_playlist(playlist_id)?;
+        let rows = db.get_playlist_tracks(playlist_id)?;
+
+        let mut summary = GigPlaylistSummaryDTO {
+            playlist_id,
+            name: playlist.name,
+            track_count: rows.len(),
+            duration_ms: 0,
+            analyzed_tracks: 0,
+            covers_slot: false,
+        };
+        for (track, bpm, _, key, _) in rows {
+            let duration_ms = track.duration_ms.unwrap_or(0) as i64;
+            let analyzed = bpm.is_some_and(|b| b > 0.0) && key.is_some();
+            summary.duration_ms += duration_ms;
+            if analyzed {
+                summary.analyzed_tracks += 1;
+            }
+
+            if track.id.is_some_and(|id| seen.insert(id)) {
+                total_duration_ms += duration_ms;
+                if analyzed {
+                    analyzed_tracks += 1;
+                }
+                if !Path::new(&track.file_path).exists() {
+                    missing_files += 1;
+                }
+            }
+        }
+        summary.covers_slot = summary.duration_ms >= set_length_ms;
+        playlists.push(summary);
+    }
+
+    let total_tracks = seen.len();
+    let analysis_coverage = if total_tracks > 0 {
+        analyzed_tracks as f64 / total_tracks as f64
+    } else {
+        0.0
+    };
+    let ready = playlists.iter().any(|p| p.covers_slot)
+        && analyzed_tracks == total_tracks
+        && missing_files == 0;
+
+    Ok(GigOverviewDTO {
+        gig,
+        set_length_ms,
+        playlists,
+        total_tracks,
+        total_duration_ms,
+        analyzed_tracks,
+        analysis_coverage,
+        missing_files,
+        ready,
+    })
+}
+
+/// All gigs, soonest first
+#[tauri::command]
+pub fn get_gigs(state: State<AppState>) -> Result<Vec<GigDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let gigs = db.get_gigs()
+        .map_err(|e| format!("Failed to get gigs: {}", e))?;
+    gigs.into_iter()
+        .map(|gig| gig_dto(db, gig))
+        .collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("Failed to get gig playlists: {}", e))
+}
+
+/// Create a gig on `date` ('YYYY-MM-DD') with a slot of `set_length_minutes`
+#[tauri::command]
+pub fn create_gig(
+    state: State<AppState>,
+    name: String,
+    venue: Option<String>,
+    date: String,
+    set_length_minutes: i64,
+    notes: Option<String>,
+) -> Result<GigDTO, String> {
+    validate_gig(&name, &date, set_length_minutes)?;
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let id = db
+        .create_gig(name.trim(), non_empty(venue).as_deref(), &date, set_length_minutes, non_empty(notes).as_deref())
+        .map_err(|e| format!("Failed to create gig: {}", e))?;
+    db.get_gig(id)
+        .and_then(|gig| gig_dto(db, gig))
+        .map_err(|e| format!("Failed to get gig: {}", e))
+}
+
+#[tauri::command]
+pub fn update_gig(
+    state: State<AppState>,
+    id: i64,
+    name: String,
+    venue: Option<String>,
+    date: String,
+    set_length_minutes: i64,
+    notes: Option<String>,
+) -> Result<GigDTO, String> {
+    validate_gig(&name, &date, set_length_minutes)?;
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.update_gig(id, name.trim(), non_empty(venue).as_deref(), &date, set_length_minutes, non_empty(notes).as_deref())
+        .map_err(|e| format!("Failed to update gig: {}", e))?;
+    db.get_gig(id)
+        .and_then(|gig| gig_dto(db, gig))
+        .map_err(|e| format!("Failed to get gig: {}", e))
+}
+
+/// Delete a gig. Its playlists are kept.
+#[tauri::command]
+pub fn delete_gig(state: State<AppState>, id: i64) -> Result<(), String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.delete_gig(id)
+        .map_err(|e| format!("Failed to delete gig: {}", e))
+}
+
+/// Link a playlist to a gig (appended after the gig's other playlists)
+#[tauri::command]
+pub fn link_playlist_to_gig(state: State<AppState>, gig_id: i64, playlist_id: i64) -> Result<(), String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.get_gig(gig_id)
+        .map_err(|e| format!("Failed to get gig: {}", e))?;
+    db.get_playlist(playlist_id)
+        .map_err(|e| format!("Failed to get playlist: {}", e))?;
+    db.link_gig_playlist(gig_id, playlist_id)
+        .map_err(|e| format!("Failed to link playlist: {}", e))
+}
+
+#[tauri::command]
+pub fn unlink_playlist_from_gig(state: State<AppState>, gig_id: i64, playlist_id: i64) -> Result<(), String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.unlink_gig_playlist(gig_id, playlist_id)
+        .map_err(|e| format!("Failed to unlink playlist: {}", e))
+}
+
+/// How prepared a gig is: playlist lengths against the slot, analysis coverage and
+/// missing files
+#[tauri::command]
+pub fn get_gig_overview(state: State<AppState>, id: i64) -> Result<GigOverviewDTO, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let gig = db.get_gig(id)
+        .map_err(|e| format!("Failed to get gig: {}", e))?;
+    build_overview(db, gig)
+        .map_err(|e| format!("Failed to build gig overview: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{create_test_track, Track};
+
+    fn track(path: &str, duration_ms: i32) -> Track {
+        Track {
+            file_path: path.to_string(),
+            file_hash: path.to_string(),
+            duration_ms: Some(duration_ms),
+            ..create_test_track()
+        }
+    }
+
+    #[test]
+    fn test_gig_overview() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let file = std::env::temp_dir().join("recodeck_gig_test.mp3");
+        std::fs::write(&file, b"").unwrap();
+        let present = db.create_track(&track(file.to_str().unwrap(), 40 * 60_000)).unwrap();
+        let missing = db.create_track(&track("/nonexistent/b.mp3", 30 * 60_000)).unwrap();
+        db.save_bpm_analysis(present, 124.0, 0.9).unwrap();
+        db.save_key_analysis(present, "8A", 0.8).unwrap();
+
+        let main = db.create_playlist("Main", "manual", None).unwrap();
+        db.add_track_to_playlist(main, present).unwrap();
+        db.add_track_to_playlist(main, missing).unwrap();
+        let backup = db.create_playlist("Backup", "manual", None).unwrap();
+        db.add_track_to_playlist(backup, present).unwrap();
+
+        let gig_id = db.create_gig("Residency", Some("Club"), "2026-11-06", 60, None).unwrap();
+        db.link_gig_playlist(gig_id, main).unwrap();
+        db.link_gig_playlist(gig_id, backup).unwrap();
+        db.link_gig_playlist(gig_id, main).unwrap();
+        assert_eq!(db.get_gig_playlist_ids(gig_id).unwrap(), vec![main, backup]);
+
+        let overview = build_overview(&db, db.get_gig(gig_id).unwrap()).unwrap();
+        assert_eq!(overview.set_length_ms, 3_600_000);
+        assert!(overview.playlists[0].covers_slot);
+        assert!(!overview.playlists[1].covers_slot);
+        assert_eq!(overview.playlists[0].analyzed_tracks, 1);
+        // The shared track counts once
+        assert_eq!(overview.total_tracks, 2);
+        assert_eq!(overview.total_duration_ms, 70 * 60_000);
+        assert_eq!(overview.analysis_coverage, 0.5);
+        assert_eq!(overview.missing_files, 1);
+        assert!(!overview.ready);
+
+        // Deleting a playlist drops its link; deleting the gig keeps the playlists
+        db.delete_playlist(main).unwrap();
+        assert_eq!(db.get_gig_playlist_ids(gig_id).unwrap(), vec![backup]);
+        db.delete_gig(gig_id).unwrap();
+        assert!(db.get_gigs().unwrap().is_empty());
+        assert!(db.get_playlist(backup).is_ok());
+
+        std::fs::remove_file(file).unwrap();
+    }
+}
@@ -0,0 +1,77 @@
+// Play history and popularity scoring
+// Plays are recorded when a track is loaded into the player. A background job
+// periodically recomputes each track's popularity score (recency-weighted plays
+// + rating - skip penalty), which get_tracks_paginated can sort by.
+// Plays stopped or replaced within the first 30 seconds are marked as skips
+// (see playback::finish_current_play).
+
+use crate::commands::library::{AppState, TrackDTO};
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{Manager, State};
+
+/// How often the background job recomputes popularity scores
+const SCORE_JOB_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Default number of tracks returned by get_most_skipped
+const DEFAULT_MOST_SKIPPED_LIMIT: i64 = 50;
+
+/// A track the user keeps passing over
+#[derive(Debug, Serialize)]
+pub struct SkippedTrackDTO {
+    pub track: TrackDTO,
+    pub skip_count: i64,
+    pub play_count: i64,
+}
+
+/// Tracks skipped most often while auditioning, worst first — candidates for purging.
+#[tauri::command]
+pub fn get_most_skipped(
+    state: State<AppState>,
+    limit: Option<i64>,
+) -> Result<Vec<SkippedTrackDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let rows = db
+        .get_most_skipped(limit.unwrap_or(DEFAULT_MOST_SKIPPED_LIMIT))
+        .map_err(|e| format!("Failed to get skipped tracks: {}", e))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(track, skip_count, play_count)| SkippedTrackDTO {
+            track: TrackDTO::from(track),
+            skip_count,
+            play_count,
+        })
+        .collect())
+}
+
+/// Recompute popularity scores now (e.g. after importing history). Returns the number of scored tracks.
+#[tauri::command]
+pub fn recompute_track_scores(state: State<AppState>) -> Result<usize, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.recompute_track_scores()
+        .map_err(|e| format!("Failed to recompute track scores: {}", e))
+}
+
+/// Background job: recompute scores once at startup, then every SCORE_JOB_INTERVAL.
+/// Spawned from init_database; runs for the lifetime of the app.
+pub async fn run_score_job(app_handle: tauri::AppHandle) {
+    loop {
+        {
+            let app_state = app_handle.state::<AppState>();
+            let db_lock = app_state.db.lock().unwrap();
+            if let Some(db) = db_lock.as_ref() {
+                match db.recompute_track_scores() {
+                    Ok(count) => eprintln!("[scores] Recomputed popularity for {} tracks", count),
+                    Err(e) => eprintln!("[scores] Failed to recompute popularity: {}", e),
+                }
+            }
+        } // lock released before sleeping
+
+        tokio::time::sleep(SCORE_JOB_INTERVAL).await;
+    }
+}
@@ -0,0 +1,267 @@
+// Global hotkeys — system-wide shortcuts for auditioning with the RecoDeck window in the
+// background: play/pause, next in queue, rate the playing track 1-5 and add it to the
+// working playlist. Ratings and playlist adds are done here (they need nothing but the
+// playing track); every fired hotkey is emitted as "hotkey-action" so the frontend can
+// drive the player (play/pause, next) or refresh and confirm (rate, add).
+// Like MIDI, hotkeys are started by the frontend once the database is open.
+
+use crate::commands::library::AppState;
+use crate::commands::playback::PlaybackState;
+use crate::commands::playlists::{ensure_editable, notify_playlists_changed};
+use crate::db::Database;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+/// Setting key for the hotkey bindings (JSON)
+pub const HOTKEYS_SETTING: &str = "global_hotkeys";
+/// Setting key for the playlist "add to working playlist" adds to
+pub const WORKING_PLAYLIST_SETTING: &str = "working_playlist_id";
+
+/// What a hotkey does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HotkeyAction {
+    PlayPause,
+    NextInQueue,
+    /// Rate the playing track 1-5 stars
+    Rate { stars: i32 },
+    /// Add the playing track to the working playlist
+    AddToWorkingPlaylist,
+}
+
+/// Binds one accelerator (e.g. "CommandOrControl+Alt+P") to an action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HotkeyBinding {
+    pub accelerator: String,
+    pub action: HotkeyAction,
+}
+
+/// Hotkey settings as edited in the settings screen
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HotkeyConfig {
+    pub bindings: Vec<HotkeyBinding>,
+    pub working_playlist_id: Option<i64>,
+}
+
+/// Payload of the "hotkey-action" event
+#[derive(Debug, Clone, Serialize)]
+pub struct HotkeyActionEvent {
+    pub action: HotkeyAction,
+    /// The playing track, for rate and add
+    pub track_id: Option<i64>,
+    /// Why a rate or add didn't happen (nothing playing, no working playlist, ...)
+    pub error: Option<String>,
+}
+
+/// Managed state: whether the hotkeys are registered with the OS
+#[derive(Default)]
+pub struct HotkeyState {
+    active: Mutex<bool>,
+}
+
+impl HotkeyState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Parse and check bindings: every accelerator valid, none bound twice, stars 1-5
+pub fn validate_bindings(bindings: &[HotkeyBinding]) -> Result<Vec<(Shortcut, HotkeyAction)>, String> {
+    let mut seen = HashSet::new();
+    bindings
+        .iter()
+        .map(|binding| {
+            if let HotkeyAction::Rate { stars } = binding.action {
+                if !(1..=5).contains(&stars) {
+                    return Err(format!("Invalid rating {} for '{}'. Must be 1-5", stars, binding.accelerator));
+                }
+            }
+            let shortcut: Shortcut = binding
+                .accelerator
+                .parse()
+                .map_err(|e| format!("Invalid hotkey '{}': {}", binding.accelerator, e))?;
+            if !seen.insert(shortcut) {
+                return Err(format!("Hotkey '{}' is bound more than once", binding.accelerator));
+            }
+            Ok((shortcut, binding.action))
+        })
+        .collect()
+}
+
+fn load_config(db: &Database) -> Result<HotkeyConfig, String> {
+    let bindings = db
+        .get_setting(HOTKEYS_SETTING)
+        .map_err(|e| format!("Failed to load hotkeys: {}", e))?
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    let working_playlist_id = db
+        .get_setting(WORKING_PLAYLIST_SETTING)
+        .map_err(|e| format!("Failed to load working playlist: {}", e))?
+        .and_then(|id| id.parse().ok());
+    Ok(HotkeyConfig { bindings, working_playlist_id })
+}
+
+/// Rate or add the playing track; returns it
+fn apply_to_current_track(app: &AppHandle, action: HotkeyAction) -> Result<Option<i64>, String> {
+    let track_id = *app.state::<PlaybackState>().current_track_id.lock().unwrap();
+    let track_id = match action {
+        HotkeyAction::Rate { .. } | HotkeyAction::AddToWorkingPlaylist => {
+            track_id.ok_or("Nothing is playing")?
+        }
+        _ => return Ok(track_id),
+    };
+
+    let state = app.state::<AppState>();
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    match action {
+        HotkeyAction::Rate { stars } => {
+            let mut track = db.get_track(track_id)
+                .map_err(|e| format!("Failed to get track: {}", e))?;
+            track.rating = stars;
+            db.update_track(&track)
+                .map_err(|e| format!("Failed to rate track: {}", e))?;
+        }
+        HotkeyAction::AddToWorkingPlaylist => {
+            let playlist_id = load_config(db)?
+                .working_playlist_id
+                .ok_or("No working playlist set")?;
+            ensure_editable(db, playlist_id)?;
+            db.add_track_to_playlist(playlist_id, track_id)
+                .map_err(|e| format!("Failed to add track: {}", e))?;
+            notify_playlists_changed(app);
+        }
+        _ => {}
+    }
+    Ok(Some(track_id))
+}
+
+fn fire(app: &AppHandle, action: HotkeyAction) {
+    let event = match apply_to_current_track(app, action) {
+        Ok(track_id) => HotkeyActionEvent { action, track_id, error: None },
+        Err(e) => {
+            eprintln!("[hotkeys] {:?} failed: {}", action, e);
+            HotkeyActionEvent { action, track_id: None, error: Some(e) }
+        }
+    };
+    let _ = app.emit("hotkey-action", &event);
+}
+
+/// Replace the registered shortcuts with `bindings`
+fn register(app: &AppHandle, bindings: &[HotkeyBinding]) -> Result<(), String> {
+    let shortcuts = validate_bindings(bindings)?;
+    let global = app.global_shortcut();
+    global.unregister_all()
+        .map_err(|e| format!("Failed to clear hotkeys: {}", e))?;
+
+    for ((shortcut, action), binding) in shortcuts.into_iter().zip(bindings) {
+        global
+            .on_shortcut(shortcut, move |app, _shortcut, event| {
+                if event.state == ShortcutState::Pressed {
+                    fire(app, action);
+                }
+            })
+            .map_err(|e| format!("Failed to register '{}' (in use by another app?): {}", binding.accelerator, e))?;
+    }
+    Ok(())
+}
+
+/// Register the saved hotkeys with the OS. Replaces any registered before.
+#[tauri::command]
+pub fn start_global_hotkeys(
+    app: AppHandle,
+    state: State<AppState>,
+    hotkey_state: State<HotkeyState>,
+) -> Result<usize, String> {
+    let config = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        load_config(db)?
+    };
+
+    register(&app, &config.bindings)?;
+    *hotkey_state.active.lock().unwrap() = true;
+    eprintln!("[hotkeys] Registered {} global hotkeys", config.bindings.len());
+    Ok(config.bindings.len())
+}
+
+/// Unregister all global hotkeys
+#[tauri::command]
+pub fn stop_global_hotkeys(app: AppHandle, hotkey_state: State<HotkeyState>) -> Result<(), String> {
+    app.global_shortcut()
+        .unregister_all()
+        .map_err(|e| format!("Failed to clear hotkeys: {}", e))?;
+    *hotkey_state.active.lock().unwrap() = false;
+    Ok(())
+}
+
+/// Get the hotkey bindings and working playlist
+#[tauri::command]
+pub fn get_hotkey_config(state: State<AppState>) -> Result<HotkeyConfig, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    load_config(db)
+}
+
+/// Save hotkey bindings and the working playlist. Re-registers the hotkeys if they're active.
+#[tauri::command]
+pub fn set_hotkey_config(
+    app: AppHandle,
+    state: State<AppState>,
+    hotkey_state: State<HotkeyState>,
+    config: HotkeyConfig,
+) -> Result<(), String> {
+    validate_bindings(&config.bindings)?;
+    let json = serde_json::to_string(&config.bindings)
+        .map_err(|e| format!("Failed to serialize hotkeys: {}", e))?;
+    {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        if let Some(playlist_id) = config.working_playlist_id {
+            ensure_editable(db, playlist_id)?;
+        }
+        db.set_setting(HOTKEYS_SETTING, &json)
+            .map_err(|e| format!("Failed to save hotkeys: {}", e))?;
+        db.set_setting(
+            WORKING_PLAYLIST_SETTING,
+            &config.working_playlist_id.map(|id| id.to_string()).unwrap_or_default(),
+        )
+        .map_err(|e| format!("Failed to save working playlist: {}", e))?;
+    }
+
+    if *hotkey_state.active.lock().unwrap() {
+        register(&app, &config.bindings)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(accelerator: &str, action: HotkeyAction) -> HotkeyBinding {
+        HotkeyBinding { accelerator: accelerator.to_string(), action }
+    }
+
+    #[test]
+    fn test_validate_bindings() {
+        let bindings = vec![
+            binding("CommandOrControl+Alt+P", HotkeyAction::PlayPause),
+            binding("CommandOrControl+Alt+5", HotkeyAction::Rate { stars: 5 }),
+            binding("CommandOrControl+Alt+A", HotkeyAction::AddToWorkingPlaylist),
+        ];
+        assert_eq!(validate_bindings(&bindings).unwrap().len(), 3);
+
+        let duplicate = vec![bindings[0].clone(), binding("CommandOrControl+Alt+P", HotkeyAction::NextInQueue)];
+        assert!(validate_bindings(&duplicate).unwrap_err().contains("more than once"));
+        assert!(validate_bindings(&[binding("CommandOrControl+Alt+6", HotkeyAction::Rate { stars: 6 })]).is_err());
+        assert!(validate_bindings(&[binding("", HotkeyAction::PlayPause)]).is_err());
+
+        let json = serde_json::to_string(&bindings[1]).unwrap();
+        assert_eq!(json, r#"{"accelerator":"CommandOrControl+Alt+5","action":{"type":"rate","stars":5}}"#);
+    }
+}
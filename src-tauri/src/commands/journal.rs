@@ -0,0 +1,199 @@
+// Operation journal: library changes made in the background, which nobody clicked and so
+// nobody can Ctrl+Z. For now that is the file watcher's handling of deleted files (marking
+// their tracks missing or removing them). Each entry keeps what undoing it needs: the
+// flagged track IDs, or full snapshots of the removed tracks (Database::snapshot_track).
+
+use crate::commands::library::AppState;
+use crate::commands::playlists::notify_playlists_changed;
+use crate::db::{Database, JournalEntry, MissingTrack};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::{AppHandle, Emitter, State};
+
+pub const MARK_MISSING_OPERATION: &str = "mark_missing";
+pub const REMOVE_TRACKS_OPERATION: &str = "remove_tracks";
+
+#[derive(Serialize, Deserialize)]
+struct MarkMissingPayload {
+    track_ids: Vec<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RemoveTracksPayload {
+    tracks: Vec<serde_json::Value>,
+}
+
+/// "Marked Track.mp3 missing" / "Marked 12 tracks missing"
+fn describe(verb: &str, tracks: &[&(i64, String)], suffix: &str) -> String {
+    match tracks {
+        [(_, file_path)] => {
+            let name = Path::new(file_path).file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+            format!("{} {}{}", verb, name, suffix)
+        }
+        _ => format!("{} {} tracks{}", verb, tracks.len(), suffix),
+    }
+}
+
+/// Flag tracks (ID, file path) missing and journal it. Returns how many weren't already.
+pub(crate) fn mark_tracks_missing(db: &Database, tracks: &[(i64, String)]) -> Result<usize, String> {
+    let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+    let ids: Vec<i64> = tracks.iter().map(|(id, _)| *id).collect();
+    let marked = db.mark_tracks_missing(&ids)
+        .map_err(|e| format!("Failed to mark tracks missing: {}", e))?;
+    if marked.is_empty() {
+        return Ok(0);
+    }
+    let marked_tracks: Vec<&(i64, String)> = tracks.iter().filter(|(id, _)| marked.contains(id)).collect();
+    let payload = serde_json::to_string(&MarkMissingPayload { track_ids: marked.clone() })
+        .map_err(|e| format!("Failed to serialize journal entry: {}", e))?;
+    db.add_journal_entry(MARK_MISSING_OPERATION, &describe("Marked", &marked_tracks, " missing"), &payload)
+        .map_err(|e| format!("Failed to write journal entry: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit: {}", e))?;
+    eprintln!("[journal] Marked {} tracks missing", marked.len());
+    Ok(marked.len())
+}
+
+/// Remove tracks (ID, file path) with all their data and journal it. Returns how many.
+pub(crate) fn remove_tracks(db: &Database, tracks: &[(i64, String)]) -> Result<usize, String> {
+    if tracks.is_empty() {
+        return Ok(0);
+    }
+    let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+    let mut snapshots = Vec::new();
+    for (track_id, _) in tracks {
+        snapshots.push(db.snapshot_track(*track_id)
+            .map_err(|e| format!("Failed to snapshot track {}: {}", track_id, e))?);
+        db.delete_track_data(*track_id)
+            .map_err(|e| format!("Failed to remove track {}: {}", track_id, e))?;
+    }
+    let payload = serde_json::to_string(&RemoveTracksPayload { tracks: snapshots })
+        .map_err(|e| format!("Failed to serialize journal entry: {}", e))?;
+    let removed: Vec<&(i64, String)> = tracks.iter().collect();
+    db.add_journal_entry(REMOVE_TRACKS_OPERATION, &describe("Removed", &removed, " (file deleted)"), &payload)
+        .map_err(|e| format!("Failed to write journal entry: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit: {}", e))?;
+    eprintln!("[journal] Removed {} tracks with deleted files", tracks.len());
+    Ok(tracks.len())
+}
+
+/// Revert a journal entry. Returns the number of tracks put back (or unflagged).
+pub(crate) fn undo_entry(db: &Database, entry: &JournalEntry) -> Result<usize, String> {
+    let invalid = |e: serde_json::Error| format!("Invalid journal entry {}: {}", entry.id, e);
+    match entry.operation.as_str() {
+        MARK_MISSING_OPERATION => {
+            let payload: MarkMissingPayload = serde_json::from_str(&entry.payload).map_err(invalid)?;
+            db.clear_tracks_missing(&payload.track_ids)
+                .map_err(|e| format!("Failed to clear missing flags: {}", e))
+        }
+        REMOVE_TRACKS_OPERATION => {
+            let payload: RemoveTracksPayload = serde_json::from_str(&entry.payload).map_err(invalid)?;
+            let mut restored = 0;
+            for snapshot in &payload.tracks {
+                let Some(track_id) = db.restore_track(snapshot)
+                    .map_err(|e| format!("Failed to restore track: {}", e))?
+                else {
+                    continue;
+                };
+                restored += 1;
+                // Usually still gone: flag it, so it doesn't come back looking healthy
+                let file_path = snapshot["tracks"][0]["file_path"].as_str().unwrap_or_default();
+                if !Path::new(file_path).exists() {
+                    db.mark_tracks_missing(&[track_id])
+                        .map_err(|e| format!("Failed to mark track missing: {}", e))?;
+                }
+            }
+            Ok(restored)
+        }
+        other => Err(format!("Cannot undo operation '{}'", other)),
+    }
+}
+
+/// A journal entry as sent to the frontend (without its payload)
+#[derive(Debug, Serialize)]
+pub struct JournalEntryDTO {
+    pub id: i64,
+    pub operation: String,
+    pub description: String,
+    pub created_at: String,
+    pub undone_at: Option<String>,
+}
+
+impl From<JournalEntry> for JournalEntryDTO {
+    fn from(entry: JournalEntry) -> Self {
+        JournalEntryDTO {
+            id: entry.id,
+            operation: entry.operation,
+            description: entry.description,
+            created_at: entry.created_at,
+            undone_at: entry.undone_at,
+        }
+    }
+}
+
+/// Newest journal entries first (default 50)
+#[tauri::command]
+pub fn get_operation_journal(state: State<AppState>, limit: Option<i64>) -> Result<Vec<JournalEntryDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let entries = db.get_journal_entries(limit.unwrap_or(50))
+        .map_err(|e| format!("Failed to get journal: {}", e))?;
+    Ok(entries.into_iter().map(JournalEntryDTO::from).collect())
+}
+
+/// Undo a journal entry: unflag the tracks it marked missing, or put back the tracks it
+/// removed (with cues, playlist entries, tags and, if still cached, analysis). Removed
+/// tracks whose path has been imported again meanwhile are skipped. Returns the number of
+/// tracks affected.
+#[tauri::command]
+pub fn undo_operation(app: AppHandle, state: State<AppState>, id: i64) -> Result<usize, String> {
+    let undone = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let entry = db.get_journal_entry(id)
+            .map_err(|e| format!("Journal entry {} not found: {}", id, e))?;
+        if entry.undone_at.is_some() {
+            return Err(format!("'{}' was already undone", entry.description));
+        }
+        let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        let undone = undo_entry(db, &entry)?;
+        db.set_journal_entry_undone(id)
+            .map_err(|e| format!("Failed to update journal: {}", e))?;
+        tx.commit().map_err(|e| format!("Failed to commit: {}", e))?;
+        undone
+    };
+    let _ = app.emit("library-changed", ());
+    notify_playlists_changed(&app);
+    Ok(undone)
+}
+
+/// A track whose file was seen deleted
+#[derive(Debug, Serialize)]
+pub struct MissingTrackDTO {
+    pub track_id: i64,
+    pub file_path: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub missing_since: String,
+}
+
+impl From<MissingTrack> for MissingTrackDTO {
+    fn from(track: MissingTrack) -> Self {
+        MissingTrackDTO {
+            track_id: track.track_id,
+            file_path: track.file_path,
+            title: track.title,
+            artist: track.artist,
+            missing_since: track.missing_since,
+        }
+    }
+}
+
+/// Tracks flagged missing by the file watcher, most recently first
+#[tauri::command]
+pub fn get_missing_tracks(state: State<AppState>) -> Result<Vec<MissingTrackDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let tracks = db.get_missing_tracks()
+        .map_err(|e| format!("Failed to get missing tracks: {}", e))?;
+    Ok(tracks.into_iter().map(MissingTrackDTO::from).collect())
+}
@@ -0,0 +1,178 @@
+// Key colors: a color per Camelot key, for key badges in the track list and the key
+// column of rendered tracklists. Defaults follow the Camelot wheel (one hue per hour,
+// minor keys a deeper shade than major); the setting stores only the user's changes,
+// so keys they never touched follow the defaults.
+
+use crate::audio::key::{key_to_camelot, parse_camelot};
+use crate::commands::library::AppState;
+use crate::db::Database;
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::State;
+
+/// Setting key: JSON object of changed colors, e.g. {"8A": "#ff0000"}
+pub const KEY_COLORS_SETTING: &str = "key_colors";
+
+/// Camelot wheel colors, in wheel order
+pub const DEFAULT_KEY_COLORS: [(&str, &str); 24] = [
+    ("1A", "#36e2c6"), ("1B", "#7ef1de"),
+    ("2A", "#36e270"), ("2B", "#7ef1a5"),
+    ("3A", "#7ee236"), ("3B", "#aef17e"),
+    ("4A", "#e2c636"), ("4B", "#f1de7e"),
+    ("5A", "#e28c36"), ("5B", "#f1b87e"),
+    ("6A", "#e25336"), ("6B", "#f1927e"),
+    ("7A", "#e23661"), ("7B", "#f17e9b"),
+    ("8A", "#e236b7"), ("8B", "#f17ed4"),
+    ("9A", "#b736e2"), ("9B", "#d47ef1"),
+    ("10A", "#6136e2"), ("10B", "#9b7ef1"),
+    ("11A", "#3670e2"), ("11B", "#7ea5f1"),
+    ("12A", "#36b7e2"), ("12B", "#7ed4f1"),
+];
+
+/// "8a", " 08A " -> "8A"
+fn normalize_key(key: &str) -> Option<String> {
+    let (number, is_minor) = parse_camelot(key.trim().trim_start_matches('0'))?;
+    Some(format!("{}{}", number, if is_minor { 'A' } else { 'B' }))
+}
+
+/// "#ABC" or "#aabbcc" -> "#aabbcc"
+fn normalize_color(color: &str) -> Option<String> {
+    let hex = color.trim().strip_prefix('#')?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    match hex.len() {
+        3 => Some(format!("#{}", hex.chars().flat_map(|c| [c, c]).collect::<String>().to_lowercase())),
+        6 => Some(format!("#{}", hex.to_lowercase())),
+        _ => None,
+    }
+}
+
+/// The key -> color mapping in effect: the defaults with the stored changes on top
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyColors {
+    colors: HashMap<String, String>,
+}
+
+impl Default for KeyColors {
+    fn default() -> Self {
+        KeyColors {
+            colors: DEFAULT_KEY_COLORS
+                .iter()
+                .map(|(key, color)| (key.to_string(), color.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl KeyColors {
+    /// Stored changes that aren't a valid key and color are ignored
+    pub fn from_settings(db: &Database) -> Self {
+        let changes: HashMap<String, String> = db.get_setting(KEY_COLORS_SETTING)
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        let mut key_colors = KeyColors::default();
+        for (key, color) in changes {
+            if let (Some(key), Some(color)) = (normalize_key(&key), normalize_color(&color)) {
+                key_colors.colors.insert(key, color);
+            }
+        }
+        key_colors
+    }
+
+    /// Color of a key in any notation key_to_camelot reads; None without a (known) key
+    pub fn resolve(&self, key: Option<&str>) -> Option<String> {
+        self.colors.get(&key_to_camelot(key?)?).cloned()
+    }
+}
+
+/// A key's color, as shown in the settings editor
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyColorDTO {
+    /// Camelot key, e.g. "8A"
+    pub key: String,
+    /// "#rrggbb"
+    pub color: String,
+    pub is_default: bool,
+}
+
+fn key_color_list(key_colors: &KeyColors) -> Vec<KeyColorDTO> {
+    DEFAULT_KEY_COLORS
+        .iter()
+        .map(|(key, default)| {
+            let color = key_colors.colors[*key].clone();
+            KeyColorDTO { key: key.to_string(), is_default: color == *default, color }
+        })
+        .collect()
+}
+
+/// Every key's color, in wheel order (1A, 1B, 2A, ...)
+#[tauri::command]
+pub fn get_key_colors(state: State<AppState>) -> Result<Vec<KeyColorDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    Ok(key_color_list(&KeyColors::from_settings(db)))
+}
+
+/// Replace the key colors: `colors` maps Camelot keys to "#rrggbb" (or "#rgb"); keys left
+/// out get their default color, so an empty map resets them all. Returns every key's color.
+#[tauri::command]
+pub fn set_key_colors(state: State<AppState>, colors: HashMap<String, String>) -> Result<Vec<KeyColorDTO>, String> {
+    let mut changes = HashMap::new();
+    for (key, color) in &colors {
+        let camelot = normalize_key(key).ok_or_else(|| format!("Not a Camelot key: {}", key))?;
+        let color = normalize_color(color)
+            .ok_or_else(|| format!("Invalid color for {}: {} (use #rrggbb)", camelot, color))?;
+        if KeyColors::default().colors[&camelot] != color {
+            changes.insert(camelot, color);
+        }
+    }
+    let json = serde_json::to_string(&changes)
+        .map_err(|e| format!("Failed to serialize key colors: {}", e))?;
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.set_setting(KEY_COLORS_SETTING, &json)
+        .map_err(|e| format!("Failed to save key colors: {}", e))?;
+    Ok(key_color_list(&KeyColors::from_settings(db)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize_key(" 08a ").as_deref(), Some("8A"));
+        assert_eq!(normalize_key("13A"), None);
+        assert_eq!(normalize_color("#ABC").as_deref(), Some("#aabbcc"));
+        assert_eq!(normalize_color("#12ab9F").as_deref(), Some("#12ab9f"));
+        assert_eq!(normalize_color("12ab9f"), None);
+        assert_eq!(normalize_color("#12ab9g"), None);
+    }
+
+    #[test]
+    fn test_key_colors_from_settings() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        assert_eq!(KeyColors::from_settings(&db), KeyColors::default());
+
+        db.set_setting(KEY_COLORS_SETTING, r##"{"8a": "#F00", "99Z": "#000000", "1A": "red"}"##).unwrap();
+        let key_colors = KeyColors::from_settings(&db);
+        assert_eq!(key_colors.resolve(Some("8A")).as_deref(), Some("#ff0000"));
+        // Other notations resolve through their Camelot key (Am = 8A, C = 8B)
+        assert_eq!(key_colors.resolve(Some("Am")).as_deref(), Some("#ff0000"));
+        assert_eq!(key_colors.resolve(Some("C")).as_deref(), Some("#f17ed4"));
+        assert_eq!(key_colors.resolve(Some("1A")).as_deref(), Some("#36e2c6"));
+        assert_eq!(key_colors.resolve(Some("")), None);
+        assert_eq!(key_colors.resolve(None), None);
+
+        let list = key_color_list(&key_colors);
+        assert_eq!(list.len(), 24);
+        assert_eq!(list[0].key, "1A");
+        assert!(!list[14].is_default && list[14].key == "8A");
+        assert_eq!(list.iter().filter(|c| !c.is_default).count(), 1);
+    }
+}
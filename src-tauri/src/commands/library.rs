@@ -1,19 +1,35 @@
 // Tauri commands for library management
 
-use crate::db::{Database, Track};
-use crate::scanner::{ScanResult, Scanner};
+use crate::ai::context_builder::SampledContext;
+use crate::artwork;
+use crate::audio::{self, mime::StreamMimeConfig};
+use crate::commands::analysis_queue::{auto_analyze_enabled, AnalysisQueueState};
+use crate::commands::key_colors::KeyColors;
+use crate::commands::playlists::notify_playlists_changed;
+use crate::commands::server::CompanionState;
+use crate::commands::track_cache::{self, TrackCache};
+use crate::db::{Database, DbMutex, DistinctColumn, Track, TrackWithAnalysis, SORT_IGNORE_ARTICLES_SETTING};
+use crate::scanner::{collapse_roots, DirectoryScan, ScanOptions, ScanResult, ScannedFile, Scanner, SCAN_BATCH_SIZE};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Mutex;
-use tauri::State;
+use tauri::{Emitter, Manager, State};
+
+/// Event sent when a track's file_path changes (payload: TrackRenamedEvent)
+pub const TRACK_RENAMED_EVENT: &str = "track-renamed";
 
 /// Application state with database connection
 pub struct AppState {
-    pub db: Mutex<Option<Database>>,
-    /// Pre-built AI context JSON, rebuilt on library changes
-    pub ai_context_cache: Mutex<Option<String>>,
+    /// Shared connection; lock() records the caller for the lock watchdog
+    pub db: DbMutex,
+    /// Pre-built AI context (the whole library, or a sample of a big one), rebuilt on library changes
+    pub ai_context_cache: Mutex<Option<SampledContext>>,
     /// Path to the SQLite database file (needed for companion server's own connection)
     pub db_path: Mutex<Option<String>>,
+    /// The whole library as TrackDTOs, for the read-only track views
+    pub track_cache: TrackCache,
 }
 
 /// Serializable track for frontend
@@ -27,6 +43,10 @@ pub struct TrackDTO {
     pub album: Option<String>,
     pub album_artist: Option<String>,
     pub track_number: Option<i32>,
+    pub disc_number: Option<i32>,
+    pub total_tracks: Option<i32>,
+    pub isrc: Option<String>,
+    pub catalog_number: Option<String>,
     pub year: Option<i32>,
     pub label: Option<String>,
     pub duration_ms: Option<i32>,
@@ -42,11 +62,67 @@ pub struct TrackDTO {
     pub artwork_path: Option<String>,
     pub genre: Option<String>,
     pub genre_source: Option<String>, // 'user', 'tag', 'ai'
+    pub energy_level: Option<i32>,
+    pub color: Option<String>,
     // Analysis fields (from track_analysis table via LEFT JOIN)
     pub bpm: Option<f64>,
     pub bpm_confidence: Option<f64>,
     pub musical_key: Option<String>,
     pub key_confidence: Option<f64>,
+    /// Color of musical_key (commands::key_colors), "#rrggbb"
+    pub key_color: Option<String>,
+}
+
+/// Every TrackDTO field, as named in JSON: what a list view can pick from with `fields`
+pub const TRACK_FIELDS: [&str; 34] = [
+    "id", "file_path", "file_hash", "title", "artist", "album", "album_artist",
+    "track_number", "disc_number", "total_tracks", "isrc", "catalog_number", "year", "label",
+    "duration_ms", "file_format", "bitrate", "sample_rate", "file_size", "date_added",
+    "date_modified", "play_count", "rating", "comment", "artwork_path", "genre", "genre_source",
+    "energy_level", "color", "bpm", "bpm_confidence", "musical_key", "key_confidence", "key_color",
+];
+
+/// Tracks for a list view: whole TrackDTOs, or only the fields the view asked for
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum TrackRows {
+    Full(Vec<TrackDTO>),
+    Selected(Vec<serde_json::Map<String, serde_json::Value>>),
+}
+
+/// Keep only `fields` (TRACK_FIELDS names) of each track; id is always kept, so rows
+/// can be told apart. None keeps every field.
+pub fn select_fields(tracks: Vec<TrackDTO>, fields: Option<&[String]>) -> Result<TrackRows, String> {
+    let Some(fields) = fields else {
+        return Ok(TrackRows::Full(tracks));
+    };
+    if let Some(unknown) = fields.iter().find(|f| !TRACK_FIELDS.contains(&f.as_str())) {
+        return Err(format!("Unknown track field: {}", unknown));
+    }
+    tracks
+        .into_iter()
+        .map(|track| match serde_json::to_value(track) {
+            Ok(serde_json::Value::Object(mut row)) => {
+                row.retain(|name, _| name == "id" || fields.contains(name));
+                Ok(row)
+            }
+            Ok(_) => Err("Track did not serialize to an object".to_string()),
+            Err(e) => Err(format!("Failed to serialize track: {}", e)),
+        })
+        .collect::<Result<_, _>>()
+        .map(TrackRows::Selected)
+}
+
+/// TrackDTO of a track and its analysis data, with the key's color
+pub(crate) fn dto_from_row(row: TrackWithAnalysis, key_colors: &KeyColors) -> TrackDTO {
+    let (track, bpm, bpm_conf, key, key_conf) = row;
+    let mut dto = TrackDTO::from(track);
+    dto.bpm = bpm;
+    dto.bpm_confidence = bpm_conf;
+    dto.musical_key = key;
+    dto.key_confidence = key_conf;
+    dto.key_color = key_colors.resolve(dto.musical_key.as_deref());
+    dto
 }
 
 impl From<Track> for TrackDTO {
@@ -60,6 +136,10 @@ impl From<Track> for TrackDTO {
             album: track.album,
             album_artist: track.album_artist,
             track_number: track.track_number,
+            disc_number: track.disc_number,
+            total_tracks: track.total_tracks,
+            isrc: track.isrc,
+            catalog_number: track.catalog_number,
             year: track.year,
             label: track.label,
             duration_ms: track.duration_ms,
@@ -75,10 +155,13 @@ impl From<Track> for TrackDTO {
             artwork_path: track.artwork_path,
             genre: track.genre,
             genre_source: track.genre_source,
+            energy_level: track.energy_level,
+            color: track.color,
             bpm: None,
             bpm_confidence: None,
             musical_key: None,
             key_confidence: None,
+            key_color: None,
         }
     }
 }
@@ -109,6 +192,12 @@ impl From<TrackDTO> for Track {
             artwork_path: dto.artwork_path,
             genre: dto.genre,
             genre_source: dto.genre_source,
+            energy_level: dto.energy_level,
+            color: dto.color,
+            disc_number: dto.disc_number,
+            total_tracks: dto.total_tracks,
+            isrc: dto.isrc,
+            catalog_number: dto.catalog_number,
             // Note: bpm/bpm_confidence are analysis-only fields, not stored on Track
         }
     }
@@ -119,8 +208,12 @@ impl From<TrackDTO> for Track {
 pub struct ScanResultDTO {
     pub total_files: usize,
     pub imported: usize,
+    pub updated: usize,
+    pub moved: usize,
     pub skipped: usize,
     pub errors: Vec<ScanErrorDTO>,
+    /// Symlinks not followed, or leading somewhere already scanned
+    pub symlinks_skipped: Vec<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -134,6 +227,8 @@ impl From<ScanResult> for ScanResultDTO {
         ScanResultDTO {
             total_files: result.total_files,
             imported: result.imported,
+            updated: result.updated,
+            moved: result.moved,
             skipped: result.skipped,
             errors: result
                 .errors
@@ -143,6 +238,11 @@ impl From<ScanResult> for ScanResultDTO {
                     error: e.error,
                 })
                 .collect(),
+            symlinks_skipped: result
+                .symlinks_skipped
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect(),
         }
     }
 }
@@ -171,23 +271,90 @@ pub fn init_database(
     db.run_migrations()
         .map_err(|e| format!("Failed to run migrations: {}", e))?;
 
+    // Batch jobs that left a marker behind were interrupted (crash / force quit).
+    // Work committed before the crash is kept; re-running the job resumes it.
+    for marker in db.get_batch_markers().unwrap_or_default() {
+        eprintln!(
+            "[init] Batch job '{}' was interrupted after {} items (started {})",
+            marker.job, marker.processed, marker.started_at
+        );
+    }
+
     // PERFORMANCE: Skip expensive maintenance operations on startup
     // Users can run these manually via settings if needed:
     // - remove_duplicate_tracks() - loads all tracks into memory
     // - normalize_all_file_paths() - loads all tracks into memory
     // Both are now exposed as manual commands: cleanup_duplicate_tracks, normalize_file_paths
 
+    audio::mime::configure(StreamMimeConfig::from_settings(&db));
+    audio::stream_access::refresh(&db);
+
     *state.db_path.lock().unwrap() = Some(db_path);
     *state.db.lock().unwrap() = Some(db);
+    // A new connection starts its change stamp over
+    state.track_cache.invalidate();
 
     // Auto-start companion server if enabled (non-blocking)
     tauri::async_runtime::spawn(
-        crate::commands::server::auto_start_companion(app_handle)
+        crate::commands::server::auto_start_companion(app_handle.clone())
+    );
+
+    // Keep popularity scores fresh in the background
+    tauri::async_runtime::spawn(
+        crate::commands::history::run_score_job(app_handle.clone())
     );
 
+    // Resume auto-analysis of tracks imported but not yet analyzed
+    std::thread::spawn(move || crate::commands::analysis_queue::queue_backlog(&app_handle));
+
     Ok("Database initialized successfully".to_string())
 }
 
+/// An unfinished scan/analysis batch from a previous session
+#[derive(Debug, Serialize)]
+pub struct InterruptedBatchDTO {
+    /// Job name: "scan:<path>", "analyze_bpm", "analyze_keys"
+    pub job: String,
+    pub detail: Option<String>,
+    pub processed: i64,
+    pub total: Option<i64>,
+    pub started_at: String,
+    pub updated_at: String,
+}
+
+/// List batch jobs that were interrupted before finishing, so the UI can offer to
+/// re-run them. Scans and analysis skip already-committed work, so re-running resumes.
+#[tauri::command]
+pub fn get_interrupted_batches(state: State<AppState>) -> Result<Vec<InterruptedBatchDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let markers = db.get_batch_markers()
+        .map_err(|e| format!("Failed to get batch markers: {}", e))?;
+
+    Ok(markers
+        .into_iter()
+        .map(|m| InterruptedBatchDTO {
+            job: m.job,
+            detail: m.detail,
+            processed: m.processed,
+            total: m.total,
+            started_at: m.started_at,
+            updated_at: m.updated_at,
+        })
+        .collect())
+}
+
+/// Dismiss an interrupted batch without re-running it
+#[tauri::command]
+pub fn dismiss_interrupted_batch(state: State<AppState>, job: String) -> Result<(), String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.finish_batch_marker(&job)
+        .map_err(|e| format!("Failed to dismiss batch: {}", e))
+}
+
 /// Get all tracks from the library (includes analysis data like BPM)
 /// WARNING: For large libraries (>1000 tracks), use get_tracks_paginated instead
 #[tauri::command]
@@ -195,38 +362,39 @@ pub fn get_all_tracks(state: State<AppState>) -> Result<Vec<TrackDTO>, String> {
     let db_lock = state.db.lock().unwrap();
     let db = db_lock.as_ref().ok_or("Database not initialized")?;
 
-    // Use LEFT JOIN query to include analysis data (BPM, key, etc.)
-    let rows = db.get_all_tracks_with_analysis()
-        .map_err(|e| format!("Failed to get tracks: {}", e))?;
-
-    Ok(rows.into_iter().map(|(track, bpm, bpm_conf, key, key_conf)| {
-        let mut dto = TrackDTO::from(track);
-        dto.bpm = bpm;
-        dto.bpm_confidence = bpm_conf;
-        dto.musical_key = key;
-        dto.key_confidence = key_conf;
-        dto
-    }).collect())
+    Ok(state.track_cache.tracks(db)?.to_vec())
 }
 
 /// Get paginated tracks from the library (includes analysis data like BPM)
 /// PERFORMANCE: Use this for initial load and large libraries
+/// sort_by: optional order — "score" sorts by popularity (see commands::history);
+/// "title", "artist" and "album" sort alphabetically (see get_sort_ignore_articles);
+/// pages in id order come from the track cache.
+/// fields: optional TRACK_FIELDS to return (see select_fields), for smaller payloads
 #[tauri::command]
-pub fn get_tracks_paginated(state: State<AppState>, limit: i64, offset: i64) -> Result<Vec<TrackDTO>, String> {
+pub fn get_tracks_paginated(
+    state: State<AppState>,
+    limit: i64,
+    offset: i64,
+    sort_by: Option<String>,
+    fields: Option<Vec<String>>,
+) -> Result<TrackRows, String> {
     let db_lock = state.db.lock().unwrap();
     let db = db_lock.as_ref().ok_or("Database not initialized")?;
 
-    let rows = db.get_tracks_with_analysis_paginated(limit, offset)
+    if sort_by.is_none() {
+        let tracks = state.track_cache.tracks(db)?;
+        let start = (offset.max(0) as usize).min(tracks.len());
+        let end = start.saturating_add(limit.max(0) as usize).min(tracks.len());
+        return select_fields(tracks[start..end].to_vec(), fields.as_deref());
+    }
+
+    let ignore_articles = db.get_setting(SORT_IGNORE_ARTICLES_SETTING).ok().flatten().as_deref() == Some("true");
+    let rows = db.get_tracks_with_analysis_paginated(limit, offset, sort_by.as_deref(), ignore_articles)
         .map_err(|e| format!("Failed to get tracks: {}", e))?;
 
-    Ok(rows.into_iter().map(|(track, bpm, bpm_conf, key, key_conf)| {
-        let mut dto = TrackDTO::from(track);
-        dto.bpm = bpm;
-        dto.bpm_confidence = bpm_conf;
-        dto.musical_key = key;
-        dto.key_confidence = key_conf;
-        dto
-    }).collect())
+    let key_colors = KeyColors::from_settings(db);
+    select_fields(rows.into_iter().map(|row| dto_from_row(row, &key_colors)).collect(), fields.as_deref())
 }
 
 /// Get a single track by ID
@@ -241,24 +409,234 @@ pub fn get_track(state: State<AppState>, id: i64) -> Result<TrackDTO, String> {
     Ok(TrackDTO::from(track))
 }
 
-/// Update a track
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackRenamedEvent {
+    pub track_id: i64,
+    pub old_path: String,
+    pub new_path: String,
+}
+
+/// Update a track. If its file_path changes, everything holding the old path is told
+/// (see propagate_track_rename).
 #[tauri::command]
-pub fn update_track(state: State<AppState>, track: TrackDTO) -> Result<(), String> {
+pub fn update_track(app: tauri::AppHandle, state: State<AppState>, track: TrackDTO) -> Result<(), String> {
+    let renamed = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+        let old_path = match track.id {
+            Some(id) => Some(db.get_track(id)
+                .map_err(|e| format!("Failed to get track {}: {}", id, e))?
+                .file_path),
+            None => None,
+        };
+        db.with_busy_retry(|db| db.update_track(&Track::from(track.clone())))
+            .map_err(|e| format!("Failed to update track: {}", e))?;
+
+        match (track.id, old_path) {
+            (Some(track_id), Some(old_path)) if old_path != track.file_path => Some(TrackRenamedEvent {
+                track_id,
+                old_path,
+                new_path: track.file_path,
+            }),
+            _ => None,
+        }
+    };
+
+    if let Some(event) = renamed {
+        propagate_track_rename(&app, &state, &event);
+    }
+    Ok(())
+}
+
+/// After a track's file moved: drop the AI context (rebuilt on next use), revoke companion
+/// stream tickets for the track, and tell the frontend so players and playlist views
+/// stop using the old path
+pub(crate) fn propagate_track_rename(app: &tauri::AppHandle, state: &AppState, event: &TrackRenamedEvent) {
+    eprintln!(
+        "[library] Track {} moved: {} -> {}",
+        event.track_id, event.old_path, event.new_path
+    );
+    if let Ok(mut cache) = state.ai_context_cache.lock() {
+        *cache = None;
+    }
+    state.track_cache.invalidate();
+    if let Some(running) = app.state::<CompanionState>().running_server.lock().unwrap().as_ref() {
+        let revoked = running.state.invalidate_track_tickets(event.track_id);
+        if revoked > 0 {
+            eprintln!("[library] Revoked {} stream ticket(s) for track {}", revoked, event.track_id);
+        }
+    }
+    let _ = app.emit(TRACK_RENAMED_EVENT, event);
+    notify_playlists_changed(app);
+}
+
+/// Set (or clear with null) a track's energy level (1-10)
+#[tauri::command]
+pub fn set_track_energy(
+    state: State<AppState>,
+    track_id: i64,
+    energy_level: Option<i32>,
+) -> Result<(), String> {
+    if let Some(level) = energy_level {
+        if !(1..=crate::scanner::MAX_ENERGY_LEVEL).contains(&level) {
+            return Err(format!(
+                "Invalid energy level {}. Must be between 1 and {}",
+                level,
+                crate::scanner::MAX_ENERGY_LEVEL
+            ));
+        }
+    }
+
     let db_lock = state.db.lock().unwrap();
     let db = db_lock.as_ref().ok_or("Database not initialized")?;
-    
-    db.update_track(&Track::from(track))
-        .map_err(|e| format!("Failed to update track: {}", e))
+
+    db.set_track_energy(track_id, energy_level)
+        .map_err(|e| format!("Failed to set energy level: {}", e))
+}
+
+/// Check a track color is "#RRGGBB"
+pub(crate) fn validate_color(color: &str) -> Result<(), String> {
+    let valid = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid color '{}'. Expected #RRGGBB", color))
+    }
+}
+
+/// Set or clear a track's color tag ("#RRGGBB")
+#[tauri::command]
+pub fn set_track_color(
+    state: State<AppState>,
+    track_id: i64,
+    color: Option<String>,
+) -> Result<(), String> {
+    if let Some(c) = &color {
+        validate_color(c)?;
+    }
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.set_track_color(track_id, color.as_deref())
+        .map_err(|e| format!("Failed to set color: {}", e))
+}
+
+/// Check a cooldown date is a real 'YYYY-MM-DD' date
+pub(crate) fn validate_date(date: &str) -> Result<(), String> {
+    let invalid = || format!("Invalid date '{}'. Expected YYYY-MM-DD", date);
+    let parts: Vec<&str> = date.split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        return Err(invalid());
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return Err(invalid());
+    }
+    let (Ok(year), Ok(month), Ok(day)) = (year.parse::<u32>(), month.parse::<u32>(), day.parse::<u32>()) else {
+        return Err(invalid());
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return Err(invalid()),
+    };
+    if day == 0 || day > days_in_month {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// Set or clear a track's cooldown: until `until` ('YYYY-MM-DD', exclusive) the track is
+/// left out of built and AI-generated playlists. Drops the AI context, which lists cooldowns.
+#[tauri::command]
+pub fn set_track_cooldown(
+    state: State<AppState>,
+    track_id: i64,
+    until: Option<String>,
+) -> Result<(), String> {
+    if let Some(date) = &until {
+        validate_date(date)?;
+    }
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.set_track_cooldown(track_id, until.as_deref())
+        .map_err(|e| format!("Failed to set cooldown: {}", e))?;
+    if let Ok(mut cache) = state.ai_context_cache.lock() {
+        *cache = None;
+    }
+    Ok(())
+}
+
+/// Tracks currently in cooldown, as track ID -> cooldown date
+#[tauri::command]
+pub fn get_track_cooldowns(state: State<AppState>) -> Result<HashMap<i64, String>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.get_active_cooldowns()
+        .map_err(|e| format!("Failed to get cooldowns: {}", e))
+}
+
+/// Look again for a cover image next to a track's file (e.g. after dropping a cover.jpg into
+/// the folder). Files with embedded art keep none. Returns the new artwork path.
+#[tauri::command]
+pub fn refresh_artwork(state: State<AppState>, track_id: i64) -> Result<Option<String>, String> {
+    let file_path = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        db.get_track(track_id)
+            .map_err(|e| format!("Failed to get track {}: {}", track_id, e))?
+            .file_path
+    };
+
+    let artwork_path = Scanner::detect_artwork(Path::new(&file_path))?;
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.set_track_artwork(track_id, artwork_path.as_deref())
+        .map_err(|e| format!("Failed to set artwork: {}", e))?;
+    if let Some(dir) = artwork::thumbnail_dir(db) {
+        artwork::remove_thumbnails(&dir, track_id);
+    }
+    Ok(artwork_path)
+}
+
+/// Path of a track's artwork thumbnail (`size` 64 or 256 px, JPEG), rendered and cached on
+/// first use. None if the track has no artwork.
+#[tauri::command]
+pub fn get_artwork_thumbnail(state: State<AppState>, track_id: i64, size: u32) -> Result<Option<String>, String> {
+    artwork::check_size(size)?;
+    let (track, dir) = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let track = db.get_track(track_id)
+            .map_err(|e| format!("Failed to get track {}: {}", track_id, e))?;
+        let dir = artwork::thumbnail_dir(db).ok_or("No thumbnail cache for this database")?;
+        (track, dir)
+    };
+
+    let path = artwork::thumbnail(&dir, track_id, &track.file_path, track.artwork_path.as_deref(), size)?;
+    Ok(path.map(|p| p.to_string_lossy().to_string()))
 }
 
 /// Delete a track
 #[tauri::command]
-pub fn delete_track(state: State<AppState>, id: i64) -> Result<(), String> {
+pub fn delete_track(app: tauri::AppHandle, state: State<AppState>, id: i64) -> Result<(), String> {
     let db_lock = state.db.lock().unwrap();
     let db = db_lock.as_ref().ok_or("Database not initialized")?;
     
     db.delete_track(id)
-        .map_err(|e| format!("Failed to delete track: {}", e))
+        .map_err(|e| format!("Failed to delete track: {}", e))?;
+    notify_playlists_changed(&app);
+    Ok(())
 }
 
 /// Count total tracks
@@ -271,89 +649,146 @@ pub fn count_tracks(state: State<AppState>) -> Result<i64, String> {
         .map_err(|e| format!("Failed to count tracks: {}", e))
 }
 
+/// One value of a track column and how many tracks have it
+#[derive(Debug, Clone, Serialize)]
+pub struct DistinctValueDTO {
+    pub value: String,
+    pub count: i64,
+}
+
+/// Distinct values with track counts for filter dropdowns, keyed by column:
+/// "genre", "key", "year", "label", "format". All columns when none are given.
+#[tauri::command]
+pub fn get_distinct_values(
+    state: State<AppState>,
+    columns: Option<Vec<String>>,
+) -> Result<HashMap<String, Vec<DistinctValueDTO>>, String> {
+    let columns = match columns {
+        Some(names) => names
+            .iter()
+            .map(|name| DistinctColumn::from_name(name).ok_or_else(|| format!("Unknown column: {}", name)))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => DistinctColumn::ALL.to_vec(),
+    };
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    columns
+        .into_iter()
+        .map(|column| {
+            let values = db.get_distinct_values(column)
+                .map_err(|e| format!("Failed to get {} values: {}", column.name(), e))?;
+            let values = values.into_iter().map(|(value, count)| DistinctValueDTO { value, count }).collect();
+            Ok((column.name().to_string(), values))
+        })
+        .collect()
+}
+
 /// Scan a directory and import tracks.
-/// Releases the DB mutex between file imports so other commands aren't blocked.
+/// Metadata is extracted without holding the DB mutex; writes happen in
+/// transactions of SCAN_BATCH_SIZE files, so a crash loses at most one batch
+/// and other commands are only blocked for the (short) commit.
+/// Files already in the library are skipped when their size and mtime are
+/// unchanged, and only re-read (and re-hashed) when they differ.
+/// New files with the same content as a track whose file is gone are treated as
+/// moves: the existing track's path is updated, keeping its analysis, cues,
+/// playlists and ratings.
 #[tauri::command]
-pub fn scan_directory(state: State<AppState>, path: String) -> Result<ScanResultDTO, String> {
-    // 1. Load known paths (brief lock)
-    let known_paths = {
+pub fn scan_directory(
+    app: tauri::AppHandle,
+    state: State<AppState>,
+    queue: State<AnalysisQueueState>,
+    path: String,
+) -> Result<ScanResultDTO, String> {
+    // 1. Load known files and the scan settings (brief lock)
+    let (known_files, options, auto_analyze) = {
         let db_lock = state.db.lock().unwrap();
         let db = db_lock.as_ref().ok_or("Database not initialized")?;
-        db.get_all_file_paths().map_err(|e| format!("Failed to get file paths: {}", e))?
+        let known_files = db.get_file_stats()
+            .map_err(|e| format!("Failed to get file paths: {}", e))?;
+        (known_files, ScanOptions::from_settings(db), auto_analyze_enabled(db))
     }; // lock released
 
     // 2. Scan filesystem for audio files (no lock needed)
-    let files = Scanner::scan_directory(Path::new(&path));
+    let DirectoryScan { files, symlinks_skipped } =
+        Scanner::scan_directory_with(Path::new(&path), options.symlink_mode(Path::new(&path)));
+    if !symlinks_skipped.is_empty() {
+        eprintln!("[scan_directory] Skipped {} symlinks in {}", symlinks_skipped.len(), path);
+    }
     let total_files = files.len();
-    let mut imported = 0;
-    let mut skipped = 0;
-    let mut errors = Vec::new();
+    let mut result = ScanResult {
+        total_files,
+        imported: 0,
+        updated: 0,
+        moved: 0,
+        skipped: 0,
+        errors: Vec::new(),
+        new_track_ids: Vec::new(),
+        symlinks_skipped,
+    };
+
+    // Recovery marker: survives a crash so the UI can offer to re-run the scan
+    let marker = format!("scan:{}", path);
+    {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        db.start_batch_marker(&marker, Some(&path), Some(total_files as i64))
+            .map_err(|e| format!("Failed to write scan marker: {}", e))?;
+    }
+
+    let mut batch: Vec<ScannedFile> = Vec::with_capacity(SCAN_BATCH_SIZE);
+    let mut processed = 0usize;
 
     for file_path in files {
-        // Skip files already in DB (no I/O needed)
+        processed += 1;
+
+        // 3. Stat known files, extract metadata + hash for new/changed ones
+        //    (no lock needed, this is the expensive part)
         let path_str = file_path.to_string_lossy().to_string();
-        if known_paths.contains(&path_str) {
-            skipped += 1;
-            continue;
+        match Scanner::scan_file(&file_path, known_files.get(&path_str), &options) {
+            Some(Ok(scanned)) => batch.push(scanned),
+            Some(Err(e)) => result.errors.push(crate::scanner::ScanError {
+                file_path: file_path.clone(),
+                error: e,
+            }),
+            None => result.skipped += 1,
         }
 
-        // 3. Extract metadata + hash (no lock needed, this is the expensive part)
-        let metadata = match Scanner::extract_metadata(&file_path) {
-            Ok(m) => m,
-            Err(e) => {
-                errors.push(crate::scanner::ScanError {
-                    file_path: file_path.clone(),
-                    error: e,
-                });
-                continue;
-            }
-        };
-
-        // 4. Insert into DB (brief lock per file)
-        let (track, tag_bpm, tag_genre) = metadata;
-        {
+        // 4. Write a full batch in one transaction (brief lock per batch)
+        if batch.len() >= SCAN_BATCH_SIZE {
             let db_lock = state.db.lock().unwrap();
             let db = db_lock.as_ref().ok_or("Database not initialized")?;
+            Scanner::write_batch(db, batch.drain(..), &mut result, Some((&marker, processed)))
+                .map_err(|e| format!("Failed to commit scan batch: {}", e))?;
+        }
+    }
 
-            // Check for duplicate hash
-            if track.file_hash != "unknown" {
-                if db.track_exists_with_hash(&track.file_hash).unwrap_or(false) {
-                    skipped += 1;
-                    continue;
-                }
-            }
+    {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        Scanner::write_batch(db, batch.drain(..), &mut result, Some((&marker, processed)))
+            .map_err(|e| format!("Failed to commit scan batch: {}", e))?;
+        db.finish_batch_marker(&marker)
+            .map_err(|e| format!("Failed to clear scan marker: {}", e))?;
+    }
 
-            match db.create_track(&track) {
-                Ok(id) => {
-                    if let Some(bpm) = tag_bpm {
-                        let _ = db.save_bpm_analysis(id, bpm, 0.99);
-                    }
-                    if let Some(genre) = tag_genre {
-                        let _ = db.save_track_genre(id, &genre, "tag");
-                    }
-                    imported += 1;
-                }
-                Err(e) => {
-                    let err_str = format!("{}", e);
-                    if err_str.contains("UNIQUE constraint") {
-                        skipped += 1;
-                    } else {
-                        errors.push(crate::scanner::ScanError {
-                            file_path: file_path.clone(),
-                            error: err_str,
-                        });
-                    }
-                }
-            }
-        } // lock released after each file
+    // 5. Keep folder-mirrored playlists in step with what's on disk
+    {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        if let Err(e) = crate::commands::playlists::sync_mirrored_folders(db) {
+            eprintln!("[scan_directory] {}", e);
+        }
     }
+    notify_playlists_changed(&app);
 
-    Ok(ScanResultDTO::from(ScanResult {
-        total_files,
-        imported,
-        skipped,
-        errors,
-    }))
+    // 6. Hand new imports to the background analysis queue
+    if auto_analyze {
+        queue.enqueue(&app, result.new_track_ids.iter().copied());
+    }
+
+    Ok(ScanResultDTO::from(result))
 }
 
 /// Search tracks by query string across all text fields
@@ -368,6 +803,17 @@ pub fn search_tracks(state: State<AppState>, query: String) -> Result<Vec<TrackD
     Ok(tracks.into_iter().map(TrackDTO::from).collect())
 }
 
+/// IDs of the tracks search_tracks finds, for filtering a list already loaded (the track
+/// table matches as you type, and adds the transliterated matches only the backend knows)
+#[tauri::command]
+pub fn search_track_ids(state: State<AppState>, query: String) -> Result<Vec<i64>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let tracks = db.search_tracks(&query)
+        .map_err(|e| format!("Failed to search tracks: {}", e))?;
+    Ok(tracks.into_iter().filter_map(|track| track.id).collect())
+}
+
 /// Get list of audio files in a directory (without importing)
 #[tauri::command]
 pub fn list_audio_files(path: String) -> Result<Vec<String>, String> {
@@ -445,26 +891,17 @@ pub fn list_subdirectories(state: State<AppState>, path: String) -> Result<Vec<F
 }
 
 /// Get tracks in a specific folder (by file_path prefix), includes analysis data
+/// fields: optional TRACK_FIELDS to return (see select_fields)
 #[tauri::command]
-pub fn get_tracks_in_folder(state: State<AppState>, path: String) -> Result<Vec<TrackDTO>, String> {
+pub fn get_tracks_in_folder(
+    state: State<AppState>,
+    path: String,
+    fields: Option<Vec<String>>,
+) -> Result<TrackRows, String> {
     let db_lock = state.db.lock().unwrap();
     let db = db_lock.as_ref().ok_or("Database not initialized")?;
 
-    let rows = db
-        .get_tracks_in_folder_with_analysis(&path)
-        .map_err(|e| format!("Failed to get tracks in folder: {}", e))?;
-
-    Ok(rows
-        .into_iter()
-        .map(|(track, bpm, bpm_conf, key, key_conf)| {
-            let mut dto = TrackDTO::from(track);
-            dto.bpm = bpm;
-            dto.bpm_confidence = bpm_conf;
-            dto.musical_key = key;
-            dto.key_confidence = key_conf;
-            dto
-        })
-        .collect())
+    select_fields(track_cache::in_folder(&state.track_cache.tracks(db)?, &path), fields.as_deref())
 }
 
 /// Count tracks in a specific folder (by file_path prefix)
@@ -478,26 +915,17 @@ pub fn count_tracks_in_folder(state: State<AppState>, path: String) -> Result<i6
 }
 
 /// Get tracks directly in a specific folder (non-recursive, shallow), includes analysis data
+/// fields: optional TRACK_FIELDS to return (see select_fields)
 #[tauri::command]
-pub fn get_tracks_in_folder_shallow(state: State<AppState>, path: String) -> Result<Vec<TrackDTO>, String> {
+pub fn get_tracks_in_folder_shallow(
+    state: State<AppState>,
+    path: String,
+    fields: Option<Vec<String>>,
+) -> Result<TrackRows, String> {
     let db_lock = state.db.lock().unwrap();
     let db = db_lock.as_ref().ok_or("Database not initialized")?;
 
-    let rows = db
-        .get_tracks_in_folder_shallow_with_analysis(&path)
-        .map_err(|e| format!("Failed to get tracks in folder (shallow): {}", e))?;
-
-    Ok(rows
-        .into_iter()
-        .map(|(track, bpm, bpm_conf, key, key_conf)| {
-            let mut dto = TrackDTO::from(track);
-            dto.bpm = bpm;
-            dto.bpm_confidence = bpm_conf;
-            dto.musical_key = key;
-            dto.key_confidence = key_conf;
-            dto
-        })
-        .collect())
+    select_fields(track_cache::in_folder_shallow(&state.track_cache.tracks(db)?, &path), fields.as_deref())
 }
 
 /// Count tracks directly in a specific folder (non-recursive, shallow)
@@ -510,52 +938,230 @@ pub fn count_tracks_in_folder_shallow(state: State<AppState>, path: String) -> R
         .map_err(|e| format!("Failed to count tracks (shallow): {}", e))
 }
 
+/// Paths listed in a stray track cleanup preview
+const STRAY_SAMPLE_SIZE: usize = 20;
+
+/// What cleanup_stray_tracks would remove
+#[derive(Debug, Serialize)]
+pub struct StrayTracksPreviewDTO {
+    /// Tracks outside every library folder
+    pub count: usize,
+    pub total_tracks: i64,
+    /// The first few of their paths
+    pub sample_paths: Vec<String>,
+    /// No library folders are configured, so every track counts as stray:
+    /// cleanup_stray_tracks then also needs `force`
+    pub requires_force: bool,
+    /// Pass to cleanup_stray_tracks; only valid while folders and strays stay the same
+    pub confirm_token: String,
+}
+
+fn library_folders(db: &Database) -> Result<Vec<String>, String> {
+    let folders_json = db.get_setting("library_folders")
+        .map_err(|e| format!("Failed to get library folders: {}", e))?;
+    Ok(match folders_json {
+        Some(json) => collapse_roots(&serde_json::from_str::<Vec<String>>(&json).unwrap_or_default()),
+        None => Vec::new(),
+    })
+}
+
+/// The cleanup preview for `library_folders`. The token is a digest of the folders and
+/// stray track IDs, so it stops matching once either changes.
+fn stray_tracks_preview(db: &Database, library_folders: &[String]) -> Result<StrayTracksPreviewDTO, String> {
+    let stray = db.get_tracks_not_in_folders(library_folders)
+        .map_err(|e| format!("Failed to find stray tracks: {}", e))?;
+    let total_tracks = db.count_tracks()
+        .map_err(|e| format!("Failed to count tracks: {}", e))?;
+
+    let mut hasher = Sha256::new();
+    for folder in library_folders {
+        hasher.update(folder.as_bytes());
+        hasher.update([0]);
+    }
+    for (id, _) in &stray {
+        hasher.update(id.to_le_bytes());
+    }
+    let confirm_token = hasher.finalize()[..8].iter().map(|b| format!("{:02x}", b)).collect();
+
+    Ok(StrayTracksPreviewDTO {
+        count: stray.len(),
+        total_tracks,
+        sample_paths: stray.iter().take(STRAY_SAMPLE_SIZE).map(|(_, path)| path.clone()).collect(),
+        requires_force: library_folders.is_empty() && !stray.is_empty(),
+        confirm_token,
+    })
+}
+
+/// Preview cleanup_stray_tracks: how many tracks lie outside every configured library
+/// folder, a sample of their paths, and the token needed to remove them. Changes nothing.
+#[tauri::command]
+pub fn preview_stray_tracks_cleanup(state: State<AppState>) -> Result<StrayTracksPreviewDTO, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    stray_tracks_preview(db, &library_folders(db)?)
+}
+
 /// Clean up tracks that are not in any of the configured library folders.
 /// Removes stray files (like Viber voice messages) that were accidentally imported.
-/// If no folders are configured, removes ALL tracks.
+/// `confirm_token` comes from preview_stray_tracks_cleanup; if the folders or the stray
+/// tracks changed since, nothing is removed. With no folders configured every track is
+/// stray, which is only removed with `force`.
 /// Returns the number of deleted tracks.
 #[tauri::command]
-pub fn cleanup_stray_tracks(state: State<AppState>) -> Result<usize, String> {
+pub fn cleanup_stray_tracks(state: State<AppState>, confirm_token: String, force: Option<bool>) -> Result<usize, String> {
     let db_lock = state.db.lock().unwrap();
     let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    cleanup_stray_tracks_confirmed(db, &confirm_token, force.unwrap_or(false))
+}
 
-    // Get library folders from settings
-    let folders_json = db.get_setting("library_folders")
-        .map_err(|e| format!("Failed to get library folders: {}", e))?;
+fn cleanup_stray_tracks_confirmed(db: &Database, confirm_token: &str, force: bool) -> Result<usize, String> {
+    let library_folders = library_folders(db)?;
+    let preview = stray_tracks_preview(db, &library_folders)?;
+    if preview.confirm_token != confirm_token {
+        return Err("The library changed since the cleanup was previewed; preview it again".to_string());
+    }
+    if preview.requires_force && !force {
+        return Err(format!(
+            "No library folders are configured: refusing to remove all {} tracks without force",
+            preview.count
+        ));
+    }
+    if preview.count == 0 {
+        return Ok(0);
+    }
+    let removed = db.remove_tracks_not_in_folders(&library_folders)
+        .map_err(|e| format!("Failed to cleanup tracks: {}", e))?;
+    eprintln!("[library] Removed {} stray tracks", removed);
+    Ok(removed)
+}
 
-    let library_folders: Vec<String> = match folders_json {
-        Some(json) => serde_json::from_str(&json).unwrap_or_default(),
-        None => Vec::new(),
-    };
+/// A track with its BPM/key analysis filled in; None if it doesn't exist
+pub(crate) fn track_with_analysis(db: &Database, track_id: i64) -> Option<TrackDTO> {
+    let track = db.get_track(track_id).ok()?;
+    let mut dto = TrackDTO::from(track);
+    if let Ok(Some(analysis)) = db.get_track_analysis(track_id) {
+        dto.bpm = analysis.bpm;
+        dto.bpm_confidence = analysis.bpm_confidence;
+        dto.musical_key = analysis.musical_key;
+        dto.key_confidence = analysis.key_confidence;
+        dto.key_color = KeyColors::from_settings(db).resolve(dto.musical_key.as_deref());
+    }
+    Some(dto)
+}
+
+/// One copy in a duplicate cluster
+#[derive(Debug, Serialize)]
+pub struct DuplicateCandidateDTO {
+    pub track: TrackDTO,
+    pub playlist_count: i64,
+}
+
+/// Tracks that look like copies of each other
+#[derive(Debug, Serialize)]
+pub struct DuplicateClusterDTO {
+    /// Why they were grouped: "hash" (same content) and/or "filename_size"
+    pub reasons: Vec<String>,
+    pub tracks: Vec<DuplicateCandidateDTO>,
+}
+
+/// Find groups of duplicate tracks without deleting anything, so the user can pick
+/// which copy to keep (see resolve_duplicates). Groups by content hash and by
+/// filename + size; tracks scanned in lazy hash mode are hashed first.
+#[tauri::command]
+pub fn find_duplicate_clusters(state: State<AppState>) -> Result<Vec<DuplicateClusterDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    Scanner::hash_pending(db)
+        .map_err(|e| format!("Failed to hash tracks: {}", e))?;
+
+    let groups = db.find_duplicate_groups()
+        .map_err(|e| format!("Failed to find duplicates: {}", e))?;
+
+    Ok(groups
+        .into_iter()
+        .map(|(reasons, ids)| DuplicateClusterDTO {
+            reasons,
+            tracks: ids
+                .into_iter()
+                .filter_map(|id| {
+                    Some(DuplicateCandidateDTO {
+                        track: track_with_analysis(db, id)?,
+                        playlist_count: db.count_playlists_with_track(id).unwrap_or(0),
+                    })
+                })
+                .collect(),
+        })
+        .collect())
+}
+
+/// Keep one copy of a duplicate and delete the others (database entries only; files
+/// are left alone). Playlist entries, play history, play counts and the best rating move
+/// to the kept track, as does analysis it lacks; with `merge_metadata`, its empty tag
+/// fields are filled from the removed copies (lowest ID first). All or nothing.
+#[tauri::command]
+pub fn resolve_duplicates(
+    app: tauri::AppHandle,
+    state: State<AppState>,
+    keep_id: i64,
+    remove_ids: Vec<i64>,
+    merge_metadata: bool,
+) -> Result<TrackDTO, String> {
+    if remove_ids.contains(&keep_id) {
+        return Err("The kept track can't also be removed".to_string());
+    }
 
-    // If no folders configured, remove ALL tracks (since none are valid)
-    // Otherwise, remove tracks not in configured folders
-    db.remove_tracks_not_in_folders(&library_folders)
-        .map_err(|e| format!("Failed to cleanup tracks: {}", e))
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+    db.get_track(keep_id)
+        .map_err(|e| format!("Failed to get track {}: {}", keep_id, e))?;
+    let mut remove_ids = remove_ids;
+    remove_ids.sort();
+    remove_ids.dedup();
+    for &remove_id in &remove_ids {
+        db.get_track(remove_id)
+            .map_err(|e| format!("Failed to get track {}: {}", remove_id, e))?;
+        db.merge_duplicate_track(keep_id, remove_id, merge_metadata)
+            .map_err(|e| format!("Failed to merge track {}: {}", remove_id, e))?;
+    }
+    tx.commit().map_err(|e| format!("Failed to commit duplicate resolution: {}", e))?;
+    notify_playlists_changed(&app);
+
+    track_with_analysis(db, keep_id).ok_or_else(|| format!("Track {} not found", keep_id))
 }
 
 /// Remove duplicate tracks that share the same file content (same hash) or same filename.
 /// Keeps the track with the lowest ID (earliest import) for each duplicate group.
+/// Tracks scanned in lazy hash mode are hashed first.
 /// Returns the number of deleted duplicates.
 #[tauri::command]
-pub fn cleanup_duplicate_tracks(state: State<AppState>) -> Result<usize, String> {
+pub fn cleanup_duplicate_tracks(app: tauri::AppHandle, state: State<AppState>) -> Result<usize, String> {
     let db_lock = state.db.lock().unwrap();
     let db = db_lock.as_ref().ok_or("Database not initialized")?;
 
-    db.remove_duplicate_tracks()
-        .map_err(|e| format!("Failed to cleanup duplicates: {}", e))
+    Scanner::hash_pending(db)
+        .map_err(|e| format!("Failed to hash tracks: {}", e))?;
+
+    let removed = db.remove_duplicate_tracks()
+        .map_err(|e| format!("Failed to cleanup duplicates: {}", e))?;
+    notify_playlists_changed(&app);
+    Ok(removed)
 }
 
 /// Normalize all file paths in the database (remove double slashes, trailing slashes).
 /// Fixes paths that were stored incorrectly during scanning.
 /// Returns the number of tracks updated.
 #[tauri::command]
-pub fn normalize_file_paths(state: State<AppState>) -> Result<usize, String> {
+pub fn normalize_file_paths(app: tauri::AppHandle, state: State<AppState>) -> Result<usize, String> {
     let db_lock = state.db.lock().unwrap();
     let db = db_lock.as_ref().ok_or("Database not initialized")?;
 
-    db.normalize_all_file_paths()
-        .map_err(|e| format!("Failed to normalize file paths: {}", e))
+    let updated = db.normalize_all_file_paths()
+        .map_err(|e| format!("Failed to normalize file paths: {}", e))?;
+    notify_playlists_changed(&app);
+    Ok(updated)
 }
 
 /// Debug info about tracks in database
@@ -592,3 +1198,74 @@ pub fn get_debug_tracks(state: State<AppState>) -> Result<Vec<DebugTrackInfo>, S
         })
     }).collect())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_track;
+
+    fn add_track(db: &Database, path: &str) {
+        let track = Track { file_path: path.to_string(), file_hash: path.to_string(), ..create_test_track() };
+        db.create_track(&track).unwrap();
+    }
+
+    #[test]
+    fn test_cleanup_stray_tracks_needs_confirmation() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        add_track(&db, "/Music/a.mp3");
+        add_track(&db, "/Downloads/voice.m4a");
+
+        // No folders: everything is stray, and only goes with force
+        let preview = stray_tracks_preview(&db, &library_folders(&db).unwrap()).unwrap();
+        assert_eq!((preview.count, preview.total_tracks, preview.requires_force), (2, 2, true));
+        assert!(cleanup_stray_tracks_confirmed(&db, &preview.confirm_token, false).is_err());
+
+        db.set_setting("library_folders", r#"["/Music"]"#).unwrap();
+        // The old token no longer matches
+        assert!(cleanup_stray_tracks_confirmed(&db, &preview.confirm_token, true).is_err());
+        let preview = stray_tracks_preview(&db, &library_folders(&db).unwrap()).unwrap();
+        assert_eq!(preview.sample_paths, ["/Downloads/voice.m4a"]);
+        assert!(!preview.requires_force);
+
+        // Nor once the strays change
+        add_track(&db, "/Downloads/other.mp3");
+        assert!(cleanup_stray_tracks_confirmed(&db, &preview.confirm_token, false).is_err());
+        let preview = stray_tracks_preview(&db, &library_folders(&db).unwrap()).unwrap();
+        assert_eq!(cleanup_stray_tracks_confirmed(&db, &preview.confirm_token, false), Ok(2));
+        assert_eq!(db.count_tracks().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_select_fields() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        add_track(&db, "/music/a.mp3");
+        let key_colors = KeyColors::default();
+        let tracks: Vec<TrackDTO> = db.get_all_tracks_with_analysis().unwrap()
+            .into_iter()
+            .map(|row| dto_from_row(row, &key_colors))
+            .collect();
+
+        // TRACK_FIELDS is exactly what a TrackDTO serializes to
+        let full = serde_json::to_value(&tracks[0]).unwrap();
+        let mut names: Vec<&str> = full.as_object().unwrap().keys().map(String::as_str).collect();
+        let mut expected = TRACK_FIELDS.to_vec();
+        names.sort();
+        expected.sort();
+        assert_eq!(names, expected);
+
+        assert!(matches!(select_fields(tracks.clone(), None).unwrap(), TrackRows::Full(_)));
+        let fields = vec!["file_path".to_string(), "bpm".to_string()];
+        let TrackRows::Selected(rows) = select_fields(tracks.clone(), Some(&fields)).unwrap() else {
+            panic!("expected selected fields");
+        };
+        let mut keys: Vec<&String> = rows[0].keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["bpm", "file_path", "id"]);
+        assert_eq!(rows[0]["file_path"], "/music/a.mp3");
+
+        let err = select_fields(tracks, Some(&["bpmm".to_string()])).unwrap_err();
+        assert_eq!(err, "Unknown track field: bpmm");
+    }
+}
@@ -0,0 +1,313 @@
+// System media controls — shows the auditioned track in the OS media session (macOS
+// Now Playing, MPRIS on Linux, SMTC on Windows) so media keys and the system media widget
+// can control it. The session follows the "player-state"/"player-position" events the
+// player already emits for the mini player; OS commands are sent back as "player-action"
+// events, so they take the same play/pause/seek path (and PlaybackState) as the player's
+// own buttons.
+// Built only with the `media-controls` cargo feature; without it start() does nothing.
+
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+use tauri::AppHandle;
+
+#[cfg(feature = "media-controls")]
+use souvlaki::{MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig, SeekDirection};
+#[cfg(feature = "media-controls")]
+use std::{cell::RefCell, sync::{Arc, Mutex}, time::Duration};
+#[cfg(feature = "media-controls")]
+use tauri::{Emitter, Listener, Manager};
+
+/// How far a plain "seek forward/backward" from the OS jumps
+const SEEK_STEP_MS: f64 = 10_000.0;
+/// Position drift (vs. elapsed time) treated as a seek, so the OS timeline is corrected
+const SEEK_DETECT_MS: f64 = 1_500.0;
+
+/// Track fields of the "player-state" payload (a frontend Track)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct NowPlayingTrack {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub artwork_path: Option<String>,
+    pub duration_ms: Option<f64>,
+}
+
+/// Payload of the "player-state" event
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerStatePayload {
+    pub current_track: Option<NowPlayingTrack>,
+    #[serde(default)]
+    pub is_playing: bool,
+    #[serde(default)]
+    pub position: f64,
+}
+
+/// Payload of the "player-position" event
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlayerPositionPayload {
+    pub position: f64,
+}
+
+/// A "player-action" event, as sent by the mini player
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlayerAction {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// Position in ms for "seek"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<u64>,
+}
+
+impl PlayerAction {
+    fn new(kind: &'static str) -> Self {
+        Self { kind, payload: None }
+    }
+
+    fn seek(position_ms: f64) -> Self {
+        Self { kind: "seek", payload: Some(position_ms.max(0.0) as u64) }
+    }
+}
+
+/// What the session knows about the player
+#[derive(Debug, Default)]
+pub struct SessionState {
+    pub track: Option<NowPlayingTrack>,
+    pub playing: bool,
+    pub position_ms: f64,
+    /// When position_ms was reported
+    pub reported_at: Option<Instant>,
+}
+
+impl SessionState {
+    /// Current position: the last report plus the time played since
+    pub fn estimated_position(&self) -> f64 {
+        match (self.playing, self.reported_at) {
+            (true, Some(at)) => self.position_ms + at.elapsed().as_millis() as f64,
+            _ => self.position_ms,
+        }
+    }
+
+    fn report_position(&mut self, position_ms: f64) {
+        self.position_ms = position_ms;
+        self.reported_at = Some(Instant::now());
+    }
+}
+
+/// Map an OS media command to a player action. Play/pause/stop only toggle when the
+/// player isn't already in that state.
+#[cfg(feature = "media-controls")]
+pub fn map_event(event: &MediaControlEvent, session: &SessionState) -> Option<PlayerAction> {
+    let offset = |direction: &SeekDirection, ms: f64| match direction {
+        SeekDirection::Forward => session.estimated_position() + ms,
+        SeekDirection::Backward => session.estimated_position() - ms,
+    };
+    match event {
+        MediaControlEvent::Toggle => Some(PlayerAction::new("playPause")),
+        MediaControlEvent::Play => (!session.playing).then(|| PlayerAction::new("playPause")),
+        MediaControlEvent::Pause | MediaControlEvent::Stop => {
+            session.playing.then(|| PlayerAction::new("playPause"))
+        }
+        MediaControlEvent::Next => Some(PlayerAction::new("next")),
+        MediaControlEvent::Previous => Some(PlayerAction::new("previous")),
+        MediaControlEvent::Seek(direction) => Some(PlayerAction::seek(offset(direction, SEEK_STEP_MS))),
+        MediaControlEvent::SeekBy(direction, by) => {
+            Some(PlayerAction::seek(offset(direction, by.as_millis() as f64)))
+        }
+        MediaControlEvent::SetPosition(MediaPosition(to)) => Some(PlayerAction::seek(to.as_millis() as f64)),
+        _ => None,
+    }
+}
+
+/// file:// URL for the artwork, as the OS media sessions expect
+fn file_url(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    if path.starts_with('/') {
+        format!("file://{}", path)
+    } else {
+        format!("file:///{}", path)
+    }
+}
+
+#[cfg(feature = "media-controls")]
+thread_local! {
+    /// The controls live on the main thread (required on macOS and Windows)
+    static CONTROLS: RefCell<Option<MediaControls>> = const { RefCell::new(None) };
+}
+
+/// Run `update` on the main thread's controls
+#[cfg(feature = "media-controls")]
+fn with_controls(
+    app: &AppHandle,
+    update: impl FnOnce(&mut MediaControls) -> Result<(), souvlaki::Error> + Send + 'static,
+) {
+    let _ = app.run_on_main_thread(move || {
+        CONTROLS.with(|controls| {
+            if let Some(controls) = controls.borrow_mut().as_mut() {
+                if let Err(e) = update(controls) {
+                    eprintln!("[media] Failed to update media session: {:?}", e);
+                }
+            }
+        })
+    });
+}
+
+#[cfg(feature = "media-controls")]
+fn publish_playback(app: &AppHandle, session: &SessionState) {
+    let progress = Some(MediaPosition(Duration::from_millis(session.estimated_position().max(0.0) as u64)));
+    let playback = match (&session.track, session.playing) {
+        (None, _) => MediaPlayback::Stopped,
+        (Some(_), true) => MediaPlayback::Playing { progress },
+        (Some(_), false) => MediaPlayback::Paused { progress },
+    };
+    with_controls(app, move |controls| controls.set_playback(playback));
+}
+
+#[cfg(feature = "media-controls")]
+fn publish_metadata(app: &AppHandle, track: NowPlayingTrack) {
+    with_controls(app, move |controls| {
+        let cover_url = track.artwork_path.as_deref().map(file_url);
+        controls.set_metadata(MediaMetadata {
+            title: track.title.as_deref(),
+            artist: track.artist.as_deref(),
+            album: track.album.as_deref(),
+            cover_url: cover_url.as_deref(),
+            duration: track.duration_ms.map(|ms| Duration::from_millis(ms.max(0.0) as u64)),
+        })
+    });
+}
+
+/// Register with the OS media session and follow the player. Call from setup (main thread).
+pub fn start(app: &AppHandle) {
+    #[cfg(feature = "media-controls")]
+    {
+        #[cfg(target_os = "windows")]
+        let hwnd = app
+            .get_webview_window("main")
+            .and_then(|window| window.hwnd().ok())
+            .map(|hwnd| hwnd.0 as *mut std::ffi::c_void);
+        #[cfg(not(target_os = "windows"))]
+        let hwnd = None;
+
+        let mut controls = match MediaControls::new(PlatformConfig {
+            display_name: "RecoDeck",
+            dbus_name: "recodeck",
+            hwnd,
+        }) {
+            Ok(controls) => controls,
+            Err(e) => {
+                eprintln!("[media] Media session unavailable: {:?}", e);
+                return;
+            }
+        };
+
+        let session = Arc::new(Mutex::new(SessionState::default()));
+
+        let h = app.clone();
+        let s = session.clone();
+        let attached = controls.attach(move |event| {
+            if matches!(event, MediaControlEvent::Raise) {
+                if let Some(window) = h.get_webview_window("main") {
+                    let _ = window.unminimize();
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+                return;
+            }
+            let action = map_event(&event, &s.lock().unwrap());
+            if let Some(action) = action {
+                let _ = h.emit("player-action", action);
+            }
+        });
+        if let Err(e) = attached {
+            eprintln!("[media] Failed to attach media controls: {:?}", e);
+            return;
+        }
+        CONTROLS.with(|c| *c.borrow_mut() = Some(controls));
+
+        let h = app.clone();
+        let s = session.clone();
+        app.listen("player-state", move |event| {
+            let Ok(state) = serde_json::from_str::<PlayerStatePayload>(event.payload()) else {
+                return;
+            };
+            let mut session = s.lock().unwrap();
+            if session.track != state.current_track {
+                if let Some(track) = state.current_track.clone() {
+                    publish_metadata(&h, track);
+                }
+                session.track = state.current_track;
+            }
+            session.playing = state.is_playing;
+            session.report_position(state.position);
+            publish_playback(&h, &session);
+        });
+
+        let h = app.clone();
+        app.listen("player-position", move |event| {
+            let Ok(update) = serde_json::from_str::<PlayerPositionPayload>(event.payload()) else {
+                return;
+            };
+            let mut session = session.lock().unwrap();
+            let jumped = (update.position - session.estimated_position()).abs() > SEEK_DETECT_MS;
+            session.report_position(update.position);
+            if jumped {
+                publish_playback(&h, &session);
+            }
+        });
+
+        eprintln!("[media] Media session started");
+    }
+    #[cfg(not(feature = "media-controls"))]
+    {
+        let _ = app;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_player_state_payload() {
+        let json = r#"{"currentTrack":{"id":3,"title":"Halcyon","artist":"Orbital","album":null,
+            "artwork_path":"/art/3.jpg","duration_ms":331000},"isPlaying":true,"position":1200,
+            "duration":331000,"isLoading":false}"#;
+        let state: PlayerStatePayload = serde_json::from_str(json).unwrap();
+        let track = state.current_track.unwrap();
+        assert_eq!(track.title.as_deref(), Some("Halcyon"));
+        assert_eq!(track.duration_ms, Some(331000.0));
+        assert!(state.is_playing);
+
+        assert_eq!(file_url("/art/3.jpg"), "file:///art/3.jpg");
+        assert_eq!(file_url("C:\\art\\3.jpg"), "file:///C:/art/3.jpg");
+        assert_eq!(
+            serde_json::to_string(&PlayerAction::seek(-50.0)).unwrap(),
+            r#"{"type":"seek","payload":0}"#
+        );
+    }
+
+    #[cfg(feature = "media-controls")]
+    #[test]
+    fn test_map_event() {
+        let mut session = SessionState { position_ms: 30_000.0, ..Default::default() };
+        let kind = |event: MediaControlEvent, session: &SessionState| map_event(&event, session).map(|a| a.kind);
+
+        assert_eq!(kind(MediaControlEvent::Play, &session), Some("playPause"));
+        assert_eq!(kind(MediaControlEvent::Pause, &session), None);
+        assert_eq!(kind(MediaControlEvent::Next, &session), Some("next"));
+        assert_eq!(
+            map_event(&MediaControlEvent::Seek(SeekDirection::Backward), &session),
+            Some(PlayerAction::seek(20_000.0))
+        );
+        assert_eq!(
+            map_event(&MediaControlEvent::SetPosition(MediaPosition(Duration::from_secs(90))), &session),
+            Some(PlayerAction::seek(90_000.0))
+        );
+
+        session.playing = true;
+        assert_eq!(kind(MediaControlEvent::Play, &session), None);
+        assert_eq!(kind(MediaControlEvent::Stop, &session), Some("playPause"));
+        assert_eq!(kind(MediaControlEvent::Raise, &session), None);
+    }
+}
@@ -0,0 +1,310 @@
+// MIDI controller input — maps notes/CCs from a controller (e.g. its browse section)
+// to preview transport actions. Actions are emitted as "midi-action" events and carried
+// out by the frontend, which owns the selection and the audio output.
+// Every incoming message is also emitted as "midi-message" so the UI can offer MIDI learn.
+// Built only with the `midi` cargo feature; without it the commands report that MIDI is unavailable.
+
+use crate::commands::library::AppState;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, State};
+
+#[cfg(feature = "midi")]
+use tauri::Emitter;
+
+/// Setting key for the MIDI mappings (JSON)
+pub const MIDI_MAPPINGS_SETTING: &str = "midi_mappings";
+/// Client name shown to the OS MIDI system
+#[cfg(feature = "midi")]
+const MIDI_CLIENT_NAME: &str = "RecoDeck";
+
+/// What the frontend should do when a mapped control fires
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MidiAction {
+    /// Load the selected track into the preview player
+    LoadSelected,
+    PlayPause,
+    /// Jump to a position: value is 0.0-1.0 of the track
+    Seek,
+    SeekForward,
+    SeekBackward,
+    RateUp,
+    RateDown,
+    /// Move the selection: value is the number of rows (negative = up)
+    Browse,
+}
+
+/// Kind of MIDI message a mapping listens to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MidiControlKind {
+    Note,
+    Cc,
+}
+
+/// Maps one note or CC to an action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MidiMapping {
+    pub kind: MidiControlKind,
+    /// MIDI channel 1-16; None matches any channel
+    pub channel: Option<u8>,
+    /// Note or CC number (0-127)
+    pub number: u8,
+    pub action: MidiAction,
+}
+
+/// A parsed channel message, emitted as "midi-message" for MIDI learn
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MidiMessageEvent {
+    pub kind: MidiControlKind,
+    /// 1-16
+    pub channel: u8,
+    pub number: u8,
+    /// Velocity or CC value (0-127)
+    pub value: u8,
+}
+
+/// Payload of the "midi-action" event
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MidiActionEvent {
+    pub action: MidiAction,
+    /// 0.0-1.0 for Seek, signed row count for Browse, 1.0 for buttons
+    pub value: f64,
+}
+
+/// Managed state holding the open MIDI connection (so it doesn't get dropped)
+/// and the mappings the input callback reads.
+#[derive(Default)]
+pub struct MidiState {
+    #[cfg(feature = "midi")]
+    connection: Mutex<Option<midir::MidiInputConnection<()>>>,
+    mappings: Arc<Mutex<Vec<MidiMapping>>>,
+}
+
+impl MidiState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Parse a raw MIDI message into a note-on or CC. Note-offs (and note-ons with
+/// velocity 0) and all other message types are ignored.
+pub fn parse_message(bytes: &[u8]) -> Option<MidiMessageEvent> {
+    let (status, number, value) = match bytes {
+        [status, number, value, ..] => (*status, *number, *value),
+        _ => return None,
+    };
+    let channel = (status & 0x0F) + 1;
+    let kind = match status & 0xF0 {
+        0x90 if value > 0 => MidiControlKind::Note,
+        0xB0 => MidiControlKind::Cc,
+        _ => return None,
+    };
+    Some(MidiMessageEvent { kind, channel, number, value })
+}
+
+/// Find the action for a message. Buttons (notes, or CCs sent as 127/0) fire on press only;
+/// Seek reads an absolute CC; Browse reads a relative encoder (1-63 = down, 65-127 = up).
+pub fn map_message(mappings: &[MidiMapping], message: &MidiMessageEvent) -> Option<MidiActionEvent> {
+    let mapping = mappings.iter().find(|m| {
+        m.kind == message.kind
+            && m.number == message.number
+            && m.channel.is_none_or(|c| c == message.channel)
+    })?;
+
+    let value = match mapping.action {
+        MidiAction::Seek => message.value as f64 / 127.0,
+        MidiAction::Browse => match (message.kind, message.value) {
+            (MidiControlKind::Note, _) => 1.0,
+            (MidiControlKind::Cc, 0) | (MidiControlKind::Cc, 64) => return None,
+            (MidiControlKind::Cc, v) if v < 64 => v as f64,
+            (MidiControlKind::Cc, v) => -((128 - v as i32) as f64),
+        },
+        _ if message.value == 0 => return None,
+        _ => 1.0,
+    };
+    Some(MidiActionEvent { action: mapping.action, value })
+}
+
+fn load_mappings(state: &State<AppState>) -> Result<Vec<MidiMapping>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    Ok(db.get_setting(MIDI_MAPPINGS_SETTING)
+        .map_err(|e| format!("Failed to load MIDI mappings: {}", e))?
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default())
+}
+
+/// List the names of available MIDI input ports
+#[tauri::command]
+pub fn list_midi_inputs() -> Result<Vec<String>, String> {
+    #[cfg(feature = "midi")]
+    {
+        let input = midir::MidiInput::new(MIDI_CLIENT_NAME)
+            .map_err(|e| format!("Failed to open MIDI: {}", e))?;
+        Ok(input
+            .ports()
+            .iter()
+            .filter_map(|port| input.port_name(port).ok())
+            .collect())
+    }
+    #[cfg(not(feature = "midi"))]
+    {
+        Ok(Vec::new())
+    }
+}
+
+/// Connect to a MIDI input (by name, or the first one available) and start emitting
+/// "midi-message" and "midi-action" events. Replaces any existing connection.
+#[tauri::command]
+pub fn start_midi(
+    app: AppHandle,
+    state: State<AppState>,
+    midi_state: State<MidiState>,
+    port_name: Option<String>,
+) -> Result<String, String> {
+    *midi_state.mappings.lock().unwrap() = load_mappings(&state)?;
+
+    #[cfg(feature = "midi")]
+    {
+        let mut connection = midi_state.connection.lock().unwrap();
+        // Drop any existing connection first
+        *connection = None;
+
+        let input = midir::MidiInput::new(MIDI_CLIENT_NAME)
+            .map_err(|e| format!("Failed to open MIDI: {}", e))?;
+        let ports = input.ports();
+        let port = ports
+            .iter()
+            .find(|port| match &port_name {
+                Some(name) => input.port_name(port).map(|n| &n == name).unwrap_or(false),
+                None => true,
+            })
+            .ok_or_else(|| match &port_name {
+                Some(name) => format!("MIDI input '{}' not found", name),
+                None => "No MIDI inputs available".to_string(),
+            })?;
+        let name = input.port_name(port).unwrap_or_default();
+
+        let mappings = midi_state.mappings.clone();
+        let conn = input
+            .connect(
+                port,
+                "recodeck-input",
+                move |_stamp, bytes, _| {
+                    let message = match parse_message(bytes) {
+                        Some(m) => m,
+                        None => return,
+                    };
+                    let _ = app.emit("midi-message", &message);
+                    if let Some(action) = map_message(&mappings.lock().unwrap(), &message) {
+                        let _ = app.emit("midi-action", &action);
+                    }
+                },
+                (),
+            )
+            .map_err(|e| format!("Failed to connect to MIDI input: {}", e))?;
+
+        *connection = Some(conn);
+        eprintln!("[midi] Listening on '{}'", name);
+        Ok(name)
+    }
+    #[cfg(not(feature = "midi"))]
+    {
+        let _ = (app, port_name);
+        Err("This build of RecoDeck has no MIDI support".to_string())
+    }
+}
+
+/// Close the MIDI connection, if any
+#[tauri::command]
+pub fn stop_midi(midi_state: State<MidiState>) -> Result<(), String> {
+    #[cfg(feature = "midi")]
+    {
+        if let Some(connection) = midi_state.connection.lock().unwrap().take() {
+            connection.close();
+        }
+    }
+    #[cfg(not(feature = "midi"))]
+    {
+        let _ = midi_state;
+    }
+    Ok(())
+}
+
+/// Get the configured MIDI mappings
+#[tauri::command]
+pub fn get_midi_mappings(state: State<AppState>) -> Result<Vec<MidiMapping>, String> {
+    load_mappings(&state)
+}
+
+/// Save MIDI mappings. Applies immediately to an open connection.
+#[tauri::command]
+pub fn set_midi_mappings(
+    state: State<AppState>,
+    midi_state: State<MidiState>,
+    mappings: Vec<MidiMapping>,
+) -> Result<(), String> {
+    if let Some(m) = mappings.iter().find(|m| m.number > 127 || m.channel.is_some_and(|c| !(1..=16).contains(&c))) {
+        return Err(format!(
+            "Invalid MIDI mapping for {:?}: number must be 0-127 and channel 1-16",
+            m.action
+        ));
+    }
+
+    let json = serde_json::to_string(&mappings)
+        .map_err(|e| format!("Failed to serialize MIDI mappings: {}", e))?;
+    {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        db.set_setting(MIDI_MAPPINGS_SETTING, &json)
+            .map_err(|e| format!("Failed to save MIDI mappings: {}", e))?;
+    }
+
+    *midi_state.mappings.lock().unwrap() = mappings;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(kind: MidiControlKind, number: u8, action: MidiAction) -> MidiMapping {
+        MidiMapping { kind, channel: None, number, action }
+    }
+
+    #[test]
+    fn test_parse_message() {
+        let note_on = parse_message(&[0x91, 36, 100]).unwrap();
+        assert_eq!(note_on.kind, MidiControlKind::Note);
+        assert_eq!(note_on.channel, 2);
+        assert_eq!(note_on.number, 36);
+
+        assert!(parse_message(&[0x91, 36, 0]).is_none()); // note-on with velocity 0 = note-off
+        assert!(parse_message(&[0x81, 36, 64]).is_none());
+        assert_eq!(parse_message(&[0xB0, 7, 127]).unwrap().kind, MidiControlKind::Cc);
+        assert!(parse_message(&[0xF8]).is_none());
+    }
+
+    #[test]
+    fn test_map_message() {
+        let mappings = vec![
+            mapping(MidiControlKind::Note, 36, MidiAction::PlayPause),
+            mapping(MidiControlKind::Cc, 20, MidiAction::Browse),
+            mapping(MidiControlKind::Cc, 21, MidiAction::Seek),
+            mapping(MidiControlKind::Cc, 22, MidiAction::LoadSelected),
+        ];
+        let map = |bytes: &[u8]| map_message(&mappings, &parse_message(bytes).unwrap());
+
+        assert_eq!(map(&[0x90, 36, 90]).unwrap().action, MidiAction::PlayPause);
+        assert!(map(&[0x90, 37, 90]).is_none());
+        assert_eq!(map(&[0xB0, 20, 1]).unwrap().value, 1.0);
+        assert_eq!(map(&[0xB0, 20, 127]).unwrap().value, -1.0);
+        assert_eq!(map(&[0xB0, 21, 127]).unwrap().value, 1.0);
+        // CC buttons fire on press (127), not release (0)
+        assert!(map(&[0xB0, 22, 127]).is_some());
+        assert!(map(&[0xB0, 22, 0]).is_none());
+    }
+}
@@ -2,16 +2,52 @@
 
 pub mod ai;
 pub mod analysis;
+pub mod analysis_errors;
+pub mod analysis_queue;
+pub mod batch;
+pub mod beat_markers;
+pub mod custom_fields;
+pub mod file_manager;
 pub mod genre;
+pub mod gigs;
+pub mod history;
+pub mod hotkeys;
+pub mod journal;
+pub mod key_colors;
 pub mod library;
+pub mod media_session;
+pub mod midi;
+pub mod onboarding;
+pub mod perf;
 pub mod playback;
+pub mod playlist_builder;
+pub mod playlist_export;
 pub mod playlists;
+pub mod profile;
+pub mod prune;
+pub mod purchases;
+pub mod quality;
+pub mod queue;
+pub mod recording;
+pub mod rekordbox;
+pub mod reports;
 pub mod server;
+pub mod sessions;
 pub mod settings;
+pub mod sidecars;
+pub mod sync;
+pub mod track_cache;
+pub mod track_groups;
+pub mod tracklist;
+pub mod verify;
 pub mod watcher;
 
 // Re-export commonly used items
+pub use analysis_queue::AnalysisQueueState;
+pub use hotkeys::HotkeyState;
 pub use library::{AppState, TrackDTO};
+pub use midi::MidiState;
 pub use playback::PlaybackState;
+pub use queue::QueueState;
 pub use server::CompanionState;
 pub use watcher::WatcherState;
@@ -0,0 +1,282 @@
+// Tauri commands for the first-run onboarding flow
+// Tracks which onboarding steps the user has completed (persisted in the
+// `settings` table as JSON) and suggests likely music folders for the
+// initial scan by probing well-known locations on the filesystem.
+
+use crate::commands::analysis_queue::AnalysisQueueState;
+use crate::commands::library::{scan_directory, AppState, ScanResultDTO};
+use crate::scanner::{collapse_roots, Scanner};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::State;
+use walkdir::WalkDir;
+
+/// Settings key holding the onboarding progress JSON
+const ONBOARDING_SETTING_KEY: &str = "onboarding_state";
+
+/// Onboarding steps, in the order the UI presents them
+const ONBOARDING_STEPS: &[&str] = &["welcome", "library_folders", "initial_scan", "analysis"];
+
+/// How deep to walk a candidate folder when probing for audio files
+const PROBE_MAX_DEPTH: usize = 4;
+/// Stop counting audio files in a candidate folder after this many
+const PROBE_MAX_FILES: usize = 500;
+
+/// Persisted onboarding progress
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoredOnboarding {
+    completed_steps: Vec<String>,
+}
+
+/// Onboarding state returned to the frontend
+#[derive(Debug, Serialize)]
+pub struct OnboardingStateDTO {
+    /// All steps in presentation order
+    pub steps: Vec<String>,
+    pub completed_steps: Vec<String>,
+    /// First step not yet completed (None when onboarding is finished)
+    pub current_step: Option<String>,
+    pub is_complete: bool,
+    /// True if the library already has tracks (e.g. onboarding skipped on an existing install)
+    pub has_tracks: bool,
+}
+
+/// A candidate music folder found on this machine
+#[derive(Debug, Serialize)]
+pub struct MusicFolderSuggestion {
+    pub path: String,
+    /// Human-readable description ("Music", "Rekordbox", "Traktor", ...)
+    pub label: String,
+    /// Number of audio files found while probing (capped at PROBE_MAX_FILES)
+    pub audio_file_count: usize,
+    /// True if the probe stopped at the cap — the folder holds at least this many files
+    pub count_is_estimate: bool,
+    /// True if the folder is already one of the library folders
+    pub already_added: bool,
+}
+
+fn load_onboarding(state: &State<AppState>) -> Result<StoredOnboarding, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    match db
+        .get_setting(ONBOARDING_SETTING_KEY)
+        .map_err(|e| format!("Failed to get onboarding state: {}", e))?
+    {
+        Some(json_str) => serde_json::from_str(&json_str)
+            .map_err(|e| format!("Failed to parse onboarding state JSON: {}", e)),
+        None => Ok(StoredOnboarding::default()),
+    }
+}
+
+fn build_state_dto(state: &State<AppState>, stored: StoredOnboarding) -> Result<OnboardingStateDTO, String> {
+    let has_tracks = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        db.count_tracks()
+            .map_err(|e| format!("Failed to count tracks: {}", e))?
+            > 0
+    };
+
+    let current_step = ONBOARDING_STEPS
+        .iter()
+        .find(|step| !stored.completed_steps.iter().any(|s| s == *step))
+        .map(|s| s.to_string());
+
+    Ok(OnboardingStateDTO {
+        steps: ONBOARDING_STEPS.iter().map(|s| s.to_string()).collect(),
+        is_complete: current_step.is_none(),
+        current_step,
+        completed_steps: stored.completed_steps,
+        has_tracks,
+    })
+}
+
+fn get_saved_library_folders(state: &State<AppState>) -> Result<Vec<String>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    match db
+        .get_setting("library_folders")
+        .map_err(|e| format!("Failed to get library folders: {}", e))?
+    {
+        Some(json_str) => serde_json::from_str::<Vec<String>>(&json_str)
+            .map_err(|e| format!("Failed to parse library folders JSON: {}", e)),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Get the current onboarding progress.
+#[tauri::command]
+pub fn get_onboarding_state(state: State<AppState>) -> Result<OnboardingStateDTO, String> {
+    let stored = load_onboarding(&state)?;
+    build_state_dto(&state, stored)
+}
+
+/// Mark an onboarding step as completed. Returns the updated state.
+#[tauri::command]
+pub fn complete_onboarding_step(state: State<AppState>, step: String) -> Result<OnboardingStateDTO, String> {
+    if !ONBOARDING_STEPS.contains(&step.as_str()) {
+        return Err(format!(
+            "Invalid onboarding step '{}'. Valid steps: {}",
+            step,
+            ONBOARDING_STEPS.join(", ")
+        ));
+    }
+
+    let mut stored = load_onboarding(&state)?;
+    if !stored.completed_steps.contains(&step) {
+        stored.completed_steps.push(step);
+    }
+
+    {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let json_str = serde_json::to_string(&stored)
+            .map_err(|e| format!("Failed to serialize onboarding state: {}", e))?;
+        db.set_setting(ONBOARDING_SETTING_KEY, &json_str)
+            .map_err(|e| format!("Failed to save onboarding state: {}", e))?;
+    }
+
+    build_state_dto(&state, stored)
+}
+
+/// Suggest likely music folders on this machine for the initial scan.
+/// Only folders that exist and contain at least one audio file are returned.
+#[tauri::command]
+pub fn suggest_music_folders(state: State<AppState>) -> Result<Vec<MusicFolderSuggestion>, String> {
+    let existing = get_saved_library_folders(&state)?;
+    let home = match home_dir() {
+        Some(h) => h,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut suggestions: Vec<MusicFolderSuggestion> = Vec::new();
+    for (path, label) in candidate_music_folders(&home) {
+        if !path.is_dir() {
+            continue;
+        }
+        let path_str = path.to_string_lossy().to_string();
+        if suggestions.iter().any(|s| s.path == path_str) {
+            continue;
+        }
+
+        let audio_file_count = count_audio_files(&path);
+        if audio_file_count == 0 {
+            continue;
+        }
+
+        suggestions.push(MusicFolderSuggestion {
+            already_added: existing.contains(&path_str),
+            path: path_str,
+            label: label.to_string(),
+            audio_file_count,
+            count_is_estimate: audio_file_count >= PROBE_MAX_FILES,
+        });
+    }
+
+    Ok(suggestions)
+}
+
+/// Guided initial scan: add the chosen folders to the library and scan them.
+/// Folders already in the library are scanned but not added twice; chosen folders inside
+/// another chosen or library folder are folded into it.
+/// Marks the `library_folders` and `initial_scan` steps as completed.
+#[tauri::command]
+pub fn run_initial_scan(
+    app: tauri::AppHandle,
+    state: State<AppState>,
+    queue: State<AnalysisQueueState>,
+    paths: Vec<String>,
+) -> Result<ScanResultDTO, String> {
+    let mut folders = get_saved_library_folders(&state)?;
+    for path in &paths {
+        if !Path::new(path).is_dir() {
+            return Err(format!("Path is not a directory: {}", path));
+        }
+        if !folders.contains(path) {
+            folders.push(path.clone());
+        }
+    }
+    let folders = collapse_roots(&folders);
+
+    {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let json_str = serde_json::to_string(&folders)
+            .map_err(|e| format!("Failed to serialize library folders: {}", e))?;
+        db.set_setting("library_folders", &json_str)
+            .map_err(|e| format!("Failed to save library folders: {}", e))?;
+        crate::audio::stream_access::refresh(db);
+    }
+
+    let mut total = ScanResultDTO {
+        total_files: 0,
+        imported: 0,
+        updated: 0,
+        moved: 0,
+        skipped: 0,
+        errors: Vec::new(),
+        symlinks_skipped: Vec::new(),
+    };
+    for path in collapse_roots(&paths) {
+        let result = scan_directory(app.clone(), state.clone(), queue.clone(), path)?;
+        total.total_files += result.total_files;
+        total.imported += result.imported;
+        total.updated += result.updated;
+        total.moved += result.moved;
+        total.skipped += result.skipped;
+        total.errors.extend(result.errors);
+        total.symlinks_skipped.extend(result.symlinks_skipped);
+    }
+
+    complete_onboarding_step(state.clone(), "library_folders".to_string())?;
+    complete_onboarding_step(state, "initial_scan".to_string())?;
+
+    Ok(total)
+}
+
+/// Resolve the user's home directory from the environment
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Platform-specific candidate locations, most likely first
+fn candidate_music_folders(home: &Path) -> Vec<(PathBuf, &'static str)> {
+    let music = home.join("Music");
+    let mut candidates = vec![(music.clone(), "Music")];
+
+    if cfg!(target_os = "macos") {
+        candidates.push((music.join("Music").join("Media.localized").join("Music"), "Apple Music"));
+        candidates.push((music.join("iTunes").join("iTunes Media").join("Music"), "iTunes"));
+    }
+    if cfg!(target_os = "linux") {
+        if let Some(xdg_music) = std::env::var_os("XDG_MUSIC_DIR") {
+            candidates.push((PathBuf::from(xdg_music), "Music"));
+        }
+    }
+
+    // DJ software default locations (same layout on macOS and Windows)
+    candidates.push((music.join("rekordbox"), "Rekordbox"));
+    candidates.push((music.join("PioneerDJ"), "Rekordbox"));
+    candidates.push((music.join("Traktor"), "Traktor"));
+    candidates.push((home.join("Documents").join("Native Instruments"), "Traktor"));
+    candidates.push((music.join("Serato"), "Serato"));
+
+    candidates.push((home.join("Downloads"), "Downloads"));
+    candidates
+}
+
+/// Count audio files under a folder, bounded in depth and total count so
+/// probing a huge home directory stays fast.
+fn count_audio_files(path: &Path) -> usize {
+    WalkDir::new(path)
+        .max_depth(PROBE_MAX_DEPTH)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && Scanner::is_audio_file(e.path()))
+        .take(PROBE_MAX_FILES)
+        .count()
+}
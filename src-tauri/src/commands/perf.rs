@@ -0,0 +1,20 @@
+// Tauri commands for the performance trace (see crate::perf; needs the "perf-trace" feature)
+
+use crate::perf::{self, PerformanceStats};
+
+/// Raw events kept in the response unless the caller asks for more
+const DEFAULT_RECENT_LIMIT: usize = 200;
+
+/// Per-command/query/lock timings from the ring buffer, plus the most recent raw events.
+/// `enabled` is false (and everything empty) in builds without "perf-trace".
+#[tauri::command]
+pub fn get_performance_stats(limit: Option<usize>) -> Result<PerformanceStats, String> {
+    Ok(perf::stats(limit.unwrap_or(DEFAULT_RECENT_LIMIT).min(perf::CAPACITY)))
+}
+
+/// Empty the ring buffer (e.g. before reproducing a slow interaction)
+#[tauri::command]
+pub fn clear_performance_stats() -> Result<(), String> {
+    perf::clear();
+    Ok(())
+}
@@ -1,16 +1,66 @@
-use crate::audio::decoder::AudioDecoder;
+use crate::audio::decoder::{AudioChunk, AudioDecoder};
+use crate::audio::prefetch::PrefetchBuffer;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use tauri::{AppHandle, Emitter, State};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
 use tokio::task;
 
+/// A play that ends (stop / next track) before this much listening time counts as a skip
+const SKIP_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Setting key: start tracks after their detected leading silence ("true"/"false", off by default)
+pub const SKIP_LEADING_SILENCE_SETTING: &str = "skip_leading_silence";
+/// Lead-ins shorter than this are left alone
+const MIN_SKIPPED_SILENCE_MS: i64 = 250;
+/// Setting key: sample format of "audio-chunk" events ("f32" or "i16"; f32 by default)
+pub const STREAM_SAMPLE_FORMAT_SETTING: &str = "stream_sample_format";
+/// Setting key: minimum length of each "audio-chunk" in ms (0 = one decoded packet each)
+pub const STREAM_CHUNK_MS_SETTING: &str = "stream_chunk_ms";
+/// Longest chunk length allowed (longer would delay seeks and the first sound)
+pub const MAX_STREAM_CHUNK_MS: u32 = 500;
+/// How often the streaming task emits "playback-position"
+const POSITION_INTERVAL: Duration = Duration::from_millis(250);
+/// Decoded audio the decoder thread keeps ready for the streaming task
+const PREFETCH_MS: f64 = 2_000.0;
+/// How long the decoder thread waits for room before checking for cancellation
+const PREFETCH_WAIT: Duration = Duration::from_millis(50);
+/// Audio the streaming task keeps queued in the webview ahead of what's heard
+const SEND_AHEAD_MS: u64 = 1_000;
+/// Less than this queued in the webview with nothing decoded is reported as an underrun
+const UNDERRUN_MS: u64 = 100;
+/// Streaming task tick while waiting for the webview to need audio
+const EMIT_TICK: Duration = Duration::from_millis(10);
+
+/// The play_history entry for the loaded track plus how long it has actually been heard.
+/// Wall-clock based: the decoder runs ahead of the speakers, so its position can't be used.
+pub struct CurrentPlay {
+    pub history_id: i64,
+    listened: Duration,
+    resumed_at: Option<Instant>,
+}
+
+impl CurrentPlay {
+    fn listened(&self) -> Duration {
+        self.listened + self.resumed_at.map(|t| t.elapsed()).unwrap_or_default()
+    }
+}
+
 /// Playback state shared across commands
 pub struct PlaybackState {
     pub decoder: Arc<Mutex<Option<AudioDecoder>>>,
     pub is_playing: Arc<Mutex<bool>>,
     pub current_track_id: Arc<Mutex<Option<i64>>>,
     pub task_generation: Arc<Mutex<u64>>,
+    pub current_play: Arc<Mutex<Option<CurrentPlay>>>,
+    /// Where the loaded track was started from; drives auto-advance at end of track
+    pub context: Arc<Mutex<Option<PlayContext>>>,
+    /// Range being auditioned by play_section
+    pub section: Arc<Mutex<Option<PlaySection>>>,
+    /// How audio is sent to the webview (refreshed from settings when a track loads)
+    pub stream_config: Arc<Mutex<StreamConfig>>,
 }
 
 impl PlaybackState {
@@ -20,10 +70,266 @@ impl PlaybackState {
             is_playing: Arc::new(Mutex::new(false)),
             current_track_id: Arc::new(Mutex::new(None)),
             task_generation: Arc::new(Mutex::new(0)),
+            current_play: Arc::new(Mutex::new(None)),
+            context: Arc::new(Mutex::new(None)),
+            section: Arc::new(Mutex::new(None)),
+            stream_config: Arc::new(Mutex::new(StreamConfig::default())),
+        }
+    }
+}
+
+/// Sample format of "audio-chunk" events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SampleFormat {
+    #[default]
+    F32,
+    /// Half the payload; plenty for auditioning
+    I16,
+}
+
+/// How audio is sent to the webview. i16 samples and longer chunks cut the IPC and
+/// serialization cost, which helps on slower machines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct StreamConfig {
+    pub sample_format: SampleFormat,
+    /// Decoded packets are merged until a chunk is at least this long (0 = one packet each)
+    pub chunk_ms: u32,
+}
+
+impl StreamConfig {
+    pub fn from_settings(db: &crate::db::Database) -> Self {
+        let sample_format = match db.get_setting(STREAM_SAMPLE_FORMAT_SETTING).ok().flatten().as_deref() {
+            Some("i16") => SampleFormat::I16,
+            _ => SampleFormat::F32,
+        };
+        let chunk_ms = db
+            .get_setting(STREAM_CHUNK_MS_SETTING)
+            .ok()
+            .flatten()
+            .and_then(|ms| ms.parse::<u32>().ok())
+            .unwrap_or(0)
+            .min(MAX_STREAM_CHUNK_MS);
+        Self { sample_format, chunk_ms }
+    }
+}
+
+/// Merges consecutive decoded chunks until they reach a minimum length
+struct ChunkMerger {
+    target_ms: f64,
+    pending: Option<AudioChunk>,
+}
+
+impl ChunkMerger {
+    fn new(target_ms: u32) -> Self {
+        Self {
+            target_ms: target_ms as f64,
+            pending: None,
+        }
+    }
+
+    /// Add a chunk; returns a merged chunk once it's long enough
+    fn add(&mut self, chunk: AudioChunk) -> Option<AudioChunk> {
+        let merged = match self.pending.take() {
+            Some(mut pending) if pending.sample_rate == chunk.sample_rate => {
+                pending.samples.extend_from_slice(&chunk.samples);
+                pending
+            }
+            Some(pending) => {
+                // Sample rate changed: send what we have, start again
+                self.pending = Some(chunk);
+                return Some(pending);
+            }
+            None => chunk,
+        };
+        if chunk_length_ms(&merged) >= self.target_ms {
+            Some(merged)
+        } else {
+            self.pending = Some(merged);
+            None
+        }
+    }
+
+    /// What's left (at a seek, section loop or the end of the track)
+    fn flush(&mut self) -> Option<AudioChunk> {
+        self.pending.take()
+    }
+}
+
+/// Close out the current play: store how long it was heard and whether it was a skip.
+/// Non-fatal — history is best-effort and must never block playback.
+fn finish_current_play(db: &crate::db::Database, playback_state: &PlaybackState) {
+    let play = match playback_state.current_play.lock() {
+        Ok(mut lock) => lock.take(),
+        Err(_) => None,
+    };
+    if let Some(play) = play {
+        let listened = play.listened();
+        let skipped = listened < SKIP_THRESHOLD;
+        if let Err(e) = db.with_busy_retry(|db| db.finish_play(play.history_id, listened.as_millis() as i64, skipped)) {
+            eprintln!("[playback] Failed to finish play {}: {}", play.history_id, e);
+        }
+    }
+}
+
+/// Mark the context's current track as auditioned in its playlist (see migration 022).
+/// Only playlist contexts count. Best-effort, like play history.
+fn mark_auditioned(db: &crate::db::Database, context: &PlayContext) {
+    if context.kind != PlayContextKind::Playlist {
+        return;
+    }
+    let playlist_id = context.id.as_deref().and_then(|id| id.parse::<i64>().ok());
+    let (Some(playlist_id), Some(track_id)) = (playlist_id, context.current()) else {
+        return;
+    };
+    if let Err(e) = db.with_busy_retry(|db| db.mark_auditioned(playlist_id, track_id)) {
+        eprintln!("[playback] Failed to mark track {} auditioned in playlist {}: {}", track_id, playlist_id, e);
+    }
+}
+
+/// What auto-advance plays next when a track ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlayContextKind {
+    Folder,
+    Playlist,
+    Queue,
+}
+
+/// The folder/playlist being played through (track IDs resolved when it was set),
+/// or the audition queue
+#[derive(Debug, Clone)]
+pub struct PlayContext {
+    pub kind: PlayContextKind,
+    /// Folder path or playlist ID; None for the queue
+    pub id: Option<String>,
+    pub track_ids: Vec<i64>,
+    pub position: usize,
+}
+
+impl PlayContext {
+    /// Track at the current position (always None for the queue)
+    fn current(&self) -> Option<i64> {
+        self.track_ids.get(self.position).copied()
+    }
+
+    /// Step to the next track in a folder/playlist
+    fn advance(&mut self) -> Option<i64> {
+        if self.position + 1 >= self.track_ids.len() {
+            return None;
+        }
+        self.position += 1;
+        self.current()
+    }
+}
+
+/// A range of the loaded track played on its own, optionally looping
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PlaySection {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub looped: bool,
+}
+
+impl PlaySection {
+    /// Whether a chunk at `position_ms` is past the section (or the track ran out first)
+    fn is_over(&self, position_ms: u64, track_ended: bool) -> bool {
+        track_ended || position_ms >= self.end_ms
+    }
+
+    fn contains(&self, position_ms: u64) -> bool {
+        position_ms >= self.start_ms && position_ms < self.end_ms
+    }
+}
+
+/// Payload of the "playback-position" event
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybackPositionEvent {
+    pub track_id: Option<i64>,
+    /// Estimated audible position
+    pub position_ms: u64,
+    /// Audio sent to the frontend but not yet heard
+    pub buffered_ms: u64,
+    pub duration_ms: u64,
+    /// Times the decoder fell behind since the stream started
+    pub underruns: u32,
+}
+
+/// Payload of the "playback-underrun" event: the decoder fell behind and the webview
+/// ran (nearly) out of audio
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybackUnderrunEvent {
+    pub track_id: Option<i64>,
+    pub position_ms: u64,
+    /// Underruns since the stream started
+    pub underruns: u32,
+}
+
+/// What the decoder thread hands the streaming task
+enum Prefetched {
+    Chunk(AudioChunk),
+    /// End of track: auto-advance
+    TrackEnd,
+    /// End of a section preview that doesn't loop
+    SectionEnd,
+    /// Decoding failed for good
+    Error(String),
+}
+
+/// Estimates the audible position from wall-clock time since streaming started.
+/// The decoder runs ahead of the speakers, so its position can't be used directly;
+/// instead each chunk sent is mapped to its place in the stream, which also keeps the
+/// estimate right across section loops.
+struct PositionClock {
+    /// (stream offset ms, track position ms, length ms) of chunks not yet fully heard
+    chunks: VecDeque<(f64, u64, f64)>,
+    streamed_ms: f64,
+    /// Reported until the first chunk is sent
+    start_ms: u64,
+}
+
+impl PositionClock {
+    fn new(start_ms: u64) -> Self {
+        Self {
+            chunks: VecDeque::new(),
+            streamed_ms: 0.0,
+            start_ms,
+        }
+    }
+
+    /// Record a chunk handed to the frontend `elapsed_ms` after streaming started. After
+    /// an underrun the frontend schedules it from now, not after the previous chunk.
+    fn push(&mut self, position_ms: u64, length_ms: f64, elapsed_ms: f64) {
+        self.streamed_ms = self.streamed_ms.max(elapsed_ms);
+        self.chunks.push_back((self.streamed_ms, position_ms, length_ms));
+        self.streamed_ms += length_ms;
+    }
+
+    /// (position, buffered) in ms, `elapsed_ms` after streaming started
+    fn at(&mut self, elapsed_ms: f64) -> (u64, u64) {
+        // Drop chunks already heard, keeping the last one to clamp to
+        while self.chunks.len() > 1 && self.chunks[0].0 + self.chunks[0].2 <= elapsed_ms {
+            self.chunks.pop_front();
         }
+        let position = match self.chunks.front() {
+            Some(&(offset, position_ms, length_ms)) => {
+                position_ms + (elapsed_ms - offset).clamp(0.0, length_ms) as u64
+            }
+            None => self.start_ms,
+        };
+        (position, (self.streamed_ms - elapsed_ms).max(0.0) as u64)
     }
 }
 
+/// Payload of the "track-changed" event, emitted when auto-advance starts the next track
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackChangedEvent {
+    pub track_id: i64,
+    pub context_type: PlayContextKind,
+    /// Position in the folder/playlist; None for the queue
+    pub position: Option<usize>,
+}
+
 /// Playback status returned to frontend
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlaybackStatus {
@@ -48,15 +354,70 @@ pub async fn load_track(
     let db = db.as_ref()
         .ok_or_else(|| "Database not initialized".to_string())?;
 
+    let status = load_track_into(db, &playback_state, track_id)?;
+
+    // Loading a track from outside the folder/playlist being played ends auto-advance
+    // (the queue keeps going: it's independent of what's loaded)
+    if let Ok(mut context) = playback_state.context.lock() {
+        let outside = context.as_ref()
+            .map(|c| c.kind != PlayContextKind::Queue && c.current() != Some(track_id))
+            .unwrap_or(false);
+        if outside {
+            *context = None;
+        } else if let Some(c) = context.as_ref() {
+            mark_auditioned(db, c);
+        }
+    }
+
+    Ok(status)
+}
+
+/// Load a track into the decoder and start its play_history entry
+fn load_track_into(
+    db: &crate::db::Database,
+    playback_state: &PlaybackState,
+    track_id: i64,
+) -> Result<PlaybackStatus, String> {
     let track = db.get_track(track_id)
         .map_err(|e| format!("Failed to get track: {}", e))?;
     let file_path = PathBuf::from(&track.file_path);
 
     // Create decoder
-    let decoder = AudioDecoder::new(&file_path)?;
+    let mut decoder = AudioDecoder::new(&file_path)?;
+
+    *playback_state.stream_config.lock().unwrap() = StreamConfig::from_settings(db);
+
+    // Optionally start after the dead air at the top of the file
+    let skip_silence = db.get_setting(SKIP_LEADING_SILENCE_SETTING).ok().flatten().as_deref() == Some("true");
+    if skip_silence {
+        if let Ok(Some(lead_ms)) = db.get_silence_lead(track_id) {
+            if lead_ms >= MIN_SKIPPED_SILENCE_MS {
+                if let Err(e) = decoder.seek(lead_ms as u64) {
+                    eprintln!("[playback] Failed to skip leading silence of track {}: {}", track_id, e);
+                }
+            }
+        }
+    }
+
+    // Close out the previous track's play (a skip if it was passed over quickly),
+    // then record this one for history/popularity (non-fatal if it fails)
+    finish_current_play(db, playback_state);
+    match db.with_busy_retry(|db| db.record_play(track_id)) {
+        Ok(history_id) => {
+            let mut play_lock = playback_state.current_play.lock()
+                .map_err(|e| format!("Failed to lock current play: {}", e))?;
+            *play_lock = Some(CurrentPlay {
+                history_id,
+                listened: Duration::ZERO,
+                resumed_at: None,
+            });
+        }
+        Err(e) => eprintln!("[playback] Failed to record play for track {}: {}", track_id, e),
+    }
 
     let sample_rate = decoder.sample_rate();
     let duration_ms = decoder.duration_ms();
+    let position_ms = decoder.current_position_ms();
 
     // Increment generation to cancel any running tasks
     {
@@ -65,6 +426,10 @@ pub async fn load_track(
         *gen += 1;
     }
 
+    if let Ok(mut section) = playback_state.section.lock() {
+        *section = None;
+    }
+
     // Store decoder
     let mut decoder_lock = playback_state.decoder.lock()
         .map_err(|e| format!("Failed to lock decoder: {}", e))?;
@@ -82,7 +447,7 @@ pub async fn load_track(
     Ok(PlaybackStatus {
         is_playing: false,
         track_id: Some(track_id),
-        position_ms: 0,
+        position_ms,
         duration_ms,
         sample_rate,
     })
@@ -94,6 +459,12 @@ pub async fn play(
     app: AppHandle,
     playback_state: State<'_, PlaybackState>,
 ) -> Result<PlaybackStatus, String> {
+    start_playback(app, &playback_state)?;
+    get_playback_status(playback_state).await
+}
+
+/// Set the playing flag and spawn the task that streams the loaded track
+fn start_playback(app: AppHandle, playback_state: &PlaybackState) -> Result<(), String> {
     // Set playing state
     {
         let mut is_playing = playback_state.is_playing.lock()
@@ -101,23 +472,59 @@ pub async fn play(
         *is_playing = true;
     }
 
+    // Start the listening clock
+    if let Ok(mut play_lock) = playback_state.current_play.lock() {
+        if let Some(play) = play_lock.as_mut() {
+            play.resumed_at.get_or_insert_with(Instant::now);
+        }
+    }
+
+    // Cancel any task still streaming (e.g. a pause immediately followed by play)
+    let current_generation = {
+        let mut gen = playback_state.task_generation.lock().unwrap();
+        *gen += 1;
+        *gen
+    };
+
     // Clone the Arc pointers (not the entire state)
     let decoder_arc = Arc::clone(&playback_state.decoder);
     let is_playing_arc = Arc::clone(&playback_state.is_playing);
     let generation_arc = Arc::clone(&playback_state.task_generation);
+    let section_arc = Arc::clone(&playback_state.section);
 
-    // Capture current generation
-    let current_generation = {
-        let gen = playback_state.task_generation.lock().unwrap();
-        *gen
-    };
+    let track_id = *playback_state.current_track_id.lock().unwrap();
+    let stream_config = *playback_state.stream_config.lock().unwrap();
+    let start_ms = playback_state.decoder.lock().unwrap()
+        .as_ref()
+        .map(|d| d.current_position_ms())
+        .unwrap_or(0);
 
     // Spawn background task to stream audio chunks
     task::spawn(async move {
-        let mut consecutive_errors = 0;
-        // Increased limit since decode errors are now handled internally by skipping packets
-        // This limit is mainly for other types of errors (I/O, etc.)
-        const MAX_CONSECUTIVE_ERRORS: u32 = 20;
+        // Decoder thread keeps PREFETCH_MS of audio ready; this task sends it on at
+        // playback speed
+        let buffer = Arc::new(PrefetchBuffer::new(PREFETCH_MS));
+        let decoder_thread = {
+            let buffer = Arc::clone(&buffer);
+            let decoder_arc = Arc::clone(&decoder_arc);
+            let is_playing_arc = Arc::clone(&is_playing_arc);
+            let generation_arc = Arc::clone(&generation_arc);
+            task::spawn_blocking(move || {
+                let is_current = || {
+                    *generation_arc.lock().unwrap() == current_generation && *is_playing_arc.lock().unwrap()
+                };
+                prefetch_audio(&buffer, &decoder_arc, &section_arc, stream_config.chunk_ms, is_current);
+            })
+        };
+
+        let mut clock = PositionClock::new(start_ms);
+        let stream_started = Instant::now();
+        let mut last_position_event: Option<Instant> = None;
+        let mut duration_ms = 0;
+        let mut started = false;
+        let mut underruns = 0u32;
+        let mut in_underrun = false;
+        let mut paused = false;
 
         loop {
             // Check if task was cancelled (generation changed)
@@ -136,110 +543,271 @@ pub async fn play(
             };
 
             if !is_playing {
+                paused = true;
                 break;
             }
 
-            // Decode next chunk
-            let chunk_result = {
-                let mut decoder_lock = decoder_arc.lock().unwrap();
-                if let Some(decoder) = decoder_lock.as_mut() {
-                    decoder.decode_next_chunk()
-                } else {
-                    break;
-                }
-            };
+            let elapsed_ms = stream_started.elapsed().as_secs_f64() * 1000.0;
+            let (position_ms, ahead_ms) = clock.at(elapsed_ms);
+            let mut sent = false;
 
-            match chunk_result {
-                Ok(Some(chunk)) => {
-                    // Reset error counter on successful decode
-                    consecutive_errors = 0;
-                    
-                    if chunk.is_end {
-                        // End of track reached - only log warnings for early end
-                        let position_ms = chunk.position_ms;
-                        let duration_ms = chunk.duration_ms;
-                        let gap_ms = if duration_ms > position_ms {
-                            duration_ms - position_ms
-                        } else {
-                            0
+            // Keep SEND_AHEAD_MS queued in the webview
+            if ahead_ms < SEND_AHEAD_MS {
+                match buffer.pop() {
+                    Some(Prefetched::Chunk(chunk)) => {
+                        let emitted = match stream_config.sample_format {
+                            SampleFormat::F32 => app.emit("audio-chunk", &chunk),
+                            SampleFormat::I16 => app.emit("audio-chunk", chunk.to_i16()),
                         };
-
-                        if gap_ms > 30000 {
-                            eprintln!("[playback] WARNING: Track ended early! position={}ms, duration={}ms, gap={}ms (~{}s)",
-                                     position_ms, duration_ms, gap_ms, gap_ms / 1000);
+                        if emitted.is_err() {
+                            break;
                         }
-
+                        clock.push(chunk.position_ms, chunk_length_ms(&chunk), elapsed_ms);
+                        duration_ms = chunk.duration_ms;
+                        started = true;
+                        in_underrun = false;
+                        sent = true;
+                    }
+                    Some(Prefetched::TrackEnd) => {
+                        on_track_end(&app);
+                        break;
+                    }
+                    Some(Prefetched::SectionEnd) => {
                         let _ = app.emit("audio-ended", ());
                         break;
                     }
-
-                    // Emit chunk to frontend
-                    if app.emit("audio-chunk", &chunk).is_err() {
+                    Some(Prefetched::Error(e)) => {
+                        let _ = app.emit("audio-error", format!("Playback error: {}", e));
                         break;
                     }
+                    None => {
+                        // Nothing decoded and the webview is about to run dry
+                        if started && ahead_ms < UNDERRUN_MS && !in_underrun {
+                            in_underrun = true;
+                            underruns += 1;
+                            eprintln!("[playback] Underrun at {}ms: decoder fell behind ({} so far)", position_ms, underruns);
+                            let _ = app.emit(
+                                "playback-underrun",
+                                PlaybackUnderrunEvent { track_id, position_ms, underruns },
+                            );
+                        }
+                    }
+                }
+            }
+
+            // Periodic position for progress bars and playheads
+            if started && last_position_event.map(|t| t.elapsed() >= POSITION_INTERVAL).unwrap_or(true) {
+                let (position_ms, buffered_ms) = clock.at(stream_started.elapsed().as_secs_f64() * 1000.0);
+                let _ = app.emit(
+                    "playback-position",
+                    PlaybackPositionEvent {
+                        track_id,
+                        position_ms,
+                        buffered_ms,
+                        duration_ms,
+                        underruns,
+                    },
+                );
+                last_position_event = Some(Instant::now());
+            }
+
+            if !sent {
+                tokio::time::sleep(EMIT_TICK).await;
+            }
+        }
 
-                    // Small delay to prevent overwhelming the IPC channel
-                    tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        buffer.close();
+        let _ = decoder_thread.await;
+
+        // Paused: the decoder has run ahead of what was heard (prefetched and queued
+        // audio is dropped), so put it back where playback stopped for resume
+        if paused && started && *generation_arc.lock().unwrap() == current_generation {
+            let (position_ms, _) = clock.at(stream_started.elapsed().as_secs_f64() * 1000.0);
+            if let Some(decoder) = decoder_arc.lock().unwrap().as_mut() {
+                if let Err(e) = decoder.seek(position_ms) {
+                    eprintln!("[playback] Failed to rewind to {}ms after pause: {}", position_ms, e);
                 }
-                Ok(None) => {
-                    // End of file - only log warnings for early end
-                    let (position_ms, duration_ms) = {
-                        let decoder_lock = decoder_arc.lock().unwrap();
-                        if let Some(decoder) = decoder_lock.as_ref() {
-                            (decoder.current_position_ms(), decoder.duration_ms())
-                        } else {
-                            (0, 0)
-                        }
-                    };
-                    let gap_ms = if duration_ms > position_ms {
-                        duration_ms - position_ms
-                    } else {
-                        0
-                    };
+            }
+        }
+
+        // Reset playing state when done, unless a newer task (e.g. auto-advance) took over
+        if *generation_arc.lock().unwrap() == current_generation {
+            let mut is_playing = is_playing_arc.lock().unwrap();
+            *is_playing = false;
+        }
+    });
 
+    Ok(())
+}
+
+/// Length of a chunk's audio in ms
+fn chunk_length_ms(chunk: &AudioChunk) -> f64 {
+    if chunk.sample_rate == 0 {
+        return 0.0;
+    }
+    (chunk.samples.len() / 2) as f64 * 1000.0 / chunk.sample_rate as f64
+}
+
+/// Push into the prefetch buffer, waiting for room while the task is current.
+/// False if it was closed or cancelled first.
+fn push_prefetched(
+    buffer: &PrefetchBuffer<Prefetched>,
+    mut item: Prefetched,
+    length_ms: f64,
+    is_current: &impl Fn() -> bool,
+) -> bool {
+    loop {
+        match buffer.push(item, length_ms, PREFETCH_WAIT) {
+            Ok(()) => return true,
+            Err(back) if is_current() && !buffer.is_closed() => item = back,
+            Err(_) => return false,
+        }
+    }
+}
+
+/// Decoder thread: decode the loaded track into `buffer` (in chunks of at least
+/// `chunk_ms`) until the track or section ends, decoding fails for good, or the task is
+/// cancelled / paused / closed
+fn prefetch_audio(
+    buffer: &PrefetchBuffer<Prefetched>,
+    decoder_arc: &Mutex<Option<AudioDecoder>>,
+    section_arc: &Mutex<Option<PlaySection>>,
+    chunk_ms: u32,
+    is_current: impl Fn() -> bool,
+) {
+    let mut merger = ChunkMerger::new(chunk_ms);
+    // Send any partly merged chunk before moving elsewhere in the track
+    let flush = |merger: &mut ChunkMerger| match merger.flush() {
+        Some(chunk) => {
+            let length_ms = chunk_length_ms(&chunk);
+            push_prefetched(buffer, Prefetched::Chunk(chunk), length_ms, &is_current)
+        }
+        None => true,
+    };
+
+    let mut consecutive_errors = 0;
+    // Increased limit since decode errors are now handled internally by skipping packets
+    // This limit is mainly for other types of errors (I/O, etc.)
+    const MAX_CONSECUTIVE_ERRORS: u32 = 20;
+    // Guards against looping a section that yields no audio
+    let mut played_since_loop = true;
+
+    while is_current() && !buffer.is_closed() {
+        // Decode next chunk
+        let chunk_result = {
+            let mut decoder_lock = decoder_arc.lock().unwrap();
+            if let Some(decoder) = decoder_lock.as_mut() {
+                decoder.decode_next_chunk()
+            } else {
+                return;
+            }
+        };
+
+        // Section preview: at the section end, jump back to its start or stop
+        let section = *section_arc.lock().unwrap();
+        if let Some(section) = section {
+            let over = match &chunk_result {
+                Ok(Some(chunk)) => section.is_over(chunk.position_ms, chunk.is_end),
+                Ok(None) => true,
+                Err(_) => false,
+            };
+            if over {
+                if !flush(&mut merger) {
+                    return;
+                }
+                if !section.looped || !played_since_loop {
+                    push_prefetched(buffer, Prefetched::SectionEnd, 0.0, &is_current);
+                    return;
+                }
+                let seeked = match decoder_arc.lock().unwrap().as_mut() {
+                    Some(decoder) => decoder.seek(section.start_ms),
+                    None => return,
+                };
+                if let Err(e) = seeked {
+                    eprintln!("[playback] Failed to loop section: {}", e);
+                    push_prefetched(buffer, Prefetched::Error(e), 0.0, &is_current);
+                    return;
+                }
+                played_since_loop = false;
+                continue;
+            }
+        }
+
+        match chunk_result {
+            Ok(Some(chunk)) => {
+                // Reset error counter on successful decode
+                consecutive_errors = 0;
+
+                if chunk.is_end {
+                    // End of track reached - only log warnings for early end
+                    let gap_ms = chunk.duration_ms.saturating_sub(chunk.position_ms);
                     if gap_ms > 30000 {
-                        eprintln!("[playback] WARNING: Track ended early (Ok(None))! position={}ms, duration={}ms, gap={}ms (~{}s)",
-                                 position_ms, duration_ms, gap_ms, gap_ms / 1000);
+                        eprintln!("[playback] WARNING: Track ended early! position={}ms, duration={}ms, gap={}ms (~{}s)",
+                                 chunk.position_ms, chunk.duration_ms, gap_ms, gap_ms / 1000);
+                    }
+                    if flush(&mut merger) {
+                        push_prefetched(buffer, Prefetched::TrackEnd, 0.0, &is_current);
                     }
+                    return;
+                }
 
-                    let _ = app.emit("audio-ended", ());
-                    break;
+                played_since_loop = true;
+                if let Some(chunk) = merger.add(chunk) {
+                    let length_ms = chunk_length_ms(&chunk);
+                    if !push_prefetched(buffer, Prefetched::Chunk(chunk), length_ms, &is_current) {
+                        return;
+                    }
                 }
-                Err(e) => {
-                    // Non-decode errors (I/O errors, etc.) - decode errors are now handled internally
-                    consecutive_errors += 1;
-                    
-                    // Get current position for logging
-                    let position_ms = {
-                        let decoder_lock = decoder_arc.lock().unwrap();
-                        if let Some(decoder) = decoder_lock.as_ref() {
-                            decoder.current_position_ms()
-                        } else {
-                            0
-                        }
-                    };
-                    
-                    eprintln!("[playback] Playback error (attempt {}/{}): {} (position={}ms)", 
-                              consecutive_errors, MAX_CONSECUTIVE_ERRORS, e, position_ms);
-                    
-                    if consecutive_errors >= MAX_CONSECUTIVE_ERRORS {
-                        eprintln!("[playback] Too many consecutive errors, stopping playback");
-                        let _ = app.emit("audio-error", format!("Playback error: {}", e));
-                        break;
+            }
+            Ok(None) => {
+                // End of file - only log warnings for early end
+                let (position_ms, duration_ms) = {
+                    let decoder_lock = decoder_arc.lock().unwrap();
+                    if let Some(decoder) = decoder_lock.as_ref() {
+                        (decoder.current_position_ms(), decoder.duration_ms())
+                    } else {
+                        (0, 0)
                     }
-                    
-                    // Brief pause before retry (transient errors after seek may resolve)
-                    tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+                };
+                let gap_ms = duration_ms.saturating_sub(position_ms);
+                if gap_ms > 30000 {
+                    eprintln!("[playback] WARNING: Track ended early (Ok(None))! position={}ms, duration={}ms, gap={}ms (~{}s)",
+                             position_ms, duration_ms, gap_ms, gap_ms / 1000);
+                }
+                if flush(&mut merger) {
+                    push_prefetched(buffer, Prefetched::TrackEnd, 0.0, &is_current);
                 }
+                return;
             }
-        }
+            Err(e) => {
+                // Non-decode errors (I/O errors, etc.) - decode errors are now handled internally
+                consecutive_errors += 1;
 
-        // Reset playing state when done
-        let mut is_playing = is_playing_arc.lock().unwrap();
-        *is_playing = false;
-    });
+                // Get current position for logging
+                let position_ms = {
+                    let decoder_lock = decoder_arc.lock().unwrap();
+                    if let Some(decoder) = decoder_lock.as_ref() {
+                        decoder.current_position_ms()
+                    } else {
+                        0
+                    }
+                };
 
-    get_playback_status(playback_state).await
+                eprintln!("[playback] Playback error (attempt {}/{}): {} (position={}ms)",
+                          consecutive_errors, MAX_CONSECUTIVE_ERRORS, e, position_ms);
+
+                if consecutive_errors >= MAX_CONSECUTIVE_ERRORS {
+                    eprintln!("[playback] Too many consecutive errors, stopping playback");
+                    flush(&mut merger);
+                    push_prefetched(buffer, Prefetched::Error(e), 0.0, &is_current);
+                    return;
+                }
+
+                // Brief pause before retry (transient errors after seek may resolve)
+                std::thread::sleep(Duration::from_millis(50));
+            }
+        }
+    }
 }
 
 /// Pause playback
@@ -253,6 +821,15 @@ pub async fn pause(
         *is_playing = false;
     }
 
+    // Pause the listening clock
+    if let Ok(mut play_lock) = playback_state.current_play.lock() {
+        if let Some(play) = play_lock.as_mut() {
+            if let Some(resumed_at) = play.resumed_at.take() {
+                play.listened += resumed_at.elapsed();
+            }
+        }
+    }
+
     get_playback_status(playback_state).await
 }
 
@@ -265,22 +842,32 @@ pub async fn resume(
     play(app, playback_state).await
 }
 
-/// Seek to a specific position in milliseconds
+/// Seek to a specific position in milliseconds. While playing, streaming carries on
+/// from the new position; while paused, play/resume starts from there.
 #[tauri::command]
 pub async fn seek(
+    app: AppHandle,
     position_ms: u64,
     playback_state: State<'_, PlaybackState>,
 ) -> Result<PlaybackStatus, String> {
     // Increment generation first to cancel running task
-    {
+    let seek_generation = {
         let mut gen = playback_state.task_generation.lock()
             .map_err(|e| format!("Failed to lock generation: {}", e))?;
         *gen += 1;
-    }
+        *gen
+    };
 
     // Brief delay to ensure old task notices cancellation
     tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
 
+    // Seeking out of an auditioned section ends the section preview
+    if let Ok(mut section) = playback_state.section.lock() {
+        if section.map(|s| !s.contains(position_ms)).unwrap_or(false) {
+            *section = None;
+        }
+    }
+
     {
         let mut decoder_lock = playback_state.decoder.lock()
             .map_err(|e| format!("Failed to lock decoder: {}", e))?;
@@ -290,14 +877,34 @@ pub async fn seek(
         }
     }
 
+    // The cancelled task leaves is_playing set, so restart streaming if it was playing.
+    // While scrubbing only the latest seek restarts it (an older one finding the
+    // generation moved on leaves it to the newer seek or play).
+    let was_playing = *playback_state.is_playing.lock()
+        .map_err(|e| format!("Failed to lock playing state: {}", e))?;
+    let latest = *playback_state.task_generation.lock().unwrap() == seek_generation;
+    let loaded = playback_state.current_track_id.lock().unwrap().is_some();
+    if was_playing && latest && loaded {
+        start_playback(app, &playback_state)?;
+    }
+
     get_playback_status(playback_state).await
 }
 
 /// Stop playback and unload track
 #[tauri::command]
 pub async fn stop(
+    app_state: State<'_, crate::commands::library::AppState>,
     playback_state: State<'_, PlaybackState>,
 ) -> Result<PlaybackStatus, String> {
+    {
+        let db_lock = app_state.db.lock()
+            .map_err(|e| format!("Failed to lock database: {}", e))?;
+        if let Some(db) = db_lock.as_ref() {
+            finish_current_play(db, &playback_state);
+        }
+    }
+
     let mut is_playing = playback_state.is_playing.lock()
         .map_err(|e| format!("Failed to lock playing state: {}", e))?;
     *is_playing = false;
@@ -355,3 +962,282 @@ pub async fn get_playback_status(
         sample_rate,
     })
 }
+
+/// Called by the streaming task at end of track: load and play the next track from the
+/// play context, skipping ones that fail to load. Emits "track-changed" when it moves on,
+/// "audio-ended" when there's nothing left.
+fn on_track_end(app: &AppHandle) {
+    let playback_state = app.state::<PlaybackState>();
+    let app_state = app.state::<crate::commands::library::AppState>();
+
+    loop {
+        let next = {
+            let mut context = playback_state.context.lock().unwrap();
+            match context.as_mut() {
+                Some(c) if c.kind == PlayContextKind::Queue => {
+                    app.state::<crate::commands::queue::QueueState>()
+                        .queue
+                        .dequeue()
+                        .map(|id| (id, PlayContextKind::Queue, None))
+                }
+                Some(c) => c.advance().map(|id| (id, c.kind, Some(c.position))),
+                None => None,
+            }
+        };
+        let Some((track_id, context_type, position)) = next else {
+            break;
+        };
+
+        let loaded = {
+            let db_lock = app_state.db.lock().unwrap();
+            match db_lock.as_ref() {
+                Some(db) => load_track_into(db, &playback_state, track_id).inspect(|_| {
+                    if let Some(c) = playback_state.context.lock().unwrap().as_ref() {
+                        mark_auditioned(db, c);
+                    }
+                }),
+                None => Err("Database not initialized".to_string()),
+            }
+        };
+        match loaded.and_then(|_| start_playback(app.clone(), &playback_state)) {
+            Ok(()) => {
+                let _ = app.emit(
+                    "track-changed",
+                    TrackChangedEvent { track_id, context_type, position },
+                );
+                return;
+            }
+            Err(e) => eprintln!("[playback] Auto-advance skipping track {}: {}", track_id, e),
+        }
+    }
+
+    let _ = app.emit("audio-ended", ());
+}
+
+/// The active play context, for the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayContextDTO {
+    pub context_type: PlayContextKind,
+    pub id: Option<String>,
+    pub position: usize,
+    pub track_count: usize,
+}
+
+impl From<&PlayContext> for PlayContextDTO {
+    fn from(context: &PlayContext) -> Self {
+        Self {
+            context_type: context.kind,
+            id: context.id.clone(),
+            position: context.position,
+            track_count: context.track_ids.len(),
+        }
+    }
+}
+
+/// Set what plays after the current track ends: the rest of a folder (recursive, in
+/// library order), a playlist (`id` is the playlist ID) or the audition queue.
+/// `position` is the index of the track being played in the folder/playlist.
+/// Tracks played from a playlist context are marked auditioned in that playlist.
+#[tauri::command]
+pub fn set_play_context(
+    app_state: State<'_, crate::commands::library::AppState>,
+    playback_state: State<'_, PlaybackState>,
+    context_type: PlayContextKind,
+    id: Option<String>,
+    position: usize,
+) -> Result<PlayContextDTO, String> {
+    let track_ids = match context_type {
+        PlayContextKind::Queue => Vec::new(),
+        PlayContextKind::Folder | PlayContextKind::Playlist => {
+            let id = id.as_deref().ok_or("A folder path or playlist ID is required")?;
+            let db_lock = app_state.db.lock().unwrap();
+            let db = db_lock.as_ref().ok_or("Database not initialized")?;
+            let rows = if context_type == PlayContextKind::Folder {
+                db.get_tracks_in_folder_with_analysis(id)
+                    .map_err(|e| format!("Failed to get tracks in folder: {}", e))?
+            } else {
+                let playlist_id = id.parse::<i64>()
+                    .map_err(|_| format!("Invalid playlist ID: {}", id))?;
+                db.get_playlist_tracks(playlist_id)
+                    .map_err(|e| format!("Failed to get playlist tracks: {}", e))?
+            };
+            let track_ids: Vec<i64> = rows.into_iter().filter_map(|(t, ..)| t.id).collect();
+            if position >= track_ids.len() {
+                return Err(format!("Position {} out of range ({} tracks)", position, track_ids.len()));
+            }
+            track_ids
+        }
+    };
+
+    let context = PlayContext {
+        kind: context_type,
+        id: if context_type == PlayContextKind::Queue { None } else { id },
+        track_ids,
+        position,
+    };
+    if context_type == PlayContextKind::Playlist {
+        let db_lock = app_state.db.lock().unwrap();
+        if let Some(db) = db_lock.as_ref() {
+            mark_auditioned(db, &context);
+        }
+    }
+    let dto = PlayContextDTO::from(&context);
+    *playback_state.context.lock().unwrap() = Some(context);
+    Ok(dto)
+}
+
+/// Get the active play context, if any
+#[tauri::command]
+pub fn get_play_context(playback_state: State<'_, PlaybackState>) -> Result<Option<PlayContextDTO>, String> {
+    Ok(playback_state.context.lock().unwrap().as_ref().map(PlayContextDTO::from))
+}
+
+/// Turn off auto-advance; playback stops at the end of the current track
+#[tauri::command]
+pub fn clear_play_context(playback_state: State<'_, PlaybackState>) -> Result<(), String> {
+    *playback_state.context.lock().unwrap() = None;
+    Ok(())
+}
+
+/// Play only `start_ms..end_ms` of a track, looping it when `looped` is set (e.g. to
+/// audition a breakdown or the outro bars). Loads the track if it isn't loaded already.
+/// Without looping, playback stops at the section end ("audio-ended", no auto-advance).
+#[tauri::command]
+pub async fn play_section(
+    app: AppHandle,
+    app_state: State<'_, crate::commands::library::AppState>,
+    playback_state: State<'_, PlaybackState>,
+    track_id: i64,
+    start_ms: u64,
+    end_ms: u64,
+    looped: bool,
+) -> Result<PlaybackStatus, String> {
+    if end_ms <= start_ms {
+        return Err(format!("Section end ({}ms) must be after its start ({}ms)", end_ms, start_ms));
+    }
+
+    let loaded = *playback_state.current_track_id.lock()
+        .map_err(|e| format!("Failed to lock track ID: {}", e))? == Some(track_id);
+    if loaded {
+        // Stop the running task before moving the decoder
+        {
+            let mut gen = playback_state.task_generation.lock()
+                .map_err(|e| format!("Failed to lock generation: {}", e))?;
+            *gen += 1;
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+    } else {
+        let db_lock = app_state.db.lock()
+            .map_err(|e| format!("Failed to lock database: {}", e))?;
+        let db = db_lock.as_ref()
+            .ok_or_else(|| "Database not initialized".to_string())?;
+        load_track_into(db, &playback_state, track_id)?;
+    }
+
+    {
+        let mut decoder_lock = playback_state.decoder.lock()
+            .map_err(|e| format!("Failed to lock decoder: {}", e))?;
+        let decoder = decoder_lock.as_mut().ok_or("No track loaded")?;
+        let duration_ms = decoder.duration_ms();
+        if duration_ms > 0 && start_ms >= duration_ms {
+            return Err(format!("Section start ({}ms) is past the end of the track ({}ms)", start_ms, duration_ms));
+        }
+        decoder.seek(start_ms)?;
+    }
+
+    {
+        let mut section = playback_state.section.lock()
+            .map_err(|e| format!("Failed to lock section: {}", e))?;
+        *section = Some(PlaySection { start_ms, end_ms, looped });
+    }
+
+    start_playback(app, &playback_state)?;
+    get_playback_status(playback_state).await
+}
+
+/// End a section preview; playback carries on through the rest of the track
+#[tauri::command]
+pub fn clear_play_section(playback_state: State<'_, PlaybackState>) -> Result<(), String> {
+    *playback_state.section.lock().unwrap() = None;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_play_context_advance() {
+        let mut context = PlayContext {
+            kind: PlayContextKind::Playlist,
+            id: Some("1".to_string()),
+            track_ids: vec![10, 20, 30],
+            position: 1,
+        };
+        assert_eq!(context.current(), Some(20));
+        assert_eq!(context.advance(), Some(30));
+        assert_eq!(context.position, 2);
+        assert_eq!(context.advance(), None);
+        assert_eq!(context.position, 2);
+    }
+
+    #[test]
+    fn test_play_section_bounds() {
+        let section = PlaySection { start_ms: 10_000, end_ms: 20_000, looped: true };
+        assert!(!section.is_over(19_999, false));
+        assert!(section.is_over(20_000, false));
+        assert!(section.is_over(15_000, true));
+        assert!(section.contains(10_000));
+        assert!(!section.contains(20_000));
+        assert!(!section.contains(5_000));
+    }
+
+    #[test]
+    fn test_position_clock() {
+        let mut clock = PositionClock::new(5_000);
+        assert_eq!(clock.at(0.0), (5_000, 0));
+
+        clock.push(5_000, 100.0, 0.0);
+        clock.push(5_100, 100.0, 0.0);
+        assert_eq!(clock.at(50.0), (5_050, 150));
+        assert_eq!(clock.at(150.0), (5_150, 50));
+
+        // Looped back to a section start: the stream keeps going, the track position jumps
+        clock.push(1_000, 100.0, 180.0);
+        assert_eq!(clock.at(250.0), (1_050, 50));
+        // Past everything sent: clamp to the end of the last chunk
+        assert_eq!(clock.at(400.0), (1_100, 0));
+
+        // Sent after an underrun: heard from when it was sent
+        clock.push(1_100, 100.0, 500.0);
+        assert_eq!(clock.at(550.0), (1_150, 50));
+    }
+
+    #[test]
+    fn test_chunk_merger() {
+        // 441 frames at 44.1 kHz = 10 ms
+        let chunk = |position_ms: u64, sample_rate: u32| AudioChunk {
+            samples: vec![0.25; 882],
+            sample_rate,
+            position_ms,
+            duration_ms: 60_000,
+            is_end: false,
+        };
+
+        let mut merger = ChunkMerger::new(25);
+        assert!(merger.add(chunk(0, 44_100)).is_none());
+        assert!(merger.add(chunk(10, 44_100)).is_none());
+        let merged = merger.add(chunk(20, 44_100)).unwrap();
+        assert_eq!((merged.position_ms, merged.samples.len()), (0, 882 * 3));
+
+        assert!(merger.add(chunk(30, 44_100)).is_none());
+        // A different sample rate isn't merged in
+        assert_eq!(merger.add(chunk(40, 48_000)).unwrap().position_ms, 30);
+        assert_eq!(merger.flush().unwrap().sample_rate, 48_000);
+        assert!(merger.flush().is_none());
+
+        // 0 ms: every packet is its own chunk
+        assert!(ChunkMerger::new(0).add(chunk(0, 44_100)).is_some());
+        assert_eq!(chunk(0, 44_100).to_i16().samples[0], 8192);
+    }
+}
@@ -0,0 +1,343 @@
+// Target-duration playlist builder
+// build_playlist_to_duration picks tracks matching seed filters (genre, BPM range, keys,
+// energy) whose total length comes as close as possible to a target, e.g. a one-hour
+// radio show or a 90-minute warm-up slot. The selection is a subset-sum over whole
+// seconds; the result is a candidate list the frontend can save as a playlist. Tracks in
+// cooldown (see set_track_cooldown) are left out unless the filters ask for them.
+
+use crate::audio::key::{camelot_compatible, parse_camelot};
+use crate::commands::custom_fields::CustomFieldFilter;
+use crate::commands::genre::genre_merge_key;
+use crate::commands::key_colors::KeyColors;
+use crate::commands::library::{AppState, TrackDTO};
+use crate::db::GenreProfile;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+/// The selection may run over the target by at most this much
+const MAX_OVERSHOOT_SECS: u64 = 5 * 60;
+/// Longest target accepted (keeps the subset-sum table small)
+const MAX_TARGET_MINUTES: u32 = 12 * 60;
+
+/// Which tracks may be picked. Empty lists and missing bounds don't filter.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SeedFilters {
+    /// Any of these genres (matched ignoring case, spacing and punctuation)
+    pub genres: Vec<String>,
+    pub min_bpm: Option<f64>,
+    pub max_bpm: Option<f64>,
+    /// Camelot keys (e.g. "8A")
+    pub keys: Vec<String>,
+    /// Also accept keys that mix harmonically with `keys`
+    pub harmonic_keys: bool,
+    pub min_energy: Option<i32>,
+    pub max_energy: Option<i32>,
+    /// Minimum star rating (0-5)
+    pub min_rating: Option<i32>,
+    /// Also pick tracks whose cooldown date hasn't passed yet
+    pub include_cooled_down: bool,
+    /// Conditions on user-defined fields (all must match)
+    pub custom_fields: Vec<CustomFieldFilter>,
+    /// Fill a missing BPM range and keys from the chosen genres' learned profiles
+    /// (see learn_genre_profiles)
+    pub use_genre_profiles: bool,
+}
+
+impl SeedFilters {
+    /// Missing BPM bounds become the widest typical range of the chosen genres, missing
+    /// keys their common keys. Genres without a profile add nothing.
+    fn fill_from_genre_profiles(&mut self, profiles: &[GenreProfile]) {
+        let wanted: Vec<String> = self.genres.iter().map(|g| genre_merge_key(g)).collect();
+        let chosen: Vec<&GenreProfile> = profiles
+            .iter()
+            .filter(|p| wanted.contains(&genre_merge_key(&p.genre)))
+            .collect();
+
+        if self.min_bpm.is_none() && self.max_bpm.is_none() {
+            self.min_bpm = chosen.iter().filter_map(|p| p.min_bpm).reduce(f64::min);
+            self.max_bpm = chosen.iter().filter_map(|p| p.max_bpm).reduce(f64::max);
+        }
+        if self.keys.is_empty() {
+            for key in chosen.iter().flat_map(|p| &p.common_keys) {
+                if !self.keys.contains(key) {
+                    self.keys.push(key.clone());
+                }
+            }
+        }
+    }
+
+    fn matches(&self, track: &TrackDTO) -> bool {
+        if !self.genres.is_empty() {
+            let Some(genre) = track.genre.as_deref().map(genre_merge_key) else {
+                return false;
+            };
+            if !self.genres.iter().any(|g| genre_merge_key(g) == genre) {
+                return false;
+            }
+        }
+
+        if self.min_bpm.is_some() || self.max_bpm.is_some() {
+            let Some(bpm) = track.bpm.filter(|b| *b > 0.0) else {
+                return false;
+            };
+            if self.min_bpm.is_some_and(|min| bpm < min) || self.max_bpm.is_some_and(|max| bpm > max) {
+                return false;
+            }
+        }
+
+        if !self.keys.is_empty() {
+            let Some(key) = track.musical_key.as_deref().filter(|k| parse_camelot(k).is_some()) else {
+                return false;
+            };
+            let key_matches = |wanted: &String| {
+                wanted.eq_ignore_ascii_case(key) || (self.harmonic_keys && camelot_compatible(wanted, key))
+            };
+            if !self.keys.iter().any(key_matches) {
+                return false;
+            }
+        }
+
+        if self.min_energy.is_some() || self.max_energy.is_some() {
+            let Some(energy) = track.energy_level else {
+                return false;
+            };
+            if self.min_energy.is_some_and(|min| energy < min) || self.max_energy.is_some_and(|max| energy > max) {
+                return false;
+            }
+        }
+
+        self.min_rating.is_none_or(|min| track.rating >= min)
+    }
+}
+
+/// Candidate set returned by build_playlist_to_duration
+#[derive(Debug, Clone, Serialize)]
+pub struct DurationPlaylistDTO {
+    /// Picked tracks, ordered by BPM then energy (a gradual build)
+    pub tracks: Vec<TrackDTO>,
+    pub total_duration_ms: i64,
+    pub target_duration_ms: i64,
+    /// Tracks that matched the filters
+    pub matching_tracks: usize,
+}
+
+/// Pick items whose durations (seconds) sum as close to `target` as possible, going over
+/// by at most MAX_OVERSHOOT_SECS (ties prefer staying under). Earlier items are preferred
+/// when several subsets reach the same total. Returns item indices.
+fn pick_to_duration(durations: &[u64], target: u64) -> Vec<usize> {
+    let limit = (target + MAX_OVERSHOOT_SECS) as usize;
+    // reached_by[s] = the item that first made total s reachable; walking back from s
+    // through those items reconstructs the subset
+    let mut reached_by: Vec<Option<usize>> = vec![None; limit + 1];
+    let mut reachable = vec![false; limit + 1];
+    reachable[0] = true;
+
+    for (i, &duration) in durations.iter().enumerate() {
+        let d = duration as usize;
+        if d == 0 || d > limit {
+            continue;
+        }
+        for sum in (d..=limit).rev() {
+            if !reachable[sum] && reachable[sum - d] {
+                reachable[sum] = true;
+                reached_by[sum] = Some(i);
+            }
+        }
+    }
+
+    let target = target as usize;
+    let best = (0..=limit)
+        .filter(|&sum| reachable[sum])
+        .min_by_key(|&sum| (sum.abs_diff(target), sum > target))
+        .unwrap_or(0);
+
+    let mut picked = Vec::new();
+    let mut sum = best;
+    while let Some(i) = reached_by[sum] {
+        picked.push(i);
+        sum -= durations[i] as usize;
+    }
+    picked.reverse();
+    picked
+}
+
+/// Select tracks matching `seed_filters` whose total duration best approximates
+/// `target_minutes`. Higher-rated, less-played tracks are preferred. Nothing is saved.
+#[tauri::command]
+pub fn build_playlist_to_duration(
+    state: State<AppState>,
+    mut seed_filters: SeedFilters,
+    target_minutes: u32,
+) -> Result<DurationPlaylistDTO, String> {
+    if target_minutes == 0 || target_minutes > MAX_TARGET_MINUTES {
+        return Err(format!("Target duration must be 1-{} minutes", MAX_TARGET_MINUTES));
+    }
+
+    let mut candidates = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        if seed_filters.use_genre_profiles {
+            let profiles = db.get_genre_profiles()
+                .map_err(|e| format!("Failed to get genre profiles: {}", e))?;
+            seed_filters.fill_from_genre_profiles(&profiles);
+        }
+        let rows = db.get_all_tracks_with_analysis()
+            .map_err(|e| format!("Failed to get tracks: {}", e))?;
+        let cooled_down = if seed_filters.include_cooled_down {
+            Default::default()
+        } else {
+            db.get_active_cooldowns()
+                .map_err(|e| format!("Failed to get cooldowns: {}", e))?
+        };
+
+        let key_colors = KeyColors::from_settings(db);
+        let candidates: Vec<TrackDTO> = rows.into_iter()
+            .map(|(track, bpm, bpm_conf, key, key_conf)| {
+                let mut dto = TrackDTO::from(track);
+                dto.bpm = bpm;
+                dto.bpm_confidence = bpm_conf;
+                dto.musical_key = key;
+                dto.key_confidence = key_conf;
+                dto.key_color = key_colors.resolve(dto.musical_key.as_deref());
+                dto
+            })
+            .filter(|t| t.duration_ms.is_some_and(|d| d > 0))
+            .filter(|t| !t.id.is_some_and(|id| cooled_down.contains_key(&id)))
+            .filter(|t| seed_filters.matches(t))
+            .collect();
+        if seed_filters.custom_fields.is_empty() {
+            candidates
+        } else {
+            let values = db.get_all_custom_field_values()
+                .map_err(|e| format!("Failed to get custom field values: {}", e))?;
+            candidates
+                .into_iter()
+                .filter(|t| {
+                    let track_values = t.id.and_then(|id| values.get(&id));
+                    seed_filters.custom_fields.iter().all(|f| f.matches(track_values))
+                })
+                .collect()
+        }
+    };
+    let matching_tracks = candidates.len();
+
+    candidates.sort_by(|a, b| b.rating.cmp(&a.rating).then(a.play_count.cmp(&b.play_count)));
+    let durations: Vec<u64> = candidates
+        .iter()
+        .map(|t| (t.duration_ms.unwrap_or(0) as u64 + 500) / 1000)
+        .collect();
+    let picked = pick_to_duration(&durations, target_minutes as u64 * 60);
+
+    let mut tracks: Vec<TrackDTO> = picked.into_iter().map(|i| candidates[i].clone()).collect();
+    tracks.sort_by(|a, b| {
+        a.bpm
+            .unwrap_or(0.0)
+            .total_cmp(&b.bpm.unwrap_or(0.0))
+            .then(a.energy_level.cmp(&b.energy_level))
+    });
+    let total_duration_ms = tracks.iter().map(|t| t.duration_ms.unwrap_or(0) as i64).sum();
+
+    eprintln!(
+        "[playlist_builder] {} of {} matching tracks, {:.1} of {} minutes",
+        tracks.len(),
+        matching_tracks,
+        total_duration_ms as f64 / 60_000.0,
+        target_minutes
+    );
+
+    Ok(DurationPlaylistDTO {
+        tracks,
+        total_duration_ms,
+        target_duration_ms: target_minutes as i64 * 60_000,
+        matching_tracks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{create_test_track, Track};
+
+    #[test]
+    fn test_pick_to_duration() {
+        // 60 minutes from tracks of 7:00, 6:30, 8:00, ... (no subset hits 3600 exactly)
+        let durations = [420, 390, 480, 300, 510, 445, 600, 360, 405, 330];
+        let picked = pick_to_duration(&durations, 3600);
+        let total: u64 = picked.iter().map(|&i| durations[i]).sum();
+        assert_eq!(total, 3610);
+        let mut unique = picked.clone();
+        unique.dedup();
+        assert_eq!(unique.len(), picked.len());
+
+        // Can't reach the target: closest total, slightly over beats far under
+        assert_eq!(pick_to_duration(&[400, 400], 700), vec![0, 1]);
+        assert_eq!(pick_to_duration(&[400, 400], 1200), vec![0, 1]);
+        // Ties prefer staying under the target
+        assert_eq!(pick_to_duration(&[290, 310], 300), vec![0]);
+        assert!(pick_to_duration(&[], 300).is_empty());
+    }
+
+    #[test]
+    fn test_seed_filters() {
+        let mut track = TrackDTO::from(Track {
+            id: Some(1),
+            file_path: "/music/a.mp3".to_string(),
+            file_hash: "a".to_string(),
+            duration_ms: Some(400_000),
+            genre: Some("Deep House".to_string()),
+            energy_level: Some(5),
+            ..create_test_track()
+        });
+        track.bpm = Some(122.0);
+        track.musical_key = Some("8A".to_string());
+        let filters = SeedFilters {
+            genres: vec!["deep-house".to_string()],
+            min_bpm: Some(118.0),
+            max_bpm: Some(124.0),
+            keys: vec!["9A".to_string()],
+            harmonic_keys: true,
+            ..SeedFilters::default()
+        };
+        assert!(filters.matches(&track));
+        assert!(!SeedFilters { harmonic_keys: false, ..filters.clone() }.matches(&track));
+        assert!(!SeedFilters { max_bpm: Some(120.0), ..filters.clone() }.matches(&track));
+        assert!(!SeedFilters { min_energy: Some(6), ..filters }.matches(&track));
+    }
+
+    #[test]
+    fn test_fill_from_genre_profiles() {
+        let profile = |genre: &str, min, max, keys: &[&str]| GenreProfile {
+            genre: genre.to_string(),
+            track_count: 10,
+            min_bpm: Some(min),
+            median_bpm: None,
+            max_bpm: Some(max),
+            common_keys: keys.iter().map(|k| k.to_string()).collect(),
+        };
+        let profiles = vec![
+            profile("Deep House", 118.0, 124.0, &["8A", "5A"]),
+            profile("Tech House", 123.0, 127.0, &["8A", "10A"]),
+            profile("Techno", 128.0, 138.0, &["1A"]),
+        ];
+
+        let mut filters = SeedFilters {
+            genres: vec!["deep house".to_string(), "Tech-House".to_string()],
+            ..SeedFilters::default()
+        };
+        filters.fill_from_genre_profiles(&profiles);
+        assert_eq!((filters.min_bpm, filters.max_bpm), (Some(118.0), Some(127.0)));
+        assert_eq!(filters.keys, vec!["8A", "5A", "10A"]);
+
+        // Set values are kept
+        let mut filters = SeedFilters {
+            genres: vec!["Techno".to_string()],
+            max_bpm: Some(130.0),
+            keys: vec!["2A".to_string()],
+            ..SeedFilters::default()
+        };
+        filters.fill_from_genre_profiles(&profiles);
+        assert_eq!((filters.min_bpm, filters.max_bpm), (None, Some(130.0)));
+        assert_eq!(filters.keys, vec!["2A"]);
+    }
+}
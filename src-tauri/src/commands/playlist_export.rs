@@ -0,0 +1,371 @@
+// Playlist mirror export — writes every playlist as an .m3u file into a chosen folder
+// (folders become directories), so Apple Music, Finder and other players see the crates.
+// Commands that change playlists emit "playlists-changed"; the listener re-exports
+// after a short debounce. Only files whose content changed are rewritten, and files
+// from playlists that no longer exist are removed (tracked in a manifest file).
+
+use crate::commands::library::AppState;
+use crate::db::{Database, Playlist};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Listener, Manager, State};
+
+/// Setting key: folder the playlists are mirrored into (unset = export off)
+pub const PLAYLIST_EXPORT_FOLDER_SETTING: &str = "playlist_export_folder";
+/// Event emitted by commands that change playlists or their tracks
+pub const PLAYLISTS_CHANGED_EVENT: &str = "playlists-changed";
+/// Relative paths of the files written by the last export, one per line
+const MANIFEST_FILE: &str = ".recodeck-playlists";
+/// Changes within this window are exported together
+const EXPORT_DEBOUNCE: Duration = Duration::from_secs(2);
+/// Longest file or directory name written (most filesystems allow 255 bytes)
+const MAX_NAME_CHARS: usize = 120;
+
+/// Managed state: whether an export is already scheduled
+pub struct PlaylistExportState {
+    scheduled: AtomicBool,
+}
+
+impl Default for PlaylistExportState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlaylistExportState {
+    pub fn new() -> Self {
+        Self {
+            scheduled: AtomicBool::new(false),
+        }
+    }
+}
+
+/// Outcome of an export
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct PlaylistExportSummaryDTO {
+    pub playlists: usize,
+    /// Files created or rewritten
+    pub written: usize,
+    pub unchanged: usize,
+    /// Files of deleted or renamed playlists removed
+    pub removed: usize,
+}
+
+/// Re-export whenever playlists change (called once from setup)
+pub fn start_listener(app: &AppHandle) {
+    let handle = app.clone();
+    app.listen(PLAYLISTS_CHANGED_EVENT, move |_| schedule_export(&handle));
+}
+
+/// Export after EXPORT_DEBOUNCE, unless an export is already scheduled
+/// (it will pick up this change too)
+fn schedule_export(app: &AppHandle) {
+    if app.state::<PlaylistExportState>().scheduled.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app = app.clone();
+    thread::spawn(move || {
+        thread::sleep(EXPORT_DEBOUNCE);
+        app.state::<PlaylistExportState>().scheduled.store(false, Ordering::SeqCst);
+        let state = app.state::<AppState>();
+        match run_export(&state) {
+            Ok(Some(summary)) if summary.written + summary.removed > 0 => eprintln!(
+                "[playlist_export] Exported {} playlists ({} written, {} removed)",
+                summary.playlists, summary.written, summary.removed
+            ),
+            Ok(_) => {}
+            Err(e) => eprintln!("[playlist_export] Export failed: {}", e),
+        }
+    });
+}
+
+/// Export to the configured folder. None when export is off.
+fn run_export(state: &AppState) -> Result<Option<PlaylistExportSummaryDTO>, String> {
+    // Build the files under the lock, write them without it
+    let (root, files) = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let root = match db.get_setting(PLAYLIST_EXPORT_FOLDER_SETTING)
+            .map_err(|e| format!("Failed to get export folder: {}", e))?
+        {
+            Some(root) => root,
+            None => return Ok(None),
+        };
+        (root, plan_export(db)?)
+    };
+    write_export(Path::new(&root), &files).map(Some)
+}
+
+/// File or directory name for a playlist: no path separators or characters
+/// Windows/Finder reject, not hidden, not empty
+fn safe_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_NAME_CHARS)
+        .collect();
+    let cleaned = cleaned.trim().trim_end_matches('.').trim_start_matches('.').trim();
+    if cleaned.is_empty() {
+        "Untitled".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// Extended M3U for one playlist: absolute file paths with duration and "Artist - Title"
+fn render_m3u(db: &Database, playlist: &Playlist) -> Result<String, String> {
+    let id = playlist.id.unwrap_or(0);
+    let tracks = db.get_playlist_tracks(id)
+        .map_err(|e| format!("Failed to get tracks of playlist {}: {}", id, e))?;
+    let one_line = |text: &str| text.replace(['\r', '\n'], " ");
+
+    let mut m3u = format!("#EXTM3U\n#PLAYLIST:{}\n", one_line(&playlist.name));
+    for (track, ..) in tracks {
+        let duration_secs = track.duration_ms.map(|ms| ms / 1000).unwrap_or(-1);
+        let title = match (&track.artist, &track.title) {
+            (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+            (None, Some(title)) => title.clone(),
+            _ => Path::new(&track.file_path)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        };
+        m3u.push_str(&format!(
+            "#EXTINF:{},{}\n{}\n",
+            duration_secs,
+            one_line(&title),
+            track.file_path
+        ));
+    }
+    Ok(m3u)
+}
+
+/// Every playlist's relative .m3u path and content. Playlists with children
+/// (folders, mirrored folder playlists) become directories; sibling name clashes
+/// get " (2)", " (3)"... in creation order.
+fn plan_export(db: &Database) -> Result<Vec<(PathBuf, String)>, String> {
+    let mut playlists = db.get_all_playlists()
+        .map_err(|e| format!("Failed to get playlists: {}", e))?;
+    playlists.sort_by_key(|p| p.id);
+    let by_id: HashMap<i64, &Playlist> = playlists.iter().filter_map(|p| p.id.map(|id| (id, p))).collect();
+    let has_children: HashSet<i64> = playlists.iter().filter_map(|p| p.parent_id).collect();
+
+    // Unique names per parent directory, separately for directories and .m3u files
+    let mut dir_names: HashMap<i64, String> = HashMap::new();
+    let mut file_names: HashMap<i64, String> = HashMap::new();
+    let mut taken: HashSet<(Option<i64>, bool, String)> = HashSet::new();
+    for playlist in &playlists {
+        let id = match playlist.id {
+            Some(id) => id,
+            None => continue,
+        };
+        let parent = playlist.parent_id.filter(|p| by_id.contains_key(p));
+        let base = safe_file_name(&playlist.name);
+        let mut unique = |is_dir: bool| {
+            let mut name = base.clone();
+            let mut n = 2;
+            while !taken.insert((parent, is_dir, name.to_lowercase())) {
+                name = format!("{} ({})", base, n);
+                n += 1;
+            }
+            name
+        };
+        if has_children.contains(&id) {
+            dir_names.insert(id, unique(true));
+        }
+        if playlist.playlist_type != "folder" {
+            file_names.insert(id, unique(false));
+        }
+    }
+
+    let mut files = Vec::new();
+    for playlist in &playlists {
+        let id = match playlist.id {
+            Some(id) => id,
+            None => continue,
+        };
+        let file_name = match file_names.get(&id) {
+            Some(name) => name,
+            None => continue,
+        };
+        // Ancestor directories, bounded in case of a parent cycle
+        let mut dirs = Vec::new();
+        let mut parent = playlist.parent_id;
+        while let Some(parent_id) = parent {
+            if dirs.len() > by_id.len() {
+                break;
+            }
+            match (by_id.get(&parent_id), dir_names.get(&parent_id)) {
+                (Some(p), Some(dir)) => {
+                    dirs.push(dir.clone());
+                    parent = p.parent_id;
+                }
+                _ => break,
+            }
+        }
+        let mut path: PathBuf = dirs.iter().rev().collect();
+        path.push(format!("{}.m3u", file_name));
+        files.push((path, render_m3u(db, playlist)?));
+    }
+    Ok(files)
+}
+
+/// Write `files` under `root`, skipping unchanged ones, and remove files written by
+/// the previous export that are no longer part of it (plus directories left empty).
+fn write_export(root: &Path, files: &[(PathBuf, String)]) -> Result<PlaylistExportSummaryDTO, String> {
+    fs::create_dir_all(root)
+        .map_err(|e| format!("Failed to create {}: {}", root.display(), e))?;
+    let mut summary = PlaylistExportSummaryDTO {
+        playlists: files.len(),
+        ..Default::default()
+    };
+
+    for (relative, content) in files {
+        let path = root.join(relative);
+        if fs::read_to_string(&path).ok().as_deref() == Some(content.as_str()) {
+            summary.unchanged += 1;
+            continue;
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        // Write then rename, so readers never see a half-written playlist
+        let tmp = path.with_extension("m3u.tmp");
+        fs::write(&tmp, content)
+            .and_then(|_| fs::rename(&tmp, &path))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        summary.written += 1;
+    }
+
+    let manifest_path = root.join(MANIFEST_FILE);
+    let current: HashSet<String> = files
+        .iter()
+        .map(|(relative, _)| relative.to_string_lossy().to_string())
+        .collect();
+    let previous = fs::read_to_string(&manifest_path).unwrap_or_default();
+    for stale in previous.lines().filter(|l| !l.is_empty() && !current.contains(*l)) {
+        let path = root.join(stale);
+        if fs::remove_file(&path).is_ok() {
+            summary.removed += 1;
+        }
+        // Remove directories left empty, up to the root (remove_dir fails on non-empty ones)
+        let mut dir = path.parent();
+        while let Some(d) = dir.filter(|d| *d != root && d.starts_with(root)) {
+            if fs::remove_dir(d).is_err() {
+                break;
+            }
+            dir = d.parent();
+        }
+    }
+
+    let mut manifest: Vec<&String> = current.iter().collect();
+    manifest.sort();
+    let manifest: String = manifest.into_iter().map(|l| format!("{}\n", l)).collect();
+    fs::write(&manifest_path, manifest)
+        .map_err(|e| format!("Failed to write {}: {}", manifest_path.display(), e))?;
+
+    Ok(summary)
+}
+
+/// Folder playlists are exported into, if export is on
+#[tauri::command]
+pub fn get_playlist_export_folder(state: State<AppState>) -> Result<Option<String>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.get_setting(PLAYLIST_EXPORT_FOLDER_SETTING)
+        .map_err(|e| format!("Failed to get export folder: {}", e))
+}
+
+/// Set the export folder (None turns export off; files already written are kept)
+/// and export into it right away
+#[tauri::command]
+pub fn set_playlist_export_folder(
+    state: State<AppState>,
+    folder: Option<String>,
+) -> Result<Option<PlaylistExportSummaryDTO>, String> {
+    {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        match &folder {
+            Some(folder) => {
+                if !Path::new(folder).is_dir() {
+                    return Err(format!("Path is not a directory: {}", folder));
+                }
+                db.set_setting(PLAYLIST_EXPORT_FOLDER_SETTING, folder)
+            }
+            None => db.delete_setting(PLAYLIST_EXPORT_FOLDER_SETTING),
+        }
+        .map_err(|e| format!("Failed to save export folder: {}", e))?;
+    }
+    run_export(&state)
+}
+
+/// Export all playlists now. None when no export folder is set.
+#[tauri::command]
+pub fn export_playlists_m3u(state: State<AppState>) -> Result<Option<PlaylistExportSummaryDTO>, String> {
+    run_export(&state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{create_test_track, Track};
+
+    fn track(path: &str, title: &str) -> Track {
+        Track {
+            file_path: path.to_string(),
+            file_hash: path.to_string(),
+            title: Some(title.to_string()),
+            artist: Some("Artist".to_string()),
+            duration_ms: Some(185_000),
+            ..create_test_track()
+        }
+    }
+
+    #[test]
+    fn test_safe_file_name() {
+        assert_eq!(safe_file_name("House / Tech: Peak?"), "House _ Tech_ Peak_");
+        assert_eq!(safe_file_name(".hidden"), "hidden");
+        assert_eq!(safe_file_name("  ..  "), "Untitled");
+    }
+
+    #[test]
+    fn test_export_mirrors_playlist_tree() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let track_id = db.create_track(&track("/music/one.mp3", "One")).unwrap();
+        let folder = db.create_playlist("Gigs", "folder", None).unwrap();
+        let set = db.create_playlist("Warmup", "manual", Some(folder)).unwrap();
+        db.create_playlist("Warmup", "manual", Some(folder)).unwrap();
+        db.add_track_to_playlist(set, track_id).unwrap();
+
+        let files = plan_export(&db).unwrap();
+        let paths: Vec<String> = files.iter().map(|(p, _)| p.to_string_lossy().to_string()).collect();
+        assert_eq!(paths, vec!["Gigs/Warmup.m3u", "Gigs/Warmup (2).m3u"]);
+        assert_eq!(files[0].1, "#EXTM3U\n#PLAYLIST:Warmup\n#EXTINF:185,Artist - One\n/music/one.mp3\n");
+
+        let root = tempfile::TempDir::new().unwrap();
+        let summary = write_export(root.path(), &files).unwrap();
+        assert_eq!((summary.written, summary.unchanged, summary.removed), (2, 0, 0));
+        let summary = write_export(root.path(), &files).unwrap();
+        assert_eq!((summary.written, summary.unchanged), (0, 2));
+
+        // Renaming the folder moves its playlists and cleans up the old directory
+        db.rename_playlist(folder, "Shows").unwrap();
+        let summary = write_export(root.path(), &plan_export(&db).unwrap()).unwrap();
+        assert_eq!((summary.written, summary.removed), (2, 2));
+        assert!(!root.path().join("Gigs").exists());
+        assert!(root.path().join("Shows/Warmup.m3u").exists());
+    }
+}
@@ -1,8 +1,10 @@
 // Tauri commands for playlist management
 
+use crate::commands::key_colors::KeyColors;
 use crate::commands::library::{AppState, TrackDTO};
+use crate::db::{Database, PlaylistSnapshot};
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
 
 /// Serializable playlist for frontend
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,11 +16,13 @@ pub struct PlaylistDTO {
     pub track_count: i64,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
+    pub source_folder: Option<String>,
 }
 
 /// Create a new playlist (type = "manual")
 #[tauri::command]
 pub fn create_playlist(
+    app: AppHandle,
     state: State<AppState>,
     name: String,
     parent_id: Option<i64>,
@@ -33,6 +37,7 @@ pub fn create_playlist(
     let playlist = db
         .get_playlist(id)
         .map_err(|e| format!("Failed to get playlist: {}", e))?;
+    notify_playlists_changed(&app);
 
     Ok(PlaylistDTO {
         id: playlist.id,
@@ -42,12 +47,14 @@ pub fn create_playlist(
         track_count: 0,
         created_at: playlist.created_at,
         updated_at: playlist.updated_at,
+        source_folder: playlist.source_folder,
     })
 }
 
 /// Create a new playlist folder (type = "folder")
 #[tauri::command]
 pub fn create_playlist_folder(
+    app: AppHandle,
     state: State<AppState>,
     name: String,
     parent_id: Option<i64>,
@@ -62,6 +69,7 @@ pub fn create_playlist_folder(
     let playlist = db
         .get_playlist(id)
         .map_err(|e| format!("Failed to get folder: {}", e))?;
+    notify_playlists_changed(&app);
 
     Ok(PlaylistDTO {
         id: playlist.id,
@@ -71,6 +79,7 @@ pub fn create_playlist_folder(
         track_count: 0,
         created_at: playlist.created_at,
         updated_at: playlist.updated_at,
+        source_folder: playlist.source_folder,
     })
 }
 
@@ -100,6 +109,7 @@ pub fn get_all_playlists(state: State<AppState>) -> Result<Vec<PlaylistDTO>, Str
             track_count,
             created_at: p.created_at,
             updated_at: p.updated_at,
+            source_folder: p.source_folder,
         });
     }
 
@@ -108,22 +118,26 @@ pub fn get_all_playlists(state: State<AppState>) -> Result<Vec<PlaylistDTO>, Str
 
 /// Rename a playlist or folder
 #[tauri::command]
-pub fn rename_playlist(state: State<AppState>, id: i64, name: String) -> Result<(), String> {
+pub fn rename_playlist(app: AppHandle, state: State<AppState>, id: i64, name: String) -> Result<(), String> {
     let db_lock = state.db.lock().unwrap();
     let db = db_lock.as_ref().ok_or("Database not initialized")?;
 
     db.rename_playlist(id, &name)
-        .map_err(|e| format!("Failed to rename: {}", e))
+        .map_err(|e| format!("Failed to rename: {}", e))?;
+    notify_playlists_changed(&app);
+    Ok(())
 }
 
 /// Delete a playlist or folder (and its children/track associations)
 #[tauri::command]
-pub fn delete_playlist(state: State<AppState>, id: i64) -> Result<(), String> {
+pub fn delete_playlist(app: AppHandle, state: State<AppState>, id: i64) -> Result<(), String> {
     let db_lock = state.db.lock().unwrap();
     let db = db_lock.as_ref().ok_or("Database not initialized")?;
 
     db.delete_playlist(id)
-        .map_err(|e| format!("Failed to delete: {}", e))
+        .map_err(|e| format!("Failed to delete: {}", e))?;
+    notify_playlists_changed(&app);
+    Ok(())
 }
 
 /// Get tracks in a playlist (with analysis data)
@@ -136,6 +150,7 @@ pub fn get_playlist_tracks(state: State<AppState>, playlist_id: i64) -> Result<V
         .get_playlist_tracks(playlist_id)
         .map_err(|e| format!("Failed to get playlist tracks: {}", e))?;
 
+    let key_colors = KeyColors::from_settings(db);
     Ok(rows
         .into_iter()
         .map(|(track, bpm, bpm_conf, musical_key, key_conf)| {
@@ -144,28 +159,131 @@ pub fn get_playlist_tracks(state: State<AppState>, playlist_id: i64) -> Result<V
             dto.bpm_confidence = bpm_conf;
             dto.musical_key = musical_key;
             dto.key_confidence = key_conf;
+            dto.key_color = key_colors.resolve(dto.musical_key.as_deref());
             dto
         })
         .collect())
 }
 
-/// Add a track to a playlist
+/// Another version of a song that's already in the playlist
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateVersionDTO {
+    pub track_id: i64,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// "title_artist" or "fingerprint"
+    pub reason: String,
+}
+
+/// Outcome of add_track_to_playlist
+#[derive(Debug, Clone, Serialize)]
+pub struct AddTrackResultDTO {
+    /// False if the track was already in the playlist, or held back by the version check
+    pub added: bool,
+    /// Set when the version check found other versions of the song in the playlist;
+    /// the track was not added (ask, then call again without the check)
+    pub possible_duplicate: bool,
+    pub duplicate_versions: Vec<DuplicateVersionDTO>,
+}
+
+/// Add a track to a playlist. Adding the same track twice is ignored. With
+/// `check_versions`, a track whose song is already in the playlist as a different file
+/// (same title/artist or fingerprint) is not added and comes back as a possible duplicate.
 #[tauri::command]
 pub fn add_track_to_playlist(
+    app: AppHandle,
     state: State<AppState>,
     playlist_id: i64,
     track_id: i64,
-) -> Result<(), String> {
+    check_versions: Option<bool>,
+) -> Result<AddTrackResultDTO, String> {
     let db_lock = state.db.lock().unwrap();
     let db = db_lock.as_ref().ok_or("Database not initialized")?;
 
+    ensure_editable(db, playlist_id)?;
+
+    if check_versions.unwrap_or(false) {
+        let versions = db.find_playlist_versions(playlist_id, track_id)
+            .map_err(|e| format!("Failed to check for other versions: {}", e))?;
+        if !versions.is_empty() {
+            let duplicate_versions = versions
+                .into_iter()
+                .filter_map(|(id, reason)| {
+                    let track = db.get_track(id).ok()?;
+                    Some(DuplicateVersionDTO {
+                        track_id: id,
+                        title: track.title,
+                        artist: track.artist,
+                        reason: reason.to_string(),
+                    })
+                })
+                .collect();
+            return Ok(AddTrackResultDTO {
+                added: false,
+                possible_duplicate: true,
+                duplicate_versions,
+            });
+        }
+    }
+
+    let count_before = db.count_playlist_tracks(playlist_id)
+        .map_err(|e| format!("Failed to count playlist tracks: {}", e))?;
     db.add_track_to_playlist(playlist_id, track_id)
-        .map_err(|e| format!("Failed to add track: {}", e))
+        .map_err(|e| format!("Failed to add track: {}", e))?;
+    let added = db.count_playlist_tracks(playlist_id)
+        .map_err(|e| format!("Failed to count playlist tracks: {}", e))? > count_before;
+    if added {
+        notify_playlists_changed(&app);
+    }
+
+    Ok(AddTrackResultDTO {
+        added,
+        possible_duplicate: false,
+        duplicate_versions: Vec::new(),
+    })
+}
+
+/// A track already listened to while playing through a playlist
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistAuditionDTO {
+    pub track_id: i64,
+    pub auditioned_at: String,
+}
+
+/// Tracks of a playlist that have been auditioned (played with the playlist as play
+/// context), in playlist order
+#[tauri::command]
+pub fn get_playlist_auditions(state: State<AppState>, playlist_id: i64) -> Result<Vec<PlaylistAuditionDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let auditions = db.get_playlist_auditions(playlist_id)
+        .map_err(|e| format!("Failed to get auditions: {}", e))?;
+    Ok(auditions
+        .into_iter()
+        .map(|(track_id, auditioned_at)| PlaylistAuditionDTO { track_id, auditioned_at })
+        .collect())
+}
+
+/// Clear a playlist's "already reviewed" marks: all of them, or only `track_ids`.
+/// Returns how many were cleared.
+#[tauri::command]
+pub fn reset_playlist_auditions(
+    state: State<AppState>,
+    playlist_id: i64,
+    track_ids: Option<Vec<i64>>,
+) -> Result<usize, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.reset_playlist_auditions(playlist_id, track_ids.as_deref())
+        .map_err(|e| format!("Failed to reset auditions: {}", e))
 }
 
 /// Remove a track from a playlist
 #[tauri::command]
 pub fn remove_track_from_playlist(
+    app: AppHandle,
     state: State<AppState>,
     playlist_id: i64,
     track_id: i64,
@@ -173,6 +291,196 @@ pub fn remove_track_from_playlist(
     let db_lock = state.db.lock().unwrap();
     let db = db_lock.as_ref().ok_or("Database not initialized")?;
 
+    ensure_editable(db, playlist_id)?;
     db.remove_track_from_playlist(playlist_id, track_id)
-        .map_err(|e| format!("Failed to remove track: {}", e))
+        .map_err(|e| format!("Failed to remove track: {}", e))?;
+    notify_playlists_changed(&app);
+    Ok(())
+}
+
+/// A saved state of a playlist's tracks
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistSnapshotDTO {
+    pub id: i64,
+    pub playlist_id: i64,
+    /// In playlist order
+    pub track_ids: Vec<i64>,
+    pub track_count: usize,
+    /// "auto" (before a change), "manual" or "restore" (before a restore)
+    pub reason: String,
+    pub created_at: String,
+}
+
+impl From<PlaylistSnapshot> for PlaylistSnapshotDTO {
+    fn from(snapshot: PlaylistSnapshot) -> Self {
+        PlaylistSnapshotDTO {
+            id: snapshot.id,
+            playlist_id: snapshot.playlist_id,
+            track_count: snapshot.track_ids.len(),
+            track_ids: snapshot.track_ids,
+            reason: snapshot.reason,
+            created_at: snapshot.created_at,
+        }
+    }
+}
+
+/// A playlist's snapshots, newest first. One is taken automatically before each change
+/// (a burst of changes shares one).
+#[tauri::command]
+pub fn get_playlist_history(state: State<AppState>, playlist_id: i64) -> Result<Vec<PlaylistSnapshotDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let snapshots = db.get_playlist_snapshots(playlist_id)
+        .map_err(|e| format!("Failed to get playlist history: {}", e))?;
+    Ok(snapshots.into_iter().map(PlaylistSnapshotDTO::from).collect())
+}
+
+/// Snapshot a playlist now. Returns the snapshot ID, or null if nothing changed since the last one.
+#[tauri::command]
+pub fn snapshot_playlist(state: State<AppState>, playlist_id: i64) -> Result<Option<i64>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.snapshot_playlist(playlist_id, "manual")
+        .map_err(|e| format!("Failed to snapshot playlist: {}", e))
+}
+
+/// Put a playlist back to a snapshot. The current state is snapshotted first, so this can be
+/// undone too. Returns the number of tracks restored (tracks since deleted are skipped).
+#[tauri::command]
+pub fn restore_playlist_snapshot(app: AppHandle, state: State<AppState>, snapshot_id: i64) -> Result<usize, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let snapshot = db.get_playlist_snapshot(snapshot_id)
+        .map_err(|e| format!("Snapshot {} not found: {}", snapshot_id, e))?;
+    ensure_editable(db, snapshot.playlist_id)?;
+
+    let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+    let restored = db.restore_playlist_snapshot(snapshot_id)
+        .map_err(|e| format!("Failed to restore playlist: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit: {}", e))?;
+
+    notify_playlists_changed(&app);
+    Ok(restored)
+}
+
+/// Tell listeners (e.g. the .m3u export) that playlists or their tracks changed
+pub fn notify_playlists_changed(app: &AppHandle) {
+    let _ = app.emit(crate::commands::playlist_export::PLAYLISTS_CHANGED_EVENT, ());
+}
+
+/// Mirror playlists are rebuilt from their folder on every scan, so manual edits
+/// would silently disappear — reject them up front.
+pub(crate) fn ensure_editable(db: &Database, playlist_id: i64) -> Result<(), String> {
+    let playlist = db
+        .get_playlist(playlist_id)
+        .map_err(|e| format!("Failed to get playlist: {}", e))?;
+    if playlist.playlist_type == "mirror" {
+        return Err("This playlist mirrors a folder on disk and can't be edited".to_string());
+    }
+    Ok(())
+}
+
+// --- Folder-mirrored playlists ---
+
+/// Read the list of folders mirrored as playlists from settings
+fn load_mirrored_folders(db: &Database) -> Result<Vec<String>, String> {
+    match db
+        .get_setting("mirrored_folders")
+        .map_err(|e| format!("Failed to get mirrored folders: {}", e))?
+    {
+        Some(json_str) => serde_json::from_str::<Vec<String>>(&json_str)
+            .map_err(|e| format!("Failed to parse mirrored folders JSON: {}", e)),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn save_mirrored_folders(db: &Database, folders: &[String]) -> Result<(), String> {
+    let json_str = serde_json::to_string(folders)
+        .map_err(|e| format!("Failed to serialize mirrored folders: {}", e))?;
+    db.set_setting("mirrored_folders", &json_str)
+        .map_err(|e| format!("Failed to save mirrored folders: {}", e))
+}
+
+/// Rebuild the playlists of every mirrored folder.
+/// Called by the scanner after importing so the sidebar follows the disk layout.
+pub fn sync_mirrored_folders(db: &Database) -> Result<usize, String> {
+    let mut total = 0;
+    for folder in load_mirrored_folders(db)? {
+        total += db
+            .sync_folder_playlists(&folder)
+            .map_err(|e| format!("Failed to sync playlists for {}: {}", folder, e))?;
+    }
+    Ok(total)
+}
+
+/// Get the folders currently mirrored as playlists
+#[tauri::command]
+pub fn get_mirrored_folders(state: State<AppState>) -> Result<Vec<String>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    load_mirrored_folders(db)
+}
+
+/// Mirror a folder as auto-maintained playlists (folder -> playlist, subfolder -> child playlist).
+/// Builds the playlists immediately. Returns the updated list of mirrored folders.
+#[tauri::command]
+pub fn add_mirrored_folder(app: AppHandle, state: State<AppState>, path: String) -> Result<Vec<String>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let path = path.trim_end_matches('/').to_string();
+    if !std::path::Path::new(&path).is_dir() {
+        return Err(format!("Path is not a directory: {}", path));
+    }
+
+    let mut folders = load_mirrored_folders(db)?;
+    if folders.contains(&path) {
+        return Err(format!("Folder is already mirrored: {}", path));
+    }
+    folders.push(path.clone());
+    save_mirrored_folders(db, &folders)?;
+
+    db.sync_folder_playlists(&path)
+        .map_err(|e| format!("Failed to build playlists for {}: {}", path, e))?;
+    notify_playlists_changed(&app);
+
+    Ok(folders)
+}
+
+/// Stop mirroring a folder and delete its playlists.
+/// Returns the updated list of mirrored folders.
+#[tauri::command]
+pub fn remove_mirrored_folder(app: AppHandle, state: State<AppState>, path: String) -> Result<Vec<String>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let path = path.trim_end_matches('/').to_string();
+    let mut folders = load_mirrored_folders(db)?;
+    let original_len = folders.len();
+    folders.retain(|f| f != &path);
+    if folders.len() == original_len {
+        return Err(format!("Folder is not mirrored: {}", path));
+    }
+    save_mirrored_folders(db, &folders)?;
+
+    db.delete_folder_playlists(&path)
+        .map_err(|e| format!("Failed to delete playlists for {}: {}", path, e))?;
+    notify_playlists_changed(&app);
+
+    Ok(folders)
+}
+
+/// Re-sync all mirrored folder playlists. Returns the number of mirror playlists.
+#[tauri::command]
+pub fn sync_folder_playlists(app: AppHandle, state: State<AppState>) -> Result<usize, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let count = sync_mirrored_folders(db)?;
+    notify_playlists_changed(&app);
+    Ok(count)
 }
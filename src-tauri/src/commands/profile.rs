@@ -0,0 +1,256 @@
+// Taxonomy and settings profile export/import
+// export_profile writes the genre definitions (names, colors, sort order), tags, smart
+// playlists ("saved views") and the portable preferences to a JSON file; import_profile
+// merges such a file into the library by name. Machine-specific settings (library
+// folders, export folders, companion and sync credentials, API keys) are never written
+// or applied, so a profile can be shared with another DJ or carried to a new machine.
+
+use crate::commands::analysis_queue::AUTO_ANALYZE_SETTING;
+use crate::commands::analysis::{GENRE_BPM_RANGES_SETTING, TEMPO_CURVE_SETTING};
+use crate::commands::key_colors::KEY_COLORS_SETTING;
+use crate::commands::library::AppState;
+use crate::commands::midi::MIDI_MAPPINGS_SETTING;
+use crate::commands::playback::SKIP_LEADING_SILENCE_SETTING;
+use crate::commands::playlists::notify_playlists_changed;
+use crate::db::{Database, ANALYSIS_PRIORITY_SETTING, SORT_IGNORE_ARTICLES_SETTING};
+use crate::scanner::{ENERGY_EXTRACTOR_SETTING, HASH_MODE_SETTING, WRITE_CONFLICT_SETTING};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use tauri::{AppHandle, State};
+
+/// Format version written to profile files
+const PROFILE_VERSION: u32 = 1;
+
+/// Settings that travel with a profile. Anything else in the settings table is local.
+const PROFILE_SETTINGS: &[&str] = &[
+    "theme",
+    "key_notation",
+    KEY_COLORS_SETTING,
+    "waveform_style",
+    "crossfade_enabled",
+    "crossfade_duration_sec",
+    GENRE_BPM_RANGES_SETTING,
+    TEMPO_CURVE_SETTING,
+    ANALYSIS_PRIORITY_SETTING,
+    AUTO_ANALYZE_SETTING,
+    SORT_IGNORE_ARTICLES_SETTING,
+    SKIP_LEADING_SILENCE_SETTING,
+    ENERGY_EXTRACTOR_SETTING,
+    HASH_MODE_SETTING,
+    WRITE_CONFLICT_SETTING,
+    MIDI_MAPPINGS_SETTING,
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileGenre {
+    pub name: String,
+    pub color: Option<String>,
+    #[serde(default)]
+    pub sort_order: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileTag {
+    pub name: String,
+    pub color: Option<String>,
+    pub category: Option<String>,
+}
+
+/// A smart playlist: its name and rules, without tracks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileView {
+    pub name: String,
+    pub smart_rules: Option<String>,
+    pub ai_prompt: Option<String>,
+}
+
+/// Contents of a profile file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub version: u32,
+    #[serde(default)]
+    pub genres: Vec<ProfileGenre>,
+    #[serde(default)]
+    pub tags: Vec<ProfileTag>,
+    #[serde(default)]
+    pub saved_views: Vec<ProfileView>,
+    #[serde(default)]
+    pub settings: BTreeMap<String, String>,
+}
+
+/// What an export wrote or an import applied
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ProfileSummaryDTO {
+    pub genres: usize,
+    pub tags: usize,
+    pub saved_views: usize,
+    pub settings: usize,
+    /// Import only: genres, tags and views that didn't exist yet (the rest were updated)
+    pub created: usize,
+    /// Import only: settings in the file that aren't portable and were ignored
+    pub skipped_settings: Vec<String>,
+}
+
+fn collect_profile(db: &Database) -> rusqlite::Result<Profile> {
+    let genres = db
+        .get_all_genre_definitions()?
+        .into_iter()
+        .map(|g| ProfileGenre { name: g.name, color: g.color, sort_order: g.sort_order })
+        .collect();
+    let tags = db
+        .get_all_tags()?
+        .into_iter()
+        .map(|t| ProfileTag { name: t.name, color: t.color, category: t.category })
+        .collect();
+    let saved_views = db
+        .get_all_playlists()?
+        .into_iter()
+        .filter(|p| p.playlist_type == "smart")
+        .map(|p| ProfileView { name: p.name, smart_rules: p.smart_rules, ai_prompt: p.ai_prompt })
+        .collect();
+
+    let mut settings = BTreeMap::new();
+    for key in PROFILE_SETTINGS {
+        if let Some(value) = db.get_setting(key)? {
+            settings.insert(key.to_string(), value);
+        }
+    }
+
+    Ok(Profile { version: PROFILE_VERSION, genres, tags, saved_views, settings })
+}
+
+/// Merge a profile into the library in one transaction: entries are matched by name
+/// and updated, missing ones created. Nothing is deleted.
+fn apply_profile(db: &Database, profile: &Profile) -> rusqlite::Result<ProfileSummaryDTO> {
+    let mut summary = ProfileSummaryDTO::default();
+    let tx = db.transaction()?;
+
+    for genre in &profile.genres {
+        if db.upsert_genre_definition(&genre.name, genre.color.as_deref(), genre.sort_order)? {
+            summary.created += 1;
+        }
+        summary.genres += 1;
+    }
+    for tag in &profile.tags {
+        if db.upsert_tag(&tag.name, tag.color.as_deref(), tag.category.as_deref())? {
+            summary.created += 1;
+        }
+        summary.tags += 1;
+    }
+    for view in &profile.saved_views {
+        if db.upsert_smart_playlist(&view.name, view.smart_rules.as_deref(), view.ai_prompt.as_deref())? {
+            summary.created += 1;
+        }
+        summary.saved_views += 1;
+    }
+    for (key, value) in &profile.settings {
+        if PROFILE_SETTINGS.contains(&key.as_str()) {
+            db.set_setting(key, value)?;
+            summary.settings += 1;
+        } else {
+            summary.skipped_settings.push(key.clone());
+        }
+    }
+
+    tx.commit()?;
+    Ok(summary)
+}
+
+/// Write the genre taxonomy, tags, smart playlists and portable settings to `path` (JSON)
+#[tauri::command]
+pub fn export_profile(state: State<AppState>, path: String) -> Result<ProfileSummaryDTO, String> {
+    let profile = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        collect_profile(db).map_err(|e| format!("Failed to read profile: {}", e))?
+    };
+
+    let json = serde_json::to_string_pretty(&profile)
+        .map_err(|e| format!("Failed to serialize profile: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+    eprintln!(
+        "[profile] Exported {} genres, {} tags, {} views, {} settings to {}",
+        profile.genres.len(),
+        profile.tags.len(),
+        profile.saved_views.len(),
+        profile.settings.len(),
+        path
+    );
+
+    Ok(ProfileSummaryDTO {
+        genres: profile.genres.len(),
+        tags: profile.tags.len(),
+        saved_views: profile.saved_views.len(),
+        settings: profile.settings.len(),
+        ..ProfileSummaryDTO::default()
+    })
+}
+
+/// Merge a profile written by export_profile into this library
+#[tauri::command]
+pub fn import_profile(app: AppHandle, state: State<AppState>, path: String) -> Result<ProfileSummaryDTO, String> {
+    let json = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let profile: Profile = serde_json::from_str(&json)
+        .map_err(|e| format!("Not a valid profile file: {}", e))?;
+    if profile.version > PROFILE_VERSION {
+        return Err(format!(
+            "Profile version {} is newer than this app supports ({})",
+            profile.version, PROFILE_VERSION
+        ));
+    }
+
+    let summary = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        apply_profile(db, &profile).map_err(|e| format!("Failed to import profile: {}", e))?
+    };
+    if summary.saved_views > 0 {
+        notify_playlists_changed(&app);
+    }
+
+    eprintln!(
+        "[profile] Imported {} genres, {} tags, {} views, {} settings from {} ({} new)",
+        summary.genres, summary.tags, summary.saved_views, summary.settings, path, summary.created
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_round_trip() {
+        let source = Database::new_in_memory().unwrap();
+        source.run_migrations().unwrap();
+        source.create_genre_definition("Deep House", Some("#6366f1")).unwrap();
+        source.upsert_tag("warmup", Some("#22c55e"), Some("vibe")).unwrap();
+        let smart = source.create_playlist("Fresh", "smart", None).unwrap();
+        source.upsert_smart_playlist("Fresh", Some("{\"rating\":5}"), None).unwrap();
+        source.create_playlist("Manual", "manual", None).unwrap();
+        source.set_setting("key_notation", "camelot").unwrap();
+        source.set_setting("library_folders", "[\"/music\"]").unwrap();
+
+        let profile = collect_profile(&source).unwrap();
+        assert_eq!(profile.genres.len(), 1);
+        assert_eq!(profile.tags[0].category.as_deref(), Some("vibe"));
+        assert_eq!(profile.saved_views.len(), 1);
+        assert_eq!(source.get_playlist(smart).unwrap().smart_rules.as_deref(), Some("{\"rating\":5}"));
+        assert!(!profile.settings.contains_key("library_folders"));
+
+        let target = Database::new_in_memory().unwrap();
+        target.run_migrations().unwrap();
+        target.create_genre_definition("Deep House", None).unwrap();
+        let mut incoming = profile.clone();
+        incoming.settings.insert("companion_token".to_string(), "secret".to_string());
+
+        let summary = apply_profile(&target, &incoming).unwrap();
+        assert_eq!((summary.genres, summary.tags, summary.saved_views, summary.settings), (1, 1, 1, 1));
+        assert_eq!(summary.created, 2);
+        assert_eq!(summary.skipped_settings, vec!["companion_token"]);
+        assert_eq!(target.get_setting("companion_token").unwrap(), None);
+        assert_eq!(collect_profile(&target).unwrap(), profile);
+    }
+}
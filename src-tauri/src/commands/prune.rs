@@ -0,0 +1,301 @@
+// Library pruning suggestions
+// suggest_prune_candidates ranks tracks by how safe they look to delete: never played,
+// low rating, a copy or other version of another track, imported long ago, in no
+// playlist, and long (long tracks free the most space). Each criterion scores 0-1 and the
+// weighted average is the track's score. Nothing is deleted here.
+
+use crate::commands::library::{track_with_analysis, AppState, TrackDTO};
+use crate::db::{song_key, Track};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::State;
+
+/// Tracks at least this long get the full length score
+const LONG_TRACK_MS: f64 = 600_000.0;
+
+/// How much each criterion counts (0 ignores it)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PruneWeights {
+    pub never_played: f64,
+    pub low_rating: f64,
+    pub duplicate: f64,
+    pub old_import: f64,
+    pub not_in_playlist: f64,
+    pub length: f64,
+}
+
+impl Default for PruneWeights {
+    fn default() -> Self {
+        Self {
+            never_played: 1.0,
+            low_rating: 1.0,
+            duplicate: 2.0,
+            old_import: 0.5,
+            not_in_playlist: 1.0,
+            length: 0.5,
+        }
+    }
+}
+
+/// Options for suggest_prune_candidates
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PruneOptions {
+    pub weights: PruneWeights,
+    /// Imported at least this many days ago counts as old
+    pub min_age_days: f64,
+    /// Tracks scoring below this (0-1) aren't suggested
+    pub min_score: f64,
+    pub limit: usize,
+}
+
+impl Default for PruneOptions {
+    fn default() -> Self {
+        Self {
+            weights: PruneWeights::default(),
+            min_age_days: 365.0,
+            min_score: 0.5,
+            limit: 200,
+        }
+    }
+}
+
+/// One suggested track
+#[derive(Debug, Serialize)]
+pub struct PruneCandidateDTO {
+    pub track: TrackDTO,
+    /// Weighted score, 0-1
+    pub score: f64,
+    /// Criteria the track meets: "never_played", "low_rating", "duplicate", "other_version",
+    /// "old_import", "not_in_playlist", "long"
+    pub reasons: Vec<String>,
+    pub playlist_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PruneSuggestionsDTO {
+    pub candidates: Vec<PruneCandidateDTO>,
+    /// Sum of the candidates' file sizes
+    pub total_size_bytes: i64,
+}
+
+/// How a track relates to other copies of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Duplicate {
+    None,
+    /// Same song, different file (title + artist match)
+    OtherVersion,
+    /// Same content or same file name and size
+    Copy,
+}
+
+/// What a track is scored on
+#[derive(Debug, Clone)]
+struct TrackFacts {
+    play_count: i32,
+    rating: i32,
+    duplicate: Duplicate,
+    days_since_added: Option<f64>,
+    playlist_count: i64,
+    duration_ms: Option<i32>,
+}
+
+/// Weighted score (0-1) and the criteria met
+fn score_track(facts: &TrackFacts, options: &PruneOptions) -> (f64, Vec<&'static str>) {
+    let w = &options.weights;
+    // Unrated counts half: nobody has judged it either way
+    let low_rating = match facts.rating {
+        0 => 0.5,
+        1 => 1.0,
+        2 => 0.5,
+        _ => 0.0,
+    };
+    let (duplicate, duplicate_reason) = match facts.duplicate {
+        Duplicate::Copy => (1.0, Some("duplicate")),
+        Duplicate::OtherVersion => (0.5, Some("other_version")),
+        Duplicate::None => (0.0, None),
+    };
+    let flag = |met: bool, reason: &'static str| (if met { 1.0 } else { 0.0 }, met.then_some(reason));
+    let length = facts.duration_ms.unwrap_or(0) as f64 / LONG_TRACK_MS;
+    // (weight, signal 0-1, reason when the criterion is met)
+    let signals = [
+        (w.never_played, flag(facts.play_count == 0, "never_played")),
+        (w.low_rating, (low_rating, (1..=2).contains(&facts.rating).then_some("low_rating"))),
+        (w.duplicate, (duplicate, duplicate_reason)),
+        (w.old_import, flag(facts.days_since_added.is_some_and(|d| d >= options.min_age_days), "old_import")),
+        (w.not_in_playlist, flag(facts.playlist_count == 0, "not_in_playlist")),
+        (w.length, (length.clamp(0.0, 1.0), (length >= 1.0).then_some("long"))),
+    ];
+
+    let total_weight: f64 = signals.iter().map(|(weight, _)| weight.max(0.0)).sum();
+    if total_weight <= 0.0 {
+        return (0.0, Vec::new());
+    }
+    let score = signals.iter().map(|(weight, (signal, _))| weight.max(0.0) * signal).sum::<f64>() / total_weight;
+    let reasons = signals
+        .iter()
+        .filter(|(weight, _)| *weight > 0.0)
+        .filter_map(|(_, (_, reason))| *reason)
+        .collect();
+    (score, reasons)
+}
+
+/// Tracks that are a copy or other version of a track worth keeping more. In each group
+/// the copy in the most playlists (then best rated, most played, oldest) is kept.
+fn find_duplicates(
+    tracks: &[Track],
+    copy_groups: &[Vec<i64>],
+    usage: &HashMap<i64, (i64, Option<f64>)>,
+) -> HashMap<i64, Duplicate> {
+    let by_id: HashMap<i64, &Track> = tracks.iter().filter_map(|t| Some((t.id?, t))).collect();
+    let keeper = |group: &[i64]| -> Option<i64> {
+        group.iter().copied().max_by_key(|id| {
+            let track = by_id.get(id);
+            (
+                usage.get(id).map_or(0, |u| u.0),
+                track.map_or(0, |t| t.rating),
+                track.map_or(0, |t| t.play_count),
+                -id,
+            )
+        })
+    };
+
+    let mut duplicates = HashMap::new();
+    let mut versions: HashMap<String, Vec<i64>> = HashMap::new();
+    for track in tracks {
+        if let (Some(id), Some(key)) = (track.id, song_key(track.title.as_deref(), track.artist.as_deref())) {
+            versions.entry(key).or_default().push(id);
+        }
+    }
+    for group in versions.values().filter(|g| g.len() > 1) {
+        let keep = keeper(group);
+        for &id in group.iter().filter(|&&id| Some(id) != keep) {
+            duplicates.insert(id, Duplicate::OtherVersion);
+        }
+    }
+    for group in copy_groups {
+        let keep = keeper(group);
+        for &id in group.iter().filter(|&&id| Some(id) != keep) {
+            duplicates.insert(id, Duplicate::Copy);
+        }
+    }
+    duplicates
+}
+
+/// Rank tracks that look safe to delete to reclaim disk space (highest score first,
+/// bigger files first on ties). Read-only: the user reviews and deletes.
+#[tauri::command]
+pub fn suggest_prune_candidates(
+    state: State<AppState>,
+    options: Option<PruneOptions>,
+) -> Result<PruneSuggestionsDTO, String> {
+    let options = options.unwrap_or_default();
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let tracks = db.get_all_tracks()
+        .map_err(|e| format!("Failed to get tracks: {}", e))?;
+    let usage = db.get_track_usage()
+        .map_err(|e| format!("Failed to get track usage: {}", e))?;
+    let copy_groups: Vec<Vec<i64>> = db.find_duplicate_groups()
+        .map_err(|e| format!("Failed to find duplicates: {}", e))?
+        .into_iter()
+        .map(|(_, ids)| ids)
+        .collect();
+    let duplicates = find_duplicates(&tracks, &copy_groups, &usage);
+
+    let mut scored: Vec<(f64, Vec<&'static str>, &Track, i64)> = tracks
+        .iter()
+        .filter_map(|track| {
+            let id = track.id?;
+            let (playlist_count, days_since_added) = usage.get(&id).copied().unwrap_or((0, None));
+            let facts = TrackFacts {
+                play_count: track.play_count,
+                rating: track.rating,
+                duplicate: duplicates.get(&id).copied().unwrap_or(Duplicate::None),
+                days_since_added,
+                playlist_count,
+                duration_ms: track.duration_ms,
+            };
+            let (score, reasons) = score_track(&facts, &options);
+            (score >= options.min_score).then_some((score, reasons, track, playlist_count))
+        })
+        .collect();
+    scored.sort_by(|a, b| {
+        b.0.total_cmp(&a.0)
+            .then_with(|| b.2.file_size.unwrap_or(0).cmp(&a.2.file_size.unwrap_or(0)))
+    });
+    scored.truncate(options.limit);
+
+    let total_size_bytes = scored.iter().map(|(_, _, track, _)| track.file_size.unwrap_or(0)).sum();
+    let candidates = scored
+        .into_iter()
+        .filter_map(|(score, reasons, track, playlist_count)| {
+            Some(PruneCandidateDTO {
+                track: track_with_analysis(db, track.id?)?,
+                score,
+                reasons: reasons.into_iter().map(String::from).collect(),
+                playlist_count,
+            })
+        })
+        .collect();
+
+    Ok(PruneSuggestionsDTO { candidates, total_size_bytes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn facts() -> TrackFacts {
+        TrackFacts {
+            play_count: 3,
+            rating: 4,
+            duplicate: Duplicate::None,
+            days_since_added: Some(30.0),
+            playlist_count: 2,
+            duration_ms: Some(300_000),
+        }
+    }
+
+    #[test]
+    fn test_score_track() {
+        let options = PruneOptions::default();
+
+        // A played, well-rated, recent track in playlists: only half the length score
+        let (score, reasons) = score_track(&facts(), &options);
+        assert!(score < 0.1, "score {}", score);
+        assert!(reasons.is_empty());
+
+        let forgotten = TrackFacts {
+            play_count: 0,
+            rating: 1,
+            duplicate: Duplicate::Copy,
+            days_since_added: Some(800.0),
+            playlist_count: 0,
+            duration_ms: Some(720_000),
+        };
+        let (score, reasons) = score_track(&forgotten, &options);
+        assert!((score - 1.0).abs() < 1e-9);
+        assert_eq!(
+            reasons,
+            vec!["never_played", "low_rating", "duplicate", "old_import", "not_in_playlist", "long"]
+        );
+
+        // Zero weights drop a criterion from both the score and the reasons
+        let only_duplicates = PruneOptions {
+            weights: PruneWeights {
+                never_played: 0.0,
+                low_rating: 0.0,
+                old_import: 0.0,
+                not_in_playlist: 0.0,
+                length: 0.0,
+                ..PruneWeights::default()
+            },
+            ..options
+        };
+        let other_version = TrackFacts { duplicate: Duplicate::OtherVersion, ..forgotten };
+        assert_eq!(score_track(&other_version, &only_duplicates), (0.5, vec!["other_version"]));
+    }
+}
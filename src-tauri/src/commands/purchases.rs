@@ -0,0 +1,202 @@
+// Purchase info: where a track was bought (Beatport, Bandcamp, a promo pool...), when, and
+// the order ID. The scanner guesses it from folder names (scanner::guess_purchase); it can
+// be edited per track and filtered on, e.g. to check which tracks of a radio show playlist
+// are bought rather than promos.
+
+use crate::commands::library::{validate_date, AppState};
+use crate::db::TrackPurchase;
+use crate::scanner::guess_purchase;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::State;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrackPurchaseDTO {
+    pub source: Option<String>,
+    /// YYYY-MM-DD
+    pub date: Option<String>,
+    pub order_id: Option<String>,
+}
+
+impl From<TrackPurchase> for TrackPurchaseDTO {
+    fn from(purchase: TrackPurchase) -> Self {
+        TrackPurchaseDTO {
+            source: purchase.source,
+            date: purchase.date,
+            order_id: purchase.order_id,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PurchaseSourceCount {
+    pub source: String,
+    pub count: i64,
+}
+
+/// Which tracks to keep. Missing fields don't filter.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PurchaseFilter {
+    /// Any of these sources (ignoring case)
+    pub sources: Vec<String>,
+    /// true: only tracks with a source; false: only tracks without one
+    pub has_source: Option<bool>,
+    /// Purchase date bounds (YYYY-MM-DD, inclusive); tracks without a date never match
+    pub from_date: Option<String>,
+    pub to_date: Option<String>,
+}
+
+impl PurchaseFilter {
+    pub fn matches(&self, purchase: Option<&TrackPurchase>) -> bool {
+        let source = purchase.and_then(|p| p.source.as_deref());
+        if self.has_source.is_some_and(|wanted| wanted != source.is_some()) {
+            return false;
+        }
+        if !self.sources.is_empty()
+            && !source.is_some_and(|s| self.sources.iter().any(|wanted| wanted.eq_ignore_ascii_case(s)))
+        {
+            return false;
+        }
+        if self.from_date.is_some() || self.to_date.is_some() {
+            let Some(date) = purchase.and_then(|p| p.date.as_deref()) else {
+                return false;
+            };
+            if self.from_date.as_deref().is_some_and(|from| date < from)
+                || self.to_date.as_deref().is_some_and(|to| date > to)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Blank fields become None; the date must be YYYY-MM-DD
+fn clean_purchase(purchase: TrackPurchaseDTO) -> Result<TrackPurchase, String> {
+    let clean = |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    let date = clean(purchase.date);
+    if let Some(date) = &date {
+        validate_date(date)?;
+    }
+    Ok(TrackPurchase {
+        source: clean(purchase.source),
+        date,
+        order_id: clean(purchase.order_id),
+    })
+}
+
+#[tauri::command]
+pub fn get_track_purchase(state: State<AppState>, track_id: i64) -> Result<TrackPurchaseDTO, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.get_track_purchase(track_id)
+        .map(TrackPurchaseDTO::from)
+        .map_err(|e| format!("Failed to get purchase info: {}", e))
+}
+
+/// Set a track's purchase info (blank fields clear it). Returns what was stored.
+#[tauri::command]
+pub fn set_track_purchase(
+    state: State<AppState>,
+    track_id: i64,
+    purchase: TrackPurchaseDTO,
+) -> Result<TrackPurchaseDTO, String> {
+    let purchase = clean_purchase(purchase)?;
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.set_track_purchase(track_id, &purchase)
+        .map_err(|e| format!("Failed to set purchase info: {}", e))?;
+    Ok(purchase.into())
+}
+
+/// Sources in use with their track counts, most used first
+#[tauri::command]
+pub fn get_purchase_sources(state: State<AppState>) -> Result<Vec<PurchaseSourceCount>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let sources = db.get_purchase_sources()
+        .map_err(|e| format!("Failed to get purchase sources: {}", e))?;
+    Ok(sources
+        .into_iter()
+        .map(|(source, count)| PurchaseSourceCount { source, count })
+        .collect())
+}
+
+/// Guess purchase info from folder names for tracks that have none (new scans do this
+/// already). Returns the number of tracks filled in.
+#[tauri::command]
+pub fn detect_purchase_sources(state: State<AppState>) -> Result<usize, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let tracks = db.get_tracks_without_purchase()
+        .map_err(|e| format!("Failed to get tracks: {}", e))?;
+
+    let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+    let mut filled = 0;
+    for (track_id, file_path) in tracks {
+        if let Some(purchase) = guess_purchase(Path::new(&file_path)) {
+            if db.fill_track_purchase(track_id, &purchase)
+                .map_err(|e| format!("Failed to set purchase info: {}", e))?
+            {
+                filled += 1;
+            }
+        }
+    }
+    tx.commit().map_err(|e| format!("Failed to commit: {}", e))?;
+    eprintln!("[purchases] Guessed purchase info for {} tracks", filled);
+    Ok(filled)
+}
+
+/// IDs of the tracks matching `filter`, out of `track_ids` (e.g. a playlist) or the whole
+/// library, in the given order
+#[tauri::command]
+pub fn filter_tracks_by_purchase(
+    state: State<AppState>,
+    track_ids: Option<Vec<i64>>,
+    filter: PurchaseFilter,
+) -> Result<Vec<i64>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let purchases = db.get_all_track_purchases()
+        .map_err(|e| format!("Failed to get purchase info: {}", e))?;
+    let track_ids = match track_ids {
+        Some(ids) => ids,
+        None => db.get_all_track_ids().map_err(|e| format!("Failed to get tracks: {}", e))?,
+    };
+    Ok(track_ids
+        .into_iter()
+        .filter(|id| filter.matches(purchases.get(id)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_purchase_filter() {
+        let bought = TrackPurchase {
+            source: Some("Beatport".to_string()),
+            date: Some("2024-03-12".to_string()),
+            order_id: None,
+        };
+        let filter = |sources: &[&str], has_source, from: Option<&str>| PurchaseFilter {
+            sources: sources.iter().map(|s| s.to_string()).collect(),
+            has_source,
+            from_date: from.map(String::from),
+            to_date: None,
+        };
+        assert!(filter(&["beatport", "Bandcamp"], None, None).matches(Some(&bought)));
+        assert!(!filter(&["Promo"], None, None).matches(Some(&bought)));
+        assert!(filter(&[], Some(true), Some("2024-01-01")).matches(Some(&bought)));
+        assert!(!filter(&[], None, Some("2024-06-01")).matches(Some(&bought)));
+        assert!(filter(&[], Some(false), None).matches(None));
+        assert!(!filter(&[], Some(true), None).matches(None));
+
+        assert!(clean_purchase(TrackPurchaseDTO { date: Some("2024-13-01".to_string()), ..Default::default() }).is_err());
+        let cleaned = clean_purchase(TrackPurchaseDTO { source: Some("  ".to_string()), ..Default::default() }).unwrap();
+        assert!(cleaned.is_empty());
+    }
+}
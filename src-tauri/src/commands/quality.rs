@@ -0,0 +1,163 @@
+// Technical quality checks (audio::quality): flags fake lossless files (transcoded from
+// MP3/AAC), low-bandwidth sources, DC offset and mains hum, so they can be found and purged.
+// Checks are stored as a quality_flags bitfield on track_analysis.
+
+use crate::audio::quality::{self, QualityReport, FLAG_NAMES};
+use crate::commands::library::{track_with_analysis, AppState, TrackDTO};
+use crate::db::{Database, QualityCheck};
+use serde::Serialize;
+use std::path::Path;
+use tauri::State;
+
+/// Formats that should carry the full spectrum
+fn is_lossless_format(format: Option<&str>) -> bool {
+    matches!(
+        format.map(|f| f.to_lowercase()).as_deref(),
+        Some("flac" | "wav" | "aiff" | "aif" | "alac")
+    )
+}
+
+/// A quality check as sent to the frontend
+#[derive(Debug, Serialize)]
+pub struct QualityCheckDTO {
+    pub track_id: i64,
+    /// "lossy_cutoff", "low_bandwidth", "dc_offset", "hum"
+    pub flags: Vec<String>,
+    /// Where the spectrum stops, if below 20 kHz
+    pub cutoff_hz: Option<f64>,
+    /// 50 or 60 when hum was found
+    pub hum_hz: Option<f64>,
+    pub dc_offset: f64,
+}
+
+impl From<QualityCheck> for QualityCheckDTO {
+    fn from(check: QualityCheck) -> Self {
+        QualityCheckDTO {
+            track_id: check.track_id,
+            flags: quality::flag_names(check.flags).into_iter().map(String::from).collect(),
+            cutoff_hz: check.cutoff_hz,
+            hum_hz: check.hum_hz,
+            dc_offset: check.dc_offset,
+        }
+    }
+}
+
+/// A flagged track with its check
+#[derive(Debug, Serialize)]
+pub struct QualityIssueDTO {
+    pub track: TrackDTO,
+    pub check: QualityCheckDTO,
+}
+
+fn to_check(track_id: i64, report: &QualityReport, lossless: bool) -> QualityCheck {
+    QualityCheck {
+        track_id,
+        flags: report.flags(lossless),
+        cutoff_hz: report.cutoff_hz.map(|hz| hz.round() as f64),
+        hum_hz: report.hum_hz.map(|hz| hz as f64),
+        dc_offset: report.dc_offset as f64,
+    }
+}
+
+fn save_check(db: &Database, check: &QualityCheck) -> Result<(), String> {
+    db.with_busy_retry(|db| db.save_quality_check(check))
+        .map_err(|e| format!("Failed to save quality check: {}", e))
+}
+
+/// Check one track's technical quality and store the result
+#[tauri::command]
+pub fn analyze_quality(state: State<AppState>, track_id: i64) -> Result<QualityCheckDTO, String> {
+    let (file_path, lossless) = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let track = db.get_track(track_id)
+            .map_err(|e| format!("Failed to get track {}: {}", track_id, e))?;
+        (track.file_path, is_lossless_format(track.file_format.as_deref()))
+    };
+
+    let path = Path::new(&file_path);
+    if !path.exists() {
+        return Err(format!("Audio file not found: {}", file_path));
+    }
+
+    // Heavy DSP work — no lock held
+    let report = quality::analyze_file(path)
+        .map_err(|e| format!("Quality check failed for track {}: {}", track_id, e))?;
+    let check = to_check(track_id, &report, lossless);
+    eprintln!("[quality] Track {}: {:?} flags={:?}", track_id, report, quality::flag_names(check.flags));
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    save_check(db, &check)?;
+    Ok(check.into())
+}
+
+/// Check every track that hasn't been checked yet. Returns the number checked.
+/// Releases the DB mutex during the DSP work so other commands aren't blocked.
+#[tauri::command]
+pub fn analyze_all_quality(state: State<AppState>) -> Result<usize, String> {
+    let tracks = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        db.get_tracks_without_quality_check()
+            .map_err(|e| format!("Failed to get tracks: {}", e))?
+    };
+    eprintln!("[quality] {} tracks need a quality check", tracks.len());
+
+    let mut checked = 0;
+    for (track_id, file_path, format) in tracks {
+        let path = Path::new(&file_path);
+        if !path.exists() {
+            eprintln!("[quality] Skipping missing file: {}", file_path);
+            continue;
+        }
+        let report = match quality::analyze_file(path) {
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("[quality] Track {} failed: {}", track_id, e);
+                continue;
+            }
+        };
+        let check = to_check(track_id, &report, is_lossless_format(format.as_deref()));
+
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        save_check(db, &check)?;
+        checked += 1;
+    }
+    Ok(checked)
+}
+
+/// Tracks with quality problems. `flags` limits it to some problems (flag names as in
+/// QualityCheckDTO); all of them by default.
+#[tauri::command]
+pub fn get_quality_issues(
+    state: State<AppState>,
+    flags: Option<Vec<String>>,
+) -> Result<Vec<QualityIssueDTO>, String> {
+    let mask = match flags {
+        Some(names) => names.iter().try_fold(0, |mask, name| {
+            FLAG_NAMES
+                .iter()
+                .find(|(_, n)| n == name)
+                .map(|(bit, _)| mask | bit)
+                .ok_or_else(|| format!("Unknown quality flag: {}", name))
+        })?,
+        None => FLAG_NAMES.iter().fold(0, |mask, (bit, _)| mask | bit),
+    };
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let checks = db.get_quality_issues(mask)
+        .map_err(|e| format!("Failed to get quality issues: {}", e))?;
+
+    Ok(checks
+        .into_iter()
+        .filter_map(|check| {
+            Some(QualityIssueDTO {
+                track: track_with_analysis(db, check.track_id)?,
+                check: check.into(),
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,231 @@
+// Audition queue ("up next") — an in-memory list of track IDs shared by the desktop
+// UI and the companion remote, so "play next" works from any view or from the phone.
+// Every change is emitted as "queue-changed" (the new list of track IDs).
+
+use crate::commands::library::{AppState, TrackDTO};
+use crate::commands::playback::{self, PlaybackState, PlaybackStatus};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, State};
+
+type QueueListener = Box<dyn Fn(&[i64]) + Send + Sync>;
+
+/// The queue itself; shared with the companion server
+pub struct AuditionQueue {
+    tracks: Mutex<Vec<i64>>,
+    listener: Mutex<Option<QueueListener>>,
+}
+
+impl AuditionQueue {
+    pub fn new() -> Self {
+        Self {
+            tracks: Mutex::new(Vec::new()),
+            listener: Mutex::new(None),
+        }
+    }
+
+    /// Call `listener` with the new contents after every change
+    pub fn set_listener(&self, listener: impl Fn(&[i64]) + Send + Sync + 'static) {
+        *self.listener.lock().unwrap() = Some(Box::new(listener));
+    }
+
+    fn changed(&self, tracks: &[i64]) {
+        if let Some(listener) = self.listener.lock().unwrap().as_ref() {
+            listener(tracks);
+        }
+    }
+
+    /// Apply `f` to the list and notify the listener
+    fn update<T>(&self, f: impl FnOnce(&mut Vec<i64>) -> T) -> T {
+        let mut tracks = self.tracks.lock().unwrap();
+        let result = f(&mut tracks);
+        let snapshot = tracks.clone();
+        drop(tracks);
+        self.changed(&snapshot);
+        result
+    }
+
+    pub fn snapshot(&self) -> Vec<i64> {
+        self.tracks.lock().unwrap().clone()
+    }
+
+    /// Add a track at the end, or at the front with `play_next`
+    pub fn enqueue(&self, track_id: i64, play_next: bool) {
+        self.update(|tracks| {
+            if play_next {
+                tracks.insert(0, track_id);
+            } else {
+                tracks.push(track_id);
+            }
+        });
+    }
+
+    /// Take the next track off the front
+    pub fn dequeue(&self) -> Option<i64> {
+        self.update(|tracks| (!tracks.is_empty()).then(|| tracks.remove(0)))
+    }
+
+    /// Remove the entry at `index` (the same track may be queued more than once)
+    pub fn remove(&self, index: usize) -> Result<i64, String> {
+        self.update(|tracks| {
+            if index >= tracks.len() {
+                return Err(format!("Queue position {} out of range", index));
+            }
+            Ok(tracks.remove(index))
+        })
+    }
+
+    /// Move the entry at `from` to position `to`
+    pub fn reorder(&self, from: usize, to: usize) -> Result<(), String> {
+        self.update(|tracks| {
+            if from >= tracks.len() || to >= tracks.len() {
+                return Err(format!("Queue positions {} -> {} out of range", from, to));
+            }
+            let track_id = tracks.remove(from);
+            tracks.insert(to, track_id);
+            Ok(())
+        })
+    }
+
+    pub fn clear(&self) {
+        self.update(|tracks| tracks.clear());
+    }
+}
+
+/// Managed state holding the shared queue
+pub struct QueueState {
+    pub queue: Arc<AuditionQueue>,
+}
+
+impl QueueState {
+    pub fn new() -> Self {
+        Self {
+            queue: Arc::new(AuditionQueue::new()),
+        }
+    }
+}
+
+/// Queue entries with track data, in order. Tracks deleted since they were queued are skipped.
+fn queue_tracks(state: &State<AppState>, queue: &AuditionQueue) -> Result<Vec<TrackDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    Ok(queue
+        .snapshot()
+        .into_iter()
+        .filter_map(|id| db.get_track(id).ok())
+        .map(TrackDTO::from)
+        .collect())
+}
+
+/// Get the queued tracks, next first
+#[tauri::command]
+pub fn get_queue(state: State<AppState>, queue_state: State<QueueState>) -> Result<Vec<TrackDTO>, String> {
+    queue_tracks(&state, &queue_state.queue)
+}
+
+/// Add a track to the end of the queue, or to the front with `play_next`
+#[tauri::command]
+pub fn enqueue_track(
+    state: State<AppState>,
+    queue_state: State<QueueState>,
+    track_id: i64,
+    play_next: Option<bool>,
+) -> Result<Vec<TrackDTO>, String> {
+    {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        db.get_track(track_id)
+            .map_err(|e| format!("Failed to get track: {}", e))?;
+    }
+
+    queue_state.queue.enqueue(track_id, play_next.unwrap_or(false));
+    queue_tracks(&state, &queue_state.queue)
+}
+
+/// Remove the entry at `index`
+#[tauri::command]
+pub fn dequeue_track(
+    state: State<AppState>,
+    queue_state: State<QueueState>,
+    index: usize,
+) -> Result<Vec<TrackDTO>, String> {
+    queue_state.queue.remove(index)?;
+    queue_tracks(&state, &queue_state.queue)
+}
+
+/// Move the entry at `from` to position `to`
+#[tauri::command]
+pub fn reorder_queue(
+    state: State<AppState>,
+    queue_state: State<QueueState>,
+    from: usize,
+    to: usize,
+) -> Result<Vec<TrackDTO>, String> {
+    queue_state.queue.reorder(from, to)?;
+    queue_tracks(&state, &queue_state.queue)
+}
+
+/// Empty the queue
+#[tauri::command]
+pub fn clear_queue(queue_state: State<QueueState>) -> Result<(), String> {
+    queue_state.queue.clear();
+    Ok(())
+}
+
+/// Take the next track off the queue, load it and start playing.
+/// Returns None when the queue is empty. Queued tracks that no longer load are skipped.
+#[tauri::command]
+pub async fn play_next_in_queue(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    playback_state: State<'_, PlaybackState>,
+    queue_state: State<'_, QueueState>,
+) -> Result<Option<PlaybackStatus>, String> {
+    while let Some(track_id) = queue_state.queue.dequeue() {
+        match playback::load_track(track_id, app_state.clone(), playback_state.clone()).await {
+            Ok(_) => {
+                let status = playback::play(app, playback_state).await?;
+                return Ok(Some(status));
+            }
+            Err(e) => eprintln!("[queue] Skipping track {}: {}", track_id, e),
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_operations() {
+        let queue = AuditionQueue::new();
+        queue.enqueue(1, false);
+        queue.enqueue(2, false);
+        queue.enqueue(3, true);
+        assert_eq!(queue.snapshot(), vec![3, 1, 2]);
+
+        queue.reorder(0, 2).unwrap();
+        assert_eq!(queue.snapshot(), vec![1, 2, 3]);
+        assert!(queue.reorder(0, 3).is_err());
+
+        assert_eq!(queue.remove(1).unwrap(), 2);
+        assert_eq!(queue.dequeue(), Some(1));
+        assert_eq!(queue.snapshot(), vec![3]);
+
+        queue.clear();
+        assert_eq!(queue.dequeue(), None);
+    }
+
+    #[test]
+    fn test_queue_listener() {
+        let queue = AuditionQueue::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        queue.set_listener(move |tracks| *seen_clone.lock().unwrap() = tracks.to_vec());
+
+        queue.enqueue(7, false);
+        queue.enqueue(8, true);
+        assert_eq!(*seen.lock().unwrap(), vec![8, 7]);
+    }
+}
@@ -0,0 +1,365 @@
+// Recorded set analysis — reconstruct the tracklist of a recorded DJ set
+// identify_set_tracklist fingerprints the recording (see audio::fingerprint) and slides a
+// ~10 s window over it in ~5 s steps. Each window's hashes, plus variants with their
+// least reliable bits flipped, are looked up in an index of the library's prints; the
+// (track, time offset) pairs with the most hits are checked by bit error rate over the
+// whole window. Consecutive windows matching the same track become one tracklist entry.
+// Only tracks with a stored print take part; prints are taken on demand for the tracks
+// of the playlists passed in (e.g. the ones prepared for the gig).
+
+use crate::audio::fingerprint::{self, HOP_MS, WEAK_BITS};
+use crate::commands::library::AppState;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use tauri::State;
+
+/// Window length and step, in hashes (~10 s and ~5 s)
+const WINDOW_FRAMES: usize = 216;
+const WINDOW_STEP: usize = 108;
+/// Hits a (track, offset) pair needs before it is checked
+const MIN_VOTES: u32 = 2;
+/// Pairs checked per window, most hits first
+const CANDIDATES_PER_WINDOW: usize = 5;
+/// A window matches when at most this fraction of bits differ (random audio: ~0.5)
+const MAX_BIT_ERROR_RATE: f64 = 0.35;
+/// Hashes found in more library frames than this are too common to vote (silence, hum)
+const MAX_POSTINGS: usize = 64;
+/// Windows without a match (or matching something else) a track may span
+const MAX_GAP_WINDOWS: usize = 1;
+/// Shorter matches are dropped as noise
+const MIN_SEGMENT_WINDOWS: usize = 2;
+
+/// A track found in the recording
+#[derive(Debug, Clone, Serialize)]
+pub struct IdentifiedTrackDTO {
+    pub track_id: i64,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// Where the track is heard in the recording. Neighbours overlap during transitions.
+    pub start_ms: i64,
+    pub end_ms: i64,
+    /// Position in the track at start_ms (later than 0 when the DJ cued in past the intro)
+    pub track_position_ms: i64,
+    /// 0-1, from how closely the matched windows agree with the track
+    pub confidence: f64,
+}
+
+/// Result of identify_set_tracklist
+#[derive(Debug, Clone, Serialize)]
+pub struct SetTracklistDTO {
+    pub recording_duration_ms: i64,
+    pub tracks: Vec<IdentifiedTrackDTO>,
+    /// Library tracks the recording was compared with
+    pub compared_tracks: usize,
+    /// Tracks fingerprinted during this call
+    pub fingerprinted_tracks: usize,
+}
+
+/// Best match of one window
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct WindowMatch {
+    /// Index into the prints
+    track: usize,
+    /// Track hash index minus recording hash index
+    offset: i64,
+    ber: f64,
+}
+
+/// A run of windows matching one track
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    track: usize,
+    first_window: usize,
+    last_window: usize,
+    offset: i64,
+    ber_sum: f64,
+    windows: usize,
+}
+
+/// The hash and its variants with one or two of the weak bits flipped
+fn variants(hash: u32, weak: &[u8; WEAK_BITS]) -> impl Iterator<Item = u32> + '_ {
+    let singles = weak.iter().map(move |&bit| hash ^ (1 << bit));
+    let pairs = (0..WEAK_BITS).flat_map(move |i| {
+        (i + 1..WEAK_BITS).map(move |j| hash ^ (1 << weak[i]) ^ (1 << weak[j]))
+    });
+    std::iter::once(hash).chain(singles).chain(pairs)
+}
+
+/// Hash -> (track index, hash index) for every library print
+fn build_index(prints: &[(i64, Vec<u32>)]) -> HashMap<u32, Vec<(u32, u32)>> {
+    let mut index: HashMap<u32, Vec<(u32, u32)>> = HashMap::new();
+    for (track, (_, hashes)) in prints.iter().enumerate() {
+        for (frame, &hash) in hashes.iter().enumerate() {
+            if hash != 0 && hash != u32::MAX {
+                index.entry(hash).or_default().push((track as u32, frame as u32));
+            }
+        }
+    }
+    index.retain(|_, postings| postings.len() <= MAX_POSTINGS);
+    index
+}
+
+fn match_window(
+    prints: &[(i64, Vec<u32>)],
+    index: &HashMap<u32, Vec<(u32, u32)>>,
+    hashes: &[u32],
+    weak: &[[u8; WEAK_BITS]],
+    start: usize,
+) -> Option<WindowMatch> {
+    let end = (start + WINDOW_FRAMES).min(hashes.len());
+    let mut votes: HashMap<(usize, i64), u32> = HashMap::new();
+    for frame in start..end {
+        for variant in variants(hashes[frame], &weak[frame]) {
+            for &(track, track_frame) in index.get(&variant).into_iter().flatten() {
+                *votes.entry((track as usize, track_frame as i64 - frame as i64)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut candidates: Vec<((usize, i64), u32)> = votes.into_iter().filter(|&(_, v)| v >= MIN_VOTES).collect();
+    candidates.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    candidates
+        .into_iter()
+        .take(CANDIDATES_PER_WINDOW)
+        .filter_map(|((track, offset), _)| {
+            let track_hashes = &prints[track].1;
+            // Part of the window that lies inside the track
+            let from = start.max((-offset).max(0) as usize);
+            let to = end.min((track_hashes.len() as i64 - offset).max(0) as usize);
+            if to <= from || (to - from) * 2 < end - start {
+                return None;
+            }
+            let track_from = (from as i64 + offset) as usize;
+            let ber = fingerprint::bit_error_rate(&hashes[from..to], &track_hashes[track_from..track_from + (to - from)]);
+            (ber <= MAX_BIT_ERROR_RATE).then_some(WindowMatch { track, offset, ber })
+        })
+        .min_by(|a, b| a.ber.total_cmp(&b.ber))
+}
+
+/// Join window matches into segments: a track continues across gaps of up to
+/// MAX_GAP_WINDOWS, and across a single window matching something else
+fn segments(windows: &[Option<WindowMatch>]) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut current: Option<Segment> = None;
+
+    for (i, window) in windows.iter().enumerate() {
+        let Some(m) = window else { continue };
+        if let Some(segment) = current.as_mut() {
+            if segment.track == m.track && i - segment.last_window <= MAX_GAP_WINDOWS + 1 {
+                segment.last_window = i;
+                segment.ber_sum += m.ber;
+                segment.windows += 1;
+                continue;
+            }
+            let resumes = windows.get(i + 1).copied().flatten().is_some_and(|next| next.track == segment.track);
+            if resumes {
+                continue;
+            }
+            segments.extend(current.take());
+        }
+        current = Some(Segment {
+            track: m.track,
+            first_window: i,
+            last_window: i,
+            offset: m.offset,
+            ber_sum: m.ber,
+            windows: 1,
+        });
+    }
+    segments.extend(current);
+    segments.retain(|s| s.windows >= MIN_SEGMENT_WINDOWS);
+    segments
+}
+
+/// Find the library tracks in a recording's hashes
+fn identify(prints: &[(i64, Vec<u32>)], hashes: &[u32], weak: &[[u8; WEAK_BITS]]) -> Vec<Segment> {
+    let index = build_index(prints);
+    let windows: Vec<Option<WindowMatch>> = (0..hashes.len().saturating_sub(WINDOW_FRAMES / 2))
+        .step_by(WINDOW_STEP)
+        .map(|start| match_window(prints, &index, hashes, weak, start))
+        .collect();
+    segments(&windows)
+}
+
+fn frames_to_ms(frames: i64) -> i64 {
+    (frames as f64 * HOP_MS).round() as i64
+}
+
+/// Reconstruct the tracklist of a recorded set (WAV, MP3, FLAC...) with timestamps.
+/// Tracks of `playlist_ids` that have no print yet are fingerprinted first; the
+/// recording is then compared with every fingerprinted track in the library.
+#[tauri::command]
+pub fn identify_set_tracklist(
+    state: State<AppState>,
+    recording_path: String,
+    playlist_ids: Option<Vec<i64>>,
+) -> Result<SetTracklistDTO, String> {
+    let recording = Path::new(&recording_path);
+    if !recording.exists() {
+        return Err(format!("Recording not found: {}", recording_path));
+    }
+
+    // 1. Tracks of the given playlists still missing a (current) print
+    let to_print: Vec<(i64, String, String)> = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let printed: HashSet<i64> = db.get_track_prints()
+            .map_err(|e| format!("Failed to get prints: {}", e))?
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        let mut queued = HashSet::new();
+        let mut to_print = Vec::new();
+        for playlist_id in playlist_ids.unwrap_or_default() {
+            let rows = db.get_playlist_tracks(playlist_id)
+                .map_err(|e| format!("Failed to get playlist tracks: {}", e))?;
+            for (track, ..) in rows {
+                if let Some(id) = track.id.filter(|id| !printed.contains(id) && queued.insert(*id)) {
+                    to_print.push((id, track.file_path, track.file_hash));
+                }
+            }
+        }
+        to_print
+    };
+
+    // 2. Fingerprint them without the lock
+    let mut fingerprinted_tracks = 0;
+    for (track_id, file_path, file_hash) in &to_print {
+        let hashes = match fingerprint::fingerprint_file(Path::new(file_path), false) {
+            Ok((hashes, _)) => hashes,
+            Err(e) => {
+                eprintln!("[recording] Skipping {}: {}", file_path, e);
+                continue;
+            }
+        };
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        db.save_track_print(*track_id, file_hash, &fingerprint::to_blob(&hashes))
+            .map_err(|e| format!("Failed to save print: {}", e))?;
+        fingerprinted_tracks += 1;
+    }
+
+    let prints: Vec<(i64, Vec<u32>)> = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        db.get_track_prints()
+            .map_err(|e| format!("Failed to get prints: {}", e))?
+            .into_iter()
+            .filter_map(|(id, blob)| Some((id, fingerprint::from_blob(&blob)?)))
+            .collect()
+    };
+    if prints.is_empty() {
+        return Err("No tracks are fingerprinted yet. Pass the playlists the set was played from.".to_string());
+    }
+
+    // 3. Fingerprint the recording and match
+    let (hashes, weak) = fingerprint::fingerprint_file(recording, true)?;
+    let weak = weak.unwrap_or_default();
+    let found = identify(&prints, &hashes, &weak);
+    let recording_duration_ms = frames_to_ms(hashes.len() as i64);
+
+    let tracks = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        found
+            .iter()
+            .map(|segment| {
+                let track_id = prints[segment.track].0;
+                let track = db.get_track(track_id)
+                    .map_err(|e| format!("Failed to get track: {}", e))?;
+                let start_frame = (segment.first_window * WINDOW_STEP) as i64;
+                let end_frame = (segment.last_window * WINDOW_STEP + WINDOW_FRAMES).min(hashes.len()) as i64;
+                Ok(IdentifiedTrackDTO {
+                    track_id,
+                    title: track.title,
+                    artist: track.artist,
+                    start_ms: frames_to_ms(start_frame),
+                    end_ms: frames_to_ms(end_frame),
+                    track_position_ms: frames_to_ms((start_frame + segment.offset).max(0)),
+                    confidence: (1.0 - segment.ber_sum / segment.windows as f64 / MAX_BIT_ERROR_RATE).clamp(0.0, 1.0),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?
+    };
+
+    eprintln!(
+        "[recording] Identified {} tracks in {} ({} compared, {} newly fingerprinted)",
+        tracks.len(),
+        recording_path,
+        prints.len(),
+        fingerprinted_tracks
+    );
+
+    Ok(SetTracklistDTO {
+        recording_duration_ms,
+        tracks,
+        compared_tracks: prints.len(),
+        fingerprinted_tracks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random hashes
+    fn random_hashes(seed: u64, len: usize) -> Vec<u32> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (state >> 32) as u32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_identify_segments() {
+        let prints: Vec<(i64, Vec<u32>)> = (0..3).map(|i| (10 + i, random_hashes(i as u64 + 1, 3000))).collect();
+
+        // Track 10 from 500 (cued past the intro), 11 from the start, unknown audio, then 12
+        let mut recording = Vec::new();
+        recording.extend_from_slice(&prints[0].1[500..1500]);
+        recording.extend_from_slice(&prints[1].1[..1200]);
+        recording.extend(random_hashes(99, 400));
+        recording.extend_from_slice(&prints[2].1[100..1100]);
+
+        // Degrade: flip about 6% of the bits
+        let noise = random_hashes(7, recording.len() * 32);
+        for (i, hash) in recording.iter_mut().enumerate() {
+            for bit in 0..32 {
+                if noise[i * 32 + bit] % 100 < 6 {
+                    *hash ^= 1 << bit;
+                }
+            }
+        }
+        let weak = vec![[0, 4, 8, 12]; recording.len()];
+
+        let found = identify(&prints, &recording, &weak);
+        let tracks: Vec<i64> = found.iter().map(|s| prints[s.track].0).collect();
+        assert_eq!(tracks, vec![10, 11, 12]);
+
+        assert_eq!(found[0].first_window, 0);
+        assert_eq!(found[0].offset, 500);
+        // Track 11 begins at hash 1000: the first window fully inside it starts at 1080
+        let start_11 = found[1].first_window * WINDOW_STEP;
+        assert!((900..=1080).contains(&start_11), "track 11 starts at {}", start_11);
+        assert_eq!(found[1].offset, -1000);
+        assert_eq!(found[2].offset, 100 - 2600);
+        assert!(found.iter().all(|s| s.ber_sum / (s.windows as f64) < 0.15));
+
+        // Nothing in common with the library
+        assert!(identify(&prints, &random_hashes(42, 2000), &vec![[0, 1, 2, 3]; 2000]).is_empty());
+    }
+
+    #[test]
+    fn test_segments_bridge_gaps() {
+        let m = |track| Some(WindowMatch { track, offset: 0, ber: 0.1 });
+        let windows = vec![m(0), m(0), None, m(0), m(1), m(0), m(0), m(2), m(2), m(2), m(3)];
+        let found = segments(&windows);
+        assert_eq!(found.iter().map(|s| s.track).collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!((found[0].first_window, found[0].last_window, found[0].windows), (0, 6, 5));
+    }
+}
@@ -0,0 +1,231 @@
+// Tauri commands for importing an existing Rekordbox library
+// Reads export.pdb (USB stick) or master.db (installation), matches Rekordbox
+// tracks to library tracks by path and then by content hash, and imports
+// playlists, cue points and beat grids for the matched tracks.
+
+use crate::commands::library::AppState;
+use crate::commands::playlists::notify_playlists_changed;
+use crate::db::{BeatGrid, CuePoint};
+use crate::formats::rekordbox::{RekordboxCue, RekordboxPlaylist, RekordboxSource};
+use crate::scanner::Scanner;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::{AppHandle, State};
+
+/// Name of the playlist folder that imported playlists are placed under
+const IMPORT_FOLDER_NAME: &str = "Rekordbox Import";
+
+/// Summary of a Rekordbox import
+#[derive(Debug, Serialize)]
+pub struct RekordboxImportResultDTO {
+    pub tracks_found: usize,
+    pub tracks_matched: usize,
+    /// Rekordbox paths that couldn't be matched to a library track
+    pub unmatched_paths: Vec<String>,
+    pub playlists_imported: usize,
+    pub cues_imported: usize,
+    pub beat_grids_imported: usize,
+}
+
+/// Import playlists, cues and beat grids from a Rekordbox export.pdb or master.db.
+/// `path` may be the database file, a USB stick root, or a Rekordbox data folder.
+/// Tracks must already be in the library — unmatched tracks are reported, not imported.
+#[tauri::command]
+pub fn import_rekordbox_library(
+    app: AppHandle,
+    state: State<AppState>,
+    path: String,
+) -> Result<RekordboxImportResultDTO, String> {
+    // 1. Parse the Rekordbox database (no lock needed)
+    let source = RekordboxSource::open(Path::new(&path))?;
+    let library = &source.library;
+
+    let mut result = RekordboxImportResultDTO {
+        tracks_found: library.tracks.len(),
+        tracks_matched: 0,
+        unmatched_paths: Vec::new(),
+        playlists_imported: 0,
+        cues_imported: 0,
+        beat_grids_imported: 0,
+    };
+
+    // 2. Match tracks: exact path first, then content hash (brief lock per lookup).
+    //    Library tracks scanned in lazy hash mode need their hash for the fallback.
+    {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        Scanner::hash_pending(db)
+            .map_err(|e| format!("Failed to hash tracks: {}", e))?;
+    }
+    let mut id_map: HashMap<i64, i64> = HashMap::new();
+    for rb_track in &library.tracks {
+        let local_path = source.resolve_track_path(rb_track);
+        let path_str = local_path.to_string_lossy().to_string();
+
+        let by_path = {
+            let db_lock = state.db.lock().unwrap();
+            let db = db_lock.as_ref().ok_or("Database not initialized")?;
+            db.find_track_id_by_path(&path_str)
+                .map_err(|e| format!("Failed to look up track: {}", e))?
+        };
+
+        let track_id = match by_path {
+            Some(id) => Some(id),
+            None => match Scanner::calculate_file_hash(&local_path) {
+                Ok(hash) => {
+                    let db_lock = state.db.lock().unwrap();
+                    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+                    db.find_track_id_by_hash(&hash)
+                        .map_err(|e| format!("Failed to look up track: {}", e))?
+                }
+                Err(_) => None,
+            },
+        };
+
+        match track_id {
+            Some(id) => {
+                id_map.insert(rb_track.id, id);
+            }
+            None => result.unmatched_paths.push(rb_track.file_path.clone()),
+        }
+    }
+    result.tracks_matched = id_map.len();
+
+    // 3. Cues and beat grids for matched tracks (ANLZ read outside the lock)
+    for rb_track in &library.tracks {
+        let track_id = match id_map.get(&rb_track.id) {
+            Some(id) => *id,
+            None => continue,
+        };
+
+        let analysis = source.read_analysis(rb_track);
+        let mut rb_cues = rb_track.cues.clone();
+        if rb_cues.is_empty() {
+            if let Some(a) = &analysis {
+                rb_cues = a.cues.clone();
+            }
+        }
+
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+        if !rb_cues.is_empty() {
+            let cues = to_cue_points(track_id, &rb_cues);
+            db.replace_cue_points(track_id, &cues)
+                .map_err(|e| format!("Failed to save cue points: {}", e))?;
+            result.cues_imported += rb_cues.len();
+        }
+
+        if let Some(a) = analysis.filter(|a| !a.beats.is_empty()) {
+            let grid = BeatGrid {
+                track_id,
+                bpm: rb_track.bpm.or(Some(a.beats[0].bpm)),
+                first_beat_ms: Some(a.beats[0].position_ms),
+                beats: a.beats.iter().map(|b| (b.position_ms, b.beat_number)).collect(),
+                source: "rekordbox".to_string(),
+            };
+            db.save_beat_grid(&grid)
+                .map_err(|e| format!("Failed to save beat grid: {}", e))?;
+            result.beat_grids_imported += 1;
+        }
+    }
+
+    // 4. Playlists, recreated under a single import folder
+    if !library.playlists.is_empty() {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+        let root_id = db
+            .create_playlist(IMPORT_FOLDER_NAME, "folder", None)
+            .map_err(|e| format!("Failed to create import folder: {}", e))?;
+
+        let mut ordered: Vec<&RekordboxPlaylist> = library.playlists.iter().collect();
+        ordered.sort_by_key(|p| p.sort_order);
+
+        // Parents must exist before children: repeat passes until nothing new is placed
+        let mut created: HashMap<i64, i64> = HashMap::new();
+        loop {
+            let mut progressed = false;
+            for playlist in &ordered {
+                if created.contains_key(&playlist.id) {
+                    continue;
+                }
+                let parent = match playlist.parent_id {
+                    None => root_id,
+                    Some(rb_parent) => match created.get(&rb_parent) {
+                        Some(id) => *id,
+                        // Parent not created yet (or missing) — retry next pass
+                        None if library.playlists.iter().any(|p| p.id == rb_parent) => continue,
+                        None => root_id,
+                    },
+                };
+
+                let playlist_type = if playlist.is_folder { "folder" } else { "manual" };
+                let id = db
+                    .create_playlist(&playlist.name, playlist_type, Some(parent))
+                    .map_err(|e| format!("Failed to create playlist: {}", e))?;
+                for rb_track_id in &playlist.track_ids {
+                    if let Some(track_id) = id_map.get(rb_track_id) {
+                        db.add_track_to_playlist(id, *track_id)
+                            .map_err(|e| format!("Failed to add track to playlist: {}", e))?;
+                    }
+                }
+
+                created.insert(playlist.id, id);
+                if !playlist.is_folder {
+                    result.playlists_imported += 1;
+                }
+                progressed = true;
+            }
+            if !progressed {
+                break;
+            }
+        }
+    }
+
+    eprintln!(
+        "[rekordbox] Imported {}/{} tracks, {} playlists, {} cues, {} beat grids",
+        result.tracks_matched,
+        result.tracks_found,
+        result.playlists_imported,
+        result.cues_imported,
+        result.beat_grids_imported
+    );
+    notify_playlists_changed(&app);
+
+    Ok(result)
+}
+
+/// Convert Rekordbox cues to cue_points rows (loops become a start/end pair)
+fn to_cue_points(track_id: i64, cues: &[RekordboxCue]) -> Vec<CuePoint> {
+    let mut points = Vec::new();
+    for cue in cues {
+        let label = match cue.hot_cue {
+            Some(slot) if (1..=26).contains(&slot) => {
+                Some(format!("Hot Cue {}", (b'A' + (slot - 1) as u8) as char))
+            }
+            _ => cue.comment.clone().filter(|c| !c.is_empty()),
+        };
+        let start_type = if cue.loop_end_ms.is_some() { "loop_start" } else { "cue" };
+        points.push(CuePoint {
+            id: None,
+            track_id,
+            position_ms: cue.position_ms,
+            label: label.clone(),
+            color: None,
+            cue_type: start_type.to_string(),
+        });
+        if let Some(end_ms) = cue.loop_end_ms {
+            points.push(CuePoint {
+                id: None,
+                track_id,
+                position_ms: end_ms,
+                label,
+                color: None,
+                cue_type: "loop_end".to_string(),
+            });
+        }
+    }
+    points
+}
@@ -0,0 +1,369 @@
+// Tauri commands for library reports
+// Read-only summaries computed from existing analysis data (no DSP is run here).
+
+use crate::audio::key::{camelot_compatible, camelot_neighbors, parse_camelot};
+use crate::audio::waveform::WaveformData;
+use crate::commands::library::AppState;
+use crate::db::Track;
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::State;
+
+/// A key is "weak" if fewer than this many tracks are in compatible keys...
+const MIN_COMPATIBLE_TRACKS: usize = 3;
+/// ...or fewer than this share of the analyzed tracks
+const MIN_COMPATIBLE_SHARE: f64 = 0.02;
+/// BPM ranges wider than this with no tracks are reported as gaps
+const MIN_BPM_GAP: f64 = 4.0;
+/// Bridge track suggestions per weak key / BPM gap
+const MAX_BRIDGE_SUGGESTIONS: usize = 3;
+/// How far past a gap's edges to look for bridge tracks (pitchable with the fader)
+const BPM_GAP_MARGIN: f64 = 2.0;
+/// Points in the energy curves returned by compare_tracks
+const COMPARE_CURVE_POINTS: usize = 200;
+
+#[derive(Debug, Serialize)]
+pub struct KeyCountDTO {
+    pub camelot: String,
+    pub count: usize,
+    /// Tracks in this key plus its Camelot neighbors
+    pub compatible_count: usize,
+}
+
+/// A track suggested to fill a key or BPM hole
+#[derive(Debug, Serialize)]
+pub struct BridgeTrackDTO {
+    pub track_id: i64,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub bpm: Option<f64>,
+    pub musical_key: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WeakKeyDTO {
+    pub camelot: String,
+    pub count: usize,
+    pub compatible_count: usize,
+    pub bridge_tracks: Vec<BridgeTrackDTO>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BpmGapDTO {
+    pub from_bpm: f64,
+    pub to_bpm: f64,
+    /// Tracks whose BPM is closest to either edge of the gap
+    pub bridge_tracks: Vec<BridgeTrackDTO>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MixabilityReportDTO {
+    pub total_tracks: usize,
+    pub tracks_with_key: usize,
+    pub tracks_with_bpm: usize,
+    pub key_distribution: Vec<KeyCountDTO>,
+    pub weak_keys: Vec<WeakKeyDTO>,
+    pub bpm_gaps: Vec<BpmGapDTO>,
+}
+
+/// Track row with (bpm, bpm_confidence, key, key_confidence) as returned by the db layer
+type AnalyzedTrackRow = (Track, Option<f64>, Option<f64>, Option<String>, Option<f64>);
+
+/// Minimal per-track data the report works from
+struct ReportTrack {
+    id: i64,
+    title: Option<String>,
+    artist: Option<String>,
+    bpm: Option<f64>,
+    key: Option<String>,
+}
+
+impl ReportTrack {
+    fn to_bridge(&self) -> BridgeTrackDTO {
+        BridgeTrackDTO {
+            track_id: self.id,
+            title: self.title.clone(),
+            artist: self.artist.clone(),
+            bpm: self.bpm,
+            musical_key: self.key.clone(),
+        }
+    }
+}
+
+/// Normalize a stored key to canonical Camelot ("8a" -> "8A"); None if not Camelot
+fn normalize_key(key: &Option<String>) -> Option<String> {
+    let (number, is_minor) = parse_camelot(key.as_deref()?)?;
+    Some(format!("{}{}", number, if is_minor { 'A' } else { 'B' }))
+}
+
+/// Report on key coverage, BPM gaps and bridge tracks for the library or one playlist.
+/// For a playlist, bridge suggestions are library tracks not yet in it. For the
+/// whole library they are the existing tracks that best connect to the hole.
+#[tauri::command]
+pub fn get_mixability_report(
+    state: State<AppState>,
+    playlist_id: Option<i64>,
+) -> Result<MixabilityReportDTO, String> {
+    let (scope, library) = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+        let to_report = |rows: Vec<AnalyzedTrackRow>| {
+            rows.into_iter()
+                .filter_map(|(t, bpm, _, key, _)| {
+                    Some(ReportTrack {
+                        id: t.id?,
+                        title: t.title,
+                        artist: t.artist,
+                        bpm: bpm.filter(|b| *b > 0.0),
+                        key: normalize_key(&key),
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let library = to_report(
+            db.get_all_tracks_with_analysis()
+                .map_err(|e| format!("Failed to get tracks: {}", e))?,
+        );
+        let scope = match playlist_id {
+            Some(id) => Some(to_report(
+                db.get_playlist_tracks(id)
+                    .map_err(|e| format!("Failed to get playlist tracks: {}", e))?,
+            )),
+            None => None,
+        };
+        (scope, library)
+    };
+    let report = match &scope {
+        Some(tracks) => build_mixability_report(tracks, &library, true),
+        None => build_mixability_report(&library, &library, false),
+    };
+    Ok(report)
+}
+
+fn build_mixability_report(
+    scope: &[ReportTrack],
+    library: &[ReportTrack],
+    exclude_scope: bool,
+) -> MixabilityReportDTO {
+    // --- Key distribution ---
+    let mut key_counts: HashMap<String, usize> = HashMap::new();
+    for t in scope {
+        if let Some(k) = &t.key {
+            *key_counts.entry(k.clone()).or_insert(0) += 1;
+        }
+    }
+    let tracks_with_key: usize = key_counts.values().sum();
+    let compatible_count = |key: &str| -> usize {
+        camelot_neighbors(key)
+            .iter()
+            .map(|k| key_counts.get(k).copied().unwrap_or(0))
+            .sum()
+    };
+
+    let mut key_distribution: Vec<KeyCountDTO> = key_counts
+        .iter()
+        .map(|(k, count)| KeyCountDTO {
+            camelot: k.clone(),
+            count: *count,
+            compatible_count: compatible_count(k),
+        })
+        .collect();
+    key_distribution.sort_by(|a, b| {
+        let ka = parse_camelot(&a.camelot).unwrap_or((0, false));
+        let kb = parse_camelot(&b.camelot).unwrap_or((0, false));
+        (ka.0, !ka.1).cmp(&(kb.0, !kb.1))
+    });
+
+    // --- Weak keys: populated keys with too few compatible tracks ---
+    let threshold = MIN_COMPATIBLE_TRACKS
+        .max((tracks_with_key as f64 * MIN_COMPATIBLE_SHARE).ceil() as usize);
+    let in_scope: std::collections::HashSet<i64> = if exclude_scope {
+        scope.iter().map(|t| t.id).collect()
+    } else {
+        std::collections::HashSet::new()
+    };
+
+    let weak_keys: Vec<WeakKeyDTO> = key_distribution
+        .iter()
+        .filter(|k| k.compatible_count < threshold)
+        .map(|k| {
+            // Bridge candidates: library tracks in a neighboring key,
+            // preferring keys that are themselves well connected
+            let neighbors: Vec<String> = camelot_neighbors(&k.camelot)
+                .into_iter()
+                .filter(|n| n != &k.camelot)
+                .collect();
+            let mut candidates: Vec<&ReportTrack> = library
+                .iter()
+                .filter(|t| !in_scope.contains(&t.id))
+                .filter(|t| t.key.as_ref().map(|key| neighbors.contains(key)).unwrap_or(false))
+                .collect();
+            candidates.sort_by_key(|t| std::cmp::Reverse(compatible_count(t.key.as_deref().unwrap_or(""))));
+
+            WeakKeyDTO {
+                camelot: k.camelot.clone(),
+                count: k.count,
+                compatible_count: k.compatible_count,
+                bridge_tracks: candidates
+                    .into_iter()
+                    .take(MAX_BRIDGE_SUGGESTIONS)
+                    .map(ReportTrack::to_bridge)
+                    .collect(),
+            }
+        })
+        .collect();
+
+    // --- BPM gaps between consecutive BPMs in scope ---
+    let mut bpms: Vec<f64> = scope.iter().filter_map(|t| t.bpm).collect();
+    bpms.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let tracks_with_bpm = bpms.len();
+
+    let bpm_gaps: Vec<BpmGapDTO> = bpms
+        .windows(2)
+        .filter(|w| w[1] - w[0] >= MIN_BPM_GAP)
+        .map(|w| {
+            let (from, to) = (w[0], w[1]);
+            let mut candidates: Vec<&ReportTrack> = library
+                .iter()
+                .filter(|t| !in_scope.contains(&t.id))
+                .filter(|t| {
+                    t.bpm
+                        .map(|b| b >= from - BPM_GAP_MARGIN && b <= to + BPM_GAP_MARGIN)
+                        .unwrap_or(false)
+                })
+                .collect();
+            // Prefer tracks near the middle of the gap
+            let mid = (from + to) / 2.0;
+            candidates.sort_by(|a, b| {
+                let da = (a.bpm.unwrap_or(mid) - mid).abs();
+                let db = (b.bpm.unwrap_or(mid) - mid).abs();
+                da.partial_cmp(&db).unwrap_or(std::cmp::Ordering::Equal)
+            });
+            BpmGapDTO {
+                from_bpm: from,
+                to_bpm: to,
+                bridge_tracks: candidates
+                    .into_iter()
+                    .take(MAX_BRIDGE_SUGGESTIONS)
+                    .map(ReportTrack::to_bridge)
+                    .collect(),
+            }
+        })
+        .collect();
+
+    MixabilityReportDTO {
+        total_tracks: scope.len(),
+        tracks_with_key,
+        tracks_with_bpm,
+        key_distribution,
+        weak_keys,
+        bpm_gaps,
+    }
+}
+
+/// One side of a track comparison
+#[derive(Debug, Serialize)]
+pub struct ComparedTrackDTO {
+    pub track_id: i64,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub duration_ms: Option<i32>,
+    pub bpm: Option<f64>,
+    pub musical_key: Option<String>,
+    pub loudness_lufs: Option<f64>,
+    pub energy_level: Option<i32>,
+    /// Peak level (0.0-1.0) over the track, COMPARE_CURVE_POINTS long.
+    /// None until a waveform has been generated.
+    pub energy_curve: Option<Vec<f32>>,
+}
+
+/// Side-by-side analysis of two tracks (e.g. two versions of the same tune)
+#[derive(Debug, Serialize)]
+pub struct TrackComparisonDTO {
+    pub a: ComparedTrackDTO,
+    pub b: ComparedTrackDTO,
+    /// b - a
+    pub bpm_difference: Option<f64>,
+    /// "same", "compatible" (Camelot neighbors) or "clash". None if either key is unknown.
+    pub key_verdict: Option<String>,
+    /// b - a, in LU
+    pub lufs_difference: Option<f64>,
+    /// b - a
+    pub duration_difference_ms: Option<i64>,
+}
+
+/// Compare the analysis of two tracks for an A/B panel. Energy curves are resampled
+/// from the stored overview waveforms to the same length so they line up point by point.
+#[tauri::command]
+pub fn compare_tracks(
+    state: State<AppState>,
+    track_a: i64,
+    track_b: i64,
+) -> Result<TrackComparisonDTO, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let load = |id: i64| -> Result<ComparedTrackDTO, String> {
+        let track = db.get_track(id)
+            .map_err(|e| format!("Failed to get track {}: {}", id, e))?;
+        let analysis = db.get_track_analysis(id)
+            .map_err(|e| format!("Failed to get analysis: {}", e))?;
+        let energy_curve = db.get_waveform(id, "overview")
+            .map_err(|e| format!("Failed to get waveform: {}", e))?
+            .and_then(|blob| WaveformData::from_blob(&blob).ok())
+            .map(|w| {
+                let peaks: Vec<f32> = w.points.iter().map(|p| p.peak).collect();
+                resample_curve(&peaks, COMPARE_CURVE_POINTS)
+            })
+            .filter(|curve| !curve.is_empty());
+
+        Ok(ComparedTrackDTO {
+            track_id: id,
+            title: track.title,
+            artist: track.artist,
+            duration_ms: track.duration_ms,
+            bpm: analysis.as_ref().and_then(|a| a.bpm),
+            musical_key: analysis.as_ref().and_then(|a| normalize_key(&a.musical_key)),
+            loudness_lufs: analysis.as_ref().and_then(|a| a.loudness_lufs),
+            energy_level: track.energy_level,
+            energy_curve,
+        })
+    };
+
+    let a = load(track_a)?;
+    let b = load(track_b)?;
+
+    let key_verdict = match (&a.musical_key, &b.musical_key) {
+        (Some(ka), Some(kb)) if ka == kb => Some("same"),
+        (Some(ka), Some(kb)) if camelot_compatible(ka, kb) => Some("compatible"),
+        (Some(_), Some(_)) => Some("clash"),
+        _ => None,
+    };
+
+    Ok(TrackComparisonDTO {
+        bpm_difference: a.bpm.zip(b.bpm).map(|(x, y)| y - x),
+        key_verdict: key_verdict.map(str::to_string),
+        lufs_difference: a.loudness_lufs.zip(b.loudness_lufs).map(|(x, y)| y - x),
+        duration_difference_ms: a.duration_ms.zip(b.duration_ms).map(|(x, y)| (y - x) as i64),
+        a,
+        b,
+    })
+}
+
+/// Resample a curve to `len` points: bucket averages when shrinking,
+/// nearest point when stretching
+fn resample_curve(values: &[f32], len: usize) -> Vec<f32> {
+    if values.is_empty() || len == 0 {
+        return Vec::new();
+    }
+    (0..len)
+        .map(|i| {
+            let start = i * values.len() / len;
+            let end = ((i + 1) * values.len() / len).max(start + 1);
+            let bucket = &values[start..end];
+            bucket.iter().sum::<f32>() / bucket.len() as f32
+        })
+        .collect()
+}
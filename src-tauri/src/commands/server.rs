@@ -1,14 +1,27 @@
 // Tauri commands for the mobile companion server lifecycle
 
+use crate::commands::analysis_queue::AnalysisQueueState;
 use crate::commands::library::AppState;
+use crate::commands::playlists::notify_playlists_changed;
+use crate::commands::queue::QueueState;
 use crate::db::Database;
+use crate::server::events::CompanionEvent;
+use crate::server::metrics::MetricsSnapshot;
 use crate::server::{self, RunningServer};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
 use tauri::path::BaseDirectory;
-use tauri::{Manager, State};
+use tauri::{AppHandle, Emitter, Listener, Manager, State};
+
+/// Setting key for the concurrent stream limit
+pub const MAX_STREAMS_SETTING: &str = "companion_max_streams";
+/// Concurrent stream limit when none is configured
+const DEFAULT_MAX_STREAMS: usize = 3;
+/// Setting key for browse mode ("true"/"false"): paired phones can look but not change
+pub const READ_ONLY_SETTING: &str = "companion_read_only";
 
 /// Get LAN IP suitable for QR code — avoids 127.0.0.1 so phone can reach desktop.
 fn get_lan_ip_for_qr() -> String {
@@ -38,6 +51,10 @@ pub struct CompanionState {
     pub running_server: Mutex<Option<RunningServer>>,
     /// Shared reference to library folders (kept in sync with settings)
     pub library_folders: Arc<Mutex<Vec<String>>>,
+    /// Shared concurrent stream limit (kept in sync with settings)
+    pub max_streams: Arc<AtomicUsize>,
+    /// Shared browse-mode flag (kept in sync with settings)
+    pub read_only: Arc<AtomicBool>,
 }
 
 impl CompanionState {
@@ -45,6 +62,8 @@ impl CompanionState {
         CompanionState {
             running_server: Mutex::new(None),
             library_folders: Arc::new(Mutex::new(Vec::new())),
+            max_streams: Arc::new(AtomicUsize::new(DEFAULT_MAX_STREAMS)),
+            read_only: Arc::new(AtomicBool::new(false)),
         }
     }
 }
@@ -56,6 +75,26 @@ pub struct CompanionServerInfo {
     pub token: Option<String>,
     pub port: Option<u16>,
     pub active_streams: usize,
+    /// Browse mode: phones can't change anything
+    pub read_only: bool,
+    /// Request counters and latencies since the server started (None when stopped)
+    pub metrics: Option<MetricsSnapshot>,
+}
+
+/// Everything a phone needs to connect in one scan
+#[derive(Serialize)]
+pub struct CompanionPairingPayload {
+    pub url: String,
+    /// Single-use code the PWA exchanges for the token (POST /api/pair)
+    pub pairing_code: String,
+    pub expires_in: u64,
+    /// SHA-256 fingerprint of the server's TLS certificate. Always None for now:
+    /// the companion server only speaks plain HTTP on the LAN.
+    pub tls_fingerprint: Option<String>,
+    /// Page URL for the QR code: opens the PWA, which pairs automatically
+    pub qr_url: String,
+    /// Same data as a recodeck:// deep link
+    pub deep_link: String,
 }
 
 /// Find the mobile PWA dist directory.
@@ -102,6 +141,44 @@ fn find_mobile_dist(app: Option<&tauri::AppHandle>) -> Option<PathBuf> {
     None
 }
 
+/// Load the settings a running server picks up live (library folders, stream limit,
+/// browse mode) into the shared state the server reads from.
+fn load_companion_config(app_state: &AppState, companion_state: &CompanionState) -> Result<(), String> {
+    let db_lock = app_state.db.lock().map_err(|e| e.to_string())?;
+    let db = match db_lock.as_ref() {
+        Some(db) => db,
+        None => return Ok(()),
+    };
+
+    if let Ok(Some(json_str)) = db.get_setting("library_folders") {
+        if let Ok(folders) = serde_json::from_str::<Vec<String>>(&json_str) {
+            let mut lf = companion_state
+                .library_folders
+                .lock()
+                .map_err(|e| e.to_string())?;
+            *lf = folders;
+        }
+    }
+
+    let max_streams = db
+        .get_setting(MAX_STREAMS_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(DEFAULT_MAX_STREAMS);
+    companion_state.max_streams.store(max_streams, Ordering::Relaxed);
+
+    let read_only = db
+        .get_setting(READ_ONLY_SETTING)
+        .ok()
+        .flatten()
+        .is_some_and(|v| v == "true");
+    companion_state.read_only.store(read_only, Ordering::Relaxed);
+
+    Ok(())
+}
+
 /// Internal helper to start the companion server with explicit params.
 /// Used by both the Tauri command and auto-start logic.
 fn start_companion_internal(
@@ -109,21 +186,7 @@ fn start_companion_internal(
     companion_state: &CompanionState,
     port: Option<u16>,
 ) -> Result<(String, u16, Arc<Mutex<Option<Database>>>), String> {
-    // Load library folders from settings
-    {
-        let db_lock = app_state.db.lock().map_err(|e| e.to_string())?;
-        if let Some(db) = db_lock.as_ref() {
-            if let Ok(Some(json_str)) = db.get_setting("library_folders") {
-                if let Ok(folders) = serde_json::from_str::<Vec<String>>(&json_str) {
-                    let mut lf = companion_state
-                        .library_folders
-                        .lock()
-                        .map_err(|e| e.to_string())?;
-                    *lf = folders;
-                }
-            }
-        }
-    }
+    load_companion_config(app_state, companion_state)?;
 
     // Determine token: reuse persisted one or generate new
     let token = {
@@ -171,6 +234,54 @@ fn start_companion_internal(
     Ok((token, port, db_arc))
 }
 
+/// Reload the library and playlists after a peer pushed its sync snapshot to us
+fn watch_sync(app: &AppHandle, running: &RunningServer) {
+    let app = app.clone();
+    running.state.set_sync_listener(move || {
+        let _ = app.emit("library-changed", ());
+        notify_playlists_changed(&app);
+    });
+}
+
+/// Let companion clients queue missing waveforms on the analysis worker
+fn serve_waveforms(app: &AppHandle, running: &RunningServer) {
+    let app = app.clone();
+    running.state.set_waveform_requester(move |track_id| {
+        app.state::<AnalysisQueueState>().request_remote_waveform(&app, track_id)
+    });
+}
+
+/// The fields companion clients get from "track-analyzed" and "waveform-ready"
+#[derive(Deserialize)]
+struct AnalysisEventPayload {
+    track_id: i64,
+    error: Option<String>,
+}
+
+type ToCompanionEvent = fn(AnalysisEventPayload) -> CompanionEvent;
+
+/// Relay the analysis worker's events to companion clients on /events. The listeners
+/// remove themselves once the server they relay to has stopped.
+fn relay_analysis_events(app: &AppHandle, running: &RunningServer) {
+    let relays: [(&str, ToCompanionEvent); 2] = [
+        ("track-analyzed", |p| CompanionEvent::TrackAnalyzed { track_id: p.track_id, error: p.error }),
+        ("waveform-ready", |p| CompanionEvent::WaveformReady { track_id: p.track_id, error: p.error }),
+    ];
+    for (event_name, to_companion_event) in relays {
+        let handle = app.clone();
+        let server_state = Arc::downgrade(&running.state);
+        app.listen(event_name, move |event| {
+            let Some(server_state) = Weak::upgrade(&server_state) else {
+                handle.unlisten(event.id());
+                return;
+            };
+            if let Ok(payload) = serde_json::from_str::<AnalysisEventPayload>(event.payload()) {
+                server_state.publish(to_companion_event(payload));
+            }
+        });
+    }
+}
+
 /// Persist companion server settings after successful start
 fn persist_companion_settings(app_state: &AppState, token: &str, port: u16) {
     let db_lock = app_state.db.lock().ok();
@@ -204,14 +315,20 @@ pub async fn start_companion_server(
         start_companion_internal(&app_state, &companion_state, port)?;
 
     let library_folders = companion_state.library_folders.clone();
+    let max_streams = companion_state.max_streams.clone();
+    let read_only = companion_state.read_only.clone();
+    let queue = app.state::<QueueState>().queue.clone();
 
     let mobile_dist = find_mobile_dist(Some(&app));
-    let running = server::start_server(port, token, db_arc, library_folders, 3, mobile_dist)
+    let running = server::start_server(port, token, db_arc, library_folders, max_streams, read_only, queue, mobile_dist)
         .await
         .map_err(|e| format!("Failed to start companion server: {}", e))?;
 
     // Persist token, port, and autostart setting
     persist_companion_settings(&app_state, &running.token, running.addr.port());
+    watch_sync(&app, &running);
+    serve_waveforms(&app, &running);
+    relay_analysis_events(&app, &running);
 
     let lan_ip = get_lan_ip_for_qr();
 
@@ -222,6 +339,8 @@ pub async fn start_companion_server(
         token: Some(running.token.clone()),
         port: Some(running.addr.port()),
         active_streams: 0,
+        read_only: running.state.is_read_only(),
+        metrics: None,
     };
 
     let mut lock = companion_state
@@ -272,7 +391,9 @@ pub fn get_companion_status(
                 url: Some(format!("http://{}:{}", lan_ip, server.addr.port())),
                 token: Some(server.token.clone()),
                 port: Some(server.addr.port()),
-                active_streams: 0, // TODO: get from server state
+                active_streams: server.state.active_stream_count(),
+                read_only: server.state.is_read_only(),
+                metrics: Some(server.state.metrics.snapshot(server.state.active_stream_count())),
             })
         }
         None => Ok(CompanionServerInfo {
@@ -281,10 +402,47 @@ pub fn get_companion_status(
             token: None,
             port: None,
             active_streams: 0,
+            read_only: companion_state.read_only.load(Ordering::Relaxed),
+            metrics: None,
         }),
     }
 }
 
+/// Create a pairing payload for the QR code: server URL plus a fresh single-use pairing
+/// code, so scanning connects the phone without typing or exposing the token in the QR.
+#[tauri::command]
+pub fn get_companion_pairing_payload(
+    companion_state: State<'_, CompanionState>,
+) -> Result<CompanionPairingPayload, String> {
+    let lock = companion_state
+        .running_server
+        .lock()
+        .map_err(|e| e.to_string())?;
+    let server = lock.as_ref().ok_or("Companion server is not running")?;
+
+    let url = format!("http://{}:{}", get_lan_ip_for_qr(), server.addr.port());
+    let pairing_code = server.state.create_pairing_code();
+    let tls_fingerprint: Option<String> = None;
+
+    let mut deep_link = format!(
+        "recodeck://pair?url={}&code={}",
+        server::percent_encode(&url),
+        pairing_code
+    );
+    if let Some(fingerprint) = &tls_fingerprint {
+        deep_link.push_str(&format!("&fp={}", server::percent_encode(fingerprint)));
+    }
+
+    Ok(CompanionPairingPayload {
+        qr_url: format!("{}/?code={}", url, pairing_code),
+        deep_link,
+        url,
+        pairing_code,
+        expires_in: server::PAIRING_CODE_TTL.as_secs(),
+        tls_fingerprint,
+    })
+}
+
 /// Regenerate the auth token, invalidating all active sessions
 #[tauri::command]
 pub async fn regenerate_companion_token(
@@ -318,6 +476,81 @@ pub async fn regenerate_companion_token(
     start_companion_server(app, app_state, companion_state, None).await
 }
 
+/// Apply changed companion settings to the running server without dropping connected clients.
+/// Library folders, the stream limit and browse mode are picked up live; the listener is
+/// only restarted (keeping the token) when `port` differs from the port it is bound to.
+/// Saves `port`, `max_streams` and `read_only` when given. There are no transcode settings yet —
+/// streams are always served as the original file.
+#[tauri::command]
+pub async fn reload_companion_config(
+    app: tauri::AppHandle,
+    app_state: State<'_, AppState>,
+    companion_state: State<'_, CompanionState>,
+    port: Option<u16>,
+    max_streams: Option<usize>,
+    read_only: Option<bool>,
+) -> Result<CompanionServerInfo, String> {
+    if max_streams == Some(0) {
+        return Err("max_streams must be at least 1".to_string());
+    }
+
+    // Persist the new values so a later start (and the reload below) uses them
+    {
+        let db_lock = app_state.db.lock().map_err(|e| e.to_string())?;
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        if let Some(max_streams) = max_streams {
+            db.set_setting(MAX_STREAMS_SETTING, &max_streams.to_string())
+                .map_err(|e| format!("Failed to save stream limit: {}", e))?;
+        }
+        if let Some(port) = port {
+            db.set_setting("companion_port", &port.to_string())
+                .map_err(|e| format!("Failed to save port: {}", e))?;
+        }
+        if let Some(read_only) = read_only {
+            db.set_setting(READ_ONLY_SETTING, if read_only { "true" } else { "false" })
+                .map_err(|e| format!("Failed to save browse mode: {}", e))?;
+        }
+    }
+
+    load_companion_config(&app_state, &companion_state)?;
+
+    let needs_restart = {
+        let lock = companion_state
+            .running_server
+            .lock()
+            .map_err(|e| e.to_string())?;
+        match (lock.as_ref(), port) {
+            (Some(server), Some(port)) => server.addr.port() != port,
+            _ => false,
+        }
+    };
+
+    if needs_restart {
+        eprintln!("[companion] Port changed, restarting listener");
+        {
+            let mut lock = companion_state
+                .running_server
+                .lock()
+                .map_err(|e| e.to_string())?;
+            if let Some(server) = lock.take() {
+                let _ = server.shutdown_tx.send(());
+            }
+        }
+
+        // Brief pause for port release
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+        return start_companion_server(app, app_state, companion_state, port).await;
+    }
+
+    eprintln!(
+        "[companion] Config reloaded (max streams: {}, read-only: {})",
+        companion_state.max_streams.load(Ordering::Relaxed),
+        companion_state.read_only.load(Ordering::Relaxed)
+    );
+    get_companion_status(companion_state)
+}
+
 /// Auto-start the companion server if `companion_autostart` is enabled.
 /// Called from `init_database` after the DB is ready.
 pub async fn auto_start_companion(app_handle: tauri::AppHandle) {
@@ -369,11 +602,17 @@ pub async fn auto_start_companion(app_handle: tauri::AppHandle) {
     };
 
     let library_folders = companion_state.library_folders.clone();
+    let max_streams = companion_state.max_streams.clone();
+    let read_only = companion_state.read_only.clone();
+    let queue = app_handle.state::<QueueState>().queue.clone();
     let mobile_dist = find_mobile_dist(Some(&app_handle));
 
-    match server::start_server(port, token, db_arc, library_folders, 3, mobile_dist).await {
+    match server::start_server(port, token, db_arc, library_folders, max_streams, read_only, queue, mobile_dist).await {
         Ok(running) => {
             persist_companion_settings(&app_state, &running.token, running.addr.port());
+            watch_sync(&app_handle, &running);
+            serve_waveforms(&app_handle, &running);
+            relay_analysis_events(&app_handle, &running);
 
             let lan_ip = get_lan_ip_for_qr();
             eprintln!(
@@ -0,0 +1,516 @@
+// Listening sessions and playlist drafts
+// A session is a run of plays with no gap longer than SESSION_GAP_SECS, identified by its
+// first play_history row. suggest_playlist_from_session clusters the tracks that were
+// kept (played past the skip threshold) by BPM, key and genre closeness and stores each
+// cluster as a playlist draft, which the user can accept (becomes a real playlist) or
+// discard. Clustering and default names are deterministic; AI naming is optional.
+
+use crate::ai::ClaudeClient;
+use crate::audio::key::{camelot_compatible, parse_camelot};
+use crate::commands::ai::{get_api_key_from_db, record_usage};
+use crate::commands::library::{track_with_analysis, AppState, TrackDTO};
+use crate::commands::playlists::notify_playlists_changed;
+use crate::db::{Database, PlayRecord, PlaylistDraft};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use tauri::{AppHandle, State};
+
+/// A pause longer than this between plays starts a new session
+const SESSION_GAP_SECS: i64 = 45 * 60;
+/// Default number of sessions returned by get_listening_sessions
+const DEFAULT_SESSION_LIMIT: usize = 20;
+/// Smaller clusters aren't proposed on their own
+const MIN_CLUSTER_SIZE: usize = 3;
+/// Tracks at most this far apart (see track_distance) end up in the same cluster
+const CLUSTER_DISTANCE: f64 = 1.5;
+/// BPM difference (relative) that counts as one unit of distance
+const BPM_TOLERANCE: f64 = 0.06;
+
+/// A run of plays without a long pause
+#[derive(Debug, Clone, PartialEq)]
+struct Session {
+    id: i64,
+    plays: Vec<PlayRecord>,
+}
+
+impl Session {
+    /// Distinct tracks played past the skip threshold, in first-played order
+    fn kept_tracks(&self) -> Vec<i64> {
+        let mut seen = HashSet::new();
+        self.plays
+            .iter()
+            .filter(|p| !p.skipped)
+            .filter(|p| seen.insert(p.track_id))
+            .map(|p| p.track_id)
+            .collect()
+    }
+}
+
+/// Split play history (oldest first) into sessions
+fn split_sessions(history: Vec<PlayRecord>) -> Vec<Session> {
+    let mut sessions: Vec<Session> = Vec::new();
+    for play in history {
+        match sessions.last_mut() {
+            Some(session)
+                if play.played_at_secs - session.plays.last().unwrap().played_at_secs <= SESSION_GAP_SECS =>
+            {
+                session.plays.push(play)
+            }
+            _ => sessions.push(Session { id: play.id, plays: vec![play] }),
+        }
+    }
+    sessions
+}
+
+/// What clustering looks at
+#[derive(Debug, Clone)]
+struct TrackFeatures {
+    track_id: i64,
+    bpm: Option<f64>,
+    key: Option<String>,
+    genre: Option<String>,
+}
+
+/// 0 = same BPM (allowing half/double time), same or compatible key and same genre.
+/// Unknown values count as half a mismatch.
+fn track_distance(a: &TrackFeatures, b: &TrackFeatures) -> f64 {
+    let bpm = match (a.bpm, b.bpm) {
+        (Some(x), Some(y)) if x > 0.0 && y > 0.0 => [y, y * 2.0, y / 2.0]
+            .iter()
+            .map(|&y| (x - y).abs() / x.min(y))
+            .fold(f64::MAX, f64::min)
+            / BPM_TOLERANCE,
+        _ => 0.5,
+    }
+    .min(2.0);
+
+    let key = match (a.key.as_deref(), b.key.as_deref()) {
+        (Some(x), Some(y)) if parse_camelot(x).is_some() && parse_camelot(y).is_some() => {
+            if camelot_compatible(x, y) { 0.0 } else { 1.0 }
+        }
+        _ => 0.5,
+    };
+
+    let genre = match (a.genre.as_deref(), b.genre.as_deref()) {
+        (Some(x), Some(y)) => if x.eq_ignore_ascii_case(y) { 0.0 } else { 1.0 },
+        _ => 0.5,
+    };
+
+    bpm + key + genre
+}
+
+/// Single-linkage clusters of tracks within CLUSTER_DISTANCE, largest first.
+/// Each cluster is ordered by BPM (unknown last) so it plays as a gradual build.
+/// If no cluster reaches MIN_CLUSTER_SIZE, all tracks form one cluster.
+fn cluster_tracks(tracks: &[TrackFeatures]) -> Vec<Vec<TrackFeatures>> {
+    let mut parent: Vec<usize> = (0..tracks.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..tracks.len() {
+        for j in i + 1..tracks.len() {
+            if track_distance(&tracks[i], &tracks[j]) <= CLUSTER_DISTANCE {
+                let (ri, rj) = (root(&mut parent, i), root(&mut parent, j));
+                parent[ri.max(rj)] = ri.min(rj);
+            }
+        }
+    }
+
+    // Group by root, keeping first-played order of the clusters for ties
+    let mut groups: Vec<(usize, Vec<TrackFeatures>)> = Vec::new();
+    for (i, track) in tracks.iter().enumerate() {
+        let r = root(&mut parent, i);
+        match groups.iter_mut().find(|(root, _)| *root == r) {
+            Some((_, members)) => members.push(track.clone()),
+            None => groups.push((r, vec![track.clone()])),
+        }
+    }
+    let mut clusters: Vec<Vec<TrackFeatures>> = groups
+        .into_iter()
+        .map(|(_, members)| members)
+        .filter(|members| members.len() >= MIN_CLUSTER_SIZE)
+        .collect();
+    if clusters.is_empty() && !tracks.is_empty() {
+        clusters.push(tracks.to_vec());
+    }
+    clusters.sort_by_key(|members| std::cmp::Reverse(members.len()));
+
+    for members in &mut clusters {
+        members.sort_by(|a, b| match (a.bpm, b.bpm) {
+            (Some(x), Some(y)) => x.total_cmp(&y),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+    }
+    clusters
+}
+
+/// Most common non-empty values, most frequent first (ties by first appearance)
+fn most_common<'a>(values: impl Iterator<Item = &'a str>, limit: usize) -> Vec<&'a str> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for value in values.filter(|v| !v.is_empty()) {
+        match counts.iter_mut().find(|(v, _)| v.eq_ignore_ascii_case(value)) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((value, 1)),
+        }
+    }
+    counts.sort_by_key(|&(_, n)| std::cmp::Reverse(n));
+    counts.into_iter().take(limit).map(|(v, _)| v).collect()
+}
+
+/// Default name, e.g. "Tech House 124-126 BPM (8A/9A)"
+fn cluster_name(members: &[TrackFeatures]) -> String {
+    let genre = most_common(members.iter().filter_map(|t| t.genre.as_deref()), 1)
+        .first()
+        .map(|g| g.to_string())
+        .unwrap_or_else(|| "Session".to_string());
+
+    let bpms: Vec<f64> = members.iter().filter_map(|t| t.bpm).collect();
+    let mut name = genre;
+    if !bpms.is_empty() {
+        let lo = bpms.iter().cloned().fold(f64::MAX, f64::min).round();
+        let hi = bpms.iter().cloned().fold(f64::MIN, f64::max).round();
+        if lo == hi {
+            name.push_str(&format!(" {} BPM", lo));
+        } else {
+            name.push_str(&format!(" {}-{} BPM", lo, hi));
+        }
+    }
+
+    let keys = most_common(members.iter().filter_map(|t| t.key.as_deref()), 2);
+    if !keys.is_empty() {
+        name.push_str(&format!(" ({})", keys.join("/")));
+    }
+    name
+}
+
+/// Summary of a listening session
+#[derive(Debug, Serialize)]
+pub struct ListeningSessionDTO {
+    /// First play_history row of the session
+    pub id: i64,
+    pub started_at: String,
+    pub ended_at: String,
+    pub play_count: usize,
+    /// Distinct tracks that weren't skipped
+    pub kept_count: usize,
+}
+
+impl From<&Session> for ListeningSessionDTO {
+    fn from(session: &Session) -> Self {
+        Self {
+            id: session.id,
+            started_at: session.plays.first().map(|p| p.played_at.clone()).unwrap_or_default(),
+            ended_at: session.plays.last().map(|p| p.played_at.clone()).unwrap_or_default(),
+            play_count: session.plays.len(),
+            kept_count: session.kept_tracks().len(),
+        }
+    }
+}
+
+/// A proposed playlist
+#[derive(Debug, Serialize)]
+pub struct PlaylistDraftDTO {
+    pub id: i64,
+    pub session_id: i64,
+    pub name: String,
+    pub tracks: Vec<TrackDTO>,
+    pub created_at: String,
+}
+
+fn draft_to_dto(db: &Database, draft: PlaylistDraft) -> PlaylistDraftDTO {
+    PlaylistDraftDTO {
+        id: draft.id,
+        session_id: draft.session_id,
+        name: draft.name,
+        // Tracks deleted since the draft was made are left out
+        tracks: draft.track_ids.iter().filter_map(|&id| track_with_analysis(db, id)).collect(),
+        created_at: draft.created_at,
+    }
+}
+
+fn load_sessions(db: &Database) -> Result<Vec<Session>, String> {
+    let history = db.get_play_history()
+        .map_err(|e| format!("Failed to get play history: {}", e))?;
+    Ok(split_sessions(history))
+}
+
+/// Recent listening sessions, newest first
+#[tauri::command]
+pub fn get_listening_sessions(
+    state: State<AppState>,
+    limit: Option<usize>,
+) -> Result<Vec<ListeningSessionDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    Ok(load_sessions(db)?
+        .iter()
+        .rev()
+        .take(limit.unwrap_or(DEFAULT_SESSION_LIMIT))
+        .map(ListeningSessionDTO::from)
+        .collect())
+}
+
+/// Ask Claude for one short name per cluster. None if it can't be used.
+async fn ai_cluster_names(state: &State<'_, AppState>, api_key: String, clusters: &[Vec<String>]) -> Option<Vec<String>> {
+    let listing = clusters
+        .iter()
+        .enumerate()
+        .map(|(i, tracks)| format!("Playlist {}:\n{}", i + 1, tracks.join("\n")))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let prompt = format!(
+        "Suggest a short, evocative name (at most 5 words) for each of these DJ playlists.\n\n{}\n\nRespond with only a JSON array of {} strings, in order.",
+        listing,
+        clusters.len()
+    );
+
+    let client = ClaudeClient::new(api_key);
+    let messages = vec![crate::ai::claude_client::Message {
+        role: "user".to_string(),
+        content: prompt,
+    }];
+    let response = client.chat(messages, None).await;
+    record_usage(state, &client, "session_naming");
+    let response = match response {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("[sessions] AI naming failed: {}", e);
+            return None;
+        }
+    };
+
+    let start = response.find('[')?;
+    let end = response.rfind(']')?;
+    let names: Vec<String> = serde_json::from_str(response.get(start..=end)?).ok()?;
+    let names: Vec<String> = names.into_iter().map(|n| n.trim().to_string()).collect();
+    (names.len() == clusters.len() && names.iter().all(|n| !n.is_empty())).then_some(names)
+}
+
+/// Propose playlists from the tracks kept during a listening session: one draft per
+/// cluster of tracks close in BPM, key and genre. Replaces the session's earlier drafts.
+/// With `ai_names`, Claude names the drafts (falls back to the default names if no key
+/// is configured or the request fails).
+#[tauri::command]
+pub async fn suggest_playlist_from_session(
+    state: State<'_, AppState>,
+    session_id: i64,
+    ai_names: Option<bool>,
+) -> Result<Vec<PlaylistDraftDTO>, String> {
+    let (clusters, descriptions) = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+        let session = load_sessions(db)?
+            .into_iter()
+            .find(|s| s.id == session_id)
+            .ok_or_else(|| format!("Listening session {} not found", session_id))?;
+
+        let mut titles: HashMap<i64, String> = HashMap::new();
+        let features: Vec<TrackFeatures> = session
+            .kept_tracks()
+            .into_iter()
+            .filter_map(|id| {
+                let track = db.get_track(id).ok()?;
+                let analysis = db.get_track_analysis(id).ok().flatten();
+                titles.insert(id, format!(
+                    "{} - {}",
+                    track.artist.as_deref().unwrap_or("Unknown"),
+                    track.title.as_deref().unwrap_or("Untitled")
+                ));
+                Some(TrackFeatures {
+                    track_id: id,
+                    bpm: analysis.as_ref().and_then(|a| a.bpm),
+                    key: analysis.and_then(|a| a.musical_key),
+                    genre: track.genre,
+                })
+            })
+            .collect();
+        if features.is_empty() {
+            return Err("No tracks were kept in this session".to_string());
+        }
+
+        let clusters = cluster_tracks(&features);
+        let descriptions: Vec<Vec<String>> = clusters
+            .iter()
+            .map(|members| {
+                members
+                    .iter()
+                    .map(|t| {
+                        let mut line = titles.get(&t.track_id).cloned().unwrap_or_default();
+                        if let Some(bpm) = t.bpm {
+                            line.push_str(&format!(", {:.0} BPM", bpm));
+                        }
+                        if let Some(key) = &t.key {
+                            line.push_str(&format!(", {}", key));
+                        }
+                        if let Some(genre) = &t.genre {
+                            line.push_str(&format!(", {}", genre));
+                        }
+                        line
+                    })
+                    .collect()
+            })
+            .collect();
+        (clusters, descriptions)
+    };
+
+    let mut names: Vec<String> = clusters.iter().map(|members| cluster_name(members)).collect();
+    if ai_names.unwrap_or(false) {
+        if let Some(api_key) = get_api_key_from_db(&state)? {
+            if let Some(ai) = ai_cluster_names(&state, api_key, &descriptions).await {
+                names = ai;
+            }
+        }
+    }
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+    db.delete_session_drafts(session_id)
+        .map_err(|e| format!("Failed to clear old drafts: {}", e))?;
+    let mut draft_ids = Vec::with_capacity(clusters.len());
+    for (members, name) in clusters.iter().zip(&names) {
+        let track_ids: Vec<i64> = members.iter().map(|t| t.track_id).collect();
+        draft_ids.push(
+            db.create_playlist_draft(session_id, name, &track_ids)
+                .map_err(|e| format!("Failed to save playlist draft: {}", e))?,
+        );
+    }
+    tx.commit().map_err(|e| format!("Failed to commit drafts: {}", e))?;
+
+    draft_ids
+        .into_iter()
+        .map(|id| {
+            db.get_playlist_draft(id)
+                .map(|draft| draft_to_dto(db, draft))
+                .map_err(|e| format!("Failed to get playlist draft: {}", e))
+        })
+        .collect()
+}
+
+/// All playlist drafts waiting to be accepted or discarded, newest first
+#[tauri::command]
+pub fn get_playlist_drafts(state: State<AppState>) -> Result<Vec<PlaylistDraftDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let drafts = db.get_playlist_drafts()
+        .map_err(|e| format!("Failed to get playlist drafts: {}", e))?;
+    Ok(drafts.into_iter().map(|draft| draft_to_dto(db, draft)).collect())
+}
+
+/// Turn a draft into a real playlist (optionally renamed). Returns the new playlist ID.
+#[tauri::command]
+pub fn accept_playlist_draft(
+    app: AppHandle,
+    state: State<AppState>,
+    draft_id: i64,
+    name: Option<String>,
+) -> Result<i64, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let draft = db.get_playlist_draft(draft_id)
+        .map_err(|e| format!("Failed to get playlist draft: {}", e))?;
+    let name = name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or(draft.name);
+
+    let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+    let playlist_id = db.create_playlist(&name, "manual", None)
+        .map_err(|e| format!("Failed to create playlist: {}", e))?;
+    for track_id in draft.track_ids {
+        if db.get_track(track_id).is_ok() {
+            db.add_track_to_playlist(playlist_id, track_id)
+                .map_err(|e| format!("Failed to add track to playlist: {}", e))?;
+        }
+    }
+    db.delete_playlist_draft(draft_id)
+        .map_err(|e| format!("Failed to delete playlist draft: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit playlist: {}", e))?;
+    notify_playlists_changed(&app);
+
+    Ok(playlist_id)
+}
+
+/// Throw a draft away
+#[tauri::command]
+pub fn discard_playlist_draft(state: State<AppState>, draft_id: i64) -> Result<(), String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.delete_playlist_draft(draft_id)
+        .map_err(|e| format!("Failed to delete playlist draft: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(id: i64, track_id: i64, secs: i64, skipped: bool) -> PlayRecord {
+        PlayRecord { id, track_id, played_at: String::new(), played_at_secs: secs, skipped }
+    }
+
+    fn features(track_id: i64, bpm: f64, key: &str, genre: &str) -> TrackFeatures {
+        TrackFeatures {
+            track_id,
+            bpm: Some(bpm),
+            key: Some(key.to_string()),
+            genre: Some(genre.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_split_sessions_and_kept_tracks() {
+        let sessions = split_sessions(vec![
+            play(1, 10, 0, false),
+            play(2, 11, 60, true),
+            play(3, 10, 120, false),
+            play(4, 12, 180, false),
+            play(5, 13, 180 + SESSION_GAP_SECS + 1, false),
+        ]);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].id, 1);
+        assert_eq!(sessions[0].kept_tracks(), vec![10, 12]);
+        assert_eq!(sessions[1].id, 5);
+    }
+
+    #[test]
+    fn test_cluster_tracks() {
+        let tracks = vec![
+            features(1, 124.0, "8A", "Tech House"),
+            features(2, 172.0, "3B", "Drum & Bass"),
+            features(3, 125.0, "9A", "Tech House"),
+            features(4, 174.0, "3B", "Drum & Bass"),
+            features(5, 126.0, "8A", "Tech House"),
+            features(6, 87.0, "4B", "Drum & Bass"), // half time
+        ];
+        let clusters = cluster_tracks(&tracks);
+        assert_eq!(clusters.len(), 2);
+        let ids: Vec<Vec<i64>> = clusters
+            .iter()
+            .map(|c| c.iter().map(|t| t.track_id).collect())
+            .collect();
+        assert_eq!(ids, vec![vec![1, 3, 5], vec![6, 2, 4]]);
+        assert_eq!(cluster_name(&clusters[0]), "Tech House 124-126 BPM (8A/9A)");
+    }
+
+    #[test]
+    fn test_cluster_tracks_falls_back_to_one_playlist() {
+        let tracks = vec![
+            features(1, 124.0, "8A", "Tech House"),
+            features(2, 172.0, "3B", "Drum & Bass"),
+        ];
+        let clusters = cluster_tracks(&tracks);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].len(), 2);
+    }
+}
@@ -2,7 +2,20 @@
 // Handles library folders, theme selection, and generic key-value settings.
 // All settings are stored in the SQLite `settings` table as JSON strings.
 
+use crate::audio::mime::{self, StreamMimeConfig, STREAM_MIME_SETTING};
+use crate::audio::stream_access::{self, STREAM_RESTRICT_SETTING};
+use crate::commands::analysis::{tempo_curve_enabled, TEMPO_CURVE_SETTING};
 use crate::commands::library::AppState;
+use crate::commands::playback::{
+    PlaybackState, SampleFormat, StreamConfig, MAX_STREAM_CHUNK_MS, SKIP_LEADING_SILENCE_SETTING,
+    STREAM_CHUNK_MS_SETTING, STREAM_SAMPLE_FORMAT_SETTING,
+};
+use crate::db::SORT_IGNORE_ARTICLES_SETTING;
+use crate::scanner::{
+    collapse_roots, load_symlink_folders, normalize_root, EnergyExtractor, HashMode, SymlinkMode,
+    WriteConflictMode, ENERGY_EXTRACTOR_SETTING, HASH_MODE_SETTING, SYMLINK_FOLDERS_SETTING,
+    WRITE_CONFLICT_SETTING,
+};
 use serde::{Deserialize, Serialize};
 use tauri::State;
 
@@ -31,13 +44,19 @@ pub fn set_setting(state: State<AppState>, key: String, value: String) -> Result
     let db = db_lock.as_ref().ok_or("Database not initialized")?;
 
     db.set_setting(&key, &value)
-        .map_err(|e| format!("Failed to set setting '{}': {}", key, e))
+        .map_err(|e| format!("Failed to set setting '{}': {}", key, e))?;
+    if ["library_folders", SYMLINK_FOLDERS_SETTING, STREAM_RESTRICT_SETTING].contains(&key.as_str()) {
+        stream_access::refresh(db);
+    }
+    Ok(())
 }
 
 // --- Library folder management ---
 
 /// Get all saved library folders.
-/// Returns an empty array if no folders have been configured.
+/// Returns an empty array if no folders have been configured. Folders nested in another
+/// library folder (saved before overlaps were folded on add) are left out, so callers
+/// scanning every folder scan each file once.
 #[tauri::command]
 pub fn get_library_folders(state: State<AppState>) -> Result<Vec<String>, String> {
     let db_lock = state.db.lock().unwrap();
@@ -51,16 +70,18 @@ pub fn get_library_folders(state: State<AppState>) -> Result<Vec<String>, String
         Some(json_str) => {
             let folders: Vec<String> = serde_json::from_str(&json_str)
                 .map_err(|e| format!("Failed to parse library folders JSON: {}", e))?;
-            Ok(folders)
+            Ok(collapse_roots(&folders))
         }
         None => Ok(Vec::new()),
     }
 }
 
-/// Add a library folder. Prevents duplicates.
+/// Add a library folder. Prevents duplicates: a folder inside an existing library folder
+/// is rejected, and library folders inside the new one are folded into it.
 /// Returns the updated list of folders.
 #[tauri::command]
 pub fn add_library_folder(state: State<AppState>, path: String) -> Result<Vec<String>, String> {
+    let path = normalize_root(&path);
     let db_lock = state.db.lock().unwrap();
     let db = db_lock.as_ref().ok_or("Database not initialized")?;
 
@@ -74,9 +95,13 @@ pub fn add_library_folder(state: State<AppState>, path: String) -> Result<Vec<St
         None => Vec::new(),
     };
 
-    // Check for duplicates (case-sensitive path comparison)
-    if folders.contains(&path) {
-        return Err(format!("Folder already in library: {}", path));
+    // Check for duplicates and overlaps (case-sensitive path comparison)
+    folders = collapse_roots(&folders);
+    if let Some(root) = folders.iter().find(|f| std::path::Path::new(&path).starts_with(f)) {
+        if *root == path {
+            return Err(format!("Folder already in library: {}", path));
+        }
+        return Err(format!("Folder is already in the library as part of {}", root));
     }
 
     // Verify the path exists and is a directory
@@ -88,7 +113,12 @@ pub fn add_library_folder(state: State<AppState>, path: String) -> Result<Vec<St
         return Err(format!("Path is not a directory: {}", path));
     }
 
-    // Add the folder
+    // Add the folder, replacing library folders inside it
+    let nested = folders.len();
+    folders.retain(|f| !std::path::Path::new(f).starts_with(&path));
+    if folders.len() < nested {
+        eprintln!("[settings] {} replaces {} nested library folder(s)", path, nested - folders.len());
+    }
     folders.push(path);
 
     // Save back to settings
@@ -96,6 +126,7 @@ pub fn add_library_folder(state: State<AppState>, path: String) -> Result<Vec<St
         .map_err(|e| format!("Failed to serialize library folders: {}", e))?;
     db.set_setting("library_folders", &json_str)
         .map_err(|e| format!("Failed to save library folders: {}", e))?;
+    stream_access::refresh(db);
 
     Ok(folders)
 }
@@ -118,8 +149,9 @@ pub fn remove_library_folder(state: State<AppState>, path: String) -> Result<Vec
     };
 
     // Remove the folder
+    let path = normalize_root(&path);
     let original_len = folders.len();
-    folders.retain(|f| f != &path);
+    folders.retain(|f| normalize_root(f) != path);
 
     if folders.len() == original_len {
         return Err(format!("Folder not found in library: {}", path));
@@ -130,6 +162,7 @@ pub fn remove_library_folder(state: State<AppState>, path: String) -> Result<Vec
         .map_err(|e| format!("Failed to serialize library folders: {}", e))?;
     db.set_setting("library_folders", &json_str)
         .map_err(|e| format!("Failed to save library folders: {}", e))?;
+    stream_access::refresh(db);
 
     Ok(folders)
 }
@@ -168,3 +201,324 @@ pub fn set_theme(state: State<AppState>, theme: String) -> Result<(), String> {
     db.set_setting("theme", &theme)
         .map_err(|e| format!("Failed to save theme: {}", e))
 }
+
+// --- Playback ---
+
+/// Whether tracks start after their detected leading silence
+#[tauri::command]
+pub fn get_skip_leading_silence(state: State<AppState>) -> Result<bool, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let value = db.get_setting(SKIP_LEADING_SILENCE_SETTING)
+        .map_err(|e| format!("Failed to get skip leading silence setting: {}", e))?;
+    Ok(value.as_deref() == Some("true"))
+}
+
+/// Start loaded tracks after their leading silence (detected during waveform analysis)
+#[tauri::command]
+pub fn set_skip_leading_silence(state: State<AppState>, enabled: bool) -> Result<(), String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.set_setting(SKIP_LEADING_SILENCE_SETTING, if enabled { "true" } else { "false" })
+        .map_err(|e| format!("Failed to save skip leading silence setting: {}", e))
+}
+
+/// Whether the player only streams files in the library folders (and the artwork cache)
+#[tauri::command]
+pub fn get_stream_restrict_to_library(state: State<AppState>) -> Result<bool, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let value = db.get_setting(STREAM_RESTRICT_SETTING)
+        .map_err(|e| format!("Failed to get stream restriction setting: {}", e))?;
+    Ok(value.as_deref() != Some("false"))
+}
+
+/// Restrict the player's stream:// protocol to the library folders (on by default); off,
+/// it serves any file path it's asked for. Applies to the next request.
+#[tauri::command]
+pub fn set_stream_restrict_to_library(state: State<AppState>, enabled: bool) -> Result<(), String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.set_setting(STREAM_RESTRICT_SETTING, if enabled { "true" } else { "false" })
+        .map_err(|e| format!("Failed to save stream restriction setting: {}", e))?;
+    stream_access::refresh(db);
+    Ok(())
+}
+
+/// Sample format and chunk length of the audio sent to the webview
+#[tauri::command]
+pub fn get_stream_config(state: State<AppState>) -> Result<StreamConfig, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    Ok(StreamConfig::from_settings(db))
+}
+
+/// Set how audio is sent to the webview. Applies from the next play/seek.
+#[tauri::command]
+pub fn set_stream_config(
+    state: State<AppState>,
+    playback_state: State<PlaybackState>,
+    config: StreamConfig,
+) -> Result<(), String> {
+    if config.chunk_ms > MAX_STREAM_CHUNK_MS {
+        return Err(format!("Invalid chunk length {}ms. Must be 0-{}ms", config.chunk_ms, MAX_STREAM_CHUNK_MS));
+    }
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let sample_format = match config.sample_format {
+        SampleFormat::F32 => "f32",
+        SampleFormat::I16 => "i16",
+    };
+    db.set_setting(STREAM_SAMPLE_FORMAT_SETTING, sample_format)
+        .map_err(|e| format!("Failed to save stream sample format: {}", e))?;
+    db.set_setting(STREAM_CHUNK_MS_SETTING, &config.chunk_ms.to_string())
+        .map_err(|e| format!("Failed to save stream chunk length: {}", e))?;
+
+    *playback_state.stream_config.lock().unwrap() = config;
+    Ok(())
+}
+
+/// Whether alphabetical sorting ignores a leading "The", "A" or "An"
+#[tauri::command]
+pub fn get_sort_ignore_articles(state: State<AppState>) -> Result<bool, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let value = db.get_setting(SORT_IGNORE_ARTICLES_SETTING)
+        .map_err(|e| format!("Failed to get sort setting: {}", e))?;
+    Ok(value.as_deref() == Some("true"))
+}
+
+/// Sort "The Prodigy" under P when sorting by artist/title/album
+#[tauri::command]
+pub fn set_sort_ignore_articles(state: State<AppState>, enabled: bool) -> Result<(), String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.set_setting(SORT_IGNORE_ARTICLES_SETTING, if enabled { "true" } else { "false" })
+        .map_err(|e| format!("Failed to save sort setting: {}", e))
+}
+
+// --- Analysis source priority ---
+
+/// Get which BPM/key source wins: "tag" (values from file tags, the default)
+/// or "analysis" (RecoDeck's own analysis replaces tag values).
+#[tauri::command]
+pub fn get_analysis_priority(state: State<AppState>) -> Result<String, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    Ok(if db.prefer_tag_values() { "tag" } else { "analysis" }.to_string())
+}
+
+/// Set which BPM/key source wins. With "analysis", batch analysis also re-analyzes
+/// tracks whose values came from tags, and rescans no longer overwrite analyzed values.
+#[tauri::command]
+pub fn set_analysis_priority(state: State<AppState>, priority: String) -> Result<(), String> {
+    if priority != "tag" && priority != "analysis" {
+        return Err(format!(
+            "Invalid analysis priority '{}'. Valid values: tag, analysis",
+            priority
+        ));
+    }
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.set_setting(crate::db::ANALYSIS_PRIORITY_SETTING, &priority)
+        .map_err(|e| format!("Failed to save analysis priority: {}", e))
+}
+
+/// Whether BPM analysis also measures a tempo curve (to flag variable-tempo tracks)
+#[tauri::command]
+pub fn get_tempo_curve_analysis(state: State<AppState>) -> Result<bool, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    Ok(tempo_curve_enabled(db))
+}
+
+/// Measure a tempo curve alongside every BPM analysis (single-track and auto-analysis)
+#[tauri::command]
+pub fn set_tempo_curve_analysis(state: State<AppState>, enabled: bool) -> Result<(), String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.set_setting(TEMPO_CURVE_SETTING, if enabled { "true" } else { "false" })
+        .map_err(|e| format!("Failed to save tempo curve setting: {}", e))
+}
+
+// --- Scan hash mode ---
+
+/// Get when the scanner hashes files: "full" (every new or changed file, the default)
+/// or "lazy" (only when duplicate detection needs the hash)
+#[tauri::command]
+pub fn get_scan_hash_mode(state: State<AppState>) -> Result<HashMode, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    Ok(HashMode::from_settings(db))
+}
+
+/// Set when the scanner hashes files. Lazy mode makes scans of large libraries much
+/// faster; duplicate content is then only found by the duplicate cleanup, not at import.
+#[tauri::command]
+pub fn set_scan_hash_mode(state: State<AppState>, mode: HashMode) -> Result<(), String> {
+    let value = match mode {
+        HashMode::Full => "full",
+        HashMode::Lazy => "lazy",
+    };
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.set_setting(HASH_MODE_SETTING, value)
+        .map_err(|e| format!("Failed to save scan hash mode: {}", e))
+}
+
+// --- Scan symlinks ---
+
+/// A library folder's symlink handling
+#[derive(Debug, Serialize)]
+pub struct FolderSymlinkModeDTO {
+    pub folder: String,
+    pub mode: SymlinkMode,
+}
+
+/// How each library folder's scans treat symlinks: "follow" (the default; each folder and
+/// file is still scanned once) or "skip"
+#[tauri::command]
+pub fn get_folder_symlink_modes(state: State<AppState>) -> Result<Vec<FolderSymlinkModeDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let folders: Vec<String> = match db.get_setting("library_folders")
+        .map_err(|e| format!("Failed to get library folders: {}", e))?
+    {
+        Some(json_str) => serde_json::from_str(&json_str).unwrap_or_default(),
+        None => Vec::new(),
+    };
+    let modes = load_symlink_folders(db);
+    Ok(folders
+        .iter()
+        .map(|folder| {
+            let folder = normalize_root(folder);
+            let mode = modes.get(&folder).copied().unwrap_or_default();
+            FolderSymlinkModeDTO { folder, mode }
+        })
+        .collect())
+}
+
+/// Set whether scans of a library folder follow or skip symlinks. Applies from the next scan.
+#[tauri::command]
+pub fn set_folder_symlink_mode(state: State<AppState>, folder: String, mode: SymlinkMode) -> Result<(), String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let mut modes = load_symlink_folders(db);
+    let folder = normalize_root(&folder);
+    if mode == SymlinkMode::default() {
+        modes.remove(&folder);
+    } else {
+        modes.insert(folder, mode);
+    }
+    let json = serde_json::to_string(&modes)
+        .map_err(|e| format!("Failed to serialize symlink modes: {}", e))?;
+    db.set_setting(SYMLINK_FOLDERS_SETTING, &json)
+        .map_err(|e| format!("Failed to save symlink modes: {}", e))?;
+    stream_access::refresh(db);
+    Ok(())
+}
+
+// --- Write conflicts ---
+
+/// Get what happens when RecoDeck is about to write to a file that another program
+/// modified since it was read: "refuse" (the default) or "reread"
+#[tauri::command]
+pub fn get_write_conflict_mode(state: State<AppState>) -> Result<WriteConflictMode, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    Ok(WriteConflictMode::from_settings(db))
+}
+
+/// Set the write conflict mode. "reread" picks up the outside edits into the library
+/// and then writes; "refuse" leaves the file alone until it is rescanned.
+#[tauri::command]
+pub fn set_write_conflict_mode(state: State<AppState>, mode: WriteConflictMode) -> Result<(), String> {
+    let value = match mode {
+        WriteConflictMode::Refuse => "refuse",
+        WriteConflictMode::Reread => "reread",
+    };
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.set_setting(WRITE_CONFLICT_SETTING, value)
+        .map_err(|e| format!("Failed to save write conflict mode: {}", e))
+}
+
+// --- Energy extractor ---
+
+/// Get the fields and keywords the scanner uses to find energy levels in tags
+#[tauri::command]
+pub fn get_energy_extractor(state: State<AppState>) -> Result<EnergyExtractor, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    Ok(EnergyExtractor::from_settings(db))
+}
+
+/// Save the energy extractor config. Applies to tracks imported from now on.
+#[tauri::command]
+pub fn set_energy_extractor(state: State<AppState>, config: EnergyExtractor) -> Result<(), String> {
+    if config.fields.iter().all(|f| f.trim().is_empty()) {
+        return Err("At least one field is required".to_string());
+    }
+
+    let json = serde_json::to_string(&config)
+        .map_err(|e| format!("Failed to serialize energy extractor: {}", e))?;
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.set_setting(ENERGY_EXTRACTOR_SETTING, &json)
+        .map_err(|e| format!("Failed to save energy extractor: {}", e))
+}
+
+// --- Stream Content-Type ---
+
+/// Get how the Content-Type of streamed files is chosen: content probing on/off and
+/// per-extension overrides
+#[tauri::command]
+pub fn get_stream_mime_config(state: State<AppState>) -> Result<StreamMimeConfig, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    Ok(StreamMimeConfig::from_settings(db))
+}
+
+/// Save the stream Content-Type config. Applies to the next request (player and companion).
+/// Returns the normalized config.
+#[tauri::command]
+pub fn set_stream_mime_config(
+    state: State<AppState>,
+    config: StreamMimeConfig,
+) -> Result<StreamMimeConfig, String> {
+    let config = config.validate()?;
+    let json = serde_json::to_string(&config)
+        .map_err(|e| format!("Failed to serialize stream MIME config: {}", e))?;
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.set_setting(STREAM_MIME_SETTING, &json)
+        .map_err(|e| format!("Failed to save stream MIME config: {}", e))?;
+    mime::configure(config.clone());
+    Ok(config)
+}
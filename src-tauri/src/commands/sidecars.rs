@@ -0,0 +1,167 @@
+// Analysis sidecars (formats::sidecar): write a track's BPM, key, waveform overview and
+// cues next to its file, and read them back, to move analysis between machines with the
+// files themselves. File I/O runs without the DB lock, which is taken once per track.
+
+use crate::commands::library::AppState;
+use crate::db::Database;
+use crate::formats::sidecar::{self, Sidecar, SidecarSettings, SIDECAR_SETTING};
+use serde::Serialize;
+use std::path::Path;
+use tauri::State;
+
+/// Outcome of writing or reading sidecars for a set of tracks
+#[derive(Debug, Default, Serialize)]
+pub struct SidecarResult {
+    /// Sidecars written / tracks filled from a sidecar
+    pub processed: usize,
+    /// Tracks with nothing to write, or without a (matching) sidecar
+    pub skipped: usize,
+    pub errors: Vec<String>,
+}
+
+/// A track's sidecar as it should be on disk, with the audio file's path.
+/// None if the track has no analysis or cues to carry.
+fn build_sidecar(db: &Database, track_id: i64) -> Result<Option<(String, Sidecar)>, String> {
+    let track = db.get_track(track_id)
+        .map_err(|e| format!("Failed to get track {}: {}", track_id, e))?;
+    let analysis = db.get_portable_analysis(track_id)
+        .map_err(|e| format!("Failed to get analysis: {}", e))?
+        .unwrap_or_default();
+    let cues = db.get_cue_points(track_id)
+        .map_err(|e| format!("Failed to get cue points: {}", e))?;
+    if analysis.bpm.is_none()
+        && analysis.musical_key.is_none()
+        && analysis.waveform_overview.is_none()
+        && cues.is_empty()
+    {
+        return Ok(None);
+    }
+    let sidecar = Sidecar::new(track.duration_ms.map(i64::from), &analysis, &cues);
+    Ok(Some((track.file_path, sidecar)))
+}
+
+/// Write a freshly analyzed track's sidecar if sidecars are enabled. Failures are only
+/// logged: the analysis itself is saved.
+pub(crate) fn write_sidecar_after_analysis(db: &Database, track_id: i64) {
+    if !SidecarSettings::from_settings(db).write {
+        return;
+    }
+    let written = build_sidecar(db, track_id).and_then(|built| match built {
+        Some((file_path, sidecar)) => sidecar::write_sidecar(Path::new(&file_path), &sidecar),
+        None => Ok(()),
+    });
+    if let Err(e) = written {
+        eprintln!("[sidecars] Track {}: {}", track_id, e);
+    }
+}
+
+fn track_ids_or_all(db: &Database, track_ids: Option<Vec<i64>>) -> Result<Vec<i64>, String> {
+    match track_ids {
+        Some(ids) => Ok(ids),
+        None => db.get_all_track_ids().map_err(|e| format!("Failed to get tracks: {}", e)),
+    }
+}
+
+#[tauri::command]
+pub fn get_sidecar_settings(state: State<AppState>) -> Result<SidecarSettings, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    Ok(SidecarSettings::from_settings(db))
+}
+
+/// `write`: write sidecars after analysis; `read`: pick them up when scanning files in
+#[tauri::command]
+pub fn set_sidecar_settings(state: State<AppState>, settings: SidecarSettings) -> Result<(), String> {
+    let json = serde_json::to_string(&settings)
+        .map_err(|e| format!("Failed to serialize sidecar settings: {}", e))?;
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.set_setting(SIDECAR_SETTING, &json)
+        .map_err(|e| format!("Failed to save sidecar settings: {}", e))
+}
+
+/// Write sidecars for `track_ids`, or every track. Existing sidecars are replaced.
+#[tauri::command]
+pub fn export_sidecars(state: State<AppState>, track_ids: Option<Vec<i64>>) -> Result<SidecarResult, String> {
+    let track_ids = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        track_ids_or_all(db, track_ids)?
+    };
+
+    let mut result = SidecarResult::default();
+    for track_id in track_ids {
+        let built = {
+            let db_lock = state.db.lock().unwrap();
+            let db = db_lock.as_ref().ok_or("Database not initialized")?;
+            build_sidecar(db, track_id)
+        };
+        let (file_path, sidecar) = match built {
+            Ok(Some(built)) => built,
+            Ok(None) => {
+                result.skipped += 1;
+                continue;
+            }
+            Err(e) => {
+                result.errors.push(e);
+                continue;
+            }
+        };
+        match sidecar::write_sidecar(Path::new(&file_path), &sidecar) {
+            Ok(()) => result.processed += 1,
+            Err(e) => result.errors.push(e),
+        }
+    }
+    eprintln!(
+        "[sidecars] Wrote {} sidecars ({} tracks without analysis, {} errors)",
+        result.processed, result.skipped, result.errors.len()
+    );
+    Ok(result)
+}
+
+/// Fill the analysis and cues of `track_ids`, or every track, from their sidecars. As on
+/// import: missing values are filled, BPM/key from tags are replaced only if analyzed values
+/// are preferred, and cues only go to tracks without cues.
+#[tauri::command]
+pub fn import_sidecars(state: State<AppState>, track_ids: Option<Vec<i64>>) -> Result<SidecarResult, String> {
+    let track_ids = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        track_ids_or_all(db, track_ids)?
+    };
+
+    let mut result = SidecarResult::default();
+    for track_id in track_ids {
+        let track = {
+            let db_lock = state.db.lock().unwrap();
+            let db = db_lock.as_ref().ok_or("Database not initialized")?;
+            db.get_track(track_id)
+                .map_err(|e| format!("Failed to get track {}: {}", track_id, e))?
+        };
+        let sidecar = match sidecar::read_sidecar(Path::new(&track.file_path)) {
+            Ok(Some(sidecar)) if sidecar.matches_duration(track.duration_ms.map(i64::from)) => sidecar,
+            Ok(_) => {
+                result.skipped += 1;
+                continue;
+            }
+            Err(e) => {
+                result.errors.push(e);
+                continue;
+            }
+        };
+
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        db.fill_portable_analysis(track_id, &sidecar.analysis())
+            .and_then(|_| db.fill_cue_points(track_id, &sidecar.cue_points(track_id)))
+            .map_err(|e| format!("Failed to save sidecar analysis: {}", e))?;
+        tx.commit().map_err(|e| format!("Failed to commit: {}", e))?;
+        result.processed += 1;
+    }
+    eprintln!(
+        "[sidecars] Read {} sidecars ({} tracks without one, {} errors)",
+        result.processed, result.skipped, result.errors.len()
+    );
+    Ok(result)
+}
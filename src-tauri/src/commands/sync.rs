@@ -0,0 +1,206 @@
+// Tauri commands for syncing library metadata with another RecoDeck instance on the LAN.
+// The other instance runs its companion server; this one pairs with it using a pairing
+// code (like the PWA), then pulls its snapshot, merges it, and pushes the merged result
+// back so both libraries end up the same (see crate::sync).
+
+use crate::commands::library::AppState;
+use crate::commands::playlists::notify_playlists_changed;
+use crate::server::API_VERSION;
+use crate::sync::{self, SyncSnapshot, SyncStats};
+use reqwest::{Client, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+
+/// Setting key: the paired peer as JSON ({ url, token })
+pub const SYNC_PEER_SETTING: &str = "sync_peer";
+/// Snapshots of large libraries take a while to build and transfer
+const SYNC_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SyncPeer {
+    url: String,
+    token: String,
+}
+
+/// Outcome of a sync: what changed here (pulled) and on the peer (pushed)
+#[derive(Debug, Clone, Serialize)]
+pub struct LibrarySyncResultDTO {
+    pub peer_url: String,
+    pub pulled: SyncStats,
+    pub pushed: SyncStats,
+}
+
+#[derive(Deserialize)]
+struct PairResponse {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct CapabilitiesResponse {
+    #[serde(default)]
+    features: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ErrorEnvelope {
+    message: String,
+}
+
+/// "192.168.1.20:8384" -> "http://192.168.1.20:8384"
+fn normalize_peer_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    if url.starts_with("http://") || url.starts_with("https://") {
+        url.to_string()
+    } else {
+        format!("http://{}", url)
+    }
+}
+
+fn api_url(peer_url: &str, path: &str) -> String {
+    format!("{}/api/v{}{}", peer_url, API_VERSION, path)
+}
+
+fn http_client() -> Result<Client, String> {
+    Client::builder()
+        .timeout(SYNC_TIMEOUT)
+        .user_agent(concat!("RecoDeck/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// Send a request to the peer; non-2xx responses become their envelope message
+async fn send(request: RequestBuilder, what: &str) -> Result<Response, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach peer ({}): {}", what, e))?;
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let message = response
+        .json::<ErrorEnvelope>()
+        .await
+        .map(|e| e.message)
+        .unwrap_or_else(|_| status.to_string());
+    Err(format!("Peer refused {}: {}", what, message))
+}
+
+fn load_peer(state: &AppState) -> Result<Option<SyncPeer>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    match db.get_setting(SYNC_PEER_SETTING)
+        .map_err(|e| format!("Failed to get sync peer: {}", e))?
+    {
+        Some(json_str) => serde_json::from_str(&json_str)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse sync peer JSON: {}", e)),
+        None => Ok(None),
+    }
+}
+
+/// Pair with another RecoDeck instance using the pairing code its companion server shows.
+/// Returns the peer URL as stored.
+#[tauri::command]
+pub async fn pair_sync_peer(state: State<'_, AppState>, url: String, code: String) -> Result<String, String> {
+    let url = normalize_peer_url(&url);
+    let client = http_client()?;
+
+    let capabilities: CapabilitiesResponse = send(client.get(api_url(&url, "/capabilities")), "capabilities")
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Unexpected capabilities response: {}", e))?;
+    if !capabilities.features.iter().any(|f| f == "library_sync") {
+        return Err("The peer's RecoDeck version doesn't support library sync".to_string());
+    }
+
+    let paired: PairResponse = send(
+        client.post(api_url(&url, "/pair")).json(&serde_json::json!({ "code": code })),
+        "pairing",
+    )
+    .await?
+    .json()
+    .await
+    .map_err(|e| format!("Unexpected pairing response: {}", e))?;
+
+    let peer = SyncPeer { url: url.clone(), token: paired.token };
+    let json_str = serde_json::to_string(&peer)
+        .map_err(|e| format!("Failed to serialize sync peer: {}", e))?;
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.set_setting(SYNC_PEER_SETTING, &json_str)
+        .map_err(|e| format!("Failed to save sync peer: {}", e))?;
+
+    Ok(url)
+}
+
+/// URL of the paired peer, if any
+#[tauri::command]
+pub fn get_sync_peer(state: State<AppState>) -> Result<Option<String>, String> {
+    Ok(load_peer(&state)?.map(|peer| peer.url))
+}
+
+/// Forget the paired peer
+#[tauri::command]
+pub fn forget_sync_peer(state: State<AppState>) -> Result<(), String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    db.delete_setting(SYNC_PEER_SETTING)
+        .map_err(|e| format!("Failed to forget sync peer: {}", e))
+}
+
+/// Two-way sync with the paired peer: pull and merge its snapshot, then push ours
+/// (which now includes its changes) so it merges what it's missing.
+#[tauri::command]
+pub async fn sync_with_peer(app: AppHandle, state: State<'_, AppState>) -> Result<LibrarySyncResultDTO, String> {
+    let peer = load_peer(&state)?.ok_or("No sync peer paired")?;
+    let client = http_client()?;
+    let snapshot_url = api_url(&peer.url, "/sync/snapshot");
+
+    // 1. Pull
+    let remote: SyncSnapshot = send(client.get(&snapshot_url).bearer_auth(&peer.token), "snapshot download")
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Unexpected snapshot from peer: {}", e))?;
+
+    // 2. Merge, then snapshot the merged library (one lock)
+    let (pulled, local) = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let pulled = sync::apply_snapshot(db, &remote)?;
+        (pulled, sync::build_snapshot(db)?)
+    };
+    if pulled != SyncStats::default() {
+        let _ = app.emit("library-changed", ());
+        notify_playlists_changed(&app);
+    }
+
+    // 3. Push
+    let pushed: SyncStats = send(
+        client.post(&snapshot_url).bearer_auth(&peer.token).json(&local),
+        "snapshot upload",
+    )
+    .await?
+    .json()
+    .await
+    .map_err(|e| format!("Unexpected sync response from peer: {}", e))?;
+
+    eprintln!(
+        "[sync] Synced with {}: pulled {} tracks/{} playlists, pushed {} tracks/{} playlists",
+        peer.url,
+        pulled.tracks_updated + pulled.cues_updated,
+        pulled.playlists_created + pulled.playlists_updated,
+        pushed.tracks_updated + pushed.cues_updated,
+        pushed.playlists_created + pushed.playlists_updated
+    );
+
+    Ok(LibrarySyncResultDTO {
+        peer_url: peer.url,
+        pulled,
+        pushed,
+    })
+}
@@ -0,0 +1,135 @@
+// Track cache: the whole library as TrackDTOs, kept between calls so switching views
+// (all tracks, a folder, the first page) doesn't re-query SQLite and rebuild every
+// struct. The cache is rebuilt on the next read after
+// - invalidate(): bumps the generation, called from the event bus ("library-changed",
+//   "track-analyzed") and when the database is (re)opened
+// - any write to the database: its change stamp (Database::change_stamp) covers writes
+//   through the app's connection and commits by the companion server's, so a command
+//   that forgets to emit an event can't leave a stale list behind
+// Only read-only commands use it; anything filtered or sorted by SQL still queries.
+
+use crate::commands::key_colors::KeyColors;
+use crate::commands::library::{dto_from_row, TrackDTO};
+use crate::db::Database;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+struct CachedTracks {
+    /// TrackCache generation and database change stamp the list was built at
+    generation: u64,
+    stamp: (i64, i64),
+    tracks: Arc<Vec<TrackDTO>>,
+}
+
+/// Every track with its analysis data, ordered by id (like get_all_tracks_with_analysis)
+#[derive(Default)]
+pub struct TrackCache {
+    generation: AtomicU64,
+    cached: Mutex<Option<CachedTracks>>,
+}
+
+impl TrackCache {
+    /// Drop the cached list; the next read rebuilds it
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// The cached list, rebuilt first if it's out of date. Call with the database lock
+    /// held, so no write lands between checking the stamp and reading the tracks.
+    pub fn tracks(&self, db: &Database) -> Result<Arc<Vec<TrackDTO>>, String> {
+        let generation = self.generation.load(Ordering::SeqCst);
+        let stamp = db.change_stamp()
+            .map_err(|e| format!("Failed to check for library changes: {}", e))?;
+
+        let mut cached = self.cached.lock().unwrap();
+        if let Some(cached) = cached.as_ref() {
+            if cached.generation == generation && cached.stamp == stamp {
+                return Ok(Arc::clone(&cached.tracks));
+            }
+        }
+
+        let rows = db.get_all_tracks_with_analysis()
+            .map_err(|e| format!("Failed to get tracks: {}", e))?;
+        let key_colors = KeyColors::from_settings(db);
+        let tracks: Arc<Vec<TrackDTO>> = Arc::new(rows.into_iter().map(|row| dto_from_row(row, &key_colors)).collect());
+        *cached = Some(CachedTracks { generation, stamp, tracks: Arc::clone(&tracks) });
+        Ok(tracks)
+    }
+}
+
+/// Whether `path` starts with `prefix`, ignoring ASCII case (like SQLite's LIKE, which
+/// the folder queries use)
+fn has_prefix(path: &str, prefix: &str) -> bool {
+    path.get(..prefix.len()).is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
+
+/// Tracks in `folder` and its subfolders (Database::get_tracks_in_folder_with_analysis)
+pub fn in_folder(tracks: &[TrackDTO], folder: &str) -> Vec<TrackDTO> {
+    let prefix = format!("{}/", folder.trim_end_matches('/'));
+    tracks.iter().filter(|t| has_prefix(&t.file_path, &prefix)).cloned().collect()
+}
+
+/// Tracks directly in `folder` (Database::get_tracks_in_folder_shallow_with_analysis)
+pub fn in_folder_shallow(tracks: &[TrackDTO], folder: &str) -> Vec<TrackDTO> {
+    let prefix = format!("{}/", folder.trim_end_matches('/'));
+    tracks
+        .iter()
+        .filter(|t| has_prefix(&t.file_path, &prefix) && !t.file_path[prefix.len()..].contains('/'))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{create_test_track, Track};
+
+    fn add_track(db: &Database, path: &str) -> i64 {
+        let track = Track { file_path: path.to_string(), file_hash: path.to_string(), ..create_test_track() };
+        db.create_track(&track).unwrap()
+    }
+
+    #[test]
+    fn test_cache_reused_until_something_changes() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let cache = TrackCache::default();
+        let first_id = add_track(&db, "/music/a.mp3");
+
+        let tracks = cache.tracks(&db).unwrap();
+        assert_eq!(tracks.len(), 1);
+        assert!(Arc::ptr_eq(&tracks, &cache.tracks(&db).unwrap()));
+
+        // A write (even one no event announced) rebuilds it
+        db.save_bpm_analysis(first_id, 128.0, 0.9).unwrap();
+        let analyzed = cache.tracks(&db).unwrap();
+        assert!(!Arc::ptr_eq(&tracks, &analyzed));
+        assert_eq!(analyzed[0].bpm, Some(128.0));
+        add_track(&db, "/music/b.mp3");
+        assert_eq!(cache.tracks(&db).unwrap().len(), 2);
+
+        let tracks = cache.tracks(&db).unwrap();
+        cache.invalidate();
+        assert!(!Arc::ptr_eq(&tracks, &cache.tracks(&db).unwrap()));
+    }
+
+    #[test]
+    fn test_folder_filters() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        for path in ["/music/a.mp3", "/music/house/b.mp3", "/Music/c.mp3", "/musical/d.mp3"] {
+            add_track(&db, path);
+        }
+        let tracks = TrackCache::default().tracks(&db).unwrap();
+        let paths = |tracks: Vec<TrackDTO>| tracks.into_iter().map(|t| t.file_path).collect::<Vec<_>>();
+
+        // Same tracks, in the same order, as the SQL folder queries
+        for folder in ["/music", "/music/", "/music/house", "/MUSIC", "/nowhere"] {
+            let expected = db.get_tracks_in_folder_with_analysis(folder).unwrap();
+            assert_eq!(paths(in_folder(&tracks, folder)), expected.into_iter().map(|r| r.0.file_path).collect::<Vec<_>>());
+            let expected = db.get_tracks_in_folder_shallow_with_analysis(folder).unwrap();
+            assert_eq!(paths(in_folder_shallow(&tracks, folder)), expected.into_iter().map(|r| r.0.file_path).collect::<Vec<_>>());
+        }
+        assert_eq!(paths(in_folder_shallow(&tracks, "/music")), ["/music/a.mp3", "/Music/c.mp3"]);
+    }
+}
@@ -0,0 +1,127 @@
+// Grouped track lists: by album, artist, genre, key, BPM decade or month added
+// get_tracks_grouped returns only the group headers, with counts and totals computed in
+// SQL; a group's tracks are fetched a page at a time with get_track_group_members when it
+// is expanded. A grouped view never has to load (and group) the whole library.
+
+use crate::commands::key_colors::KeyColors;
+use crate::commands::library::{AppState, TrackDTO};
+use crate::db::{TrackFilter, TrackGroup, TrackGrouping};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+/// Filters of a grouped view. Empty lists and missing bounds don't filter.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TrackFilterDTO {
+    pub genres: Vec<String>,
+    /// Camelot keys (e.g. "8A")
+    pub keys: Vec<String>,
+    pub min_bpm: Option<f64>,
+    pub max_bpm: Option<f64>,
+    /// Minimum star rating (0-5)
+    pub min_rating: Option<i32>,
+    /// Text in the title, artist, album or label
+    pub search: Option<String>,
+}
+
+impl From<TrackFilterDTO> for TrackFilter {
+    fn from(dto: TrackFilterDTO) -> Self {
+        TrackFilter {
+            genres: dto.genres,
+            keys: dto.keys,
+            min_bpm: dto.min_bpm,
+            max_bpm: dto.max_bpm,
+            min_rating: dto.min_rating,
+            search: dto.search,
+        }
+    }
+}
+
+/// A group header
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackGroupDTO {
+    /// Album/artist/genre/key, "120" for 120-129 BPM, "2024-05" for a month added.
+    /// None for the tracks without one; pass it back to get_track_group_members as is.
+    pub value: Option<String>,
+    pub track_count: i64,
+    pub total_duration_ms: i64,
+    pub min_bpm: Option<f64>,
+    pub avg_bpm: Option<f64>,
+    pub max_bpm: Option<f64>,
+    pub total_plays: i64,
+    /// Newest date added in the group
+    pub last_added: Option<String>,
+}
+
+impl From<TrackGroup> for TrackGroupDTO {
+    fn from(group: TrackGroup) -> Self {
+        TrackGroupDTO {
+            value: group.value,
+            track_count: group.track_count,
+            total_duration_ms: group.total_duration_ms,
+            min_bpm: group.min_bpm,
+            avg_bpm: group.avg_bpm.map(|bpm| (bpm * 10.0).round() / 10.0),
+            max_bpm: group.max_bpm,
+            total_plays: group.total_plays,
+            last_added: group.last_added,
+        }
+    }
+}
+
+fn parse_grouping(group_by: &str) -> Result<TrackGrouping, String> {
+    TrackGrouping::from_name(group_by).ok_or_else(|| {
+        let names: Vec<&str> = TrackGrouping::ALL.iter().map(|g| g.name()).collect();
+        format!("Unknown grouping: {} (use one of {})", group_by, names.join(", "))
+    })
+}
+
+/// Group headers of the tracks matching `filters`. `group_by`: "album", "artist", "genre",
+/// "key", "bpm_decade" or "month_added". The group of tracks without a value comes last.
+#[tauri::command]
+pub fn get_tracks_grouped(
+    state: State<AppState>,
+    group_by: String,
+    filters: Option<TrackFilterDTO>,
+) -> Result<Vec<TrackGroupDTO>, String> {
+    let grouping = parse_grouping(&group_by)?;
+    let filter = TrackFilter::from(filters.unwrap_or_default());
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let groups = db.get_track_groups(grouping, &filter)
+        .map_err(|e| format!("Failed to group tracks: {}", e))?;
+    Ok(groups.into_iter().map(TrackGroupDTO::from).collect())
+}
+
+/// A page of one group's tracks (same `group_by` and `filters` as get_tracks_grouped;
+/// `value` from its header)
+#[tauri::command]
+pub fn get_track_group_members(
+    state: State<AppState>,
+    group_by: String,
+    value: Option<String>,
+    filters: Option<TrackFilterDTO>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<TrackDTO>, String> {
+    let grouping = parse_grouping(&group_by)?;
+    let filter = TrackFilter::from(filters.unwrap_or_default());
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let rows = db.get_track_group_members(grouping, value.as_deref(), &filter, limit, offset)
+        .map_err(|e| format!("Failed to get group tracks: {}", e))?;
+
+    let key_colors = KeyColors::from_settings(db);
+    Ok(rows.into_iter().map(|(track, bpm, bpm_conf, key, key_conf)| {
+        let mut dto = TrackDTO::from(track);
+        dto.bpm = bpm;
+        dto.bpm_confidence = bpm_conf;
+        dto.musical_key = key;
+        dto.key_confidence = key_conf;
+        dto.key_color = key_colors.resolve(dto.musical_key.as_deref());
+        dto
+    }).collect())
+}
@@ -0,0 +1,438 @@
+// Printable tracklists — a playlist rendered as an HTML page or a PDF set sheet
+// (artist, title, key, BPM, duration and total time), e.g. to send to a radio station,
+// or as CSV with label, catalog number and ISRC for play reporting. Keys carry their
+// color (commands::key_colors): a swatch in HTML and PDF, a column in CSV.
+// The PDF is written directly: text only, in the built-in Helvetica fonts, so no
+// renderer or font files are needed.
+
+use crate::commands::key_colors::KeyColors;
+use crate::commands::library::AppState;
+use crate::db::Track;
+use serde::Deserialize;
+use tauri::State;
+
+/// Output format for render_tracklist
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TracklistFormat {
+    Html,
+    Pdf,
+    Csv,
+}
+
+/// One row of the tracklist
+struct TracklistRow {
+    artist: String,
+    title: String,
+    key: String,
+    /// "#rrggbb", or empty without a key
+    key_color: String,
+    bpm: String,
+    duration: String,
+    label: String,
+    catalog_number: String,
+    isrc: String,
+}
+
+struct Tracklist {
+    name: String,
+    rows: Vec<TracklistRow>,
+    total_ms: i64,
+}
+
+/// Render a playlist's tracklist. Returns the file contents: UTF-8 HTML/CSV or PDF bytes.
+#[tauri::command]
+pub fn render_tracklist(
+    state: State<AppState>,
+    playlist_id: i64,
+    format: TracklistFormat,
+) -> Result<Vec<u8>, String> {
+    let tracklist = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let playlist = db.get_playlist(playlist_id)
+            .map_err(|e| format!("Failed to get playlist: {}", e))?;
+        let tracks = db.get_playlist_tracks(playlist_id)
+            .map_err(|e| format!("Failed to get playlist tracks: {}", e))?;
+        build_tracklist(
+            playlist.name,
+            tracks.into_iter().map(|(track, bpm, _, key, _)| (track, bpm, key)),
+            &KeyColors::from_settings(db),
+        )
+    };
+
+    Ok(match format {
+        TracklistFormat::Html => render_html(&tracklist).into_bytes(),
+        TracklistFormat::Pdf => render_pdf(&tracklist),
+        TracklistFormat::Csv => render_csv(&tracklist).into_bytes(),
+    })
+}
+
+fn build_tracklist(
+    name: String,
+    tracks: impl IntoIterator<Item = (Track, Option<f64>, Option<String>)>,
+    key_colors: &KeyColors,
+) -> Tracklist {
+    let mut total_ms = 0i64;
+    let rows = tracks
+        .into_iter()
+        .map(|(track, bpm, key)| {
+            let duration_ms = track.duration_ms.map(|ms| ms as i64);
+            total_ms += duration_ms.unwrap_or(0);
+            TracklistRow {
+                artist: track.artist.unwrap_or_default(),
+                title: track.title.unwrap_or_else(|| file_stem(&track.file_path)),
+                key_color: key_colors.resolve(key.as_deref()).unwrap_or_default(),
+                key: key.unwrap_or_default(),
+                bpm: bpm.map(|b| format!("{:.0}", b)).unwrap_or_default(),
+                duration: duration_ms.map(format_duration).unwrap_or_default(),
+                label: track.label.unwrap_or_default(),
+                catalog_number: track.catalog_number.unwrap_or_default(),
+                isrc: track.isrc.unwrap_or_default(),
+            }
+        })
+        .collect();
+    Tracklist { name, rows, total_ms }
+}
+
+fn file_stem(path: &str) -> String {
+    std::path::Path::new(path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// "m:ss", or "h:mm:ss" from an hour up
+fn format_duration(ms: i64) -> String {
+    let secs = ms / 1000;
+    let (h, m, s) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+fn summary_line(tracklist: &Tracklist) -> String {
+    format!(
+        "{} tracks - total time {}",
+        tracklist.rows.len(),
+        format_duration(tracklist.total_ms)
+    )
+}
+
+// --- HTML ---
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Colored square in front of a key; key_color is always "#rrggbb" or empty
+fn html_swatch(key_color: &str) -> String {
+    if key_color.is_empty() {
+        String::new()
+    } else {
+        format!("<span class=\"swatch\" style=\"background: {}\"></span>", key_color)
+    }
+}
+
+fn render_html(tracklist: &Tracklist) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape_html(&tracklist.name)));
+    html.push_str(
+        "<style>\n\
+         body { font-family: Helvetica, Arial, sans-serif; margin: 2em; color: #111; }\n\
+         h1 { font-size: 1.4em; margin-bottom: 0.2em; }\n\
+         p.summary { color: #555; margin-top: 0; }\n\
+         table { border-collapse: collapse; width: 100%; font-size: 0.9em; }\n\
+         th, td { text-align: left; padding: 0.3em 0.6em; border-bottom: 1px solid #ddd; }\n\
+         th { border-bottom: 2px solid #111; }\n\
+         td.num { text-align: right; font-variant-numeric: tabular-nums; }\n\
+         span.swatch { display: inline-block; width: 0.7em; height: 0.7em; margin-right: 0.4em; border-radius: 2px; }\n\
+         </style>\n",
+    );
+    html.push_str("</head>\n<body>\n");
+    html.push_str(&format!("<h1>{}</h1>\n", escape_html(&tracklist.name)));
+    html.push_str(&format!("<p class=\"summary\">{}</p>\n", summary_line(tracklist)));
+    html.push_str("<table>\n<thead><tr><th>#</th><th>Artist</th><th>Title</th><th>Key</th><th>BPM</th><th>Time</th></tr></thead>\n<tbody>\n");
+    for (i, row) in tracklist.rows.iter().enumerate() {
+        html.push_str(&format!(
+            "<tr><td class=\"num\">{}</td><td>{}</td><td>{}</td><td>{}{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>\n",
+            i + 1,
+            escape_html(&row.artist),
+            escape_html(&row.title),
+            html_swatch(&row.key_color),
+            escape_html(&row.key),
+            row.bpm,
+            row.duration
+        ));
+    }
+    html.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+    html
+}
+
+// --- CSV ---
+
+/// RFC 4180 field: quoted when it contains a comma, quote or line break
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn render_csv(tracklist: &Tracklist) -> String {
+    let mut csv = String::from("#,Artist,Title,Label,Catalog Number,ISRC,Key,Key Color,BPM,Time\r\n");
+    for (i, row) in tracklist.rows.iter().enumerate() {
+        let fields = [
+            (i + 1).to_string(),
+            csv_field(&row.artist),
+            csv_field(&row.title),
+            csv_field(&row.label),
+            csv_field(&row.catalog_number),
+            csv_field(&row.isrc),
+            csv_field(&row.key),
+            row.key_color.clone(),
+            row.bpm.clone(),
+            row.duration.clone(),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+// --- PDF ---
+
+/// A4 in points
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 50.0;
+const FONT_SIZE: f64 = 9.0;
+const LINE_HEIGHT: f64 = 14.0;
+/// Column x positions and max characters (Helvetica averages ~0.5em per character)
+const COLUMNS: [(f64, usize); 6] = [(50.0, 4), (75.0, 30), (230.0, 40), (450.0, 4), (478.0, 4), (510.0, 8)];
+/// Key color swatch: x position and edge length
+const SWATCH_X: f64 = 440.0;
+const SWATCH_SIZE: f64 = 7.0;
+
+/// PDF string literal in WinAnsi encoding: characters outside Latin-1 become '?'
+fn pdf_text(text: &str, max_chars: usize) -> String {
+    let mut out = String::from("(");
+    let count = text.chars().count();
+    for (i, c) in text.chars().enumerate() {
+        if count > max_chars && i + 1 >= max_chars {
+            out.push_str("...");
+            break;
+        }
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\u{20}'..='\u{7e}' => out.push(c),
+            '\u{a0}'..='\u{ff}' => out.push_str(&format!("\\{:03o}", c as u32)),
+            _ => out.push('?'),
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// Content stream operators drawing one line of cells at height `y`
+fn pdf_row(cells: [&str; 6], y: f64, bold: bool) -> String {
+    let font = if bold { "F2" } else { "F1" };
+    let mut ops = String::new();
+    for ((x, max_chars), text) in COLUMNS.iter().zip(cells) {
+        if text.is_empty() {
+            continue;
+        }
+        ops.push_str(&format!(
+            "BT /{} {} Tf {:.1} {:.1} Td {} Tj ET\n",
+            font, FONT_SIZE, x, y, pdf_text(text, *max_chars)
+        ));
+    }
+    ops
+}
+
+/// Operators filling a key color swatch at height `y`, in the key column. Empty for a
+/// missing or malformed color.
+fn pdf_swatch(key_color: &str, y: f64) -> String {
+    let channel = |i: usize| key_color.get(i..i + 2).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+    let (Some(r), Some(g), Some(b)) = (channel(1), channel(3), channel(5)) else {
+        return String::new();
+    };
+    format!(
+        "{:.3} {:.3} {:.3} rg {:.1} {:.1} {} {} re f 0 g\n",
+        r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0,
+        SWATCH_X, y - 0.5, SWATCH_SIZE, SWATCH_SIZE
+    )
+}
+
+/// Lay the tracklist out on A4 pages, returning each page's content stream
+fn pdf_pages(tracklist: &Tracklist) -> Vec<String> {
+    let header = ["#", "Artist", "Title", "Key", "BPM", "Time"];
+    let mut pages = Vec::new();
+    let mut page = format!(
+        "BT /F2 16 Tf {:.1} {:.1} Td {} Tj ET\nBT /F1 10 Tf {:.1} {:.1} Td {} Tj ET\n",
+        MARGIN,
+        PAGE_HEIGHT - MARGIN,
+        pdf_text(&tracklist.name, 80),
+        MARGIN,
+        PAGE_HEIGHT - MARGIN - 18.0,
+        pdf_text(&summary_line(tracklist), 80)
+    );
+    let mut y = PAGE_HEIGHT - MARGIN - 48.0;
+    page.push_str(&pdf_row(header, y, true));
+    y -= LINE_HEIGHT;
+
+    for (i, row) in tracklist.rows.iter().enumerate() {
+        if y < MARGIN {
+            pages.push(std::mem::take(&mut page));
+            y = PAGE_HEIGHT - MARGIN;
+            page.push_str(&pdf_row(header, y, true));
+            y -= LINE_HEIGHT;
+        }
+        let number = (i + 1).to_string();
+        page.push_str(&pdf_swatch(&row.key_color, y));
+        page.push_str(&pdf_row(
+            [&number, &row.artist, &row.title, &row.key, &row.bpm, &row.duration],
+            y,
+            false,
+        ));
+        y -= LINE_HEIGHT;
+    }
+    pages.push(page);
+    pages
+}
+
+/// Write a PDF: catalog, page tree, two base-14 fonts, then a page + content stream per page
+fn render_pdf(tracklist: &Tracklist) -> Vec<u8> {
+    let pages = pdf_pages(tracklist);
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 5 + i * 2).collect();
+
+    let mut objects: Vec<String> = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids.iter().map(|id| format!("{} 0 R", id)).collect::<Vec<_>>().join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_string(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>".to_string(),
+    ];
+    for (content, page_id) in pages.iter().zip(&page_ids) {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH, PAGE_HEIGHT, page_id + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+    let xref_offset = pdf.len();
+    pdf.push_str(&format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref_offset
+    ));
+    pdf.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracklist(rows: usize) -> Tracklist {
+        Tracklist {
+            name: "Friday <Warmup>".to_string(),
+            rows: (0..rows)
+                .map(|i| TracklistRow {
+                    artist: "Åsa & Co".to_string(),
+                    title: format!("Track (Mix) {}", i),
+                    key: "8A".to_string(),
+                    key_color: "#e236b7".to_string(),
+                    bpm: "124".to_string(),
+                    duration: "6:05".to_string(),
+                    label: "Drumcode".to_string(),
+                    catalog_number: "DC123".to_string(),
+                    isrc: "GBAYE0601498".to_string(),
+                })
+                .collect(),
+            total_ms: 3_725_000,
+        }
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(365_000), "6:05");
+        assert_eq!(format_duration(3_725_000), "1:02:05");
+    }
+
+    #[test]
+    fn test_render_html_escapes() {
+        let html = render_html(&tracklist(2));
+        assert!(html.contains("<h1>Friday &lt;Warmup&gt;</h1>"));
+        assert!(html.contains("Åsa &amp; Co"));
+        assert!(html.contains("2 tracks - total time 1:02:05"));
+        assert!(html.contains("<td><span class=\"swatch\" style=\"background: #e236b7\"></span>8A</td>"));
+    }
+
+    #[test]
+    fn test_render_csv() {
+        let csv = render_csv(&tracklist(1));
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+        assert_eq!(lines[0], "#,Artist,Title,Label,Catalog Number,ISRC,Key,Key Color,BPM,Time");
+        assert_eq!(lines[1], "1,Åsa & Co,Track (Mix) 0,Drumcode,DC123,GBAYE0601498,8A,#e236b7,124,6:05");
+        assert_eq!(csv_field("Hello, \"World\""), "\"Hello, \"\"World\"\"\"");
+    }
+
+    #[test]
+    fn test_pdf_text() {
+        assert_eq!(pdf_text("A (B) \\", 20), "(A \\(B\\) \\\\)");
+        assert_eq!(pdf_text("Å→", 20), "(\\305?)");
+        assert_eq!(pdf_text("abcdefgh", 5), "(abcd...)");
+    }
+
+    #[test]
+    fn test_pdf_swatch() {
+        assert_eq!(pdf_swatch("#ff8000", 100.0), "1.000 0.502 0.000 rg 440.0 99.5 7 7 re f 0 g\n");
+        assert_eq!(pdf_swatch("", 100.0), "");
+        assert_eq!(pdf_swatch("#ff80", 100.0), "");
+    }
+
+    #[test]
+    fn test_render_pdf_structure() {
+        // Enough rows for more than one page
+        let pdf = String::from_utf8(render_pdf(&tracklist(100))).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("/Count 2"));
+
+        // xref offsets point at their objects
+        let xref = pdf.rfind("\nxref\n").unwrap() + 1;
+        let startxref: usize = pdf.lines().rev().nth(1).unwrap().parse().unwrap();
+        assert_eq!(startxref, xref);
+        let first_offset: usize = pdf[xref..].lines().nth(3).unwrap()[..10].parse().unwrap();
+        assert!(pdf[first_offset..].starts_with("1 0 obj"));
+    }
+}
@@ -0,0 +1,304 @@
+// File integrity verification — re-hash tracks' files and compare with the hash stored
+// at scan time, to catch bit rot or accidental edits in archival libraries.
+// Hashing runs without the DB lock; results are committed in small transactions.
+
+use crate::commands::library::AppState;
+use crate::db::{Database, PENDING_HASH};
+use crate::scanner::Scanner;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::State;
+
+/// Verification results written per transaction
+const VERIFY_COMMIT_EVERY: usize = 50;
+/// Recovery marker job name (see db::BatchMarker)
+const VERIFY_JOB: &str = "verify_all";
+
+/// Outcome of re-hashing one file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyStatus {
+    /// Content matches the stored hash
+    Ok,
+    /// Content changed since the stored hash was taken
+    Mismatch,
+    Missing,
+    Unreadable,
+    /// The track had no hash yet (lazy hash mode); the computed one becomes its baseline
+    Baselined,
+}
+
+impl VerifyStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerifyStatus::Ok => "ok",
+            VerifyStatus::Mismatch => "mismatch",
+            VerifyStatus::Missing => "missing",
+            VerifyStatus::Unreadable => "unreadable",
+            VerifyStatus::Baselined => "baselined",
+        }
+    }
+}
+
+/// Re-hash `path` and compare with `expected`. Returns the status and computed hash.
+/// Only does file I/O, so call it without holding the DB lock.
+fn verify_file(path: &Path, expected: &str) -> (VerifyStatus, Option<String>) {
+    if !path.exists() {
+        return (VerifyStatus::Missing, None);
+    }
+    let actual = match Scanner::calculate_file_hash(path) {
+        Ok(hash) => hash,
+        Err(e) => {
+            eprintln!("[verify] Failed to read {}: {}", path.display(), e);
+            return (VerifyStatus::Unreadable, None);
+        }
+    };
+    let status = if expected == PENDING_HASH || expected == "unknown" {
+        VerifyStatus::Baselined
+    } else if actual == expected {
+        VerifyStatus::Ok
+    } else {
+        VerifyStatus::Mismatch
+    };
+    (status, Some(actual))
+}
+
+/// Store a verification result (call inside the caller's transaction, if any)
+fn save_result(db: &Database, track_id: i64, status: VerifyStatus, actual: Option<&str>) -> rusqlite::Result<()> {
+    if let (VerifyStatus::Baselined, Some(hash)) = (status, actual) {
+        db.set_track_hash(track_id, hash)?;
+    }
+    db.save_verification(track_id, status.as_str(), actual)
+}
+
+/// Result of verifying one track
+#[derive(Debug, Serialize)]
+pub struct VerificationDTO {
+    pub track_id: i64,
+    /// "ok", "mismatch", "missing", "unreadable" or "baselined"
+    pub status: String,
+    pub expected_hash: String,
+    pub actual_hash: Option<String>,
+}
+
+/// Counts from a verify_all run
+#[derive(Debug, Default, Serialize)]
+pub struct VerifySummaryDTO {
+    pub checked: usize,
+    pub ok: usize,
+    pub mismatch: usize,
+    pub missing: usize,
+    pub unreadable: usize,
+    pub baselined: usize,
+}
+
+impl VerifySummaryDTO {
+    fn count(&mut self, status: VerifyStatus) {
+        self.checked += 1;
+        match status {
+            VerifyStatus::Ok => self.ok += 1,
+            VerifyStatus::Mismatch => self.mismatch += 1,
+            VerifyStatus::Missing => self.missing += 1,
+            VerifyStatus::Unreadable => self.unreadable += 1,
+            VerifyStatus::Baselined => self.baselined += 1,
+        }
+    }
+}
+
+/// A track whose last verification found a problem
+#[derive(Debug, Serialize)]
+pub struct VerificationProblemDTO {
+    pub track_id: i64,
+    pub file_path: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub status: String,
+    pub expected_hash: String,
+    pub actual_hash: Option<String>,
+    pub verified_at: String,
+}
+
+/// Library-wide verification state
+#[derive(Debug, Serialize)]
+pub struct VerificationReportDTO {
+    pub total_tracks: i64,
+    pub verified: usize,
+    /// Oldest verification timestamp (the audit is at least this fresh), if any
+    pub oldest_verified_at: Option<String>,
+    /// Count per status of the latest verification
+    pub by_status: HashMap<String, usize>,
+    /// Tracks whose latest verification was mismatch, missing or unreadable
+    pub problems: Vec<VerificationProblemDTO>,
+}
+
+/// Re-hash one track's file and compare it with its stored hash
+#[tauri::command]
+pub fn verify_track(state: State<AppState>, track_id: i64) -> Result<VerificationDTO, String> {
+    let track = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        db.get_track(track_id)
+            .map_err(|e| format!("Failed to get track: {}", e))?
+    };
+
+    let (status, actual) = verify_file(Path::new(&track.file_path), &track.file_hash);
+
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    db.with_busy_retry(|db| {
+        let tx = db.transaction()?;
+        save_result(db, track_id, status, actual.as_deref())?;
+        tx.commit()
+    })
+    .map_err(|e| format!("Failed to save verification: {}", e))?;
+
+    Ok(VerificationDTO {
+        track_id,
+        status: status.as_str().to_string(),
+        expected_hash: track.file_hash,
+        actual_hash: actual,
+    })
+}
+
+/// Re-hash every track's file. With `max_age_days`, tracks verified more recently
+/// than that are skipped, so an interrupted or periodic audit picks up where it left off.
+#[tauri::command]
+pub fn verify_all(state: State<AppState>, max_age_days: Option<i64>) -> Result<VerifySummaryDTO, String> {
+    let tracks = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let tracks = db.get_tracks_to_verify(max_age_days)
+            .map_err(|e| format!("Failed to get tracks: {}", e))?;
+        db.start_batch_marker(VERIFY_JOB, None, Some(tracks.len() as i64))
+            .map_err(|e| format!("Failed to write batch marker: {}", e))?;
+        tracks
+    };
+
+    eprintln!("[verify] Verifying {} tracks", tracks.len());
+
+    let mut summary = VerifySummaryDTO::default();
+    let mut pending: Vec<(i64, VerifyStatus, Option<String>)> = Vec::new();
+    for (i, (track_id, file_path, file_hash)) in tracks.iter().enumerate() {
+        // Hashing — no lock held
+        let (status, actual) = verify_file(Path::new(file_path), file_hash);
+        if status == VerifyStatus::Mismatch {
+            eprintln!("[verify] Track {} changed on disk: {}", track_id, file_path);
+        }
+        summary.count(status);
+        pending.push((*track_id, status, actual));
+
+        if pending.len() >= VERIFY_COMMIT_EVERY || i + 1 == tracks.len() {
+            let db_lock = state.db.lock().unwrap();
+            let db = db_lock.as_ref().ok_or("Database not initialized")?;
+            db.with_busy_retry(|db| {
+                let tx = db.transaction()?;
+                for (track_id, status, actual) in &pending {
+                    save_result(db, *track_id, *status, actual.as_deref())?;
+                }
+                db.update_batch_marker(VERIFY_JOB, (i + 1) as i64)?;
+                tx.commit()
+            })
+            .map_err(|e| format!("Failed to save verifications: {}", e))?;
+            pending.clear();
+        }
+    }
+
+    {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        db.finish_batch_marker(VERIFY_JOB)
+            .map_err(|e| format!("Failed to clear batch marker: {}", e))?;
+    }
+
+    eprintln!(
+        "[verify] Done: {} checked, {} mismatched, {} missing, {} unreadable",
+        summary.checked, summary.mismatch, summary.missing, summary.unreadable
+    );
+    Ok(summary)
+}
+
+/// Verification state of the library: how much has been checked and which files failed
+#[tauri::command]
+pub fn get_verification_report(state: State<AppState>) -> Result<VerificationReportDTO, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let total_tracks = db.count_tracks()
+        .map_err(|e| format!("Failed to count tracks: {}", e))?;
+    let verifications = db.get_verifications()
+        .map_err(|e| format!("Failed to get verifications: {}", e))?;
+
+    let mut by_status: HashMap<String, usize> = HashMap::new();
+    let mut problems = Vec::new();
+    for v in &verifications {
+        *by_status.entry(v.status.clone()).or_default() += 1;
+        if matches!(v.status.as_str(), "mismatch" | "missing" | "unreadable") {
+            if let Ok(track) = db.get_track(v.track_id) {
+                problems.push(VerificationProblemDTO {
+                    track_id: v.track_id,
+                    file_path: track.file_path,
+                    title: track.title,
+                    artist: track.artist,
+                    status: v.status.clone(),
+                    expected_hash: track.file_hash,
+                    actual_hash: v.actual_hash.clone(),
+                    verified_at: v.verified_at.clone(),
+                });
+            }
+        }
+    }
+
+    Ok(VerificationReportDTO {
+        total_tracks,
+        verified: verifications.len(),
+        oldest_verified_at: verifications.iter().map(|v| v.verified_at.clone()).min(),
+        by_status,
+        problems,
+    })
+}
+
+/// Accept a track's current file content as correct (e.g. after an intentional tag
+/// edit): its last verified hash becomes the stored hash and the mismatch is cleared.
+#[tauri::command]
+pub fn accept_verified_hash(state: State<AppState>, track_id: i64) -> Result<(), String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let verification = db.get_verification(track_id)
+        .map_err(|e| format!("Failed to get verification: {}", e))?
+        .ok_or_else(|| format!("Track {} has not been verified", track_id))?;
+    if verification.status != "mismatch" {
+        return Err(format!("Track {} has no hash mismatch to accept", track_id));
+    }
+    let hash = verification.actual_hash.ok_or("Verification has no hash")?;
+
+    let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+    db.set_track_hash(track_id, &hash)
+        .map_err(|e| format!("Failed to update hash: {}", e))?;
+    db.save_verification(track_id, VerifyStatus::Ok.as_str(), Some(&hash))
+        .map_err(|e| format!("Failed to save verification: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_verify_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"audio").unwrap();
+        let hash = Scanner::calculate_file_hash(file.path()).unwrap();
+
+        assert_eq!(verify_file(file.path(), &hash), (VerifyStatus::Ok, Some(hash.clone())));
+        assert_eq!(verify_file(file.path(), PENDING_HASH).0, VerifyStatus::Baselined);
+
+        file.write_all(b" edited").unwrap();
+        let (status, actual) = verify_file(file.path(), &hash);
+        assert_eq!(status, VerifyStatus::Mismatch);
+        assert_ne!(actual, Some(hash.clone()));
+
+        assert_eq!(verify_file(Path::new("/nonexistent/track.flac"), &hash), (VerifyStatus::Missing, None));
+    }
+}
@@ -1,21 +1,102 @@
 // File system watcher — watches library folders for new/removed audio files
 // and emits Tauri events so the frontend auto-refreshes.
+// Also watches the folders of watch rules (e.g. ~/Downloads/Promos): new audio files there
+// are imported, tagged and added to a playlist once they have finished being written.
+// Library folders on network shares (SMB/NFS) get no OS change notifications, so they can
+// be switched to polling: their directory listings are re-scanned every few seconds and
+// diffed instead.
+// Deleted files: after a grace period (a file "deleted" by a move or an editor's save comes
+// back), their tracks are marked missing or removed, per DeletedFileSettings, and the change
+// goes into the operation journal so it can be undone (commands::journal).
 
-use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::Path;
-use std::sync::Mutex;
+use crate::commands::journal;
+use crate::commands::library::AppState;
+use crate::commands::playlists::notify_playlists_changed;
+use crate::db::{Database, WatchRule};
+use crate::scanner::{collapse_roots, normalize_root, Scanner};
+use notify::event::ModifyKind;
+use notify::{Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::{Duration, Instant};
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// How often a new file's size is checked while waiting for it to finish writing
+const SETTLE_INTERVAL: Duration = Duration::from_secs(1);
+/// Give up on files still growing after this long (stalled download)
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Setting key: library folders watched by polling, with their interval (JSON folder -> seconds)
+pub const POLL_FOLDERS_SETTING: &str = "watcher_poll_folders";
+/// Allowed polling intervals (a full listing of a big share every few seconds is too much)
+const MIN_POLL_INTERVAL_SECS: u64 = 5;
+const MAX_POLL_INTERVAL_SECS: u64 = 3600;
+
+/// Setting key: what happens to tracks whose file was deleted (DeletedFileSettings JSON)
+pub const DELETED_FILES_SETTING: &str = "watcher_deleted_files";
+const MAX_GRACE_PERIOD_SECS: u64 = 24 * 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletedFileAction {
+    /// Leave the track as it is
+    Ignore,
+    /// Flag it missing (cleared if the file comes back)
+    #[default]
+    MarkMissing,
+    /// Remove it with its analysis, cues and playlist entries. A file moved outside the
+    /// library folders looks deleted too; undo brings the track back.
+    Remove,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeletedFileSettings {
+    pub action: DeletedFileAction,
+    /// How long a file must stay gone before its track is touched
+    pub grace_period_secs: u64,
+}
+
+impl Default for DeletedFileSettings {
+    fn default() -> Self {
+        DeletedFileSettings {
+            action: DeletedFileAction::MarkMissing,
+            grace_period_secs: 60,
+        }
+    }
+}
+
+impl DeletedFileSettings {
+    pub fn from_settings(db: &Database) -> Self {
+        db.get_setting(DELETED_FILES_SETTING)
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Paths to re-check, with when: deleted ones after the grace period, re-created ones at once
+type PendingPaths = Arc<Mutex<HashMap<PathBuf, Instant>>>;
 
 /// Managed state holding the active file watcher (so it doesn't get dropped).
 pub struct WatcherState {
     pub watcher: Mutex<Option<RecommendedWatcher>>,
+    /// One polling watcher per library folder switched to polling
+    pub poll_watchers: Mutex<Vec<PollWatcher>>,
+    /// Library folders from the last start_file_watcher call; kept so rule changes can restart the watcher
+    pub folders: Mutex<Vec<String>>,
 }
 
 impl WatcherState {
     pub fn new() -> Self {
         Self {
             watcher: Mutex::new(None),
+            poll_watchers: Mutex::new(Vec::new()),
+            folders: Mutex::new(Vec::new()),
         }
     }
 }
@@ -33,74 +114,566 @@ fn is_audio_file(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
-/// Start watching the given library folders for file changes.
-/// When audio files are created, modified, or removed, emits a "library-changed" event
-/// so the frontend can re-scan and reload.
-#[tauri::command]
-pub fn start_file_watcher(
-    app: AppHandle,
-    watcher_state: State<WatcherState>,
-    folders: Vec<String>,
-) -> Result<(), String> {
+/// Wait until the file at `path` stops growing (downloads and copies fire the create
+/// event before the data is written). False if it disappeared or never settled.
+fn wait_until_settled(path: &Path) -> bool {
+    let started = Instant::now();
+    let mut last_size = None;
+    while started.elapsed() < SETTLE_TIMEOUT {
+        thread::sleep(SETTLE_INTERVAL);
+        let size = match std::fs::metadata(path) {
+            Ok(meta) => meta.len(),
+            Err(_) => return false,
+        };
+        if size > 0 && last_size == Some(size) {
+            return true;
+        }
+        last_size = Some(size);
+    }
+    eprintln!("[watcher] Gave up waiting for {} to finish writing", path.display());
+    false
+}
+
+/// Import a new file and apply every matching rule: tag it and add it to the rule's
+/// playlist (created if missing). Returns the track ID, or None if the file was already
+/// in the library or is a duplicate of an existing track.
+fn apply_watch_rules(db: &Database, path: &Path, rules: &[WatchRule]) -> Result<Option<i64>, String> {
+    let matching: Vec<&WatchRule> = rules.iter().filter(|r| r.enabled && r.matches(path)).collect();
+    if matching.is_empty() {
+        return Ok(None);
+    }
+    if db.track_exists_with_path(&path.to_string_lossy())
+        .map_err(|e| format!("Database error: {}", e))?
+    {
+        return Ok(None);
+    }
+
+    let tx = db.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+    let track_id = match Scanner::import_file(db, path) {
+        Ok(id) => id,
+        Err(e) if e == "DUPLICATE_HASH" => {
+            eprintln!("[watcher] Skipping {}: already in the library", path.display());
+            return Ok(None);
+        }
+        Err(e) => return Err(e),
+    };
+    for rule in matching {
+        if let Some(tag) = &rule.tag {
+            db.add_track_tag(track_id, tag)
+                .map_err(|e| format!("Failed to tag track: {}", e))?;
+        }
+        if let Some(name) = &rule.playlist_name {
+            db.get_or_create_playlist(name)
+                .and_then(|playlist_id| db.add_track_to_playlist(playlist_id, track_id))
+                .map_err(|e| format!("Failed to add track to playlist: {}", e))?;
+        }
+    }
+    tx.commit().map_err(|e| format!("Failed to commit: {}", e))?;
+    Ok(Some(track_id))
+}
+
+/// Import `path` in the background once it has finished writing
+fn spawn_rule_import(app: AppHandle, path: PathBuf, rules: Arc<Vec<WatchRule>>, in_flight: Arc<Mutex<HashSet<PathBuf>>>) {
+    thread::spawn(move || {
+        if wait_until_settled(&path) {
+            let state = app.state::<AppState>();
+            let result = match state.db.lock().unwrap().as_ref() {
+                Some(db) => apply_watch_rules(db, &path, &rules),
+                None => Err("Database not initialized".to_string()),
+            };
+            match result {
+                Ok(Some(track_id)) => {
+                    eprintln!("[watcher] Auto-imported {} as track {}", path.display(), track_id);
+                    let _ = app.emit("library-changed", ());
+                    notify_playlists_changed(&app);
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("[watcher] Failed to auto-import {}: {}", path.display(), e),
+            }
+        }
+        in_flight.lock().unwrap().remove(&path);
+    });
+}
+
+/// Bring the tracks at or under each path (a file or a deleted folder) in line with the disk:
+/// tracks whose file is gone are marked missing or removed, as `action` says; tracks whose
+/// file is there again lose their missing flag. Returns whether anything changed and
+/// whether tracks were removed.
+fn apply_deleted_files(db: &Database, paths: &[PathBuf], action: DeletedFileAction) -> Result<(bool, bool), String> {
+    let mut gone = Vec::new();
+    let mut back = Vec::new();
+    for path in paths {
+        let tracks = db.get_track_paths_under(&path.to_string_lossy())
+            .map_err(|e| format!("Failed to get tracks: {}", e))?;
+        for (track_id, file_path) in tracks {
+            if Path::new(&file_path).exists() {
+                back.push(track_id);
+            } else {
+                gone.push((track_id, file_path));
+            }
+        }
+    }
+    let cleared = db.clear_tracks_missing(&back)
+        .map_err(|e| format!("Failed to clear missing flags: {}", e))?;
+    let changed = match action {
+        DeletedFileAction::Ignore => 0,
+        DeletedFileAction::MarkMissing => journal::mark_tracks_missing(db, &gone)?,
+        DeletedFileAction::Remove => journal::remove_tracks(db, &gone)?,
+    };
+    Ok((cleared + changed > 0, action == DeletedFileAction::Remove && changed > 0))
+}
+
+/// Re-check `path` at `due`. A worker thread runs while paths are pending.
+fn queue_path_check(app: &AppHandle, pending: &PendingPaths, path: PathBuf, due: Instant, action: DeletedFileAction) {
+    let mut lock = pending.lock().unwrap();
+    let start_worker = lock.is_empty();
+    lock.insert(path, due);
+    drop(lock);
+    if start_worker {
+        spawn_path_check_worker(app.clone(), pending.clone(), action);
+    }
+}
+
+/// Sleep until the next pending path is due and check it; exit once none are left
+fn spawn_path_check_worker(app: AppHandle, pending: PendingPaths, action: DeletedFileAction) {
+    thread::spawn(move || loop {
+        let due: Vec<PathBuf> = {
+            let mut lock = pending.lock().unwrap();
+            let now = Instant::now();
+            let Some(next) = lock.values().min().copied() else {
+                return;
+            };
+            if next > now {
+                drop(lock);
+                thread::sleep(next - now);
+                continue;
+            }
+            let due: Vec<PathBuf> = lock.iter().filter(|(_, at)| **at <= now).map(|(p, _)| p.clone()).collect();
+            lock.retain(|_, at| *at > now);
+            due
+        };
+
+        let state = app.state::<AppState>();
+        let result = match state.db.lock().unwrap().as_ref() {
+            Some(db) => apply_deleted_files(db, &due, action),
+            None => Err("Database not initialized".to_string()),
+        };
+        match result {
+            Ok((changed, removed)) => {
+                if changed {
+                    let _ = app.emit("library-changed", ());
+                }
+                if removed {
+                    notify_playlists_changed(&app);
+                }
+            }
+            Err(e) => eprintln!("[watcher] Failed to update deleted files: {}", e),
+        }
+    });
+}
+
+/// Library folders to poll, with their interval
+fn load_poll_folders(db: &Database) -> Result<HashMap<String, u64>, String> {
+    let value = db.get_setting(POLL_FOLDERS_SETTING)
+        .map_err(|e| format!("Failed to get polled folders: {}", e))?;
+    Ok(value.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default())
+}
+
+/// (Re)create the watcher for the stored library folders plus the folders of enabled watch rules
+fn restart_watcher(app: &AppHandle, watcher_state: &WatcherState, state: &AppState) -> Result<(), String> {
+    let (rules, poll_folders, deleted_files) = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let rules: Vec<WatchRule> = db.get_watch_rules()
+            .map_err(|e| format!("Failed to get watch rules: {}", e))?
+            .into_iter()
+            .filter(|r| r.enabled)
+            .collect();
+        (rules, load_poll_folders(db)?, DeletedFileSettings::from_settings(db))
+    };
+    // Nested library folders are watched once, through their parent
+    let folders = collapse_roots(&watcher_state.folders.lock().unwrap());
     let mut watcher_lock = watcher_state.watcher.lock().unwrap();
+    let mut poll_lock = watcher_state.poll_watchers.lock().unwrap();
 
-    // Drop any existing watcher first
+    // Drop any existing watchers first
     *watcher_lock = None;
+    poll_lock.clear();
 
-    if folders.is_empty() {
+    if folders.is_empty() && rules.is_empty() {
         return Ok(());
     }
 
     // Debounce: only emit events if enough time has passed since the last one
-    let last_emit = std::sync::Arc::new(Mutex::new(Instant::now() - Duration::from_secs(10)));
+    let last_emit = Arc::new(Mutex::new(Instant::now() - Duration::from_secs(10)));
     let app_handle = app.clone();
+    let rules = Arc::new(rules);
+    let rule_folders: Vec<String> = rules.iter().map(|r| r.folder.clone()).collect();
+    // Files waiting to settle, so repeated events for one file import it once
+    let in_flight: Arc<Mutex<HashSet<PathBuf>>> = Arc::new(Mutex::new(HashSet::new()));
+    let pending: PendingPaths = Arc::new(Mutex::new(HashMap::new()));
+    let grace_period = Duration::from_secs(deleted_files.grace_period_secs);
 
-    let watcher = RecommendedWatcher::new(
-        move |result: Result<Event, notify::Error>| {
-            if let Ok(event) = result {
-                // Only react to create/modify/remove events
-                let dominated = matches!(
-                    event.kind,
-                    EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
-                );
-                if !dominated {
-                    return;
-                }
+    let handler = move |result: Result<Event, notify::Error>| {
+        if let Ok(event) = result {
+            // Only react to create/modify/remove events
+            let dominated = matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            );
+            if !dominated {
+                return;
+            }
 
-                // Check if any affected path is an audio file
-                let has_audio = event.paths.iter().any(|p| is_audio_file(p));
-                if !has_audio {
-                    return;
+            // Deleted (or renamed away) audio files and folders: their tracks are handled once
+            // the grace period is over. Audio files that appear may be missing tracks coming back.
+            if deleted_files.action != DeletedFileAction::Ignore {
+                for path in event.paths.iter().filter(|p| is_audio_file(p) || p.extension().is_none()) {
+                    let gone = matches!(event.kind, EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_)))
+                        && !path.exists();
+                    if gone {
+                        queue_path_check(&app_handle, &pending, path.clone(), Instant::now() + grace_period, deleted_files.action);
+                    } else if is_audio_file(path)
+                        && deleted_files.action == DeletedFileAction::MarkMissing
+                        && matches!(event.kind, EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_)))
+                    {
+                        queue_path_check(&app_handle, &pending, path.clone(), Instant::now(), deleted_files.action);
+                    }
                 }
+            }
+
+            // Check if any affected path is an audio file
+            let has_audio = event.paths.iter().any(|p| is_audio_file(p));
+            if !has_audio {
+                return;
+            }
 
-                // Debounce: at most one event per 2 seconds
-                let mut last = last_emit.lock().unwrap();
-                if last.elapsed() < Duration::from_secs(2) {
-                    return;
+            // New files (created, or renamed into place by a browser) go through the watch rules
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_))) {
+                for path in event.paths.iter().filter(|p| is_audio_file(p) && p.is_file()) {
+                    if rules.iter().any(|r| r.matches(path))
+                        && in_flight.lock().unwrap().insert(path.clone())
+                    {
+                        spawn_rule_import(app_handle.clone(), path.clone(), rules.clone(), in_flight.clone());
+                    }
                 }
-                *last = Instant::now();
-                drop(last);
+            }
 
-                // Emit event to frontend
-                let _ = app_handle.emit("library-changed", ());
+            // Debounce: at most one event per 2 seconds
+            let mut last = last_emit.lock().unwrap();
+            if last.elapsed() < Duration::from_secs(2) {
+                return;
             }
-        },
+            *last = Instant::now();
+            drop(last);
+
+            // Emit event to frontend
+            let _ = app_handle.emit("library-changed", ());
+        }
+    };
+
+    let watcher = RecommendedWatcher::new(
+        handler.clone(),
         Config::default().with_poll_interval(Duration::from_secs(2)),
     )
     .map_err(|e| format!("Failed to create file watcher: {}", e))?;
 
     *watcher_lock = Some(watcher);
 
-    // Now watch each folder
+    // Now watch each folder. Rule folders inside a library folder are already covered.
     let watcher_ref = watcher_lock.as_mut().unwrap();
     for folder in &folders {
         let path = Path::new(folder);
+        if !path.is_dir() {
+            continue;
+        }
+        if let Some(&interval_secs) = poll_folders.get(folder) {
+            let mut poller = PollWatcher::new(
+                handler.clone(),
+                Config::default().with_poll_interval(Duration::from_secs(interval_secs)),
+            )
+            .map_err(|e| format!("Failed to create polling watcher: {}", e))?;
+            poller
+                .watch(path, RecursiveMode::Recursive)
+                .map_err(|e| format!("Failed to poll {}: {}", folder, e))?;
+            eprintln!("[watcher] Polling {} every {}s", folder, interval_secs);
+            poll_lock.push(poller);
+        } else {
+            watcher_ref
+                .watch(path, RecursiveMode::Recursive)
+                .map_err(|e| format!("Failed to watch {}: {}", folder, e))?;
+        }
+    }
+    for folder in &rule_folders {
+        let path = Path::new(folder);
+        if folders.iter().any(|f| path.starts_with(f)) {
+            continue;
+        }
         if path.is_dir() {
             watcher_ref
                 .watch(path, RecursiveMode::Recursive)
                 .map_err(|e| format!("Failed to watch {}: {}", folder, e))?;
+        } else {
+            eprintln!("[watcher] Watch rule folder not found: {}", folder);
         }
     }
 
     Ok(())
 }
+
+/// Start watching the given library folders for file changes.
+/// When audio files are created, modified, or removed, emits a "library-changed" event
+/// so the frontend can re-scan and reload. Folders of enabled watch rules are watched too.
+#[tauri::command]
+pub fn start_file_watcher(
+    app: AppHandle,
+    watcher_state: State<WatcherState>,
+    state: State<AppState>,
+    folders: Vec<String>,
+) -> Result<(), String> {
+    *watcher_state.folders.lock().unwrap() = folders;
+    restart_watcher(&app, &watcher_state, &state)
+}
+
+/// A library folder watched by polling
+#[derive(Debug, Serialize)]
+pub struct PollFolderDTO {
+    pub folder: String,
+    pub interval_secs: u64,
+}
+
+/// Library folders switched to polling
+#[tauri::command]
+pub fn get_polled_folders(state: State<AppState>) -> Result<Vec<PollFolderDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let mut folders: Vec<PollFolderDTO> = load_poll_folders(db)?
+        .into_iter()
+        .map(|(folder, interval_secs)| PollFolderDTO { folder, interval_secs })
+        .collect();
+    folders.sort_by(|a, b| a.folder.cmp(&b.folder));
+    Ok(folders)
+}
+
+/// Watch a library folder by polling every `interval_secs` (for network shares, which send
+/// no change notifications), or switch it back to OS notifications with None.
+/// The watcher is restarted to pick it up.
+#[tauri::command]
+pub fn set_folder_polling(
+    app: AppHandle,
+    watcher_state: State<WatcherState>,
+    state: State<AppState>,
+    folder: String,
+    interval_secs: Option<u64>,
+) -> Result<(), String> {
+    if let Some(secs) = interval_secs {
+        if !(MIN_POLL_INTERVAL_SECS..=MAX_POLL_INTERVAL_SECS).contains(&secs) {
+            return Err(format!(
+                "Invalid polling interval {}s. Must be {}-{}s",
+                secs, MIN_POLL_INTERVAL_SECS, MAX_POLL_INTERVAL_SECS
+            ));
+        }
+    }
+    {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let folder = normalize_root(&folder);
+        let mut poll_folders = load_poll_folders(db)?;
+        match interval_secs {
+            Some(secs) => poll_folders.insert(folder, secs),
+            None => poll_folders.remove(&folder),
+        };
+        let json = serde_json::to_string(&poll_folders)
+            .map_err(|e| format!("Failed to serialize polled folders: {}", e))?;
+        db.set_setting(POLL_FOLDERS_SETTING, &json)
+            .map_err(|e| format!("Failed to save polled folders: {}", e))?;
+    }
+    restart_watcher(&app, &watcher_state, &state)
+}
+
+#[tauri::command]
+pub fn get_deleted_file_settings(state: State<AppState>) -> Result<DeletedFileSettings, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    Ok(DeletedFileSettings::from_settings(db))
+}
+
+/// What to do with tracks whose file is deleted: `action` "ignore", "mark_missing" or
+/// "remove", after `grace_period_secs`. The watcher is restarted to pick it up.
+#[tauri::command]
+pub fn set_deleted_file_settings(
+    app: AppHandle,
+    watcher_state: State<WatcherState>,
+    state: State<AppState>,
+    settings: DeletedFileSettings,
+) -> Result<(), String> {
+    if settings.grace_period_secs > MAX_GRACE_PERIOD_SECS {
+        return Err(format!("Grace period must be at most {}s", MAX_GRACE_PERIOD_SECS));
+    }
+    {
+        let json = serde_json::to_string(&settings)
+            .map_err(|e| format!("Failed to serialize deleted file settings: {}", e))?;
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        db.set_setting(DELETED_FILES_SETTING, &json)
+            .map_err(|e| format!("Failed to save deleted file settings: {}", e))?;
+    }
+    restart_watcher(&app, &watcher_state, &state)
+}
+
+/// A watch folder rule as sent to the frontend
+#[derive(Debug, Serialize)]
+pub struct WatchRuleDTO {
+    pub id: i64,
+    pub folder: String,
+    /// Tag added to imported tracks
+    pub tag: Option<String>,
+    /// Playlist imported tracks are appended to
+    pub playlist_name: Option<String>,
+    pub enabled: bool,
+}
+
+impl From<WatchRule> for WatchRuleDTO {
+    fn from(rule: WatchRule) -> Self {
+        WatchRuleDTO {
+            id: rule.id,
+            folder: rule.folder,
+            tag: rule.tag,
+            playlist_name: rule.playlist_name,
+            enabled: rule.enabled,
+        }
+    }
+}
+
+/// Blank strings from the form mean "not set"
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+#[tauri::command]
+pub fn get_watch_rules(state: State<AppState>) -> Result<Vec<WatchRuleDTO>, String> {
+    let db_lock = state.db.lock().unwrap();
+    let db = db_lock.as_ref().ok_or("Database not initialized")?;
+    let rules = db.get_watch_rules()
+        .map_err(|e| format!("Failed to get watch rules: {}", e))?;
+    Ok(rules.into_iter().map(WatchRuleDTO::from).collect())
+}
+
+/// Create a rule: new audio files in `folder` are imported, tagged with `tag` and
+/// added to the playlist `playlist_name`. The watcher is restarted to pick it up.
+#[tauri::command]
+pub fn create_watch_rule(
+    app: AppHandle,
+    watcher_state: State<WatcherState>,
+    state: State<AppState>,
+    folder: String,
+    tag: Option<String>,
+    playlist_name: Option<String>,
+) -> Result<WatchRuleDTO, String> {
+    if !Path::new(&folder).is_dir() {
+        return Err(format!("Folder not found: {}", folder));
+    }
+    let rule = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        let id = db.create_watch_rule(&folder, non_empty(tag).as_deref(), non_empty(playlist_name).as_deref())
+            .map_err(|e| format!("Failed to create watch rule: {}", e))?;
+        db.get_watch_rule(id)
+            .map_err(|e| format!("Failed to get watch rule: {}", e))?
+    };
+    restart_watcher(&app, &watcher_state, &state)?;
+    Ok(rule.into())
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn update_watch_rule(
+    app: AppHandle,
+    watcher_state: State<WatcherState>,
+    state: State<AppState>,
+    id: i64,
+    folder: String,
+    tag: Option<String>,
+    playlist_name: Option<String>,
+    enabled: bool,
+) -> Result<WatchRuleDTO, String> {
+    if !Path::new(&folder).is_dir() {
+        return Err(format!("Folder not found: {}", folder));
+    }
+    let rule = {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        db.update_watch_rule(id, &folder, non_empty(tag).as_deref(), non_empty(playlist_name).as_deref(), enabled)
+            .map_err(|e| format!("Failed to update watch rule: {}", e))?;
+        db.get_watch_rule(id)
+            .map_err(|e| format!("Failed to get watch rule: {}", e))?
+    };
+    restart_watcher(&app, &watcher_state, &state)?;
+    Ok(rule.into())
+}
+
+#[tauri::command]
+pub fn delete_watch_rule(
+    app: AppHandle,
+    watcher_state: State<WatcherState>,
+    state: State<AppState>,
+    id: i64,
+) -> Result<(), String> {
+    {
+        let db_lock = state.db.lock().unwrap();
+        let db = db_lock.as_ref().ok_or("Database not initialized")?;
+        db.delete_watch_rule(id)
+            .map_err(|e| format!("Failed to delete watch rule: {}", e))?;
+    }
+    restart_watcher(&app, &watcher_state, &state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{create_test_track, Track};
+    use tempfile::TempDir;
+
+    fn add_track(db: &Database, path: &Path) -> i64 {
+        let path = path.to_string_lossy().into_owned();
+        let track = Track { file_path: path.clone(), file_hash: path, ..create_test_track() };
+        db.create_track(&track).unwrap()
+    }
+
+    #[test]
+    fn test_apply_deleted_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let folder = temp_dir.path().join("Promos");
+        std::fs::create_dir(&folder).unwrap();
+        let kept = temp_dir.path().join("kept.mp3");
+        let deleted = folder.join("deleted.mp3");
+        std::fs::write(&kept, b"audio").unwrap();
+
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let kept_id = add_track(&db, &kept);
+        let deleted_id = add_track(&db, &deleted);
+
+        // A deleted folder: only the track whose file is gone is flagged
+        let paths = [folder.clone(), kept.clone()];
+        assert_eq!(apply_deleted_files(&db, &paths, DeletedFileAction::MarkMissing), Ok((true, false)));
+        let missing = db.get_missing_tracks().unwrap();
+        assert_eq!(missing.iter().map(|t| t.track_id).collect::<Vec<_>>(), vec![deleted_id]);
+        assert_eq!(apply_deleted_files(&db, &paths, DeletedFileAction::MarkMissing), Ok((false, false)));
+
+        // Removed, then put back by undoing the journal entry
+        assert_eq!(apply_deleted_files(&db, &paths, DeletedFileAction::Remove), Ok((true, true)));
+        assert!(db.get_track(deleted_id).is_err());
+        assert!(db.get_track(kept_id).is_ok());
+        let entry = &db.get_journal_entries(1).unwrap()[0];
+        assert_eq!(entry.operation, journal::REMOVE_TRACKS_OPERATION);
+        assert_eq!(entry.description, "Removed deleted.mp3 (file deleted)");
+        assert_eq!(journal::undo_entry(&db, entry), Ok(1));
+        assert_eq!(db.get_missing_tracks().unwrap()[0].track_id, deleted_id);
+
+        // The file is back
+        std::fs::write(&deleted, b"audio").unwrap();
+        assert_eq!(apply_deleted_files(&db, &[deleted], DeletedFileAction::MarkMissing), Ok((true, false)));
+        assert!(db.get_missing_tracks().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+// Locale-aware text collations registered on every connection
+// SQLite's BINARY collation sorts by code point, so "Édith" lands after "Z" and "abba" after "ZZ Top".
+// These compare case- and accent-folded keys instead (Latin scripts: é -> e, đ -> d, ß -> ss),
+// falling back to BINARY for ties so the order is stable.
+
+use rusqlite::{Connection, Result};
+use std::cmp::Ordering;
+
+/// Case- and accent-insensitive: `ORDER BY t.artist COLLATE UNICODE`
+pub const UNICODE: &str = "UNICODE";
+/// Like UNICODE, also ignoring a leading "The", "A" or "An" ("The Prodigy" sorts under P)
+pub const UNICODE_NO_ARTICLE: &str = "UNICODE_NO_ARTICLE";
+
+/// Register the collations on a connection (collations are per connection)
+pub fn register(conn: &Connection) -> Result<()> {
+    conn.create_collation(UNICODE, |a, b| compare(a, b, false))?;
+    conn.create_collation(UNICODE_NO_ARTICLE, |a, b| compare(a, b, true))
+}
+
+fn compare(a: &str, b: &str, ignore_articles: bool) -> Ordering {
+    sort_key(a, ignore_articles)
+        .cmp(&sort_key(b, ignore_articles))
+        .then_with(|| a.cmp(b))
+}
+
+/// Lowercased, accent-stripped form of `text` used for comparison
+pub fn sort_key(text: &str, ignore_articles: bool) -> String {
+    let mut key = String::with_capacity(text.len());
+    for c in text.trim_start().chars().flat_map(char::to_lowercase) {
+        match c {
+            'ß' => key.push_str("ss"),
+            'æ' => key.push_str("ae"),
+            'œ' => key.push_str("oe"),
+            'þ' => key.push_str("th"),
+            _ => key.push(fold_accent(c)),
+        }
+    }
+    if ignore_articles {
+        for article in ["the ", "a ", "an "] {
+            if let Some(rest) = key.strip_prefix(article) {
+                let rest = rest.trim_start();
+                if !rest.is_empty() {
+                    return rest.to_string();
+                }
+            }
+        }
+    }
+    key
+}
+
+/// Base letter for accented lowercase Latin letters (Latin-1 Supplement + Latin Extended-A)
+fn fold_accent(c: char) -> char {
+    match c {
+        'à'..='å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'ď' | 'đ' | 'ð' => 'd',
+        'è'..='ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => 'e',
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => 'g',
+        'ĥ' | 'ħ' => 'h',
+        'ì'..='ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => 'i',
+        'ĵ' => 'j',
+        'ķ' => 'k',
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => 'l',
+        'ñ' | 'ń' | 'ņ' | 'ň' => 'n',
+        'ò'..='ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => 'o',
+        'ŕ' | 'ŗ' | 'ř' => 'r',
+        'ś' | 'ŝ' | 'ş' | 'š' => 's',
+        'ţ' | 'ť' | 'ŧ' => 't',
+        'ù'..='ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => 'u',
+        'ŵ' => 'w',
+        'ý' | 'ÿ' | 'ŷ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_key() {
+        assert_eq!(sort_key("Édith Piaf", false), "edith piaf");
+        assert_eq!(sort_key("Đorđe Balašević", false), "dorde balasevic");
+        assert_eq!(sort_key("Straße", false), "strasse");
+        assert_eq!(sort_key("The Prodigy", true), "prodigy");
+        assert_eq!(sort_key("The Prodigy", false), "the prodigy");
+        assert_eq!(sort_key("A Tribe Called Quest", true), "tribe called quest");
+        assert_eq!(sort_key("The", true), "the");
+        assert_eq!(sort_key("Abba", true), "abba");
+    }
+
+    #[test]
+    fn test_collation_order() {
+        let conn = Connection::open_in_memory().unwrap();
+        register(&conn).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE t (name TEXT);
+             INSERT INTO t VALUES ('Zeds Dead'), ('Édith Piaf'), ('abba'), ('The Prodigy'), ('Eric Prydz'), ('Sasha');",
+        )
+        .unwrap();
+        let sorted = |collation: &str| -> Vec<String> {
+            let mut stmt = conn
+                .prepare(&format!("SELECT name FROM t ORDER BY name COLLATE {}", collation))
+                .unwrap();
+            stmt.query_map([], |row| row.get(0)).unwrap().map(|r| r.unwrap()).collect()
+        };
+        assert_eq!(
+            sorted(UNICODE),
+            vec!["abba", "Édith Piaf", "Eric Prydz", "Sasha", "The Prodigy", "Zeds Dead"]
+        );
+        assert_eq!(
+            sorted(UNICODE_NO_ARTICLE),
+            vec!["abba", "Édith Piaf", "Eric Prydz", "The Prodigy", "Sasha", "Zeds Dead"]
+        );
+    }
+}
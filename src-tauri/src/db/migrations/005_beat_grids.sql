@@ -0,0 +1,13 @@
+-- Migration 005: Beat grids
+-- One row per track. Beats are stored as a JSON array of [position_ms, beat_number]
+-- pairs (beat_number = position in bar, 1-4) to avoid one row per beat.
+-- source: 'rekordbox' for imported grids, 'analysis' for grids computed by RecoDeck
+
+CREATE TABLE IF NOT EXISTS track_beat_grids (
+    track_id        INTEGER PRIMARY KEY REFERENCES tracks(id),
+    bpm             REAL,
+    first_beat_ms   INTEGER,
+    beats           TEXT NOT NULL,
+    source          TEXT NOT NULL,
+    updated_at      TEXT DEFAULT (datetime('now'))
+);
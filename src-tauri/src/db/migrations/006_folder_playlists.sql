@@ -0,0 +1,6 @@
+-- Migration 006: Folder-mirrored playlists
+-- Playlists of type 'mirror' are generated from an on-disk folder and kept in sync
+-- by the scanner. source_folder holds the folder path they mirror.
+ALTER TABLE playlists ADD COLUMN source_folder TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_playlists_source_folder ON playlists(source_folder);
@@ -0,0 +1,22 @@
+-- Migration 007: Play history and popularity scores
+-- play_history: one row per playback. skipped = 1 when the track was abandoned early.
+-- track_scores: recency-weighted popularity, recomputed periodically by a background job.
+
+CREATE TABLE IF NOT EXISTS play_history (
+    id              INTEGER PRIMARY KEY,
+    track_id        INTEGER NOT NULL REFERENCES tracks(id),
+    played_at       TEXT DEFAULT (datetime('now')),
+    listened_ms     INTEGER,
+    skipped         INTEGER DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS idx_play_history_track ON play_history(track_id);
+CREATE INDEX IF NOT EXISTS idx_play_history_played_at ON play_history(played_at);
+
+CREATE TABLE IF NOT EXISTS track_scores (
+    track_id        INTEGER PRIMARY KEY REFERENCES tracks(id),
+    score           REAL NOT NULL,
+    updated_at      TEXT DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_track_scores_score ON track_scores(score);
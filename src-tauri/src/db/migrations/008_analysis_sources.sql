@@ -0,0 +1,5 @@
+-- Migration 008: Where BPM/key values came from
+-- 'tag' = read from file tags at scan time (Traktor/Rekordbox/Mixed In Key wrote them),
+-- 'analysis' = computed by RecoDeck. NULL for rows written before this migration.
+ALTER TABLE track_analysis ADD COLUMN bpm_source TEXT;
+ALTER TABLE track_analysis ADD COLUMN key_source TEXT;
@@ -0,0 +1,6 @@
+-- Migration 009: Energy level (1-10)
+-- Parsed from comment/custom tags at scan time (e.g. "Energy 7" written by
+-- Mixed In Key) or set manually.
+ALTER TABLE tracks ADD COLUMN energy_level INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_tracks_energy_level ON tracks(energy_level);
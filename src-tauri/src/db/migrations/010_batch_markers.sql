@@ -0,0 +1,12 @@
+-- Migration 010: Recovery markers for long-running batch jobs
+-- A row is written when a scan/analysis batch starts, its processed count is
+-- updated at every periodic commit, and the row is deleted when the job
+-- finishes. A row that survives an app restart means the job was interrupted.
+CREATE TABLE IF NOT EXISTS batch_markers (
+    job         TEXT PRIMARY KEY,        -- e.g. 'scan:/Music', 'analyze_bpm'
+    detail      TEXT,
+    processed   INTEGER NOT NULL DEFAULT 0,
+    total       INTEGER,
+    started_at  TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at  TEXT NOT NULL DEFAULT (datetime('now'))
+);
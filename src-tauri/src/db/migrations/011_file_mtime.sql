@@ -0,0 +1,4 @@
+-- Migration 011: File modification time
+-- Unix seconds of the file's mtime when it was last scanned. Together with
+-- file_size it lets rescans skip unchanged files without re-hashing them.
+ALTER TABLE tracks ADD COLUMN file_mtime INTEGER;
@@ -0,0 +1,4 @@
+-- Migration 012: Track color tag
+-- A user-assigned "#RRGGBB" color (like Rekordbox/Traktor track colors), set
+-- manually or by batch actions.
+ALTER TABLE tracks ADD COLUMN color TEXT;
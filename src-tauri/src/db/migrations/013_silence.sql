@@ -0,0 +1,5 @@
+-- Migration 013: Leading/trailing silence
+-- Dead air at the start and end of a track (ms), detected from the waveform
+-- during analysis. Playback can skip the lead-in.
+ALTER TABLE track_analysis ADD COLUMN silence_lead_ms INTEGER;
+ALTER TABLE track_analysis ADD COLUMN silence_tail_ms INTEGER;
@@ -0,0 +1,12 @@
+-- Migration 014: Playlist drafts
+-- Playlists proposed from a listening session, waiting for the user to accept
+-- (becomes a real playlist) or discard them. track_ids is a JSON array, in order.
+CREATE TABLE IF NOT EXISTS playlist_drafts (
+    id          INTEGER PRIMARY KEY,
+    session_id  INTEGER NOT NULL,        -- first play_history row of the session
+    name        TEXT NOT NULL,
+    track_ids   TEXT NOT NULL,
+    created_at  TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_playlist_drafts_session ON playlist_drafts(session_id);
@@ -0,0 +1,5 @@
+-- Migration 015: Disc number and track total
+-- Read from tags on scan so multi-disc albums sort disc by disc instead of
+-- interleaving tracks with the same track number.
+ALTER TABLE tracks ADD COLUMN disc_number INTEGER;
+ALTER TABLE tracks ADD COLUMN total_tracks INTEGER;
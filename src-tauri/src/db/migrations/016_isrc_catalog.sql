@@ -0,0 +1,5 @@
+-- Migration 016: ISRC and catalog number
+-- Read from tags on scan (ID3 TSRC / Vorbis ISRC, CATALOGNUMBER); searchable and
+-- included in tracklist exports for radio-play reporting.
+ALTER TABLE tracks ADD COLUMN isrc TEXT;
+ALTER TABLE tracks ADD COLUMN catalog_number TEXT;
@@ -0,0 +1,12 @@
+-- Migration 017: File integrity verification
+-- Latest re-hash of each track's file compared with tracks.file_hash.
+-- status: 'ok', 'mismatch' (content changed), 'missing', 'unreadable', or
+-- 'baselined' (the track had no hash yet; the computed one was stored).
+CREATE TABLE IF NOT EXISTS track_verifications (
+    track_id     INTEGER PRIMARY KEY REFERENCES tracks(id) ON DELETE CASCADE,
+    status       TEXT NOT NULL,
+    actual_hash  TEXT,                    -- hash computed at verification (NULL if unreadable)
+    verified_at  TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_track_verifications_status ON track_verifications(status);
@@ -0,0 +1,11 @@
+-- Migration 018: Watch folder rules
+-- New audio files appearing under `folder` are imported automatically, tagged with
+-- `tag` and appended to the manual playlist named `playlist_name` (created if missing).
+CREATE TABLE IF NOT EXISTS watch_rules (
+    id              INTEGER PRIMARY KEY,
+    folder          TEXT NOT NULL UNIQUE,
+    tag             TEXT,                    -- e.g. "Promo"; NULL = don't tag
+    playlist_name   TEXT,                    -- e.g. "Inbox"; NULL = don't add to a playlist
+    enabled         BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at      TEXT NOT NULL DEFAULT (datetime('now'))
+);
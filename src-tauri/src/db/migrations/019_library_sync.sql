@@ -0,0 +1,71 @@
+-- Migration 019: Library sync between two RecoDeck instances
+-- Tracks are matched across machines by content hash, playlists by a random sync_id.
+-- The *_updated_at columns are maintained by triggers so every write path is covered;
+-- on conflict the newer side wins. A statement that sets the timestamp itself (a sync
+-- applying the other side's change) keeps that timestamp.
+ALTER TABLE tracks ADD COLUMN metadata_updated_at TEXT;  -- rating, color, comment, genre, energy
+ALTER TABLE tracks ADD COLUMN cues_updated_at TEXT;
+ALTER TABLE playlists ADD COLUMN sync_id TEXT;
+
+UPDATE playlists SET sync_id = lower(hex(randomblob(16))) WHERE sync_id IS NULL;
+UPDATE playlists SET updated_at = COALESCE(updated_at, created_at, datetime('now'));
+CREATE UNIQUE INDEX IF NOT EXISTS idx_playlists_sync_id ON playlists(sync_id);
+
+-- Existing ratings, colors, energy and cues count as set when the track was added
+UPDATE tracks SET metadata_updated_at = COALESCE(date_added, datetime('now'))
+WHERE rating > 0 OR color IS NOT NULL OR energy_level IS NOT NULL;
+UPDATE tracks SET cues_updated_at = COALESCE(date_added, datetime('now'))
+WHERE id IN (SELECT track_id FROM cue_points);
+
+CREATE TRIGGER IF NOT EXISTS trg_playlists_sync_insert AFTER INSERT ON playlists
+WHEN NEW.sync_id IS NULL OR NEW.updated_at IS NULL
+BEGIN
+    UPDATE playlists
+    SET sync_id = COALESCE(NEW.sync_id, lower(hex(randomblob(16)))),
+        updated_at = COALESCE(NEW.updated_at, datetime('now'))
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_playlists_touch AFTER UPDATE OF name, parent_id, smart_rules, ai_prompt ON playlists
+WHEN NEW.updated_at IS OLD.updated_at
+BEGIN
+    UPDATE playlists SET updated_at = datetime('now') WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_playlist_tracks_insert AFTER INSERT ON playlist_tracks
+BEGIN
+    UPDATE playlists SET updated_at = datetime('now') WHERE id = NEW.playlist_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_playlist_tracks_delete AFTER DELETE ON playlist_tracks
+BEGIN
+    UPDATE playlists SET updated_at = datetime('now') WHERE id = OLD.playlist_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_playlist_tracks_move AFTER UPDATE OF position ON playlist_tracks
+BEGIN
+    UPDATE playlists SET updated_at = datetime('now') WHERE id = NEW.playlist_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_tracks_metadata_touch AFTER UPDATE OF rating, color, comment, genre, energy_level ON tracks
+WHEN NEW.metadata_updated_at IS OLD.metadata_updated_at
+ AND (NEW.rating IS NOT OLD.rating OR NEW.color IS NOT OLD.color OR NEW.comment IS NOT OLD.comment
+      OR NEW.genre IS NOT OLD.genre OR NEW.energy_level IS NOT OLD.energy_level)
+BEGIN
+    UPDATE tracks SET metadata_updated_at = datetime('now') WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_cue_points_insert AFTER INSERT ON cue_points
+BEGIN
+    UPDATE tracks SET cues_updated_at = datetime('now') WHERE id = NEW.track_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_cue_points_update AFTER UPDATE ON cue_points
+BEGIN
+    UPDATE tracks SET cues_updated_at = datetime('now') WHERE id = NEW.track_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_cue_points_delete AFTER DELETE ON cue_points
+BEGIN
+    UPDATE tracks SET cues_updated_at = datetime('now') WHERE id = OLD.track_id;
+END;
@@ -0,0 +1,5 @@
+-- Migration 020: Where a track's energy level came from
+-- 'tag' = generic tag field, 'mixedinkey' = written by Mixed In Key, 'user' = set in RecoDeck.
+-- Tag values never replace a 'user' value. NULL for levels stored before this
+-- migration; those are kept as well, since they may have been set by hand.
+ALTER TABLE tracks ADD COLUMN energy_source TEXT;
@@ -0,0 +1,5 @@
+-- Migration 021: Tempo curve (BPM per window) for tracks with drifting tempo
+-- tempo_curve is a JSON array of { time_ms, bpm }; tempo_variable flags tracks
+-- whose BPM spread across windows is wide enough to need manual beatmatching.
+ALTER TABLE track_analysis ADD COLUMN tempo_curve TEXT;
+ALTER TABLE track_analysis ADD COLUMN tempo_variable INTEGER;
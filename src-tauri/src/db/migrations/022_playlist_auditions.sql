@@ -0,0 +1,9 @@
+-- Migration 022: Per-playlist audition marks ("already reviewed")
+-- A row means the track was played with the playlist as play context; set
+-- automatically by playback, cleared per playlist by reset_playlist_auditions.
+CREATE TABLE IF NOT EXISTS playlist_auditions (
+    playlist_id     INTEGER NOT NULL REFERENCES playlists(id),
+    track_id        INTEGER NOT NULL REFERENCES tracks(id),
+    auditioned_at   TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (playlist_id, track_id)
+);
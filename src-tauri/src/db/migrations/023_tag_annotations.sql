@@ -0,0 +1,7 @@
+-- Migration 023: Source of ratings and cue points imported from file tags
+-- rating_source: 'tag' = POPM / RATING field, 'user' = set in RecoDeck, NULL = from before
+-- this migration (kept, like a user rating, unless it is still 0).
+-- cue_points.source: 'serato' = Serato markers in the file's tags; NULL = set in RecoDeck,
+-- imported from Rekordbox or synced. Tag imports only ever replace their own cues.
+ALTER TABLE tracks ADD COLUMN rating_source TEXT;
+ALTER TABLE cue_points ADD COLUMN source TEXT;
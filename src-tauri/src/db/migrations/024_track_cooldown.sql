@@ -0,0 +1,4 @@
+-- Migration 024: "Don't play again before" date per track
+-- cooldown_until: 'YYYY-MM-DD' (local date); the track is cooled down while today is
+-- before it. NULL = no cooldown. Playlist builders and AI generation skip cooled-down tracks.
+ALTER TABLE tracks ADD COLUMN cooldown_until TEXT;
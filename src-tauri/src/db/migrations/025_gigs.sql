@@ -0,0 +1,21 @@
+-- Migration 025: Gigs (venue / date / slot length) and the playlists prepared for them
+-- date: 'YYYY-MM-DD'. A playlist can be linked to several gigs; position orders the
+-- playlists within a gig (e.g. main set first, then backups).
+CREATE TABLE IF NOT EXISTS gigs (
+    id                  INTEGER PRIMARY KEY,
+    name                TEXT NOT NULL,
+    venue               TEXT,
+    date                TEXT NOT NULL,
+    set_length_minutes  INTEGER NOT NULL,
+    notes               TEXT,
+    created_at          TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE IF NOT EXISTS gig_playlists (
+    gig_id          INTEGER NOT NULL REFERENCES gigs(id),
+    playlist_id     INTEGER NOT NULL REFERENCES playlists(id),
+    position        INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (gig_id, playlist_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_gig_playlists_playlist ON gig_playlists(playlist_id);
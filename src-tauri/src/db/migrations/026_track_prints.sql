@@ -0,0 +1,9 @@
+-- Migration 026: Sub-fingerprints for identifying tracks in recorded sets
+-- data: audio::fingerprint blob (one 32-bit hash per ~46 ms). file_hash is the track's
+-- hash when the print was taken; a print whose hash no longer matches is stale.
+CREATE TABLE IF NOT EXISTS track_prints (
+    track_id        INTEGER PRIMARY KEY REFERENCES tracks(id),
+    file_hash       TEXT NOT NULL,
+    data            BLOB NOT NULL,
+    created_at      TEXT NOT NULL DEFAULT (datetime('now'))
+);
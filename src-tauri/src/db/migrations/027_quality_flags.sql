@@ -0,0 +1,7 @@
+-- Migration 027: Technical quality checks (audio::quality)
+-- quality_flags is a bitfield: 1 lossy cutoff in a lossless file, 2 low bandwidth,
+-- 4 DC offset, 8 mains hum. NULL = not checked yet. cutoff/hum are NULL when not found.
+ALTER TABLE track_analysis ADD COLUMN quality_flags INTEGER;
+ALTER TABLE track_analysis ADD COLUMN quality_cutoff_hz REAL;
+ALTER TABLE track_analysis ADD COLUMN quality_hum_hz REAL;
+ALTER TABLE track_analysis ADD COLUMN quality_dc_offset REAL;
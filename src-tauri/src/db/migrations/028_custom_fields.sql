@@ -0,0 +1,19 @@
+-- Migration 028: User-defined track fields ("Vinyl owned", "Stems available", ...)
+-- field_type: 'text', 'number', 'boolean' ("true"/"false") or 'date' ('YYYY-MM-DD').
+-- Values are stored as text; a track without a row for a field has no value for it.
+CREATE TABLE IF NOT EXISTS custom_field_definitions (
+    id              INTEGER PRIMARY KEY,
+    name            TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    field_type      TEXT NOT NULL DEFAULT 'text',
+    sort_order      INTEGER NOT NULL DEFAULT 0,
+    created_at      TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE IF NOT EXISTS track_custom_fields (
+    track_id        INTEGER NOT NULL REFERENCES tracks(id),
+    field_id        INTEGER NOT NULL REFERENCES custom_field_definitions(id),
+    value           TEXT NOT NULL,
+    PRIMARY KEY (track_id, field_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_track_custom_fields_field ON track_custom_fields(field_id);
@@ -0,0 +1,13 @@
+-- Migration 029: Playlist membership history
+-- A snapshot is taken before a playlist's tracks change (a burst of changes, like
+-- "remove all", shares one), on demand, and before a restore. track_ids is a JSON
+-- array, in order. reason: 'auto', 'manual' or 'restore'.
+CREATE TABLE IF NOT EXISTS playlist_snapshots (
+    id              INTEGER PRIMARY KEY,
+    playlist_id     INTEGER NOT NULL,
+    track_ids       TEXT NOT NULL,
+    reason          TEXT NOT NULL DEFAULT 'auto',
+    created_at      TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_playlist_snapshots_playlist ON playlist_snapshots(playlist_id, id);
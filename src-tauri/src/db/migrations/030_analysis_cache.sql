@@ -0,0 +1,49 @@
+-- Migration 030: Analysis kept by content hash
+-- When a track's analysis row is deleted (always done before deleting the track), it is
+-- copied here under the file's content hash, so a file re-imported later (same content, any path)
+-- gets BPM, key, loudness and waveforms back without being analyzed again.
+-- Lazily hashed ('pending') and unhashable ('unknown') files are not cached.
+CREATE TABLE IF NOT EXISTS analysis_cache (
+    file_hash         TEXT PRIMARY KEY,
+    bpm               REAL,
+    bpm_confidence    REAL,
+    bpm_source        TEXT,
+    musical_key       TEXT,
+    key_confidence    REAL,
+    key_source        TEXT,
+    loudness_lufs     REAL,
+    dynamic_range     REAL,
+    spectral_centroid REAL,
+    waveform_overview BLOB,
+    waveform_detail   BLOB,
+    silence_lead_ms   INTEGER,
+    silence_tail_ms   INTEGER,
+    tempo_curve       TEXT,
+    tempo_variable    INTEGER,
+    quality_flags     INTEGER,
+    quality_cutoff_hz REAL,
+    quality_hum_hz    REAL,
+    quality_dc_offset REAL,
+    analyzed_at       TEXT,
+    cached_at         TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TRIGGER IF NOT EXISTS trg_track_analysis_cache BEFORE DELETE ON track_analysis
+WHEN OLD.bpm IS NOT NULL OR OLD.musical_key IS NOT NULL OR OLD.loudness_lufs IS NOT NULL
+  OR OLD.waveform_overview IS NOT NULL
+BEGIN
+    INSERT OR REPLACE INTO analysis_cache (
+        file_hash, bpm, bpm_confidence, bpm_source, musical_key, key_confidence, key_source,
+        loudness_lufs, dynamic_range, spectral_centroid, waveform_overview, waveform_detail,
+        silence_lead_ms, silence_tail_ms, tempo_curve, tempo_variable,
+        quality_flags, quality_cutoff_hz, quality_hum_hz, quality_dc_offset, analyzed_at)
+    SELECT t.file_hash, OLD.bpm, OLD.bpm_confidence, OLD.bpm_source, OLD.musical_key,
+        OLD.key_confidence, OLD.key_source, OLD.loudness_lufs, OLD.dynamic_range,
+        OLD.spectral_centroid, OLD.waveform_overview, OLD.waveform_detail,
+        OLD.silence_lead_ms, OLD.silence_tail_ms, OLD.tempo_curve, OLD.tempo_variable,
+        OLD.quality_flags, OLD.quality_cutoff_hz, OLD.quality_hum_hz, OLD.quality_dc_offset,
+        OLD.analyzed_at
+    FROM tracks t
+    WHERE t.id = OLD.track_id AND t.file_hash NOT IN ('pending', 'unknown');
+END;
+
@@ -0,0 +1,8 @@
+-- Migration 031: Where a track was bought (or received as a promo)
+-- purchase_source is free text ("Beatport", "Bandcamp", "Promo", ...); the scanner fills it
+-- in from folder names (store download folders, extracted order archives) when empty.
+-- purchase_date is YYYY-MM-DD.
+ALTER TABLE tracks ADD COLUMN purchase_source TEXT;
+ALTER TABLE tracks ADD COLUMN purchase_date TEXT;
+ALTER TABLE tracks ADD COLUMN purchase_order_id TEXT;
+CREATE INDEX IF NOT EXISTS idx_tracks_purchase_source ON tracks(purchase_source);
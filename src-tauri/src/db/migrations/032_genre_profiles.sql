@@ -0,0 +1,13 @@
+-- Migration 032: Genre profiles learned from the library
+-- Typical BPM range (middle 80% of analyzed BPMs) and most common Camelot keys per genre,
+-- recomputed by learn_genre_profiles. Used to pre-fill playlist builder filters and
+-- in AI prompts that name a genre.
+CREATE TABLE IF NOT EXISTS genre_profiles (
+    genre        TEXT PRIMARY KEY,
+    track_count  INTEGER NOT NULL,
+    min_bpm      REAL,
+    median_bpm   REAL,
+    max_bpm      REAL,
+    common_keys  TEXT NOT NULL DEFAULT '',  -- comma-separated, most common first
+    updated_at   TEXT NOT NULL DEFAULT (datetime('now'))
+);
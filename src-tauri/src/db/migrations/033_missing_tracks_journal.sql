@@ -0,0 +1,16 @@
+-- Migration 033: Missing files and the operation journal
+-- tracks.missing_since is set when the file watcher sees a track's file deleted (and
+-- cleared when it comes back). operation_journal records library changes made in the
+-- background, such as the watcher's cleanup of deleted files, with a JSON payload holding
+-- what undoing the change needs (for removed tracks: their rows in every per-track table).
+ALTER TABLE tracks ADD COLUMN missing_since TEXT;
+CREATE INDEX IF NOT EXISTS idx_tracks_missing_since ON tracks(missing_since);
+
+CREATE TABLE IF NOT EXISTS operation_journal (
+    id              INTEGER PRIMARY KEY,
+    operation       TEXT NOT NULL,           -- 'mark_missing', 'remove_tracks'
+    description     TEXT NOT NULL,
+    payload         TEXT NOT NULL,
+    created_at      TEXT NOT NULL DEFAULT (datetime('now')),
+    undone_at       TEXT
+);
@@ -0,0 +1,11 @@
+-- Migration 034: AI token usage
+-- Tokens reported by the Claude API, summed per calendar month (UTC, 'YYYY-MM') and
+-- feature ('playlist_generation', 'playlist_refinement', 'chat', 'session_naming').
+CREATE TABLE IF NOT EXISTS ai_usage (
+    month           TEXT NOT NULL,
+    purpose         TEXT NOT NULL,
+    requests        INTEGER NOT NULL DEFAULT 0,
+    input_tokens    INTEGER NOT NULL DEFAULT 0,
+    output_tokens   INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (month, purpose)
+);
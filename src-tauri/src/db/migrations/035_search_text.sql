@@ -0,0 +1,13 @@
+-- Migration 035: Transliterated search text
+-- tracks.search_text holds title, artist, album, album artist, label and genre folded to
+-- lowercase ASCII (db/search_text.rs), so Latin-script searches find "Жу" as "zhu".
+-- It is computed in Rust: NULL means "to compute" (Database::refresh_search_text), and
+-- editing one of those fields sets it back to NULL.
+ALTER TABLE tracks ADD COLUMN search_text TEXT;
+CREATE INDEX IF NOT EXISTS idx_tracks_search_text_stale ON tracks(id) WHERE search_text IS NULL;
+
+CREATE TRIGGER IF NOT EXISTS tracks_search_text_stale
+AFTER UPDATE OF title, artist, album, album_artist, label, genre ON tracks
+BEGIN
+    UPDATE tracks SET search_text = NULL WHERE id = NEW.id;
+END;
@@ -0,0 +1,14 @@
+-- Migration 036: Failed analyses
+-- One row per track whose last analysis attempt failed (missing file, undecodable audio,
+-- failing detector). attempts counts failures in a row; after too many the file is skipped
+-- until retried by hand. file_hash is the track's hash at the last failure: when the file
+-- changes, the count starts over. The row is deleted once an analysis succeeds.
+CREATE TABLE IF NOT EXISTS analysis_errors (
+    track_id        INTEGER PRIMARY KEY REFERENCES tracks(id),
+    file_hash       TEXT NOT NULL,
+    error_class     TEXT NOT NULL,           -- 'missing_file', 'decode', 'analysis'
+    message         TEXT NOT NULL,
+    attempts        INTEGER NOT NULL DEFAULT 1,
+    first_failed_at TEXT NOT NULL DEFAULT (datetime('now')),
+    last_failed_at  TEXT NOT NULL DEFAULT (datetime('now'))
+);
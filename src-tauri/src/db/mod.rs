@@ -1,19 +1,359 @@
 // Database layer - SQLite connection, migrations, queries
 
-use rusqlite::{params, Connection, Result};
-use std::path::Path;
+pub mod collation;
+pub mod search_text;
+mod watchdog;
+
+pub use watchdog::{DbGuard, DbMutex};
+
+use rusqlite::{params, Connection, ErrorCode, Result, TransactionBehavior};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long a statement waits for another connection's write lock before failing with SQLITE_BUSY
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+/// with_busy_retry: attempts before giving up, and the first backoff delay (doubles each retry)
+const BUSY_RETRIES: u32 = 4;
+const BUSY_RETRY_DELAY: Duration = Duration::from_millis(100);
+/// with_busy_retry: busy_timeout of each attempt. The caller holds the app's database mutex
+/// throughout, so this keeps the whole retry under 2s (4 x 250ms + 700ms of backoff)
+/// instead of 4 x BUSY_TIMEOUT.
+const BUSY_RETRY_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Popularity score: a play's weight halves every this many days
+const SCORE_HALF_LIFE_DAYS: f64 = 30.0;
+/// Popularity score: points per rating star
+const SCORE_RATING_WEIGHT: f64 = 1.0;
+/// Popularity score: a (recency-weighted) skip cancels this fraction of a play
+const SCORE_SKIP_PENALTY: f64 = 0.5;
+
+/// Playlist history: a change within this many seconds of the previous one belongs to the
+/// same burst ("remove all", an AI regeneration), which gets one snapshot before it starts
+const SNAPSHOT_COALESCE_SECS: i64 = 10;
+/// Playlist history: snapshots kept per playlist
+const SNAPSHOTS_KEPT: i64 = 50;
+/// Operation journal entries kept (oldest dropped first)
+const JOURNAL_ENTRIES_KEPT: i64 = 200;
+
+/// Per-track tables cleared when a track is removed with delete_track_data, and kept in its
+/// snapshot (snapshot_track) for undo. track_analysis isn't among them: deleting it copies it
+/// to analysis_cache (migration 030), where restoring picks it up.
+const TRACK_DATA_TABLES: [&str; 17] = [
+    "playlist_tracks", "playlist_auditions", "cue_points", "play_history", "track_scores",
+    "track_beat_grids", "track_fingerprints", "track_prints", "track_deep_analysis",
+    "track_embeddings", "track_discogs_styles", "track_instruments", "track_tags",
+    "track_genres", "track_custom_fields", "track_verifications", "analysis_errors",
+];
+
+/// Track columns get_distinct_values can list (filter dropdowns)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DistinctColumn {
+    Genre,
+    Key,
+    Year,
+    Label,
+    Format,
+}
+
+impl DistinctColumn {
+    pub const ALL: [DistinctColumn; 5] = [Self::Genre, Self::Key, Self::Year, Self::Label, Self::Format];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Genre => "genre",
+            Self::Key => "key",
+            Self::Year => "year",
+            Self::Label => "label",
+            Self::Format => "format",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    /// Query listing the column's values with track counts (newest years first, the rest A-Z)
+    fn query(self) -> &'static str {
+        match self {
+            Self::Genre => "SELECT genre, COUNT(*) FROM tracks
+                           WHERE genre IS NOT NULL AND TRIM(genre) != ''
+                           GROUP BY genre ORDER BY genre COLLATE UNICODE",
+            Self::Key => "SELECT a.musical_key, COUNT(*) FROM tracks t
+                          JOIN track_analysis a ON a.track_id = t.id
+                          WHERE a.musical_key IS NOT NULL AND TRIM(a.musical_key) != ''
+                          GROUP BY a.musical_key ORDER BY a.musical_key",
+            Self::Year => "SELECT CAST(year AS TEXT), COUNT(*) FROM tracks
+                           WHERE year > 0
+                           GROUP BY year ORDER BY year DESC",
+            Self::Label => "SELECT label, COUNT(*) FROM tracks
+                            WHERE label IS NOT NULL AND TRIM(label) != ''
+                            GROUP BY label ORDER BY label COLLATE UNICODE",
+            Self::Format => "SELECT LOWER(file_format), COUNT(*) FROM tracks
+                             WHERE file_format IS NOT NULL AND TRIM(file_format) != ''
+                             GROUP BY LOWER(file_format) ORDER BY LOWER(file_format)",
+        }
+    }
+}
+
+/// What get_track_groups groups tracks by (grouped list views)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrackGrouping {
+    Album,
+    Artist,
+    Genre,
+    Key,
+    /// 120 for 120-129.9 BPM
+    BpmDecade,
+    /// 'YYYY-MM' of date_added
+    MonthAdded,
+}
+
+impl TrackGrouping {
+    pub const ALL: [TrackGrouping; 6] = [
+        Self::Album, Self::Artist, Self::Genre, Self::Key, Self::BpmDecade, Self::MonthAdded,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Album => "album",
+            Self::Artist => "artist",
+            Self::Genre => "genre",
+            Self::Key => "key",
+            Self::BpmDecade => "bpm_decade",
+            Self::MonthAdded => "month_added",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|g| g.name() == name)
+    }
+
+    /// A track's group value over `t` (tracks) and `a` (track_analysis); NULL if it has none
+    fn value_sql(self) -> &'static str {
+        match self {
+            Self::Album => "NULLIF(TRIM(t.album), '')",
+            Self::Artist => "NULLIF(TRIM(t.artist), '')",
+            Self::Genre => "NULLIF(TRIM(t.genre), '')",
+            Self::Key => "NULLIF(TRIM(a.musical_key), '')",
+            Self::BpmDecade => "CASE WHEN a.bpm > 0 THEN CAST(CAST(a.bpm / 10 AS INTEGER) * 10 AS TEXT) END",
+            Self::MonthAdded => "strftime('%Y-%m', t.date_added)",
+        }
+    }
+
+    /// Order of the groups (the one without a value last)
+    fn group_order(self) -> &'static str {
+        match self {
+            Self::Album | Self::Artist | Self::Genre => "value IS NULL, value COLLATE UNICODE",
+            Self::Key | Self::BpmDecade => "value IS NULL, CAST(value AS INTEGER), value",
+            Self::MonthAdded => "value IS NULL, value DESC",
+        }
+    }
+
+    /// Order of the tracks within a group
+    fn member_order(self) -> &'static str {
+        match self {
+            Self::Album => "t.disc_number, t.track_number, t.title COLLATE UNICODE, t.id",
+            Self::BpmDecade => "a.bpm, t.id",
+            Self::MonthAdded => "t.date_added DESC, t.id",
+            Self::Artist | Self::Genre | Self::Key => {
+                "t.artist IS NULL, t.artist COLLATE UNICODE, t.title COLLATE UNICODE, t.id"
+            }
+        }
+    }
+}
+
+/// Conditions for filtered track queries (grouped views). Empty lists and missing bounds
+/// don't filter.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackFilter {
+    /// Any of these genres (ignoring case)
+    pub genres: Vec<String>,
+    /// Any of these keys (ignoring case)
+    pub keys: Vec<String>,
+    pub min_bpm: Option<f64>,
+    pub max_bpm: Option<f64>,
+    pub min_rating: Option<i32>,
+    /// Text in the title, artist, album or label
+    pub search: Option<String>,
+}
+
+impl TrackFilter {
+    /// WHERE clause over `t` (tracks) and `a` (track_analysis), with its parameters
+    fn where_sql(&self) -> (String, Vec<rusqlite::types::Value>) {
+        use rusqlite::types::Value;
+        let mut conditions = vec!["1".to_string()];
+        let mut values: Vec<Value> = Vec::new();
+        let placeholders = |n: usize| vec!["?"; n].join(", ");
+
+        if !self.genres.is_empty() {
+            conditions.push(format!("t.genre COLLATE NOCASE IN ({})", placeholders(self.genres.len())));
+            values.extend(self.genres.iter().map(|g| Value::Text(g.trim().to_string())));
+        }
+        if !self.keys.is_empty() {
+            conditions.push(format!("a.musical_key COLLATE NOCASE IN ({})", placeholders(self.keys.len())));
+            values.extend(self.keys.iter().map(|k| Value::Text(k.trim().to_string())));
+        }
+        if let Some(min) = self.min_bpm {
+            conditions.push("a.bpm >= ?".to_string());
+            values.push(Value::Real(min));
+        }
+        if let Some(max) = self.max_bpm {
+            conditions.push("a.bpm <= ?".to_string());
+            values.push(Value::Real(max));
+        }
+        if let Some(min) = self.min_rating {
+            conditions.push("t.rating >= ?".to_string());
+            values.push(Value::Integer(min.into()));
+        }
+        if let Some(search) = self.search.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            conditions.push(
+                "(t.title LIKE ? COLLATE NOCASE OR t.artist LIKE ? COLLATE NOCASE
+                  OR t.album LIKE ? COLLATE NOCASE OR t.label LIKE ? COLLATE NOCASE
+                  OR t.search_text LIKE ?)"
+                    .to_string(),
+            );
+            let pattern = format!("%{}%", search);
+            values.extend(std::iter::repeat_n(Value::Text(pattern), 4));
+            values.push(search_text::like_pattern(search).map_or(Value::Null, Value::Text));
+        }
+        (conditions.join(" AND "), values)
+    }
+}
+
+/// Header of a group of tracks (see get_track_groups)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackGroup {
+    /// None for the tracks without a value (no album, not analyzed...)
+    pub value: Option<String>,
+    pub track_count: i64,
+    pub total_duration_ms: i64,
+    pub min_bpm: Option<f64>,
+    pub avg_bpm: Option<f64>,
+    pub max_bpm: Option<f64>,
+    pub total_plays: i64,
+    /// Newest date_added in the group
+    pub last_added: Option<String>,
+}
+
+/// Confidence stored for BPM/key values read from file tags
+pub const TAG_VALUE_CONFIDENCE: f64 = 0.99;
+/// Setting key: which BPM/key source wins when both exist ("tag" or "analysis")
+pub const ANALYSIS_PRIORITY_SETTING: &str = "analysis_priority";
+/// Setting key: "true" to sort artist/title/album ignoring a leading "The"/"A"/"An"
+pub const SORT_IGNORE_ARTICLES_SETTING: &str = "sort_ignore_articles";
+/// Source of BPM/key/energy values read from generic file tags
+pub const TAG_SOURCE: &str = "tag";
+/// Source of values written into the tags by Mixed In Key (recognized by its fields)
+pub const MIXED_IN_KEY_SOURCE: &str = "mixedinkey";
+/// Source of cue points read from Serato's markers in file tags
+pub const SERATO_SOURCE: &str = "serato";
+/// file_hash placeholder for tracks scanned in lazy hash mode (hash computed on demand)
+pub const PENDING_HASH: &str = "pending";
+
+/// track_analysis columns kept in analysis_cache
+const ANALYSIS_CACHE_COLUMNS: [&str; 20] = [
+    "bpm", "bpm_confidence", "bpm_source", "musical_key", "key_confidence", "key_source",
+    "loudness_lufs", "dynamic_range", "spectral_centroid", "waveform_overview", "waveform_detail",
+    "silence_lead_ms", "silence_tail_ms", "tempo_curve", "tempo_variable",
+    "quality_flags", "quality_cutoff_hz", "quality_hum_hz", "quality_dc_offset", "analyzed_at",
+];
+
+/// tracks columns read into a Track, in Track::from_row order. Queries select these first
+/// (track_columns), so extra columns start at index TRACK_COLUMNS.len().
+const TRACK_COLUMNS: [&str; 29] = [
+    "id", "file_path", "file_hash", "title", "artist", "album", "album_artist",
+    "track_number", "year", "label", "duration_ms", "file_format",
+    "bitrate", "sample_rate", "file_size", "date_added", "date_modified",
+    "play_count", "rating", "comment", "artwork_path", "genre", "genre_source",
+    "energy_level", "color", "disc_number", "total_tracks", "isrc", "catalog_number",
+];
+
+/// TRACK_COLUMNS as a SELECT list, qualified with a table alias unless `alias` is empty
+fn track_columns(alias: &str) -> String {
+    if alias.is_empty() {
+        return TRACK_COLUMNS.join(", ");
+    }
+    TRACK_COLUMNS
+        .iter()
+        .map(|c| format!("{}.{}", alias, c))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// SELECT list for TrackWithAnalysis rows: `tracks t` columns, then BPM/key from
+/// `track_analysis a` (LEFT JOINed)
+fn track_with_analysis_columns() -> String {
+    format!("{}, a.bpm, a.bpm_confidence, a.musical_key, a.key_confidence", track_columns("t"))
+}
+
+/// Title + artist reduced to what identifies the song across versions: bracketed
+/// parts ("(Extended Mix)", "[Remastered]") dropped, lowercase letters and digits only.
+/// None if either is missing.
+pub fn song_key(title: Option<&str>, artist: Option<&str>) -> Option<String> {
+    fn reduce(text: &str) -> String {
+        let mut depth = 0usize;
+        let mut out = String::new();
+        for c in text.chars() {
+            match c {
+                '(' | '[' => depth += 1,
+                ')' | ']' => depth = depth.saturating_sub(1),
+                _ if depth == 0 && c.is_alphanumeric() => out.extend(c.to_lowercase()),
+                _ => {}
+            }
+        }
+        out
+    }
+    let (title, artist) = (reduce(title?), reduce(artist?));
+    if title.is_empty() || artist.is_empty() {
+        return None;
+    }
+    Some(format!("{}\0{}", artist, title))
+}
+
+/// Whether a BPM/key/energy source means "read from file tags" (as opposed to
+/// analyzed by RecoDeck or set by the user)
+pub fn is_tag_source(source: Option<&str>) -> bool {
+    matches!(source, Some(TAG_SOURCE) | Some(MIXED_IN_KEY_SOURCE))
+}
+
+/// Whether an error means another connection holds the database lock
+pub fn is_busy(error: &rusqlite::Error) -> bool {
+    matches!(
+        error,
+        rusqlite::Error::SqliteFailure(e, _)
+            if e.code == ErrorCode::DatabaseBusy || e.code == ErrorCode::DatabaseLocked
+    )
+}
+
+/// LIKE pattern matching everything that starts with `prefix` (use with ESCAPE '\').
+/// Paths may contain LIKE wildcards (e.g. "Deep_House").
+fn like_prefix(prefix: &str) -> String {
+    format!("{}%", prefix.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"))
+}
+
+/// Size and mtime recorded for a track's file at its last scan
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileStat {
+    pub track_id: i64,
+    pub size: Option<i64>,
+    /// Unix seconds; None for tracks scanned before mtimes were recorded
+    pub mtime: Option<i64>,
+}
 
 /// Represents a playlist or playlist folder in the database.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Playlist {
     pub id: Option<i64>,
     pub name: String,
-    pub playlist_type: String, // "manual", "smart", "folder"
+    pub playlist_type: String, // "manual", "smart", "folder", "mirror"
     pub parent_id: Option<i64>,
     pub smart_rules: Option<String>,
     pub ai_prompt: Option<String>,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
+    /// On-disk folder this playlist mirrors (only for type "mirror")
+    pub source_folder: Option<String>,
 }
 
 /// Represents DSP analysis results for a track (from the track_analysis table).
@@ -29,6 +369,10 @@ pub struct TrackAnalysis {
     pub dynamic_range: Option<f64>,
     pub spectral_centroid: Option<f64>,
     pub analyzed_at: Option<String>,
+    pub silence_lead_ms: Option<i64>,
+    pub silence_tail_ms: Option<i64>,
+    /// Set once a tempo curve has been analyzed: true if the tempo drifts
+    pub tempo_variable: Option<bool>,
 }
 
 /// Represents a track in the database
@@ -57,6 +401,305 @@ pub struct Track {
     pub artwork_path: Option<String>,
     pub genre: Option<String>,
     pub genre_source: Option<String>, // 'user', 'tag', 'ai'
+    pub energy_level: Option<i32>,    // 1-10, from comment/custom tags or set manually
+    pub color: Option<String>,        // "#RRGGBB", user-assigned
+    pub disc_number: Option<i32>,
+    pub total_tracks: Option<i32>,    // tracks on the disc (or album, if the tag has no disc split)
+    pub isrc: Option<String>,
+    pub catalog_number: Option<String>,
+}
+
+impl Track {
+    /// Read a track from a row starting with the track_columns
+    fn from_row(row: &rusqlite::Row) -> Result<Track> {
+        Ok(Track {
+            id: row.get(0)?,
+            file_path: row.get(1)?,
+            file_hash: row.get(2)?,
+            title: row.get(3)?,
+            artist: row.get(4)?,
+            album: row.get(5)?,
+            album_artist: row.get(6)?,
+            track_number: row.get(7)?,
+            year: row.get(8)?,
+            label: row.get(9)?,
+            duration_ms: row.get(10)?,
+            file_format: row.get(11)?,
+            bitrate: row.get(12)?,
+            sample_rate: row.get(13)?,
+            file_size: row.get(14)?,
+            date_added: row.get(15)?,
+            date_modified: row.get(16)?,
+            play_count: row.get(17)?,
+            rating: row.get(18)?,
+            comment: row.get(19)?,
+            artwork_path: row.get(20)?,
+            genre: row.get(21)?,
+            genre_source: row.get(22)?,
+            energy_level: row.get(23)?,
+            color: row.get(24)?,
+            disc_number: row.get(25)?,
+            total_tracks: row.get(26)?,
+            isrc: row.get(27)?,
+            catalog_number: row.get(28)?,
+        })
+    }
+}
+
+/// A track with its BPM, BPM confidence, musical key and key confidence (None when not analyzed)
+pub type TrackWithAnalysis = (Track, Option<f64>, Option<f64>, Option<String>, Option<f64>);
+
+/// Read a row selected with track_with_analysis_columns
+fn track_with_analysis_from_row(row: &rusqlite::Row) -> Result<TrackWithAnalysis> {
+    let track = Track::from_row(row)?;
+    let n = TRACK_COLUMNS.len();
+    Ok((track, row.get(n)?, row.get(n + 1)?, row.get(n + 2)?, row.get(n + 3)?))
+}
+
+/// BPM range and keys typical of a genre in the library (see migration 032)
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenreProfile {
+    pub genre: String,
+    /// Tracks of the genre the profile was learned from
+    pub track_count: i64,
+    pub min_bpm: Option<f64>,
+    pub median_bpm: Option<f64>,
+    pub max_bpm: Option<f64>,
+    /// Camelot keys, most common first
+    pub common_keys: Vec<String>,
+}
+
+/// A track's genre, BPM and key, as read for learning genre profiles
+pub type GenreSample = (String, Option<f64>, Option<String>);
+
+/// Recovery marker for a scan/analysis batch job (see migration 010)
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchMarker {
+    pub job: String,
+    pub detail: Option<String>,
+    pub processed: i64,
+    pub total: Option<i64>,
+    pub started_at: String,
+    pub updated_at: String,
+}
+
+/// One play_history row
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayRecord {
+    pub id: i64,
+    pub track_id: i64,
+    pub played_at: String,
+    /// played_at as Unix seconds, for gap arithmetic
+    pub played_at_secs: i64,
+    pub skipped: bool,
+}
+
+/// A track's listening history and cooldown, as given to the AI (plays are tracks.play_count)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayStats {
+    pub skips: i64,
+    /// Whole days since the last play; None if never played
+    pub days_since_played: Option<i64>,
+    /// Active cooldown date ('YYYY-MM-DD')
+    pub cooldown_until: Option<String>,
+}
+
+/// A track whose file was seen deleted (see migration 033)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingTrack {
+    pub track_id: i64,
+    pub file_path: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub missing_since: String,
+}
+
+/// AI tokens used by one feature in one month (see migration 034)
+#[derive(Debug, Clone, PartialEq)]
+pub struct AiUsage {
+    /// "YYYY-MM" (UTC)
+    pub month: String,
+    pub purpose: String,
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+}
+
+/// A track whose analysis keeps failing (see migration 036)
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalysisFailure {
+    pub track_id: i64,
+    pub file_path: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub error_class: String,
+    pub message: String,
+    /// Failures in a row since the file last changed
+    pub attempts: i64,
+    pub last_failed_at: String,
+}
+
+/// A background library change that can be undone (see migration 033)
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    pub id: i64,
+    pub operation: String,
+    pub description: String,
+    /// JSON; its shape depends on `operation`
+    pub payload: String,
+    pub created_at: String,
+    pub undone_at: Option<String>,
+}
+
+/// A column value as JSON, for track snapshots (blobs as {"hex": "..."})
+fn sql_to_json(value: rusqlite::types::ValueRef) -> serde_json::Value {
+    use rusqlite::types::ValueRef;
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => f.into(),
+        ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned().into(),
+        ValueRef::Blob(blob) => {
+            let hex: String = blob.iter().map(|b| format!("{:02x}", b)).collect();
+            serde_json::json!({ "hex": hex })
+        }
+    }
+}
+
+/// Inverse of sql_to_json
+fn json_to_sql(value: &serde_json::Value) -> rusqlite::types::Value {
+    use rusqlite::types::Value;
+    match value {
+        serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => n.as_f64().map_or(Value::Null, Value::Real),
+        },
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        serde_json::Value::Object(map) => {
+            let hex = map.get("hex").and_then(|h| h.as_str()).unwrap_or("");
+            let blob: Option<Vec<u8>> = (0..hex.len())
+                .step_by(2)
+                .map(|i| hex.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+                .collect();
+            blob.map_or(Value::Null, Value::Blob)
+        }
+        _ => Value::Null,
+    }
+}
+
+/// Latest integrity check of a track's file (see migration 017)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackVerification {
+    pub track_id: i64,
+    pub status: String,
+    pub actual_hash: Option<String>,
+    pub verified_at: String,
+}
+
+/// Result of a track's technical quality check (see migration 027)
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityCheck {
+    pub track_id: i64,
+    /// audio::quality FLAG_* bits
+    pub flags: u32,
+    pub cutoff_hz: Option<f64>,
+    pub hum_hz: Option<f64>,
+    pub dc_offset: f64,
+}
+
+/// Auto-import rule for a watched folder (see migration 018)
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchRule {
+    pub id: i64,
+    pub folder: String,
+    pub tag: Option<String>,
+    pub playlist_name: Option<String>,
+    pub enabled: bool,
+}
+
+impl WatchRule {
+    /// Whether `path` lies inside this rule's folder (any depth)
+    pub fn matches(&self, path: &Path) -> bool {
+        path.starts_with(&self.folder)
+    }
+}
+
+/// A gig: where and when, and how long the set is (see migration 025)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gig {
+    pub id: i64,
+    pub name: String,
+    pub venue: Option<String>,
+    /// 'YYYY-MM-DD'
+    pub date: String,
+    pub set_length_minutes: i64,
+    pub notes: Option<String>,
+}
+
+/// A track's user metadata as exchanged by library sync (see migration 019)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackSyncState {
+    pub track_id: i64,
+    pub file_hash: String,
+    pub rating: i32,
+    pub color: Option<String>,
+    pub comment: Option<String>,
+    pub genre: Option<String>,
+    pub genre_source: Option<String>,
+    pub energy_level: Option<i32>,
+    pub metadata_updated_at: Option<String>,
+    pub cues_updated_at: Option<String>,
+}
+
+/// A playlist as exchanged by library sync: identified by sync_id, tracks by content hash
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaylistSyncState {
+    pub id: i64,
+    pub sync_id: String,
+    pub name: String,
+    pub playlist_type: String,
+    pub parent_sync_id: Option<String>,
+    pub smart_rules: Option<String>,
+    pub ai_prompt: Option<String>,
+    pub updated_at: Option<String>,
+    pub track_hashes: Vec<String>,
+}
+
+/// A playlist proposed from a listening session (see migration 014)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaylistDraft {
+    pub id: i64,
+    pub session_id: i64,
+    pub name: String,
+    pub track_ids: Vec<i64>,
+    pub created_at: String,
+}
+
+/// Where a track was bought (see migration 031)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackPurchase {
+    pub source: Option<String>,
+    /// YYYY-MM-DD
+    pub date: Option<String>,
+    pub order_id: Option<String>,
+}
+
+impl TrackPurchase {
+    pub fn is_empty(&self) -> bool {
+        self.source.is_none() && self.date.is_none() && self.order_id.is_none()
+    }
+}
+
+/// A playlist's tracks at some point (see migration 029)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaylistSnapshot {
+    pub id: i64,
+    pub playlist_id: i64,
+    pub track_ids: Vec<i64>,
+    /// "auto", "manual" or "restore"
+    pub reason: String,
+    pub created_at: String,
 }
 
 /// Represents a genre definition in the user's taxonomy
@@ -68,24 +711,147 @@ pub struct GenreDefinition {
     pub sort_order: i32,
 }
 
+/// A tag from the flexible tag system
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag {
+    pub id: Option<i64>,
+    pub name: String,
+    pub color: Option<String>,
+    pub category: Option<String>,
+}
+
+/// A user-defined track field (see migration 028)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomFieldDefinition {
+    pub id: i64,
+    pub name: String,
+    /// "text", "number", "boolean" or "date"
+    pub field_type: String,
+    pub sort_order: i32,
+}
+
+/// A cue point or loop marker on a track (from the cue_points table)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CuePoint {
+    pub id: Option<i64>,
+    pub track_id: i64,
+    pub position_ms: i64,
+    pub label: Option<String>,
+    pub color: Option<String>,
+    pub cue_type: String, // 'cue', 'loop_start', 'loop_end'
+}
+
+/// Analysis values that travel with the audio file in a sidecar (formats::sidecar)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PortableAnalysis {
+    pub bpm: Option<f64>,
+    pub bpm_confidence: Option<f64>,
+    pub musical_key: Option<String>,
+    pub key_confidence: Option<f64>,
+    pub silence_lead_ms: Option<i64>,
+    pub silence_tail_ms: Option<i64>,
+    pub waveform_overview: Option<Vec<u8>>,
+}
+
+/// A track's beat grid. `beats` holds (position_ms, beat_number_in_bar) pairs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeatGrid {
+    pub track_id: i64,
+    pub bpm: Option<f64>,
+    pub first_beat_ms: Option<i64>,
+    pub beats: Vec<(i64, u8)>,
+    pub source: String, // 'rekordbox', 'analysis'
+}
+
 /// Database connection wrapper
 pub struct Database {
     conn: Connection,
 }
 
 impl Database {
-    /// Create a new database connection
+    /// Create a new database connection.
+    /// Uses WAL journaling: a crash mid-batch can only lose uncommitted work, and
+    /// readers (e.g. the companion server) aren't blocked by long write batches.
+    /// Writers wait up to BUSY_TIMEOUT for each other instead of failing immediately.
     pub fn new(path: &Path) -> Result<Self> {
-        let conn = Connection::open(path)?;
+        #[allow(unused_mut)]
+        let mut conn = Connection::open(path)?;
+        #[cfg(feature = "perf-trace")]
+        conn.profile(Some(crate::perf::record_query));
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        collation::register(&conn)?;
         Ok(Database { conn })
     }
 
     /// Create an in-memory database (for testing)
     pub fn new_in_memory() -> Result<Self> {
         let conn = Connection::open_in_memory()?;
+        collation::register(&conn)?;
         Ok(Database { conn })
     }
 
+    /// The database file; None for an in-memory database
+    pub fn file_path(&self) -> Option<PathBuf> {
+        self.conn.path().filter(|path| !path.is_empty()).map(PathBuf::from)
+    }
+
+    /// Begin an explicit transaction. Every Database method called while it is
+    /// alive runs inside it; call commit() on the returned handle (dropping it
+    /// rolls back). Used to batch many small writes in scans and analysis.
+    pub fn transaction(&self) -> Result<rusqlite::Transaction<'_>> {
+        self.conn.unchecked_transaction()
+    }
+
+    /// Changes when anything was written to the database: rows changed through this
+    /// connection, and commits by other connections (the companion server's). Equal
+    /// stamps mean nothing was written in between.
+    pub fn change_stamp(&self) -> Result<(i64, i64)> {
+        self.conn.query_row(
+            "SELECT total_changes(), (SELECT data_version FROM pragma_data_version)",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+    }
+
+    /// Run a write, retrying with backoff while another connection holds the write lock
+    /// (SQLITE_BUSY/LOCKED after busy_timeout ran out, or a stale WAL snapshot, which
+    /// fails without waiting). `op` is re-run from the start, so wrap a whole
+    /// transaction, not a statement inside one. Each attempt waits at most
+    /// BUSY_RETRY_TIMEOUT for the lock, so the total wait stays bounded.
+    pub fn with_busy_retry<T>(&self, mut op: impl FnMut(&Database) -> Result<T>) -> Result<T> {
+        self.conn.busy_timeout(BUSY_RETRY_TIMEOUT)?;
+        let mut delay = BUSY_RETRY_DELAY;
+        let mut attempt = 1;
+        let result = loop {
+            match op(self) {
+                Err(e) if is_busy(&e) && attempt < BUSY_RETRIES => {
+                    eprintln!(
+                        "[db] Database busy, retrying in {}ms ({}/{})",
+                        delay.as_millis(),
+                        attempt,
+                        BUSY_RETRIES - 1
+                    );
+                    std::thread::sleep(delay);
+                    delay *= 2;
+                    attempt += 1;
+                }
+                result => break result,
+            }
+        };
+        self.conn.busy_timeout(BUSY_TIMEOUT)?;
+        result
+    }
+
+    /// Begin a transaction holding the write lock from the start (BEGIN IMMEDIATE), retried
+    /// like with_busy_retry while another connection writes. Writes inside it then can't
+    /// fail with SQLITE_BUSY halfway, so batches that log per-row errors and carry on (scans,
+    /// sync) use this instead of transaction().
+    pub fn begin_write(&self) -> Result<rusqlite::Transaction<'_>> {
+        self.with_busy_retry(|_| rusqlite::Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate))
+    }
+
     /// Run migrations to set up the database schema
     pub fn run_migrations(&self) -> Result<()> {
         // Run all migrations in order
@@ -117,121 +883,336 @@ impl Database {
             self.conn.execute_batch(migration_003)?;
         }
 
-        Ok(())
-    }
+        // Migration 005: Beat grids table (CREATE IF NOT EXISTS, safe to re-run)
+        let migration_005 = include_str!("migrations/005_beat_grids.sql");
+        self.conn.execute_batch(migration_005)?;
 
-    /// Create a new track
-    pub fn create_track(&self, track: &Track) -> Result<i64> {
-        self.conn.execute(
-            "INSERT INTO tracks (
-                file_path, file_hash, title, artist, album, album_artist,
-                track_number, year, label, duration_ms, file_format,
-                bitrate, sample_rate, file_size, date_modified,
-                play_count, rating, comment, artwork_path, genre, genre_source
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-            params![
-                track.file_path,
-                track.file_hash,
-                track.title,
-                track.artist,
-                track.album,
-                track.album_artist,
-                track.track_number,
-                track.year,
-                track.label,
-                track.duration_ms,
-                track.file_format,
-                track.bitrate,
-                track.sample_rate,
-                track.file_size,
-                track.date_modified,
-                track.play_count,
-                track.rating,
-                track.comment,
-                track.artwork_path,
-                track.genre,
-                track.genre_source,
-            ],
+        // Migration 006: source_folder column for folder-mirrored playlists
+        let has_source_folder: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('playlists') WHERE name = 'source_folder'",
+            [],
+            |row| row.get(0),
         )?;
-        Ok(self.conn.last_insert_rowid())
-    }
 
-    /// Read a track by ID
-    pub fn get_track(&self, id: i64) -> Result<Track> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, file_path, file_hash, title, artist, album, album_artist,
-                    track_number, year, label, duration_ms, file_format,
-                    bitrate, sample_rate, file_size, date_added, date_modified,
-                    play_count, rating, comment, artwork_path, genre, genre_source
-             FROM tracks WHERE id = ?"
-        )?;
+        if !has_source_folder {
+            let migration_006 = include_str!("migrations/006_folder_playlists.sql");
+            self.conn.execute_batch(migration_006)?;
+        }
 
-        stmt.query_row([id], |row| {
-            Ok(Track {
-                id: row.get(0)?,
-                file_path: row.get(1)?,
-                file_hash: row.get(2)?,
-                title: row.get(3)?,
-                artist: row.get(4)?,
-                album: row.get(5)?,
-                album_artist: row.get(6)?,
-                track_number: row.get(7)?,
-                year: row.get(8)?,
-                label: row.get(9)?,
-                duration_ms: row.get(10)?,
-                file_format: row.get(11)?,
-                bitrate: row.get(12)?,
-                sample_rate: row.get(13)?,
-                file_size: row.get(14)?,
-                date_added: row.get(15)?,
-                date_modified: row.get(16)?,
-                play_count: row.get(17)?,
-                rating: row.get(18)?,
-                comment: row.get(19)?,
-                artwork_path: row.get(20)?,
-                genre: row.get(21)?,
-                genre_source: row.get(22)?,
-            })
-        })
-    }
+        // Migration 007: Play history + popularity scores (CREATE IF NOT EXISTS, safe to re-run)
+        let migration_007 = include_str!("migrations/007_play_history.sql");
+        self.conn.execute_batch(migration_007)?;
 
-    /// Get all tracks
-    pub fn get_all_tracks(&self) -> Result<Vec<Track>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, file_path, file_hash, title, artist, album, album_artist,
-                    track_number, year, label, duration_ms, file_format,
-                    bitrate, sample_rate, file_size, date_added, date_modified,
-                    play_count, rating, comment, artwork_path, genre, genre_source
-             FROM tracks ORDER BY id"
+        // Migration 008: bpm_source / key_source columns on track_analysis
+        let has_key_source: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('track_analysis') WHERE name = 'key_source'",
+            [],
+            |row| row.get(0),
         )?;
 
-        let tracks = stmt.query_map([], |row| {
-            Ok(Track {
-                id: row.get(0)?,
-                file_path: row.get(1)?,
-                file_hash: row.get(2)?,
-                title: row.get(3)?,
-                artist: row.get(4)?,
-                album: row.get(5)?,
-                album_artist: row.get(6)?,
-                track_number: row.get(7)?,
-                year: row.get(8)?,
-                label: row.get(9)?,
-                duration_ms: row.get(10)?,
-                file_format: row.get(11)?,
-                bitrate: row.get(12)?,
-                sample_rate: row.get(13)?,
-                file_size: row.get(14)?,
-                date_added: row.get(15)?,
-                date_modified: row.get(16)?,
-                play_count: row.get(17)?,
-                rating: row.get(18)?,
-                comment: row.get(19)?,
-                artwork_path: row.get(20)?,
-                genre: row.get(21)?,
-                genre_source: row.get(22)?,
-            })
-        })?;
+        if !has_key_source {
+            let migration_008 = include_str!("migrations/008_analysis_sources.sql");
+            self.conn.execute_batch(migration_008)?;
+        }
+
+        // Migration 009: energy_level column on tracks
+        let has_energy_level: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('tracks') WHERE name = 'energy_level'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_energy_level {
+            let migration_009 = include_str!("migrations/009_energy_level.sql");
+            self.conn.execute_batch(migration_009)?;
+        }
+
+        // Migration 010: Batch job recovery markers (CREATE IF NOT EXISTS, safe to re-run)
+        let migration_010 = include_str!("migrations/010_batch_markers.sql");
+        self.conn.execute_batch(migration_010)?;
+
+        // Migration 011: file_mtime column on tracks
+        let has_file_mtime: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('tracks') WHERE name = 'file_mtime'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_file_mtime {
+            let migration_011 = include_str!("migrations/011_file_mtime.sql");
+            self.conn.execute_batch(migration_011)?;
+        }
+
+        // Migration 012: color column on tracks
+        let has_color: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('tracks') WHERE name = 'color'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_color {
+            let migration_012 = include_str!("migrations/012_track_color.sql");
+            self.conn.execute_batch(migration_012)?;
+        }
+
+        // Migration 013: silence_lead_ms / silence_tail_ms columns on track_analysis
+        let has_silence: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('track_analysis') WHERE name = 'silence_lead_ms'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_silence {
+            let migration_013 = include_str!("migrations/013_silence.sql");
+            self.conn.execute_batch(migration_013)?;
+        }
+
+        // Migration 014: Playlist drafts (CREATE IF NOT EXISTS, safe to re-run)
+        let migration_014 = include_str!("migrations/014_playlist_drafts.sql");
+        self.conn.execute_batch(migration_014)?;
+
+        // Migration 015: disc_number / total_tracks columns on tracks
+        let has_disc_number: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('tracks') WHERE name = 'disc_number'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_disc_number {
+            let migration_015 = include_str!("migrations/015_disc_number.sql");
+            self.conn.execute_batch(migration_015)?;
+        }
+
+        // Migration 016: isrc / catalog_number columns on tracks
+        let has_isrc: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('tracks') WHERE name = 'isrc'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_isrc {
+            let migration_016 = include_str!("migrations/016_isrc_catalog.sql");
+            self.conn.execute_batch(migration_016)?;
+        }
+
+        // Migration 017: Track verifications (CREATE IF NOT EXISTS, safe to re-run)
+        let migration_017 = include_str!("migrations/017_track_verifications.sql");
+        self.conn.execute_batch(migration_017)?;
+
+        // Migration 018: Watch folder rules (CREATE IF NOT EXISTS, safe to re-run)
+        let migration_018 = include_str!("migrations/018_watch_rules.sql");
+        self.conn.execute_batch(migration_018)?;
+
+        // Migration 019: sync ids / timestamps for library sync (+ triggers keeping them current)
+        let has_sync_id: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('playlists') WHERE name = 'sync_id'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_sync_id {
+            let migration_019 = include_str!("migrations/019_library_sync.sql");
+            self.conn.execute_batch(migration_019)?;
+        }
+
+        // Migration 020: energy_source column on tracks
+        let has_energy_source: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('tracks') WHERE name = 'energy_source'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_energy_source {
+            let migration_020 = include_str!("migrations/020_energy_source.sql");
+            self.conn.execute_batch(migration_020)?;
+        }
+
+        // Migration 021: tempo_curve / tempo_variable columns on track_analysis
+        let has_tempo_curve: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('track_analysis') WHERE name = 'tempo_curve'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_tempo_curve {
+            let migration_021 = include_str!("migrations/021_tempo_curve.sql");
+            self.conn.execute_batch(migration_021)?;
+        }
+
+        // Migration 022: Playlist audition marks (CREATE IF NOT EXISTS, safe to re-run)
+        let migration_022 = include_str!("migrations/022_playlist_auditions.sql");
+        self.conn.execute_batch(migration_022)?;
+
+        // Migration 023: rating_source on tracks, source on cue_points
+        let has_rating_source: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('tracks') WHERE name = 'rating_source'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_rating_source {
+            let migration_023 = include_str!("migrations/023_tag_annotations.sql");
+            self.conn.execute_batch(migration_023)?;
+        }
+
+        // Migration 024: cooldown_until on tracks
+        let has_cooldown: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('tracks') WHERE name = 'cooldown_until'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_cooldown {
+            let migration_024 = include_str!("migrations/024_track_cooldown.sql");
+            self.conn.execute_batch(migration_024)?;
+        }
+
+        // Migration 025: Gigs and their playlists (CREATE IF NOT EXISTS, safe to re-run)
+        let migration_025 = include_str!("migrations/025_gigs.sql");
+        self.conn.execute_batch(migration_025)?;
+
+        // Migration 026: Sub-fingerprints for set identification (CREATE IF NOT EXISTS, safe to re-run)
+        let migration_026 = include_str!("migrations/026_track_prints.sql");
+        self.conn.execute_batch(migration_026)?;
+
+        // Migration 027: quality_* columns on track_analysis
+        let has_quality_flags: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('track_analysis') WHERE name = 'quality_flags'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_quality_flags {
+            let migration_027 = include_str!("migrations/027_quality_flags.sql");
+            self.conn.execute_batch(migration_027)?;
+        }
+
+        // Migration 028: Custom field definitions and values (CREATE IF NOT EXISTS, safe to re-run)
+        let migration_028 = include_str!("migrations/028_custom_fields.sql");
+        self.conn.execute_batch(migration_028)?;
+
+        // Migration 029: Playlist snapshots (CREATE IF NOT EXISTS, safe to re-run)
+        let migration_029 = include_str!("migrations/029_playlist_snapshots.sql");
+        self.conn.execute_batch(migration_029)?;
+
+        // Migration 030: Analysis cache by content hash (CREATE IF NOT EXISTS, safe to re-run)
+        let migration_030 = include_str!("migrations/030_analysis_cache.sql");
+        self.conn.execute_batch(migration_030)?;
+
+        // Migration 031: purchase_* columns on tracks
+        let has_purchase_source: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('tracks') WHERE name = 'purchase_source'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_purchase_source {
+            let migration_031 = include_str!("migrations/031_purchase_info.sql");
+            self.conn.execute_batch(migration_031)?;
+        }
+
+        // Migration 032: Genre profiles (CREATE IF NOT EXISTS, safe to re-run)
+        let migration_032 = include_str!("migrations/032_genre_profiles.sql");
+        self.conn.execute_batch(migration_032)?;
+
+        // Migration 033: tracks.missing_since and the operation journal
+        let has_missing_since: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('tracks') WHERE name = 'missing_since'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_missing_since {
+            let migration_033 = include_str!("migrations/033_missing_tracks_journal.sql");
+            self.conn.execute_batch(migration_033)?;
+        }
+
+        // Migration 034: AI token usage per month
+        let migration_034 = include_str!("migrations/034_ai_usage.sql");
+        self.conn.execute_batch(migration_034)?;
+
+        // Migration 035: tracks.search_text (transliterated search)
+        let has_search_text: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('tracks') WHERE name = 'search_text'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_search_text {
+            let migration_035 = include_str!("migrations/035_search_text.sql");
+            self.conn.execute_batch(migration_035)?;
+        }
+
+        // Migration 036: Failed analyses (CREATE IF NOT EXISTS, safe to re-run)
+        let migration_036 = include_str!("migrations/036_analysis_errors.sql");
+        self.conn.execute_batch(migration_036)?;
+
+        Ok(())
+    }
+
+    /// Create a new track
+    pub fn create_track(&self, track: &Track) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO tracks (
+                file_path, file_hash, title, artist, album, album_artist,
+                track_number, year, label, duration_ms, file_format,
+                bitrate, sample_rate, file_size, date_modified,
+                play_count, rating, comment, artwork_path, genre, genre_source, energy_level, color,
+                disc_number, total_tracks, isrc, catalog_number
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                track.file_path,
+                track.file_hash,
+                track.title,
+                track.artist,
+                track.album,
+                track.album_artist,
+                track.track_number,
+                track.year,
+                track.label,
+                track.duration_ms,
+                track.file_format,
+                track.bitrate,
+                track.sample_rate,
+                track.file_size,
+                track.date_modified,
+                track.play_count,
+                track.rating,
+                track.comment,
+                track.artwork_path,
+                track.genre,
+                track.genre_source,
+                track.energy_level,
+                track.color,
+                track.disc_number,
+                track.total_tracks,
+                track.isrc,
+                track.catalog_number,
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Read a track by ID
+    pub fn get_track(&self, id: i64) -> Result<Track> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}
+             FROM tracks WHERE id = ?",
+            track_columns("")
+        ))?;
+
+        stmt.query_row([id], Track::from_row)
+    }
+
+    /// Get all tracks
+    pub fn get_all_tracks(&self) -> Result<Vec<Track>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}
+             FROM tracks ORDER BY id",
+            track_columns("")
+        ))?;
+
+        let tracks = stmt.query_map([], Track::from_row)?;
 
         tracks.collect()
     }
@@ -242,6 +1223,12 @@ impl Database {
             rusqlite::Error::InvalidParameterName("Track ID is required for update".to_string())
         })?;
 
+        // A changed rating is the user's from now on (tag imports won't replace it)
+        self.conn.execute(
+            "UPDATE tracks SET rating_source = 'user' WHERE id = ? AND rating != ?",
+            params![id, track.rating],
+        )?;
+
         self.conn.execute(
             "UPDATE tracks SET
                 file_path = ?, file_hash = ?, title = ?, artist = ?,
@@ -249,7 +1236,8 @@ impl Database {
                 label = ?, duration_ms = ?, file_format = ?, bitrate = ?,
                 sample_rate = ?, file_size = ?, date_modified = ?,
                 play_count = ?, rating = ?, comment = ?, artwork_path = ?,
-                genre = ?, genre_source = ?
+                genre = ?, genre_source = ?, energy_level = ?, color = ?,
+                disc_number = ?, total_tracks = ?, isrc = ?, catalog_number = ?
              WHERE id = ?",
             params![
                 track.file_path,
@@ -273,321 +1261,1901 @@ impl Database {
                 track.artwork_path,
                 track.genre,
                 track.genre_source,
+                track.energy_level,
+                track.color,
+                track.disc_number,
+                track.total_tracks,
+                track.isrc,
+                track.catalog_number,
                 id,
             ],
         )?;
         Ok(())
     }
 
-    /// Delete a track by ID
-    pub fn delete_track(&self, id: i64) -> Result<()> {
-        self.conn.execute("DELETE FROM tracks WHERE id = ?", [id])?;
+    /// Set or clear a track's color ("#RRGGBB")
+    pub fn set_track_color(&self, track_id: i64, color: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET color = ? WHERE id = ?",
+            params![color, track_id],
+        )?;
+        Ok(())
+    }
+
+    /// Set or clear a track's cooldown date ('YYYY-MM-DD')
+    pub fn set_track_cooldown(&self, track_id: i64, until: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET cooldown_until = ? WHERE id = ?",
+            params![until, track_id],
+        )?;
+        Ok(())
+    }
+
+    /// Tracks whose cooldown hasn't ended yet (cooldown date after today), with the date
+    pub fn get_active_cooldowns(&self) -> Result<HashMap<i64, String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, cooldown_until FROM tracks
+             WHERE cooldown_until IS NOT NULL AND cooldown_until > date('now', 'localtime')",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Every stored BPM with its track's genre, as (track_id, genre, bpm)
+    pub fn get_bpms_with_genre(&self) -> Result<Vec<(i64, Option<String>, f64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.id, t.genre, a.bpm
+             FROM tracks t JOIN track_analysis a ON a.track_id = t.id
+             WHERE a.bpm IS NOT NULL
+             ORDER BY t.id"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    /// (track ID, BPM, confidence) of every BPM computed by analysis (not read from tags)
+    pub fn get_analyzed_bpms(&self) -> Result<Vec<(i64, f64, f64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT track_id, bpm, COALESCE(bpm_confidence, 0)
+             FROM track_analysis
+             WHERE bpm IS NOT NULL AND (bpm_source IS NULL OR bpm_source = 'analysis')
+             ORDER BY track_id"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    /// Replace a stored BPM, keeping its confidence and source (octave corrections)
+    pub fn set_bpm(&self, track_id: i64, bpm: f64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE track_analysis SET bpm = ? WHERE track_id = ?",
+            params![bpm, track_id],
+        )?;
+        Ok(())
+    }
+
+    /// Set or clear a track's energy level
+    pub fn set_track_energy(&self, track_id: i64, energy_level: Option<i32>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET energy_level = ?1, energy_source = CASE WHEN ?1 IS NULL THEN NULL ELSE 'user' END
+             WHERE id = ?2",
+            params![energy_level, track_id],
+        )?;
+        Ok(())
+    }
+
+    /// Delete a track by ID, with all its per-track data (see delete_track_data)
+    pub fn delete_track(&self, id: i64) -> Result<()> {
+        // In one transaction, unless the caller already is in one
+        let tx = if self.conn.is_autocommit() { Some(self.transaction()?) } else { None };
+        self.delete_track_data(id)?;
+        if let Some(tx) = tx {
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Delete a track with its analysis, cues, playlist entries, history and every other
+    /// per-track row. The analysis goes to analysis_cache. Track IDs are reused, so rows
+    /// left behind would end up on the next imported track. Run inside a transaction.
+    pub fn delete_track_data(&self, track_id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM track_analysis WHERE track_id = ?", [track_id])?;
+        for table in TRACK_DATA_TABLES {
+            self.conn.execute(&format!("DELETE FROM {} WHERE track_id = ?", table), [track_id])?;
+        }
+        self.conn.execute("DELETE FROM tracks WHERE id = ?", [track_id])?;
+        Ok(())
+    }
+
+    /// A track's rows in tracks and TRACK_DATA_TABLES as JSON ({"tracks": [row],
+    /// "cue_points": [rows], ...}), for restoring it with restore_track after
+    /// delete_track_data. Row IDs of the other tables are left out.
+    pub fn snapshot_track(&self, track_id: i64) -> Result<serde_json::Value> {
+        let mut snapshot = serde_json::Map::new();
+        let key_columns = std::iter::once(("tracks", "id")).chain(TRACK_DATA_TABLES.iter().map(|t| (*t, "track_id")));
+        for (table, key_column) in key_columns {
+            let mut stmt = self.conn.prepare(&format!("SELECT * FROM {} WHERE {} = ?", table, key_column))?;
+            let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+            let mut rows = stmt.query([track_id])?;
+            let mut table_rows = Vec::new();
+            while let Some(row) = rows.next()? {
+                let mut object = serde_json::Map::new();
+                for (i, column) in columns.iter().enumerate() {
+                    if table != "tracks" && column == "id" {
+                        continue;
+                    }
+                    object.insert(column.clone(), sql_to_json(row.get_ref(i)?));
+                }
+                table_rows.push(serde_json::Value::Object(object));
+            }
+            if !table_rows.is_empty() {
+                snapshot.insert(table.to_string(), serde_json::Value::Array(table_rows));
+            }
+        }
+        Ok(serde_json::Value::Object(snapshot))
+    }
+
+    /// Put back a track from snapshot_track, with its analysis from analysis_cache if still
+    /// there. It gets its old ID unless that was reused. Returns the track ID, or None if
+    /// the snapshot has no track or another track has its file path by now.
+    /// Run inside a transaction.
+    pub fn restore_track(&self, snapshot: &serde_json::Value) -> Result<Option<i64>> {
+        let Some(track) = snapshot.get("tracks").and_then(|rows| rows.get(0)).and_then(|row| row.as_object()) else {
+            return Ok(None);
+        };
+        let (Some(old_id), Some(file_path)) = (
+            track.get("id").and_then(|id| id.as_i64()),
+            track.get("file_path").and_then(|path| path.as_str()),
+        ) else {
+            return Ok(None);
+        };
+        if self.find_track_id_by_path(file_path)?.is_some() {
+            return Ok(None);
+        }
+        let id_taken: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM tracks WHERE id = ?",
+            [old_id],
+            |row| row.get(0),
+        )?;
+        let mut track = track.clone();
+        if id_taken {
+            track.remove("id");
+        }
+        self.insert_json_row("tracks", &track)?;
+        let track_id = if id_taken { self.conn.last_insert_rowid() } else { old_id };
+
+        for table in TRACK_DATA_TABLES {
+            let rows = snapshot.get(table).and_then(|rows| rows.as_array()).into_iter().flatten();
+            for row in rows.filter_map(|row| row.as_object()) {
+                let mut row = row.clone();
+                row.insert("track_id".to_string(), track_id.into());
+                self.insert_json_row(table, &row)?;
+            }
+        }
+        if let Some(file_hash) = track.get("file_hash").and_then(|hash| hash.as_str()) {
+            self.restore_cached_analysis(track_id, file_hash)?;
+        }
+        Ok(Some(track_id))
+    }
+
+    /// Insert a snapshot row; keys that aren't columns of `table` (any more) are skipped
+    fn insert_json_row(&self, table: &str, row: &serde_json::Map<String, serde_json::Value>) -> Result<()> {
+        let mut stmt = self.conn.prepare("SELECT name FROM pragma_table_info(?)")?;
+        let columns: HashSet<String> = stmt.query_map([table], |r| r.get(0))?.collect::<Result<_>>()?;
+        let (names, values): (Vec<&String>, Vec<rusqlite::types::Value>) = row
+            .iter()
+            .filter(|(column, _)| columns.contains(*column))
+            .map(|(column, value)| (column, json_to_sql(value)))
+            .unzip();
+        if names.is_empty() {
+            return Ok(());
+        }
+        let column_list = names.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ");
+        let placeholders = vec!["?"; names.len()].join(", ");
+        self.conn.execute(
+            &format!("INSERT OR IGNORE INTO {} ({}) VALUES ({})", table, column_list, placeholders),
+            rusqlite::params_from_iter(values),
+        )?;
+        Ok(())
+    }
+
+    // --- Missing files (see migration 033) ---
+
+    /// Tracks whose file is `path` or lies under it (a deleted folder), as (ID, file path)
+    pub fn get_track_paths_under(&self, path: &str) -> Result<Vec<(i64, String)>> {
+        let prefix = format!("{}{}", path.trim_end_matches(['/', '\\']), std::path::MAIN_SEPARATOR);
+        let pattern = like_prefix(&prefix);
+        let mut stmt = self.conn.prepare(
+            "SELECT id, file_path FROM tracks WHERE file_path = ?1 OR file_path LIKE ?2 ESCAPE '\\'
+             ORDER BY id",
+        )?;
+        let rows = stmt.query_map(params![path, pattern], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Flag tracks as missing. Returns the IDs that weren't flagged already.
+    pub fn mark_tracks_missing(&self, track_ids: &[i64]) -> Result<Vec<i64>> {
+        let mut marked = Vec::new();
+        for &track_id in track_ids {
+            let changed = self.conn.execute(
+                "UPDATE tracks SET missing_since = datetime('now') WHERE id = ? AND missing_since IS NULL",
+                [track_id],
+            )?;
+            if changed > 0 {
+                marked.push(track_id);
+            }
+        }
+        Ok(marked)
+    }
+
+    /// Clear the missing flag (the file is back). Returns how many tracks had it.
+    pub fn clear_tracks_missing(&self, track_ids: &[i64]) -> Result<usize> {
+        let mut cleared = 0;
+        for &track_id in track_ids {
+            cleared += self.conn.execute(
+                "UPDATE tracks SET missing_since = NULL WHERE id = ? AND missing_since IS NOT NULL",
+                [track_id],
+            )?;
+        }
+        Ok(cleared)
+    }
+
+    /// Tracks flagged missing, most recently first
+    pub fn get_missing_tracks(&self) -> Result<Vec<MissingTrack>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, file_path, title, artist, missing_since FROM tracks
+             WHERE missing_since IS NOT NULL ORDER BY missing_since DESC, id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(MissingTrack {
+                track_id: row.get(0)?,
+                file_path: row.get(1)?,
+                title: row.get(2)?,
+                artist: row.get(3)?,
+                missing_since: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    // --- AI usage (see migration 034) ---
+
+    /// Add requests and tokens to this month's tally for `purpose`
+    pub fn record_ai_usage(&self, purpose: &str, requests: i64, input_tokens: i64, output_tokens: i64) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO ai_usage (month, purpose, requests, input_tokens, output_tokens)
+             VALUES (strftime('%Y-%m', 'now'), ?1, ?2, ?3, ?4)
+             ON CONFLICT(month, purpose) DO UPDATE SET
+                requests = requests + excluded.requests,
+                input_tokens = input_tokens + excluded.input_tokens,
+                output_tokens = output_tokens + excluded.output_tokens",
+            params![purpose, requests, input_tokens, output_tokens],
+        )?;
+        Ok(())
+    }
+
+    /// Usage of the latest `months` months with any, newest month first
+    pub fn get_ai_usage(&self, months: i64) -> Result<Vec<AiUsage>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT month, purpose, requests, input_tokens, output_tokens FROM ai_usage
+             WHERE month IN (SELECT DISTINCT month FROM ai_usage ORDER BY month DESC LIMIT ?)
+             ORDER BY month DESC, purpose",
+        )?;
+        let rows = stmt.query_map([months], |row| {
+            Ok(AiUsage {
+                month: row.get(0)?,
+                purpose: row.get(1)?,
+                requests: row.get(2)?,
+                input_tokens: row.get(3)?,
+                output_tokens: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    // --- Failed analyses (see migration 036) ---
+
+    /// Record a failed analysis of a track. The attempt count goes up if the file is
+    /// unchanged since the last failure and starts over at 1 otherwise. Returns it.
+    pub fn record_analysis_failure(&self, track_id: i64, error_class: &str, message: &str) -> Result<i64> {
+        self.conn.query_row(
+            "INSERT INTO analysis_errors (track_id, file_hash, error_class, message)
+             SELECT id, file_hash, ?2, ?3 FROM tracks WHERE id = ?1
+             ON CONFLICT(track_id) DO UPDATE SET
+                attempts = CASE WHEN file_hash = excluded.file_hash THEN attempts + 1 ELSE 1 END,
+                first_failed_at = CASE WHEN file_hash = excluded.file_hash
+                    THEN first_failed_at ELSE excluded.first_failed_at END,
+                file_hash = excluded.file_hash,
+                error_class = excluded.error_class,
+                message = excluded.message,
+                last_failed_at = excluded.last_failed_at
+             RETURNING attempts",
+            params![track_id, error_class, message],
+            |row| row.get(0),
+        )
+    }
+
+    /// Forget a track's failures (after it was analyzed)
+    pub fn clear_analysis_failure(&self, track_id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM analysis_errors WHERE track_id = ?", [track_id])?;
+        Ok(())
+    }
+
+    /// Tracks whose analysis failed, most recent first. Failures from before the file
+    /// last changed are left out.
+    pub fn get_analysis_failures(&self) -> Result<Vec<AnalysisFailure>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT e.track_id, t.file_path, t.title, t.artist, e.error_class, e.message,
+                    e.attempts, e.last_failed_at
+             FROM analysis_errors e JOIN tracks t ON t.id = e.track_id AND t.file_hash = e.file_hash
+             ORDER BY e.last_failed_at DESC, e.track_id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(AnalysisFailure {
+                track_id: row.get(0)?,
+                file_path: row.get(1)?,
+                title: row.get(2)?,
+                artist: row.get(3)?,
+                error_class: row.get(4)?,
+                message: row.get(5)?,
+                attempts: row.get(6)?,
+                last_failed_at: row.get(7)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Tracks that failed at least `max_attempts` times in a row on their current file
+    pub fn get_analysis_skipped_tracks(&self, max_attempts: i64) -> Result<HashSet<i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT e.track_id FROM analysis_errors e
+             JOIN tracks t ON t.id = e.track_id AND t.file_hash = e.file_hash
+             WHERE e.attempts >= ?",
+        )?;
+        let rows = stmt.query_map([max_attempts], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// Forget the failures of `track_ids` (all tracks if None). Returns the tracks cleared.
+    pub fn reset_analysis_failures(&self, track_ids: Option<&[i64]>) -> Result<Vec<i64>> {
+        let failed: Vec<i64> = self
+            .get_analysis_failures()?
+            .into_iter()
+            .map(|f| f.track_id)
+            .filter(|id| track_ids.is_none_or(|ids| ids.contains(id)))
+            .collect();
+        for id in &failed {
+            self.clear_analysis_failure(*id)?;
+        }
+        Ok(failed)
+    }
+
+    // --- Operation journal (see migration 033) ---
+
+    /// Record an operation; only the newest JOURNAL_ENTRIES_KEPT are kept. Returns its ID.
+    pub fn add_journal_entry(&self, operation: &str, description: &str, payload: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO operation_journal (operation, description, payload) VALUES (?, ?, ?)",
+            params![operation, description, payload],
+        )?;
+        let id = self.conn.last_insert_rowid();
+        self.conn.execute(
+            "DELETE FROM operation_journal WHERE id NOT IN
+             (SELECT id FROM operation_journal ORDER BY id DESC LIMIT ?)",
+            [JOURNAL_ENTRIES_KEPT],
+        )?;
+        Ok(id)
+    }
+
+    fn row_to_journal_entry(row: &rusqlite::Row) -> Result<JournalEntry> {
+        Ok(JournalEntry {
+            id: row.get(0)?,
+            operation: row.get(1)?,
+            description: row.get(2)?,
+            payload: row.get(3)?,
+            created_at: row.get(4)?,
+            undone_at: row.get(5)?,
+        })
+    }
+
+    /// Newest entries first
+    pub fn get_journal_entries(&self, limit: i64) -> Result<Vec<JournalEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, operation, description, payload, created_at, undone_at
+             FROM operation_journal ORDER BY id DESC LIMIT ?",
+        )?;
+        let rows = stmt.query_map([limit], Self::row_to_journal_entry)?;
+        rows.collect()
+    }
+
+    pub fn get_journal_entry(&self, id: i64) -> Result<JournalEntry> {
+        self.conn.query_row(
+            "SELECT id, operation, description, payload, created_at, undone_at
+             FROM operation_journal WHERE id = ?",
+            [id],
+            Self::row_to_journal_entry,
+        )
+    }
+
+    pub fn set_journal_entry_undone(&self, id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE operation_journal SET undone_at = datetime('now') WHERE id = ?",
+            [id],
+        )?;
+        Ok(())
+    }
+
+    /// Set or clear a track's artwork image path
+    pub fn set_track_artwork(&self, track_id: i64, artwork_path: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET artwork_path = ? WHERE id = ?",
+            params![artwork_path, track_id],
+        )?;
+        Ok(())
+    }
+
+    /// IDs of all tracks, ascending
+    pub fn get_all_track_ids(&self) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare("SELECT id FROM tracks ORDER BY id")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// Count total tracks
+    pub fn count_tracks(&self) -> Result<i64> {
+        let count: i64 = self.conn.query_row("SELECT COUNT(*) FROM tracks", [], |row| row.get(0))?;
+        Ok(count)
+    }
+
+    // --- Settings operations ---
+
+    /// Get a setting value by key. Returns None if the key doesn't exist.
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let mut stmt = self.conn.prepare("SELECT value FROM settings WHERE key = ?")?;
+        let result = stmt.query_row([key], |row| row.get::<_, Option<String>>(0));
+
+        match result {
+            Ok(value) => Ok(value),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Set a setting value (upsert: insert or update if key exists).
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    /// Whether BPM/key values from file tags win over RecoDeck's own analysis.
+    /// Controlled by the `analysis_priority` setting ("tag" or "analysis"); defaults to tags.
+    pub fn prefer_tag_values(&self) -> bool {
+        !matches!(
+            self.get_setting(ANALYSIS_PRIORITY_SETTING).ok().flatten().as_deref(),
+            Some("analysis")
+        )
+    }
+
+    /// Delete a setting by key.
+    pub fn delete_setting(&self, key: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM settings WHERE key = ?", [key])?;
+        Ok(())
+    }
+
+    // --- Playlist operations ---
+
+    /// Create a new playlist or folder. Returns the new playlist ID.
+    pub fn create_playlist(&self, name: &str, playlist_type: &str, parent_id: Option<i64>) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO playlists (name, type, parent_id) VALUES (?, ?, ?)",
+            params![name, playlist_type, parent_id],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Get all playlists and folders, ordered by name.
+    pub fn get_all_playlists(&self) -> Result<Vec<Playlist>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, type, parent_id, smart_rules, ai_prompt, created_at, updated_at, source_folder
+             FROM playlists ORDER BY name COLLATE UNICODE"
+        )?;
+
+        let playlists = stmt.query_map([], |row| {
+            Ok(Playlist {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                playlist_type: row.get(2)?,
+                parent_id: row.get(3)?,
+                smart_rules: row.get(4)?,
+                ai_prompt: row.get(5)?,
+                created_at: row.get(6)?,
+                updated_at: row.get(7)?,
+                source_folder: row.get(8)?,
+            })
+        })?;
+
+        playlists.collect()
+    }
+
+    /// Get a single playlist by ID.
+    pub fn get_playlist(&self, id: i64) -> Result<Playlist> {
+        self.conn.query_row(
+            "SELECT id, name, type, parent_id, smart_rules, ai_prompt, created_at, updated_at, source_folder
+             FROM playlists WHERE id = ?",
+            [id],
+            |row| {
+                Ok(Playlist {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    playlist_type: row.get(2)?,
+                    parent_id: row.get(3)?,
+                    smart_rules: row.get(4)?,
+                    ai_prompt: row.get(5)?,
+                    created_at: row.get(6)?,
+                    updated_at: row.get(7)?,
+                    source_folder: row.get(8)?,
+                })
+            },
+        )
+    }
+
+    /// Rename a playlist or folder.
+    pub fn rename_playlist(&self, id: i64, name: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE playlists SET name = ?, updated_at = datetime('now') WHERE id = ?",
+            params![name, id],
+        )?;
+        Ok(())
+    }
+
+    /// Delete a playlist (and its track associations). Also deletes child playlists if it's a folder.
+    pub fn delete_playlist(&self, id: i64) -> Result<()> {
+        // Delete track associations
+        self.conn.execute("DELETE FROM playlist_tracks WHERE playlist_id = ?", [id])?;
+        self.conn.execute("DELETE FROM playlist_auditions WHERE playlist_id = ?", [id])?;
+        self.conn.execute("DELETE FROM gig_playlists WHERE playlist_id = ?", [id])?;
+        self.conn.execute("DELETE FROM playlist_snapshots WHERE playlist_id = ?", [id])?;
+        // Delete children (if folder) — their tracks too
+        let children: Vec<i64> = {
+            let mut stmt = self.conn.prepare("SELECT id FROM playlists WHERE parent_id = ?")?;
+            let ids = stmt.query_map([id], |row| row.get(0))?;
+            ids.collect::<Result<Vec<i64>>>()?
+        };
+        for child_id in children {
+            self.delete_playlist(child_id)?;
+        }
+        // Delete the playlist/folder itself
+        self.conn.execute("DELETE FROM playlists WHERE id = ?", [id])?;
+        Ok(())
+    }
+
+    /// Get tracks in a playlist (with analysis data), ordered by position.
+    pub fn get_playlist_tracks(&self, playlist_id: i64) -> Result<Vec<TrackWithAnalysis>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}
+             FROM playlist_tracks pt
+             JOIN tracks t ON pt.track_id = t.id
+             LEFT JOIN track_analysis a ON t.id = a.track_id
+             WHERE pt.playlist_id = ?
+             ORDER BY pt.position, t.id",
+            track_with_analysis_columns()
+        ))?;
+
+        let rows = stmt.query_map([playlist_id], track_with_analysis_from_row)?;
+
+        rows.collect()
+    }
+
+    /// Add a track to a playlist at the end.
+    pub fn add_track_to_playlist(&self, playlist_id: i64, track_id: i64) -> Result<()> {
+        self.auto_snapshot_playlist(playlist_id)?;
+        let max_pos: i64 = self.conn.query_row(
+            "SELECT COALESCE(MAX(position), 0) FROM playlist_tracks WHERE playlist_id = ?",
+            [playlist_id],
+            |row| row.get(0),
+        )?;
+
+        self.conn.execute(
+            "INSERT OR IGNORE INTO playlist_tracks (playlist_id, track_id, position) VALUES (?, ?, ?)",
+            params![playlist_id, track_id, max_pos + 1],
+        )?;
+        Ok(())
+    }
+
+    /// Other versions of a track's song already in a playlist: different files with the
+    /// same title and artist (ignoring "(Extended Mix)"-style suffixes, case and punctuation)
+    /// or the same audio fingerprint / AcoustID / MusicBrainz recording.
+    /// Returns (track_id, reason) with reason "title_artist" or "fingerprint".
+    pub fn find_playlist_versions(&self, playlist_id: i64, track_id: i64) -> Result<Vec<(i64, &'static str)>> {
+        let mut versions: Vec<(i64, &'static str)> = Vec::new();
+
+        let mut stmt = self.conn.prepare(
+            "SELECT pt.track_id FROM playlist_tracks pt
+             JOIN track_fingerprints f ON f.track_id = pt.track_id
+             JOIN track_fingerprints mine ON mine.track_id = ?2
+             WHERE pt.playlist_id = ?1 AND pt.track_id != ?2
+               AND (f.chromaprint = mine.chromaprint
+                    OR f.acoustid = mine.acoustid
+                    OR f.musicbrainz_id = mine.musicbrainz_id)
+             ORDER BY pt.position",
+        )?;
+        for id in stmt.query_map(params![playlist_id, track_id], |row| row.get::<_, i64>(0))? {
+            versions.push((id?, "fingerprint"));
+        }
+
+        let track = self.get_track(track_id)?;
+        if let Some(key) = song_key(track.title.as_deref(), track.artist.as_deref()) {
+            let mut stmt = self.conn.prepare(
+                "SELECT t.id, t.title, t.artist FROM playlist_tracks pt
+                 JOIN tracks t ON t.id = pt.track_id
+                 WHERE pt.playlist_id = ?1 AND pt.track_id != ?2
+                 ORDER BY pt.position",
+            )?;
+            let rows = stmt.query_map(params![playlist_id, track_id], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, Option<String>>(2)?))
+            })?;
+            for row in rows {
+                let (id, title, artist) = row?;
+                if !versions.iter().any(|(v, _)| *v == id)
+                    && song_key(title.as_deref(), artist.as_deref()).as_ref() == Some(&key)
+                {
+                    versions.push((id, "title_artist"));
+                }
+            }
+        }
+
+        Ok(versions)
+    }
+
+    /// Remove a track from a playlist.
+    pub fn remove_track_from_playlist(&self, playlist_id: i64, track_id: i64) -> Result<()> {
+        self.auto_snapshot_playlist(playlist_id)?;
+        self.conn.execute(
+            "DELETE FROM playlist_tracks WHERE playlist_id = ? AND track_id = ?",
+            params![playlist_id, track_id],
+        )?;
+        Ok(())
+    }
+
+    /// Replace a playlist's tracks with `track_ids`, in that order (call inside a transaction).
+    /// The current tracks are snapshotted first with `reason`.
+    pub fn set_playlist_tracks(&self, playlist_id: i64, track_ids: &[i64], reason: &str) -> Result<()> {
+        self.snapshot_playlist(playlist_id, reason)?;
+        self.conn.execute("DELETE FROM playlist_tracks WHERE playlist_id = ?", [playlist_id])?;
+        for (position, track_id) in track_ids.iter().enumerate() {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO playlist_tracks (playlist_id, track_id, position) VALUES (?, ?, ?)",
+                params![playlist_id, track_id, position as i64 + 1],
+            )?;
+        }
+        Ok(())
+    }
+
+    // --- Playlist history ---
+
+    /// A playlist's track IDs, in order
+    pub fn get_playlist_track_ids(&self, playlist_id: i64) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT track_id FROM playlist_tracks WHERE playlist_id = ? ORDER BY position"
+        )?;
+        let rows = stmt.query_map([playlist_id], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// Snapshot a playlist's current tracks unless they equal the latest snapshot.
+    /// Returns the new snapshot's ID. Old snapshots beyond SNAPSHOTS_KEPT are dropped.
+    pub fn snapshot_playlist(&self, playlist_id: i64, reason: &str) -> Result<Option<i64>> {
+        let track_ids = self.get_playlist_track_ids(playlist_id)?;
+        let latest = self.get_playlist_snapshots(playlist_id)?.into_iter().next();
+        if latest.is_some_and(|s| s.track_ids == track_ids) {
+            return Ok(None);
+        }
+        let track_ids_json = serde_json::to_string(&track_ids)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        self.conn.execute(
+            "INSERT INTO playlist_snapshots (playlist_id, track_ids, reason) VALUES (?, ?, ?)",
+            params![playlist_id, track_ids_json, reason],
+        )?;
+        let id = self.conn.last_insert_rowid();
+        self.conn.execute(
+            "DELETE FROM playlist_snapshots WHERE playlist_id = ?1 AND id NOT IN
+                (SELECT id FROM playlist_snapshots WHERE playlist_id = ?1 ORDER BY id DESC LIMIT ?2)",
+            params![playlist_id, SNAPSHOTS_KEPT],
+        )?;
+        Ok(Some(id))
+    }
+
+    /// Snapshot before a change, unless the playlist is empty or was changed moments ago
+    /// (the snapshot before that change covers this one too)
+    fn auto_snapshot_playlist(&self, playlist_id: i64) -> Result<()> {
+        let recent: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM playlists
+             WHERE id = ? AND strftime('%s', 'now') - strftime('%s', updated_at) < ?",
+            params![playlist_id, SNAPSHOT_COALESCE_SECS],
+            |row| row.get(0),
+        )?;
+        if !recent && self.count_playlist_tracks(playlist_id)? > 0 {
+            self.snapshot_playlist(playlist_id, "auto")?;
+        }
+        Ok(())
+    }
+
+    /// A playlist's snapshots, newest first
+    pub fn get_playlist_snapshots(&self, playlist_id: i64) -> Result<Vec<PlaylistSnapshot>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, playlist_id, track_ids, reason, created_at FROM playlist_snapshots
+             WHERE playlist_id = ? ORDER BY id DESC"
+        )?;
+        let rows = stmt.query_map([playlist_id], Self::row_to_playlist_snapshot)?;
+        rows.collect()
+    }
+
+    pub fn get_playlist_snapshot(&self, snapshot_id: i64) -> Result<PlaylistSnapshot> {
+        self.conn.query_row(
+            "SELECT id, playlist_id, track_ids, reason, created_at FROM playlist_snapshots WHERE id = ?",
+            [snapshot_id],
+            Self::row_to_playlist_snapshot,
+        )
+    }
+
+    fn row_to_playlist_snapshot(row: &rusqlite::Row) -> Result<PlaylistSnapshot> {
+        let track_ids_json: String = row.get(2)?;
+        let track_ids = serde_json::from_str(&track_ids_json).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e))
+        })?;
+        Ok(PlaylistSnapshot {
+            id: row.get(0)?,
+            playlist_id: row.get(1)?,
+            track_ids,
+            reason: row.get(3)?,
+            created_at: row.get(4)?,
+        })
+    }
+
+    /// Put a playlist back to a snapshot (call inside a transaction). The current tracks
+    /// are snapshotted first, so the restore can itself be undone. Tracks deleted from the
+    /// library since are skipped. Returns the number of tracks restored.
+    pub fn restore_playlist_snapshot(&self, snapshot_id: i64) -> Result<usize> {
+        let snapshot = self.get_playlist_snapshot(snapshot_id)?;
+        let playlist_id = snapshot.playlist_id;
+        self.snapshot_playlist(playlist_id, "restore")?;
+
+        self.conn.execute("DELETE FROM playlist_tracks WHERE playlist_id = ?", [playlist_id])?;
+        let mut restored = 0;
+        for track_id in &snapshot.track_ids {
+            restored += self.conn.execute(
+                "INSERT OR IGNORE INTO playlist_tracks (playlist_id, track_id, position)
+                 SELECT ?1, id, ?2 FROM tracks WHERE id = ?3",
+                params![playlist_id, restored as i64 + 1, track_id],
+            )?;
+        }
+        Ok(restored)
+    }
+
+    /// Mark a track as auditioned in a playlist (refreshes the time if already marked)
+    pub fn mark_auditioned(&self, playlist_id: i64, track_id: i64) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO playlist_auditions (playlist_id, track_id) VALUES (?1, ?2)
+             ON CONFLICT(playlist_id, track_id) DO UPDATE SET auditioned_at = datetime('now')",
+            params![playlist_id, track_id],
+        )?;
+        Ok(())
+    }
+
+    /// (track_id, auditioned_at) for a playlist's auditioned tracks that are still in it
+    pub fn get_playlist_auditions(&self, playlist_id: i64) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT a.track_id, a.auditioned_at FROM playlist_auditions a
+             JOIN playlist_tracks pt ON pt.playlist_id = a.playlist_id AND pt.track_id = a.track_id
+             WHERE a.playlist_id = ?
+             ORDER BY pt.position",
+        )?;
+        let rows = stmt.query_map([playlist_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Clear a playlist's audition marks (all of them, or just `track_ids`). Returns the number cleared.
+    pub fn reset_playlist_auditions(&self, playlist_id: i64, track_ids: Option<&[i64]>) -> Result<usize> {
+        match track_ids {
+            None => self.conn.execute("DELETE FROM playlist_auditions WHERE playlist_id = ?", [playlist_id]),
+            Some(ids) => {
+                let mut cleared = 0;
+                for &track_id in ids {
+                    cleared += self.conn.execute(
+                        "DELETE FROM playlist_auditions WHERE playlist_id = ?1 AND track_id = ?2",
+                        params![playlist_id, track_id],
+                    )?;
+                }
+                Ok(cleared)
+            }
+        }
+    }
+
+    /// Count tracks in a playlist.
+    pub fn count_playlist_tracks(&self, playlist_id: i64) -> Result<i64> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM playlist_tracks WHERE playlist_id = ?",
+            [playlist_id],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    // --- Folder-mirrored playlist operations ---
+
+    /// Rebuild the mirror playlists for an on-disk folder from the tracks in the library.
+    /// The folder becomes a playlist holding its direct tracks; each subfolder that
+    /// contains tracks (at any depth) becomes a child playlist. Playlists for folders
+    /// that no longer hold any tracks are removed. Returns the number of mirror playlists.
+    pub fn sync_folder_playlists(&self, root_folder: &str) -> Result<usize> {
+        let root = root_folder.trim_end_matches('/').to_string();
+        let pattern = like_prefix(&format!("{}/", root));
+
+        // Folder -> direct track IDs (ordered by path); ancestors get empty entries
+        let mut folders: std::collections::BTreeMap<String, Vec<i64>> = std::collections::BTreeMap::new();
+        folders.insert(root.clone(), Vec::new());
+        {
+            let mut stmt = self.conn.prepare(
+                "SELECT id, file_path FROM tracks WHERE file_path LIKE ? ESCAPE '\\' ORDER BY file_path"
+            )?;
+            let rows = stmt.query_map([&pattern], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+            for row in rows {
+                let (id, path) = row?;
+                let parent = match path.rfind('/') {
+                    Some(idx) => path[..idx].to_string(),
+                    None => continue,
+                };
+                let mut dir = parent.clone();
+                while dir.len() > root.len() && !folders.contains_key(&dir) {
+                    folders.insert(dir.clone(), Vec::new());
+                    dir = match dir.rfind('/') {
+                        Some(idx) => dir[..idx].to_string(),
+                        None => break,
+                    };
+                }
+                folders.entry(parent).or_default().push(id);
+            }
+        }
+
+        // Existing mirror playlists for this root: source_folder -> playlist id
+        let mut existing: std::collections::HashMap<String, i64> = {
+            let mut stmt = self.conn.prepare(
+                "SELECT id, source_folder FROM playlists
+                 WHERE type = 'mirror' AND (source_folder = ?1 OR source_folder LIKE ?2 ESCAPE '\\')"
+            )?;
+            let rows = stmt.query_map(params![&root, &pattern], |row| {
+                Ok((row.get::<_, String>(1)?, row.get::<_, i64>(0)?))
+            })?;
+            rows.collect::<Result<_>>()?
+        };
+
+        let tx = self.conn.unchecked_transaction()?;
+
+        // BTreeMap order guarantees a parent folder is handled before its children
+        let mut ids: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
+        for (folder, track_ids) in &folders {
+            let playlist_id = match existing.remove(folder) {
+                Some(id) => id,
+                None => {
+                    let parent_id = folder
+                        .rfind('/')
+                        .and_then(|idx| ids.get(&folder[..idx]).copied());
+                    let name = folder.rsplit('/').next().unwrap_or(folder);
+                    tx.execute(
+                        "INSERT INTO playlists (name, type, parent_id, source_folder) VALUES (?, 'mirror', ?, ?)",
+                        params![name, parent_id, folder],
+                    )?;
+                    tx.last_insert_rowid()
+                }
+            };
+            ids.insert(folder.clone(), playlist_id);
+
+            tx.execute("DELETE FROM playlist_tracks WHERE playlist_id = ?", [playlist_id])?;
+            for (position, track_id) in track_ids.iter().enumerate() {
+                tx.execute(
+                    "INSERT OR IGNORE INTO playlist_tracks (playlist_id, track_id, position) VALUES (?, ?, ?)",
+                    params![playlist_id, track_id, position as i64 + 1],
+                )?;
+            }
+        }
+        tx.commit()?;
+
+        // Whatever is left mirrors folders that no longer contain tracks
+        for (_, stale_id) in existing {
+            self.delete_playlist(stale_id)?;
+        }
+
+        Ok(ids.len())
+    }
+
+    /// Remove all mirror playlists for a folder (the root playlist and its children).
+    pub fn delete_folder_playlists(&self, root_folder: &str) -> Result<()> {
+        let root = root_folder.trim_end_matches('/');
+        let root_id: Option<i64> = match self.conn.query_row(
+            "SELECT id FROM playlists WHERE type = 'mirror' AND source_folder = ?",
+            [root],
+            |row| row.get(0),
+        ) {
+            Ok(id) => Some(id),
+            Err(rusqlite::Error::QueryReturnedNoRows) => None,
+            Err(e) => return Err(e),
+        };
+        if let Some(id) = root_id {
+            self.delete_playlist(id)?;
+        }
+        Ok(())
+    }
+
+    /// Get all tracks with their analysis data (BPM, key, etc.) via LEFT JOIN.
+    /// Returns (Track, Option<bpm>, Option<bpm_confidence>, Option<musical_key>, Option<key_confidence>) tuples.
+    pub fn get_all_tracks_with_analysis(&self) -> Result<Vec<TrackWithAnalysis>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}
+             FROM tracks t
+             LEFT JOIN track_analysis a ON t.id = a.track_id
+             ORDER BY t.id",
+            track_with_analysis_columns()
+        ))?;
+
+        let rows = stmt.query_map([], track_with_analysis_from_row)?;
+
+        rows.collect()
+    }
+
+    /// Get a paginated subset of tracks with analysis data.
+    /// PERFORMANCE: Use this instead of get_all_tracks_with_analysis() for large libraries.
+    /// Returns (Track, Option<bpm>, Option<bpm_confidence>, Option<musical_key>, Option<key_confidence>) tuples.
+    /// sort_by: None/"id" = import order, "score" = popularity (highest first),
+    /// "title"/"artist"/"album" = alphabetical (accent- and case-insensitive, empty values last;
+    /// `ignore_articles` sorts "The Prodigy" under P).
+    pub fn get_tracks_with_analysis_paginated(&self, limit: i64, offset: i64, sort_by: Option<&str>, ignore_articles: bool) -> Result<Vec<TrackWithAnalysis>> {
+        let text = if ignore_articles { collation::UNICODE_NO_ARTICLE } else { collation::UNICODE };
+        // Whitelisted sort orders (never interpolate caller input into SQL)
+        let order_by = match sort_by {
+            Some("score") => "COALESCE(s.score, 0) DESC, t.id".to_string(),
+            Some("title") => format!("t.title IS NULL, t.title COLLATE {c}, t.artist COLLATE {c}, t.id", c = text),
+            Some("artist") => format!(
+                "t.artist IS NULL, t.artist COLLATE {c}, t.album COLLATE {c}, t.disc_number, t.track_number, t.title COLLATE {c}, t.id",
+                c = text
+            ),
+            Some("album") => format!("t.album IS NULL, t.album COLLATE {c}, t.disc_number, t.track_number, t.id", c = text),
+            _ => "t.id".to_string(),
+        };
+        let query = format!(
+            "SELECT {}
+             FROM tracks t
+             LEFT JOIN track_analysis a ON t.id = a.track_id
+             LEFT JOIN track_scores s ON t.id = s.track_id
+             ORDER BY {}
+             LIMIT ? OFFSET ?",
+            track_with_analysis_columns(),
+            order_by
+        );
+        let mut stmt = self.conn.prepare(&query)?;
+
+        let rows = stmt.query_map([limit, offset], track_with_analysis_from_row)?;
+
+        rows.collect()
+    }
+
+    // --- Playlist draft operations ---
+
+    /// Store a proposed playlist. Returns the draft ID.
+    pub fn create_playlist_draft(&self, session_id: i64, name: &str, track_ids: &[i64]) -> Result<i64> {
+        let track_ids_json = serde_json::to_string(track_ids)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        self.conn.execute(
+            "INSERT INTO playlist_drafts (session_id, name, track_ids) VALUES (?, ?, ?)",
+            params![session_id, name, track_ids_json],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// All pending drafts, newest first
+    pub fn get_playlist_drafts(&self) -> Result<Vec<PlaylistDraft>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, session_id, name, track_ids, created_at
+             FROM playlist_drafts ORDER BY created_at DESC, id"
+        )?;
+        let rows = stmt.query_map([], Self::row_to_playlist_draft)?;
+        rows.collect()
+    }
+
+    pub fn get_playlist_draft(&self, draft_id: i64) -> Result<PlaylistDraft> {
+        self.conn.query_row(
+            "SELECT id, session_id, name, track_ids, created_at FROM playlist_drafts WHERE id = ?",
+            [draft_id],
+            Self::row_to_playlist_draft,
+        )
+    }
+
+    fn row_to_playlist_draft(row: &rusqlite::Row) -> Result<PlaylistDraft> {
+        let track_ids_json: String = row.get(3)?;
+        let track_ids = serde_json::from_str(&track_ids_json).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e))
+        })?;
+        Ok(PlaylistDraft {
+            id: row.get(0)?,
+            session_id: row.get(1)?,
+            name: row.get(2)?,
+            track_ids,
+            created_at: row.get(4)?,
+        })
+    }
+
+    pub fn delete_playlist_draft(&self, draft_id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM playlist_drafts WHERE id = ?", [draft_id])?;
+        Ok(())
+    }
+
+    /// Drop a session's earlier drafts (before suggesting again)
+    pub fn delete_session_drafts(&self, session_id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM playlist_drafts WHERE session_id = ?", [session_id])?;
+        Ok(())
+    }
+
+    // --- Batch marker operations ---
+
+    /// Record that a batch job started (replaces any stale marker for the same job)
+    pub fn start_batch_marker(&self, job: &str, detail: Option<&str>, total: Option<i64>) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO batch_markers (job, detail, processed, total, started_at, updated_at)
+             VALUES (?1, ?2, 0, ?3, datetime('now'), datetime('now'))",
+            params![job, detail, total],
+        )?;
+        Ok(())
+    }
+
+    /// Update a running job's progress (call inside the batch's transaction)
+    pub fn update_batch_marker(&self, job: &str, processed: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE batch_markers SET processed = ?, updated_at = datetime('now') WHERE job = ?",
+            params![processed, job],
+        )?;
+        Ok(())
+    }
+
+    /// Remove a job's marker once it has finished
+    pub fn finish_batch_marker(&self, job: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM batch_markers WHERE job = ?", [job])?;
+        Ok(())
+    }
+
+    /// Markers still present: jobs that didn't finish (interrupted by a crash or quit)
+    pub fn get_batch_markers(&self) -> Result<Vec<BatchMarker>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT job, detail, processed, total, started_at, updated_at
+             FROM batch_markers ORDER BY started_at"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(BatchMarker {
+                job: row.get(0)?,
+                detail: row.get(1)?,
+                processed: row.get(2)?,
+                total: row.get(3)?,
+                started_at: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    // --- File verification operations ---
+
+    /// Record the result of re-hashing a track's file (replaces its previous result)
+    pub fn save_verification(&self, track_id: i64, status: &str, actual_hash: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO track_verifications (track_id, status, actual_hash, verified_at)
+             VALUES (?1, ?2, ?3, datetime('now'))",
+            params![track_id, status, actual_hash],
+        )?;
+        Ok(())
+    }
+
+    /// Latest verification of a track, if it has been verified
+    pub fn get_verification(&self, track_id: i64) -> Result<Option<TrackVerification>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT track_id, status, actual_hash, verified_at FROM track_verifications WHERE track_id = ?"
+        )?;
+        let mut rows = stmt.query_map([track_id], |row| {
+            Ok(TrackVerification {
+                track_id: row.get(0)?,
+                status: row.get(1)?,
+                actual_hash: row.get(2)?,
+                verified_at: row.get(3)?,
+            })
+        })?;
+        rows.next().transpose()
+    }
+
+    /// Latest verification of every track that has one
+    pub fn get_verifications(&self) -> Result<Vec<TrackVerification>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT v.track_id, v.status, v.actual_hash, v.verified_at
+             FROM track_verifications v
+             JOIN tracks t ON t.id = v.track_id
+             ORDER BY v.track_id"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(TrackVerification {
+                track_id: row.get(0)?,
+                status: row.get(1)?,
+                actual_hash: row.get(2)?,
+                verified_at: row.get(3)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// (id, file_path, file_hash) of tracks not verified within `max_age_days`
+    /// (all tracks when None), least recently verified first
+    pub fn get_tracks_to_verify(&self, max_age_days: Option<i64>) -> Result<Vec<(i64, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.id, t.file_path, t.file_hash
+             FROM tracks t
+             LEFT JOIN track_verifications v ON v.track_id = t.id
+             WHERE ?1 IS NULL OR v.verified_at IS NULL
+                OR v.verified_at < datetime('now', '-' || ?1 || ' days')
+             ORDER BY v.verified_at IS NOT NULL, v.verified_at, t.id"
+        )?;
+        let rows = stmt.query_map([max_age_days], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    // --- Watch rule operations ---
+
+    /// All watch rules, by folder
+    pub fn get_watch_rules(&self) -> Result<Vec<WatchRule>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, folder, tag, playlist_name, enabled FROM watch_rules ORDER BY folder"
+        )?;
+        let rows = stmt.query_map([], Self::row_to_watch_rule)?;
+        rows.collect()
+    }
+
+    pub fn get_watch_rule(&self, id: i64) -> Result<WatchRule> {
+        self.conn.query_row(
+            "SELECT id, folder, tag, playlist_name, enabled FROM watch_rules WHERE id = ?",
+            [id],
+            Self::row_to_watch_rule,
+        )
+    }
+
+    fn row_to_watch_rule(row: &rusqlite::Row) -> Result<WatchRule> {
+        Ok(WatchRule {
+            id: row.get(0)?,
+            folder: row.get(1)?,
+            tag: row.get(2)?,
+            playlist_name: row.get(3)?,
+            enabled: row.get(4)?,
+        })
+    }
+
+    /// Create a watch rule. Returns the new rule ID.
+    pub fn create_watch_rule(&self, folder: &str, tag: Option<&str>, playlist_name: Option<&str>) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO watch_rules (folder, tag, playlist_name) VALUES (?, ?, ?)",
+            params![folder, tag, playlist_name],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn update_watch_rule(
+        &self,
+        id: i64,
+        folder: &str,
+        tag: Option<&str>,
+        playlist_name: Option<&str>,
+        enabled: bool,
+    ) -> Result<()> {
+        let updated = self.conn.execute(
+            "UPDATE watch_rules SET folder = ?, tag = ?, playlist_name = ?, enabled = ? WHERE id = ?",
+            params![folder, tag, playlist_name, enabled, id],
+        )?;
+        if updated == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        Ok(())
+    }
+
+    pub fn delete_watch_rule(&self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM watch_rules WHERE id = ?", [id])?;
+        Ok(())
+    }
+
+    // --- Set identification prints ---
+
+    /// Store a track's sub-fingerprint, taken from the file with hash `file_hash`
+    pub fn save_track_print(&self, track_id: i64, file_hash: &str, data: &[u8]) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO track_prints (track_id, file_hash, data) VALUES (?, ?, ?)",
+            params![track_id, file_hash, data],
+        )?;
+        Ok(())
+    }
+
+    /// Prints that still match their track's file, as (track_id, data)
+    pub fn get_track_prints(&self) -> Result<Vec<(i64, Vec<u8>)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT p.track_id, p.data FROM track_prints p
+             JOIN tracks t ON t.id = p.track_id AND t.file_hash = p.file_hash
+             ORDER BY p.track_id"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    // --- Gig operations ---
+
+    /// All gigs, soonest first
+    pub fn get_gigs(&self) -> Result<Vec<Gig>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, venue, date, set_length_minutes, notes FROM gigs ORDER BY date, id"
+        )?;
+        let rows = stmt.query_map([], Self::row_to_gig)?;
+        rows.collect()
+    }
+
+    pub fn get_gig(&self, id: i64) -> Result<Gig> {
+        self.conn.query_row(
+            "SELECT id, name, venue, date, set_length_minutes, notes FROM gigs WHERE id = ?",
+            [id],
+            Self::row_to_gig,
+        )
+    }
+
+    fn row_to_gig(row: &rusqlite::Row) -> Result<Gig> {
+        Ok(Gig {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            venue: row.get(2)?,
+            date: row.get(3)?,
+            set_length_minutes: row.get(4)?,
+            notes: row.get(5)?,
+        })
+    }
+
+    /// Create a gig. Returns the new gig ID.
+    pub fn create_gig(
+        &self,
+        name: &str,
+        venue: Option<&str>,
+        date: &str,
+        set_length_minutes: i64,
+        notes: Option<&str>,
+    ) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO gigs (name, venue, date, set_length_minutes, notes) VALUES (?, ?, ?, ?, ?)",
+            params![name, venue, date, set_length_minutes, notes],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn update_gig(
+        &self,
+        id: i64,
+        name: &str,
+        venue: Option<&str>,
+        date: &str,
+        set_length_minutes: i64,
+        notes: Option<&str>,
+    ) -> Result<()> {
+        let updated = self.conn.execute(
+            "UPDATE gigs SET name = ?, venue = ?, date = ?, set_length_minutes = ?, notes = ? WHERE id = ?",
+            params![name, venue, date, set_length_minutes, notes, id],
+        )?;
+        if updated == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        Ok(())
+    }
+
+    /// Delete a gig and its playlist links (the playlists themselves are kept)
+    pub fn delete_gig(&self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM gig_playlists WHERE gig_id = ?", [id])?;
+        self.conn.execute("DELETE FROM gigs WHERE id = ?", [id])?;
+        Ok(())
+    }
+
+    /// Link a playlist to a gig, after the ones already linked. Linking again is a no-op.
+    pub fn link_gig_playlist(&self, gig_id: i64, playlist_id: i64) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO gig_playlists (gig_id, playlist_id, position)
+             SELECT ?1, ?2, COALESCE(MAX(position) + 1, 0) FROM gig_playlists WHERE gig_id = ?1",
+            params![gig_id, playlist_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn unlink_gig_playlist(&self, gig_id: i64, playlist_id: i64) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM gig_playlists WHERE gig_id = ? AND playlist_id = ?",
+            params![gig_id, playlist_id],
+        )?;
+        Ok(())
+    }
+
+    /// IDs of the playlists linked to a gig, in order
+    pub fn get_gig_playlist_ids(&self, gig_id: i64) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT playlist_id FROM gig_playlists WHERE gig_id = ? ORDER BY position, playlist_id"
+        )?;
+        let rows = stmt.query_map([gig_id], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    // --- Library sync operations ---
+
+    /// Sync state of every track with a real content hash (pending/unknown hashes
+    /// can't be matched on another machine)
+    pub fn get_track_sync_states(&self) -> Result<Vec<TrackSyncState>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, file_hash, rating, color, comment, genre, genre_source, energy_level,
+                    metadata_updated_at, cues_updated_at
+             FROM tracks WHERE file_hash NOT IN ('unknown', ?) ORDER BY id"
+        )?;
+        let rows = stmt.query_map([PENDING_HASH], |row| {
+            Ok(TrackSyncState {
+                track_id: row.get(0)?,
+                file_hash: row.get(1)?,
+                rating: row.get::<_, Option<i32>>(2)?.unwrap_or(0),
+                color: row.get(3)?,
+                comment: row.get(4)?,
+                genre: row.get(5)?,
+                genre_source: row.get(6)?,
+                energy_level: row.get(7)?,
+                metadata_updated_at: row.get(8)?,
+                cues_updated_at: row.get(9)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Overwrite a track's user metadata with synced values, keeping their timestamp
+    pub fn apply_track_sync_metadata(&self, track_id: i64, state: &TrackSyncState) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET rating = ?, color = ?, comment = ?, genre = ?, genre_source = ?,
+                               energy_level = ?, metadata_updated_at = ?
+             WHERE id = ?",
+            params![
+                state.rating,
+                state.color,
+                state.comment,
+                state.genre,
+                state.genre_source,
+                state.energy_level,
+                state.metadata_updated_at,
+                track_id,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Replace a track's cues with synced ones, keeping their timestamp.
+    /// Runs inside the caller's transaction (unlike replace_cue_points).
+    pub fn apply_track_sync_cues(&self, track_id: i64, cues: &[CuePoint], cues_updated_at: Option<&str>) -> Result<()> {
+        self.conn.execute("DELETE FROM cue_points WHERE track_id = ?", [track_id])?;
+        for cue in cues {
+            self.conn.execute(
+                "INSERT INTO cue_points (track_id, position_ms, label, color, type) VALUES (?, ?, ?, ?, ?)",
+                params![track_id, cue.position_ms, cue.label, cue.color, cue.cue_type],
+            )?;
+        }
+        // After the cue writes, whose triggers stamp the current time
+        self.conn.execute(
+            "UPDATE tracks SET cues_updated_at = ? WHERE id = ?",
+            params![cues_updated_at, track_id],
+        )?;
         Ok(())
     }
 
-    /// Count total tracks
-    pub fn count_tracks(&self) -> Result<i64> {
-        let count: i64 = self.conn.query_row("SELECT COUNT(*) FROM tracks", [], |row| row.get(0))?;
-        Ok(count)
-    }
-
-    // --- Settings operations ---
+    /// Sync state of every playlist except folder mirrors (those are rebuilt from disk).
+    /// Parents come before their children.
+    pub fn get_playlist_sync_states(&self) -> Result<Vec<PlaylistSyncState>> {
+        let mut playlists = {
+            let mut stmt = self.conn.prepare(
+                "SELECT p.id, p.sync_id, p.name, p.type, parent.sync_id, p.smart_rules, p.ai_prompt, p.updated_at
+                 FROM playlists p LEFT JOIN playlists parent ON parent.id = p.parent_id
+                 WHERE p.type != 'mirror' AND p.sync_id IS NOT NULL
+                 ORDER BY p.id"
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(PlaylistSyncState {
+                    id: row.get(0)?,
+                    sync_id: row.get(1)?,
+                    name: row.get(2)?,
+                    playlist_type: row.get::<_, Option<String>>(3)?.unwrap_or_else(|| "manual".to_string()),
+                    parent_sync_id: row.get(4)?,
+                    smart_rules: row.get(5)?,
+                    ai_prompt: row.get(6)?,
+                    updated_at: row.get(7)?,
+                    track_hashes: Vec::new(),
+                })
+            })?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
 
-    /// Get a setting value by key. Returns None if the key doesn't exist.
-    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
-        let mut stmt = self.conn.prepare("SELECT value FROM settings WHERE key = ?")?;
-        let result = stmt.query_row([key], |row| row.get::<_, Option<String>>(0));
+        let mut stmt = self.conn.prepare(
+            "SELECT t.file_hash FROM playlist_tracks pt JOIN tracks t ON pt.track_id = t.id
+             WHERE pt.playlist_id = ? ORDER BY pt.position, t.id"
+        )?;
+        for playlist in &mut playlists {
+            let hashes = stmt.query_map([playlist.id], |row| row.get(0))?;
+            playlist.track_hashes = hashes.collect::<Result<Vec<String>>>()?;
+        }
 
-        match result {
-            Ok(value) => Ok(value),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(e),
+        // Parents first, so a receiver can create them before their children
+        let mut ordered = Vec::with_capacity(playlists.len());
+        let mut placed: HashSet<String> = HashSet::new();
+        while !playlists.is_empty() {
+            let known: HashSet<String> = playlists.iter().map(|p| p.sync_id.clone()).collect();
+            let (ready, waiting): (Vec<_>, Vec<_>) = playlists.into_iter().partition(|p| {
+                p.parent_sync_id.as_ref().is_none_or(|parent| placed.contains(parent) || !known.contains(parent))
+            });
+            if ready.is_empty() {
+                // Parent cycle: emit the rest as they are
+                ordered.extend(waiting);
+                break;
+            }
+            placed.extend(ready.iter().map(|p| p.sync_id.clone()));
+            ordered.extend(ready);
+            playlists = waiting;
         }
+        Ok(ordered)
     }
 
-    /// Set a setting value (upsert: insert or update if key exists).
-    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
-        self.conn.execute(
-            "INSERT INTO settings (key, value) VALUES (?1, ?2)
-             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
-            params![key, value],
-        )?;
-        Ok(())
+    /// Local ID of the playlist with this sync_id
+    pub fn find_playlist_by_sync_id(&self, sync_id: &str) -> Result<Option<i64>> {
+        let mut stmt = self.conn.prepare("SELECT id FROM playlists WHERE sync_id = ?")?;
+        let mut rows = stmt.query_map([sync_id], |row| row.get(0))?;
+        rows.next().transpose()
     }
 
-    /// Delete a setting by key.
-    pub fn delete_setting(&self, key: &str) -> Result<()> {
-        self.conn.execute("DELETE FROM settings WHERE key = ?", [key])?;
-        Ok(())
+    /// Create (`id` None) or overwrite a playlist from synced state: name, parent, rules
+    /// and track list (`track_ids` in order). The synced updated_at is kept.
+    /// Returns the playlist ID.
+    pub fn save_synced_playlist(
+        &self,
+        id: Option<i64>,
+        state: &PlaylistSyncState,
+        parent_id: Option<i64>,
+        track_ids: &[i64],
+    ) -> Result<i64> {
+        let id = match id {
+            Some(id) => {
+                self.conn.execute(
+                    "UPDATE playlists SET name = ?, parent_id = ?, smart_rules = ?, ai_prompt = ? WHERE id = ?",
+                    params![state.name, parent_id, state.smart_rules, state.ai_prompt, id],
+                )?;
+                id
+            }
+            None => {
+                self.conn.execute(
+                    "INSERT INTO playlists (name, type, parent_id, smart_rules, ai_prompt, sync_id)
+                     VALUES (?, ?, ?, ?, ?, ?)",
+                    params![state.name, state.playlist_type, parent_id, state.smart_rules, state.ai_prompt, state.sync_id],
+                )?;
+                self.conn.last_insert_rowid()
+            }
+        };
+        self.auto_snapshot_playlist(id)?;
+        self.conn.execute("DELETE FROM playlist_tracks WHERE playlist_id = ?", [id])?;
+        for (position, track_id) in track_ids.iter().enumerate() {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO playlist_tracks (playlist_id, track_id, position) VALUES (?, ?, ?)",
+                params![id, track_id, position as i64 + 1],
+            )?;
+        }
+        // After the track writes, whose triggers stamp the current time
+        self.conn.execute(
+            "UPDATE playlists SET updated_at = ? WHERE id = ?",
+            params![state.updated_at, id],
+        )?;
+        Ok(id)
     }
 
-    // --- Playlist operations ---
+    // --- Tag operations ---
 
-    /// Create a new playlist or folder. Returns the new playlist ID.
-    pub fn create_playlist(&self, name: &str, playlist_type: &str, parent_id: Option<i64>) -> Result<i64> {
+    /// Attach a tag to a track, creating the tag if it doesn't exist yet
+    pub fn add_track_tag(&self, track_id: i64, tag_name: &str) -> Result<()> {
+        self.conn.execute("INSERT OR IGNORE INTO tags (name) VALUES (?)", [tag_name])?;
         self.conn.execute(
-            "INSERT INTO playlists (name, type, parent_id) VALUES (?, ?, ?)",
-            params![name, playlist_type, parent_id],
+            "INSERT OR IGNORE INTO track_tags (track_id, tag_id)
+             SELECT ?, id FROM tags WHERE name = ?",
+            params![track_id, tag_name],
         )?;
-        Ok(self.conn.last_insert_rowid())
+        Ok(())
     }
 
-    /// Get all playlists and folders, ordered by name.
-    pub fn get_all_playlists(&self) -> Result<Vec<Playlist>> {
+    /// Names of a track's tags, alphabetically
+    pub fn get_track_tags(&self, track_id: i64) -> Result<Vec<String>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, type, parent_id, smart_rules, ai_prompt, created_at, updated_at
-             FROM playlists ORDER BY name"
+            "SELECT tg.name FROM track_tags tt JOIN tags tg ON tg.id = tt.tag_id
+             WHERE tt.track_id = ? ORDER BY tg.name COLLATE UNICODE"
         )?;
+        let rows = stmt.query_map([track_id], |row| row.get(0))?;
+        rows.collect()
+    }
 
-        let playlists = stmt.query_map([], |row| {
-            Ok(Playlist {
+    // --- Custom fields ---
+
+    /// Field definitions in display order
+    pub fn get_custom_field_definitions(&self) -> Result<Vec<CustomFieldDefinition>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, field_type, sort_order FROM custom_field_definitions
+             ORDER BY sort_order, name COLLATE UNICODE"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(CustomFieldDefinition {
                 id: row.get(0)?,
                 name: row.get(1)?,
-                playlist_type: row.get(2)?,
-                parent_id: row.get(3)?,
-                smart_rules: row.get(4)?,
-                ai_prompt: row.get(5)?,
-                created_at: row.get(6)?,
-                updated_at: row.get(7)?,
+                field_type: row.get(2)?,
+                sort_order: row.get(3)?,
             })
         })?;
-
-        playlists.collect()
+        rows.collect()
     }
 
-    /// Get a single playlist by ID.
-    pub fn get_playlist(&self, id: i64) -> Result<Playlist> {
+    pub fn get_custom_field_definition(&self, id: i64) -> Result<CustomFieldDefinition> {
         self.conn.query_row(
-            "SELECT id, name, type, parent_id, smart_rules, ai_prompt, created_at, updated_at
-             FROM playlists WHERE id = ?",
+            "SELECT id, name, field_type, sort_order FROM custom_field_definitions WHERE id = ?",
             [id],
             |row| {
-                Ok(Playlist {
+                Ok(CustomFieldDefinition {
                     id: row.get(0)?,
                     name: row.get(1)?,
-                    playlist_type: row.get(2)?,
-                    parent_id: row.get(3)?,
-                    smart_rules: row.get(4)?,
-                    ai_prompt: row.get(5)?,
-                    created_at: row.get(6)?,
-                    updated_at: row.get(7)?,
+                    field_type: row.get(2)?,
+                    sort_order: row.get(3)?,
                 })
             },
         )
     }
 
-    /// Rename a playlist or folder.
-    pub fn rename_playlist(&self, id: i64, name: &str) -> Result<()> {
+    /// Add a field at the end of the display order
+    pub fn create_custom_field_definition(&self, name: &str, field_type: &str) -> Result<i64> {
         self.conn.execute(
-            "UPDATE playlists SET name = ?, updated_at = datetime('now') WHERE id = ?",
-            params![name, id],
+            "INSERT INTO custom_field_definitions (name, field_type, sort_order)
+             VALUES (?1, ?2, (SELECT COALESCE(MAX(sort_order), -1) + 1 FROM custom_field_definitions))",
+            params![name, field_type],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Rename and/or reorder a field (its type can't change; values were checked against it)
+    pub fn update_custom_field_definition(&self, id: i64, name: &str, sort_order: i32) -> Result<()> {
+        self.conn.execute(
+            "UPDATE custom_field_definitions SET name = ?, sort_order = ? WHERE id = ?",
+            params![name, sort_order, id],
         )?;
         Ok(())
     }
 
-    /// Delete a playlist (and its track associations). Also deletes child playlists if it's a folder.
-    pub fn delete_playlist(&self, id: i64) -> Result<()> {
-        // Delete track associations
-        self.conn.execute("DELETE FROM playlist_tracks WHERE playlist_id = ?", [id])?;
-        // Delete children (if folder) — their tracks too
-        let children: Vec<i64> = {
-            let mut stmt = self.conn.prepare("SELECT id FROM playlists WHERE parent_id = ?")?;
-            let ids = stmt.query_map([id], |row| row.get(0))?;
-            ids.collect::<Result<Vec<i64>>>()?
-        };
-        for child_id in children {
-            self.delete_playlist(child_id)?;
-        }
-        // Delete the playlist/folder itself
-        self.conn.execute("DELETE FROM playlists WHERE id = ?", [id])?;
+    /// Delete a field and every track's value for it
+    pub fn delete_custom_field_definition(&self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM track_custom_fields WHERE field_id = ?", [id])?;
+        self.conn.execute("DELETE FROM custom_field_definitions WHERE id = ?", [id])?;
         Ok(())
     }
 
-    /// Get tracks in a playlist (with analysis data), ordered by position.
-    pub fn get_playlist_tracks(&self, playlist_id: i64) -> Result<Vec<(Track, Option<f64>, Option<f64>, Option<String>, Option<f64>)>> {
+    /// A track's custom values as field ID -> value
+    pub fn get_track_custom_fields(&self, track_id: i64) -> Result<HashMap<i64, String>> {
         let mut stmt = self.conn.prepare(
-            "SELECT t.id, t.file_path, t.file_hash, t.title, t.artist, t.album, t.album_artist,
-                    t.track_number, t.year, t.label, t.duration_ms, t.file_format,
-                    t.bitrate, t.sample_rate, t.file_size, t.date_added, t.date_modified,
-                    t.play_count, t.rating, t.comment, t.artwork_path, t.genre, t.genre_source,
-                    a.bpm, a.bpm_confidence, a.musical_key, a.key_confidence
-             FROM playlist_tracks pt
-             JOIN tracks t ON pt.track_id = t.id
-             LEFT JOIN track_analysis a ON t.id = a.track_id
-             WHERE pt.playlist_id = ?
-             ORDER BY pt.position, t.id"
+            "SELECT field_id, value FROM track_custom_fields WHERE track_id = ?"
         )?;
+        let rows = stmt.query_map([track_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Every track's custom values as track ID -> (field ID -> value)
+    pub fn get_all_custom_field_values(&self) -> Result<HashMap<i64, HashMap<i64, String>>> {
+        let mut stmt = self.conn.prepare("SELECT track_id, field_id, value FROM track_custom_fields")?;
+        let mut rows = stmt.query([])?;
+        let mut values: HashMap<i64, HashMap<i64, String>> = HashMap::new();
+        while let Some(row) = rows.next()? {
+            values.entry(row.get(0)?).or_default().insert(row.get(1)?, row.get(2)?);
+        }
+        Ok(values)
+    }
+
+    /// Set a track's value for a field; None clears it
+    pub fn set_track_custom_field(&self, track_id: i64, field_id: i64, value: Option<&str>) -> Result<()> {
+        match value {
+            Some(value) => self.conn.execute(
+                "INSERT INTO track_custom_fields (track_id, field_id, value) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(track_id, field_id) DO UPDATE SET value = excluded.value",
+                params![track_id, field_id, value],
+            )?,
+            None => self.conn.execute(
+                "DELETE FROM track_custom_fields WHERE track_id = ? AND field_id = ?",
+                params![track_id, field_id],
+            )?,
+        };
+        Ok(())
+    }
 
-        let rows = stmt.query_map([playlist_id], |row| {
-            let track = Track {
+    /// All tags, alphabetically
+    pub fn get_all_tags(&self) -> Result<Vec<Tag>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, color, category FROM tags ORDER BY name COLLATE UNICODE"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Tag {
                 id: row.get(0)?,
-                file_path: row.get(1)?,
-                file_hash: row.get(2)?,
-                title: row.get(3)?,
-                artist: row.get(4)?,
-                album: row.get(5)?,
-                album_artist: row.get(6)?,
-                track_number: row.get(7)?,
-                year: row.get(8)?,
-                label: row.get(9)?,
-                duration_ms: row.get(10)?,
-                file_format: row.get(11)?,
-                bitrate: row.get(12)?,
-                sample_rate: row.get(13)?,
-                file_size: row.get(14)?,
-                date_added: row.get(15)?,
-                date_modified: row.get(16)?,
-                play_count: row.get(17)?,
-                rating: row.get(18)?,
-                comment: row.get(19)?,
-                artwork_path: row.get(20)?,
-                genre: row.get(21)?,
-                genre_source: row.get(22)?,
-            };
-            let bpm: Option<f64> = row.get(23)?;
-            let bpm_conf: Option<f64> = row.get(24)?;
-            let musical_key: Option<String> = row.get(25)?;
-            let key_conf: Option<f64> = row.get(26)?;
-            Ok((track, bpm, bpm_conf, musical_key, key_conf))
+                name: row.get(1)?,
+                color: row.get(2)?,
+                category: row.get(3)?,
+            })
         })?;
-
         rows.collect()
     }
 
-    /// Add a track to a playlist at the end.
-    pub fn add_track_to_playlist(&self, playlist_id: i64, track_id: i64) -> Result<()> {
-        let max_pos: i64 = self.conn.query_row(
-            "SELECT COALESCE(MAX(position), 0) FROM playlist_tracks WHERE playlist_id = ?",
-            [playlist_id],
-            |row| row.get(0),
+    /// Create a tag or update the color and category of the existing one with this name.
+    /// Returns true if the tag was created.
+    pub fn upsert_tag(&self, name: &str, color: Option<&str>, category: Option<&str>) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE tags SET color = ?, category = ? WHERE name = ?",
+            params![color, category, name],
         )?;
+        if updated == 0 {
+            self.conn.execute(
+                "INSERT INTO tags (name, color, category) VALUES (?, ?, ?)",
+                params![name, color, category],
+            )?;
+        }
+        Ok(updated == 0)
+    }
 
-        self.conn.execute(
-            "INSERT OR IGNORE INTO playlist_tracks (playlist_id, track_id, position) VALUES (?, ?, ?)",
-            params![playlist_id, track_id, max_pos + 1],
+    /// Create a top-level smart playlist or update the rules of the existing one with
+    /// this name (case-insensitive). Returns true if the playlist was created.
+    pub fn upsert_smart_playlist(&self, name: &str, smart_rules: Option<&str>, ai_prompt: Option<&str>) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE playlists SET smart_rules = ?, ai_prompt = ?
+             WHERE name = ? COLLATE NOCASE AND type = 'smart'",
+            params![smart_rules, ai_prompt, name],
         )?;
-        Ok(())
+        if updated == 0 {
+            self.conn.execute(
+                "INSERT INTO playlists (name, type, smart_rules, ai_prompt) VALUES (?, 'smart', ?, ?)",
+                params![name, smart_rules, ai_prompt],
+            )?;
+        }
+        Ok(updated == 0)
     }
 
-    /// Remove a track from a playlist.
-    pub fn remove_track_from_playlist(&self, playlist_id: i64, track_id: i64) -> Result<()> {
+    /// ID of the top-level manual playlist called `name` (case-insensitive), creating it if missing
+    pub fn get_or_create_playlist(&self, name: &str) -> Result<i64> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id FROM playlists
+             WHERE name = ? COLLATE NOCASE AND type = 'manual' AND parent_id IS NULL
+             ORDER BY id LIMIT 1"
+        )?;
+        let existing = stmt.query_map([name], |row| row.get(0))?.next().transpose()?;
+        match existing {
+            Some(id) => Ok(id),
+            None => self.create_playlist(name, "manual", None),
+        }
+    }
+
+    // --- Purchase info ---
+
+    pub fn get_track_purchase(&self, track_id: i64) -> Result<TrackPurchase> {
+        self.conn.query_row(
+            "SELECT purchase_source, purchase_date, purchase_order_id FROM tracks WHERE id = ?",
+            [track_id],
+            |row| Ok(TrackPurchase { source: row.get(0)?, date: row.get(1)?, order_id: row.get(2)? }),
+        )
+    }
+
+    pub fn set_track_purchase(&self, track_id: i64, purchase: &TrackPurchase) -> Result<()> {
         self.conn.execute(
-            "DELETE FROM playlist_tracks WHERE playlist_id = ? AND track_id = ?",
-            params![playlist_id, track_id],
+            "UPDATE tracks SET purchase_source = ?, purchase_date = ?, purchase_order_id = ? WHERE id = ?",
+            params![purchase.source, purchase.date, purchase.order_id, track_id],
         )?;
         Ok(())
     }
 
-    /// Count tracks in a playlist.
-    pub fn count_playlist_tracks(&self, playlist_id: i64) -> Result<i64> {
-        let count: i64 = self.conn.query_row(
-            "SELECT COUNT(*) FROM playlist_tracks WHERE playlist_id = ?",
-            [playlist_id],
-            |row| row.get(0),
+    /// Set a guessed purchase unless the track already has purchase info. Returns whether it was set.
+    pub fn fill_track_purchase(&self, track_id: i64, purchase: &TrackPurchase) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE tracks SET purchase_source = ?, purchase_date = ?, purchase_order_id = ?
+             WHERE id = ? AND purchase_source IS NULL AND purchase_date IS NULL AND purchase_order_id IS NULL",
+            params![purchase.source, purchase.date, purchase.order_id, track_id],
         )?;
-        Ok(count)
+        Ok(updated > 0)
     }
 
-    /// Get all tracks with their analysis data (BPM, key, etc.) via LEFT JOIN.
-    /// Returns (Track, Option<bpm>, Option<bpm_confidence>, Option<musical_key>, Option<key_confidence>) tuples.
-    pub fn get_all_tracks_with_analysis(&self) -> Result<Vec<(Track, Option<f64>, Option<f64>, Option<String>, Option<f64>)>> {
+    /// Purchase info of every track that has some, by track ID
+    pub fn get_all_track_purchases(&self) -> Result<HashMap<i64, TrackPurchase>> {
         let mut stmt = self.conn.prepare(
-            "SELECT t.id, t.file_path, t.file_hash, t.title, t.artist, t.album, t.album_artist,
-                    t.track_number, t.year, t.label, t.duration_ms, t.file_format,
-                    t.bitrate, t.sample_rate, t.file_size, t.date_added, t.date_modified,
-                    t.play_count, t.rating, t.comment, t.artwork_path, t.genre, t.genre_source,
-                    a.bpm, a.bpm_confidence, a.musical_key, a.key_confidence
-             FROM tracks t
-             LEFT JOIN track_analysis a ON t.id = a.track_id
-             ORDER BY t.id"
+            "SELECT id, purchase_source, purchase_date, purchase_order_id FROM tracks
+             WHERE purchase_source IS NOT NULL OR purchase_date IS NOT NULL OR purchase_order_id IS NOT NULL"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get(0)?, TrackPurchase { source: row.get(1)?, date: row.get(2)?, order_id: row.get(3)? }))
+        })?;
+        rows.collect()
+    }
+
+    /// Purchase sources in use with their track counts, most used first
+    pub fn get_purchase_sources(&self) -> Result<Vec<(String, i64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT purchase_source, COUNT(*) FROM tracks WHERE purchase_source IS NOT NULL
+             GROUP BY purchase_source COLLATE NOCASE ORDER BY COUNT(*) DESC, purchase_source"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Tracks without any purchase info, as (id, file_path)
+    pub fn get_tracks_without_purchase(&self) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, file_path FROM tracks
+             WHERE purchase_source IS NULL AND purchase_date IS NULL AND purchase_order_id IS NULL
+             ORDER BY id"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    // --- Play history operations ---
+
+    /// Record that a track started playing. Also bumps tracks.play_count.
+    /// Returns the play_history row ID (used later to mark the play as skipped).
+    pub fn record_play(&self, track_id: i64) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO play_history (track_id) VALUES (?)",
+            [track_id],
+        )?;
+        let id = self.conn.last_insert_rowid();
+        self.conn.execute(
+            "UPDATE tracks SET play_count = play_count + 1 WHERE id = ?",
+            [track_id],
+        )?;
+        Ok(id)
+    }
+
+    /// Close out a play: how long it was listened to and whether it counts as a skip
+    pub fn finish_play(&self, history_id: i64, listened_ms: i64, skipped: bool) -> Result<()> {
+        self.conn.execute(
+            "UPDATE play_history SET listened_ms = ?, skipped = ? WHERE id = ?",
+            params![listened_ms, skipped as i64, history_id],
         )?;
+        Ok(())
+    }
 
+    /// Every play, oldest first
+    pub fn get_play_history(&self) -> Result<Vec<PlayRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, track_id, played_at, CAST(strftime('%s', played_at) AS INTEGER), COALESCE(skipped, 0)
+             FROM play_history ORDER BY played_at, id"
+        )?;
         let rows = stmt.query_map([], |row| {
-            let track = Track {
+            Ok(PlayRecord {
                 id: row.get(0)?,
-                file_path: row.get(1)?,
-                file_hash: row.get(2)?,
-                title: row.get(3)?,
-                artist: row.get(4)?,
-                album: row.get(5)?,
-                album_artist: row.get(6)?,
-                track_number: row.get(7)?,
-                year: row.get(8)?,
-                label: row.get(9)?,
-                duration_ms: row.get(10)?,
-                file_format: row.get(11)?,
-                bitrate: row.get(12)?,
-                sample_rate: row.get(13)?,
-                file_size: row.get(14)?,
-                date_added: row.get(15)?,
-                date_modified: row.get(16)?,
-                play_count: row.get(17)?,
-                rating: row.get(18)?,
-                comment: row.get(19)?,
-                artwork_path: row.get(20)?,
-                genre: row.get(21)?,
-                genre_source: row.get(22)?,
-            };
-            let bpm: Option<f64> = row.get(23)?;
-            let bpm_conf: Option<f64> = row.get(24)?;
-            let musical_key: Option<String> = row.get(25)?;
-            let key_conf: Option<f64> = row.get(26)?;
-            Ok((track, bpm, bpm_conf, musical_key, key_conf))
+                track_id: row.get(1)?,
+                played_at: row.get(2)?,
+                played_at_secs: row.get(3)?,
+                skipped: row.get::<_, i64>(4)? != 0,
+            })
         })?;
-
         rows.collect()
     }
 
-    /// Get a paginated subset of tracks with analysis data.
-    /// PERFORMANCE: Use this instead of get_all_tracks_with_analysis() for large libraries.
-    /// Returns (Track, Option<bpm>, Option<bpm_confidence>, Option<musical_key>, Option<key_confidence>) tuples.
-    pub fn get_tracks_with_analysis_paginated(&self, limit: i64, offset: i64) -> Result<Vec<(Track, Option<f64>, Option<f64>, Option<String>, Option<f64>)>> {
+    /// Skips, days since the last play and active cooldown of every track that has been
+    /// played or is in cooldown
+    pub fn get_play_stats(&self) -> Result<HashMap<i64, PlayStats>> {
         let mut stmt = self.conn.prepare(
-            "SELECT t.id, t.file_path, t.file_hash, t.title, t.artist, t.album, t.album_artist,
-                    t.track_number, t.year, t.label, t.duration_ms, t.file_format,
-                    t.bitrate, t.sample_rate, t.file_size, t.date_added, t.date_modified,
-                    t.play_count, t.rating, t.comment, t.artwork_path, t.genre, t.genre_source,
-                    a.bpm, a.bpm_confidence, a.musical_key, a.key_confidence
+            "SELECT t.id, COALESCE(h.skips, 0), h.days,
+                    CASE WHEN t.cooldown_until > date('now', 'localtime') THEN t.cooldown_until END
              FROM tracks t
-             LEFT JOIN track_analysis a ON t.id = a.track_id
-             ORDER BY t.id
-             LIMIT ? OFFSET ?"
+             LEFT JOIN (SELECT track_id, SUM(COALESCE(skipped, 0)) AS skips,
+                               CAST(julianday('now') - julianday(MAX(played_at)) AS INTEGER) AS days
+                        FROM play_history GROUP BY track_id) h ON h.track_id = t.id
+             WHERE h.track_id IS NOT NULL OR t.cooldown_until > date('now', 'localtime')"
         )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get(0)?, PlayStats {
+                skips: row.get(1)?,
+                days_since_played: row.get(2)?,
+                cooldown_until: row.get(3)?,
+            }))
+        })?;
+        rows.collect()
+    }
+
+    /// Changes whenever a play is recorded or closed out, and every day; tells when
+    /// get_play_stats results are stale
+    pub fn get_play_history_stamp(&self) -> Result<String> {
+        self.conn.query_row(
+            "SELECT COUNT(*) || ':' || COALESCE(SUM(skipped), 0) || ':' || date('now', 'localtime')
+             FROM play_history",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    /// Tracks with the most skips, as (track, skip_count, play_count).
+    /// Ordered by skip count, then by the share of plays that were skips.
+    pub fn get_most_skipped(&self, limit: i64) -> Result<Vec<(Track, i64, i64)>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {},
+                    h.skips, h.plays
+             FROM tracks t
+             JOIN (SELECT track_id, SUM(skipped) AS skips, COUNT(*) AS plays
+                   FROM play_history GROUP BY track_id) h ON h.track_id = t.id
+             WHERE h.skips > 0
+             ORDER BY h.skips DESC, CAST(h.skips AS REAL) / h.plays DESC, t.id
+             LIMIT ?",
+            track_columns("t")
+        ))?;
+
+        let rows = stmt.query_map([limit], |row| {
+            let track = Track::from_row(row)?;
+            Ok((track, row.get(TRACK_COLUMNS.len())?, row.get(TRACK_COLUMNS.len() + 1)?))
+        })?;
+
+        rows.collect()
+    }
+
+    /// Recompute popularity scores for every track:
+    /// recency-weighted plays + rating bonus - recency-weighted skip penalty.
+    /// Returns the number of scores written.
+    pub fn recompute_track_scores(&self) -> Result<usize> {
+        let mut scores: std::collections::HashMap<i64, f64> = std::collections::HashMap::new();
+
+        {
+            let mut stmt = self.conn.prepare(
+                "SELECT track_id, julianday('now') - julianday(played_at), COALESCE(skipped, 0)
+                 FROM play_history"
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, Option<f64>>(1)?, row.get::<_, i64>(2)?))
+            })?;
+            for row in rows {
+                let (track_id, age_days, skipped) = row?;
+                let weight = 0.5f64.powf(age_days.unwrap_or(0.0).max(0.0) / SCORE_HALF_LIFE_DAYS);
+                let delta = if skipped != 0 { -SCORE_SKIP_PENALTY * weight } else { weight };
+                *scores.entry(track_id).or_insert(0.0) += delta;
+            }
+        }
+
+        {
+            let mut stmt = self.conn.prepare("SELECT id, rating FROM tracks WHERE rating > 0")?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?;
+            for row in rows {
+                let (track_id, rating) = row?;
+                *scores.entry(track_id).or_insert(0.0) += rating as f64 * SCORE_RATING_WEIGHT;
+            }
+        }
 
-        let rows = stmt.query_map([limit, offset], |row| {
-            let track = Track {
-                id: row.get(0)?,
-                file_path: row.get(1)?,
-                file_hash: row.get(2)?,
-                title: row.get(3)?,
-                artist: row.get(4)?,
-                album: row.get(5)?,
-                album_artist: row.get(6)?,
-                track_number: row.get(7)?,
-                year: row.get(8)?,
-                label: row.get(9)?,
-                duration_ms: row.get(10)?,
-                file_format: row.get(11)?,
-                bitrate: row.get(12)?,
-                sample_rate: row.get(13)?,
-                file_size: row.get(14)?,
-                date_added: row.get(15)?,
-                date_modified: row.get(16)?,
-                play_count: row.get(17)?,
-                rating: row.get(18)?,
-                comment: row.get(19)?,
-                artwork_path: row.get(20)?,
-                genre: row.get(21)?,
-                genre_source: row.get(22)?,
-            };
-            let bpm: Option<f64> = row.get(23)?;
-            let bpm_conf: Option<f64> = row.get(24)?;
-            let musical_key: Option<String> = row.get(25)?;
-            let key_conf: Option<f64> = row.get(26)?;
-            Ok((track, bpm, bpm_conf, musical_key, key_conf))
-        })?;
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM track_scores", [])?;
+        for (track_id, score) in &scores {
+            tx.execute(
+                "INSERT INTO track_scores (track_id, score) SELECT ?1, ?2 WHERE EXISTS (SELECT 1 FROM tracks WHERE id = ?1)",
+                params![track_id, score],
+            )?;
+        }
+        tx.commit()?;
 
-        rows.collect()
+        Ok(scores.len())
+    }
+
+    /// Get a track's popularity score. Returns None if it hasn't been scored yet.
+    pub fn get_track_score(&self, track_id: i64) -> Result<Option<f64>> {
+        match self.conn.query_row(
+            "SELECT score FROM track_scores WHERE track_id = ?",
+            [track_id],
+            |row| row.get(0),
+        ) {
+            Ok(score) => Ok(Some(score)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
     }
 
     // --- Track Analysis operations ---
@@ -596,17 +3164,36 @@ impl Database {
     /// Uses upsert: inserts a new row or updates existing BPM fields.
     pub fn save_bpm_analysis(&self, track_id: i64, bpm: f64, bpm_confidence: f64) -> Result<()> {
         self.conn.execute(
-            "INSERT INTO track_analysis (track_id, bpm, bpm_confidence, analyzed_at)
-             VALUES (?1, ?2, ?3, datetime('now'))
+            "INSERT INTO track_analysis (track_id, bpm, bpm_confidence, bpm_source, analyzed_at)
+             VALUES (?1, ?2, ?3, 'analysis', datetime('now'))
              ON CONFLICT(track_id) DO UPDATE SET
                 bpm = excluded.bpm,
                 bpm_confidence = excluded.bpm_confidence,
+                bpm_source = excluded.bpm_source,
                 analyzed_at = excluded.analyzed_at",
             params![track_id, bpm, bpm_confidence],
         )?;
         Ok(())
     }
 
+    /// Save a BPM read from file tags (`source`: TAG_SOURCE or MIXED_IN_KEY_SOURCE).
+    /// When analyzed values are preferred, an existing analyzed BPM is kept.
+    /// Returns true if the tag value was stored.
+    pub fn save_tag_bpm(&self, track_id: i64, bpm: f64, source: &str) -> Result<bool> {
+        let changed = self.conn.execute(
+            "INSERT INTO track_analysis (track_id, bpm, bpm_confidence, bpm_source, analyzed_at)
+             VALUES (?1, ?2, ?3, ?5, datetime('now'))
+             ON CONFLICT(track_id) DO UPDATE SET
+                bpm = excluded.bpm,
+                bpm_confidence = excluded.bpm_confidence,
+                bpm_source = excluded.bpm_source,
+                analyzed_at = excluded.analyzed_at
+             WHERE ?4 OR track_analysis.bpm IS NULL OR track_analysis.bpm_source IN ('tag', 'mixedinkey')",
+            params![track_id, bpm, TAG_VALUE_CONFIDENCE, self.prefer_tag_values(), source],
+        )?;
+        Ok(changed > 0)
+    }
+
     /// Get BPM analysis result for a track. Returns (bpm, confidence) or None if not analyzed.
     pub fn get_bpm_analysis(&self, track_id: i64) -> Result<Option<(f64, f64)>> {
         let mut stmt = self.conn.prepare(
@@ -627,12 +3214,28 @@ impl Database {
         }
     }
 
+    /// Integrated loudness (LUFS) of those of `track_ids` that have one
+    pub fn get_track_loudness(&self, track_ids: &[i64]) -> Result<HashMap<i64, f64>> {
+        if track_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let placeholders = vec!["?"; track_ids.len()].join(", ");
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT track_id, loudness_lufs FROM track_analysis
+             WHERE loudness_lufs IS NOT NULL AND track_id IN ({})",
+            placeholders
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(track_ids), |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
     /// Get full track analysis record for a track. Returns None if not analyzed.
     /// This struct will grow as more analysis types are added (key, loudness, etc.)
     pub fn get_track_analysis(&self, track_id: i64) -> Result<Option<TrackAnalysis>> {
         let mut stmt = self.conn.prepare(
             "SELECT track_id, bpm, bpm_confidence, musical_key, key_confidence,
-                    loudness_lufs, dynamic_range, spectral_centroid, analyzed_at
+                    loudness_lufs, dynamic_range, spectral_centroid, analyzed_at,
+                    silence_lead_ms, silence_tail_ms, tempo_variable
              FROM track_analysis WHERE track_id = ?"
         )?;
 
@@ -647,6 +3250,9 @@ impl Database {
                 dynamic_range: row.get(6)?,
                 spectral_centroid: row.get(7)?,
                 analyzed_at: row.get(8)?,
+                silence_lead_ms: row.get(9)?,
+                silence_tail_ms: row.get(10)?,
+                tempo_variable: row.get(11)?,
             })
         });
 
@@ -674,17 +3280,127 @@ impl Database {
     /// Does NOT overwrite BPM fields if they already exist — only touches key columns.
     pub fn save_key_analysis(&self, track_id: i64, musical_key: &str, key_confidence: f64) -> Result<()> {
         self.conn.execute(
-            "INSERT INTO track_analysis (track_id, musical_key, key_confidence, analyzed_at)
-             VALUES (?1, ?2, ?3, datetime('now'))
+            "INSERT INTO track_analysis (track_id, musical_key, key_confidence, key_source, analyzed_at)
+             VALUES (?1, ?2, ?3, 'analysis', datetime('now'))
              ON CONFLICT(track_id) DO UPDATE SET
                 musical_key = excluded.musical_key,
                 key_confidence = excluded.key_confidence,
+                key_source = excluded.key_source,
                 analyzed_at = excluded.analyzed_at",
             params![track_id, musical_key, key_confidence],
         )?;
         Ok(())
     }
 
+    /// Save a track's measured integrated loudness (LUFS)
+    pub fn save_loudness_analysis(&self, track_id: i64, loudness_lufs: f64) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO track_analysis (track_id, loudness_lufs, analyzed_at)
+             VALUES (?1, ?2, datetime('now'))
+             ON CONFLICT(track_id) DO UPDATE SET
+                loudness_lufs = excluded.loudness_lufs,
+                analyzed_at = excluded.analyzed_at",
+            params![track_id, loudness_lufs],
+        )?;
+        Ok(())
+    }
+
+    /// Save a key read from file tags (`source` as for save_tag_bpm). `camelot` must
+    /// already be Camelot. When analyzed values are preferred, an existing analyzed key
+    /// is kept. Returns true if the tag value was stored.
+    pub fn save_tag_key(&self, track_id: i64, camelot: &str, source: &str) -> Result<bool> {
+        let changed = self.conn.execute(
+            "INSERT INTO track_analysis (track_id, musical_key, key_confidence, key_source, analyzed_at)
+             VALUES (?1, ?2, ?3, ?5, datetime('now'))
+             ON CONFLICT(track_id) DO UPDATE SET
+                musical_key = excluded.musical_key,
+                key_confidence = excluded.key_confidence,
+                key_source = excluded.key_source,
+                analyzed_at = excluded.analyzed_at
+             WHERE ?4 OR track_analysis.musical_key IS NULL OR track_analysis.key_source IN ('tag', 'mixedinkey')",
+            params![track_id, camelot, TAG_VALUE_CONFIDENCE, self.prefer_tag_values(), source],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Save an energy level read from file tags (`source` as for save_tag_bpm).
+    /// Levels set by the user, or of unknown origin, are kept.
+    /// Returns true if the tag value was stored.
+    pub fn save_tag_energy(&self, track_id: i64, energy_level: i32, source: &str) -> Result<bool> {
+        let changed = self.conn.execute(
+            "UPDATE tracks SET energy_level = ?, energy_source = ?
+             WHERE id = ? AND (energy_level IS NULL OR energy_source IN ('tag', 'mixedinkey'))",
+            params![energy_level, source, track_id],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Store a star rating (1-5) read from file tags. Returns false if the track has a
+    /// rating that didn't come from tags (set by hand, or from before sources were kept).
+    pub fn save_tag_rating(&self, track_id: i64, rating: i32, source: &str) -> Result<bool> {
+        let changed = self.conn.execute(
+            "UPDATE tracks SET rating = ?, rating_source = ?
+             WHERE id = ? AND (rating = 0 OR rating_source = 'tag')",
+            params![rating, source, track_id],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Replace the cues a tag import stored for a track (`source`, e.g. 'serato') with
+    /// `cues`. Returns false and leaves everything alone if the track has cues from
+    /// anywhere else (set in RecoDeck, imported from Rekordbox, synced).
+    pub fn save_tag_cues(&self, track_id: i64, cues: &[CuePoint], source: &str) -> Result<bool> {
+        let other_cues: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM cue_points WHERE track_id = ? AND (source IS NULL OR source != ?)",
+            params![track_id, source],
+            |row| row.get(0),
+        )?;
+        if other_cues > 0 {
+            return Ok(false);
+        }
+        self.conn.execute(
+            "DELETE FROM cue_points WHERE track_id = ? AND source = ?",
+            params![track_id, source],
+        )?;
+        for cue in cues {
+            self.conn.execute(
+                "INSERT INTO cue_points (track_id, position_ms, label, color, type, source)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                params![track_id, cue.position_ms, cue.label, cue.color, cue.cue_type, source],
+            )?;
+        }
+        Ok(true)
+    }
+
+    /// Whether a track's BPM and key still need analysis. Values read from tags
+    /// count as done unless analyzed values are preferred. Returns (bpm, key).
+    pub fn needs_analysis(&self, track_id: i64) -> Result<(bool, bool)> {
+        let prefer_tags = self.prefer_tag_values();
+        match self.conn.query_row(
+            "SELECT bpm IS NOT NULL, bpm_source, musical_key IS NOT NULL, key_source
+             FROM track_analysis WHERE track_id = ?",
+            [track_id],
+            |row| {
+                Ok((
+                    row.get::<_, bool>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, bool>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                ))
+            },
+        ) {
+            Ok((has_bpm, bpm_source, has_key, key_source)) => {
+                let is_tag = |source: &Option<String>| is_tag_source(source.as_deref());
+                Ok((
+                    !has_bpm || (!prefer_tags && is_tag(&bpm_source)),
+                    !has_key || (!prefer_tags && is_tag(&key_source)),
+                ))
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok((true, true)),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Get key analysis result for a track. Returns (key, confidence) or None if not analyzed.
     pub fn get_key_analysis(&self, track_id: i64) -> Result<Option<(String, f64)>> {
         let mut stmt = self.conn.prepare(
@@ -733,6 +3449,108 @@ impl Database {
         Ok(())
     }
 
+    /// Save detected leading/trailing silence (ms)
+    pub fn save_silence(&self, track_id: i64, lead_ms: i64, tail_ms: i64) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO track_analysis (track_id, silence_lead_ms, silence_tail_ms, analyzed_at)
+             VALUES (?1, ?2, ?3, datetime('now'))
+             ON CONFLICT(track_id) DO UPDATE SET
+                silence_lead_ms = excluded.silence_lead_ms,
+                silence_tail_ms = excluded.silence_tail_ms,
+                analyzed_at = excluded.analyzed_at",
+            params![track_id, lead_ms, tail_ms],
+        )?;
+        Ok(())
+    }
+
+    /// Save a track's tempo curve (JSON array of { time_ms, bpm }) and variable-tempo flag
+    pub fn save_tempo_curve(&self, track_id: i64, curve_json: &str, variable: bool) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO track_analysis (track_id, tempo_curve, tempo_variable, analyzed_at)
+             VALUES (?1, ?2, ?3, datetime('now'))
+             ON CONFLICT(track_id) DO UPDATE SET
+                tempo_curve = excluded.tempo_curve,
+                tempo_variable = excluded.tempo_variable,
+                analyzed_at = excluded.analyzed_at",
+            params![track_id, curve_json, variable],
+        )?;
+        Ok(())
+    }
+
+    /// A track's tempo curve JSON, if it has been analyzed
+    pub fn get_tempo_curve(&self, track_id: i64) -> Result<Option<String>> {
+        match self.conn.query_row(
+            "SELECT tempo_curve FROM track_analysis WHERE track_id = ?",
+            [track_id],
+            |row| row.get(0),
+        ) {
+            Ok(curve) => Ok(curve),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save_quality_check(&self, check: &QualityCheck) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO track_analysis (track_id, quality_flags, quality_cutoff_hz, quality_hum_hz,
+                                         quality_dc_offset, analyzed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, datetime('now'))
+             ON CONFLICT(track_id) DO UPDATE SET
+                quality_flags = excluded.quality_flags,
+                quality_cutoff_hz = excluded.quality_cutoff_hz,
+                quality_hum_hz = excluded.quality_hum_hz,
+                quality_dc_offset = excluded.quality_dc_offset,
+                analyzed_at = excluded.analyzed_at",
+            params![check.track_id, check.flags, check.cutoff_hz, check.hum_hz, check.dc_offset],
+        )?;
+        Ok(())
+    }
+
+    /// Quality checks with any of the `mask` bits set, worst (most flags) first
+    pub fn get_quality_issues(&self, mask: u32) -> Result<Vec<QualityCheck>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT track_id, quality_flags, quality_cutoff_hz, quality_hum_hz, quality_dc_offset
+             FROM track_analysis
+             WHERE quality_flags & ?1 != 0
+             ORDER BY quality_flags DESC, track_id",
+        )?;
+        let rows = stmt.query_map([mask], |row| {
+            Ok(QualityCheck {
+                track_id: row.get(0)?,
+                flags: row.get(1)?,
+                cutoff_hz: row.get(2)?,
+                hum_hz: row.get(3)?,
+                dc_offset: row.get::<_, Option<f64>>(4)?.unwrap_or(0.0),
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Tracks whose quality hasn't been checked yet: (id, file_path, file_format)
+    pub fn get_tracks_without_quality_check(&self) -> Result<Vec<(i64, String, Option<String>)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.id, t.file_path, t.file_format FROM tracks t
+             LEFT JOIN track_analysis a ON a.track_id = t.id
+             WHERE a.quality_flags IS NULL
+             ORDER BY t.id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    /// Leading silence (ms) for a track, if it has been detected
+    pub fn get_silence_lead(&self, track_id: i64) -> Result<Option<i64>> {
+        match self.conn.query_row(
+            "SELECT silence_lead_ms FROM track_analysis WHERE track_id = ?",
+            [track_id],
+            |row| row.get(0),
+        ) {
+            Ok(lead) => Ok(lead),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Get waveform data for a track. Returns (overview_blob, detail_blob) or None if not available.
     /// Level parameter: "overview" or "detail"
     pub fn get_waveform(&self, track_id: i64, level: &str) -> Result<Option<Vec<u8>>> {
@@ -768,6 +3586,134 @@ impl Database {
         Ok(count > 0)
     }
 
+    // --- Sidecar analysis (formats::sidecar) ---
+
+    /// A track's analysis as written to sidecar files. None if it has none.
+    pub fn get_portable_analysis(&self, track_id: i64) -> Result<Option<PortableAnalysis>> {
+        match self.conn.query_row(
+            "SELECT bpm, bpm_confidence, musical_key, key_confidence,
+                    silence_lead_ms, silence_tail_ms, waveform_overview
+             FROM track_analysis WHERE track_id = ?",
+            [track_id],
+            |row| {
+                Ok(PortableAnalysis {
+                    bpm: row.get(0)?,
+                    bpm_confidence: row.get(1)?,
+                    musical_key: row.get(2)?,
+                    key_confidence: row.get(3)?,
+                    silence_lead_ms: row.get(4)?,
+                    silence_tail_ms: row.get(5)?,
+                    waveform_overview: row.get(6)?,
+                })
+            },
+        ) {
+            Ok(analysis) => Ok(Some(analysis)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Fill a track's analysis from a sidecar. BPM and key are stored as analyzed values
+    /// where the track has none, or only tag values while analyzed values are preferred;
+    /// silence and the overview waveform only where missing.
+    pub fn fill_portable_analysis(&self, track_id: i64, analysis: &PortableAnalysis) -> Result<()> {
+        let current = match self.conn.query_row(
+            "SELECT bpm IS NOT NULL, bpm_source, musical_key IS NOT NULL, key_source
+             FROM track_analysis WHERE track_id = ?",
+            [track_id],
+            |row| {
+                Ok((
+                    row.get::<_, bool>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, bool>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                ))
+            },
+        ) {
+            Ok(current) => current,
+            Err(rusqlite::Error::QueryReturnedNoRows) => (false, None, false, None),
+            Err(e) => return Err(e),
+        };
+        let (has_bpm, bpm_source, has_key, key_source) = current;
+        let prefer_tags = self.prefer_tag_values();
+        let replaceable = |has_value: bool, source: &Option<String>| {
+            !has_value || (!prefer_tags && is_tag_source(source.as_deref()))
+        };
+
+        if let (Some(bpm), true) = (analysis.bpm, replaceable(has_bpm, &bpm_source)) {
+            self.save_bpm_analysis(track_id, bpm, analysis.bpm_confidence.unwrap_or(0.0))?;
+        }
+        if let (Some(key), true) = (&analysis.musical_key, replaceable(has_key, &key_source)) {
+            self.save_key_analysis(track_id, key, analysis.key_confidence.unwrap_or(0.0))?;
+        }
+        self.conn.execute(
+            "INSERT INTO track_analysis (track_id, silence_lead_ms, silence_tail_ms, waveform_overview, analyzed_at)
+             VALUES (?1, ?2, ?3, ?4, datetime('now'))
+             ON CONFLICT(track_id) DO UPDATE SET
+                silence_lead_ms = COALESCE(track_analysis.silence_lead_ms, excluded.silence_lead_ms),
+                silence_tail_ms = COALESCE(track_analysis.silence_tail_ms, excluded.silence_tail_ms),
+                waveform_overview = COALESCE(track_analysis.waveform_overview, excluded.waveform_overview)",
+            params![track_id, analysis.silence_lead_ms, analysis.silence_tail_ms, analysis.waveform_overview],
+        )?;
+        Ok(())
+    }
+
+    /// Give a track cues from a sidecar if it has none. Runs inside the caller's
+    /// transaction. Returns true if the cues were stored.
+    pub fn fill_cue_points(&self, track_id: i64, cues: &[CuePoint]) -> Result<bool> {
+        let existing: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM cue_points WHERE track_id = ?",
+            [track_id],
+            |row| row.get(0),
+        )?;
+        if existing > 0 || cues.is_empty() {
+            return Ok(false);
+        }
+        for cue in cues {
+            self.conn.execute(
+                "INSERT INTO cue_points (track_id, position_ms, label, color, type) VALUES (?, ?, ?, ?, ?)",
+                params![track_id, cue.position_ms, cue.label, cue.color, cue.cue_type],
+            )?;
+        }
+        Ok(true)
+    }
+
+    // --- Analysis cache (by content hash, see migration 030) ---
+
+    /// Give a track the analysis cached for its content hash, filling only values the
+    /// track doesn't have yet (e.g. BPM read from tags stays). The cache entry is dropped
+    /// once used. Returns whether anything was cached for the hash.
+    pub fn restore_cached_analysis(&self, track_id: i64, file_hash: &str) -> Result<bool> {
+        let columns = ANALYSIS_CACHE_COLUMNS.join(", ");
+        let updates = ANALYSIS_CACHE_COLUMNS
+            .iter()
+            .map(|c| format!("{c} = COALESCE(track_analysis.{c}, excluded.{c})"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let restored = self.conn.execute(
+            &format!(
+                "INSERT INTO track_analysis (track_id, {columns})
+                 SELECT ?1, {columns} FROM analysis_cache WHERE file_hash = ?2
+                 ON CONFLICT(track_id) DO UPDATE SET {updates}"
+            ),
+            params![track_id, file_hash],
+        )?;
+        if restored > 0 {
+            self.conn.execute("DELETE FROM analysis_cache WHERE file_hash = ?", [file_hash])?;
+        }
+        Ok(restored > 0)
+    }
+
+    /// Number of cached analyses
+    pub fn count_analysis_cache(&self) -> Result<i64> {
+        self.conn.query_row("SELECT COUNT(*) FROM analysis_cache", [], |row| row.get(0))
+    }
+
+    /// Drop all cached analyses. Returns the number dropped.
+    pub fn clear_analysis_cache(&self) -> Result<usize> {
+        self.conn.execute("DELETE FROM analysis_cache", [])
+    }
+
     /// Check if a track with the given file_path already exists in the database.
     /// Used to skip re-importing files that are already tracked.
     pub fn track_exists_with_path(&self, file_path: &str) -> Result<bool> {
@@ -793,6 +3739,125 @@ impl Database {
         Ok(set)
     }
 
+    /// Get the recorded size/mtime of every track's file, keyed by path.
+    /// Used by rescans to skip files that haven't changed since the last scan.
+    pub fn get_file_stats(&self) -> Result<std::collections::HashMap<String, FileStat>> {
+        let mut stmt = self.conn.prepare("SELECT file_path, id, file_size, file_mtime FROM tracks")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                FileStat {
+                    track_id: row.get(1)?,
+                    size: row.get(2)?,
+                    mtime: row.get(3)?,
+                },
+            ))
+        })?;
+        rows.collect()
+    }
+
+    /// Size and mtime recorded for one track's file
+    pub fn get_file_stat(&self, track_id: i64) -> Result<FileStat> {
+        self.conn.query_row(
+            "SELECT id, file_size, file_mtime FROM tracks WHERE id = ?",
+            [track_id],
+            |row| {
+                Ok(FileStat {
+                    track_id: row.get(0)?,
+                    size: row.get(1)?,
+                    mtime: row.get(2)?,
+                })
+            },
+        )
+    }
+
+    /// Record the size/mtime a track's file had when it was scanned
+    pub fn set_track_file_stat(&self, track_id: i64, size: Option<i64>, mtime: Option<i64>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET file_size = ?, file_mtime = ? WHERE id = ?",
+            params![size, mtime, track_id],
+        )?;
+        Ok(())
+    }
+
+    /// Update the file-level fields of a track whose file changed on disk
+    /// (hash, size, format, duration, bitrate, sample rate). Tag-derived fields are
+    /// left alone so edits made in RecoDeck aren't overwritten by a rescan; disc number,
+    /// track total, ISRC and catalog number are only filled in when missing (tracks
+    /// scanned before they were read).
+    pub fn update_track_file_info(&self, track_id: i64, track: &Track) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET
+                file_hash = ?, file_size = ?, file_format = ?, duration_ms = ?,
+                bitrate = ?, sample_rate = ?,
+                disc_number = COALESCE(disc_number, ?), total_tracks = COALESCE(total_tracks, ?),
+                isrc = COALESCE(isrc, ?), catalog_number = COALESCE(catalog_number, ?),
+                artwork_path = COALESCE(artwork_path, ?)
+             WHERE id = ?",
+            params![
+                track.file_hash,
+                track.file_size,
+                track.file_format,
+                track.duration_ms,
+                track.bitrate,
+                track.sample_rate,
+                track.disc_number,
+                track.total_tracks,
+                track.isrc,
+                track.catalog_number,
+                track.artwork_path,
+                track_id,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Tracks whose content hash hasn't been computed yet (lazy hash mode), as (id, path)
+    pub fn get_tracks_pending_hash(&self) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare("SELECT id, file_path FROM tracks WHERE file_hash = ?")?;
+        let rows = stmt.query_map([PENDING_HASH], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Store a track's content hash
+    pub fn set_track_hash(&self, track_id: i64, file_hash: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET file_hash = ? WHERE id = ?",
+            params![file_hash, track_id],
+        )?;
+        Ok(())
+    }
+
+    /// Tracks with the given content hash, as (id, file_path). Used to detect moved files.
+    pub fn find_tracks_by_hash(&self, file_hash: &str) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare("SELECT id, file_path FROM tracks WHERE file_hash = ? ORDER BY id")?;
+        let rows = stmt.query_map([file_hash], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Tracks of the given file size that have a real content hash, as (id, file_path, file_hash).
+    /// Lets lazily hashed scans find move candidates before hashing the new file.
+    pub fn find_hashed_tracks_by_size(&self, file_size: i64) -> Result<Vec<(i64, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, file_path, file_hash FROM tracks
+             WHERE file_size = ? AND file_hash NOT IN ('unknown', ?) ORDER BY id",
+        )?;
+        let rows = stmt.query_map(params![file_size, PENDING_HASH], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?;
+        rows.collect()
+    }
+
+    /// Point a track at its file's new location. Everything keyed by track id
+    /// (analysis, cues, playlists, ratings, play history) stays attached.
+    pub fn update_track_path(&self, track_id: i64, file_path: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET file_path = ? WHERE id = ?",
+            params![file_path, track_id],
+        )?;
+        Ok(())
+    }
+
     /// Check if a track with the given file_hash already exists in the database.
     /// Used to prevent importing duplicate content at different file paths.
     pub fn track_exists_with_hash(&self, file_hash: &str) -> Result<bool> {
@@ -806,23 +3871,23 @@ impl Database {
 
     /// Remove duplicate tracks.
     /// Detection methods (in order):
-    /// 1. Same file_hash (excluding 'unknown') - exact same file content
+    /// 1. Same file_hash (excluding 'unknown' and 'pending') - exact same file content
     /// 2. Same file name + file size - catches identical copies at different paths
     /// NOTE: We do NOT dedupe by title alone - different artists can have songs with the same name.
     /// Keeps the track with the lowest id (earliest import) for each duplicate group.
-    /// Also cleans up related analysis data and playlist associations for removed tracks.
+    /// Removed tracks go with all their per-track data (delete_track_data), in one transaction.
     /// Returns the number of deleted tracks.
     pub fn remove_duplicate_tracks(&self) -> Result<usize> {
         let mut dup_ids: Vec<i64> = Vec::new();
 
-        // 1. Find duplicates by file_hash (excluding 'unknown')
+        // 1. Find duplicates by file_hash (excluding 'unknown' and not-yet-hashed tracks)
         {
             let mut stmt = self.conn.prepare(
                 "SELECT t1.id, t1.file_path, t1.file_hash FROM tracks t1
                  INNER JOIN tracks t2 ON t1.file_hash = t2.file_hash
-                 WHERE t1.id > t2.id AND t1.file_hash != 'unknown'"
+                 WHERE t1.id > t2.id AND t1.file_hash NOT IN ('unknown', ?)"
             )?;
-            let rows = stmt.query_map([], |row| {
+            let rows = stmt.query_map([PENDING_HASH], |row| {
                 Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
             })?;
 
@@ -873,32 +3938,186 @@ impl Database {
                 }
             }
 
-            if filename_dups > 0 {
-                println!("Found {} duplicates by filename+size", filename_dups);
-            }
-        }
+            if filename_dups > 0 {
+                println!("Found {} duplicates by filename+size", filename_dups);
+            }
+        }
+
+        // NOTE: We intentionally do NOT dedupe by title alone.
+        // Different artists can have songs with the same name, and users may have
+        // different versions/remixes of the same track - these are NOT duplicates.
+
+        if dup_ids.is_empty() {
+            println!("No duplicates found");
+            return Ok(0);
+        }
+
+        let count = dup_ids.len();
+        println!("Removing {} duplicate tracks...", count);
+
+        let tx = self.transaction()?;
+        for &id in &dup_ids {
+            self.delete_track_data(id)?;
+        }
+        tx.commit()?;
+
+        println!("Successfully removed {} duplicate tracks", count);
+        Ok(count)
+    }
+
+    /// Groups of tracks that look like copies of each other, by the same rules as
+    /// remove_duplicate_tracks: same file_hash, or same file name + size.
+    /// Returns (reasons, track IDs ascending) per group; reasons are "hash" and/or "filename_size".
+    pub fn find_duplicate_groups(&self) -> Result<Vec<(Vec<String>, Vec<i64>)>> {
+        use std::collections::HashMap;
+
+        let tracks = self.get_all_tracks()?;
+        let ids: Vec<i64> = tracks.iter().filter_map(|t| t.id).collect();
+        let index: HashMap<i64, usize> = ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+        let mut parent: Vec<usize> = (0..ids.len()).collect();
+        fn root(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+
+        // (key kind, key) -> first track index; each link remembers why it was made
+        let mut first_seen: HashMap<(&str, String), usize> = HashMap::new();
+        let mut links: Vec<(usize, &str)> = Vec::new();
+        for track in &tracks {
+            let Some(i) = track.id.map(|id| index[&id]) else { continue };
+            let mut keys = Vec::new();
+            if track.file_hash != "unknown" && track.file_hash != PENDING_HASH {
+                keys.push(("hash", track.file_hash.clone()));
+            }
+            if let Some(size) = track.file_size {
+                let filename = track.file_path.rsplit('/').next().unwrap_or(&track.file_path).to_lowercase();
+                keys.push(("filename_size", format!("{}\0{}", filename, size)));
+            }
+            for key in keys {
+                let reason = key.0;
+                match first_seen.get(&key) {
+                    Some(&j) => {
+                        let (ri, rj) = (root(&mut parent, i), root(&mut parent, j));
+                        parent[ri.max(rj)] = ri.min(rj);
+                        links.push((i, reason));
+                    }
+                    None => {
+                        first_seen.insert(key, i);
+                    }
+                }
+            }
+        }
+
+        let mut groups: HashMap<usize, (Vec<String>, Vec<i64>)> = HashMap::new();
+        for (i, &id) in ids.iter().enumerate() {
+            let r = root(&mut parent, i);
+            groups.entry(r).or_default().1.push(id);
+        }
+        for (i, reason) in links {
+            let r = root(&mut parent, i);
+            let reasons = &mut groups.get_mut(&r).unwrap().0;
+            if !reasons.iter().any(|x| x == reason) {
+                reasons.push(reason.to_string());
+            }
+        }
+
+        let mut groups: Vec<(Vec<String>, Vec<i64>)> = groups
+            .into_values()
+            .filter(|(_, members)| members.len() > 1)
+            .map(|(mut reasons, mut members)| {
+                reasons.sort();
+                members.sort();
+                (reasons, members)
+            })
+            .collect();
+        groups.sort_by_key(|(_, members)| members[0]);
+        Ok(groups)
+    }
+
+    /// Number of playlists a track is in
+    pub fn count_playlists_with_track(&self, track_id: i64) -> Result<i64> {
+        self.conn.query_row(
+            "SELECT COUNT(*) FROM playlist_tracks WHERE track_id = ?",
+            [track_id],
+            |row| row.get(0),
+        )
+    }
+
+    /// Per track: (playlists it's in, days since it was added; None if unknown)
+    pub fn get_track_usage(&self) -> Result<HashMap<i64, (i64, Option<f64>)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.id,
+                    (SELECT COUNT(*) FROM playlist_tracks pt WHERE pt.track_id = t.id),
+                    julianday('now') - julianday(t.date_added)
+             FROM tracks t",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?;
+        rows.collect()
+    }
 
-        // NOTE: We intentionally do NOT dedupe by title alone.
-        // Different artists can have songs with the same name, and users may have
-        // different versions/remixes of the same track - these are NOT duplicates.
+    /// Fold duplicate `remove_id` into `keep_id` and delete it (call inside a transaction).
+    /// Playlist entries, play history, play count and the higher rating always carry over;
+    /// analysis, beat grid, cues and other per-track data move only where `keep_id` has none.
+    /// Score, analysis errors and verifications describe the removed file and are dropped
+    /// with it. With `merge_metadata`, empty tag fields on `keep_id` are filled from `remove_id`.
+    pub fn merge_duplicate_track(&self, keep_id: i64, remove_id: i64, merge_metadata: bool) -> Result<()> {
+        // Cue points only if the kept copy has none (positions are per file)
+        self.conn.execute(
+            "UPDATE cue_points SET track_id = ?1
+             WHERE track_id = ?2 AND NOT EXISTS (SELECT 1 FROM cue_points WHERE track_id = ?1)",
+            params![keep_id, remove_id],
+        )?;
+        self.conn.execute(
+            "UPDATE play_history SET track_id = ?1 WHERE track_id = ?2",
+            params![keep_id, remove_id],
+        )?;
 
-        if dup_ids.is_empty() {
-            println!("No duplicates found");
-            return Ok(0);
+        // The other tables keyed by track (at most one row per track or per (track, value)):
+        // move rows that don't collide with the kept track's. delete_track_data drops the rest.
+        let moved = std::iter::once("track_analysis").chain(TRACK_DATA_TABLES).filter(|table| {
+            !["cue_points", "play_history", "track_scores", "analysis_errors", "track_verifications"].contains(table)
+        });
+        for table in moved {
+            self.conn.execute(
+                &format!("UPDATE OR IGNORE {} SET track_id = ?1 WHERE track_id = ?2", table),
+                params![keep_id, remove_id],
+            )?;
         }
 
-        let count = dup_ids.len();
-        println!("Removing {} duplicate tracks...", count);
+        self.conn.execute(
+            "UPDATE tracks SET
+                play_count = play_count + (SELECT play_count FROM tracks WHERE id = ?2),
+                rating = MAX(rating, (SELECT rating FROM tracks WHERE id = ?2))
+             WHERE id = ?1",
+            params![keep_id, remove_id],
+        )?;
 
-        for id in &dup_ids {
-            // Remove related data first
-            self.conn.execute("DELETE FROM track_analysis WHERE track_id = ?", [id])?;
-            self.conn.execute("DELETE FROM playlist_tracks WHERE track_id = ?", [id])?;
-            self.conn.execute("DELETE FROM tracks WHERE id = ?", [id])?;
+        if merge_metadata {
+            self.conn.execute(
+                "UPDATE tracks SET
+                    title = COALESCE(tracks.title, r.title),
+                    artist = COALESCE(tracks.artist, r.artist),
+                    album = COALESCE(tracks.album, r.album),
+                    album_artist = COALESCE(tracks.album_artist, r.album_artist),
+                    track_number = COALESCE(tracks.track_number, r.track_number),
+                    year = COALESCE(tracks.year, r.year),
+                    label = COALESCE(tracks.label, r.label),
+                    comment = COALESCE(tracks.comment, r.comment),
+                    artwork_path = COALESCE(tracks.artwork_path, r.artwork_path),
+                    genre_source = CASE WHEN tracks.genre IS NULL THEN r.genre_source ELSE tracks.genre_source END,
+                    genre = COALESCE(tracks.genre, r.genre),
+                    energy_level = COALESCE(tracks.energy_level, r.energy_level),
+                    color = COALESCE(tracks.color, r.color)
+                 FROM (SELECT * FROM tracks WHERE id = ?2) AS r
+                 WHERE tracks.id = ?1",
+                params![keep_id, remove_id],
+            )?;
         }
 
-        println!("Successfully removed {} duplicate tracks", count);
-        Ok(count)
+        self.delete_track_data(remove_id)
     }
 
     /// Count tracks whose file_path starts with a given folder path prefix.
@@ -918,56 +4137,22 @@ impl Database {
 
     /// Get tracks in a specific folder (by file_path prefix) with analysis data.
     /// Matches tracks directly in the folder and all subfolders.
-    pub fn get_tracks_in_folder_with_analysis(&self, folder_path: &str) -> Result<Vec<(Track, Option<f64>, Option<f64>, Option<String>, Option<f64>)>> {
+    pub fn get_tracks_in_folder_with_analysis(&self, folder_path: &str) -> Result<Vec<TrackWithAnalysis>> {
         // Normalize path: remove trailing slash if present
         let normalized = folder_path.trim_end_matches('/');
         // Pattern: folder/% matches anything inside the folder (including nested)
         let pattern = format!("{}/%", normalized);
 
-        let mut stmt = self.conn.prepare(
-            "SELECT t.id, t.file_path, t.file_hash, t.title, t.artist, t.album, t.album_artist,
-                    t.track_number, t.year, t.label, t.duration_ms, t.file_format,
-                    t.bitrate, t.sample_rate, t.file_size, t.date_added, t.date_modified,
-                    t.play_count, t.rating, t.comment, t.artwork_path, t.genre, t.genre_source,
-                    a.bpm, a.bpm_confidence, a.musical_key, a.key_confidence
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}
              FROM tracks t
              LEFT JOIN track_analysis a ON t.id = a.track_id
              WHERE t.file_path LIKE ?
-             ORDER BY t.id"
-        )?;
+             ORDER BY t.id",
+            track_with_analysis_columns()
+        ))?;
 
-        let rows = stmt.query_map([&pattern], |row| {
-            let track = Track {
-                id: row.get(0)?,
-                file_path: row.get(1)?,
-                file_hash: row.get(2)?,
-                title: row.get(3)?,
-                artist: row.get(4)?,
-                album: row.get(5)?,
-                album_artist: row.get(6)?,
-                track_number: row.get(7)?,
-                year: row.get(8)?,
-                label: row.get(9)?,
-                duration_ms: row.get(10)?,
-                file_format: row.get(11)?,
-                bitrate: row.get(12)?,
-                sample_rate: row.get(13)?,
-                file_size: row.get(14)?,
-                date_added: row.get(15)?,
-                date_modified: row.get(16)?,
-                play_count: row.get(17)?,
-                rating: row.get(18)?,
-                comment: row.get(19)?,
-                artwork_path: row.get(20)?,
-                genre: row.get(21)?,
-                genre_source: row.get(22)?,
-            };
-            let bpm: Option<f64> = row.get(23)?;
-            let bpm_conf: Option<f64> = row.get(24)?;
-            let musical_key: Option<String> = row.get(25)?;
-            let key_conf: Option<f64> = row.get(26)?;
-            Ok((track, bpm, bpm_conf, musical_key, key_conf))
-        })?;
+        let rows = stmt.query_map([&pattern], track_with_analysis_from_row)?;
 
         rows.collect()
     }
@@ -994,57 +4179,23 @@ impl Database {
 
     /// Get tracks directly in a specific folder (non-recursive, shallow) with analysis data.
     /// Only matches tracks in the immediate folder, not in subfolders.
-    pub fn get_tracks_in_folder_shallow_with_analysis(&self, folder_path: &str) -> Result<Vec<(Track, Option<f64>, Option<f64>, Option<String>, Option<f64>)>> {
+    pub fn get_tracks_in_folder_shallow_with_analysis(&self, folder_path: &str) -> Result<Vec<TrackWithAnalysis>> {
         // Normalize path: remove trailing slash if present
         let normalized = folder_path.trim_end_matches('/');
         let prefix = format!("{}/", normalized);
         let pattern = format!("{}%", prefix);
 
-        let mut stmt = self.conn.prepare(
-            "SELECT t.id, t.file_path, t.file_hash, t.title, t.artist, t.album, t.album_artist,
-                    t.track_number, t.year, t.label, t.duration_ms, t.file_format,
-                    t.bitrate, t.sample_rate, t.file_size, t.date_added, t.date_modified,
-                    t.play_count, t.rating, t.comment, t.artwork_path, t.genre, t.genre_source,
-                    a.bpm, a.bpm_confidence, a.musical_key, a.key_confidence
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}
              FROM tracks t
              LEFT JOIN track_analysis a ON t.id = a.track_id
              WHERE t.file_path LIKE ?1
              AND instr(substr(t.file_path, length(?2) + 1), '/') = 0
-             ORDER BY t.id"
-        )?;
+             ORDER BY t.id",
+            track_with_analysis_columns()
+        ))?;
 
-        let rows = stmt.query_map(params![&pattern, &prefix], |row| {
-            let track = Track {
-                id: row.get(0)?,
-                file_path: row.get(1)?,
-                file_hash: row.get(2)?,
-                title: row.get(3)?,
-                artist: row.get(4)?,
-                album: row.get(5)?,
-                album_artist: row.get(6)?,
-                track_number: row.get(7)?,
-                year: row.get(8)?,
-                label: row.get(9)?,
-                duration_ms: row.get(10)?,
-                file_format: row.get(11)?,
-                bitrate: row.get(12)?,
-                sample_rate: row.get(13)?,
-                file_size: row.get(14)?,
-                date_added: row.get(15)?,
-                date_modified: row.get(16)?,
-                play_count: row.get(17)?,
-                rating: row.get(18)?,
-                comment: row.get(19)?,
-                artwork_path: row.get(20)?,
-                genre: row.get(21)?,
-                genre_source: row.get(22)?,
-            };
-            let bpm: Option<f64> = row.get(23)?;
-            let bpm_conf: Option<f64> = row.get(24)?;
-            let musical_key: Option<String> = row.get(25)?;
-            let key_conf: Option<f64> = row.get(26)?;
-            Ok((track, bpm, bpm_conf, musical_key, key_conf))
-        })?;
+        let rows = stmt.query_map(params![&pattern, &prefix], track_with_analysis_from_row)?;
 
         rows.collect()
     }
@@ -1123,20 +4274,12 @@ impl Database {
         Ok(updated)
     }
 
-    /// Remove tracks that are NOT under any of the given library folder paths.
-    /// Used to clean up stray tracks (e.g., from Viber or other apps) that were accidentally imported.
-    /// Returns the number of deleted tracks.
-    /// OPTIMIZED: Uses pure SQL instead of loading all tracks into memory.
-    pub fn remove_tracks_not_in_folders(&self, library_folders: &[String]) -> Result<usize> {
+    /// WHERE condition matching tracks NOT under any of the folders, with its parameters.
+    /// With no folders it matches every track.
+    fn not_in_folders_clause(library_folders: &[String]) -> (String, Vec<String>) {
         if library_folders.is_empty() {
-            // No folders configured - delete ALL tracks
-            self.conn.execute("DELETE FROM track_analysis WHERE track_id IN (SELECT id FROM tracks)", [])?;
-            self.conn.execute("DELETE FROM playlist_tracks WHERE track_id IN (SELECT id FROM tracks)", [])?;
-            let count = self.conn.execute("DELETE FROM tracks", [])?;
-            return Ok(count);
+            return ("1".to_string(), Vec::new());
         }
-
-        // Build SQL WHERE clause to find tracks NOT in any library folder
         // Use "NOT (file_path LIKE 'folder1/%' OR file_path LIKE 'folder2/%' ...)"
         let mut conditions = Vec::new();
         let mut params: Vec<String> = Vec::new();
@@ -1147,47 +4290,80 @@ impl Database {
             } else {
                 format!("{}/", folder)
             };
-            conditions.push(format!("file_path LIKE ?{}", params.len() + 1));
-            params.push(format!("{}%", folder_normalized));
+            conditions.push(format!("file_path LIKE ?{} ESCAPE '\\'", params.len() + 1));
+            params.push(like_prefix(&folder_normalized));
         }
 
-        let where_clause = format!("NOT ({})", conditions.join(" OR "));
-
-        // Delete using SQL only - no need to load tracks into memory
-        let delete_query = format!(
-            "DELETE FROM tracks WHERE id IN (SELECT id FROM tracks WHERE {})",
-            where_clause
-        );
+        (format!("NOT ({})", conditions.join(" OR ")), params)
+    }
 
-        // Delete related data first
-        let analysis_query = format!(
-            "DELETE FROM track_analysis WHERE track_id IN (SELECT id FROM tracks WHERE {})",
-            where_clause
-        );
-        let playlist_query = format!(
-            "DELETE FROM playlist_tracks WHERE track_id IN (SELECT id FROM tracks WHERE {})",
+    /// IDs and paths of the tracks remove_tracks_not_in_folders would remove, by ID
+    pub fn get_tracks_not_in_folders(&self, library_folders: &[String]) -> Result<Vec<(i64, String)>> {
+        let (where_clause, params) = Self::not_in_folders_clause(library_folders);
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, file_path FROM tracks WHERE {} ORDER BY id",
             where_clause
-        );
-
-        // Execute deletions
-        let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|s| s as &dyn rusqlite::ToSql).collect();
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
 
-        self.conn.execute(&analysis_query, rusqlite::params_from_iter(params_refs.iter()))?;
-        self.conn.execute(&playlist_query, rusqlite::params_from_iter(params_refs.iter()))?;
-        let deleted = self.conn.execute(&delete_query, rusqlite::params_from_iter(params_refs.iter()))?;
+    /// Remove tracks that are NOT under any of the given library folder paths.
+    /// Used to clean up stray tracks (e.g., from Viber or other apps) that were accidentally imported.
+    /// Each goes with all its per-track data (delete_track_data), in one transaction.
+    /// Returns the number of deleted tracks.
+    pub fn remove_tracks_not_in_folders(&self, library_folders: &[String]) -> Result<usize> {
+        let stray = self.get_tracks_not_in_folders(library_folders)?;
+        let tx = self.transaction()?;
+        for (id, _) in &stray {
+            self.delete_track_data(*id)?;
+        }
+        tx.commit()?;
+        Ok(stray.len())
+    }
 
-        Ok(deleted)
+    /// Fill search_text for the tracks without it (new, or edited since). Returns how many.
+    pub fn refresh_search_text(&self) -> Result<usize> {
+        let stale: Vec<(i64, String)> = {
+            let mut stmt = self.conn.prepare(&format!(
+                "SELECT id, {} FROM tracks WHERE search_text IS NULL",
+                search_text::SEARCH_TEXT_COLUMNS.join(", ")
+            ))?;
+            let rows = stmt.query_map([], |row| {
+                let fields = (1..=search_text::SEARCH_TEXT_COLUMNS.len())
+                    .map(|i| row.get(i))
+                    .collect::<Result<Vec<Option<String>>>>()?;
+                Ok((row.get(0)?, search_text::search_text(&fields)))
+            })?;
+            rows.collect::<Result<_>>()?
+        };
+        if stale.is_empty() {
+            return Ok(0);
+        }
+        // Batched, unless the caller already is in a transaction
+        let tx = if self.conn.is_autocommit() { Some(self.transaction()?) } else { None };
+        {
+            let mut stmt = self.conn.prepare("UPDATE tracks SET search_text = ? WHERE id = ?")?;
+            for (id, text) in &stale {
+                stmt.execute(params![text, id])?;
+            }
+        }
+        if let Some(tx) = tx {
+            tx.commit()?;
+        }
+        Ok(stale.len())
     }
 
-    /// Search tracks by query string across text fields (title, artist, album, label, comment, file_path, genre)
-    /// Returns all tracks where any text field contains the query (case-insensitive)
+    /// Search tracks by query string across text fields (title, artist, album, label, comment, file_path, genre,
+    /// ISRC, catalog number)
+    /// Returns all tracks where any text field contains the query (case-insensitive), or whose
+    /// transliterated text does ("Zhu" finds "Жу", "Beyonce" finds "Beyoncé")
     pub fn search_tracks(&self, query: &str) -> Result<Vec<Track>> {
+        self.refresh_search_text()?;
         let like_pattern = format!("%{}%", query);
-        let mut stmt = self.conn.prepare(
-            "SELECT id, file_path, file_hash, title, artist, album, album_artist,
-                    track_number, year, label, duration_ms, file_format,
-                    bitrate, sample_rate, file_size, date_added, date_modified,
-                    play_count, rating, comment, artwork_path, genre, genre_source
+        let folded_pattern = search_text::like_pattern(query);
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}
              FROM tracks
              WHERE title LIKE ?1 COLLATE NOCASE
                 OR artist LIKE ?1 COLLATE NOCASE
@@ -1196,36 +4372,15 @@ impl Database {
                 OR comment LIKE ?1 COLLATE NOCASE
                 OR file_path LIKE ?1 COLLATE NOCASE
                 OR genre LIKE ?1 COLLATE NOCASE
-             ORDER BY id"
-        )?;
+                OR isrc LIKE ?1 COLLATE NOCASE
+                OR catalog_number LIKE ?1 COLLATE NOCASE
+                OR id IN (SELECT track_id FROM track_custom_fields WHERE value LIKE ?1 COLLATE NOCASE)
+                OR search_text LIKE ?2
+             ORDER BY id",
+            track_columns("")
+        ))?;
 
-        let tracks = stmt.query_map([&like_pattern], |row| {
-            Ok(Track {
-                id: row.get(0)?,
-                file_path: row.get(1)?,
-                file_hash: row.get(2)?,
-                title: row.get(3)?,
-                artist: row.get(4)?,
-                album: row.get(5)?,
-                album_artist: row.get(6)?,
-                track_number: row.get(7)?,
-                year: row.get(8)?,
-                label: row.get(9)?,
-                duration_ms: row.get(10)?,
-                file_format: row.get(11)?,
-                bitrate: row.get(12)?,
-                sample_rate: row.get(13)?,
-                file_size: row.get(14)?,
-                date_added: row.get(15)?,
-                date_modified: row.get(16)?,
-                play_count: row.get(17)?,
-                rating: row.get(18)?,
-                comment: row.get(19)?,
-                artwork_path: row.get(20)?,
-                genre: row.get(21)?,
-                genre_source: row.get(22)?,
-            })
-        })?;
+        let tracks = stmt.query_map(params![like_pattern, folded_pattern], Track::from_row)?;
 
         tracks.collect()
     }
@@ -1297,7 +4452,7 @@ impl Database {
             "SELECT genre, COUNT(*) FROM tracks
              WHERE genre IS NOT NULL
              GROUP BY genre
-             ORDER BY genre"
+             ORDER BY genre COLLATE UNICODE"
         )?;
 
         let rows = stmt.query_map([], |row| {
@@ -1307,53 +4462,146 @@ impl Database {
         rows.collect()
     }
 
+    /// Distinct values of a track column with track counts
+    pub fn get_distinct_values(&self, column: DistinctColumn) -> Result<Vec<(String, i64)>> {
+        let mut stmt = self.conn.prepare_cached(column.query())?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        rows.collect()
+    }
+
+    /// Groups of the tracks matching `filter`, with counts and totals, in display order
+    pub fn get_track_groups(&self, grouping: TrackGrouping, filter: &TrackFilter) -> Result<Vec<TrackGroup>> {
+        if filter.search.is_some() {
+            self.refresh_search_text()?;
+        }
+        let (where_sql, values) = filter.where_sql();
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} AS value, COUNT(*), COALESCE(SUM(t.duration_ms), 0),
+                    MIN(NULLIF(a.bpm, 0)), AVG(NULLIF(a.bpm, 0)), MAX(NULLIF(a.bpm, 0)),
+                    COALESCE(SUM(t.play_count), 0), MAX(t.date_added)
+             FROM tracks t
+             LEFT JOIN track_analysis a ON a.track_id = t.id
+             WHERE {}
+             GROUP BY value
+             ORDER BY {}",
+            grouping.value_sql(),
+            where_sql,
+            grouping.group_order()
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values), |row| {
+            Ok(TrackGroup {
+                value: row.get(0)?,
+                track_count: row.get(1)?,
+                total_duration_ms: row.get(2)?,
+                min_bpm: row.get(3)?,
+                avg_bpm: row.get(4)?,
+                max_bpm: row.get(5)?,
+                total_plays: row.get(6)?,
+                last_added: row.get(7)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// A page of the tracks in one group (`value` None: the tracks without a value)
+    pub fn get_track_group_members(
+        &self,
+        grouping: TrackGrouping,
+        value: Option<&str>,
+        filter: &TrackFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<TrackWithAnalysis>> {
+        if filter.search.is_some() {
+            self.refresh_search_text()?;
+        }
+        let (where_sql, mut values) = filter.where_sql();
+        values.push(value.map_or(rusqlite::types::Value::Null, |v| v.to_string().into()));
+        values.push(limit.into());
+        values.push(offset.into());
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}
+             FROM tracks t
+             LEFT JOIN track_analysis a ON a.track_id = t.id
+             WHERE {} AND ({}) IS ?
+             ORDER BY {}
+             LIMIT ? OFFSET ?",
+            track_with_analysis_columns(),
+            where_sql,
+            grouping.value_sql(),
+            grouping.member_order()
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values), track_with_analysis_from_row)?;
+        rows.collect()
+    }
+
     /// Get tracks by genre (with analysis data)
-    pub fn get_tracks_by_genre(&self, genre: &str) -> Result<Vec<(Track, Option<f64>, Option<f64>, Option<String>, Option<f64>)>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT t.id, t.file_path, t.file_hash, t.title, t.artist, t.album, t.album_artist,
-                    t.track_number, t.year, t.label, t.duration_ms, t.file_format,
-                    t.bitrate, t.sample_rate, t.file_size, t.date_added, t.date_modified,
-                    t.play_count, t.rating, t.comment, t.artwork_path, t.genre, t.genre_source,
-                    a.bpm, a.bpm_confidence, a.musical_key, a.key_confidence
+    pub fn get_tracks_by_genre(&self, genre: &str) -> Result<Vec<TrackWithAnalysis>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}
              FROM tracks t
              LEFT JOIN track_analysis a ON t.id = a.track_id
              WHERE t.genre = ?
-             ORDER BY t.id"
+             ORDER BY t.id",
+            track_with_analysis_columns()
+        ))?;
+
+        let rows = stmt.query_map([genre], track_with_analysis_from_row)?;
+
+        rows.collect()
+    }
+
+    /// (genre, BPM, key) of every track with a genre, for learning genre profiles
+    pub fn get_genre_bpm_keys(&self) -> Result<Vec<GenreSample>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.genre, a.bpm, a.musical_key
+             FROM tracks t
+             LEFT JOIN track_analysis a ON t.id = a.track_id
+             WHERE t.genre IS NOT NULL AND t.genre != ''"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    /// Replace all genre profiles
+    pub fn replace_genre_profiles(&self, profiles: &[GenreProfile]) -> Result<()> {
+        self.conn.execute("DELETE FROM genre_profiles", [])?;
+        let mut stmt = self.conn.prepare(
+            "INSERT INTO genre_profiles (genre, track_count, min_bpm, median_bpm, max_bpm, common_keys)
+             VALUES (?, ?, ?, ?, ?, ?)"
         )?;
+        for p in profiles {
+            stmt.execute(params![
+                p.genre,
+                p.track_count,
+                p.min_bpm,
+                p.median_bpm,
+                p.max_bpm,
+                p.common_keys.join(","),
+            ])?;
+        }
+        Ok(())
+    }
 
-        let rows = stmt.query_map([genre], |row| {
-            let track = Track {
-                id: row.get(0)?,
-                file_path: row.get(1)?,
-                file_hash: row.get(2)?,
-                title: row.get(3)?,
-                artist: row.get(4)?,
-                album: row.get(5)?,
-                album_artist: row.get(6)?,
-                track_number: row.get(7)?,
-                year: row.get(8)?,
-                label: row.get(9)?,
-                duration_ms: row.get(10)?,
-                file_format: row.get(11)?,
-                bitrate: row.get(12)?,
-                sample_rate: row.get(13)?,
-                file_size: row.get(14)?,
-                date_added: row.get(15)?,
-                date_modified: row.get(16)?,
-                play_count: row.get(17)?,
-                rating: row.get(18)?,
-                comment: row.get(19)?,
-                artwork_path: row.get(20)?,
-                genre: row.get(21)?,
-                genre_source: row.get(22)?,
-            };
-            let bpm: Option<f64> = row.get(23)?;
-            let bpm_conf: Option<f64> = row.get(24)?;
-            let musical_key: Option<String> = row.get(25)?;
-            let key_conf: Option<f64> = row.get(26)?;
-            Ok((track, bpm, bpm_conf, musical_key, key_conf))
+    /// Genre profiles, biggest genres first
+    pub fn get_genre_profiles(&self) -> Result<Vec<GenreProfile>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT genre, track_count, min_bpm, median_bpm, max_bpm, common_keys
+             FROM genre_profiles ORDER BY track_count DESC, genre"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let keys: String = row.get(5)?;
+            Ok(GenreProfile {
+                genre: row.get(0)?,
+                track_count: row.get(1)?,
+                min_bpm: row.get(2)?,
+                median_bpm: row.get(3)?,
+                max_bpm: row.get(4)?,
+                common_keys: keys.split(',').filter(|k| !k.is_empty()).map(String::from).collect(),
+            })
         })?;
-
         rows.collect()
     }
 
@@ -1388,6 +4636,22 @@ impl Database {
         genres.collect()
     }
 
+    /// Create a genre definition or update the color and sort order of the existing one
+    /// with this name. Returns true if the genre was created.
+    pub fn upsert_genre_definition(&self, name: &str, color: Option<&str>, sort_order: i32) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE genre_definitions SET color = ?, sort_order = ? WHERE name = ?",
+            params![color, sort_order, name],
+        )?;
+        if updated == 0 {
+            self.conn.execute(
+                "INSERT INTO genre_definitions (name, color, sort_order) VALUES (?, ?, ?)",
+                params![name, color, sort_order],
+            )?;
+        }
+        Ok(updated == 0)
+    }
+
     /// Delete a genre definition (does NOT remove genre from tracks)
     pub fn delete_genre_definition(&self, id: i64) -> Result<()> {
         self.conn.execute("DELETE FROM genre_definitions WHERE id = ?", [id])?;
@@ -1418,6 +4682,41 @@ impl Database {
         Ok(())
     }
 
+    /// Merge several genres into one: every track tagged with a name in `from_names`
+    /// is moved to `to_name` (keeping its genre_source). Definitions for the merged
+    /// names are removed; if `to_name` has no definition yet, the first merged
+    /// definition is renamed to it so its color is kept. Returns the number of tracks updated.
+    pub fn merge_genres(&self, from_names: &[String], to_name: &str) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut updated = 0;
+
+        let mut has_target_def: bool = tx.query_row(
+            "SELECT COUNT(*) > 0 FROM genre_definitions WHERE name = ?",
+            [to_name],
+            |row| row.get(0),
+        )?;
+
+        for name in from_names.iter().filter(|n| n.as_str() != to_name) {
+            updated += tx.execute(
+                "UPDATE tracks SET genre = ? WHERE genre = ?",
+                params![to_name, name],
+            )?;
+
+            if has_target_def {
+                tx.execute("DELETE FROM genre_definitions WHERE name = ?", [name])?;
+            } else {
+                let renamed = tx.execute(
+                    "UPDATE genre_definitions SET name = ? WHERE name = ?",
+                    params![to_name, name],
+                )?;
+                has_target_def = renamed > 0;
+            }
+        }
+
+        tx.commit()?;
+        Ok(updated)
+    }
+
     /// Bulk set genre for multiple tracks
     pub fn bulk_set_genre(&self, track_ids: &[i64], genre: &str) -> Result<usize> {
         let mut count = 0;
@@ -1427,38 +4726,350 @@ impl Database {
         }
         Ok(count)
     }
+
+    // --- Track lookup operations ---
+
+    /// Find a track ID by exact file path. Returns None if no track has this path.
+    pub fn find_track_id_by_path(&self, file_path: &str) -> Result<Option<i64>> {
+        match self.conn.query_row(
+            "SELECT id FROM tracks WHERE file_path = ?",
+            [file_path],
+            |row| row.get(0),
+        ) {
+            Ok(id) => Ok(Some(id)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Find a track ID by content hash. 'unknown' and pending hashes never match.
+    pub fn find_track_id_by_hash(&self, file_hash: &str) -> Result<Option<i64>> {
+        if file_hash == "unknown" || file_hash == PENDING_HASH {
+            return Ok(None);
+        }
+        match self.conn.query_row(
+            "SELECT id FROM tracks WHERE file_hash = ? ORDER BY id LIMIT 1",
+            [file_hash],
+            |row| row.get(0),
+        ) {
+            Ok(id) => Ok(Some(id)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // --- Cue point operations ---
+
+    /// Get all cue points for a track, ordered by position.
+    pub fn get_cue_points(&self, track_id: i64) -> Result<Vec<CuePoint>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, track_id, position_ms, label, color, type
+             FROM cue_points WHERE track_id = ? ORDER BY position_ms, id"
+        )?;
+
+        let cues = stmt.query_map([track_id], |row| {
+            Ok(CuePoint {
+                id: row.get(0)?,
+                track_id: row.get(1)?,
+                position_ms: row.get(2)?,
+                label: row.get(3)?,
+                color: row.get(4)?,
+                cue_type: row.get::<_, Option<String>>(5)?.unwrap_or_else(|| "cue".to_string()),
+            })
+        })?;
+
+        cues.collect()
+    }
+
+    /// Replace all cue points of a track with the given set (used by imports).
+    pub fn replace_cue_points(&self, track_id: i64, cues: &[CuePoint]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM cue_points WHERE track_id = ?", [track_id])?;
+        for cue in cues {
+            tx.execute(
+                "INSERT INTO cue_points (track_id, position_ms, label, color, type) VALUES (?, ?, ?, ?, ?)",
+                params![track_id, cue.position_ms, cue.label, cue.color, cue.cue_type],
+            )?;
+        }
+        tx.commit()
+    }
+
+    // --- Beat grid operations ---
+
+    /// Save (upsert) a track's beat grid.
+    pub fn save_beat_grid(&self, grid: &BeatGrid) -> Result<()> {
+        let beats_json = serde_json::to_string(&grid.beats)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        self.conn.execute(
+            "INSERT INTO track_beat_grids (track_id, bpm, first_beat_ms, beats, source, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, datetime('now'))
+             ON CONFLICT(track_id) DO UPDATE SET
+                bpm = excluded.bpm,
+                first_beat_ms = excluded.first_beat_ms,
+                beats = excluded.beats,
+                source = excluded.source,
+                updated_at = excluded.updated_at",
+            params![grid.track_id, grid.bpm, grid.first_beat_ms, beats_json, grid.source],
+        )?;
+        Ok(())
+    }
+
+    /// Get a track's beat grid. Returns None if the track has no grid.
+    pub fn get_beat_grid(&self, track_id: i64) -> Result<Option<BeatGrid>> {
+        let result = self.conn.query_row(
+            "SELECT track_id, bpm, first_beat_ms, beats, source FROM track_beat_grids WHERE track_id = ?",
+            [track_id],
+            |row| {
+                let beats_json: String = row.get(3)?;
+                let beats: Vec<(i64, u8)> = serde_json::from_str(&beats_json).map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e))
+                })?;
+                Ok(BeatGrid {
+                    track_id: row.get(0)?,
+                    bpm: row.get(1)?,
+                    first_beat_ms: row.get(2)?,
+                    beats,
+                    source: row.get(4)?,
+                })
+            },
+        );
+
+        match result {
+            Ok(grid) => Ok(Some(grid)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Track fixture for tests; override fields with `Track { .., ..create_test_track() }`
+#[cfg(test)]
+pub(crate) fn create_test_track() -> Track {
+    Track {
+        id: None,
+        file_path: "/path/to/test.mp3".to_string(),
+        file_hash: "abc123".to_string(),
+        title: Some("Test Track".to_string()),
+        artist: Some("Test Artist".to_string()),
+        album: Some("Test Album".to_string()),
+        album_artist: None,
+        track_number: Some(1),
+        year: Some(2024),
+        label: Some("Test Label".to_string()),
+        duration_ms: Some(240000),
+        file_format: Some("mp3".to_string()),
+        bitrate: Some(320),
+        sample_rate: Some(44100),
+        file_size: Some(10_000_000),
+        date_added: None,
+        date_modified: None,
+        play_count: 0,
+        rating: 0,
+        comment: None,
+        artwork_path: None,
+        genre: None,
+        genre_source: None,
+        energy_level: None,
+        color: None,
+        disc_number: None,
+        total_tracks: None,
+        isrc: None,
+        catalog_number: None,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn create_test_track() -> Track {
-        Track {
-            id: None,
-            file_path: "/path/to/test.mp3".to_string(),
-            file_hash: "abc123".to_string(),
-            title: Some("Test Track".to_string()),
-            artist: Some("Test Artist".to_string()),
-            album: Some("Test Album".to_string()),
-            album_artist: None,
-            track_number: Some(1),
-            year: Some(2024),
-            label: Some("Test Label".to_string()),
-            duration_ms: Some(240000),
-            file_format: Some("mp3".to_string()),
-            bitrate: Some(320),
-            sample_rate: Some(44100),
-            file_size: Some(10_000_000),
-            date_added: None,
-            date_modified: None,
-            play_count: 0,
-            rating: 0,
-            comment: None,
-            artwork_path: None,
-            genre: None,
-            genre_source: None,
+    #[test]
+    fn test_track_row_mapping() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        // Every column set to a distinct value, so a shifted index shows up
+        let track = Track {
+            album_artist: Some("Album Artist".to_string()),
+            play_count: 3,
+            rating: 4,
+            comment: Some("Comment".to_string()),
+            artwork_path: Some("/path/to/cover.jpg".to_string()),
+            genre: Some("Techno".to_string()),
+            genre_source: Some("user".to_string()),
+            energy_level: Some(7),
+            color: Some("#FF0000".to_string()),
+            disc_number: Some(2),
+            total_tracks: Some(12),
+            isrc: Some("GBAYE0601498".to_string()),
+            catalog_number: Some("CAT001".to_string()),
+            ..create_test_track()
+        };
+        let id = db.create_track(&track).unwrap();
+        db.save_bpm_analysis(id, 128.0, 0.9).unwrap();
+        db.save_key_analysis(id, "8A", 0.7).unwrap();
+
+        let stored = db.get_track(id).unwrap();
+        assert_eq!(stored, Track { id: Some(id), date_added: stored.date_added.clone(), ..track.clone() });
+        assert!(stored.date_added.is_some());
+        assert_eq!(db.get_all_tracks().unwrap(), vec![stored.clone()]);
+        assert_eq!(db.search_tracks("CAT001").unwrap(), vec![stored.clone()]);
+
+        let (with_analysis, bpm, bpm_confidence, key, key_confidence) =
+            db.get_all_tracks_with_analysis().unwrap().remove(0);
+        assert_eq!(with_analysis, stored);
+        assert_eq!((bpm, bpm_confidence, key.as_deref(), key_confidence), (Some(128.0), Some(0.9), Some("8A"), Some(0.7)));
+    }
+
+    #[test]
+    fn test_duplicate_groups_and_merge() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let mut a = create_test_track();
+        a.file_path = "/music/a/song.mp3".to_string();
+        a.play_count = 2;
+        a.genre = None;
+        let mut b = create_test_track();
+        b.file_path = "/music/b/Song.mp3".to_string();
+        b.file_hash = "other".to_string();
+        b.play_count = 3;
+        b.rating = 4;
+        b.genre = Some("Techno".to_string());
+        let mut c = create_test_track();
+        c.file_path = "/music/c/copy.mp3".to_string();
+        let mut d = create_test_track();
+        d.file_path = "/music/d/unrelated.mp3".to_string();
+        d.file_hash = "unique".to_string();
+        let ids: Vec<i64> = [a, b, c, d].iter().map(|t| db.create_track(t).unwrap()).collect();
+
+        let groups = db.find_duplicate_groups().unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].0, vec!["filename_size".to_string(), "hash".to_string()]);
+        assert_eq!(groups[0].1, vec![ids[0], ids[1], ids[2]]);
+
+        let playlist = db.create_playlist("Set", "manual", None).unwrap();
+        db.add_track_to_playlist(playlist, ids[1]).unwrap();
+        db.save_bpm_analysis(ids[1], 128.0, 0.9).unwrap();
+        db.save_verification(ids[1], "ok", None).unwrap();
+
+        db.merge_duplicate_track(ids[0], ids[1], true).unwrap();
+        let kept = db.get_track(ids[0]).unwrap();
+        assert_eq!(kept.play_count, 5);
+        assert_eq!(kept.rating, 4);
+        assert_eq!(kept.genre.as_deref(), Some("Techno"));
+        assert!(db.get_track(ids[1]).is_err());
+        assert_eq!(db.count_playlists_with_track(ids[0]).unwrap(), 1);
+        assert_eq!(db.get_track_analysis(ids[0]).unwrap().unwrap().bpm, Some(128.0));
+        // The removed copy's file check isn't moved, nor left for a reused ID
+        assert!(db.get_verification(ids[1]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_find_playlist_versions() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let track = |path: &str, title: &str, artist: &str| {
+            let mut t = create_test_track();
+            t.file_path = path.to_string();
+            t.file_hash = path.to_string();
+            t.title = Some(title.to_string());
+            t.artist = Some(artist.to_string());
+            db.create_track(&t).unwrap()
+        };
+        let extended = track("/a.mp3", "Strings of Life (Extended Mix)", "Rhythim Is Rhythim");
+        let radio = track("/b.mp3", "Strings Of Life [Radio Edit]", "Rhythim is Rhythim");
+        let other = track("/c.mp3", "Nude Photo", "Rhythim Is Rhythim");
+        let remaster = track("/d.mp3", "Different Title", "Someone");
+
+        let playlist = db.create_playlist("Classics", "manual", None).unwrap();
+        db.add_track_to_playlist(playlist, extended).unwrap();
+        db.add_track_to_playlist(playlist, other).unwrap();
+        db.add_track_to_playlist(playlist, remaster).unwrap();
+
+        assert_eq!(db.find_playlist_versions(playlist, radio).unwrap(), vec![(extended, "title_artist")]);
+        // The track itself doesn't count
+        assert!(db.find_playlist_versions(playlist, other).unwrap().is_empty());
+
+        for (id, acoustid) in [(remaster, "abc"), (radio, "abc")] {
+            db.conn.execute(
+                "INSERT INTO track_fingerprints (track_id, chromaprint, acoustid) VALUES (?1, ?2, ?3)",
+                params![id, format!("fp{}", id), acoustid],
+            ).unwrap();
+        }
+        assert_eq!(
+            db.find_playlist_versions(playlist, radio).unwrap(),
+            vec![(remaster, "fingerprint"), (extended, "title_artist")]
+        );
+    }
+
+    #[test]
+    fn test_playlist_auditions() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let mut ids = Vec::new();
+        for path in ["/a.mp3", "/b.mp3", "/c.mp3"] {
+            let mut t = create_test_track();
+            t.file_path = path.to_string();
+            t.file_hash = path.to_string();
+            ids.push(db.create_track(&t).unwrap());
         }
+        let playlist = db.create_playlist("Candidates", "manual", None).unwrap();
+        for &id in &ids {
+            db.add_track_to_playlist(playlist, id).unwrap();
+        }
+
+        db.mark_auditioned(playlist, ids[2]).unwrap();
+        db.mark_auditioned(playlist, ids[0]).unwrap();
+        db.mark_auditioned(playlist, ids[0]).unwrap();
+        let marked: Vec<i64> = db.get_playlist_auditions(playlist).unwrap().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(marked, vec![ids[0], ids[2]]);
+
+        // Removed tracks don't show up
+        db.remove_track_from_playlist(playlist, ids[2]).unwrap();
+        assert_eq!(db.get_playlist_auditions(playlist).unwrap().len(), 1);
+
+        assert_eq!(db.reset_playlist_auditions(playlist, Some(&[ids[1]])).unwrap(), 0);
+        assert_eq!(db.reset_playlist_auditions(playlist, None).unwrap(), 2);
+        assert!(db.get_playlist_auditions(playlist).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_with_busy_retry() {
+        let db = Database::new_in_memory().unwrap();
+        let mut calls = 0;
+        let result = db.with_busy_retry(|_| {
+            calls += 1;
+            if calls < 3 {
+                Err(rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY), None))
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let result: Result<()> = db.with_busy_retry(|_| Err(rusqlite::Error::QueryReturnedNoRows));
+        assert!(matches!(result, Err(rusqlite::Error::QueryReturnedNoRows)));
+    }
+
+    #[test]
+    fn test_begin_write_gives_up_in_bounded_time() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("library.db");
+        let holder = Database::new(&path).unwrap();
+        holder.run_migrations().unwrap();
+        let writer = Database::new(&path).unwrap();
+
+        let held = holder.begin_write().unwrap();
+        let started = std::time::Instant::now();
+        let err = writer.begin_write().map(|_| ()).unwrap_err();
+        assert!(is_busy(&err));
+        // Not BUSY_RETRIES x BUSY_TIMEOUT
+        assert!(started.elapsed() < Duration::from_secs(3), "{:?}", started.elapsed());
+
+        drop(held);
+        writer.begin_write().unwrap().commit().unwrap();
     }
 
     #[test]
@@ -1772,9 +5383,38 @@ mod tests {
         let db = Database::new_in_memory().unwrap();
         db.run_migrations().unwrap();
 
-        // Should not error when deleting a key that doesn't exist
-        let result = db.delete_setting("nonexistent");
-        assert!(result.is_ok());
+        // Should not error when deleting a key that doesn't exist
+        let result = db.delete_setting("nonexistent");
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_search_tracks_transliterated() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let mut track = create_test_track();
+        track.artist = Some("Жу".to_string());
+        track.title = Some("Déjà Vu".to_string());
+        let id = db.create_track(&track).unwrap();
+
+        let ids = |query: &str| -> Vec<i64> {
+            db.search_tracks(query).unwrap().into_iter().filter_map(|t| t.id).collect()
+        };
+        assert_eq!(ids("Zhu"), vec![id]);
+        assert_eq!(ids("deja vu"), vec![id]);
+        assert_eq!(ids("Жу"), vec![id]);
+        // Not across fields
+        assert!(ids("vu zhu").is_empty());
+
+        // Editing a field recomputes the key
+        db.conn.execute("UPDATE tracks SET artist = 'Кино' WHERE id = ?", [id]).unwrap();
+        assert!(ids("zhu").is_empty());
+        assert_eq!(ids("kino"), vec![id]);
+        assert_eq!(db.refresh_search_text().unwrap(), 0);
+
+        let filter = TrackFilter { search: Some("Kino".to_string()), ..Default::default() };
+        assert_eq!(db.get_track_groups(TrackGrouping::Artist, &filter).unwrap()[0].value.as_deref(), Some("Кино"));
     }
 
     #[test]
@@ -2230,6 +5870,212 @@ mod tests {
         assert_eq!(counts[1].1, 2);
     }
 
+    #[test]
+    fn test_get_distinct_values() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        for (i, (year, label, format)) in [(2019, "Drumcode", "MP3"), (2021, "Drumcode", "flac"), (0, "", "mp3")]
+            .into_iter()
+            .enumerate()
+        {
+            let mut track = create_test_track();
+            track.file_path = format!("/track{}.mp3", i);
+            track.file_hash = format!("hash{}", i);
+            track.year = Some(year);
+            track.label = Some(label.to_string());
+            track.file_format = Some(format.to_string());
+            let id = db.create_track(&track).unwrap();
+            db.save_key_analysis(id, if i == 0 { "8A" } else { "5A" }, 0.9).unwrap();
+        }
+
+        let values = |column| db.get_distinct_values(column).unwrap();
+        assert_eq!(values(DistinctColumn::Year), vec![("2021".to_string(), 1), ("2019".to_string(), 1)]);
+        assert_eq!(values(DistinctColumn::Label), vec![("Drumcode".to_string(), 2)]);
+        assert_eq!(values(DistinctColumn::Format), vec![("flac".to_string(), 1), ("mp3".to_string(), 2)]);
+        assert_eq!(values(DistinctColumn::Key), vec![("5A".to_string(), 2), ("8A".to_string(), 1)]);
+        assert!(values(DistinctColumn::Genre).is_empty());
+        assert_eq!(DistinctColumn::from_name("key"), Some(DistinctColumn::Key));
+    }
+
+    #[test]
+    fn test_quality_checks() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            let mut track = create_test_track();
+            track.file_path = format!("/track{}.flac", i);
+            track.file_hash = format!("hash{}", i);
+            ids.push(db.create_track(&track).unwrap());
+        }
+        db.save_bpm_analysis(ids[0], 124.0, 0.9).unwrap();
+        let check = |track_id, flags| QualityCheck { track_id, flags, cutoff_hz: Some(16_020.0), hum_hz: None, dc_offset: 0.0 };
+        db.save_quality_check(&check(ids[0], 3)).unwrap();
+        db.save_quality_check(&check(ids[1], 0)).unwrap();
+
+        assert_eq!(db.get_quality_issues(1).unwrap(), vec![check(ids[0], 3)]);
+        assert!(db.get_quality_issues(8).unwrap().is_empty());
+        let unchecked: Vec<i64> = db.get_tracks_without_quality_check().unwrap().into_iter().map(|t| t.0).collect();
+        assert_eq!(unchecked, vec![ids[2]]);
+        // Saving the check kept the BPM
+        assert_eq!(db.get_bpm_analysis(ids[0]).unwrap().map(|a| a.0), Some(124.0));
+    }
+
+    #[test]
+    fn test_custom_fields() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let track_id = db.create_track(&create_test_track()).unwrap();
+
+        let vinyl = db.create_custom_field_definition("Vinyl owned", "boolean").unwrap();
+        let shelf = db.create_custom_field_definition("Shelf", "text").unwrap();
+        assert!(db.create_custom_field_definition("vinyl OWNED", "text").is_err());
+        db.update_custom_field_definition(shelf, "Crate", -1).unwrap();
+        let names: Vec<String> = db.get_custom_field_definitions().unwrap().into_iter().map(|f| f.name).collect();
+        assert_eq!(names, vec!["Crate", "Vinyl owned"]);
+
+        db.set_track_custom_field(track_id, vinyl, Some("true")).unwrap();
+        db.set_track_custom_field(track_id, shelf, Some("B-12")).unwrap();
+        db.set_track_custom_field(track_id, shelf, Some("C-03")).unwrap();
+        assert_eq!(db.get_track_custom_fields(track_id).unwrap().get(&shelf).map(String::as_str), Some("C-03"));
+        // Custom values are searchable
+        assert_eq!(db.search_tracks("c-03").unwrap().len(), 1);
+
+        db.set_track_custom_field(track_id, shelf, None).unwrap();
+        db.delete_custom_field_definition(vinyl).unwrap();
+        assert!(db.get_all_custom_field_values().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_remove_tracks_not_in_folders() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        for (i, path) in ["/Music/Deep_House/a.mp3", "/Music/DeepXHouse/b.mp3", "/Music/House/c.mp3"].iter().enumerate() {
+            let track = Track { file_path: path.to_string(), file_hash: format!("hash{}", i), ..create_test_track() };
+            db.create_track(&track).unwrap();
+        }
+
+        // "_" in a folder name is not a wildcard
+        let folders = vec!["/Music/Deep_House/".to_string(), "/Music/House".to_string()];
+        let stray = db.get_tracks_not_in_folders(&folders).unwrap();
+        assert_eq!(stray.iter().map(|(_, path)| path.as_str()).collect::<Vec<_>>(), ["/Music/DeepXHouse/b.mp3"]);
+        assert_eq!(db.get_tracks_not_in_folders(&[]).unwrap().len(), 3);
+        assert_eq!(db.remove_tracks_not_in_folders(&folders).unwrap(), 1);
+        let paths: Vec<String> = db.get_all_tracks().unwrap().into_iter().map(|t| t.file_path).collect();
+        assert!(!paths.contains(&"/Music/DeepXHouse/b.mp3".to_string()));
+        assert_eq!(paths.len(), 2);
+
+        // A stray track's history and score don't carry over to the track that gets its ID next
+        let stray_id = db.create_track(&Track { file_path: "/Downloads/voice.mp3".to_string(), ..create_test_track() }).unwrap();
+        db.record_play(stray_id).unwrap();
+        db.recompute_track_scores().unwrap();
+        assert!(db.get_track_score(stray_id).unwrap().is_some());
+        assert_eq!(db.remove_tracks_not_in_folders(&folders).unwrap(), 1);
+        let reimported = db.create_track(&Track { file_path: "/Music/House/d.mp3".to_string(), ..create_test_track() }).unwrap();
+        assert_eq!(reimported, stray_id);
+        assert!(db.get_play_history().unwrap().iter().all(|p| p.track_id != reimported));
+        assert_eq!(db.get_track_score(reimported).unwrap(), None);
+    }
+
+    #[test]
+    fn test_track_deletes_clear_per_track_rows() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let history_of = |db: &Database, id: i64| db.get_play_history().unwrap().into_iter().filter(|p| p.track_id == id).count();
+
+        let id = db.create_track(&create_test_track()).unwrap();
+        db.record_play(id).unwrap();
+        db.delete_track(id).unwrap();
+        // IDs are reused: the next track mustn't inherit the deleted one's history
+        let reused = db.create_track(&create_test_track()).unwrap();
+        assert_eq!(reused, id);
+        assert_eq!(history_of(&db, reused), 0);
+
+        let copy = db.create_track(&Track { file_path: "/path/to/copy.mp3".to_string(), ..create_test_track() }).unwrap();
+        db.record_play(copy).unwrap();
+        assert_eq!(db.remove_duplicate_tracks().unwrap(), 1);
+        let reimported = db.create_track(&Track { file_path: "/path/to/other.mp3".to_string(), file_hash: "other".to_string(), ..create_test_track() }).unwrap();
+        assert_eq!(reimported, copy);
+        assert_eq!(history_of(&db, reimported), 0);
+    }
+
+    #[test]
+    fn test_analysis_cache_survives_reimport() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let track = create_test_track();
+        let id = db.create_track(&track).unwrap();
+        db.save_bpm_analysis(id, 124.0, 0.9).unwrap();
+        db.save_waveform(id, &[1, 2, 3], &[4, 5, 6]).unwrap();
+        db.conn.execute("DELETE FROM track_analysis WHERE track_id = ?", [id]).unwrap();
+        db.delete_track(id).unwrap();
+        assert_eq!(db.count_analysis_cache().unwrap(), 1);
+
+        // Same content at a new path
+        let moved = Track { file_path: "/elsewhere/test.mp3".to_string(), ..track.clone() };
+        let new_id = db.create_track(&moved).unwrap();
+        assert!(db.restore_cached_analysis(new_id, &moved.file_hash).unwrap());
+        let analysis = db.get_track_analysis(new_id).unwrap().unwrap();
+        assert_eq!(analysis.bpm, Some(124.0));
+        assert_eq!(db.get_waveform(new_id, "overview").unwrap(), Some(vec![1, 2, 3]));
+        assert_eq!(db.count_analysis_cache().unwrap(), 0);
+        assert!(!db.restore_cached_analysis(new_id, &moved.file_hash).unwrap());
+
+        // Clearing a live track's analysis caches it too; lazily hashed files are never cached
+        db.conn.execute("DELETE FROM track_analysis WHERE track_id = ?", [new_id]).unwrap();
+        assert_eq!(db.count_analysis_cache().unwrap(), 1);
+        let pending = Track { file_path: "/lazy.mp3".to_string(), file_hash: PENDING_HASH.to_string(), ..track };
+        let pending_id = db.create_track(&pending).unwrap();
+        db.save_bpm_analysis(pending_id, 100.0, 0.9).unwrap();
+        db.conn.execute("DELETE FROM track_analysis WHERE track_id = ?", [pending_id]).unwrap();
+        assert_eq!(db.count_analysis_cache().unwrap(), 1);
+        assert_eq!(db.clear_analysis_cache().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_playlist_snapshots() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let playlist_id = db.create_playlist("Warm-up", "manual", None).unwrap();
+        let ids: Vec<i64> = (0..3)
+            .map(|i| {
+                let mut track = create_test_track();
+                track.file_path = format!("/track{}.mp3", i);
+                track.file_hash = format!("hash{}", i);
+                db.create_track(&track).unwrap()
+            })
+            .collect();
+
+        // Filling an empty playlist: nothing worth keeping, and one burst
+        for &id in &ids {
+            db.add_track_to_playlist(playlist_id, id).unwrap();
+        }
+        assert!(db.get_playlist_snapshots(playlist_id).unwrap().is_empty());
+
+        // "Remove all" a minute later: one snapshot of the full playlist for the whole burst
+        db.conn.execute("UPDATE playlists SET updated_at = datetime('now', '-1 minute')", []).unwrap();
+        for &id in &ids {
+            db.remove_track_from_playlist(playlist_id, id).unwrap();
+        }
+        let snapshots = db.get_playlist_snapshots(playlist_id).unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!((snapshots[0].track_ids.clone(), snapshots[0].reason.as_str()), (ids.clone(), "auto"));
+
+        // Restore (a deleted track is skipped), snapshotting the empty state first
+        db.delete_track(ids[1]).unwrap();
+        assert_eq!(db.restore_playlist_snapshot(snapshots[0].id).unwrap(), 2);
+        assert_eq!(db.get_playlist_track_ids(playlist_id).unwrap(), vec![ids[0], ids[2]]);
+        let latest = &db.get_playlist_snapshots(playlist_id).unwrap()[0];
+        assert_eq!((latest.track_ids.len(), latest.reason.as_str()), (0, "restore"));
+
+        // On-demand snapshots skip unchanged state
+        assert!(db.snapshot_playlist(playlist_id, "manual").unwrap().is_some());
+        assert!(db.snapshot_playlist(playlist_id, "manual").unwrap().is_none());
+    }
+
     #[test]
     fn test_get_tracks_by_genre() {
         let db = Database::new_in_memory().unwrap();
@@ -2350,4 +6196,734 @@ mod tests {
         assert_eq!(db.get_track_genre(id2).unwrap().unwrap().0, "Tech House");
         assert_eq!(db.get_track_genre(id3).unwrap().unwrap().0, "Tech House");
     }
+
+    #[test]
+    fn test_find_track_id_by_path_and_hash() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let id = db.create_track(&create_test_track()).unwrap();
+
+        assert_eq!(db.find_track_id_by_path("/path/to/test.mp3").unwrap(), Some(id));
+        assert_eq!(db.find_track_id_by_path("/other.mp3").unwrap(), None);
+        assert_eq!(db.find_track_id_by_hash("abc123").unwrap(), Some(id));
+        assert_eq!(db.find_track_id_by_hash("unknown").unwrap(), None);
+    }
+
+    #[test]
+    fn test_replace_cue_points() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let track_id = db.create_track(&create_test_track()).unwrap();
+
+        let cue = |pos: i64, cue_type: &str| CuePoint {
+            id: None,
+            track_id,
+            position_ms: pos,
+            label: None,
+            color: None,
+            cue_type: cue_type.to_string(),
+        };
+
+        db.replace_cue_points(track_id, &[cue(5000, "cue"), cue(1000, "cue")]).unwrap();
+        let cues = db.get_cue_points(track_id).unwrap();
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].position_ms, 1000);
+
+        // Replacing drops the old set
+        db.replace_cue_points(track_id, &[cue(2000, "loop_start")]).unwrap();
+        let cues = db.get_cue_points(track_id).unwrap();
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].cue_type, "loop_start");
+    }
+
+    #[test]
+    fn test_tag_rating_and_cues_keep_user_values() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let track_id = db.create_track(&create_test_track()).unwrap();
+
+        assert!(db.save_tag_rating(track_id, 3, TAG_SOURCE).unwrap());
+        assert!(db.save_tag_rating(track_id, 4, TAG_SOURCE).unwrap());
+        let mut track = db.get_track(track_id).unwrap();
+        assert_eq!(track.rating, 4);
+        track.rating = 2;
+        db.update_track(&track).unwrap();
+        assert!(!db.save_tag_rating(track_id, 5, TAG_SOURCE).unwrap());
+        assert_eq!(db.get_track(track_id).unwrap().rating, 2);
+
+        let cue = |pos: i64| CuePoint {
+            id: None,
+            track_id,
+            position_ms: pos,
+            label: None,
+            color: Some("#CC0000".to_string()),
+            cue_type: "cue".to_string(),
+        };
+        assert!(db.save_tag_cues(track_id, &[cue(1000), cue(2000)], SERATO_SOURCE).unwrap());
+        assert!(db.save_tag_cues(track_id, &[cue(3000)], SERATO_SOURCE).unwrap());
+        assert_eq!(db.get_cue_points(track_id).unwrap().len(), 1);
+        db.replace_cue_points(track_id, &[cue(500)]).unwrap();
+        assert!(!db.save_tag_cues(track_id, &[cue(3000)], SERATO_SOURCE).unwrap());
+        assert_eq!(db.get_cue_points(track_id).unwrap()[0].position_ms, 500);
+    }
+
+    #[test]
+    fn test_fill_portable_analysis() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let track_id = db.create_track(&create_test_track()).unwrap();
+        assert_eq!(db.get_portable_analysis(track_id).unwrap(), None);
+
+        db.save_tag_bpm(track_id, 125.0, TAG_SOURCE).unwrap();
+        db.save_silence(track_id, 200, 0).unwrap();
+        let sidecar = PortableAnalysis {
+            bpm: Some(124.0),
+            bpm_confidence: Some(0.9),
+            musical_key: Some("8A".to_string()),
+            key_confidence: Some(0.7),
+            silence_lead_ms: Some(50),
+            silence_tail_ms: Some(1500),
+            waveform_overview: Some(vec![1, 2, 3]),
+        };
+        // Tags preferred (the default): the tag BPM stays, missing values are filled
+        db.fill_portable_analysis(track_id, &sidecar).unwrap();
+        let stored = db.get_portable_analysis(track_id).unwrap().unwrap();
+        assert_eq!(stored.bpm, Some(125.0));
+        assert_eq!(stored.musical_key.as_deref(), Some("8A"));
+        assert_eq!((stored.silence_lead_ms, stored.silence_tail_ms), (Some(200), Some(0)));
+        assert_eq!(stored.waveform_overview, Some(vec![1, 2, 3]));
+
+        db.set_setting(ANALYSIS_PRIORITY_SETTING, "analysis").unwrap();
+        db.fill_portable_analysis(track_id, &sidecar).unwrap();
+        assert_eq!(db.get_bpm_analysis(track_id).unwrap(), Some((124.0, 0.9)));
+        assert_eq!(db.needs_analysis(track_id).unwrap(), (false, false));
+
+        let cue = CuePoint {
+            id: None,
+            track_id,
+            position_ms: 1000,
+            label: None,
+            color: None,
+            cue_type: "cue".to_string(),
+        };
+        assert!(db.fill_cue_points(track_id, std::slice::from_ref(&cue)).unwrap());
+        assert!(!db.fill_cue_points(track_id, &[cue]).unwrap());
+        assert_eq!(db.get_cue_points(track_id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_save_and_get_beat_grid() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let track_id = db.create_track(&create_test_track()).unwrap();
+
+        assert!(db.get_beat_grid(track_id).unwrap().is_none());
+
+        let grid = BeatGrid {
+            track_id,
+            bpm: Some(128.0),
+            first_beat_ms: Some(120),
+            beats: vec![(120, 1), (589, 2), (1058, 3)],
+            source: "rekordbox".to_string(),
+        };
+        db.save_beat_grid(&grid).unwrap();
+        assert_eq!(db.get_beat_grid(track_id).unwrap(), Some(grid));
+    }
+
+    #[test]
+    fn test_sync_folder_playlists() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        for (i, path) in ["/crates/house/a.mp3", "/crates/house/deep/b.mp3", "/crates/c.mp3", "/other/d.mp3"]
+            .iter()
+            .enumerate()
+        {
+            let mut track = create_test_track();
+            track.file_path = path.to_string();
+            track.file_hash = format!("hash{}", i);
+            db.create_track(&track).unwrap();
+        }
+
+        let count = db.sync_folder_playlists("/crates/").unwrap();
+        assert_eq!(count, 3); // crates, house, deep
+
+        let playlists = db.get_all_playlists().unwrap();
+        let by_folder = |f: &str| playlists.iter().find(|p| p.source_folder.as_deref() == Some(f)).unwrap().clone();
+        let root = by_folder("/crates");
+        let house = by_folder("/crates/house");
+        let deep = by_folder("/crates/house/deep");
+        assert_eq!(root.playlist_type, "mirror");
+        assert_eq!(house.parent_id, root.id);
+        assert_eq!(deep.parent_id, house.id);
+        assert_eq!(db.count_playlist_tracks(root.id.unwrap()).unwrap(), 1);
+        assert_eq!(db.count_playlist_tracks(house.id.unwrap()).unwrap(), 1);
+
+        // Re-sync is stable: same playlists, no duplicates
+        db.sync_folder_playlists("/crates").unwrap();
+        assert_eq!(db.get_all_playlists().unwrap().len(), 3);
+
+        // Removing the only track in a subfolder drops its playlist
+        let deep_track = db.find_track_id_by_path("/crates/house/deep/b.mp3").unwrap().unwrap();
+        db.remove_track_from_playlist(deep.id.unwrap(), deep_track).unwrap();
+        db.delete_track(deep_track).unwrap();
+        assert_eq!(db.sync_folder_playlists("/crates").unwrap(), 2);
+        assert_eq!(db.get_all_playlists().unwrap().len(), 2);
+
+        db.delete_folder_playlists("/crates").unwrap();
+        assert!(db.get_all_playlists().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_folder_playlists_with_wildcards_in_path() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        for (i, path) in ["/Music/Deep_House/a.mp3", "/Music/DeepXHouse/sub/b.mp3"].iter().enumerate() {
+            let track = Track { file_path: path.to_string(), file_hash: format!("hash{}", i), ..create_test_track() };
+            db.create_track(&track).unwrap();
+        }
+
+        assert_eq!(db.sync_folder_playlists("/Music/DeepXHouse").unwrap(), 2);
+        // "_" doesn't match the sibling folder's tracks or mirror playlists
+        assert_eq!(db.sync_folder_playlists("/Music/Deep_House").unwrap(), 1);
+        let folders = |db: &Database| {
+            let mut folders: Vec<String> = db.get_all_playlists().unwrap().into_iter().filter_map(|p| p.source_folder).collect();
+            folders.sort();
+            folders
+        };
+        assert_eq!(folders(&db), ["/Music/DeepXHouse", "/Music/DeepXHouse/sub", "/Music/Deep_House"]);
+
+        db.delete_folder_playlists("/Music/Deep_House").unwrap();
+        assert_eq!(folders(&db), ["/Music/DeepXHouse", "/Music/DeepXHouse/sub"]);
+    }
+
+    #[test]
+    fn test_merge_genres() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let mut ids = Vec::new();
+        for (i, genre) in ["Tech-House", "tech house", "TECH HOUSE", "Techno"].iter().enumerate() {
+            let mut track = create_test_track();
+            track.file_path = format!("/track{}.mp3", i);
+            track.file_hash = format!("hash{}", i);
+            let id = db.create_track(&track).unwrap();
+            db.save_track_genre(id, genre, "tag").unwrap();
+            ids.push(id);
+        }
+        db.create_genre_definition("Tech-House", Some("#ff0000")).unwrap();
+        db.create_genre_definition("tech house", None).unwrap();
+
+        let updated = db
+            .merge_genres(
+                &["Tech-House".to_string(), "tech house".to_string(), "TECH HOUSE".to_string()],
+                "Tech House",
+            )
+            .unwrap();
+        assert_eq!(updated, 3);
+
+        let genres = db.get_all_genres_with_counts().unwrap();
+        assert_eq!(genres, vec![("Tech House".to_string(), 3), ("Techno".to_string(), 1)]);
+        // Source is preserved
+        assert_eq!(db.get_track_genre(ids[0]).unwrap().unwrap().1, "tag");
+
+        // First definition was renamed (color kept), the other removed
+        let defs = db.get_all_genre_definitions().unwrap();
+        assert_eq!(defs.len(), 1);
+        assert_eq!(defs[0].name, "Tech House");
+        assert_eq!(defs[0].color.as_deref(), Some("#ff0000"));
+    }
+
+    #[test]
+    fn test_record_play_and_scores() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            let mut track = create_test_track();
+            track.file_path = format!("/track{}.mp3", i);
+            track.file_hash = format!("hash{}", i);
+            ids.push(db.create_track(&track).unwrap());
+        }
+
+        // Track 0: three plays. Track 1: one play. Track 2: unplayed but rated 2.
+        for _ in 0..3 {
+            db.record_play(ids[0]).unwrap();
+        }
+        db.record_play(ids[1]).unwrap();
+        let mut rated = db.get_track(ids[2]).unwrap();
+        rated.rating = 2;
+        db.update_track(&rated).unwrap();
+
+        assert_eq!(db.get_track(ids[0]).unwrap().play_count, 3);
+
+        db.recompute_track_scores().unwrap();
+        let score0 = db.get_track_score(ids[0]).unwrap().unwrap();
+        let score1 = db.get_track_score(ids[1]).unwrap().unwrap();
+        let score2 = db.get_track_score(ids[2]).unwrap().unwrap();
+        assert!(score0 > score2 && score2 > score1);
+
+        let sorted = db.get_tracks_with_analysis_paginated(10, 0, Some("score"), false).unwrap();
+        let order: Vec<i64> = sorted.iter().map(|(t, ..)| t.id.unwrap()).collect();
+        assert_eq!(order, vec![ids[0], ids[2], ids[1]]);
+    }
+
+    #[test]
+    fn test_album_sort_uses_disc_number() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        // Disc 2 track 1 is created first; album order must still put disc 1 first
+        let mut ids = Vec::new();
+        for (i, (disc, number)) in [(2, 1), (1, 2), (1, 1)].into_iter().enumerate() {
+            let mut track = create_test_track();
+            track.file_path = format!("/album/{}.mp3", i);
+            track.file_hash = format!("hash{}", i);
+            track.disc_number = Some(disc);
+            track.track_number = Some(number);
+            track.total_tracks = Some(2);
+            ids.push(db.create_track(&track).unwrap());
+        }
+
+        assert_eq!(db.get_track(ids[0]).unwrap().disc_number, Some(2));
+        let sorted = db.get_tracks_with_analysis_paginated(10, 0, Some("album"), false).unwrap();
+        let order: Vec<i64> = sorted.iter().map(|(t, ..)| t.id.unwrap()).collect();
+        assert_eq!(order, vec![ids[2], ids[1], ids[0]]);
+    }
+
+
+    #[test]
+    fn test_skips_and_most_skipped() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            let mut track = create_test_track();
+            track.file_path = format!("/track{}.mp3", i);
+            track.file_hash = format!("hash{}", i);
+            ids.push(db.create_track(&track).unwrap());
+        }
+
+        // Track 0: skipped twice. Track 1: skipped once, played through once. Track 2: played through.
+        for _ in 0..2 {
+            let play = db.record_play(ids[0]).unwrap();
+            db.finish_play(play, 5_000, true).unwrap();
+        }
+        let play = db.record_play(ids[1]).unwrap();
+        db.finish_play(play, 10_000, true).unwrap();
+        let play = db.record_play(ids[1]).unwrap();
+        db.finish_play(play, 240_000, false).unwrap();
+        let play = db.record_play(ids[2]).unwrap();
+        db.finish_play(play, 300_000, false).unwrap();
+
+        let skipped = db.get_most_skipped(10).unwrap();
+        let summary: Vec<(i64, i64, i64)> = skipped
+            .iter()
+            .map(|(t, skips, plays)| (t.id.unwrap(), *skips, *plays))
+            .collect();
+        assert_eq!(summary, vec![(ids[0], 2, 2), (ids[1], 1, 2)]);
+
+        assert_eq!(db.get_most_skipped(1).unwrap().len(), 1);
+    }
+
+
+    #[test]
+    fn test_tag_values_respect_analysis_priority() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let track_id = db.create_track(&create_test_track()).unwrap();
+
+        // Fresh track: both tag values are stored and count as analyzed by default
+        assert!(db.save_tag_bpm(track_id, 124.0, TAG_SOURCE).unwrap());
+        assert!(db.save_tag_key(track_id, "8A", TAG_SOURCE).unwrap());
+        assert_eq!(db.needs_analysis(track_id).unwrap(), (false, false));
+
+        // Preferring analysis: tag values must be re-analyzed...
+        db.set_setting(ANALYSIS_PRIORITY_SETTING, "analysis").unwrap();
+        assert_eq!(db.needs_analysis(track_id).unwrap(), (true, true));
+
+        // ...and once analyzed, tags no longer overwrite them
+        db.save_key_analysis(track_id, "9A", 0.7).unwrap();
+        assert!(!db.save_tag_key(track_id, "8A", TAG_SOURCE).unwrap());
+        assert_eq!(db.get_key_analysis(track_id).unwrap().unwrap().0, "9A");
+        assert_eq!(db.needs_analysis(track_id).unwrap(), (true, false));
+
+        // Preferring tags again: the tag value wins
+        db.set_setting(ANALYSIS_PRIORITY_SETTING, "tag").unwrap();
+        assert!(db.save_tag_key(track_id, "8A", TAG_SOURCE).unwrap());
+        assert_eq!(db.get_key_analysis(track_id).unwrap().unwrap(), ("8A".to_string(), TAG_VALUE_CONFIDENCE));
+    }
+
+
+    #[test]
+    fn test_energy_level_roundtrip() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let mut track = create_test_track();
+        track.energy_level = Some(6);
+        let track_id = db.create_track(&track).unwrap();
+        assert_eq!(db.get_track(track_id).unwrap().energy_level, Some(6));
+
+        db.set_track_energy(track_id, Some(8)).unwrap();
+        assert_eq!(db.get_track(track_id).unwrap().energy_level, Some(8));
+
+        db.set_track_energy(track_id, None).unwrap();
+        assert_eq!(db.get_track(track_id).unwrap().energy_level, None);
+    }
+
+    #[test]
+    fn test_track_groups() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        // (genre, BPM, key, title)
+        let specs = [
+            (Some("House"), Some(124.0), Some("8A"), "Sunrise"),
+            (Some("House"), Some(128.0), Some("9A"), "Afterglow"),
+            (Some("Techno"), Some(132.0), Some("8A"), "Warehouse"),
+            (None, None, None, "Untagged"),
+        ];
+        let mut ids = Vec::new();
+        for (i, (genre, bpm, key, title)) in specs.iter().enumerate() {
+            let mut track = create_test_track();
+            track.file_path = format!("/track{}.mp3", i);
+            track.file_hash = format!("hash{}", i);
+            track.genre = genre.map(String::from);
+            track.title = Some(title.to_string());
+            let id = db.create_track(&track).unwrap();
+            if let (Some(bpm), Some(key)) = (bpm, key) {
+                db.save_bpm_analysis(id, *bpm, 0.9).unwrap();
+                db.save_key_analysis(id, key, 0.9).unwrap();
+            }
+            ids.push(id);
+        }
+
+        let all = TrackFilter::default();
+        let genres = db.get_track_groups(TrackGrouping::Genre, &all).unwrap();
+        let summary: Vec<_> = genres.iter().map(|g| (g.value.as_deref(), g.track_count)).collect();
+        assert_eq!(summary, vec![(Some("House"), 2), (Some("Techno"), 1), (None, 1)]);
+        assert_eq!((genres[0].min_bpm, genres[0].avg_bpm, genres[0].max_bpm), (Some(124.0), Some(126.0), Some(128.0)));
+        assert_eq!(genres[0].total_duration_ms, 480_000);
+
+        let decades = db.get_track_groups(TrackGrouping::BpmDecade, &all).unwrap();
+        let summary: Vec<_> = decades.iter().map(|g| (g.value.as_deref(), g.track_count)).collect();
+        assert_eq!(summary, vec![(Some("120"), 2), (Some("130"), 1), (None, 1)]);
+
+        let house = TrackFilter { genres: vec!["house".to_string()], ..Default::default() };
+        let keys = db.get_track_groups(TrackGrouping::Key, &house).unwrap();
+        assert_eq!(keys.iter().map(|g| g.value.as_deref()).collect::<Vec<_>>(), vec![Some("8A"), Some("9A")]);
+
+        let members = db.get_track_group_members(TrackGrouping::Key, Some("8A"), &all, 10, 0).unwrap();
+        assert_eq!(members.iter().map(|m| m.0.id.unwrap()).collect::<Vec<_>>(), vec![ids[0], ids[2]]);
+        let page = db.get_track_group_members(TrackGrouping::Key, Some("8A"), &all, 1, 1).unwrap();
+        assert_eq!(page[0].0.id, Some(ids[2]));
+        let ungrouped = db.get_track_group_members(TrackGrouping::Genre, None, &all, 10, 0).unwrap();
+        assert_eq!(ungrouped[0].0.id, Some(ids[3]));
+
+        let search = TrackFilter { search: Some("glow".to_string()), min_bpm: Some(125.0), ..Default::default() };
+        let groups = db.get_track_groups(TrackGrouping::Genre, &search).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].track_count, 1);
+    }
+
+    #[test]
+    fn test_track_cooldowns() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let cooled = db.create_track(&create_test_track()).unwrap();
+        let mut other = create_test_track();
+        other.file_path = "/music/other.mp3".to_string();
+        other.file_hash = "other".to_string();
+        let expired = db.create_track(&other).unwrap();
+
+        // Cooldowns end on their date: only future dates are active
+        db.set_track_cooldown(cooled, Some("2999-01-01")).unwrap();
+        db.set_track_cooldown(expired, Some("2000-01-01")).unwrap();
+        let active = db.get_active_cooldowns().unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active.get(&cooled).map(String::as_str), Some("2999-01-01"));
+
+        db.set_track_cooldown(cooled, None).unwrap();
+        assert!(db.get_active_cooldowns().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_play_stats() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            let mut track = create_test_track();
+            track.file_path = format!("/track{}.mp3", i);
+            track.file_hash = format!("hash{}", i);
+            ids.push(db.create_track(&track).unwrap());
+        }
+
+        // Track 0: played 200 days ago, then played and skipped today. Track 1: unplayed,
+        // in cooldown. Track 2: neither.
+        let old = db.record_play(ids[0]).unwrap();
+        db.conn.execute(
+            "UPDATE play_history SET played_at = datetime('now', '-200 days') WHERE id = ?",
+            [old],
+        ).unwrap();
+        let stamp = db.get_play_history_stamp().unwrap();
+        let recent = db.record_play(ids[0]).unwrap();
+        db.finish_play(recent, 5_000, true).unwrap();
+        assert_ne!(db.get_play_history_stamp().unwrap(), stamp);
+        db.set_track_cooldown(ids[1], Some("2999-01-01")).unwrap();
+
+        let stats = db.get_play_stats().unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[&ids[0]], PlayStats { skips: 1, days_since_played: Some(0), cooldown_until: None });
+        assert_eq!(stats[&ids[1]], PlayStats {
+            skips: 0,
+            days_since_played: None,
+            cooldown_until: Some("2999-01-01".to_string()),
+        });
+
+        // Only the old play left
+        db.conn.execute("DELETE FROM play_history WHERE id = ?", [recent]).unwrap();
+        assert_eq!(db.get_play_stats().unwrap()[&ids[0]].days_since_played, Some(200));
+    }
+
+    #[test]
+    fn test_remove_and_restore_track() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let mut track = create_test_track();
+        track.file_path = "/music/Deep_House/a.mp3".to_string();
+        let track_id = db.create_track(&track).unwrap();
+        db.save_bpm_analysis(track_id, 124.0, 0.9).unwrap();
+        db.add_track_tag(track_id, "warmup").unwrap();
+        let playlist_id = db.create_playlist("Set", "manual", None).unwrap();
+        db.add_track_to_playlist(playlist_id, track_id).unwrap();
+        db.fill_cue_points(track_id, &[CuePoint {
+            id: None,
+            track_id,
+            position_ms: 1500,
+            label: Some("Drop".to_string()),
+            color: None,
+            cue_type: "cue".to_string(),
+        }]).unwrap();
+
+        assert_eq!(db.get_track_paths_under("/music/Deep_House").unwrap(), vec![(track_id, track.file_path.clone())]);
+        assert_eq!(db.get_track_paths_under("/music/Deep_House/a.mp3").unwrap().len(), 1);
+        assert!(db.get_track_paths_under("/music/DeepXHouse").unwrap().is_empty());
+
+        assert_eq!(db.mark_tracks_missing(&[track_id]).unwrap(), vec![track_id]);
+        assert!(db.mark_tracks_missing(&[track_id]).unwrap().is_empty());
+        assert_eq!(db.get_missing_tracks().unwrap()[0].track_id, track_id);
+        assert_eq!(db.clear_tracks_missing(&[track_id]).unwrap(), 1);
+        assert!(db.get_missing_tracks().unwrap().is_empty());
+
+        let snapshot = db.snapshot_track(track_id).unwrap();
+        db.delete_track_data(track_id).unwrap();
+        assert!(db.get_track(track_id).is_err());
+        assert!(db.get_playlist_tracks(playlist_id).unwrap().is_empty());
+        assert!(db.get_cue_points(track_id).unwrap().is_empty());
+
+        // Survives the journal's JSON round trip
+        let snapshot: serde_json::Value = serde_json::from_str(&snapshot.to_string()).unwrap();
+        assert_eq!(db.restore_track(&snapshot).unwrap(), Some(track_id));
+        let restored = db.get_playlist_tracks(playlist_id).unwrap();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].0.title, track.title);
+        assert_eq!(restored[0].1, Some(124.0));
+        assert_eq!(db.get_track_tags(track_id).unwrap(), vec!["warmup".to_string()]);
+        assert_eq!(db.get_cue_points(track_id).unwrap()[0].label.as_deref(), Some("Drop"));
+        // Its path is taken now
+        assert_eq!(db.restore_track(&snapshot).unwrap(), None);
+
+        // A reused ID: restored under a new one
+        db.delete_track_data(track_id).unwrap();
+        let mut other = create_test_track();
+        other.file_path = "/music/other.mp3".to_string();
+        other.file_hash = "other".to_string();
+        db.conn.execute(
+            "INSERT INTO tracks (id, file_path, file_hash) VALUES (?, ?, ?)",
+            params![track_id, other.file_path, other.file_hash],
+        ).unwrap();
+        let new_id = db.restore_track(&snapshot).unwrap().unwrap();
+        assert_ne!(new_id, track_id);
+        assert_eq!(db.get_playlist_tracks(playlist_id).unwrap()[0].0.id, Some(new_id));
+    }
+
+    #[test]
+    fn test_get_track_loudness() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let track_id = db.create_track(&create_test_track()).unwrap();
+        assert!(db.get_track_loudness(&[track_id]).unwrap().is_empty());
+        db.save_bpm_analysis(track_id, 124.0, 0.9).unwrap();
+        assert!(db.get_track_loudness(&[track_id]).unwrap().is_empty());
+        db.save_loudness_analysis(track_id, -9.5).unwrap();
+        assert_eq!(db.get_track_loudness(&[track_id, 99]).unwrap(), HashMap::from([(track_id, -9.5)]));
+        assert_eq!(db.get_bpm_analysis(track_id).unwrap(), Some((124.0, 0.9)));
+        assert!(db.get_track_loudness(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_analysis_failures() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let track_id = db.create_track(&create_test_track()).unwrap();
+        assert_eq!(db.record_analysis_failure(track_id, "decode", "Decode error: bad frame").unwrap(), 1);
+        assert_eq!(db.record_analysis_failure(track_id, "decode", "Decode error: bad frame").unwrap(), 2);
+        assert!(db.get_analysis_skipped_tracks(3).unwrap().is_empty());
+        assert_eq!(db.record_analysis_failure(track_id, "missing_file", "Audio file not found").unwrap(), 3);
+        assert_eq!(db.get_analysis_skipped_tracks(3).unwrap(), HashSet::from([track_id]));
+
+        let failures = db.get_analysis_failures().unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!((failures[0].error_class.as_str(), failures[0].attempts), ("missing_file", 3));
+
+        // A changed file starts over
+        db.conn.execute("UPDATE tracks SET file_hash = 'new' WHERE id = ?", [track_id]).unwrap();
+        assert!(db.get_analysis_failures().unwrap().is_empty());
+        assert!(db.get_analysis_skipped_tracks(3).unwrap().is_empty());
+        assert_eq!(db.record_analysis_failure(track_id, "decode", "Decode error").unwrap(), 1);
+
+        assert_eq!(db.reset_analysis_failures(Some(&[99])).unwrap(), Vec::<i64>::new());
+        assert_eq!(db.reset_analysis_failures(None).unwrap(), vec![track_id]);
+        assert!(db.get_analysis_failures().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_ai_usage() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        db.record_ai_usage("chat", 1, 1000, 200).unwrap();
+        db.record_ai_usage("chat", 2, 500, 100).unwrap();
+        db.record_ai_usage("playlist_generation", 1, 30_000, 900).unwrap();
+        db.conn.execute(
+            "INSERT INTO ai_usage (month, purpose, requests, input_tokens, output_tokens)
+             VALUES ('2000-01', 'chat', 1, 10, 10)",
+            [],
+        ).unwrap();
+
+        let usage = db.get_ai_usage(1).unwrap();
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].purpose, "chat");
+        assert_eq!((usage[0].requests, usage[0].input_tokens, usage[0].output_tokens), (3, 1500, 300));
+        assert_eq!(usage[1].purpose, "playlist_generation");
+        assert_eq!(db.get_ai_usage(12).unwrap().last().unwrap().month, "2000-01");
+    }
+
+    #[test]
+    fn test_operation_journal() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let first = db.add_journal_entry("mark_missing", "Marked a.mp3 missing", "{}").unwrap();
+        for i in 0..JOURNAL_ENTRIES_KEPT {
+            db.add_journal_entry("mark_missing", &format!("Entry {}", i), "{}").unwrap();
+        }
+        let entries = db.get_journal_entries(10).unwrap();
+        assert_eq!(entries.len(), 10);
+        assert_eq!(entries[0].description, format!("Entry {}", JOURNAL_ENTRIES_KEPT - 1));
+        assert!(db.get_journal_entry(first).is_err());
+
+        db.set_journal_entry_undone(entries[0].id).unwrap();
+        assert!(db.get_journal_entry(entries[0].id).unwrap().undone_at.is_some());
+    }
+
+
+    #[test]
+    fn test_batch_markers_and_transactions() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        db.start_batch_marker("scan:/music", Some("/music"), Some(3)).unwrap();
+
+        // Committed batch: rows and marker progress persist together
+        let tx = db.transaction().unwrap();
+        let id = db.create_track(&create_test_track()).unwrap();
+        db.update_batch_marker("scan:/music", 1).unwrap();
+        tx.commit().unwrap();
+
+        // Dropped batch: rolled back, marker keeps the last committed progress
+        let tx = db.transaction().unwrap();
+        let mut other = create_test_track();
+        other.file_path = "/other.mp3".to_string();
+        other.file_hash = "other".to_string();
+        db.create_track(&other).unwrap();
+        db.update_batch_marker("scan:/music", 2).unwrap();
+        drop(tx);
+
+        assert_eq!(db.count_tracks().unwrap(), 1);
+        assert!(db.get_track(id).is_ok());
+        let markers = db.get_batch_markers().unwrap();
+        assert_eq!(markers.len(), 1);
+        assert_eq!(markers[0].processed, 1);
+        assert_eq!(markers[0].total, Some(3));
+
+        db.finish_batch_marker("scan:/music").unwrap();
+        assert!(db.get_batch_markers().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_file_stats_and_pending_hashes() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let mut track = create_test_track();
+        track.file_hash = PENDING_HASH.to_string();
+        let id = db.create_track(&track).unwrap();
+
+        let stats = db.get_file_stats().unwrap();
+        assert_eq!(stats["/path/to/test.mp3"], FileStat { track_id: id, size: Some(10_000_000), mtime: None });
+
+        db.set_track_file_stat(id, Some(42), Some(1_700_000_000)).unwrap();
+        let stats = db.get_file_stats().unwrap();
+        assert_eq!(stats["/path/to/test.mp3"].mtime, Some(1_700_000_000));
+        assert_eq!(stats["/path/to/test.mp3"].size, Some(42));
+
+        // Pending hashes never match and are listed until computed
+        assert_eq!(db.find_track_id_by_hash(PENDING_HASH).unwrap(), None);
+        assert_eq!(db.get_tracks_pending_hash().unwrap(), vec![(id, "/path/to/test.mp3".to_string())]);
+        db.set_track_hash(id, "deadbeef").unwrap();
+        assert!(db.get_tracks_pending_hash().unwrap().is_empty());
+        assert_eq!(db.find_track_id_by_hash("deadbeef").unwrap(), Some(id));
+    }
+
+    #[test]
+    fn test_watch_rules_tags_and_inbox() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+
+        let rule_id = db.create_watch_rule("/home/dj/Downloads/Promos", Some("Promo"), Some("Inbox")).unwrap();
+        let rule = db.get_watch_rule(rule_id).unwrap();
+        assert!(rule.enabled);
+        assert!(rule.matches(Path::new("/home/dj/Downloads/Promos/label/track.mp3")));
+        assert!(!rule.matches(Path::new("/home/dj/Downloads/Promos2/track.mp3")));
+
+        db.update_watch_rule(rule_id, "/home/dj/Downloads/Promos", None, Some("Inbox"), false).unwrap();
+        assert_eq!(db.get_watch_rules().unwrap()[0].tag, None);
+        assert!(!db.get_watch_rules().unwrap()[0].enabled);
+        assert!(db.update_watch_rule(999, "/x", None, None, true).is_err());
+
+        let track_id = db.create_track(&create_test_track()).unwrap();
+        db.add_track_tag(track_id, "Promo").unwrap();
+        db.add_track_tag(track_id, "Promo").unwrap();
+        db.add_track_tag(track_id, "Ambient").unwrap();
+        assert_eq!(db.get_track_tags(track_id).unwrap(), vec!["Ambient", "Promo"]);
+
+        // The inbox playlist is created once, then reused (case-insensitively)
+        let inbox = db.get_or_create_playlist("Inbox").unwrap();
+        assert_eq!(db.get_or_create_playlist("inbox").unwrap(), inbox);
+        assert_eq!(db.get_all_playlists().unwrap().len(), 1);
+
+        db.delete_watch_rule(rule_id).unwrap();
+        assert!(db.get_watch_rules().unwrap().is_empty());
+    }
 }
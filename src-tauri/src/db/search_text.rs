@@ -0,0 +1,134 @@
+// Search keys: track text folded to plain lowercase ASCII
+// An artist tagged in Cyrillic, Greek or Japanese can't be found by typing their name in
+// Latin letters, and "Beyonce" doesn't LIKE-match "Beyoncé". So each track keeps its
+// searchable fields transliterated in tracks.search_text (migration 035): "Жу" -> "zhu",
+// "Beyoncé" -> "beyonce". Searches fold the query the same way and also match against it.
+// Transliteration is deunicode's, one reading per character, so kanji get their Mandarin
+// reading; kana are romanized by syllable first (きゃ -> "kya", not "kiya").
+
+/// Columns of tracks folded into search_text (the triggers of migration 035 list them too)
+pub const SEARCH_TEXT_COLUMNS: [&str; 6] = ["title", "artist", "album", "album_artist", "label", "genre"];
+
+fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{30ff}')
+}
+
+/// Romaji of one kana in Hepburn, which is how names are written (and typed): deunicode uses
+/// Kunrei for these (し "si", ち "ti", つ "tu", ...)
+fn kana_syllable(c: char) -> &'static str {
+    // Katakana -> the hiragana of the same sound
+    let hiragana = match c {
+        '\u{30a1}'..='\u{30f6}' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    };
+    match hiragana {
+        'し' => "shi",
+        'ち' => "chi",
+        'つ' => "tsu",
+        'ふ' => "fu",
+        'じ' | 'ぢ' => "ji",
+        'づ' => "zu",
+        _ => deunicode::deunicode_char(c).unwrap_or("").trim(),
+    }
+}
+
+/// Kana in `text` romanized, other characters left as they are. deunicode spells kana one
+/// character at a time, so contracted sounds (きゃ "ki"+"ya") and the small っ (doubling the
+/// next consonant) are joined here; the long vowel mark ー is dropped.
+fn romanize_kana(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut double_next = false;
+    for c in text.chars() {
+        if !is_kana(c) {
+            double_next = false;
+            out.push(c);
+            continue;
+        }
+        match c {
+            'っ' | 'ッ' => double_next = true,
+            'ー' => {}
+            'ゃ' | 'ゅ' | 'ょ' | 'ャ' | 'ュ' | 'ョ' if out.ends_with('i') && out.len() >= 2 => {
+                let vowel = match c {
+                    'ゃ' | 'ャ' => 'a',
+                    'ゅ' | 'ュ' => 'u',
+                    _ => 'o',
+                };
+                out.pop();
+                // し+ゃ -> "sha", ち+ゃ -> "cha", じ+ゃ -> "ja"; others get a "y": "kya"
+                if !(out.ends_with("sh") || out.ends_with("ch") || out.ends_with('j')) {
+                    out.push('y');
+                }
+                out.push(vowel);
+            }
+            _ => {
+                let syllable = kana_syllable(c);
+                if double_next {
+                    match syllable.chars().next() {
+                        Some('c') => out.push('t'),
+                        Some(first) if !"aeiou".contains(first) => out.push(first),
+                        _ => {}
+                    }
+                    double_next = false;
+                }
+                out.push_str(syllable);
+            }
+        }
+    }
+    out
+}
+
+/// `text` transliterated to ASCII and lowercased, with every run of anything but letters and
+/// digits turned into one space ("Röyksopp & Robyn" -> "royksopp robyn")
+pub fn fold(text: &str) -> String {
+    let romanized;
+    let text = if text.chars().any(is_kana) {
+        romanized = romanize_kana(text);
+        &romanized
+    } else {
+        text
+    };
+    let ascii = deunicode::deunicode_with_tofu(text, " ");
+    let words: Vec<String> = ascii
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_ascii_lowercase())
+        .collect();
+    words.join(" ")
+}
+
+/// search_text for a track's SEARCH_TEXT_COLUMNS values. Fields are separated by '|', which
+/// no folded query contains, so a search never matches across two fields.
+pub fn search_text(fields: &[Option<String>]) -> String {
+    let folded: Vec<String> = fields.iter().flatten().map(|field| fold(field)).collect();
+    folded.join("|")
+}
+
+/// LIKE pattern matching a query against search_text. None if nothing is left of the query
+/// once folded (only punctuation or symbols).
+pub fn like_pattern(query: &str) -> Option<String> {
+    let folded = fold(query);
+    (!folded.is_empty()).then(|| format!("%{}%", folded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(fold("Жу"), "zhu");
+        assert_eq!(fold("Beyoncé"), "beyonce");
+        assert_eq!(fold("Röyksopp & Robyn"), "royksopp robyn");
+        assert_eq!(fold("Straße"), "strasse");
+        assert_eq!(fold("Μαρία"), "maria");
+        assert_eq!(fold("きゃりーぱみゅぱみゅ"), "kyaripamyupamyu");
+        assert_eq!(fold("ちょっと"), "chotto");
+        assert_eq!(fold("シャ"), "sha");
+        assert_eq!(fold("ふじ つなみ"), "fuji tsunami");
+        assert_eq!(fold("  --  "), "");
+
+        assert_eq!(search_text(&[Some("Жу".to_string()), None, Some("Deep House".to_string())]), "zhu|deep house");
+        assert_eq!(like_pattern("ZHU"), Some("%zhu%".to_string()));
+        assert_eq!(like_pattern("%"), None);
+    }
+}
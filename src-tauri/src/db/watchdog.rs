@@ -0,0 +1,160 @@
+// Lock tracking for the app's shared database connection
+// Every lock() records its caller (file:line of the command that took it, via #[track_caller]);
+// a watchdog thread logs locks held long enough to stall the UI, and slow waits are logged too.
+
+use std::ops::{Deref, DerefMut};
+use std::panic::Location;
+use std::sync::{Arc, LockResult, Mutex, MutexGuard, PoisonError, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::Database;
+
+/// Holding the lock this long gets logged (by the watchdog while held, and on release)
+const LONG_HOLD: Duration = Duration::from_secs(5);
+/// Waiting this long to acquire the lock gets logged
+const SLOW_WAIT: Duration = Duration::from_secs(1);
+/// How often the watchdog checks the current holder
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Who holds the lock and since when
+#[derive(Clone, Copy)]
+struct Holder {
+    caller: &'static Location<'static>,
+    since: Instant,
+    /// Already reported by the watchdog
+    reported: bool,
+}
+
+/// Mutex around the app's database connection that remembers who holds it.
+/// Used like std::sync::Mutex: `state.db.lock().unwrap()`.
+pub struct DbMutex {
+    inner: Mutex<Option<Database>>,
+    holder: Arc<Mutex<Option<Holder>>>,
+}
+
+/// Guard returned by DbMutex::lock; derefs to the Option<Database>
+pub struct DbGuard<'a> {
+    guard: MutexGuard<'a, Option<Database>>,
+    holder: &'a Mutex<Option<Holder>>,
+}
+
+impl DbMutex {
+    pub fn new(db: Option<Database>) -> Self {
+        DbMutex {
+            inner: Mutex::new(db),
+            holder: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Acquire the lock, recording the caller's location as the holder
+    #[track_caller]
+    pub fn lock(&self) -> LockResult<DbGuard<'_>> {
+        let caller = Location::caller();
+        let started = Instant::now();
+        let result = self.inner.lock();
+
+        let waited = started.elapsed();
+        if waited >= SLOW_WAIT {
+            eprintln!("[db] {} waited {}ms for the database lock", caller, waited.as_millis());
+        }
+        if let Ok(mut holder) = self.holder.lock() {
+            *holder = Some(Holder {
+                caller,
+                since: Instant::now(),
+                reported: false,
+            });
+        }
+
+        match result {
+            Ok(guard) => Ok(DbGuard { guard, holder: &self.holder }),
+            Err(poisoned) => Err(PoisonError::new(DbGuard {
+                guard: poisoned.into_inner(),
+                holder: &self.holder,
+            })),
+        }
+    }
+
+    /// Start a background thread that logs when the lock has been held longer than
+    /// LONG_HOLD (once per acquisition). Stops when the DbMutex is dropped.
+    pub fn start_watchdog(&self) {
+        let holder: Weak<Mutex<Option<Holder>>> = Arc::downgrade(&self.holder);
+        thread::spawn(move || {
+            while let Some(holder) = holder.upgrade() {
+                if let Ok(mut current) = holder.lock() {
+                    if let Some(h) = current.as_mut() {
+                        let held = h.since.elapsed();
+                        if held >= LONG_HOLD && !h.reported {
+                            h.reported = true;
+                            eprintln!(
+                                "[db] Watchdog: database lock held for {}s by {}",
+                                held.as_secs(),
+                                h.caller
+                            );
+                        }
+                    }
+                }
+                drop(holder);
+                thread::sleep(WATCHDOG_INTERVAL);
+            }
+        });
+    }
+
+    /// Current holder's location and how long it has held the lock
+    pub fn current_holder(&self) -> Option<(String, Duration)> {
+        let holder = self.holder.lock().ok()?;
+        holder.as_ref().map(|h| (h.caller.to_string(), h.since.elapsed()))
+    }
+}
+
+impl Deref for DbGuard<'_> {
+    type Target = Option<Database>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl DerefMut for DbGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+impl Drop for DbGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut holder) = self.holder.lock() {
+            if let Some(h) = holder.take() {
+                let held = h.since.elapsed();
+                if crate::perf::ENABLED {
+                    crate::perf::record(crate::perf::PerfKind::DbLock, &h.caller.to_string(), held);
+                }
+                if held >= LONG_HOLD {
+                    eprintln!(
+                        "[db] {} released the database lock after {}ms",
+                        h.caller,
+                        held.as_millis()
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_records_holder() {
+        let db = DbMutex::new(None);
+        assert!(db.current_holder().is_none());
+        {
+            let guard = db.lock().unwrap();
+            assert!(guard.is_none());
+            let (caller, _) = db.current_holder().unwrap();
+            assert!(caller.contains("watchdog.rs"));
+        }
+        assert!(db.current_holder().is_none());
+    }
+}
@@ -0,0 +1,214 @@
+// Rekordbox analysis file (ANLZ0000.DAT / .EXT) reader
+// Extracts the beat grid (PQTZ) and cue lists (PCOB) written by Rekordbox.
+// ANLZ files are big-endian, made of tagged sections:
+//   fourcc (4) | len_header (u32) | len_tag (u32) | body...
+
+use std::path::Path;
+
+use super::rekordbox::{RekordboxBeat, RekordboxCue};
+
+/// Parsed contents of an ANLZ file that RecoDeck cares about
+#[derive(Debug, Default, Clone)]
+pub struct AnlzData {
+    pub beats: Vec<RekordboxBeat>,
+    pub cues: Vec<RekordboxCue>,
+}
+
+fn be_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn be_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Read an ANLZ file from disk
+pub fn read_anlz_file(path: &Path) -> Result<AnlzData, String> {
+    let data = std::fs::read(path)
+        .map_err(|e| format!("Failed to read analysis file {:?}: {}", path, e))?;
+    parse_anlz(&data)
+}
+
+/// Parse ANLZ bytes. Unknown sections are skipped.
+pub fn parse_anlz(data: &[u8]) -> Result<AnlzData, String> {
+    if data.get(0..4) != Some(b"PMAI") {
+        return Err("Not a Rekordbox analysis file (missing PMAI header)".to_string());
+    }
+    let file_header_len = be_u32(data, 4).ok_or("Truncated analysis file header")? as usize;
+
+    let mut result = AnlzData::default();
+    let mut offset = file_header_len;
+
+    while offset + 12 <= data.len() {
+        let fourcc = &data[offset..offset + 4];
+        let len_header = be_u32(data, offset + 4).unwrap_or(0) as usize;
+        let len_tag = be_u32(data, offset + 8).unwrap_or(0) as usize;
+        if len_tag < 12 || offset + len_tag > data.len() {
+            break;
+        }
+        let section = &data[offset..offset + len_tag];
+
+        match fourcc {
+            b"PQTZ" => result.beats = parse_beat_grid(section, len_header),
+            b"PCOB" => result.cues.extend(parse_cue_list(section, len_header)),
+            _ => {}
+        }
+
+        offset += len_tag;
+    }
+
+    Ok(result)
+}
+
+/// PQTZ: header has num_beats at +20, then 8-byte entries:
+/// beat_number (u16, 1-4) | tempo (u16, BPM * 100) | time (u32, ms)
+fn parse_beat_grid(section: &[u8], len_header: usize) -> Vec<RekordboxBeat> {
+    // The count comes from the file: no more beats than the section has room for
+    let room = section.len().saturating_sub(len_header) / 8;
+    let num_beats = (be_u32(section, 20).unwrap_or(0) as usize).min(room);
+    (0..num_beats)
+        .filter_map(|i| {
+            let base = len_header + i * 8;
+            Some(RekordboxBeat {
+                beat_number: be_u16(section, base)? as u8,
+                bpm: be_u16(section, base + 2)? as f64 / 100.0,
+                position_ms: be_u32(section, base + 4)? as i64,
+            })
+        })
+        .collect()
+}
+
+/// PCOB: header has num_cues at +18, followed by PCPT entries.
+/// PCPT layout: hot_cue (u32 @12, 0 = memory cue), type (u8 @28, 2 = loop),
+/// time (u32 @32, ms), loop_time (u32 @36, ms)
+fn parse_cue_list(section: &[u8], len_header: usize) -> Vec<RekordboxCue> {
+    let num_cues = be_u16(section, 18).unwrap_or(0) as usize;
+    let mut cues = Vec::with_capacity(num_cues);
+    let mut offset = len_header;
+
+    for _ in 0..num_cues {
+        if section.get(offset..offset + 4) != Some(b"PCPT") {
+            break;
+        }
+        let len_entry = match be_u32(section, offset + 8) {
+            Some(len) if len >= 40 => len as usize,
+            _ => break,
+        };
+        let entry = match section.get(offset..offset + len_entry) {
+            Some(e) => e,
+            None => break,
+        };
+
+        let hot_cue = be_u32(entry, 12).unwrap_or(0);
+        let is_loop = entry.get(28).copied() == Some(2);
+        let position_ms = be_u32(entry, 32).unwrap_or(0) as i64;
+        let loop_end_ms = be_u32(entry, 36).unwrap_or(0);
+
+        cues.push(RekordboxCue {
+            position_ms,
+            hot_cue: if hot_cue > 0 { Some(hot_cue) } else { None },
+            loop_end_ms: if is_loop && loop_end_ms > 0 {
+                Some(loop_end_ms as i64)
+            } else {
+                None
+            },
+            comment: None,
+        });
+
+        offset += len_entry;
+    }
+
+    cues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(fourcc: &[u8; 4], header: &[u8], body: &[u8], len_header: u32) -> Vec<u8> {
+        let len_tag = 12 + header.len() + body.len();
+        let mut out = Vec::new();
+        out.extend_from_slice(fourcc);
+        out.extend_from_slice(&len_header.to_be_bytes());
+        out.extend_from_slice(&(len_tag as u32).to_be_bytes());
+        out.extend_from_slice(header);
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn file_with(sections: &[Vec<u8>]) -> Vec<u8> {
+        let mut body: Vec<u8> = sections.concat();
+        let mut out = Vec::new();
+        out.extend_from_slice(b"PMAI");
+        out.extend_from_slice(&28u32.to_be_bytes());
+        out.extend_from_slice(&((28 + body.len()) as u32).to_be_bytes());
+        out.extend_from_slice(&[0u8; 16]);
+        out.append(&mut body);
+        out
+    }
+
+    #[test]
+    fn test_rejects_non_anlz() {
+        assert!(parse_anlz(b"RIFF....").is_err());
+    }
+
+    #[test]
+    fn test_parse_beat_grid() {
+        let mut header = vec![0u8; 8];
+        header.extend_from_slice(&2u32.to_be_bytes()); // num_beats
+        let mut beats = Vec::new();
+        for (n, ms) in [(1u16, 100u32), (2u16, 569u32)] {
+            beats.extend_from_slice(&n.to_be_bytes());
+            beats.extend_from_slice(&12800u16.to_be_bytes());
+            beats.extend_from_slice(&ms.to_be_bytes());
+        }
+        let data = file_with(&[section(b"PQTZ", &header, &beats, 24)]);
+
+        let parsed = parse_anlz(&data).unwrap();
+        assert_eq!(parsed.beats.len(), 2);
+        assert_eq!(parsed.beats[0].beat_number, 1);
+        assert_eq!(parsed.beats[1].position_ms, 569);
+        assert!((parsed.beats[0].bpm - 128.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_oversized_beat_count() {
+        let mut header = vec![0u8; 8];
+        header.extend_from_slice(&u32::MAX.to_be_bytes()); // num_beats
+        let mut beat = 1u16.to_be_bytes().to_vec();
+        beat.extend_from_slice(&12800u16.to_be_bytes());
+        beat.extend_from_slice(&100u32.to_be_bytes());
+        let data = file_with(&[section(b"PQTZ", &header, &beat, 24)]);
+
+        assert_eq!(parse_anlz(&data).unwrap().beats.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_cue_list() {
+        let mut header = vec![0u8; 4]; // list type
+        header.extend_from_slice(&0u16.to_be_bytes());
+        header.extend_from_slice(&1u16.to_be_bytes()); // num_cues
+        header.extend_from_slice(&0u32.to_be_bytes());
+
+        let mut entry = Vec::new();
+        entry.extend_from_slice(b"PCPT");
+        entry.extend_from_slice(&28u32.to_be_bytes());
+        entry.extend_from_slice(&56u32.to_be_bytes());
+        entry.extend_from_slice(&1u32.to_be_bytes()); // hot cue A
+        entry.extend_from_slice(&[0u8; 12]);
+        entry.push(2); // loop
+        entry.extend_from_slice(&[0u8; 3]);
+        entry.extend_from_slice(&1500u32.to_be_bytes());
+        entry.extend_from_slice(&3500u32.to_be_bytes());
+        entry.resize(56, 0);
+
+        let data = file_with(&[section(b"PCOB", &header, &entry, 24)]);
+        let parsed = parse_anlz(&data).unwrap();
+        assert_eq!(parsed.cues.len(), 1);
+        assert_eq!(parsed.cues[0].hot_cue, Some(1));
+        assert_eq!(parsed.cues[0].position_ms, 1500);
+        assert_eq!(parsed.cues[0].loop_end_ms, Some(3500));
+    }
+}
@@ -1,2 +1,10 @@
 // DJ software format support
-// Modules: rekordbox (XML), traktor (NML)
+// Modules: rekordbox (export.pdb / master.db import), anlz (Rekordbox analysis files),
+// serato (cue markers Serato embeds in file tags), sidecar (RecoDeck analysis next to audio files)
+// Planned: rekordbox (XML), traktor (NML)
+
+pub mod anlz;
+pub mod rekordbox;
+pub mod rekordbox_pdb;
+pub mod serato;
+pub mod sidecar;
@@ -0,0 +1,278 @@
+// Rekordbox library import - shared model plus the master.db reader
+// Two sources are supported:
+//   - export.pdb on a prepared USB stick (see rekordbox_pdb)
+//   - master.db from a Rekordbox installation (plain SQLite copies only;
+//     Rekordbox 6+ encrypts the live database and it can't be opened directly)
+// Beat grids and cues live in per-track ANLZ files (see anlz).
+
+use rusqlite::{Connection, OpenFlags};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::anlz::{read_anlz_file, AnlzData};
+use super::rekordbox_pdb::read_pdb_file;
+
+/// One beat in a Rekordbox beat grid
+#[derive(Debug, Clone, PartialEq)]
+pub struct RekordboxBeat {
+    /// Position in the bar (1-4)
+    pub beat_number: u8,
+    pub bpm: f64,
+    pub position_ms: i64,
+}
+
+/// A memory cue, hot cue, or loop
+#[derive(Debug, Clone, PartialEq)]
+pub struct RekordboxCue {
+    pub position_ms: i64,
+    /// Hot cue slot (1 = A, 2 = B, ...). None for memory cues.
+    pub hot_cue: Option<u32>,
+    /// End of the loop, if this cue is a loop
+    pub loop_end_ms: Option<i64>,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RekordboxTrack {
+    /// Rekordbox's own track ID (only meaningful within one library)
+    pub id: i64,
+    /// Path as stored by Rekordbox (USB-relative for export.pdb, absolute for master.db)
+    pub file_path: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub bpm: Option<f64>,
+    pub duration_ms: Option<i64>,
+    pub comment: Option<String>,
+    /// Path of the ANLZ .DAT file, relative to the library's analysis root
+    pub analyze_path: Option<String>,
+    /// Cues stored in the database itself (master.db only; export.pdb keeps them in ANLZ)
+    pub cues: Vec<RekordboxCue>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RekordboxPlaylist {
+    pub id: i64,
+    pub parent_id: Option<i64>,
+    pub sort_order: i64,
+    pub is_folder: bool,
+    pub name: String,
+    /// Rekordbox track IDs in playlist order
+    pub track_ids: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RekordboxLibrary {
+    pub tracks: Vec<RekordboxTrack>,
+    pub playlists: Vec<RekordboxPlaylist>,
+}
+
+/// A loaded Rekordbox library plus the roots needed to resolve its relative paths
+pub struct RekordboxSource {
+    pub library: RekordboxLibrary,
+    /// Root that USB-relative track paths are joined to (None when paths are absolute)
+    pub media_root: Option<PathBuf>,
+    /// Root that ANLZ paths are joined to
+    pub analysis_root: PathBuf,
+}
+
+impl RekordboxSource {
+    /// Open an export.pdb or master.db file, or a USB root / Rekordbox folder containing one.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = locate_database(path)?;
+        let file_name = file
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        if file_name.ends_with(".pdb") {
+            // <usb>/PIONEER/rekordbox/export.pdb — paths are relative to <usb>
+            let usb_root = file
+                .parent()
+                .and_then(|p| p.parent())
+                .and_then(|p| p.parent())
+                .map(Path::to_path_buf)
+                .ok_or("Cannot determine USB root for export.pdb")?;
+            Ok(RekordboxSource {
+                library: read_pdb_file(&file)?,
+                media_root: Some(usb_root.clone()),
+                analysis_root: usb_root,
+            })
+        } else {
+            // <rekordbox dir>/master.db — analysis files live under <rekordbox dir>/share
+            let base = file.parent().map(Path::to_path_buf).unwrap_or_default();
+            Ok(RekordboxSource {
+                library: read_master_db(&file)?,
+                media_root: None,
+                analysis_root: base.join("share"),
+            })
+        }
+    }
+
+    /// Resolve a track's audio file to a local path
+    pub fn resolve_track_path(&self, track: &RekordboxTrack) -> PathBuf {
+        match &self.media_root {
+            Some(root) => root.join(track.file_path.trim_start_matches('/')),
+            None => PathBuf::from(&track.file_path),
+        }
+    }
+
+    /// Read a track's ANLZ data (beat grid + cues). Returns None if the track has
+    /// no analysis file or it can't be read.
+    pub fn read_analysis(&self, track: &RekordboxTrack) -> Option<AnlzData> {
+        let rel = track.analyze_path.as_ref()?;
+        let path = self.analysis_root.join(rel.trim_start_matches('/'));
+        read_anlz_file(&path).ok()
+    }
+}
+
+/// Accept either the database file itself or a directory that contains it
+fn locate_database(path: &Path) -> Result<PathBuf, String> {
+    if path.is_file() {
+        return Ok(path.to_path_buf());
+    }
+    let candidates = [
+        path.join("PIONEER").join("rekordbox").join("export.pdb"),
+        path.join("rekordbox").join("export.pdb"),
+        path.join("export.pdb"),
+        path.join("master.db"),
+    ];
+    candidates
+        .into_iter()
+        .find(|p| p.is_file())
+        .ok_or_else(|| format!("No export.pdb or master.db found at {}", path.display()))
+}
+
+/// Read tracks, cues and playlists from a Rekordbox master.db
+pub fn read_master_db(path: &Path) -> Result<RekordboxLibrary, String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open master.db: {}", e))?;
+
+    if conn
+        .query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))
+        .is_err()
+    {
+        return Err(
+            "master.db is encrypted and can't be read directly. Export your collection to a USB stick and import its export.pdb instead."
+                .to_string(),
+        );
+    }
+
+    let mut cues_by_track: HashMap<i64, Vec<RekordboxCue>> = HashMap::new();
+    {
+        let mut stmt = conn
+            .prepare(
+                "SELECT CAST(ContentID AS INTEGER), InMsec, OutMsec, Kind, Comment
+                 FROM djmdCue WHERE COALESCE(rb_local_deleted, 0) = 0",
+            )
+            .map_err(|e| format!("Failed to read cues: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| {
+                let out_ms: Option<i64> = row.get(2)?;
+                let kind: Option<i64> = row.get(3)?;
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    RekordboxCue {
+                        position_ms: row.get::<_, Option<i64>>(1)?.unwrap_or(0),
+                        hot_cue: kind.filter(|k| *k > 0).map(|k| k as u32),
+                        loop_end_ms: out_ms.filter(|ms| *ms > 0),
+                        comment: row.get(4)?,
+                    },
+                ))
+            })
+            .map_err(|e| format!("Failed to read cues: {}", e))?;
+        for (content_id, cue) in rows.flatten() {
+            cues_by_track.entry(content_id).or_default().push(cue);
+        }
+    }
+
+    let tracks: Vec<RekordboxTrack> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT CAST(c.ID AS INTEGER), c.FolderPath, c.Title, a.Name, c.BPM, c.Length,
+                        c.Commnt, c.AnalysisDataPath
+                 FROM djmdContent c
+                 LEFT JOIN djmdArtist a ON a.ID = c.ArtistID
+                 WHERE COALESCE(c.rb_local_deleted, 0) = 0",
+            )
+            .map_err(|e| format!("Failed to read tracks: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| {
+                let bpm: Option<i64> = row.get(4)?;
+                let length_secs: Option<i64> = row.get(5)?;
+                Ok(RekordboxTrack {
+                    id: row.get(0)?,
+                    file_path: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    title: row.get(2)?,
+                    artist: row.get(3)?,
+                    bpm: bpm.filter(|b| *b > 0).map(|b| b as f64 / 100.0),
+                    duration_ms: length_secs.map(|s| s * 1000),
+                    comment: row.get(6)?,
+                    analyze_path: row.get(7)?,
+                    cues: Vec::new(),
+                })
+            })
+            .map_err(|e| format!("Failed to read tracks: {}", e))?;
+        rows.flatten()
+            .filter(|t| !t.file_path.is_empty())
+            .map(|mut t| {
+                t.cues = cues_by_track.remove(&t.id).unwrap_or_default();
+                t
+            })
+            .collect()
+    };
+
+    let mut entries: HashMap<i64, Vec<(i64, i64)>> = HashMap::new();
+    {
+        let mut stmt = conn
+            .prepare(
+                "SELECT CAST(PlaylistID AS INTEGER), CAST(ContentID AS INTEGER), TrackNo
+                 FROM djmdSongPlaylist WHERE COALESCE(rb_local_deleted, 0) = 0",
+            )
+            .map_err(|e| format!("Failed to read playlist entries: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, Option<i64>>(2)?.unwrap_or(0),
+                ))
+            })
+            .map_err(|e| format!("Failed to read playlist entries: {}", e))?;
+        for (playlist_id, content_id, track_no) in rows.flatten() {
+            entries.entry(playlist_id).or_default().push((track_no, content_id));
+        }
+    }
+
+    let playlists: Vec<RekordboxPlaylist> = {
+        // Attribute: 0 = playlist, 1 = folder, 4 = smart playlist (imported as a plain list)
+        let mut stmt = conn
+            .prepare(
+                "SELECT CAST(ID AS INTEGER), CAST(NULLIF(ParentID, 'root') AS INTEGER), Seq, Attribute, Name
+                 FROM djmdPlaylist WHERE COALESCE(rb_local_deleted, 0) = 0",
+            )
+            .map_err(|e| format!("Failed to read playlists: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(RekordboxPlaylist {
+                    id: row.get(0)?,
+                    parent_id: row.get::<_, Option<i64>>(1)?.filter(|id| *id != 0),
+                    sort_order: row.get::<_, Option<i64>>(2)?.unwrap_or(0),
+                    is_folder: row.get::<_, Option<i64>>(3)?.unwrap_or(0) == 1,
+                    name: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                    track_ids: Vec::new(),
+                })
+            })
+            .map_err(|e| format!("Failed to read playlists: {}", e))?;
+        rows.flatten()
+            .map(|mut p| {
+                if let Some(mut list) = entries.remove(&p.id) {
+                    list.sort_by_key(|(track_no, _)| *track_no);
+                    p.track_ids = list.into_iter().map(|(_, id)| id).collect();
+                }
+                p
+            })
+            .collect()
+    };
+
+    Ok(RekordboxLibrary { tracks, playlists })
+}
@@ -0,0 +1,314 @@
+// Rekordbox export.pdb reader (DeviceSQL format used on exported USB sticks)
+// Reads the track, playlist tree and playlist entry tables. Layout follows the
+// community reverse-engineering of the format (little-endian, fixed-size pages,
+// rows located through row-group indexes at the end of each page).
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::rekordbox::{RekordboxLibrary, RekordboxPlaylist, RekordboxTrack};
+
+/// Table types in the PDB file header
+const TABLE_TRACKS: u32 = 0;
+const TABLE_ARTISTS: u32 = 2;
+const TABLE_PLAYLIST_TREE: u32 = 7;
+const TABLE_PLAYLIST_ENTRIES: u32 = 8;
+
+/// Offset of the row heap within a page (after the page header)
+const PAGE_HEAP_OFFSET: usize = 0x28;
+/// Size of one row group index at the end of a page (16 row offsets + flags)
+const ROW_GROUP_SIZE: usize = 0x24;
+
+/// Indices into the track row's string offset array
+const TRACK_STR_ANALYZE_PATH: usize = 14;
+const TRACK_STR_COMMENT: usize = 16;
+const TRACK_STR_TITLE: usize = 17;
+const TRACK_STR_FILE_PATH: usize = 20;
+
+fn le_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn le_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Table pointer from the file header
+struct TablePointer {
+    table_type: u32,
+    first_page: u32,
+    last_page: u32,
+}
+
+/// Read and parse an export.pdb file
+pub fn read_pdb_file(path: &Path) -> Result<RekordboxLibrary, String> {
+    let data = std::fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    parse_pdb(&data)
+}
+
+/// Parse export.pdb bytes into tracks and playlists.
+/// Paths in the result are as stored by Rekordbox (relative to the USB root).
+pub fn parse_pdb(data: &[u8]) -> Result<RekordboxLibrary, String> {
+    let page_len = le_u32(data, 4).ok_or("Truncated PDB header")? as usize;
+    let num_tables = le_u32(data, 8).ok_or("Truncated PDB header")? as usize;
+    if page_len < 512 || num_tables == 0 || num_tables > 64 {
+        return Err("Not a Rekordbox export.pdb file".to_string());
+    }
+
+    let tables: Vec<TablePointer> = (0..num_tables)
+        .filter_map(|i| {
+            let base = 28 + i * 16;
+            Some(TablePointer {
+                table_type: le_u32(data, base)?,
+                first_page: le_u32(data, base + 8)?,
+                last_page: le_u32(data, base + 12)?,
+            })
+        })
+        .collect();
+
+    let rows_of = |table_type: u32| -> Vec<&[u8]> {
+        tables
+            .iter()
+            .find(|t| t.table_type == table_type)
+            .map(|t| collect_rows(data, page_len, t))
+            .unwrap_or_default()
+    };
+
+    // Artist names, for resolving track artist_id
+    let mut artists: HashMap<u32, String> = HashMap::new();
+    for row in rows_of(TABLE_ARTISTS) {
+        if let Some((id, name)) = parse_artist_row(row) {
+            artists.insert(id, name);
+        }
+    }
+
+    let tracks: Vec<RekordboxTrack> = rows_of(TABLE_TRACKS)
+        .into_iter()
+        .filter_map(|row| parse_track_row(row, &artists))
+        .collect();
+
+    let mut playlists: Vec<RekordboxPlaylist> = rows_of(TABLE_PLAYLIST_TREE)
+        .into_iter()
+        .filter_map(parse_playlist_row)
+        .collect();
+
+    // Playlist entries: (entry_index, track_id, playlist_id)
+    let mut entries: Vec<(u32, u32, u32)> = rows_of(TABLE_PLAYLIST_ENTRIES)
+        .into_iter()
+        .filter_map(|row| Some((le_u32(row, 0)?, le_u32(row, 4)?, le_u32(row, 8)?)))
+        .collect();
+    entries.sort_by_key(|(index, _, _)| *index);
+    for playlist in playlists.iter_mut() {
+        playlist.track_ids = entries
+            .iter()
+            .filter(|(_, _, playlist_id)| *playlist_id as i64 == playlist.id)
+            .map(|(_, track_id, _)| *track_id as i64)
+            .collect();
+    }
+
+    Ok(RekordboxLibrary { tracks, playlists })
+}
+
+/// Walk a table's page chain and collect every present row
+fn collect_rows<'a>(data: &'a [u8], page_len: usize, table: &TablePointer) -> Vec<&'a [u8]> {
+    let mut rows = Vec::new();
+    let mut page_index = table.first_page;
+    let mut visited = 0usize;
+    let max_pages = data.len() / page_len;
+
+    loop {
+        let page_start = page_index as usize * page_len;
+        let page = match data.get(page_start..page_start + page_len) {
+            Some(p) => p,
+            None => break,
+        };
+
+        let page_type = le_u32(page, 8).unwrap_or(u32::MAX);
+        let page_flags = page[27];
+        // Only data pages of the right type carry rows; index pages are skipped
+        if page_type == table.table_type && page_flags & 0x40 == 0 {
+            let num_rows_small = page[24] as usize;
+            let num_rows_large = le_u16(page, 34).unwrap_or(0) as usize;
+            let num_rows = if num_rows_large > num_rows_small && num_rows_large != 0x1fff {
+                num_rows_large
+            } else {
+                num_rows_small
+            };
+
+            for row_index in 0..num_rows {
+                let group = row_index / 16;
+                let slot = row_index % 16;
+                // A corrupt row count can claim more groups than fit between the page
+                // header and the end of the page: keep the rows read so far
+                let group_base = match page_len.checked_sub((group + 1) * ROW_GROUP_SIZE) {
+                    Some(group_start) if group_start >= PAGE_HEAP_OFFSET => group_start + ROW_GROUP_SIZE,
+                    _ => break,
+                };
+                let present = le_u16(page, group_base - 4).unwrap_or(0);
+                if present & (1 << slot) == 0 {
+                    continue;
+                }
+                let row_offset = match le_u16(page, group_base - 6 - slot * 2) {
+                    Some(o) => o as usize,
+                    None => continue,
+                };
+                if let Some(row) = page.get(PAGE_HEAP_OFFSET + row_offset..) {
+                    rows.push(row);
+                }
+            }
+        }
+
+        visited += 1;
+        if page_index == table.last_page || visited > max_pages {
+            break;
+        }
+        page_index = le_u32(page, 12).unwrap_or(table.last_page);
+    }
+
+    rows
+}
+
+/// Decode a DeviceSQL string starting at `offset` within `row`
+fn read_device_string(row: &[u8], offset: usize) -> Option<String> {
+    let kind = *row.get(offset)?;
+    match kind {
+        // Long ASCII: length (u16, includes the 4-byte header), pad, text
+        0x40 => {
+            let len = le_u16(row, offset + 1)? as usize;
+            let bytes = row.get(offset + 4..offset + len.max(4))?;
+            Some(String::from_utf8_lossy(bytes).to_string())
+        }
+        // Long UTF-16LE
+        0x90 => {
+            let len = le_u16(row, offset + 1)? as usize;
+            let bytes = row.get(offset + 4..offset + len.max(4))?;
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .collect();
+            Some(String::from_utf16_lossy(&units))
+        }
+        // Short ASCII: length (incl. this byte) in the upper 7 bits
+        k if k & 1 == 1 => {
+            let len = (k >> 1) as usize;
+            let bytes = row.get(offset + 1..offset + len.max(1))?;
+            Some(String::from_utf8_lossy(bytes).to_string())
+        }
+        _ => None,
+    }
+}
+
+fn non_empty(s: Option<String>) -> Option<String> {
+    s.filter(|v| !v.trim().is_empty())
+}
+
+fn parse_artist_row(row: &[u8]) -> Option<(u32, String)> {
+    let subtype = le_u16(row, 0)?;
+    let id = le_u32(row, 4)?;
+    let name_offset = if subtype == 0x64 {
+        le_u16(row, 10)? as usize
+    } else {
+        *row.get(9)? as usize
+    };
+    Some((id, read_device_string(row, name_offset)?))
+}
+
+fn parse_track_row(row: &[u8], artists: &HashMap<u32, String>) -> Option<RekordboxTrack> {
+    let string_at = |index: usize| -> Option<String> {
+        let offset = le_u16(row, 94 + index * 2)? as usize;
+        non_empty(read_device_string(row, offset))
+    };
+
+    let tempo = le_u32(row, 56)?;
+    let artist_id = le_u32(row, 68)?;
+    let duration_secs = le_u16(row, 84)?;
+
+    Some(RekordboxTrack {
+        id: le_u32(row, 72)? as i64,
+        file_path: string_at(TRACK_STR_FILE_PATH)?,
+        title: string_at(TRACK_STR_TITLE),
+        artist: artists.get(&artist_id).cloned(),
+        bpm: if tempo > 0 { Some(tempo as f64 / 100.0) } else { None },
+        duration_ms: Some(duration_secs as i64 * 1000),
+        comment: string_at(TRACK_STR_COMMENT),
+        analyze_path: string_at(TRACK_STR_ANALYZE_PATH),
+        cues: Vec::new(),
+    })
+}
+
+/// Playlist tree row: parent_id, unknown, sort_order, id, is_folder, name
+fn parse_playlist_row(row: &[u8]) -> Option<RekordboxPlaylist> {
+    let parent_id = le_u32(row, 0)?;
+    Some(RekordboxPlaylist {
+        id: le_u32(row, 12)? as i64,
+        parent_id: if parent_id == 0 { None } else { Some(parent_id as i64) },
+        sort_order: le_u32(row, 8)? as i64,
+        is_folder: le_u32(row, 16)? != 0,
+        name: read_device_string(row, 20).unwrap_or_default(),
+        track_ids: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_short_string() {
+        // kind byte = (len << 1) | 1, len includes the kind byte
+        let mut row = vec![((4u8) << 1) | 1];
+        row.extend_from_slice(b"abc");
+        assert_eq!(read_device_string(&row, 0).as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn test_read_long_utf16_string() {
+        let text: Vec<u8> = "Ünïcode".encode_utf16().flat_map(|u| u.to_le_bytes()).collect();
+        let mut row = vec![0x90];
+        row.extend_from_slice(&((text.len() + 4) as u16).to_le_bytes());
+        row.push(0);
+        row.extend_from_slice(&text);
+        assert_eq!(read_device_string(&row, 0).as_deref(), Some("Ünïcode"));
+    }
+
+    #[test]
+    fn test_rejects_garbage() {
+        assert!(parse_pdb(&[0u8; 64]).is_err());
+    }
+
+    #[test]
+    fn test_oversized_row_count() {
+        // One track page claiming 0x1000 rows (256 row groups, far more than 512 bytes hold)
+        let page_len = 512;
+        let mut page = vec![0u8; page_len];
+        page[8..12].copy_from_slice(&TABLE_TRACKS.to_le_bytes());
+        page[34..36].copy_from_slice(&0x1000u16.to_le_bytes());
+        // Row 0 present, at the start of the heap
+        page[page_len - 4..page_len - 2].copy_from_slice(&1u16.to_le_bytes());
+        page[page_len - 6..page_len - 4].copy_from_slice(&0u16.to_le_bytes());
+        let table = TablePointer { table_type: TABLE_TRACKS, first_page: 0, last_page: 0 };
+
+        let rows = collect_rows(&page, page_len, &table);
+        assert_eq!(rows.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_playlist_row() {
+        let mut row = Vec::new();
+        row.extend_from_slice(&5u32.to_le_bytes()); // parent
+        row.extend_from_slice(&0u32.to_le_bytes());
+        row.extend_from_slice(&2u32.to_le_bytes()); // sort order
+        row.extend_from_slice(&9u32.to_le_bytes()); // id
+        row.extend_from_slice(&0u32.to_le_bytes()); // not a folder
+        row.push((6u8 << 1) | 1);
+        row.extend_from_slice(b"Peak!");
+
+        let playlist = parse_playlist_row(&row).unwrap();
+        assert_eq!(playlist.id, 9);
+        assert_eq!(playlist.parent_id, Some(5));
+        assert!(!playlist.is_folder);
+        assert_eq!(playlist.name, "Peak!");
+    }
+}
@@ -0,0 +1,266 @@
+// Serato DJ cue markers embedded in file tags
+// Serato keeps hot cues and saved loops in a "Serato Markers2" GEOB frame (ID3v2) or a
+// SERATO_MARKERS_V2 field (FLAC/Ogg). The GEOB data is 0x01 0x01 followed by base64 text;
+// decoded, it is 0x01 0x01 followed by entries:
+//   type name (NUL-terminated) | body length (u32) | body
+// CUE body:  0x00 | index | position ms (u32) | 0x00 | RGB | 0x00 0x00 | name (NUL-terminated)
+// LOOP body: 0x00 | index | start ms (u32) | end ms (u32) | 0xFFFFFFFF | 0x00 RGB | 0x00 |
+//            locked | name (NUL-terminated)
+// Other entries (COLOR, BPMLOCK, FLIP) are skipped. All integers are big-endian.
+
+use crate::db::CuePoint;
+
+/// GEOB descriptor of the frame holding the markers
+pub const MARKERS2_DESCRIPTOR: &str = "Serato Markers2";
+/// Vorbis comment holding the markers in FLAC/Ogg files
+pub const MARKERS2_FIELD: &str = "SERATO_MARKERS_V2";
+
+/// A hot cue or saved loop from Serato
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeratoCue {
+    /// Slot, 0-based
+    pub index: u8,
+    pub position_ms: i64,
+    /// Set for loops
+    pub loop_end_ms: Option<i64>,
+    /// "#RRGGBB"
+    pub color: Option<String>,
+    pub name: Option<String>,
+}
+
+impl SeratoCue {
+    /// Rows for the cue_points table: one "cue", or "loop_start" + "loop_end"
+    pub fn to_cue_points(&self, track_id: i64) -> Vec<CuePoint> {
+        let label = self.name.clone().or_else(|| {
+            Some(match self.loop_end_ms {
+                Some(_) => format!("Loop {}", self.index as u32 + 1),
+                None => format!("Hot Cue {}", self.index as u32 + 1),
+            })
+        });
+        let point = |position_ms: i64, cue_type: &str| CuePoint {
+            id: None,
+            track_id,
+            position_ms,
+            label: label.clone(),
+            color: self.color.clone(),
+            cue_type: cue_type.to_string(),
+        };
+        match self.loop_end_ms {
+            Some(end_ms) => vec![point(self.position_ms, "loop_start"), point(end_ms, "loop_end")],
+            None => vec![point(self.position_ms, "cue")],
+        }
+    }
+}
+
+fn be_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// NUL-terminated string at the start of `data`, and the bytes after the NUL
+fn c_string(data: &[u8]) -> Option<(String, &[u8])> {
+    let end = data.iter().position(|&b| b == 0)?;
+    Some((String::from_utf8_lossy(&data[..end]).into_owned(), &data[end + 1..]))
+}
+
+/// Decode base64 leniently as Serato writes it: line breaks anywhere, padding optional,
+/// trailing NULs. None on any other character.
+fn decode_base64(text: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &c in text {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' | b'\n' | b'\r' | 0 => continue,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// "#RRGGBB" from three bytes
+fn hex_color(rgb: &[u8]) -> Option<String> {
+    match rgb {
+        [r, g, b] => Some(format!("#{:02X}{:02X}{:02X}", r, g, b)),
+        _ => None,
+    }
+}
+
+fn cue_name(data: &[u8]) -> Option<String> {
+    c_string(data)
+        .map(|(name, _)| name)
+        .filter(|name| !name.trim().is_empty())
+}
+
+fn parse_cue(body: &[u8]) -> Option<SeratoCue> {
+    Some(SeratoCue {
+        index: *body.get(1)?,
+        position_ms: be_u32(body, 2)? as i64,
+        loop_end_ms: None,
+        color: hex_color(body.get(7..10)?),
+        name: body.get(12..).and_then(cue_name),
+    })
+}
+
+fn parse_loop(body: &[u8]) -> Option<SeratoCue> {
+    let start_ms = be_u32(body, 2)? as i64;
+    let end_ms = be_u32(body, 6)? as i64;
+    if end_ms <= start_ms {
+        return None;
+    }
+    Some(SeratoCue {
+        index: *body.get(1)?,
+        position_ms: start_ms,
+        loop_end_ms: Some(end_ms),
+        color: hex_color(body.get(15..18)?),
+        name: body.get(20..).and_then(cue_name),
+    })
+}
+
+/// Parse the data of a "Serato Markers2" GEOB frame. Malformed data yields the cues
+/// read before the problem (possibly none).
+pub fn parse_markers2(data: &[u8]) -> Vec<SeratoCue> {
+    let Some(payload) = data.strip_prefix(&[0x01, 0x01]).and_then(decode_base64) else {
+        return Vec::new();
+    };
+    let Some(mut rest) = payload.strip_prefix(&[0x01, 0x01]) else {
+        return Vec::new();
+    };
+
+    let mut cues = Vec::new();
+    while let Some((name, after_name)) = c_string(rest) {
+        if name.is_empty() {
+            break;
+        }
+        let Some(len) = be_u32(after_name, 0).map(|l| l as usize) else {
+            break;
+        };
+        let Some(body) = after_name.get(4..4 + len) else {
+            break;
+        };
+        match name.as_str() {
+            "CUE" => cues.extend(parse_cue(body)),
+            "LOOP" => cues.extend(parse_loop(body)),
+            _ => {}
+        }
+        rest = &after_name[4 + len..];
+    }
+    cues.sort_by_key(|c| (c.position_ms, c.index));
+    cues
+}
+
+/// Parse a SERATO_MARKERS_V2 Vorbis comment: base64 of a GEOB-like blob
+/// ("application/octet-stream" NUL, NUL, "Serato Markers2" NUL, then the frame data)
+pub fn parse_markers2_field(text: &str) -> Vec<SeratoCue> {
+    let Some(blob) = decode_base64(text.trim().as_bytes()) else {
+        return Vec::new();
+    };
+    let data = c_string(&blob)
+        .map(|(_, rest)| rest.strip_prefix(&[0]).unwrap_or(rest))
+        .and_then(c_string)
+        .filter(|(descriptor, _)| descriptor == MARKERS2_DESCRIPTOR)
+        .map(|(_, data)| data);
+    data.map(parse_markers2).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_base64(data: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut out = String::new();
+        for chunk in data.chunks(3) {
+            let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+            for i in 0..=chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            }
+        }
+        out
+    }
+
+    fn entry(name: &str, body: &[u8]) -> Vec<u8> {
+        let mut out = name.as_bytes().to_vec();
+        out.push(0);
+        out.extend((body.len() as u32).to_be_bytes());
+        out.extend(body);
+        out
+    }
+
+    fn markers2() -> Vec<u8> {
+        let mut payload = vec![0x01, 0x01];
+        payload.extend(entry("COLOR", &[0, 0xff, 0xff, 0xff]));
+        let mut cue = vec![0, 1];
+        cue.extend(64_000u32.to_be_bytes());
+        cue.extend([0, 0xcc, 0x00, 0x00, 0, 0]);
+        cue.extend(b"Drop\0");
+        payload.extend(entry("CUE", &cue));
+        let mut saved_loop = vec![0, 0];
+        saved_loop.extend(1_000u32.to_be_bytes());
+        saved_loop.extend(9_000u32.to_be_bytes());
+        saved_loop.extend([0xff, 0xff, 0xff, 0xff, 0, 0x27, 0xaa, 0xe1, 0, 0]);
+        saved_loop.extend(b"\0");
+        payload.extend(entry("LOOP", &saved_loop));
+        payload.push(0);
+
+        // Serato wraps the base64 text in lines of 72 characters and drops padding
+        let text = encode_base64(&payload);
+        let wrapped: Vec<String> = text.as_bytes().chunks(72).map(|l| String::from_utf8_lossy(l).into_owned()).collect();
+        let mut data = vec![0x01, 0x01];
+        data.extend(wrapped.join("\n").into_bytes());
+        data.push(0);
+        data
+    }
+
+    #[test]
+    fn test_parse_markers2() {
+        let cues = parse_markers2(&markers2());
+        assert_eq!(
+            cues,
+            vec![
+                SeratoCue {
+                    index: 0,
+                    position_ms: 1_000,
+                    loop_end_ms: Some(9_000),
+                    color: Some("#27AAE1".to_string()),
+                    name: None,
+                },
+                SeratoCue {
+                    index: 1,
+                    position_ms: 64_000,
+                    loop_end_ms: None,
+                    color: Some("#CC0000".to_string()),
+                    name: Some("Drop".to_string()),
+                },
+            ]
+        );
+
+        let points: Vec<_> = cues.iter().flat_map(|c| c.to_cue_points(7)).collect();
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[1].cue_type, "loop_end");
+        assert_eq!(points[1].label.as_deref(), Some("Loop 1"));
+        assert_eq!(points[2].label.as_deref(), Some("Drop"));
+
+        assert!(parse_markers2(b"\x01\x01not base64!").is_empty());
+        assert!(parse_markers2(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_parse_markers2_field() {
+        let mut blob = b"application/octet-stream\0\0Serato Markers2\0".to_vec();
+        blob.extend(markers2());
+        assert_eq!(parse_markers2_field(&encode_base64(&blob)).len(), 2);
+        assert!(parse_markers2_field("garbage").is_empty());
+    }
+}
@@ -0,0 +1,219 @@
+// RecoDeck sidecar files: analysis carried next to the audio file
+// "Track.mp3" gets "Track.mp3.recodeck", a small JSON file with BPM, key, silence, the
+// overview waveform and cues. Copying a folder to another machine brings the analysis
+// along, without a library sync. Written on request or after analysis (SidecarSettings);
+// read by the scanner on import.
+
+use crate::db::{CuePoint, Database, PortableAnalysis};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Appended to the audio file's name
+pub const SIDECAR_EXTENSION: &str = "recodeck";
+/// Format version written to sidecars
+const SIDECAR_VERSION: u32 = 1;
+/// Sidecars for a file whose duration differs more than this are for another version of it
+const DURATION_TOLERANCE_MS: i64 = 1000;
+
+pub const SIDECAR_SETTING: &str = "analysis_sidecars";
+
+/// When sidecars are used. Both off by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SidecarSettings {
+    /// Write a track's sidecar after it is analyzed
+    pub write: bool,
+    /// Read sidecars when scanning files in
+    pub read: bool,
+}
+
+impl SidecarSettings {
+    pub fn from_settings(db: &Database) -> Self {
+        db.get_setting(SIDECAR_SETTING)
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SidecarCue {
+    pub position_ms: i64,
+    pub label: Option<String>,
+    pub color: Option<String>,
+    /// "cue", "loop_start" or "loop_end"
+    #[serde(rename = "type")]
+    pub cue_type: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sidecar {
+    pub version: u32,
+    /// Of the file the analysis is for
+    pub duration_ms: Option<i64>,
+    pub bpm: Option<f64>,
+    pub bpm_confidence: Option<f64>,
+    /// Camelot
+    pub key: Option<String>,
+    pub key_confidence: Option<f64>,
+    pub silence_lead_ms: Option<i64>,
+    pub silence_tail_ms: Option<i64>,
+    /// Overview waveform blob (audio::waveform), hex-encoded
+    pub waveform_overview: Option<String>,
+    pub cues: Vec<SidecarCue>,
+}
+
+impl Sidecar {
+    pub fn new(duration_ms: Option<i64>, analysis: &PortableAnalysis, cues: &[CuePoint]) -> Self {
+        Sidecar {
+            version: SIDECAR_VERSION,
+            duration_ms,
+            bpm: analysis.bpm,
+            bpm_confidence: analysis.bpm_confidence,
+            key: analysis.musical_key.clone(),
+            key_confidence: analysis.key_confidence,
+            silence_lead_ms: analysis.silence_lead_ms,
+            silence_tail_ms: analysis.silence_tail_ms,
+            waveform_overview: analysis.waveform_overview.as_deref().map(to_hex),
+            cues: cues
+                .iter()
+                .map(|cue| SidecarCue {
+                    position_ms: cue.position_ms,
+                    label: cue.label.clone(),
+                    color: cue.color.clone(),
+                    cue_type: cue.cue_type.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Whether the sidecar was written for a file of this duration (or either is unknown)
+    pub fn matches_duration(&self, duration_ms: Option<i64>) -> bool {
+        match (self.duration_ms, duration_ms) {
+            (Some(a), Some(b)) => (a - b).abs() <= DURATION_TOLERANCE_MS,
+            _ => true,
+        }
+    }
+
+    /// The analysis values; a waveform that doesn't decode is left out
+    pub fn analysis(&self) -> PortableAnalysis {
+        PortableAnalysis {
+            bpm: self.bpm.filter(|bpm| bpm.is_finite() && *bpm > 0.0),
+            bpm_confidence: self.bpm_confidence,
+            musical_key: self.key.clone(),
+            key_confidence: self.key_confidence,
+            silence_lead_ms: self.silence_lead_ms,
+            silence_tail_ms: self.silence_tail_ms,
+            waveform_overview: self.waveform_overview.as_deref().and_then(from_hex),
+        }
+    }
+
+    pub fn cue_points(&self, track_id: i64) -> Vec<CuePoint> {
+        self.cues
+            .iter()
+            .map(|cue| CuePoint {
+                id: None,
+                track_id,
+                position_ms: cue.position_ms,
+                label: cue.label.clone(),
+                color: cue.color.clone(),
+                cue_type: cue.cue_type.clone(),
+            })
+            .collect()
+    }
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// "Track.mp3" -> "Track.mp3.recodeck"
+pub fn sidecar_path(audio_path: &Path) -> PathBuf {
+    let mut name = audio_path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(SIDECAR_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Read the sidecar next to an audio file. Ok(None) if there is none.
+pub fn read_sidecar(audio_path: &Path) -> Result<Option<Sidecar>, String> {
+    let path = sidecar_path(audio_path);
+    let json = match std::fs::read_to_string(&path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read sidecar {:?}: {}", path, e)),
+    };
+    let sidecar: Sidecar = serde_json::from_str(&json)
+        .map_err(|e| format!("Invalid sidecar {:?}: {}", path, e))?;
+    if sidecar.version > SIDECAR_VERSION {
+        return Err(format!("Sidecar {:?} is from a newer RecoDeck (version {})", path, sidecar.version));
+    }
+    Ok(Some(sidecar))
+}
+
+/// Write the sidecar next to an audio file (via a temporary file, so a half-written
+/// sidecar is never read)
+pub fn write_sidecar(audio_path: &Path, sidecar: &Sidecar) -> Result<(), String> {
+    let path = sidecar_path(audio_path);
+    let json = serde_json::to_string(sidecar)
+        .map_err(|e| format!("Failed to serialize sidecar: {}", e))?;
+    let mut temp = path.clone().into_os_string();
+    temp.push(".tmp");
+    std::fs::write(&temp, json)
+        .and_then(|_| std::fs::rename(&temp, &path))
+        .map_err(|e| format!("Failed to write sidecar {:?}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sidecar_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let audio = temp_dir.path().join("Track.mp3");
+        assert_eq!(sidecar_path(&audio), temp_dir.path().join("Track.mp3.recodeck"));
+        assert_eq!(read_sidecar(&audio).unwrap(), None);
+
+        let analysis = PortableAnalysis {
+            bpm: Some(124.0),
+            bpm_confidence: Some(0.9),
+            musical_key: Some("8A".to_string()),
+            waveform_overview: Some(vec![1, 0xab, 0xff]),
+            ..Default::default()
+        };
+        let cues = vec![CuePoint {
+            id: Some(7),
+            track_id: 1,
+            position_ms: 1500,
+            label: Some("Drop".to_string()),
+            color: None,
+            cue_type: "cue".to_string(),
+        }];
+        write_sidecar(&audio, &Sidecar::new(Some(300_000), &analysis, &cues)).unwrap();
+
+        let sidecar = read_sidecar(&audio).unwrap().unwrap();
+        assert_eq!(sidecar.analysis(), analysis);
+        assert_eq!(sidecar.cue_points(2)[0].track_id, 2);
+        assert_eq!(sidecar.cue_points(2)[0].label.as_deref(), Some("Drop"));
+        assert!(sidecar.matches_duration(Some(300_400)));
+        assert!(!sidecar.matches_duration(Some(240_000)));
+
+        assert_eq!(from_hex("0g"), None);
+        std::fs::write(sidecar_path(&audio), "{\"version\": 99}").unwrap();
+        assert!(read_sidecar(&audio).is_err());
+    }
+}
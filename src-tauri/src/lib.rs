@@ -1,14 +1,19 @@
 // Modules
 pub mod ai;
+pub mod artwork;
 pub mod audio;
 pub mod commands;
 pub mod db;
+pub mod formats;
+pub mod perf;
 pub mod scanner;
 pub mod server;
+pub mod sync;
 
-use commands::{library::AppState, playback::PlaybackState, server::CompanionState, watcher::WatcherState};
+use commands::{analysis_queue::AnalysisQueueState, hotkeys::HotkeyState, library::AppState, midi::MidiState, playback::PlaybackState, playlist_export::PlaylistExportState, queue::QueueState, server::CompanionState, track_cache::TrackCache, watcher::WatcherState};
+use db::DbMutex;
 use std::sync::Mutex;
-use tauri::{Emitter, Listener};
+use tauri::{Emitter, Listener, Manager};
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -61,29 +66,13 @@ fn hex_val(byte: u8) -> Option<u8> {
     }
 }
 
-/// Get MIME type for an audio file based on its extension
-fn audio_mime_type(path: &str) -> &'static str {
-    match std::path::Path::new(path)
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|s| s.to_lowercase())
-        .as_deref()
-    {
-        Some("mp3") => "audio/mpeg",
-        Some("flac") => "audio/flac",
-        Some("wav") => "audio/wav",
-        Some("ogg") => "audio/ogg",
-        Some("m4a") => "audio/mp4",
-        Some("aac") => "audio/aac",
-        Some("aiff") | Some("aif") => "audio/aiff",
-        _ => "application/octet-stream",
-    }
-}
-
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .setup(|app| {
+            // Log commands that hold the database lock long enough to stall everything else
+            app.state::<AppState>().db.start_watchdog();
+
             // Relay player events between windows (main <-> mini player)
             let handle = app.handle().clone();
             for event_name in ["player-state", "player-position", "player-action", "request-player-state"] {
@@ -93,12 +82,31 @@ pub fn run() {
                     let _ = h.emit(&name, event.payload());
                 });
             }
+
+            // Library and analysis changes make the cached track list stale
+            for event_name in ["library-changed", "track-analyzed"] {
+                let h = handle.clone();
+                app.listen(event_name, move |_| h.state::<AppState>().track_cache.invalidate());
+            }
+
+            // Queue changes from any source (desktop or companion remote) reach the UI
+            let h = handle.clone();
+            app.state::<QueueState>().queue.set_listener(move |tracks| {
+                let _ = h.emit("queue-changed", tracks);
+            });
+
+            // Keep the .m3u playlist mirror in sync with playlist changes
+            commands::playlist_export::start_listener(&handle);
+
+            // Media keys and the OS Now Playing widget
+            commands::media_session::start(&handle);
             Ok(())
         })
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         // Custom protocol to serve local audio files to the webview.
         // macOS URL:  stream://localhost/<absolute_path>
         // Windows URL: http://stream.localhost/<absolute_path>
@@ -260,9 +268,17 @@ pub fn run() {
                 name.to_string()
             }
 
+            /// Read a file the stream access rules allow (audio::stream_access); others fail
+            /// with PermissionDenied
+            fn read_allowed(path: impl AsRef<std::path::Path>) -> Result<Vec<u8>, std::io::Error> {
+                let path = path.as_ref();
+                audio::stream_access::check(path)?;
+                std::fs::read(path)
+            }
+
             /// Try exact path, then path with backslashes (Windows), then " .ext" -> ".ext", then dir listing match.
             fn try_read(path: &str) -> Result<Vec<u8>, std::io::Error> {
-                let err = match std::fs::read(path) {
+                let err = match read_allowed(path) {
                     Ok(data) => return Ok(data),
                     Err(e) => e,
                 };
@@ -275,7 +291,7 @@ pub fn run() {
                     let with_backslash: String = path.replace('/', "\\");
                     if with_backslash != path {
                         eprintln!("[stream] Fallback 0 (backslashes): {:?}", with_backslash);
-                        if let Ok(data) = std::fs::read(&with_backslash) {
+                        if let Ok(data) = read_allowed(&with_backslash) {
                             return Ok(data);
                         }
                     }
@@ -285,7 +301,7 @@ pub fn run() {
                     if dot > 0 && path.as_bytes().get(dot.wrapping_sub(1)) == Some(&b' ') {
                         let fallback = format!("{}.{}", path[..dot - 1].trim_end(), &path[dot + 1..]);
                         eprintln!("[stream] Fallback 1 (no space before ext): {:?}", fallback);
-                        if let Ok(data) = std::fs::read(&fallback) {
+                        if let Ok(data) = read_allowed(&fallback) {
                             return Ok(data);
                         }
                     }
@@ -302,7 +318,7 @@ pub fn run() {
                                     && entry_path.is_file()
                                 {
                                     eprintln!("[stream] Fallback 2 (dir match): {:?}", entry_path);
-                                    return std::fs::read(&entry_path);
+                                    return read_allowed(&entry_path);
                                 }
                             }
                         }
@@ -329,7 +345,7 @@ pub fn run() {
                                                     if dir_name.replace('\\', "") == parent_name || dir_name.replace('\\', "/") == parent_name {
                                                         let candidate = entry_path.join(requested_name);
                                                         eprintln!("[stream] Fallback 3 (backslash parent): {:?}", candidate);
-                                                        if let Ok(data) = std::fs::read(&candidate) {
+                                                        if let Ok(data) = read_allowed(&candidate) {
                                                             return Ok(data);
                                                         }
                                                     }
@@ -370,7 +386,7 @@ pub fn run() {
 
             match try_read(&file_path) {
                 Ok(data) => {
-                    let mime = audio_mime_type(&file_path);
+                    let mime = audio::mime::data_mime_type(std::path::Path::new(&file_path), &data);
                     let total_len = data.len();
                     eprintln!("[stream] Serving {} ({} bytes, {})", file_path, total_len, mime);
 
@@ -396,7 +412,7 @@ pub fn run() {
                     let body_len = body.len();
                     let mut response = http::Response::builder()
                         .status(status)
-                        .header("Content-Type", mime)
+                        .header("Content-Type", mime.as_str())
                         .header("Content-Length", body_len.to_string())
                         .header("Accept-Ranges", "bytes")
                         .header("Access-Control-Allow-Origin", "*");
@@ -405,6 +421,14 @@ pub fn run() {
                     }
                     response.body(body).unwrap()
                 }
+                Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                    eprintln!("[stream] Rejected {}: {}", file_path, e);
+                    http::Response::builder()
+                        .status(403)
+                        .header("Content-Type", "text/plain")
+                        .body(format!("Not allowed: {}", e).into_bytes())
+                        .unwrap()
+                }
                 Err(e) => {
                     eprintln!("[stream] Error reading {}: {}", file_path, e);
                     http::Response::builder()
@@ -416,33 +440,68 @@ pub fn run() {
             }
         })
         .manage(AppState {
-            db: Mutex::new(None),
+            db: DbMutex::new(None),
             ai_context_cache: Mutex::new(None),
             db_path: Mutex::new(None),
+            track_cache: TrackCache::default(),
         })
         .manage(PlaybackState::new())
+        .manage(QueueState::new())
         .manage(WatcherState::new())
+        .manage(MidiState::new())
+        .manage(HotkeyState::new())
         .manage(CompanionState::new())
-        .invoke_handler(tauri::generate_handler![
+        .manage(AnalysisQueueState::new())
+        .manage(PlaylistExportState::new())
+        .invoke_handler(perf::instrument(tauri::generate_handler![
             greet,
             // Library commands
             commands::library::init_database,
+            commands::library::get_interrupted_batches,
+            commands::library::dismiss_interrupted_batch,
             commands::library::get_all_tracks,
             commands::library::get_tracks_paginated,
             commands::library::get_track,
             commands::library::update_track,
+            commands::library::set_track_energy,
+            commands::library::set_track_color,
+            commands::library::set_track_cooldown,
+            commands::library::get_track_cooldowns,
+            commands::library::refresh_artwork,
+            commands::library::get_artwork_thumbnail,
             commands::library::delete_track,
             commands::library::count_tracks,
+            commands::library::get_distinct_values,
+            commands::track_groups::get_tracks_grouped,
+            commands::track_groups::get_track_group_members,
             commands::library::scan_directory,
             commands::library::search_tracks,
+            commands::library::search_track_ids,
             commands::library::list_audio_files,
             commands::library::list_subdirectories,
             commands::library::get_tracks_in_folder,
             commands::library::count_tracks_in_folder,
             commands::library::get_tracks_in_folder_shallow,
             commands::library::count_tracks_in_folder_shallow,
+            commands::library::preview_stray_tracks_cleanup,
             commands::library::cleanup_stray_tracks,
             commands::library::cleanup_duplicate_tracks,
+            commands::library::find_duplicate_clusters,
+            commands::prune::suggest_prune_candidates,
+            commands::quality::analyze_quality,
+            commands::quality::analyze_all_quality,
+            commands::quality::get_quality_issues,
+            commands::library::resolve_duplicates,
+            // File verification commands
+            commands::verify::verify_track,
+            commands::verify::verify_all,
+            commands::verify::get_verification_report,
+            commands::verify::accept_verified_hash,
+            // Analysis sidecar commands
+            commands::sidecars::get_sidecar_settings,
+            commands::sidecars::set_sidecar_settings,
+            commands::sidecars::export_sidecars,
+            commands::sidecars::import_sidecars,
             commands::library::normalize_file_paths,
             commands::library::get_debug_tracks,
             // Playback commands
@@ -453,14 +512,70 @@ pub fn run() {
             commands::playback::seek,
             commands::playback::stop,
             commands::playback::get_playback_status,
+            commands::playback::set_play_context,
+            commands::playback::get_play_context,
+            commands::playback::clear_play_context,
+            commands::playback::play_section,
+            commands::playback::clear_play_section,
+            // Audition queue commands
+            commands::queue::get_queue,
+            commands::queue::enqueue_track,
+            commands::queue::dequeue_track,
+            commands::queue::reorder_queue,
+            commands::queue::clear_queue,
+            commands::queue::play_next_in_queue,
+            // Play history commands
+            commands::history::recompute_track_scores,
+            commands::history::get_most_skipped,
             // Analysis commands
             commands::analysis::analyze_bpm,
             commands::analysis::analyze_all_bpm,
             commands::analysis::analyze_key,
             commands::analysis::analyze_all_keys,
             commands::analysis::get_track_analysis,
+            commands::analysis::analyze_tempo_curve,
+            commands::analysis::get_tempo_curve,
+            commands::analysis_queue::get_analysis_queue_status,
+            commands::analysis_queue::set_auto_analysis,
+            commands::analysis_queue::request_waveforms,
+            commands::analysis_queue::analyze_playlist,
+            commands::analysis_errors::get_analysis_failures,
+            commands::analysis_errors::retry_failed_analyses,
+            commands::sessions::get_listening_sessions,
+            commands::sessions::suggest_playlist_from_session,
+            commands::sessions::get_playlist_drafts,
+            commands::sessions::accept_playlist_draft,
+            commands::sessions::discard_playlist_draft,
             commands::analysis::analyze_waveform,
+            commands::analysis::get_genre_bpm_ranges,
+            commands::analysis::set_genre_bpm_ranges,
+            commands::analysis::normalize_bpm_octaves,
+            commands::analysis::get_bpm_rounding,
+            commands::analysis::set_bpm_rounding,
+            commands::analysis::requantize_bpms,
             commands::analysis::get_waveform,
+            commands::analysis::get_analysis_cache_count,
+            commands::analysis::clear_analysis_cache,
+            // Report commands
+            commands::reports::get_mixability_report,
+            commands::reports::compare_tracks,
+            commands::tracklist::render_tracklist,
+            commands::beat_markers::export_beat_markers,
+            commands::batch::run_batch_actions,
+            commands::custom_fields::get_custom_fields,
+            commands::custom_fields::create_custom_field,
+            commands::custom_fields::update_custom_field,
+            commands::custom_fields::delete_custom_field,
+            commands::custom_fields::get_track_custom_fields,
+            commands::custom_fields::get_all_custom_field_values,
+            commands::custom_fields::set_track_custom_field,
+            commands::purchases::get_track_purchase,
+            commands::purchases::set_track_purchase,
+            commands::purchases::get_purchase_sources,
+            commands::purchases::detect_purchase_sources,
+            commands::purchases::filter_tracks_by_purchase,
+            commands::file_manager::reveal_track_in_file_manager,
+            commands::file_manager::open_containing_folder,
             // Playlist commands
             commands::playlists::create_playlist,
             commands::playlists::create_playlist_folder,
@@ -470,6 +585,21 @@ pub fn run() {
             commands::playlists::get_playlist_tracks,
             commands::playlists::add_track_to_playlist,
             commands::playlists::remove_track_from_playlist,
+            commands::playlists::get_playlist_history,
+            commands::playlists::snapshot_playlist,
+            commands::playlists::restore_playlist_snapshot,
+            commands::playlists::get_playlist_auditions,
+            commands::playlists::reset_playlist_auditions,
+            commands::playlists::get_mirrored_folders,
+            commands::playlists::add_mirrored_folder,
+            commands::playlists::remove_mirrored_folder,
+            commands::playlists::sync_folder_playlists,
+            commands::playlist_builder::build_playlist_to_duration,
+            commands::playlist_export::get_playlist_export_folder,
+            commands::playlist_export::set_playlist_export_folder,
+            commands::playlist_export::export_playlists_m3u,
+            // Rekordbox import commands
+            commands::rekordbox::import_rekordbox_library,
             // Genre commands
             commands::genre::set_track_genre,
             commands::genre::clear_track_genre,
@@ -480,6 +610,12 @@ pub fn run() {
             commands::genre::delete_genre_definition,
             commands::genre::rename_genre_definition,
             commands::genre::bulk_set_genre,
+            commands::genre::merge_genres,
+            commands::genre::suggest_genre_merges,
+            commands::genre::learn_genre_profiles,
+            commands::genre::get_genre_profiles,
+            commands::profile::export_profile,
+            commands::profile::import_profile,
             // Settings commands
             commands::settings::get_setting,
             commands::settings::set_setting,
@@ -488,21 +624,99 @@ pub fn run() {
             commands::settings::remove_library_folder,
             commands::settings::get_theme,
             commands::settings::set_theme,
+            commands::settings::get_analysis_priority,
+            commands::settings::set_analysis_priority,
+            commands::settings::get_tempo_curve_analysis,
+            commands::settings::set_tempo_curve_analysis,
+            commands::settings::get_scan_hash_mode,
+            commands::settings::set_scan_hash_mode,
+            commands::settings::get_folder_symlink_modes,
+            commands::settings::set_folder_symlink_mode,
+            commands::settings::get_write_conflict_mode,
+            commands::settings::set_write_conflict_mode,
+            commands::settings::get_energy_extractor,
+            commands::settings::set_energy_extractor,
+            commands::settings::get_stream_mime_config,
+            commands::settings::set_stream_mime_config,
+            commands::settings::get_stream_restrict_to_library,
+            commands::settings::set_stream_restrict_to_library,
+            commands::settings::get_skip_leading_silence,
+            commands::settings::set_skip_leading_silence,
+            commands::settings::get_stream_config,
+            commands::settings::set_stream_config,
+            commands::settings::get_sort_ignore_articles,
+            commands::settings::set_sort_ignore_articles,
+            // Onboarding commands
+            commands::onboarding::get_onboarding_state,
+            commands::onboarding::complete_onboarding_step,
+            commands::onboarding::suggest_music_folders,
+            commands::onboarding::run_initial_scan,
             // File watcher commands
             commands::watcher::start_file_watcher,
+            commands::watcher::get_polled_folders,
+            commands::watcher::set_folder_polling,
+            commands::watcher::get_watch_rules,
+            commands::watcher::create_watch_rule,
+            commands::watcher::update_watch_rule,
+            commands::watcher::delete_watch_rule,
+            commands::watcher::get_deleted_file_settings,
+            commands::watcher::set_deleted_file_settings,
+            // Operation journal commands
+            commands::journal::get_operation_journal,
+            commands::journal::undo_operation,
+            commands::journal::get_missing_tracks,
+            // Key color commands
+            commands::key_colors::get_key_colors,
+            commands::key_colors::set_key_colors,
+            // Gig commands
+            commands::gigs::get_gigs,
+            commands::gigs::create_gig,
+            commands::gigs::update_gig,
+            commands::gigs::delete_gig,
+            commands::gigs::link_playlist_to_gig,
+            commands::gigs::unlink_playlist_from_gig,
+            commands::gigs::get_gig_overview,
+            commands::recording::identify_set_tracklist,
+            // MIDI commands
+            commands::midi::list_midi_inputs,
+            commands::midi::start_midi,
+            commands::midi::stop_midi,
+            commands::midi::get_midi_mappings,
+            commands::midi::set_midi_mappings,
+            // Global hotkey commands
+            commands::hotkeys::start_global_hotkeys,
+            commands::hotkeys::stop_global_hotkeys,
+            commands::hotkeys::get_hotkey_config,
+            commands::hotkeys::set_hotkey_config,
             // AI commands
             commands::ai::set_ai_api_key,
             commands::ai::get_ai_api_key_status,
             commands::ai::delete_ai_api_key,
+            commands::ai::set_provider_api_key,
+            commands::ai::delete_provider_api_key,
+            commands::ai::get_api_key_statuses,
+            commands::ai::validate_api_key,
             commands::ai::rebuild_ai_context,
             commands::ai::ai_generate_playlist,
+            commands::ai::ai_refine_playlist,
             commands::ai::ai_chat,
+            commands::ai::get_ai_usage,
+            // Library sync commands
+            commands::sync::pair_sync_peer,
+            commands::sync::get_sync_peer,
+            commands::sync::forget_sync_peer,
+            commands::sync::sync_with_peer,
             // Companion server commands
             commands::server::start_companion_server,
             commands::server::stop_companion_server,
             commands::server::get_companion_status,
             commands::server::regenerate_companion_token,
-        ])
+            commands::server::reload_companion_config,
+            commands::server::get_companion_pairing_payload,
+            // Performance tracing commands
+            commands::perf::get_performance_stats,
+            commands::perf::clear_performance_stats,
+        ]))
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
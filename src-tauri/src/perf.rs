@@ -0,0 +1,228 @@
+// Performance tracing (cargo feature "perf-trace", e.g. `cargo tauri dev --features perf-trace`)
+// Records how long every Tauri command, SQL statement and database-lock hold takes into an
+// in-memory ring buffer; get_performance_stats summarizes it per name. Without the feature
+// record() returns immediately and no SQL profiling hook is installed.
+//
+// Command timings cover the handler call: synchronous commands are measured end to end,
+// async commands only until they are spawned. Their database work still shows up as
+// "db_lock" events (per lock() call site) and "query" events.
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::Runtime;
+use tauri::ipc::Invoke;
+
+/// Whether this build records anything
+pub const ENABLED: bool = cfg!(feature = "perf-trace");
+/// Events kept in the ring buffer (oldest dropped first)
+pub const CAPACITY: usize = 2000;
+/// Longer names (SQL text) are cut to this many characters
+const MAX_NAME_LEN: usize = 160;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PerfKind {
+    /// A Tauri command (name = command name)
+    Command,
+    /// An SQL statement (name = normalized SQL)
+    Query,
+    /// Holding the shared database lock (name = file:line of the lock() call)
+    DbLock,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PerfEvent {
+    pub kind: PerfKind,
+    pub name: String,
+    pub duration_us: u64,
+    /// Unix time in milliseconds when the event finished
+    pub at: u64,
+}
+
+/// Aggregate timings for one (kind, name) over the events in the buffer
+#[derive(Debug, Clone, Serialize)]
+pub struct PerfSummary {
+    pub kind: PerfKind,
+    pub name: String,
+    pub count: usize,
+    pub total_us: u64,
+    pub avg_us: u64,
+    pub p95_us: u64,
+    pub max_us: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PerformanceStats {
+    pub enabled: bool,
+    pub capacity: usize,
+    /// Most recent first
+    pub recent: Vec<PerfEvent>,
+    /// Slowest in total first
+    pub summary: Vec<PerfSummary>,
+}
+
+struct PerfLog {
+    events: VecDeque<PerfEvent>,
+}
+
+impl PerfLog {
+    const fn new() -> Self {
+        PerfLog { events: VecDeque::new() }
+    }
+
+    fn push(&mut self, event: PerfEvent) {
+        if self.events.len() >= CAPACITY {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    fn stats(&self, recent_limit: usize) -> PerformanceStats {
+        let mut by_name: HashMap<(PerfKind, &str), Vec<u64>> = HashMap::new();
+        for event in &self.events {
+            by_name.entry((event.kind, &event.name)).or_default().push(event.duration_us);
+        }
+
+        let mut summary: Vec<PerfSummary> = by_name
+            .into_iter()
+            .map(|((kind, name), mut durations)| {
+                durations.sort_unstable();
+                let count = durations.len();
+                let total_us: u64 = durations.iter().sum();
+                let p95_index = ((count as f64 * 0.95).ceil() as usize).clamp(1, count) - 1;
+                PerfSummary {
+                    kind,
+                    name: name.to_string(),
+                    count,
+                    total_us,
+                    avg_us: total_us / count as u64,
+                    p95_us: durations[p95_index],
+                    max_us: durations[count - 1],
+                }
+            })
+            .collect();
+        summary.sort_by(|a, b| b.total_us.cmp(&a.total_us).then_with(|| a.name.cmp(&b.name)));
+
+        PerformanceStats {
+            enabled: ENABLED,
+            capacity: CAPACITY,
+            recent: self.events.iter().rev().take(recent_limit).cloned().collect(),
+            summary,
+        }
+    }
+}
+
+static LOG: Mutex<PerfLog> = Mutex::new(PerfLog::new());
+
+/// Collapse whitespace and cut long names so similar statements group together
+fn normalize_name(name: &str) -> String {
+    let mut normalized = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if let Some((cut, _)) = normalized.char_indices().nth(MAX_NAME_LEN) {
+        normalized.truncate(cut);
+        normalized.push('…');
+    }
+    normalized
+}
+
+/// Record one timing (no-op unless built with "perf-trace")
+pub fn record(kind: PerfKind, name: &str, duration: Duration) {
+    if !ENABLED {
+        return;
+    }
+    let at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let event = PerfEvent {
+        kind,
+        name: normalize_name(name),
+        duration_us: duration.as_micros() as u64,
+        at,
+    };
+    if let Ok(mut log) = LOG.lock() {
+        log.push(event);
+    }
+}
+
+/// SQLite profile callback, installed on every connection by Database::new
+#[cfg(feature = "perf-trace")]
+pub fn record_query(sql: &str, duration: Duration) {
+    record(PerfKind::Query, sql, duration);
+}
+
+/// Wrap the app's invoke handler so every command call is timed
+pub fn instrument<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        if !ENABLED {
+            return handler(invoke);
+        }
+        let command = invoke.message.command().to_string();
+        let started = std::time::Instant::now();
+        let handled = handler(invoke);
+        record(PerfKind::Command, &command, started.elapsed());
+        handled
+    }
+}
+
+/// Snapshot of the buffer with at most `recent_limit` raw events
+pub fn stats(recent_limit: usize) -> PerformanceStats {
+    match LOG.lock() {
+        Ok(log) => log.stats(recent_limit),
+        Err(_) => PerfLog::new().stats(0),
+    }
+}
+
+pub fn clear() {
+    if let Ok(mut log) = LOG.lock() {
+        log.events.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: PerfKind, name: &str, duration_us: u64) -> PerfEvent {
+        PerfEvent { kind, name: name.to_string(), duration_us, at: 0 }
+    }
+
+    #[test]
+    fn test_ring_buffer_and_summary() {
+        let mut log = PerfLog::new();
+        for i in 0..CAPACITY + 10 {
+            log.push(event(PerfKind::Query, "SELECT 1", i as u64));
+        }
+        assert_eq!(log.events.len(), CAPACITY);
+        assert_eq!(log.events.front().unwrap().duration_us, 10);
+
+        log.events.clear();
+        for us in 1..=100 {
+            log.push(event(PerfKind::Command, "get_tracks", us));
+        }
+        log.push(event(PerfKind::DbLock, "src/commands/library.rs:10:5", 9000));
+
+        let stats = log.stats(3);
+        assert_eq!(stats.recent.len(), 3);
+        assert_eq!(stats.recent[0].kind, PerfKind::DbLock);
+
+        let command = stats.summary.iter().find(|s| s.kind == PerfKind::Command).unwrap();
+        assert_eq!(command.count, 100);
+        assert_eq!(command.total_us, 5050);
+        assert_eq!(command.avg_us, 50);
+        assert_eq!(command.p95_us, 95);
+        assert_eq!(command.max_us, 100);
+        // Sorted by total time
+        assert_eq!(stats.summary[0].kind, PerfKind::DbLock);
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("SELECT *\n   FROM tracks\n  WHERE id = ?1"), "SELECT * FROM tracks WHERE id = ?1");
+        let long = "x".repeat(MAX_NAME_LEN + 5);
+        assert_eq!(normalize_name(&long).chars().count(), MAX_NAME_LEN + 1);
+    }
+}
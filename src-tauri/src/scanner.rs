@@ -1,9 +1,19 @@
 // Library scanner - Find and extract metadata from audio files
 
-use crate::db::{Database, Track};
+use crate::audio::key::{key_to_camelot, parse_camelot};
+use crate::db::{
+    Database, FileStat, Track, TrackPurchase, MIXED_IN_KEY_SOURCE, PENDING_HASH, SERATO_SOURCE, TAG_SOURCE,
+};
+use crate::formats::serato::{self, SeratoCue};
+use crate::formats::sidecar::{self, Sidecar, SidecarSettings};
 use lofty::prelude::*;
+use lofty::config::ParseOptions;
+use lofty::file::FileType;
+use lofty::id3::v2::{Frame, GeneralEncapsulatedObject, Id3v2Tag};
 use lofty::read_from_path;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
@@ -12,13 +22,418 @@ use walkdir::WalkDir;
 /// Supported audio file extensions
 const SUPPORTED_EXTENSIONS: &[&str] = &["mp3", "flac", "wav", "aiff", "aif", "m4a", "ogg"];
 
+/// Files inserted per write transaction during a scan
+pub const SCAN_BATCH_SIZE: usize = 200;
+
+/// Setting key for the energy extractor configuration (JSON)
+pub const ENERGY_EXTRACTOR_SETTING: &str = "energy_extractor";
+/// Highest energy level accepted (Mixed In Key and most DJ tools use 1-10)
+pub const MAX_ENERGY_LEVEL: i32 = 10;
+/// Custom field Mixed In Key writes the energy level to (ID3 TXXX:EnergyLevel)
+const MIK_ENERGY_FIELD: &str = "EnergyLevel";
+/// Setting key: when content hashes are computed ("full" or "lazy")
+pub const HASH_MODE_SETTING: &str = "scan_hash_mode";
+/// Setting key: what to do before modifying a file changed by another program ("refuse" or "reread")
+pub const WRITE_CONFLICT_SETTING: &str = "write_conflict_mode";
+/// Setting key: JSON object of library folder -> SymlinkMode, for folders not following links
+pub const SYMLINK_FOLDERS_SETTING: &str = "scan_symlink_folders";
+
 /// Result of scanning a directory
 #[derive(Debug)]
 pub struct ScanResult {
     pub total_files: usize,
     pub imported: usize,
+    /// Known files whose size or mtime changed and were re-read
+    pub updated: usize,
+    /// New paths matched to a library track whose file had moved there
+    pub moved: usize,
     pub skipped: usize,
     pub errors: Vec<ScanError>,
+    /// IDs of newly imported tracks (fed to the auto-analysis queue)
+    pub new_track_ids: Vec<i64>,
+    /// Symlinks not scanned: skipped by the folder's SymlinkMode, or leading to a folder or
+    /// file already scanned through another path (which includes symlink loops)
+    pub symlinks_skipped: Vec<PathBuf>,
+}
+
+/// What the scanner does with symbolic links in a library folder
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkMode {
+    /// Scan what links point to; each folder and file is still scanned only once
+    #[default]
+    Follow,
+    /// Leave links out of the scan
+    Skip,
+}
+
+/// Library folders' symlink modes, stored for the folders that differ from the default
+pub fn load_symlink_folders(db: &Database) -> HashMap<String, SymlinkMode> {
+    db.get_setting(SYMLINK_FOLDERS_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// When the scanner computes content hashes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashMode {
+    /// Hash every new or changed file during the scan (duplicate content is caught at import)
+    #[default]
+    Full,
+    /// Don't hash during scans. Hashes are computed when duplicate detection needs them.
+    Lazy,
+}
+
+impl HashMode {
+    /// Load the configured mode, falling back to Full
+    pub fn from_settings(db: &Database) -> Self {
+        match db.get_setting(HASH_MODE_SETTING).ok().flatten().as_deref() {
+            Some("lazy") => HashMode::Lazy,
+            _ => HashMode::Full,
+        }
+    }
+}
+
+/// Whether a track's file still is what RecoDeck last read (or wrote)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    Fresh,
+    /// Modified by another program (Rekordbox, Mp3tag...) since RecoDeck last read it
+    Changed,
+    Missing,
+}
+
+/// What to do before writing to a file that changed on disk since it was read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WriteConflictMode {
+    /// Don't touch the file; the user rescans it first
+    #[default]
+    Refuse,
+    /// Re-read the file into the library, then write
+    Reread,
+}
+
+impl WriteConflictMode {
+    /// Load the configured mode, falling back to Refuse
+    pub fn from_settings(db: &Database) -> Self {
+        match db.get_setting(WRITE_CONFLICT_SETTING).ok().flatten().as_deref() {
+            Some("reread") => WriteConflictMode::Reread,
+            _ => WriteConflictMode::Refuse,
+        }
+    }
+}
+
+/// Settings that shape a scan, loaded once per scan
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    pub energy: EnergyExtractor,
+    pub hash_mode: HashMode,
+    /// Read analysis from RecoDeck sidecar files (formats::sidecar)
+    pub read_sidecars: bool,
+    /// Symlink modes by library folder (normalize_root form)
+    pub symlink_folders: HashMap<String, SymlinkMode>,
+}
+
+impl ScanOptions {
+    pub fn from_settings(db: &Database) -> Self {
+        ScanOptions {
+            energy: EnergyExtractor::from_settings(db),
+            hash_mode: HashMode::from_settings(db),
+            read_sidecars: SidecarSettings::from_settings(db).read,
+            symlink_folders: load_symlink_folders(db),
+        }
+    }
+
+    /// Symlink mode for scanning `path`: that of the innermost configured folder containing it
+    pub fn symlink_mode(&self, path: &Path) -> SymlinkMode {
+        self.symlink_folders
+            .iter()
+            .filter(|(folder, _)| path.starts_with(folder.as_str()))
+            .max_by_key(|(folder, _)| folder.len())
+            .map(|(_, mode)| *mode)
+            .unwrap_or_default()
+    }
+}
+
+/// Audio files found under a folder
+#[derive(Debug, Default)]
+pub struct DirectoryScan {
+    pub files: Vec<PathBuf>,
+    /// See ScanResult::symlinks_skipped
+    pub symlinks_skipped: Vec<PathBuf>,
+}
+
+/// What makes two paths the same folder or file: device and inode
+#[cfg(unix)]
+fn file_identity(_path: &Path, metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// What makes two paths the same folder or file: the canonical path
+#[cfg(not(unix))]
+fn file_identity(path: &Path, _metadata: &fs::Metadata) -> Option<PathBuf> {
+    fs::canonicalize(path).ok()
+}
+
+/// What a scan found for one file, ready to be written by `Scanner::write_batch`
+#[derive(Debug)]
+pub enum ScannedFile {
+    /// Not in the library yet
+    New(PathBuf, Track, TagValues),
+    /// In the library, but its size or mtime changed: (path, track id, re-read track, tag values)
+    Changed(PathBuf, i64, Track, TagValues),
+    /// In the library and assumed unchanged, but no mtime recorded yet: (track id, size, mtime)
+    Stat(i64, Option<i64>, Option<i64>),
+}
+
+/// Analysis-relevant values read from file tags
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TagValues {
+    pub bpm: Option<f64>,
+    /// Camelot notation (converted from whatever notation the tag used)
+    pub key: Option<String>,
+    pub genre: Option<String>,
+    /// Energy level (1-10) from Mixed In Key's fields or found by the EnergyExtractor
+    pub energy: Option<i32>,
+    /// Which tool wrote the values
+    pub source: TagSource,
+    /// Star rating (1-5) from a POPM frame or RATING field
+    pub rating: Option<i32>,
+    /// Hot cues and loops from Serato's markers
+    pub cues: Vec<SeratoCue>,
+    /// Analysis from a RecoDeck sidecar next to the file
+    pub sidecar: Option<Sidecar>,
+}
+
+/// Where tag values came from, stored as their source (bpm_source, key_source, energy_source)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TagSource {
+    /// Generic tags (Traktor, Rekordbox, a tag editor...)
+    #[default]
+    Tag,
+    /// The file carries Mixed In Key's fields, so its values are Mixed In Key's analysis
+    MixedInKey,
+}
+
+impl TagSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TagSource::Tag => TAG_SOURCE,
+            TagSource::MixedInKey => MIXED_IN_KEY_SOURCE,
+        }
+    }
+}
+
+/// Key and energy Mixed In Key left in a file's tags: the TXXX:EnergyLevel field and/or
+/// its comment format ("8A - Energy 7", "Energy 7 - 8A", "8A - 128 - Energy 7")
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MixedInKeyValues {
+    /// Camelot
+    pub key: Option<String>,
+    pub energy: Option<i32>,
+}
+
+impl MixedInKeyValues {
+    /// None if the tag has none of Mixed In Key's fields
+    pub fn read(tag: &lofty::tag::Tag) -> Option<Self> {
+        let energy_field = tag.get_string(&ItemKey::Unknown(MIK_ENERGY_FIELD.to_string()))
+            .or_else(|| tag.get_string(&ItemKey::Unknown(MIK_ENERGY_FIELD.to_uppercase())))
+            .and_then(|s| s.trim().parse::<i32>().ok())
+            .filter(|n| (1..=MAX_ENERGY_LEVEL).contains(n));
+        let from_comment = tag.comment().and_then(|c| Self::parse_comment(&c));
+        if energy_field.is_none() && from_comment.is_none() {
+            return None;
+        }
+        let from_comment = from_comment.unwrap_or_default();
+        Some(MixedInKeyValues {
+            key: from_comment.key,
+            energy: energy_field.or(from_comment.energy),
+        })
+    }
+
+    /// Parse a comment written by Mixed In Key. Every " - " separated part must be a
+    /// Camelot key ("8A", "8A/9A"), "Energy N" or a BPM, and a key must be present,
+    /// so ordinary comments that merely mention a key don't match.
+    pub fn parse_comment(text: &str) -> Option<Self> {
+        let mut values = MixedInKeyValues::default();
+        for part in text.split(" - ").map(str::trim) {
+            let lower = part.to_lowercase();
+            if let Some(level) = lower.strip_prefix("energy") {
+                let level: i32 = level.trim().parse().ok()?;
+                if !(1..=MAX_ENERGY_LEVEL).contains(&level) {
+                    return None;
+                }
+                values.energy = Some(level);
+            } else if let Some((number, is_minor)) = part
+                .split('/')
+                .next()
+                .and_then(|first| parse_camelot(first.trim().trim_start_matches('0')))
+            {
+                values.key.get_or_insert(format!("{}{}", number, if is_minor { 'A' } else { 'B' }));
+            } else if part.parse::<f64>().is_err() {
+                return None;
+            }
+        }
+        values.key.is_some().then_some(values)
+    }
+}
+
+/// Finds an energy level in tag fields, e.g. "Energy 7" in the comment
+/// (Mixed In Key writes "8A - Energy 7") or a custom ENERGY tag holding "7".
+/// Configurable from settings so users can point it at whatever field their tools write.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnergyExtractor {
+    /// Tag fields to search, in order: "comment", "grouping", "mood", "description",
+    /// or the name of any custom field (e.g. "ENERGY", "TXXX:EnergyLevel")
+    pub fields: Vec<String>,
+    /// Words that introduce the level ("energy 7", "level: 7"). Matched case-insensitively.
+    pub keywords: Vec<String>,
+}
+
+impl Default for EnergyExtractor {
+    fn default() -> Self {
+        EnergyExtractor {
+            fields: vec!["comment".to_string(), "ENERGY".to_string()],
+            keywords: vec!["energy".to_string(), "level".to_string()],
+        }
+    }
+}
+
+impl EnergyExtractor {
+    /// Load the configured extractor, falling back to the default
+    pub fn from_settings(db: &Database) -> Self {
+        db.get_setting(ENERGY_EXTRACTOR_SETTING)
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Search the configured fields of a tag, first match wins
+    pub fn extract(&self, tag: &lofty::tag::Tag) -> Option<i32> {
+        self.fields.iter().find_map(|field| {
+            let value = match field.to_lowercase().as_str() {
+                "comment" => tag.comment().map(|c| c.to_string()),
+                "grouping" => tag.get_string(&ItemKey::ContentGroup).map(|s| s.to_string()),
+                "mood" => tag.get_string(&ItemKey::Mood).map(|s| s.to_string()),
+                "description" => tag.get_string(&ItemKey::Description).map(|s| s.to_string()),
+                _ => {
+                    let name = field.strip_prefix("TXXX:").unwrap_or(field);
+                    tag.get_string(&ItemKey::Unknown(name.to_string()))
+                        .or_else(|| tag.get_string(&ItemKey::Unknown(name.to_uppercase())))
+                        .map(|s| s.to_string())
+                }
+            }?;
+            self.parse(&value)
+        })
+    }
+
+    /// Parse an energy level from a field's text: either the whole value is a number
+    /// ("7"), or a keyword is followed by one ("Energy 7", "8A - energy: 7", "Level-6")
+    pub fn parse(&self, text: &str) -> Option<i32> {
+        let valid = |n: i32| (1..=MAX_ENERGY_LEVEL).contains(&n);
+
+        if let Ok(n) = text.trim().parse::<i32>() {
+            return Some(n).filter(|n| valid(*n));
+        }
+
+        let lower = text.to_lowercase();
+        for keyword in &self.keywords {
+            let keyword = keyword.to_lowercase();
+            if keyword.is_empty() {
+                continue;
+            }
+            for (pos, _) in lower.match_indices(&keyword) {
+                // Keyword must start a word ("Energy", not "synergy")
+                if lower[..pos].chars().next_back().is_some_and(|c| c.is_alphanumeric()) {
+                    continue;
+                }
+                let rest = lower[pos + keyword.len()..]
+                    .trim_start_matches(|c: char| c.is_whitespace() || ":=-#".contains(c));
+                let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+                if let Ok(n) = digits.parse::<i32>() {
+                    if valid(n) {
+                        return Some(n);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Stars (1-5) for a POPM rating byte, using the common mapping (Windows Media Player,
+/// foobar2000, MusicBee): 1, 64, 128, 196, 255. 0 means unrated.
+pub fn popm_stars(rating: u8) -> Option<i32> {
+    match rating {
+        0 => None,
+        1..=31 => Some(1),
+        32..=95 => Some(2),
+        96..=159 => Some(3),
+        160..=223 => Some(4),
+        _ => Some(5),
+    }
+}
+
+/// Stars (1-5) for a text rating field (Vorbis RATING, APE, MP4 "rate"): "0.0"-"1.0"
+/// (FMPS), "1"-"5", "0"-"100" (percent) or "0"-"255" (POPM scale)
+pub fn rating_text_stars(text: &str) -> Option<i32> {
+    let text = text.trim();
+    let value: f64 = text.parse().ok()?;
+    let stars = if text.contains('.') && value <= 1.0 {
+        (value * 5.0).round() as i32
+    } else if value <= 5.0 {
+        value.round() as i32
+    } else if value <= 100.0 {
+        (value / 20.0).round() as i32
+    } else if value <= 255.0 {
+        return popm_stars(value as u8);
+    } else {
+        return None;
+    };
+    Some(stars).filter(|s| (1..=5).contains(s))
+}
+
+/// Rating and Serato cues from an ID3v2 tag's POPM and GEOB frames, which lofty's
+/// generic tag doesn't carry
+fn id3v2_annotations(tag: &Id3v2Tag) -> (Option<i32>, Vec<SeratoCue>) {
+    let mut rating = None;
+    let mut cues = Vec::new();
+    for frame in tag {
+        match frame {
+            Frame::Popularimeter(popm) => {
+                rating = rating.or_else(|| popm_stars(popm.rating));
+            }
+            Frame::Binary(binary) if binary.id().as_str() == "GEOB" => {
+                let Ok(geob) = GeneralEncapsulatedObject::parse(&binary.data, binary.flags()) else {
+                    continue;
+                };
+                if geob.descriptor.as_deref() == Some(serato::MARKERS2_DESCRIPTOR) {
+                    cues = serato::parse_markers2(&geob.data);
+                }
+            }
+            _ => {}
+        }
+    }
+    (rating, cues)
+}
+
+/// Read the ID3v2 tag of an MP3, AIFF or WAV file on its own (audio properties and
+/// pictures skipped). None for other formats or if the file has no ID3v2 tag.
+fn read_id3v2(path: &Path, file_type: FileType) -> Option<Id3v2Tag> {
+    let mut file = fs::File::open(path).ok()?;
+    let options = ParseOptions::new().read_properties(false).read_cover_art(false);
+    match file_type {
+        FileType::Mpeg => lofty::mpeg::MpegFile::read_from(&mut file, options).ok()?.id3v2().cloned(),
+        FileType::Aiff => lofty::iff::aiff::AiffFile::read_from(&mut file, options).ok()?.id3v2().cloned(),
+        FileType::Wav => lofty::iff::wav::WavFile::read_from(&mut file, options).ok()?.id3v2().cloned(),
+        _ => None,
+    }
 }
 
 /// Errors that can occur during scanning
@@ -28,29 +443,245 @@ pub struct ScanError {
     pub error: String,
 }
 
+/// Outcome of writing a new file to the library
+enum Inserted {
+    New(i64),
+    /// An existing track whose file had moved to this path
+    Moved(i64),
+}
+
+/// A library folder as stored: trimmed, without trailing separators (except a bare root)
+pub fn normalize_root(path: &str) -> String {
+    let mut root = path.trim().to_string();
+    while root.len() > 1 && (root.ends_with('/') || (cfg!(windows) && root.ends_with('\\'))) {
+        root.pop();
+    }
+    root
+}
+
+/// Library folders with nested ones folded into their parent (/Music covers /Music/House)
+/// and duplicates dropped, so each file is scanned and watched under one root.
+/// Order of the remaining folders is kept.
+pub fn collapse_roots(folders: &[String]) -> Vec<String> {
+    let folders: Vec<String> = folders.iter().map(|f| normalize_root(f)).collect();
+    let mut roots: Vec<String> = Vec::new();
+    for (i, folder) in folders.iter().enumerate() {
+        let covered = folders.iter().enumerate().any(|(j, other)| {
+            let nested = Path::new(folder).starts_with(other);
+            // Same folder twice: keep the first
+            nested && (other != folder || j < i)
+        });
+        if !covered {
+            roots.push(folder.clone());
+        }
+    }
+    roots
+}
+
+/// Image file names (without extension) used as a folder's cover, in order of preference
+const FOLDER_ARTWORK_NAMES: &[&str] = &["cover", "folder", "front", "album", "artwork"];
+const FOLDER_ARTWORK_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+
+/// Cover image next to the audio files (cover.jpg, folder.jpg, front.png...), names matched
+/// ignoring case
+pub fn find_folder_artwork(dir: &Path) -> Option<PathBuf> {
+    let mut best: Option<(usize, PathBuf)> = None;
+    for entry in fs::read_dir(dir).ok()?.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let (Some(stem), Some(ext)) = (path.file_stem(), path.extension()) else {
+            continue;
+        };
+        let (stem, ext) = (stem.to_string_lossy().to_lowercase(), ext.to_string_lossy().to_lowercase());
+        if !FOLDER_ARTWORK_EXTENSIONS.contains(&ext.as_str()) || !path.is_file() {
+            continue;
+        }
+        if let Some(rank) = FOLDER_ARTWORK_NAMES.iter().position(|name| *name == stem) {
+            if best.as_ref().is_none_or(|(best_rank, _)| rank < *best_rank) {
+                best = Some((rank, path));
+            }
+        }
+    }
+    best.map(|(_, path)| path)
+}
+
+/// Folder-name words that tell where files came from: (word, source, whether the word may be
+/// part of a longer one like "beatport_downloads" -> "beatportdownloads")
+const PURCHASE_SOURCE_WORDS: &[(&str, &str, bool)] = &[
+    ("beatport", "Beatport", true),
+    ("bandcamp", "Bandcamp", true),
+    ("traxsource", "Traxsource", true),
+    ("junodownload", "Juno Download", true),
+    ("juno", "Juno Download", false),
+    ("inflyte", "Promo", true),
+    ("promo", "Promo", false),
+    ("promos", "Promo", false),
+    ("promopool", "Promo", false),
+];
+
+fn folder_words(folder: &str) -> Vec<String> {
+    folder
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+fn purchase_source_in(words: &[String]) -> Option<&'static str> {
+    words.iter().find_map(|word| {
+        PURCHASE_SOURCE_WORDS
+            .iter()
+            .find(|(key, _, partial)| word == key || (*partial && word.contains(key)))
+            .map(|(_, source, _)| *source)
+    })
+}
+
+/// YYYY-MM-DD from consecutive words (2024 03 12) or one (20240312)
+fn purchase_date_in(words: &[String]) -> Option<String> {
+    let valid = |y: &str, m: &str, d: &str| {
+        let (Ok(year), Ok(month), Ok(day)) = (y.parse::<u32>(), m.parse::<u32>(), d.parse::<u32>()) else {
+            return None;
+        };
+        ((1990..=2100).contains(&year) && (1..=12).contains(&month) && (1..=31).contains(&day))
+            .then(|| format!("{}-{}-{}", y, m, d))
+    };
+    let digits = |w: &String, n: usize| w.len() == n && w.chars().all(|c| c.is_ascii_digit());
+    for (i, word) in words.iter().enumerate() {
+        if digits(word, 8) {
+            if let Some(date) = valid(&word[..4], &word[4..6], &word[6..]) {
+                return Some(date);
+            }
+        }
+        if let [y, m, d, ..] = &words[i..] {
+            if digits(y, 4) && digits(m, 2) && digits(d, 2) {
+                if let Some(date) = valid(y, m, d) {
+                    return Some(date);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// "Order 123456", "order_123456" or "order123456"
+fn purchase_order_in(words: &[String]) -> Option<String> {
+    let is_id = |w: &str| w.len() >= 4 && w.chars().any(|c| c.is_ascii_digit());
+    words.iter().enumerate().find_map(|(i, word)| {
+        let rest = word.strip_prefix("order")?;
+        if rest.is_empty() {
+            words.get(i + 1).filter(|next| is_id(next)).cloned()
+        } else {
+            is_id(rest).then(|| rest.to_string())
+        }
+    })
+}
+
+/// Guess where a file was bought from its folders (e.g. ".../Beatport/beatport_tracks_2024_03_12/",
+/// an extracted order archive). The folder naming the store is preferred for date and order
+/// ID, then the other folders, innermost first. None if no store or promo folder is found.
+pub fn guess_purchase(path: &Path) -> Option<TrackPurchase> {
+    let folders: Vec<Vec<String>> = path
+        .parent()?
+        .components()
+        .rev()
+        .map(|c| folder_words(&c.as_os_str().to_string_lossy()))
+        .collect();
+    let (index, source) = folders
+        .iter()
+        .enumerate()
+        .find_map(|(i, words)| purchase_source_in(words).map(|s| (i, s)))?;
+
+    let mut order: Vec<&Vec<String>> = vec![&folders[index]];
+    order.extend(folders.iter().enumerate().filter(|(i, _)| *i != index).map(|(_, w)| w));
+    Some(TrackPurchase {
+        source: Some(source.to_string()),
+        date: order.iter().find_map(|words| purchase_date_in(words)),
+        order_id: order.iter().find_map(|words| purchase_order_in(words)),
+    })
+}
+
 /// Library scanner
 pub struct Scanner;
 
 impl Scanner {
-    /// Scan a directory recursively for audio files
+    /// Scan a directory recursively for audio files, following symlinks
     pub fn scan_directory(path: &Path) -> Vec<PathBuf> {
-        let mut audio_files = Vec::new();
+        Self::scan_directory_with(path, SymlinkMode::Follow).files
+    }
 
-        for entry in WalkDir::new(path)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
+    /// Scan a directory recursively for audio files. Every folder and file is visited once,
+    /// by device and inode: a second path to it (a link to a folder already scanned, a
+    /// link back to a parent) is skipped and reported.
+    pub fn scan_directory_with(path: &Path, symlinks: SymlinkMode) -> DirectoryScan {
+        let mut scan = DirectoryScan::default();
+        let mut visited = HashSet::new();
+
+        let mut walker = WalkDir::new(path)
+            .follow_links(symlinks == SymlinkMode::Follow)
+            .into_iter();
+        while let Some(entry) = walker.next() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    // A link back to one of its own parents
+                    if let (Some(_), Some(path)) = (e.loop_ancestor(), e.path()) {
+                        scan.symlinks_skipped.push(path.to_path_buf());
+                    }
+                    continue;
+                }
+            };
             let path = entry.path();
-            if let Some(ext) = path.extension() {
-                let ext_str = ext.to_string_lossy().to_lowercase();
-                if SUPPORTED_EXTENSIONS.contains(&ext_str.as_str()) {
-                    audio_files.push(path.to_path_buf());
+            if entry.path_is_symlink() && symlinks == SymlinkMode::Skip {
+                // Not followed: only report links that lead to folders or audio files
+                if path.is_dir() || Self::is_audio_file(path) {
+                    scan.symlinks_skipped.push(path.to_path_buf());
+                }
+                continue;
+            }
+            let is_dir = entry.file_type().is_dir();
+            if !is_dir && !Self::is_audio_file(path) {
+                continue;
+            }
+            let first_visit = entry
+                .metadata()
+                .ok()
+                .and_then(|metadata| file_identity(path, &metadata))
+                .is_none_or(|identity| visited.insert(identity));
+            if !first_visit {
+                scan.symlinks_skipped.push(path.to_path_buf());
+                if is_dir {
+                    walker.skip_current_dir();
                 }
+                continue;
+            }
+            if !is_dir {
+                scan.files.push(path.to_path_buf());
             }
         }
 
-        audio_files
+        scan
+    }
+
+    /// Check whether a path has a supported audio file extension
+    pub fn is_audio_file(path: &Path) -> bool {
+        path.extension()
+            .map(|ext| {
+                let ext_str = ext.to_string_lossy().to_lowercase();
+                SUPPORTED_EXTENSIONS.contains(&ext_str.as_str())
+            })
+            .unwrap_or(false)
+    }
+
+    /// Folder cover image for a file without embedded artwork (see find_folder_artwork).
+    /// None if the file has its own picture or the folder has no cover.
+    pub fn detect_artwork(path: &Path) -> Result<Option<String>, String> {
+        let tagged_file = read_from_path(path)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if tagged_file.tags().iter().any(|tag| !tag.pictures().is_empty()) {
+            return Ok(None);
+        }
+        Ok(path.parent()
+            .and_then(find_folder_artwork)
+            .map(|p| p.to_string_lossy().to_string()))
     }
 
     /// Calculate SHA256 hash of a file (for change detection)
@@ -70,9 +701,165 @@ impl Scanner {
         Ok(format!("{:x}", hasher.finalize()))
     }
 
+    /// Size and mtime (Unix seconds) of a file, as stored in tracks.file_size/file_mtime
+    pub fn file_stat(path: &Path) -> (Option<i64>, Option<i64>) {
+        match fs::metadata(path) {
+            Ok(meta) => (
+                Some(meta.len() as i64),
+                meta.modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs() as i64),
+            ),
+            Err(_) => (None, None),
+        }
+    }
+
+    /// Compare a file with the size/mtime and hash recorded when it was last read.
+    /// A different mtime alone isn't a change when the content hash still matches
+    /// (touch, copy); without a hash, a recorded mtime that differs counts as changed.
+    pub fn file_freshness(path: &Path, known: &FileStat, known_hash: &str) -> Freshness {
+        let (size, mtime) = Self::file_stat(path);
+        if size.is_none() {
+            return Freshness::Missing;
+        }
+        if known.mtime.is_some() && size == known.size && mtime == known.mtime {
+            return Freshness::Fresh;
+        }
+        if known_hash != PENDING_HASH && known_hash != "unknown" {
+            return match Self::calculate_file_hash(path) {
+                Ok(hash) if hash == known_hash => Freshness::Fresh,
+                _ => Freshness::Changed,
+            };
+        }
+        if known.mtime.is_none() && size == known.size {
+            Freshness::Fresh
+        } else {
+            Freshness::Changed
+        }
+    }
+
+    /// Check a track's file before modifying it (tag writing, renaming), so edits made
+    /// meanwhile in another program aren't clobbered. A changed file is refused or
+    /// re-read first, per `mode`. Returns the file path to write to.
+    pub fn ensure_fresh(db: &Database, track_id: i64, mode: WriteConflictMode) -> Result<String, String> {
+        let track = db.get_track(track_id).map_err(|e| format!("Failed to get track: {}", e))?;
+        let known = db.get_file_stat(track_id).map_err(|e| format!("Database error: {}", e))?;
+        let path = Path::new(&track.file_path);
+
+        match Self::file_freshness(path, &known, &track.file_hash) {
+            Freshness::Fresh => Ok(track.file_path),
+            Freshness::Missing => Err(format!("Audio file not found: {}", track.file_path)),
+            Freshness::Changed => match mode {
+                WriteConflictMode::Refuse => Err(format!(
+                    "FILE_CHANGED: {} was modified outside RecoDeck; rescan it before writing",
+                    track.file_path
+                )),
+                WriteConflictMode::Reread => {
+                    eprintln!("[scanner] {} changed on disk, re-reading before writing", track.file_path);
+                    let (mut fresh, tag_values) = Self::extract_metadata(path, &ScanOptions::from_settings(db))?;
+                    if fresh.file_hash == PENDING_HASH {
+                        fresh.file_hash = Self::calculate_file_hash(path)
+                            .unwrap_or_else(|_| "unknown".to_string());
+                    }
+                    let (size, mtime) = Self::file_stat(path);
+                    db.update_track_file_info(track_id, &fresh)
+                        .and_then(|_| db.set_track_file_stat(track_id, size, mtime))
+                        .map_err(|e| format!("Database error: {}", e))?;
+                    Self::save_tag_values(db, track_id, &tag_values);
+                    Ok(track.file_path)
+                }
+            },
+        }
+    }
+
+    /// Record RecoDeck's own write to a track's file (new size, mtime and hash),
+    /// so it isn't mistaken for an outside change by the next freshness check or rescan.
+    pub fn record_file_write(db: &Database, track_id: i64, path: &Path) -> Result<(), String> {
+        let track = db.get_track(track_id).map_err(|e| format!("Failed to get track: {}", e))?;
+        let (size, mtime) = Self::file_stat(path);
+        let hash = if track.file_hash != PENDING_HASH {
+            Some(Self::calculate_file_hash(path).map_err(|e| format!("Failed to hash file: {}", e))?)
+        } else {
+            None
+        };
+        let tx = db.begin_write().map_err(|e| format!("Database error: {}", e))?;
+        db.set_track_file_stat(track_id, size, mtime)
+            .map_err(|e| format!("Database error: {}", e))?;
+        if let Some(hash) = hash {
+            db.set_track_hash(track_id, &hash)
+                .map_err(|e| format!("Database error: {}", e))?;
+        }
+        tx.commit().map_err(|e| format!("Database error: {}", e))
+    }
+
+    /// Compute hashes for tracks scanned in lazy hash mode, in one transaction.
+    /// Call before anything that compares hashes (duplicate detection, hash matching).
+    /// Returns the number of tracks hashed.
+    pub fn hash_pending(db: &Database) -> rusqlite::Result<usize> {
+        let pending = db.get_tracks_pending_hash()?;
+        if pending.is_empty() {
+            return Ok(0);
+        }
+
+        let tx = db.transaction()?;
+        let mut restored = 0;
+        for (track_id, file_path) in &pending {
+            let hash = Self::calculate_file_hash(Path::new(file_path))
+                .unwrap_or_else(|_| "unknown".to_string());
+            db.set_track_hash(*track_id, &hash)?;
+            if hash != "unknown" && db.restore_cached_analysis(*track_id, &hash)? {
+                restored += 1;
+            }
+        }
+        tx.commit()?;
+        if restored > 0 {
+            eprintln!("[scanner] Restored cached analysis for {} tracks", restored);
+        }
+
+        eprintln!("[scanner] Hashed {} tracks pending a content hash", pending.len());
+        Ok(pending.len())
+    }
+
+    /// Decide what a scan has to do with one file. `known` is the stat recorded for
+    /// this path, if it's already in the library. Known files whose size and mtime
+    /// still match return None: they are skipped without reading tags or hashing.
+    /// Only does file I/O, so call it without holding the DB lock.
+    pub fn scan_file(
+        path: &Path,
+        known: Option<&FileStat>,
+        options: &ScanOptions,
+    ) -> Option<Result<ScannedFile, String>> {
+        let known = match known {
+            Some(k) => k,
+            None => {
+                return Some(
+                    Self::extract_metadata(path, options)
+                        .map(|(track, tags)| ScannedFile::New(path.to_path_buf(), track, tags)),
+                )
+            }
+        };
+
+        let (size, mtime) = Self::file_stat(path);
+        if known.mtime.is_none() {
+            // Scanned before mtimes were recorded: trust the existing row and start tracking
+            return Some(Ok(ScannedFile::Stat(known.track_id, size, mtime)));
+        }
+        if size == known.size && mtime == known.mtime {
+            return None;
+        }
+
+        Some(
+            Self::extract_metadata(path, options)
+                .map(|(track, tags)| ScannedFile::Changed(path.to_path_buf(), known.track_id, track, tags)),
+        )
+    }
+
     /// Extract metadata from an audio file.
-    /// Returns the track, an optional BPM, and an optional Genre if present in file tags.
-    pub fn extract_metadata(path: &Path) -> Result<(Track, Option<f64>, Option<String>), String> {
+    /// Returns the track plus the BPM, key (converted to Camelot) and genre if present in file tags.
+    /// `options.energy` decides where an energy level is looked for. In lazy hash mode the
+    /// file isn't hashed and file_hash is set to PENDING_HASH.
+    pub fn extract_metadata(path: &Path, options: &ScanOptions) -> Result<(Track, TagValues), String> {
         // Read file with lofty
         let tagged_file = read_from_path(path)
             .map_err(|e| format!("Failed to read file: {}", e))?;
@@ -88,9 +875,12 @@ impl Scanner {
             .ok()
             .map(|m| m.len() as i64);
 
-        // Calculate file hash
-        let file_hash = Self::calculate_file_hash(path)
-            .unwrap_or_else(|_| "unknown".to_string());
+        // Calculate file hash (deferred in lazy mode)
+        let file_hash = match options.hash_mode {
+            HashMode::Full => Self::calculate_file_hash(path)
+                .unwrap_or_else(|_| "unknown".to_string()),
+            HashMode::Lazy => PENDING_HASH.to_string(),
+        };
 
         // Get format
         let file_format = path
@@ -102,13 +892,24 @@ impl Scanner {
         let tag = tagged_file.primary_tag()
             .or_else(|| tagged_file.first_tag());
 
-        let (title, artist, album, album_artist, track_number, year, label, comment, tag_bpm, tag_genre) = if let Some(tag) = tag {
+        let (title, artist, album, album_artist, track_number, year, label, comment, tag_values) = if let Some(tag) = tag {
             // BPM from file tags (ID3 TBPM, etc.) so we match Traktor/Rekordbox when they wrote it
             let bpm_str = tag.get_string(&ItemKey::Bpm)
                 .or_else(|| tag.get_string(&ItemKey::IntegerBpm));
             let bpm = bpm_str.and_then(|s| s.trim().parse::<f64>().ok())
                 .filter(|&b| b >= 40.0 && b <= 300.0);
 
+            // Key from file tags (ID3 TKEY, Vorbis INITIALKEY, etc.), normalized to Camelot.
+            // Files analyzed by Mixed In Key may only have it in the comment.
+            let mixed_in_key = MixedInKeyValues::read(tag);
+            let key = tag.get_string(&ItemKey::InitialKey)
+                .and_then(key_to_camelot)
+                .or_else(|| mixed_in_key.as_ref().and_then(|m| m.key.clone()));
+            let energy = mixed_in_key.as_ref()
+                .and_then(|m| m.energy)
+                .or_else(|| options.energy.extract(tag));
+            let source = if mixed_in_key.is_some() { TagSource::MixedInKey } else { TagSource::Tag };
+
             // Genre from file tags (ID3 TCON, Vorbis GENRE, etc.)
             let genre = tag.genre().as_deref().map(|s| s.to_string());
 
@@ -121,11 +922,55 @@ impl Scanner {
                 tag.year().map(|y| y as i32),
                 tag.get_string(&ItemKey::Label).map(|s| s.to_string()),
                 tag.comment().as_deref().map(|s| s.to_string()),
-                bpm,
-                genre,
+                TagValues { bpm, key, genre, energy, source, ..Default::default() },
             )
         } else {
-            (None, None, None, None, None, None, None, None, None, None)
+            (None, None, None, None, None, None, None, None, TagValues::default())
+        };
+
+        // Ratings and cues prepared in other software: POPM/GEOB frames for ID3v2 files
+        // (read separately, lofty's generic tag drops them), text fields otherwise
+        let (rating, cues) = match read_id3v2(path, tagged_file.file_type()) {
+            Some(id3v2) => id3v2_annotations(&id3v2),
+            None => (
+                tag.and_then(|tag| tag.get_string(&ItemKey::Popularimeter)).and_then(rating_text_stars),
+                tag.and_then(|tag| tag.get_string(&ItemKey::Unknown(serato::MARKERS2_FIELD.to_string())))
+                    .map(serato::parse_markers2_field)
+                    .unwrap_or_default(),
+            ),
+        };
+        let sidecar = if options.read_sidecars {
+            sidecar::read_sidecar(path)
+                .unwrap_or_else(|e| {
+                    eprintln!("[scanner] {}", e);
+                    None
+                })
+                .filter(|sidecar| sidecar.matches_duration(Some(duration_ms as i64)))
+        } else {
+            None
+        };
+        let tag_values = TagValues { rating, cues, sidecar, ..tag_values };
+
+        let (disc_number, total_tracks) = tag
+            .map(|tag| (tag.disk().map(|d| d as i32), tag.track_total().map(|t| t as i32)))
+            .unwrap_or((None, None));
+        // ISRC (ID3 TSRC, Vorbis ISRC) and label catalog number, for radio-play reporting
+        let tag_text = |key: ItemKey| {
+            tag.and_then(|tag| tag.get_string(&key))
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+        };
+        let isrc = tag_text(ItemKey::Isrc).map(|s| s.replace('-', "").to_uppercase());
+        let catalog_number = tag_text(ItemKey::CatalogNumber);
+
+        // No embedded picture: use a cover image from the track's folder
+        let has_embedded_artwork = tagged_file.tags().iter().any(|tag| !tag.pictures().is_empty());
+        let artwork_path = if has_embedded_artwork {
+            None
+        } else {
+            path.parent()
+                .and_then(find_folder_artwork)
+                .map(|p| p.to_string_lossy().to_string())
         };
 
         // Fallback: use filename (without extension) as title if tags are missing
@@ -220,85 +1065,274 @@ impl Scanner {
             play_count: 0,
             rating: 0,
             comment,
-            artwork_path: None,
+            artwork_path,
             genre: None, // Genre will be set after track creation based on tag_genre and source priority
             genre_source: None,
-        }, tag_bpm, tag_genre))
+            energy_level: None, // Stored with its source by save_tag_values
+            color: None,
+            disc_number,
+            total_tracks,
+            isrc,
+            catalog_number,
+        }, tag_values))
+    }
+
+    /// Write BPM, key (Camelot) and genre into the file's tags, creating the file's
+    /// primary tag type if it has none. Fields that are None are left untouched.
+    pub fn write_tags(path: &Path, values: &TagValues) -> Result<(), String> {
+        let mut tagged_file = read_from_path(path)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+
+        if tagged_file.primary_tag_mut().is_none() {
+            let tag_type = tagged_file.primary_tag_type();
+            tagged_file.insert_tag(lofty::tag::Tag::new(tag_type));
+        }
+        let tag = tagged_file
+            .primary_tag_mut()
+            .ok_or("File format doesn't support tags")?;
+
+        if let Some(bpm) = values.bpm {
+            // Integer BPM: ID3 TBPM is defined as a whole number and DJ apps read it that way
+            tag.insert_text(ItemKey::Bpm, format!("{}", bpm.round() as i64));
+        }
+        if let Some(key) = &values.key {
+            tag.insert_text(ItemKey::InitialKey, key.clone());
+        }
+        if let Some(genre) = &values.genre {
+            tag.set_genre(genre.clone());
+        }
+
+        tag.save_to_path(path, lofty::config::WriteOptions::default())
+            .map_err(|e| format!("Failed to write tags: {}", e))
+    }
+
+    /// Store tag-derived BPM, key, energy, genre, rating and cues for a new or re-read track.
+    /// BPM/key/energy are saved with the tags' source ('tag' or 'mixedinkey'); BPM/key are
+    /// subject to the analysis priority setting, energy never replaces a user-set level,
+    /// and genre never overwrites a user-assigned genre (priority: user > tag > ai).
+    /// A rating only fills an unrated track or updates one that came from tags; Serato
+    /// cues are only stored if the track has no cues from elsewhere.
+    /// A sidecar's analysis goes in first, as analyzed values, so tag values then replace
+    /// it only if tags are preferred; its cues fill a track without cues.
+    pub fn save_tag_values(db: &Database, track_id: i64, tags: &TagValues) {
+        if let Some(sidecar) = &tags.sidecar {
+            let _ = db.fill_portable_analysis(track_id, &sidecar.analysis());
+            let _ = db.fill_cue_points(track_id, &sidecar.cue_points(track_id));
+        }
+        let source = tags.source.as_str();
+        if let Some(bpm) = tags.bpm {
+            let _ = db.save_tag_bpm(track_id, bpm, source);
+        }
+        if let Some(key) = &tags.key {
+            let _ = db.save_tag_key(track_id, key, source);
+        }
+        if let Some(energy) = tags.energy {
+            let _ = db.save_tag_energy(track_id, energy, source);
+        }
+        if let Some(genre) = &tags.genre {
+            let _ = db.save_track_genre(track_id, genre, "tag");
+        }
+        if let Some(rating) = tags.rating {
+            let _ = db.save_tag_rating(track_id, rating, TAG_SOURCE);
+        }
+        if !tags.cues.is_empty() {
+            let cues: Vec<_> = tags.cues.iter().flat_map(|c| c.to_cue_points(track_id)).collect();
+            let _ = db.save_tag_cues(track_id, &cues, SERATO_SOURCE);
+        }
     }
 
     /// Import a single file into the database.
-    /// If the file has BPM in its tags (e.g. from Traktor), it is saved to track_analysis so RecoDeck matches.
+    /// If the file has BPM or key in its tags (e.g. from Traktor), they are saved to track_analysis so RecoDeck matches.
     /// If the file has Genre in its tags, it is saved with source='tag'.
-    /// Skips files whose content hash already exists (prevents duplicate content at different paths).
+    /// Skips files whose content hash already exists (prevents duplicate content at different paths),
+    /// unless the existing track's file is gone — then the track is moved to the new path.
     pub fn import_file(db: &Database, path: &Path) -> Result<i64, String> {
-        let (track, tag_bpm, tag_genre) = Self::extract_metadata(path)?;
+        let (mut track, tag_values) = Self::extract_metadata(path, &ScanOptions::from_settings(db))?;
+        match Self::insert_track(db, path, &mut track, &tag_values)? {
+            Inserted::New(id) | Inserted::Moved(id) => Ok(id),
+        }
+    }
 
-        // Skip if a track with the same content hash already exists (different path, same file)
-        if track.file_hash != "unknown" {
-            if db.track_exists_with_hash(&track.file_hash)
-                .map_err(|e| format!("Database error: {}", e))? {
-                return Err("DUPLICATE_HASH".to_string());
+    /// Find a library track whose file is missing from its recorded path but has the same
+    /// content as the new file at `path`, i.e. the file was moved. For lazily hashed files
+    /// the new file is only hashed if a missing track of the same size exists.
+    fn find_moved_track(db: &Database, path: &Path, track: &mut Track) -> Result<Option<i64>, String> {
+        let db_err = |e: rusqlite::Error| format!("Database error: {}", e);
+        let is_missing = |file_path: &str| !Path::new(file_path).exists();
+
+        if track.file_hash == PENDING_HASH {
+            let size = match track.file_size {
+                Some(size) => size,
+                None => return Ok(None),
+            };
+            let candidates: Vec<(i64, String)> = db.find_hashed_tracks_by_size(size)
+                .map_err(db_err)?
+                .into_iter()
+                .filter(|(_, file_path, _)| is_missing(file_path))
+                .map(|(id, _, hash)| (id, hash))
+                .collect();
+            if candidates.is_empty() {
+                return Ok(None);
             }
+            // Worth hashing now; keep the hash so it isn't computed again later
+            track.file_hash = Self::calculate_file_hash(path)
+                .unwrap_or_else(|_| "unknown".to_string());
+            return Ok(candidates
+                .into_iter()
+                .find(|(_, hash)| *hash == track.file_hash)
+                .map(|(id, _)| id));
+        }
+
+        if track.file_hash == "unknown" {
+            return Ok(None);
+        }
+        Ok(db.find_tracks_by_hash(&track.file_hash)
+            .map_err(db_err)?
+            .into_iter()
+            .find(|(_, file_path)| is_missing(file_path))
+            .map(|(id, _)| id))
+    }
+
+    /// Insert an extracted track plus its tag values and file stat. If the file is a
+    /// moved library track, that track's path is updated instead, so its analysis,
+    /// cues, playlists and ratings are kept. Errors with "DUPLICATE_HASH" if a track
+    /// with the same content hash already exists at another (existing) path.
+    fn insert_track(db: &Database, path: &Path, track: &mut Track, tag_values: &TagValues) -> Result<Inserted, String> {
+        let (size, mtime) = Self::file_stat(path);
+
+        if let Some(id) = Self::find_moved_track(db, path, track)? {
+            db.update_track_path(id, &track.file_path)
+                .and_then(|_| db.set_track_file_stat(id, size.or(track.file_size), mtime))
+                .map_err(|e| format!("Database error: {}", e))?;
+            eprintln!("[scanner] Track {} moved to {}", id, track.file_path);
+            return Ok(Inserted::Moved(id));
+        }
+
+        // Skip if a track with the same content hash already exists (different path, same file).
+        // Not possible for lazily hashed files; duplicate cleanup catches those later.
+        if track.file_hash != "unknown"
+            && track.file_hash != PENDING_HASH
+            && db.track_exists_with_hash(&track.file_hash)
+                .map_err(|e| format!("Database error: {}", e))?
+        {
+            return Err("DUPLICATE_HASH".to_string());
         }
 
-        let id = db.create_track(&track)
+        let id = db.create_track(track)
             .map_err(|e| format!("Database error: {}", e))?;
 
-        // If file has BPM in tags (e.g. Traktor wrote TBPM), store it so we match when user checks in Traktor
-        if let Some(bpm) = tag_bpm {
-            let _ = db.save_bpm_analysis(id, bpm, 0.99);
+        db.set_track_file_stat(id, size.or(track.file_size), mtime)
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        // Analyzed before under another track (deleted, then re-imported)
+        if track.file_hash != "unknown" && track.file_hash != PENDING_HASH
+            && db.restore_cached_analysis(id, &track.file_hash)
+                .map_err(|e| format!("Database error: {}", e))?
+        {
+            eprintln!("[scanner] Restored cached analysis for {}", track.file_path);
         }
 
-        // If file has Genre in tags (ID3 TCON, Vorbis GENRE, etc.), save it with source='tag'
-        // This will NOT overwrite any user-assigned genre (priority: user > tag > ai)
-        if let Some(genre) = tag_genre {
-            let _ = db.save_track_genre(id, &genre, "tag");
+        if let Some(purchase) = guess_purchase(path) {
+            db.fill_track_purchase(id, &purchase)
+                .map_err(|e| format!("Database error: {}", e))?;
         }
 
-        Ok(id)
+        // If file has BPM/key in tags (e.g. Traktor wrote TBPM/TKEY), store them so we match
+        // when the user checks in Traktor. Genre (TCON etc.) is saved with source='tag'.
+        Self::save_tag_values(db, id, tag_values);
+
+        Ok(Inserted::New(id))
     }
 
-    /// Import all files from a directory
+    /// Write a batch of scanned files in a single transaction, updating `result`.
+    /// Duplicates (same path or content hash) count as skipped. If `marker` is given,
+    /// its progress is committed together with the batch.
+    pub fn write_batch(
+        db: &Database,
+        batch: impl IntoIterator<Item = ScannedFile>,
+        result: &mut ScanResult,
+        marker: Option<(&str, usize)>,
+    ) -> rusqlite::Result<()> {
+        let tx = db.begin_write()?;
+        for scanned in batch {
+            match scanned {
+                ScannedFile::New(file_path, mut track, tag_values) => {
+                    match Self::insert_track(db, &file_path, &mut track, &tag_values) {
+                        Ok(Inserted::New(id)) => {
+                            result.imported += 1;
+                            result.new_track_ids.push(id);
+                        }
+                        Ok(Inserted::Moved(_)) => result.moved += 1,
+                        Err(e) => {
+                            // Check if it's a duplicate (unique constraint violation or same content hash)
+                            if e.contains("UNIQUE constraint") || e.contains("DUPLICATE_HASH") {
+                                result.skipped += 1;
+                            } else {
+                                result.errors.push(ScanError { file_path, error: e });
+                            }
+                        }
+                    }
+                }
+                ScannedFile::Changed(file_path, track_id, track, tag_values) => {
+                    let (size, mtime) = Self::file_stat(&file_path);
+                    db.update_track_file_info(track_id, &track)?;
+                    db.set_track_file_stat(track_id, size, mtime)?;
+                    // e.g. Mixed In Key analyzed the file since the last scan
+                    Self::save_tag_values(db, track_id, &tag_values);
+                    result.updated += 1;
+                }
+                ScannedFile::Stat(track_id, size, mtime) => {
+                    db.set_track_file_stat(track_id, size, mtime)?;
+                    result.skipped += 1;
+                }
+            }
+        }
+        if let Some((job, processed)) = marker {
+            db.update_batch_marker(job, processed as i64)?;
+        }
+        tx.commit()
+    }
+
+    /// Import all files from a directory, committing every SCAN_BATCH_SIZE files.
+    /// Known files are only re-read if their size or mtime changed.
     pub fn import_directory(db: &Database, path: &Path) -> ScanResult {
-        let files = Self::scan_directory(path);
-        let total_files = files.len();
-        let mut imported = 0;
-        let mut skipped = 0;
-        let mut errors = Vec::new();
+        let options = ScanOptions::from_settings(db);
+        let DirectoryScan { files, symlinks_skipped } = Self::scan_directory_with(path, options.symlink_mode(path));
+        let mut result = ScanResult {
+            total_files: files.len(),
+            imported: 0,
+            updated: 0,
+            moved: 0,
+            skipped: 0,
+            errors: Vec::new(),
+            new_track_ids: Vec::new(),
+            symlinks_skipped,
+        };
 
-        // Load all known paths in one query for fast lookups
-        let known_paths = db.get_all_file_paths().unwrap_or_default();
+        // Load all known paths + stats in one query for fast lookups
+        let known_files = db.get_file_stats().unwrap_or_default();
+        let mut batch = Vec::with_capacity(SCAN_BATCH_SIZE);
 
         for file_path in files {
-            // Fast path: skip files already in DB by path (avoids expensive hash + metadata)
             let path_str = file_path.to_string_lossy().to_string();
-            if known_paths.contains(&path_str) {
-                skipped += 1;
-                continue;
+            match Self::scan_file(&file_path, known_files.get(&path_str), &options) {
+                Some(Ok(scanned)) => batch.push(scanned),
+                Some(Err(e)) => result.errors.push(ScanError { file_path, error: e }),
+                None => result.skipped += 1,
             }
 
-            match Self::import_file(db, &file_path) {
-                Ok(_) => imported += 1,
-                Err(e) => {
-                    // Check if it's a duplicate (unique constraint violation or same content hash)
-                    if e.contains("UNIQUE constraint") || e.contains("DUPLICATE_HASH") {
-                        skipped += 1;
-                    } else {
-                        errors.push(ScanError {
-                            file_path: file_path.clone(),
-                            error: e,
-                        });
-                    }
+            if batch.len() >= SCAN_BATCH_SIZE {
+                if let Err(e) = Self::write_batch(db, batch.drain(..), &mut result, None) {
+                    eprintln!("[scanner] Failed to commit batch: {}", e);
                 }
             }
         }
 
-        ScanResult {
-            total_files,
-            imported,
-            skipped,
-            errors,
+        if let Err(e) = Self::write_batch(db, batch.drain(..), &mut result, None) {
+            eprintln!("[scanner] Failed to commit batch: {}", e);
         }
+
+        result
     }
 }
 
@@ -308,6 +1342,7 @@ mod tests {
     use std::fs::{self, File};
     use std::io::Write;
     use tempfile::TempDir;
+    use crate::db::create_test_track;
 
     fn create_temp_audio_files() -> TempDir {
         let temp_dir = TempDir::new().unwrap();
@@ -352,6 +1387,46 @@ mod tests {
         assert!(!extensions.contains(&"txt".to_string()));
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_directory_symlinks() {
+        use std::os::unix::fs::symlink;
+        let temp_dir = create_temp_audio_files();
+        let root = temp_dir.path();
+        let outside = TempDir::new().unwrap();
+        File::create(outside.path().join("linked.mp3")).unwrap();
+
+        symlink(root, root.join("subdir/loop")).unwrap();
+        symlink(root.join("subdir"), root.join("again")).unwrap();
+        symlink(root.join("track1.mp3"), root.join("alias.mp3")).unwrap();
+        symlink(outside.path(), root.join("outside")).unwrap();
+
+        // Following: the outside folder comes in, second paths and the loop don't
+        let scan = Scanner::scan_directory_with(root, SymlinkMode::Follow);
+        assert_eq!(scan.files.len(), 4);
+        assert_eq!(scan.files.iter().filter(|f| f.ends_with("track3.wav")).count(), 1);
+        assert!(scan.files.iter().any(|f| f.ends_with("outside/linked.mp3")));
+        assert_eq!(scan.symlinks_skipped.len(), 3);
+
+        let scan = Scanner::scan_directory_with(root, SymlinkMode::Skip);
+        assert_eq!(scan.files.len(), 3);
+        let mut skipped: Vec<_> = scan.symlinks_skipped.iter().map(|p| p.strip_prefix(root).unwrap().to_path_buf()).collect();
+        skipped.sort();
+        let expected: Vec<PathBuf> = ["again", "alias.mp3", "outside", "subdir/loop"].iter().map(PathBuf::from).collect();
+        assert_eq!(skipped, expected);
+
+        let options = ScanOptions {
+            symlink_folders: HashMap::from([
+                (root.to_string_lossy().to_string(), SymlinkMode::Skip),
+                (root.join("subdir").to_string_lossy().to_string(), SymlinkMode::Follow),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(options.symlink_mode(root), SymlinkMode::Skip);
+        assert_eq!(options.symlink_mode(&root.join("subdir/deeper")), SymlinkMode::Follow);
+        assert_eq!(options.symlink_mode(outside.path()), SymlinkMode::Follow);
+    }
+
     #[test]
     fn test_scan_empty_directory() {
         let temp_dir = TempDir::new().unwrap();
@@ -359,6 +1434,45 @@ mod tests {
         assert_eq!(audio_files.len(), 0);
     }
 
+    #[test]
+    fn test_find_folder_artwork() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_eq!(find_folder_artwork(temp_dir.path()), None);
+
+        fs::write(temp_dir.path().join("notes.jpg"), b"x").unwrap();
+        fs::write(temp_dir.path().join("Front.PNG"), b"x").unwrap();
+        assert_eq!(find_folder_artwork(temp_dir.path()), Some(temp_dir.path().join("Front.PNG")));
+
+        fs::write(temp_dir.path().join("cover.jpg"), b"x").unwrap();
+        assert_eq!(find_folder_artwork(temp_dir.path()), Some(temp_dir.path().join("cover.jpg")));
+    }
+
+    #[test]
+    fn test_guess_purchase() {
+        let guess = guess_purchase(Path::new("/Music/Beatport/beatport_tracks_2024_03_12/Order 9876543/a.mp3")).unwrap();
+        assert_eq!(guess.source.as_deref(), Some("Beatport"));
+        assert_eq!(guess.date.as_deref(), Some("2024-03-12"));
+        assert_eq!(guess.order_id.as_deref(), Some("9876543"));
+
+        let guess = guess_purchase(Path::new("/Music/Promos/20231301 Label/b.flac")).unwrap();
+        assert_eq!(guess.source.as_deref(), Some("Promo"));
+        assert_eq!(guess.date, None);
+
+        assert!(guess_purchase(Path::new("/Music/Junction/House/c.mp3")).is_none());
+        assert!(guess_purchase(Path::new("/Music/promotional/c.mp3")).is_none());
+    }
+
+    #[test]
+    fn test_collapse_roots() {
+        let folders: Vec<String> = ["/Music/House/", "/Music", "/Music2", "/Music", "/Other/Sets"]
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+        assert_eq!(collapse_roots(&folders), vec!["/Music", "/Music2", "/Other/Sets"]);
+        assert_eq!(normalize_root(" /Music// "), "/Music");
+        assert_eq!(normalize_root("/"), "/");
+    }
+
     #[test]
     fn test_calculate_file_hash() {
         let temp_dir = TempDir::new().unwrap();
@@ -401,6 +1515,14 @@ mod tests {
         assert!(!SUPPORTED_EXTENSIONS.contains(&"txt"));
     }
 
+    #[test]
+    fn test_is_audio_file() {
+        assert!(Scanner::is_audio_file(Path::new("/music/track.MP3")));
+        assert!(Scanner::is_audio_file(Path::new("/music/track.flac")));
+        assert!(!Scanner::is_audio_file(Path::new("/music/cover.jpg")));
+        assert!(!Scanner::is_audio_file(Path::new("/music/no_extension")));
+    }
+
     #[test]
     fn test_import_directory_integration() {
         // Create in-memory database
@@ -422,6 +1544,7 @@ mod tests {
         // Verify database is still functional after errors
         let count = db.count_tracks().unwrap();
         assert_eq!(count, result.imported as i64);
+        assert_eq!(result.new_track_ids.len(), result.imported);
     }
 
     #[test]
@@ -435,7 +1558,167 @@ mod tests {
         let result = Scanner::import_directory(&db, temp_dir.path());
         assert_eq!(result.total_files, 0);
         assert_eq!(result.imported, 0);
+        assert_eq!(result.updated, 0);
         assert_eq!(result.skipped, 0);
         assert_eq!(result.errors.len(), 0);
     }
+
+    #[test]
+    fn test_scan_file_skips_unchanged_known_files() {
+        let temp_dir = create_temp_audio_files();
+        let path = temp_dir.path().join("track1.mp3");
+        let (size, mtime) = Scanner::file_stat(&path);
+        let options = ScanOptions::default();
+
+        // Same size + mtime: skipped without reading (the dummy file would fail to parse)
+        let known = FileStat { track_id: 7, size, mtime };
+        assert!(Scanner::scan_file(&path, Some(&known), &options).is_none());
+
+        // No mtime recorded yet: just start tracking it
+        let legacy = FileStat { track_id: 7, size, mtime: None };
+        assert!(matches!(
+            Scanner::scan_file(&path, Some(&legacy), &options),
+            Some(Ok(ScannedFile::Stat(7, _, _)))
+        ));
+
+        // Changed size: re-read (fails here because the file isn't real audio)
+        let changed = FileStat { track_id: 7, size: Some(1), mtime };
+        assert!(matches!(Scanner::scan_file(&path, Some(&changed), &options), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_file_freshness() {
+        let temp_dir = create_temp_audio_files();
+        let path = temp_dir.path().join("track1.mp3");
+        let (size, mtime) = Scanner::file_stat(&path);
+        let hash = Scanner::calculate_file_hash(&path).unwrap();
+        let known = FileStat { track_id: 1, size, mtime };
+
+        assert_eq!(Scanner::file_freshness(&path, &known, &hash), Freshness::Fresh);
+        // Different mtime, same content (e.g. touched): still fresh
+        let touched = FileStat { track_id: 1, size, mtime: mtime.map(|m| m - 60) };
+        assert_eq!(Scanner::file_freshness(&path, &touched, &hash), Freshness::Fresh);
+        // Without a hash the differing mtime decides
+        assert_eq!(Scanner::file_freshness(&path, &touched, PENDING_HASH), Freshness::Changed);
+
+        // Edited in another program
+        fs::write(&path, b"retagged elsewhere").unwrap();
+        assert_eq!(Scanner::file_freshness(&path, &known, &hash), Freshness::Changed);
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Scanner::file_freshness(&path, &known, &hash), Freshness::Missing);
+    }
+
+    fn test_track(file_path: &str, file_hash: &str) -> Track {
+        Track {
+            file_path: file_path.to_string(),
+            file_hash: file_hash.to_string(),
+            rating: 4,
+            file_size: Some(13),
+            ..create_test_track()
+        }
+    }
+
+    #[test]
+    fn test_moved_file_updates_existing_track() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let temp_dir = create_temp_audio_files();
+        let new_path = temp_dir.path().join("track1.mp3");
+        let new_path_str = new_path.to_string_lossy().to_string();
+        let hash = Scanner::calculate_file_hash(&new_path).unwrap();
+
+        // Library row whose file no longer exists at its old path
+        let old_id = db.create_track(&test_track("/gone/track1.mp3", &hash)).unwrap();
+
+        let mut result = ScanResult { total_files: 1, imported: 0, updated: 0, moved: 0, skipped: 0, errors: Vec::new(), new_track_ids: Vec::new(), symlinks_skipped: Vec::new() };
+        let scanned = ScannedFile::New(new_path.clone(), test_track(&new_path_str, &hash), TagValues::default());
+        Scanner::write_batch(&db, vec![scanned], &mut result, None).unwrap();
+
+        assert_eq!(result.moved, 1);
+        assert_eq!(result.imported, 0);
+        // Moved tracks keep their analysis, so they aren't queued for auto-analysis
+        assert!(result.new_track_ids.is_empty());
+        assert_eq!(db.count_tracks().unwrap(), 1);
+        let track = db.get_track(old_id).unwrap();
+        assert_eq!(track.file_path, new_path_str);
+        assert_eq!(track.rating, 4);
+
+        // Lazily hashed scan finds the same move through the file size
+        let lazy_id = db.create_track(&test_track("/gone/track2.flac", &hash)).unwrap();
+        let other_path = temp_dir.path().join("track2.flac");
+        let other_str = other_path.to_string_lossy().to_string();
+        let scanned = ScannedFile::New(other_path, test_track(&other_str, PENDING_HASH), TagValues::default());
+        Scanner::write_batch(&db, vec![scanned], &mut result, None).unwrap();
+        assert_eq!(result.moved, 2);
+        assert_eq!(db.get_track(lazy_id).unwrap().file_path, other_str);
+    }
+
+    #[test]
+    fn test_energy_extractor_parse() {
+        let extractor = EnergyExtractor::default();
+        assert_eq!(extractor.parse("Energy 7"), Some(7));
+        assert_eq!(extractor.parse("8A - Energy 6"), Some(6));
+        assert_eq!(extractor.parse("energy:10 peak time"), Some(10));
+        assert_eq!(extractor.parse("LEVEL-3"), Some(3));
+        assert_eq!(extractor.parse(" 5 "), Some(5));
+        assert_eq!(extractor.parse("synergy 7"), None);
+        assert_eq!(extractor.parse("Energy 11"), None);
+        assert_eq!(extractor.parse("0"), None);
+        assert_eq!(extractor.parse("Purchased on Beatport"), None);
+
+        let custom = EnergyExtractor {
+            fields: vec!["comment".to_string()],
+            keywords: vec!["E".to_string()],
+        };
+        assert_eq!(custom.parse("E8 banger"), Some(8));
+    }
+
+    #[test]
+    fn test_tag_rating_stars() {
+        assert_eq!(popm_stars(0), None);
+        assert_eq!(popm_stars(1), Some(1));
+        assert_eq!(popm_stars(128), Some(3));
+        assert_eq!(popm_stars(196), Some(4));
+        assert_eq!(popm_stars(255), Some(5));
+
+        assert_eq!(rating_text_stars("0.8"), Some(4));
+        assert_eq!(rating_text_stars("3"), Some(3));
+        assert_eq!(rating_text_stars("60"), Some(3));
+        assert_eq!(rating_text_stars("255"), Some(5));
+        assert_eq!(rating_text_stars("0"), None);
+        assert_eq!(rating_text_stars("five"), None);
+    }
+
+    #[test]
+    fn test_mixed_in_key_comment() {
+        let parse = MixedInKeyValues::parse_comment;
+        let values = |key: &str, energy| Some(MixedInKeyValues { key: Some(key.to_string()), energy });
+        assert_eq!(parse("8A - Energy 7"), values("8A", Some(7)));
+        assert_eq!(parse("Energy 6 - 11B"), values("11B", Some(6)));
+        assert_eq!(parse("08A - 126 - Energy 5"), values("8A", Some(5)));
+        assert_eq!(parse("4A/5A"), values("4A", None));
+        assert_eq!(parse("Energy 7"), None);
+        assert_eq!(parse("8A - Energy 12"), None);
+        assert_eq!(parse("Remix - 8A vinyl rip"), None);
+        assert_eq!(parse("Purchased on Beatport"), None);
+    }
+
+    #[test]
+    fn test_tag_energy_keeps_user_level() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let id = db.create_track(&test_track("/music/a.mp3", "hash_a")).unwrap();
+
+        let mixed_in_key = TagValues { energy: Some(6), source: TagSource::MixedInKey, ..Default::default() };
+        Scanner::save_tag_values(&db, id, &mixed_in_key);
+        assert_eq!(db.get_track(id).unwrap().energy_level, Some(6));
+
+        // A rescan picks up newer tag values, but never replaces a level set by hand
+        Scanner::save_tag_values(&db, id, &TagValues { energy: Some(7), ..mixed_in_key.clone() });
+        assert_eq!(db.get_track(id).unwrap().energy_level, Some(7));
+        db.set_track_energy(id, Some(9)).unwrap();
+        Scanner::save_tag_values(&db, id, &mixed_in_key);
+        assert_eq!(db.get_track(id).unwrap().energy_level, Some(9));
+    }
 }
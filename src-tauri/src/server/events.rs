@@ -0,0 +1,125 @@
+// Server-sent events for companion clients (Bearer-token auth like the rest of the API)
+// GET /events — text/event-stream of CompanionEvents, so the PWA can refresh a track's
+// BPM/key/waveform when the desktop's analysis worker gets to it instead of polling.
+// Events are relayed from the desktop event bus (see commands::server); a client that
+// falls behind gets a "lagged" event and should reload what it shows.
+
+use axum::{
+    Router,
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+    routing::get,
+};
+use futures::stream::{self, Stream};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+use super::CompanionServerState;
+
+/// Events buffered per client before it counts as lagged
+pub const EVENT_BUFFER: usize = 64;
+
+/// An event pushed to companion clients; the SSE event name is the variant in snake_case
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompanionEvent {
+    /// The analysis queue finished a track (BPM, key and waveform may have changed)
+    TrackAnalyzed { track_id: i64, error: Option<String> },
+    /// A track's waveform was generated (or failed)
+    WaveformReady { track_id: i64, error: Option<String> },
+    /// The server is shutting down; the stream ends after this
+    ServerStopping,
+}
+
+impl CompanionEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            CompanionEvent::TrackAnalyzed { .. } => "track_analyzed",
+            CompanionEvent::WaveformReady { .. } => "waveform_ready",
+            CompanionEvent::ServerStopping => "server_stopping",
+        }
+    }
+
+    /// SSE frame: the event name, with the fields (if any) as JSON data
+    fn to_sse(&self) -> Event {
+        let data = match self {
+            CompanionEvent::TrackAnalyzed { track_id, error }
+            | CompanionEvent::WaveformReady { track_id, error } => {
+                serde_json::json!({ "track_id": track_id, "error": error })
+            }
+            CompanionEvent::ServerStopping => serde_json::json!({}),
+        };
+        Event::default().event(self.name()).data(data.to_string())
+    }
+}
+
+/// Event routes, relative to the API version prefix (like routes::api_routes)
+pub fn event_routes() -> Router<Arc<CompanionServerState>> {
+    Router::new().route("/events", get(stream_events))
+}
+
+async fn stream_events(
+    State(state): State<Arc<CompanionServerState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    Sse::new(event_stream(state.events.subscribe())).keep_alive(KeepAlive::default())
+}
+
+/// SSE frames for everything published after `receiver` subscribed, ending after
+/// ServerStopping (so graceful shutdown isn't held up by open event streams)
+fn event_stream(receiver: broadcast::Receiver<CompanionEvent>) -> impl Stream<Item = Result<Event, Infallible>> {
+    stream::unfold(Some(receiver), |receiver| async move {
+        let mut receiver = receiver?;
+        let event = match receiver.recv().await {
+            Ok(CompanionEvent::ServerStopping) => {
+                return Some((Ok(CompanionEvent::ServerStopping.to_sse()), None));
+            }
+            Ok(event) => event.to_sse(),
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                Event::default().event("lagged").data(serde_json::json!({ "missed": missed }).to_string())
+            }
+            Err(broadcast::error::RecvError::Closed) => return None,
+        };
+        Some((Ok(event), Some(receiver)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_event_stream_ends_after_server_stopping() {
+        let (sender, receiver) = broadcast::channel(EVENT_BUFFER);
+        let events = event_stream(receiver);
+        sender.send(CompanionEvent::TrackAnalyzed { track_id: 7, error: None }).unwrap();
+        sender.send(CompanionEvent::WaveformReady { track_id: 8, error: Some("Unreadable".into()) }).unwrap();
+        sender.send(CompanionEvent::ServerStopping).unwrap();
+        // Published after the stop: never delivered
+        sender.send(CompanionEvent::TrackAnalyzed { track_id: 9, error: None }).unwrap();
+
+        let frames: Vec<Event> = events.map(|frame| frame.unwrap()).collect().await;
+        assert_eq!(frames.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_lagged_client_keeps_receiving() {
+        let (sender, receiver) = broadcast::channel(2);
+        let events = event_stream(receiver);
+        for track_id in 0..5 {
+            sender.send(CompanionEvent::TrackAnalyzed { track_id, error: None }).unwrap();
+        }
+        drop(sender);
+
+        // "lagged", then the two events still buffered, then the end (sender gone)
+        let frames: Vec<Event> = events.map(|frame| frame.unwrap()).collect().await;
+        assert_eq!(frames.len(), 3);
+    }
+
+    #[test]
+    fn test_event_names() {
+        assert_eq!(CompanionEvent::TrackAnalyzed { track_id: 1, error: None }.name(), "track_analyzed");
+        assert_eq!(CompanionEvent::WaveformReady { track_id: 1, error: None }.name(), "waveform_ready");
+        assert_eq!(CompanionEvent::ServerStopping.name(), "server_stopping");
+    }
+}
@@ -0,0 +1,184 @@
+// In-memory request metrics for the mobile companion server
+// - Request/error counters and bytes served (from Content-Length)
+// - Per-endpoint latency keyed by route pattern (/api/tracks/{id}), never the raw URL
+// - One log line per request: method, path, status, latency (no query string: it can hold tickets)
+// Reset whenever the server restarts.
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::CompanionServerState;
+
+/// Latency totals for one endpoint
+#[derive(Debug, Default, Clone)]
+struct EndpointStats {
+    requests: u64,
+    errors: u64,
+    total: Duration,
+    max: Duration,
+}
+
+/// Counters shared by all requests
+pub struct ServerMetrics {
+    started_at: Instant,
+    requests: AtomicU64,
+    errors: AtomicU64,
+    bytes_served: AtomicU64,
+    endpoints: Mutex<HashMap<String, EndpointStats>>,
+}
+
+/// Latency summary for one endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointMetrics {
+    pub endpoint: String,
+    pub requests: u64,
+    pub errors: u64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Point-in-time copy of the server metrics
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsSnapshot {
+    pub uptime_secs: u64,
+    pub requests: u64,
+    /// Responses with a 4xx/5xx status
+    pub errors: u64,
+    pub active_streams: usize,
+    pub bytes_served: u64,
+    /// Busiest endpoints first
+    pub endpoints: Vec<EndpointMetrics>,
+}
+
+impl Default for ServerMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServerMetrics {
+    pub fn new() -> Self {
+        ServerMetrics {
+            started_at: Instant::now(),
+            requests: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            bytes_served: AtomicU64::new(0),
+            endpoints: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count one finished request
+    pub fn record(&self, endpoint: &str, status: u16, latency: Duration, bytes: u64) {
+        let is_error = status >= 400;
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if is_error {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        self.bytes_served.fetch_add(bytes, Ordering::Relaxed);
+
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let stats = endpoints.entry(endpoint.to_string()).or_default();
+        stats.requests += 1;
+        if is_error {
+            stats.errors += 1;
+        }
+        stats.total += latency;
+        stats.max = stats.max.max(latency);
+    }
+
+    pub fn snapshot(&self, active_streams: usize) -> MetricsSnapshot {
+        let mut endpoints: Vec<EndpointMetrics> = self
+            .endpoints
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(endpoint, stats)| EndpointMetrics {
+                endpoint: endpoint.clone(),
+                requests: stats.requests,
+                errors: stats.errors,
+                avg_ms: stats.total.as_secs_f64() * 1000.0 / stats.requests.max(1) as f64,
+                max_ms: stats.max.as_secs_f64() * 1000.0,
+            })
+            .collect();
+        endpoints.sort_by(|a, b| b.requests.cmp(&a.requests).then_with(|| a.endpoint.cmp(&b.endpoint)));
+
+        MetricsSnapshot {
+            uptime_secs: self.started_at.elapsed().as_secs(),
+            requests: self.requests.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            active_streams,
+            bytes_served: self.bytes_served.load(Ordering::Relaxed),
+            endpoints,
+        }
+    }
+}
+
+/// Metrics + logging middleware - outermost layer, so rejected requests
+/// (auth, rate limit) are counted too. Unrouted paths (PWA assets) share one bucket.
+pub async fn metrics_middleware(
+    state: State<Arc<CompanionServerState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let endpoint = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "static".to_string());
+
+    let response = next.run(request).await;
+
+    let latency = started.elapsed();
+    let status = response.status().as_u16();
+    let bytes = response
+        .headers()
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(0);
+    state.metrics.record(&endpoint, status, latency, bytes);
+
+    eprintln!(
+        "[companion] {} {} {} {}ms",
+        method,
+        path,
+        status,
+        latency.as_millis()
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_snapshot() {
+        let metrics = ServerMetrics::new();
+        metrics.record("/api/tracks", 200, Duration::from_millis(10), 500);
+        metrics.record("/api/tracks", 200, Duration::from_millis(30), 700);
+        metrics.record("/stream/{track_id}", 401, Duration::from_millis(2), 0);
+
+        let snapshot = metrics.snapshot(1);
+        assert_eq!(snapshot.requests, 3);
+        assert_eq!(snapshot.errors, 1);
+        assert_eq!(snapshot.bytes_served, 1200);
+        assert_eq!(snapshot.active_streams, 1);
+        assert_eq!(snapshot.endpoints[0].endpoint, "/api/tracks");
+        assert_eq!(snapshot.endpoints[0].requests, 2);
+        assert!((snapshot.endpoints[0].avg_ms - 20.0).abs() < 0.01);
+        assert!((snapshot.endpoints[0].max_ms - 30.0).abs() < 0.01);
+        assert_eq!(snapshot.endpoints[1].errors, 1);
+    }
+}
@@ -1,12 +1,16 @@
 // Mobile companion server - Axum HTTP server for LAN streaming
 // Serves REST API + audio streaming to the mobile PWA over WiFi
 
+pub mod error;
+pub mod events;
+pub mod metrics;
 pub mod routes;
 pub mod streaming;
+pub mod sync;
 
 use axum::{
     Router,
-    extract::{Extension, Request},
+    extract::{ConnectInfo, Extension, Request},
     http::{HeaderValue, Method, StatusCode},
     middleware::{self, Next},
     response::{Html, IntoResponse, Response},
@@ -15,28 +19,70 @@ use axum::{
 use rand::Rng;
 use rand::thread_rng;
 use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use tokio::sync::oneshot;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, oneshot};
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::CorsLayer;
 use tower_http::services::{ServeDir, ServeFile};
 
+use crate::commands::queue::AuditionQueue;
+use error::ApiError;
+use events::CompanionEvent;
 use crate::db::Database;
 
+/// Current REST API version (routes under /api/v1; plain /api is kept as an alias for older PWAs)
+pub const API_VERSION: u32 = 1;
+/// Max requests a single client IP may make per rate-limit window
+const RATE_LIMIT_MAX_REQUESTS: u32 = 300;
+/// Length of the rate-limit window
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+/// Stream tickets requested by the PWA: enough for Range requests during playback
+pub const STREAM_TICKET_TTL: Duration = Duration::from_secs(600);
+/// Stream tickets embedded in an m3u8 playlist must outlive the whole playlist
+pub const PLAYLIST_STREAM_TICKET_TTL: Duration = Duration::from_secs(12 * 60 * 60);
+/// Playlist tickets are handed to another device (Sonos, VLC) that fetches the m3u8 once
+pub const PLAYLIST_TICKET_TTL: Duration = Duration::from_secs(60 * 60);
+/// Pairing codes shown in the QR must be scanned within this time
+pub const PAIRING_CODE_TTL: Duration = Duration::from_secs(5 * 60);
+/// Pairing code alphabet: no 0/O or 1/I/L, so a code can also be typed
+const PAIRING_CODE_ALPHABET: &[u8] = b"23456789ABCDEFGHJKMNPQRSTUVWXYZ";
+const PAIRING_CODE_LEN: usize = 8;
+/// Non-GET endpoints that don't change anything, so browse mode allows them
+const READ_ONLY_ALLOWED: &[&str] = &["/api/pair", "/api/stream-ticket", "/api/playlist-ticket"];
+
 /// A short-lived, single-use ticket for audio streaming.
 /// Avoids putting the main auth token in audio element URLs.
+/// Bound to the IP that requested it so a sniffed ticket is useless elsewhere.
 #[derive(Debug, Clone)]
 pub struct StreamTicket {
     pub track_id: i64,
-    pub created_at: std::time::Instant,
+    pub client_ip: IpAddr,
+    pub created_at: Instant,
+    pub ttl: Duration,
 }
 
 impl StreamTicket {
-    /// Ticket valid for 10 minutes — enough for Range requests during playback
     pub fn is_expired(&self) -> bool {
-        self.created_at.elapsed().as_secs() > 600
+        self.created_at.elapsed() > self.ttl
+    }
+}
+
+/// A ticket granting access to one playlist's m3u8 without the Bearer token.
+/// Not IP-bound: the phone requests it and hands the URL to a LAN player.
+/// The stream tickets inside the m3u8 are bound to whoever fetches it.
+#[derive(Debug, Clone)]
+pub struct PlaylistTicket {
+    pub playlist_id: i64,
+    pub created_at: Instant,
+}
+
+impl PlaylistTicket {
+    pub fn is_expired(&self) -> bool {
+        self.created_at.elapsed() > PLAYLIST_TICKET_TTL
     }
 }
 
@@ -50,21 +96,52 @@ pub struct CompanionServerState {
     pub library_folders: Arc<Mutex<Vec<String>>>,
     /// Active stream tickets (ticket_string -> StreamTicket)
     pub tickets: Mutex<HashMap<String, StreamTicket>>,
+    /// Active playlist tickets (ticket_string -> PlaylistTicket)
+    pub playlist_tickets: Mutex<HashMap<String, PlaylistTicket>>,
     /// Number of currently active audio streams
     pub active_streams: AtomicUsize,
-    /// Max concurrent streams allowed
-    pub max_streams: usize,
+    /// Max concurrent streams allowed (shared so it can be changed while running)
+    pub max_streams: Arc<AtomicUsize>,
+    /// Browse mode: every request that would change something (queue, sync, ...) is
+    /// refused (shared so it can be toggled while running)
+    pub read_only: Arc<AtomicBool>,
+    /// Per-IP request counters (ip -> (window start, request count))
+    pub rate_limits: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+    /// Unredeemed pairing codes (code -> created_at), exchanged for the token via /api/pair
+    pub pairing_codes: Mutex<HashMap<String, Instant>>,
+    /// The desktop's audition queue (shared, so the phone can act as a remote)
+    pub queue: Arc<AuditionQueue>,
+    /// Request counters and latencies (served at /api/metrics)
+    pub metrics: metrics::ServerMetrics,
+    /// Called after a peer's library snapshot was merged (so the desktop UI can reload)
+    pub sync_listener: Mutex<Option<Box<dyn Fn() + Send + Sync>>>,
+    /// Queues generation of a missing waveform (set by the desktop app; see
+    /// request_waveform)
+    pub waveform_requester: Mutex<Option<Box<WaveformRequester>>>,
+    /// Events for clients subscribed to /events
+    pub events: broadcast::Sender<CompanionEvent>,
+}
+
+/// Queues waveform generation for a track; Err carries the failure of the last attempt
+pub type WaveformRequester = dyn Fn(i64) -> Result<(), String> + Send + Sync;
+
+/// Random 256-bit ticket string (hex)
+fn random_ticket() -> String {
+    let mut rng = thread_rng();
+    (0..32)
+        .map(|_| format!("{:02x}", rng.gen::<u8>()))
+        .collect()
 }
 
 impl CompanionServerState {
-    /// Generate a new random stream ticket for a track
-    pub fn create_ticket(&self, track_id: i64) -> String {
-        let ticket: String = {
-            let mut rng = thread_rng();
-            (0..32)
-                .map(|_| format!("{:02x}", rng.gen::<u8>()))
-                .collect()
-        };
+    /// Generate a new random stream ticket for a track, bound to the requesting IP
+    pub fn create_ticket(&self, track_id: i64, client_ip: IpAddr) -> String {
+        self.create_ticket_with_ttl(track_id, client_ip, STREAM_TICKET_TTL)
+    }
+
+    /// Like create_ticket, with a custom lifetime (used for m3u8 playlist entries)
+    pub fn create_ticket_with_ttl(&self, track_id: i64, client_ip: IpAddr, ttl: Duration) -> String {
+        let ticket = random_ticket();
 
         let mut tickets = self.tickets.lock().unwrap();
         // Clean expired tickets on each creation
@@ -73,30 +150,151 @@ impl CompanionServerState {
             ticket.clone(),
             StreamTicket {
                 track_id,
-                created_at: std::time::Instant::now(),
+                client_ip,
+                created_at: Instant::now(),
+                ttl,
             },
         );
         ticket
     }
 
+    /// Generate a ticket for fetching a playlist's m3u8 (see PlaylistTicket)
+    pub fn create_playlist_ticket(&self, playlist_id: i64) -> String {
+        let ticket = random_ticket();
+        let mut tickets = self.playlist_tickets.lock().unwrap();
+        tickets.retain(|_, t| !t.is_expired());
+        tickets.insert(
+            ticket.clone(),
+            PlaylistTicket {
+                playlist_id,
+                created_at: Instant::now(),
+            },
+        );
+        ticket
+    }
+
+    /// Validate a playlist ticket (multi-use until expiry). Returns playlist_id if valid.
+    pub fn validate_playlist_ticket(&self, ticket: &str) -> Option<i64> {
+        let mut tickets = self.playlist_tickets.lock().unwrap();
+        tickets.retain(|_, t| !t.is_expired());
+        tickets.get(ticket).map(|t| t.playlist_id)
+    }
+
     /// Validate a ticket (multi-use for Range requests). Returns track_id if valid.
     /// Does NOT consume — browser makes multiple Range requests for seeking/buffering.
-    pub fn validate_ticket(&self, ticket: &str) -> Option<i64> {
+    /// A ticket presented from a different IP than it was issued to is rejected.
+    pub fn validate_ticket(&self, ticket: &str, client_ip: IpAddr) -> Option<i64> {
         let mut tickets = self.tickets.lock().unwrap();
         tickets.retain(|_, t| !t.is_expired());
-        tickets.get(ticket).map(|t| t.track_id)
+        let entry = tickets.get(ticket)?;
+        if entry.client_ip != client_ip {
+            eprintln!(
+                "[companion] Stream ticket rejected: presented from a different client ({})",
+                client_ip
+            );
+            return None;
+        }
+        Some(entry.track_id)
+    }
+
+    /// Generate a short single-use pairing code (see PAIRING_CODE_TTL)
+    pub fn create_pairing_code(&self) -> String {
+        let mut rng = thread_rng();
+        let code: String = (0..PAIRING_CODE_LEN)
+            .map(|_| PAIRING_CODE_ALPHABET[rng.gen_range(0..PAIRING_CODE_ALPHABET.len())] as char)
+            .collect();
+        let mut codes = self.pairing_codes.lock().unwrap();
+        codes.retain(|_, created| created.elapsed() <= PAIRING_CODE_TTL);
+        codes.insert(code.clone(), Instant::now());
+        code
+    }
+
+    /// Consume a pairing code. Returns true if it was valid (case-insensitive).
+    pub fn redeem_pairing_code(&self, code: &str) -> bool {
+        let mut codes = self.pairing_codes.lock().unwrap();
+        codes.retain(|_, created| created.elapsed() <= PAIRING_CODE_TTL);
+        codes.remove(&code.trim().to_uppercase()).is_some()
+    }
+
+    /// Record a request from `ip` and return false if it exceeds the per-IP limit
+    pub fn check_rate_limit(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        let mut limits = self.rate_limits.lock().unwrap();
+        // Drop stale windows so the map doesn't grow unbounded
+        limits.retain(|_, (start, _)| now.duration_since(*start) < RATE_LIMIT_WINDOW);
+        let entry = limits.entry(ip).or_insert((now, 0));
+        entry.1 += 1;
+        entry.1 <= RATE_LIMIT_MAX_REQUESTS
+    }
+
+    /// Drop the stream tickets for a track (its file moved: clients must ask for a new one).
+    /// Returns the number dropped.
+    pub fn invalidate_track_tickets(&self, track_id: i64) -> usize {
+        let mut tickets = self.tickets.lock().unwrap();
+        let before = tickets.len();
+        tickets.retain(|_, t| t.track_id != track_id);
+        before - tickets.len()
     }
 
     /// Invalidate all tickets (called when token is regenerated)
     pub fn invalidate_all_tickets(&self) {
         let mut tickets = self.tickets.lock().unwrap();
         tickets.clear();
+        self.playlist_tickets.lock().unwrap().clear();
+        self.pairing_codes.lock().unwrap().clear();
     }
 
     /// Get current active stream count
     pub fn active_stream_count(&self) -> usize {
         self.active_streams.load(Ordering::Relaxed)
     }
+
+    /// Call `listener` after every merged sync snapshot
+    pub fn set_sync_listener(&self, listener: impl Fn() + Send + Sync + 'static) {
+        *self.sync_listener.lock().unwrap() = Some(Box::new(listener));
+    }
+
+    pub fn notify_synced(&self) {
+        if let Some(listener) = self.sync_listener.lock().unwrap().as_ref() {
+            listener();
+        }
+    }
+
+    pub fn set_waveform_requester(
+        &self,
+        requester: impl Fn(i64) -> Result<(), String> + Send + Sync + 'static,
+    ) {
+        *self.waveform_requester.lock().unwrap() = Some(Box::new(requester));
+    }
+
+    /// Ask the desktop to generate a track's waveform. None if nothing is listening.
+    pub fn request_waveform(&self, track_id: i64) -> Option<Result<(), String>> {
+        self.waveform_requester
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|requester| requester(track_id))
+    }
+
+    /// Push an event to the clients subscribed to /events (dropped if there are none)
+    pub fn publish(&self, event: CompanionEvent) {
+        let _ = self.events.send(event);
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
+
+    /// Optional features this server offers, listed by /api/v1/capabilities.
+    /// No "transcoding": files are always served as stored. In browse mode the
+    /// mutating features are left out and "read_only" is listed instead.
+    pub fn features(&self) -> Vec<&'static str> {
+        if self.is_read_only() {
+            vec!["streaming", "downloads", "m3u8_playlists", "pairing", "metrics", "waveforms", "artwork", "gain_hints", "track_summaries", "events", "read_only"]
+        } else {
+            vec!["streaming", "downloads", "remote_control", "m3u8_playlists", "pairing", "metrics", "library_sync", "waveforms", "artwork", "gain_hints", "track_summaries", "events"]
+        }
+    }
 }
 
 /// Holds the running server's shutdown mechanism
@@ -104,6 +302,21 @@ pub struct RunningServer {
     pub shutdown_tx: oneshot::Sender<()>,
     pub addr: SocketAddr,
     pub token: String,
+    /// Shared server state (for pairing codes and stream counts)
+    pub state: Arc<CompanionServerState>,
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters
+pub fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect()
 }
 
 /// Generate a cryptographically random 256-bit token (64 hex chars)
@@ -140,14 +353,29 @@ async fn serve_index_with_url(
     Html(html)
 }
 
+/// API path with the version prefix removed ("/api/v1/pair" -> "/api/pair")
+fn unversioned_path(path: &str) -> String {
+    match path.strip_prefix(&format!("/api/v{}", API_VERSION)) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("/api{}", rest),
+        _ => path.to_string(),
+    }
+}
+
+/// Whether a request would change something on the desktop (refused in browse mode)
+fn is_mutation(method: &Method, path: &str) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) && !READ_ONLY_ALLOWED.contains(&path)
+}
+
 /// Auth middleware - validates Bearer token on every request.
+/// In browse mode, authenticated requests that would change something are refused.
 /// Stream endpoints use ticket-based auth instead (checked in handler).
 async fn auth_middleware(
     state: axum::extract::State<Arc<CompanionServerState>>,
     request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
-    let path = request.uri().path();
+) -> Result<Response, ApiError> {
+    let path = unversioned_path(request.uri().path());
+    let path = path.as_str();
 
     // Stream endpoint uses ticket auth, not Bearer token
     if path.starts_with("/stream/") {
@@ -157,6 +385,19 @@ async fn auth_middleware(
     if path == "/api/self" {
         return Ok(next.run(request).await);
     }
+    // Public: API version and features, checked by the PWA before pairing
+    if path == "/api/capabilities" {
+        return Ok(next.run(request).await);
+    }
+    // Public: exchanges a single-use pairing code (from the QR) for the token
+    if path == "/api/pair" {
+        return Ok(next.run(request).await);
+    }
+    // m3u8 playlists are fetched by LAN players that can't send headers:
+    // the handler accepts either the Bearer token or a playlist ticket
+    if path.starts_with("/api/playlists/") && path.ends_with(".m3u8") {
+        return Ok(next.run(request).await);
+    }
 
     // All other endpoints require Bearer token
     let auth_header = request
@@ -167,24 +408,69 @@ async fn auth_middleware(
     match auth_header {
         Some(header) if header.starts_with("Bearer ") => {
             let provided_token = &header[7..];
-            if provided_token == state.token {
-                Ok(next.run(request).await)
+            if provided_token != state.token {
+                eprintln!("[companion] Rejected request to {}: invalid token", path);
+                Err(ApiError::unauthorized("Invalid token"))
+            } else if state.is_read_only() && is_mutation(request.method(), path) {
+                eprintln!("[companion] Rejected {} {}: browse mode", request.method(), path);
+                Err(ApiError::read_only())
             } else {
-                Err(StatusCode::UNAUTHORIZED)
+                Ok(next.run(request).await)
             }
         }
-        _ => Err(StatusCode::UNAUTHORIZED),
+        _ => {
+            eprintln!("[companion] Rejected request to {}: missing token", path);
+            Err(ApiError::unauthorized("Missing token"))
+        }
+    }
+}
+
+/// Let a plain OPTIONS request reach the capabilities endpoint. The CORS layer answers
+/// every OPTIONS as a preflight, so requests that aren't one (no
+/// Access-Control-Request-Method) are passed on as GET.
+async fn capabilities_options_middleware(mut request: Request, next: Next) -> Response {
+    if request.method() == Method::OPTIONS
+        && !request.headers().contains_key("access-control-request-method")
+        && unversioned_path(request.uri().path()) == "/api/capabilities"
+    {
+        *request.method_mut() = Method::GET;
+    }
+    next.run(request).await
+}
+
+/// Rate-limit middleware - caps requests per client IP within a fixed window.
+/// Applies to every route, including the public PWA assets and stream endpoints.
+async fn rate_limit_middleware(
+    state: axum::extract::State<Arc<CompanionServerState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if !state.check_rate_limit(addr.ip()) {
+        eprintln!(
+            "[companion] Rate limit exceeded for {} ({})",
+            addr.ip(),
+            request.uri().path()
+        );
+        return Err(ApiError {
+            retry_after: Some(RATE_LIMIT_WINDOW.as_secs()),
+            ..ApiError::new(StatusCode::TOO_MANY_REQUESTS, "rate_limited", "Too many requests")
+        });
     }
+    Ok(next.run(request).await)
 }
 
 /// Start the companion HTTP server on the given port.
 /// Returns the running server handle (for shutdown) or an error.
+#[allow(clippy::too_many_arguments)]
 pub async fn start_server(
     port: u16,
     token: String,
     db: Arc<Mutex<Option<Database>>>,
     library_folders: Arc<Mutex<Vec<String>>>,
-    max_streams: usize,
+    max_streams: Arc<AtomicUsize>,
+    read_only: Arc<AtomicBool>,
+    queue: Arc<AuditionQueue>,
     mobile_dist_path: Option<PathBuf>,
 ) -> Result<RunningServer, String> {
     let state = Arc::new(CompanionServerState {
@@ -192,47 +478,19 @@ pub async fn start_server(
         db,
         library_folders,
         tickets: Mutex::new(HashMap::new()),
+        playlist_tickets: Mutex::new(HashMap::new()),
         active_streams: AtomicUsize::new(0),
         max_streams,
+        read_only,
+        rate_limits: Mutex::new(HashMap::new()),
+        pairing_codes: Mutex::new(HashMap::new()),
+        queue,
+        metrics: metrics::ServerMetrics::new(),
+        sync_listener: Mutex::new(None),
+        waveform_requester: Mutex::new(None),
+        events: broadcast::channel(events::EVENT_BUFFER).0,
     });
-
-    // CORS configuration - not a security layer, auth middleware handles that
-    let cors = CorsLayer::new()
-        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
-        .allow_headers([
-            "authorization".parse().unwrap(),
-            "content-type".parse().unwrap(),
-            "range".parse().unwrap(),
-        ])
-        .allow_origin("*".parse::<HeaderValue>().unwrap());
-
-    // API + streaming routes (auth-protected)
-    let api_routes = Router::new()
-        .merge(routes::api_routes())
-        .merge(streaming::stream_routes())
-        .layer(middleware::from_fn_with_state(
-            state.clone(),
-            auth_middleware,
-        ))
-        .with_state(state);
-
-    // Serve mobile PWA static files (no auth needed — the app itself is public,
-    // only API endpoints require authentication)
-    let app = if let Some(dist_path) = mobile_dist_path.filter(|p| p.exists()) {
-        let index_html = dist_path.join("index.html");
-        eprintln!("[companion] Serving mobile PWA from {:?}", dist_path);
-        let index_routes = Router::new()
-            .route("/", get(serve_index_with_url))
-            .route("/index.html", get(serve_index_with_url))
-            .layer(Extension(index_html));
-        index_routes
-            .merge(api_routes)
-            .fallback_service(ServeDir::new(&dist_path).fallback(ServeFile::new(&dist_path.join("index.html"))))
-            .layer(cors)
-    } else {
-        eprintln!("[companion] No mobile PWA dist found, API-only mode");
-        api_routes.layer(cors)
-    };
+    let app = build_router(state.clone(), mobile_dist_path);
 
     // Try to bind to the requested port, with fallback
     let addr = try_bind(port).await?;
@@ -251,11 +509,17 @@ pub async fn start_server(
         actual_addr
     );
 
+    let events = state.events.clone();
     tokio::spawn(async move {
-        axum::serve(listener, app)
-            .with_graceful_shutdown(async {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+            .with_graceful_shutdown(async move {
                 let _ = shutdown_rx.await;
                 eprintln!("[companion] Shutdown signal received, draining connections...");
+                // Ends the /events streams, which would otherwise stay open
+                let _ = events.send(CompanionEvent::ServerStopping);
                 // Give active streams 5 seconds to finish
                 tokio::time::sleep(std::time::Duration::from_secs(5)).await;
             })
@@ -268,9 +532,64 @@ pub async fn start_server(
         shutdown_tx,
         addr: actual_addr,
         token,
+        state,
     })
 }
 
+/// All routes and middleware of the companion server. Requests need a ConnectInfo
+/// extension (the rate limit is per client IP).
+fn build_router(state: Arc<CompanionServerState>, mobile_dist_path: Option<PathBuf>) -> Router {
+    // CORS configuration - not a security layer, auth middleware handles that
+    let cors = CorsLayer::new()
+        .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::OPTIONS])
+        .allow_headers([
+            "authorization".parse().unwrap(),
+            "content-type".parse().unwrap(),
+            "range".parse().unwrap(),
+        ])
+        .allow_origin("*".parse::<HeaderValue>().unwrap());
+
+    // API + streaming routes (auth-protected). The API is served under /api/v1 and,
+    // unversioned, under /api for PWAs that predate versioning. JSON responses are
+    // gzip/brotli compressed; downloads and streams are audio and go out as they are.
+    let versioned_routes = routes::api_routes()
+        .merge(sync::sync_routes())
+        .layer(CompressionLayer::new())
+        .merge(streaming::download_routes())
+        .merge(events::event_routes());
+    let api_routes = Router::new()
+        .nest(&format!("/api/v{}", API_VERSION), versioned_routes.clone())
+        .nest("/api", versioned_routes)
+        .merge(streaming::stream_routes())
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ))
+        .layer(middleware::from_fn(error::error_envelope_middleware))
+        .with_state(state.clone());
+
+    // Serve mobile PWA static files (no auth needed — the app itself is public,
+    // only API endpoints require authentication)
+    let app = if let Some(dist_path) = mobile_dist_path.filter(|p| p.exists()) {
+        let index_html = dist_path.join("index.html");
+        eprintln!("[companion] Serving mobile PWA from {:?}", dist_path);
+        let index_routes = Router::new()
+            .route("/", get(serve_index_with_url))
+            .route("/index.html", get(serve_index_with_url))
+            .layer(Extension(index_html));
+        index_routes
+            .merge(api_routes)
+            .fallback_service(ServeDir::new(&dist_path).fallback(ServeFile::new(&dist_path.join("index.html"))))
+            .layer(cors)
+    } else {
+        eprintln!("[companion] No mobile PWA dist found, API-only mode");
+        api_routes.layer(cors)
+    };
+    let app = app.layer(middleware::from_fn(capabilities_options_middleware));
+    let app = app.layer(middleware::from_fn_with_state(state.clone(), rate_limit_middleware));
+    app.layer(middleware::from_fn_with_state(state, metrics::metrics_middleware))
+}
+
 /// Try to bind to the given port, with fallback to nearby ports then OS-assigned
 async fn try_bind(preferred_port: u16) -> Result<SocketAddr, String> {
     // Try preferred port
@@ -308,3 +627,125 @@ async fn try_bind(preferred_port: u16) -> Result<SocketAddr, String> {
 
     Err("Failed to bind to any port".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    pub(super) fn test_state() -> CompanionServerState {
+        CompanionServerState {
+            token: generate_token(),
+            db: Arc::new(Mutex::new(None)),
+            library_folders: Arc::new(Mutex::new(Vec::new())),
+            tickets: Mutex::new(HashMap::new()),
+            playlist_tickets: Mutex::new(HashMap::new()),
+            active_streams: AtomicUsize::new(0),
+            max_streams: Arc::new(AtomicUsize::new(3)),
+            read_only: Arc::new(AtomicBool::new(false)),
+            rate_limits: Mutex::new(HashMap::new()),
+            pairing_codes: Mutex::new(HashMap::new()),
+            queue: Arc::new(AuditionQueue::new()),
+            metrics: metrics::ServerMetrics::new(),
+            sync_listener: Mutex::new(None),
+            waveform_requester: Mutex::new(None),
+            events: broadcast::channel(events::EVENT_BUFFER).0,
+        }
+    }
+
+    #[test]
+    fn test_pairing_code_is_single_use() {
+        let state = test_state();
+        let code = state.create_pairing_code();
+        assert_eq!(code.len(), PAIRING_CODE_LEN);
+
+        assert!(!state.redeem_pairing_code("WRONG234"));
+        assert!(state.redeem_pairing_code(&code.to_lowercase()));
+        assert!(!state.redeem_pairing_code(&code));
+    }
+
+    #[test]
+    fn test_invalidate_track_tickets() {
+        let state = test_state();
+        let ip: IpAddr = "10.0.0.5".parse().unwrap();
+        let renamed = state.create_ticket(1, ip);
+        let other = state.create_ticket(2, ip);
+
+        assert_eq!(state.invalidate_track_tickets(1), 1);
+        assert_eq!(state.validate_ticket(&renamed, ip), None);
+        assert_eq!(state.validate_ticket(&other, ip), Some(2));
+    }
+
+    #[test]
+    fn test_request_waveform() {
+        let state = test_state();
+        assert_eq!(state.request_waveform(1), None);
+        state.set_waveform_requester(|id| if id == 1 { Ok(()) } else { Err("Audio file not found".to_string()) });
+        assert_eq!(state.request_waveform(1), Some(Ok(())));
+        assert!(state.request_waveform(2).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_browse_mode() {
+        let state = test_state();
+        assert!(state.features().contains(&"remote_control"));
+        state.read_only.store(true, Ordering::Relaxed);
+        assert!(state.features().contains(&"read_only"));
+        assert!(!state.features().contains(&"remote_control"));
+
+        assert!(is_mutation(&Method::POST, "/api/queue"));
+        assert!(is_mutation(&Method::DELETE, "/api/queue/0"));
+        assert!(is_mutation(&Method::POST, "/api/sync/snapshot"));
+        assert!(!is_mutation(&Method::GET, "/api/sync/snapshot"));
+        assert!(!is_mutation(&Method::POST, "/api/stream-ticket"));
+        assert!(!is_mutation(&Method::POST, "/api/pair"));
+    }
+
+    #[test]
+    fn test_unversioned_path() {
+        assert_eq!(unversioned_path("/api/v1/pair"), "/api/pair");
+        assert_eq!(unversioned_path("/api/v1"), "/api");
+        assert_eq!(unversioned_path("/api/pair"), "/api/pair");
+        assert_eq!(unversioned_path("/api/v10/pair"), "/api/v10/pair");
+        assert_eq!(unversioned_path("/stream/4"), "/stream/4");
+    }
+
+    /// GET `path` through the whole router with the token; returns the status and JSON body
+    async fn get_json(app: Router, token: &str, path: &str) -> (StatusCode, serde_json::Value) {
+        let mut request = Request::get(path)
+            .header("authorization", format!("Bearer {}", token))
+            .body(Body::empty())
+            .unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 5], 50000))));
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), 64 * 1024).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_router_serves_versioned_and_legacy_paths() {
+        let state = Arc::new(test_state());
+        let token = state.token.clone();
+        let app = build_router(state, None);
+
+        let (status, versioned) = get_json(app.clone(), &token, "/api/v1/capabilities").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(versioned["api_version"], API_VERSION);
+        let (status, legacy) = get_json(app.clone(), &token, "/api/capabilities").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(legacy, versioned);
+
+        // Caught by the catch-all route, not a bare 404
+        let (status, body) = get_json(app, &token, "/api/v1/nowhere").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["message"], "Unknown API endpoint");
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(percent_encode("http://10.0.0.2:8384"), "http%3A%2F%2F10.0.0.2%3A8384");
+    }
+}
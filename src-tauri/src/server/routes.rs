@@ -1,18 +1,34 @@
 // REST API routes for the mobile companion server
 // All responses sanitize data: no file_path, no absolute paths exposed.
+// Routes are mounted under /api/v1 and, for PWAs that predate versioning, under /api.
+// Errors use the { code, message } envelope (see error.rs).
 
 use axum::{
     Json, Router,
-    extract::{Path, Query, State},
-    http::StatusCode,
-    routing::{get, post},
+    body::Body,
+    extract::{ConnectInfo, Path, Query, State},
+    http::{HeaderMap, HeaderValue, Response, StatusCode, header},
+    response::IntoResponse,
+    routing::{any, delete, get, post},
 };
 use axum::extract::Request;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::SocketAddr;
 use std::sync::Arc;
 
-use super::CompanionServerState;
-use crate::db::Track;
+use super::error::ApiError;
+use super::metrics::MetricsSnapshot;
+use super::{CompanionServerState, PLAYLIST_STREAM_TICKET_TTL, PLAYLIST_TICKET_TTL, STREAM_TICKET_TTL};
+use crate::artwork;
+use crate::db::{Playlist, Track};
+
+/// Seconds a client should wait before polling for a waveform being generated
+const WAVEFORM_RETRY_AFTER_SECS: u64 = 2;
+/// Loudness tracks are normalized to by the gain hint (ReplayGain 2.0 reference level)
+const GAIN_REFERENCE_LUFS: f64 = -18.0;
+/// Gain hints are clamped to this range (dB): boosting a very quiet track further only clips
+const GAIN_HINT_RANGE_DB: (f64, f64) = (-24.0, 12.0);
 
 // ---- Sanitized DTOs (never expose file_path) ----
 
@@ -39,6 +55,9 @@ pub struct MobileTrackDTO {
     // Analysis fields
     pub bpm: Option<f64>,
     pub musical_key: Option<String>,
+    /// Gain (dB) bringing the track to GAIN_REFERENCE_LUFS, from its measured loudness.
+    /// None until the track's loudness is analyzed.
+    pub gain_db: Option<f64>,
 }
 
 impl MobileTrackDTO {
@@ -68,6 +87,7 @@ impl MobileTrackDTO {
             filename,
             bpm: None,
             musical_key: None,
+            gain_db: None,
         }
     }
 
@@ -83,6 +103,74 @@ impl MobileTrackDTO {
     }
 }
 
+/// Just what a phone's track list shows: no paths, hashes or file details
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MobileTrackSummaryDTO {
+    pub id: i64,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub bpm: Option<f64>,
+    pub musical_key: Option<String>,
+    pub duration_ms: Option<i32>,
+    /// Track color, "#RRGGBB"
+    pub color: Option<String>,
+    pub rating: i32,
+}
+
+impl MobileTrackSummaryDTO {
+    fn from_track_with_analysis(track: Track, bpm: Option<f64>, musical_key: Option<String>) -> Self {
+        MobileTrackSummaryDTO {
+            id: track.id.unwrap_or(0),
+            title: track.title,
+            artist: track.artist,
+            bpm,
+            musical_key,
+            duration_ms: track.duration_ms,
+            color: track.color,
+            rating: track.rating,
+        }
+    }
+}
+
+/// Volume normalization gain for a track measured at `loudness_lufs`, rounded to 0.1 dB
+fn gain_hint_db(loudness_lufs: f64) -> f64 {
+    let (min, max) = GAIN_HINT_RANGE_DB;
+    ((GAIN_REFERENCE_LUFS - loudness_lufs).clamp(min, max) * 10.0).round() / 10.0
+}
+
+/// Fill in the gain hints of `tracks` (one query for the whole page)
+fn add_gain_hints(db: &crate::db::Database, tracks: &mut [MobileTrackDTO]) -> rusqlite::Result<()> {
+    let ids: Vec<i64> = tracks.iter().map(|t| t.id).collect();
+    let loudness = db.get_track_loudness(&ids)?;
+    for track in tracks {
+        track.gain_db = loudness.get(&track.id).copied().map(gain_hint_db);
+    }
+    Ok(())
+}
+
+/// Playlist data for mobile clients (no mirror folder path, no rules or AI prompt)
+#[derive(Debug, Clone, Serialize)]
+pub struct MobilePlaylistDTO {
+    pub id: i64,
+    pub name: String,
+    /// "manual", "smart", "folder" or "mirror"
+    pub playlist_type: String,
+    pub parent_id: Option<i64>,
+    pub track_count: i64,
+}
+
+impl MobilePlaylistDTO {
+    fn from_playlist(playlist: Playlist, track_count: i64) -> Self {
+        MobilePlaylistDTO {
+            id: playlist.id.unwrap_or(0),
+            name: playlist.name,
+            playlist_type: playlist.playlist_type,
+            parent_id: playlist.parent_id,
+            track_count,
+        }
+    }
+}
+
 // ---- Request/Response types ----
 
 #[derive(Deserialize)]
@@ -115,43 +203,214 @@ pub struct StreamTicketResponse {
     pub stream_url: String,
 }
 
+#[derive(Deserialize)]
+pub struct PlaylistTicketRequest {
+    pub playlist_id: i64,
+}
+
+#[derive(Serialize)]
+pub struct PlaylistTicketResponse {
+    pub ticket: String,
+    pub expires_in: u64,
+    /// Relative m3u8 URL including the ticket — hand this to Sonos/VLC
+    pub playlist_url: String,
+}
+
+#[derive(Deserialize)]
+pub struct TicketQuery {
+    pub ticket: Option<String>,
+}
+
 #[derive(Serialize)]
 pub struct SelfUrlResponse {
     pub url: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct EnqueueRequest {
+    pub track_id: i64,
+    #[serde(default)]
+    pub play_next: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReorderRequest {
+    pub from: usize,
+    pub to: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PairRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PairResponse {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArtworkQuery {
+    /// Thumbnail edge length (64 or 256); the original image without it
+    pub size: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WaveformQuery {
+    /// "overview" (default) or "detail"
+    pub level: Option<String>,
+}
+
+/// Body of a 202 from the waveform endpoint
+#[derive(Debug, Serialize)]
+pub struct WaveformPendingResponse {
+    pub status: &'static str,
+    pub retry_after: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CapabilitiesResponse {
+    pub name: String,
+    pub version: String,
+    /// Newest API version, served under /api/v{n}
+    pub api_version: u32,
+    /// Every API version this server answers
+    pub api_versions: Vec<u32>,
+    /// Enabled optional features (see CompanionServerState::features)
+    pub features: Vec<&'static str>,
+}
+
 // ---- Route registration ----
 
+/// API routes, relative to the version prefix (mounted by server::start_server)
 pub fn api_routes() -> Router<Arc<CompanionServerState>> {
     Router::new()
-        .route("/api/self", get(get_self_url))
-        .route("/api/status", get(get_status))
-        .route("/api/tracks", get(get_tracks))
-        .route("/api/tracks/search", get(search_tracks))
-        .route("/api/tracks/{id}", get(get_track))
-        .route("/api/stream-ticket", post(create_stream_ticket))
+        .route("/capabilities", get(get_capabilities))
+        .route("/self", get(get_self_url))
+        .route("/pair", post(pair))
+        .route("/status", get(get_status))
+        .route("/metrics", get(get_metrics))
+        .route("/tracks", get(get_tracks))
+        .route("/playlists", get(get_playlists))
+        .route("/tracks/summary", get(get_track_summaries))
+        .route("/tracks/search", get(search_tracks))
+        .route("/tracks/{id}", get(get_track))
+        .route("/tracks/{id}/waveform", get(get_track_waveform))
+        .route("/tracks/{id}/artwork", get(get_track_artwork))
+        .route("/stream-ticket", post(create_stream_ticket))
+        .route("/playlist-ticket", post(create_playlist_ticket))
+        .route("/playlists/{file}", get(get_playlist_m3u8))
+        .route("/queue", get(get_queue).post(enqueue_track).delete(clear_queue))
+        .route("/queue/reorder", post(reorder_queue))
+        .route("/queue/{index}", delete(remove_from_queue))
+        .route("/{*rest}", any(unknown_endpoint))
+}
+
+// ---- Conditional JSON ----
+
+/// Weak ETag for a response body. Weak because the same JSON goes out gzip- or
+/// brotli-encoded depending on the client (CompressionLayer).
+fn body_etag(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    let hex: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("W/\"{}\"", hex)
+}
+
+/// Whether the request's If-None-Match lists `etag` (or is "*")
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+/// JSON response with an ETag; an empty 304 when the client already has this body,
+/// so reloading an unchanged track list costs the phone only headers
+fn json_with_etag<T: Serialize>(headers: &HeaderMap, value: &T) -> Result<Response<Body>, ApiError> {
+    let body = serde_json::to_vec(value).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let etag = body_etag(&body);
+    let response = Response::builder()
+        .header(header::ETAG, &etag)
+        // Cache, but revalidate on every load
+        .header(header::CACHE_CONTROL, "no-cache");
+    let response = if etag_matches(headers, &etag) {
+        response.status(StatusCode::NOT_MODIFIED).body(Body::empty())
+    } else {
+        response
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+    };
+    response.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into())
 }
 
 // ---- Handlers ----
 
-async fn get_self_url(request: Request) -> Json<SelfUrlResponse> {
-    let host = request
-        .headers()
+/// Base URL the client used to reach us (Host + X-Forwarded-Proto)
+fn request_base_url(headers: &HeaderMap) -> String {
+    let host = headers
         .get("host")
         .and_then(|v| v.to_str().ok())
         .unwrap_or("localhost:8384");
-    let scheme = request
-        .headers()
+    let scheme = headers
         .get("x-forwarded-proto")
         .and_then(|v| v.to_str().ok())
         .unwrap_or("http");
-    let url = format!("{}://{}", scheme, host);
+    format!("{}://{}", scheme, host)
+}
+
+/// API version and enabled features, so a PWA can adapt to the desktop it is talking to.
+/// Public; plain OPTIONS requests are answered too (see capabilities_options_middleware).
+async fn get_capabilities(
+    State(state): State<Arc<CompanionServerState>>,
+) -> ([(header::HeaderName, HeaderValue); 2], Json<CapabilitiesResponse>) {
+    (
+        [
+            (header::ALLOW, HeaderValue::from_static("GET, OPTIONS")),
+            (header::CACHE_CONTROL, HeaderValue::from_static("no-store")),
+        ],
+        Json(CapabilitiesResponse {
+            name: "RecoDeck".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            api_version: super::API_VERSION,
+            api_versions: vec![super::API_VERSION],
+            features: state.features(),
+        }),
+    )
+}
+
+/// JSON 404 for unknown API paths (instead of the PWA's index.html)
+async fn unknown_endpoint() -> ApiError {
+    ApiError::not_found("Unknown API endpoint")
+}
+
+async fn get_self_url(request: Request) -> Json<SelfUrlResponse> {
+    let url = request_base_url(request.headers());
     Json(SelfUrlResponse { url })
 }
 
+/// Exchange a pairing code from the desktop QR for the auth token (public, single use)
+async fn pair(
+    State(state): State<Arc<CompanionServerState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Json(body): Json<PairRequest>,
+) -> Result<Json<PairResponse>, ApiError> {
+    if !state.redeem_pairing_code(&body.code) {
+        eprintln!("[companion] Pairing rejected: invalid or expired code from {}", addr.ip());
+        return Err(ApiError::unauthorized("Pairing code invalid or expired"));
+    }
+    eprintln!("[companion] Paired device at {}", addr.ip());
+    Ok(Json(PairResponse {
+        token: state.token.clone(),
+    }))
+}
+
 async fn get_status(
     State(state): State<Arc<CompanionServerState>>,
-) -> Result<Json<StatusResponse>, StatusCode> {
+) -> Result<Json<StatusResponse>, ApiError> {
     let db_lock = state.db.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     let db = db_lock.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
 
@@ -164,10 +423,18 @@ async fn get_status(
     }))
 }
 
+/// Request counters, bytes served and per-endpoint latency since the server started
+async fn get_metrics(
+    State(state): State<Arc<CompanionServerState>>,
+) -> Json<MetricsSnapshot> {
+    Json(state.metrics.snapshot(state.active_stream_count()))
+}
+
 async fn get_tracks(
     State(state): State<Arc<CompanionServerState>>,
     Query(params): Query<PaginationParams>,
-) -> Result<Json<Vec<MobileTrackDTO>>, StatusCode> {
+    headers: HeaderMap,
+) -> Result<Response<Body>, ApiError> {
     let limit = params.limit.unwrap_or(50).min(500);
     let offset = params.offset.unwrap_or(0);
 
@@ -175,23 +442,76 @@ async fn get_tracks(
     let db = db_lock.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
 
     let rows = db
-        .get_tracks_with_analysis_paginated(limit, offset)
+        .get_tracks_with_analysis_paginated(limit, offset, None, false)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let tracks: Vec<MobileTrackDTO> = rows
+    let mut tracks: Vec<MobileTrackDTO> = rows
         .into_iter()
         .map(|(track, bpm, _bpm_conf, key, _key_conf)| {
             MobileTrackDTO::from_track_with_analysis(track, bpm, key)
         })
         .collect();
+    add_gain_hints(db, &mut tracks).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    json_with_etag(&headers, &tracks)
+}
+
+/// A page of tracks with only the list view's fields (MobileTrackSummaryDTO), same
+/// order and paging as /tracks
+async fn get_track_summaries(
+    State(state): State<Arc<CompanionServerState>>,
+    Query(params): Query<PaginationParams>,
+    headers: HeaderMap,
+) -> Result<Response<Body>, ApiError> {
+    let limit = params.limit.unwrap_or(50).min(500);
+    let offset = params.offset.unwrap_or(0);
+
+    let db_lock = state.db.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let db = db_lock.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    let rows = db
+        .get_tracks_with_analysis_paginated(limit, offset, None, false)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    drop(db_lock);
+
+    let tracks: Vec<MobileTrackSummaryDTO> = rows
+        .into_iter()
+        .map(|(track, bpm, _bpm_conf, key, _key_conf)| {
+            MobileTrackSummaryDTO::from_track_with_analysis(track, bpm, key)
+        })
+        .collect();
 
-    Ok(Json(tracks))
+    json_with_etag(&headers, &tracks)
+}
+
+/// All playlists and folders with their track counts
+async fn get_playlists(
+    State(state): State<Arc<CompanionServerState>>,
+    headers: HeaderMap,
+) -> Result<Response<Body>, ApiError> {
+    let db_lock = state.db.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let db = db_lock.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    let playlists = db
+        .get_all_playlists()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let playlists = playlists
+        .into_iter()
+        .map(|playlist| {
+            let count = playlist.id.map_or(Ok(0), |id| db.count_playlist_tracks(id))?;
+            Ok(MobilePlaylistDTO::from_playlist(playlist, count))
+        })
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    drop(db_lock);
+
+    json_with_etag(&headers, &playlists)
 }
 
 async fn search_tracks(
     State(state): State<Arc<CompanionServerState>>,
     Query(params): Query<SearchParams>,
-) -> Result<Json<Vec<MobileTrackDTO>>, StatusCode> {
+) -> Result<Json<Vec<MobileTrackDTO>>, ApiError> {
     let query = params.q.unwrap_or_default();
     if query.is_empty() {
         return Ok(Json(Vec::new()));
@@ -204,10 +524,11 @@ async fn search_tracks(
         .search_tracks(&query)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let mobile_tracks: Vec<MobileTrackDTO> = tracks
+    let mut mobile_tracks: Vec<MobileTrackDTO> = tracks
         .into_iter()
         .map(MobileTrackDTO::from_track)
         .collect();
+    add_gain_hints(db, &mut mobile_tracks).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(Json(mobile_tracks))
 }
@@ -215,31 +536,353 @@ async fn search_tracks(
 async fn get_track(
     State(state): State<Arc<CompanionServerState>>,
     Path(id): Path<i64>,
-) -> Result<Json<MobileTrackDTO>, StatusCode> {
+) -> Result<Json<MobileTrackDTO>, ApiError> {
     let db_lock = state.db.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     let db = db_lock.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
 
-    let track = db.get_track(id).map_err(|_| StatusCode::NOT_FOUND)?;
+    let track = db.get_track(id).map_err(|_| ApiError::not_found("Track not found"))?;
+    let mut tracks = [MobileTrackDTO::from_track(track)];
+    add_gain_hints(db, &mut tracks).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let [track] = tracks;
+
+    Ok(Json(track))
+}
+
+/// A track's cover: the original image, or with ?size=64|256 a cached JPEG thumbnail
+/// (see artwork.rs). 404 if the track has no artwork.
+async fn get_track_artwork(
+    State(state): State<Arc<CompanionServerState>>,
+    Path(id): Path<i64>,
+    Query(query): Query<ArtworkQuery>,
+) -> Result<Response<Body>, ApiError> {
+    if let Some(size) = query.size {
+        artwork::check_size(size).map_err(ApiError::bad_request)?;
+    }
+
+    let (track, dir) = {
+        let db_lock = state.db.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let db = db_lock.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+        let track = db.get_track(id).map_err(|_| ApiError::not_found("Track not found"))?;
+        (track, artwork::thumbnail_dir(db))
+    };
+
+    // Decoding and scaling a big cover takes a moment: off the async runtime
+    let image = tokio::task::spawn_blocking(move || -> Result<Option<(Vec<u8>, &'static str)>, String> {
+        let artwork_path = track.artwork_path.as_deref();
+        let Some(size) = query.size else {
+            let data = artwork::read_artwork(&track.file_path, artwork_path)?;
+            return Ok(data.map(|data| {
+                let mime = artwork::image_mime_type(&data);
+                (data, mime)
+            }));
+        };
+        let dir = dir.ok_or("No thumbnail cache for this database")?;
+        match artwork::thumbnail(&dir, id, &track.file_path, artwork_path, size)? {
+            Some(path) => std::fs::read(&path)
+                .map(|data| Some((data, "image/jpeg")))
+                .map_err(|e| format!("Failed to read thumbnail: {}", e)),
+            None => Ok(None),
+        }
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "artwork_failed", e))?;
+
+    let (data, mime) = image.ok_or_else(|| ApiError::not_found("Track has no artwork"))?;
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", mime)
+        .header("Cache-Control", "private, max-age=3600")
+        .body(Body::from(data))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into())
+}
 
-    Ok(Json(MobileTrackDTO::from_track(track)))
+/// A track's waveform blob, as stored by the desktop. A track without one is queued for
+/// generation on the desktop's analysis worker and answered with 202 and Retry-After;
+/// the client polls until the blob is served.
+async fn get_track_waveform(
+    State(state): State<Arc<CompanionServerState>>,
+    Path(id): Path<i64>,
+    Query(query): Query<WaveformQuery>,
+) -> Result<Response<Body>, ApiError> {
+    let level = query.level.as_deref().unwrap_or("overview");
+    if level != "overview" && level != "detail" {
+        return Err(ApiError::bad_request("level must be \"overview\" or \"detail\""));
+    }
+
+    let blob = {
+        let db_lock = state.db.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let db = db_lock.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+        db.get_track(id).map_err(|_| ApiError::not_found("Track not found"))?;
+        db.get_waveform(id, level).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    };
+
+    if let Some(blob) = blob {
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/octet-stream")
+            .header("Cache-Control", "private, max-age=3600")
+            .body(Body::from(blob))
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into());
+    }
+
+    match state.request_waveform(id) {
+        None => Err(ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "unavailable",
+            "Waveform generation is not available",
+        )),
+        Some(Err(e)) => Err(ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "waveform_failed", e)),
+        Some(Ok(())) => {
+            let body = Json(WaveformPendingResponse {
+                status: "pending",
+                retry_after: WAVEFORM_RETRY_AFTER_SECS,
+            });
+            Ok((
+                StatusCode::ACCEPTED,
+                [
+                    (header::RETRY_AFTER, HeaderValue::from(WAVEFORM_RETRY_AFTER_SECS)),
+                    (header::CACHE_CONTROL, HeaderValue::from_static("no-store")),
+                ],
+                body,
+            )
+                .into_response())
+        }
+    }
 }
 
 async fn create_stream_ticket(
     State(state): State<Arc<CompanionServerState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     Json(body): Json<StreamTicketRequest>,
-) -> Result<Json<StreamTicketResponse>, StatusCode> {
+) -> Result<Json<StreamTicketResponse>, ApiError> {
     // Verify the track exists
     let db_lock = state.db.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     let db = db_lock.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
-    let _track = db.get_track(body.track_id).map_err(|_| StatusCode::NOT_FOUND)?;
+    let _track = db.get_track(body.track_id).map_err(|_| ApiError::not_found("Track not found"))?;
     drop(db_lock);
 
-    let ticket = state.create_ticket(body.track_id);
+    let ticket = state.create_ticket(body.track_id, addr.ip());
     let stream_url = format!("/stream/{}", body.track_id);
 
     Ok(Json(StreamTicketResponse {
         ticket,
-        expires_in: 600,
+        expires_in: STREAM_TICKET_TTL.as_secs(),
         stream_url,
     }))
 }
+
+async fn create_playlist_ticket(
+    State(state): State<Arc<CompanionServerState>>,
+    Json(body): Json<PlaylistTicketRequest>,
+) -> Result<Json<PlaylistTicketResponse>, ApiError> {
+    // Verify the playlist exists
+    {
+        let db_lock = state.db.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let db = db_lock.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+        db.get_playlist(body.playlist_id).map_err(|_| ApiError::not_found("Playlist not found"))?;
+    }
+
+    let ticket = state.create_playlist_ticket(body.playlist_id);
+    let playlist_url = format!("/api/v{}/playlists/{}.m3u8?ticket={}", super::API_VERSION, body.playlist_id, ticket);
+
+    Ok(Json(PlaylistTicketResponse {
+        ticket,
+        expires_in: PLAYLIST_TICKET_TTL.as_secs(),
+        playlist_url,
+    }))
+}
+
+/// The desktop's audition queue, next first
+async fn get_queue(
+    State(state): State<Arc<CompanionServerState>>,
+) -> Result<Json<Vec<MobileTrackDTO>>, ApiError> {
+    let db_lock = state.db.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let db = db_lock.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    Ok(Json(
+        state
+            .queue
+            .snapshot()
+            .into_iter()
+            .filter_map(|id| db.get_track(id).ok())
+            .map(MobileTrackDTO::from_track)
+            .collect(),
+    ))
+}
+
+async fn enqueue_track(
+    State(state): State<Arc<CompanionServerState>>,
+    Json(body): Json<EnqueueRequest>,
+) -> Result<Json<Vec<MobileTrackDTO>>, ApiError> {
+    {
+        let db_lock = state.db.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let db = db_lock.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+        db.get_track(body.track_id).map_err(|_| ApiError::not_found("Track not found"))?;
+    }
+    state.queue.enqueue(body.track_id, body.play_next);
+    get_queue(State(state)).await
+}
+
+async fn reorder_queue(
+    State(state): State<Arc<CompanionServerState>>,
+    Json(body): Json<ReorderRequest>,
+) -> Result<Json<Vec<MobileTrackDTO>>, ApiError> {
+    state
+        .queue
+        .reorder(body.from, body.to)
+        .map_err(ApiError::bad_request)?;
+    get_queue(State(state)).await
+}
+
+async fn remove_from_queue(
+    State(state): State<Arc<CompanionServerState>>,
+    Path(index): Path<usize>,
+) -> Result<Json<Vec<MobileTrackDTO>>, ApiError> {
+    state.queue.remove(index).map_err(ApiError::not_found)?;
+    get_queue(State(state)).await
+}
+
+async fn clear_queue(State(state): State<Arc<CompanionServerState>>) -> StatusCode {
+    state.queue.clear();
+    StatusCode::NO_CONTENT
+}
+
+/// Serve a playlist as an extended M3U (UTF-8) for LAN players (Sonos, VLC).
+/// Auth: Bearer token or a playlist ticket. Each entry is an absolute /stream URL
+/// with its own ticket, bound to the IP that fetched the playlist and valid long
+/// enough to play the whole list.
+async fn get_playlist_m3u8(
+    State(state): State<Arc<CompanionServerState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(file): Path<String>,
+    Query(query): Query<TicketQuery>,
+    headers: HeaderMap,
+) -> Result<Response<Body>, ApiError> {
+    let playlist_id: i64 = file
+        .strip_suffix(".m3u8")
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| ApiError::not_found("Playlist not found"))?;
+
+    let has_token = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(|t| t == state.token)
+        .unwrap_or(false);
+    let has_ticket = query
+        .ticket
+        .as_deref()
+        .and_then(|t| state.validate_playlist_ticket(t))
+        .map(|id| id == playlist_id)
+        .unwrap_or(false);
+    if !has_token && !has_ticket {
+        eprintln!("[companion] Playlist rejected: missing or invalid ticket from {}", addr.ip());
+        return Err(ApiError::unauthorized("Missing or invalid playlist ticket"));
+    }
+
+    let (playlist, tracks) = {
+        let db_lock = state.db.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let db = db_lock.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+        let playlist = db.get_playlist(playlist_id).map_err(|_| ApiError::not_found("Playlist not found"))?;
+        let tracks = db
+            .get_playlist_tracks(playlist_id)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        (playlist, tracks)
+    };
+
+    let base_url = request_base_url(&headers);
+    let mut m3u = format!("#EXTM3U\n#PLAYLIST:{}\n", m3u_text(&playlist.name));
+    for (track, ..) in tracks {
+        let track_id = match track.id {
+            Some(id) => id,
+            None => continue,
+        };
+        let ticket = state.create_ticket_with_ttl(track_id, addr.ip(), PLAYLIST_STREAM_TICKET_TTL);
+        let duration_secs = track.duration_ms.map(|ms| ms / 1000).unwrap_or(-1);
+        let title = match (&track.artist, &track.title) {
+            (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+            (None, Some(title)) => title.clone(),
+            _ => format!("Track {}", track_id),
+        };
+        m3u.push_str(&format!(
+            "#EXTINF:{},{}\n{}/stream/{}?ticket={}\n",
+            duration_secs,
+            m3u_text(&title),
+            base_url,
+            track_id,
+            ticket
+        ));
+    }
+
+    eprintln!("[companion] Served playlist {} as m3u8 to {}", playlist_id, addr.ip());
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/vnd.apple.mpegurl; charset=utf-8")
+        .header("Cache-Control", "no-store")
+        .header("Referrer-Policy", "no-referrer")
+        .body(Body::from(m3u))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into())
+}
+
+/// M3U directives are line-based: keep titles on one line
+fn m3u_text(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_track;
+
+    #[test]
+    fn test_etag_matches() {
+        let etag = body_etag(b"[]");
+        assert!(etag.starts_with("W/\""));
+        assert_ne!(etag, body_etag(b"[1]"));
+
+        let request = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(value).unwrap());
+            headers
+        };
+        assert!(etag_matches(&request(&etag), &etag));
+        // Strong form of the same tag, listed among others
+        let strong = etag.trim_start_matches("W/");
+        assert!(etag_matches(&request(&format!("\"other\", {}", strong)), &etag));
+        assert!(etag_matches(&request("*"), &etag));
+        assert!(!etag_matches(&request("\"other\""), &etag));
+        assert!(!etag_matches(&HeaderMap::new(), &etag));
+    }
+
+    #[test]
+    fn test_track_summary_leaves_out_paths() {
+        let track = Track {
+            id: Some(7),
+            file_path: "/Users/dj/Music/Secret Edit.mp3".to_string(),
+            title: Some("Edit".to_string()),
+            album: Some("Album".to_string()),
+            duration_ms: Some(360_000),
+            file_size: Some(1234),
+            play_count: 3,
+            rating: 4,
+            color: Some("#FF0000".to_string()),
+            ..create_test_track()
+        };
+        let summary = MobileTrackSummaryDTO::from_track_with_analysis(track, Some(124.0), Some("8A".to_string()));
+        let json = serde_json::to_value(&summary).unwrap();
+        let mut keys: Vec<&str> = json.as_object().unwrap().keys().map(|k| k.as_str()).collect();
+        keys.sort();
+        assert_eq!(keys, ["artist", "bpm", "color", "duration_ms", "id", "musical_key", "rating", "title"]);
+        assert!(!json.to_string().contains("Secret"));
+    }
+
+    #[test]
+    fn test_gain_hint_db() {
+        assert_eq!(gain_hint_db(-18.0), 0.0);
+        assert_eq!(gain_hint_db(-8.26), -9.7);
+        assert_eq!(gain_hint_db(-23.0), 5.0);
+        assert_eq!(gain_hint_db(-60.0), 12.0);
+    }
+}
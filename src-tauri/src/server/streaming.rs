@@ -1,24 +1,30 @@
 // Secure audio streaming handler for the mobile companion server
-// - Ticket-based auth (multi-use for Range requests, 10min expiry)
+// - Ticket-based auth (multi-use for Range requests, 10min expiry, bound to client IP)
 // - Stream by track ID only (no file paths from client)
 // - Path validation against library roots
 // - Full Range header support (200/206/416)
 // - Concurrent stream limiting
 // - No sensitive data in logs
 // - Efficient file seeking (only reads requested bytes, not entire file)
+// - Whole-file downloads for offline use (Bearer-token auth, Content-Disposition)
+// - Errors use the API's { code, message } envelope
 
 use axum::{
     Router,
     body::Body,
-    extract::{Path, Query, State},
+    extract::{ConnectInfo, Path, Query, State},
     http::{HeaderMap, HeaderValue, Response, StatusCode},
+    response::IntoResponse,
     routing::get,
 };
 use std::io::{Read, Seek, SeekFrom};
+use std::net::SocketAddr;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use super::CompanionServerState;
+use super::error::ApiError;
+use crate::audio::mime::file_mime_type;
 
 #[derive(serde::Deserialize)]
 pub struct StreamQuery {
@@ -34,87 +40,65 @@ impl Drop for StreamGuard {
     }
 }
 
+/// Audio streaming (unversioned: stream URLs are handed to audio elements and LAN players)
 pub fn stream_routes() -> Router<Arc<CompanionServerState>> {
     Router::new().route("/stream/{track_id}", get(stream_track))
 }
 
+/// Download routes, relative to the API version prefix (like routes::api_routes)
+pub fn download_routes() -> Router<Arc<CompanionServerState>> {
+    Router::new().route("/tracks/{track_id}/download", get(download_track))
+}
+
 async fn stream_track(
     State(state): State<Arc<CompanionServerState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     Path(track_id): Path<i64>,
     Query(query): Query<StreamQuery>,
     headers: HeaderMap,
-) -> Result<Response<Body>, StatusCode> {
+) -> Result<Response<Body>, ApiError> {
     // 1. Validate ticket (multi-use for Range requests — browser may seek/buffer)
-    let ticket = query.ticket.ok_or(StatusCode::UNAUTHORIZED)?;
+    let ticket = query.ticket.ok_or_else(|| {
+        eprintln!("[companion] Stream rejected: no ticket from {}", addr.ip());
+        ApiError::unauthorized("Missing stream ticket")
+    })?;
     let ticket_track_id = state
-        .validate_ticket(&ticket)
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+        .validate_ticket(&ticket, addr.ip())
+        .ok_or_else(|| {
+            eprintln!("[companion] Stream rejected: invalid ticket from {}", addr.ip());
+            ApiError::unauthorized("Invalid or expired stream ticket")
+        })?;
 
     // Ticket must match the requested track
     if ticket_track_id != track_id {
-        return Err(StatusCode::UNAUTHORIZED);
+        eprintln!(
+            "[companion] Stream rejected: ticket/track mismatch from {}",
+            addr.ip()
+        );
+        return Err(ApiError::unauthorized("Stream ticket is for another track"));
     }
 
     // 2. Check concurrent stream limit
     let current = state.active_streams.load(Ordering::Relaxed);
-    if current >= state.max_streams {
-        let mut resp = Response::new(Body::from("Too many active streams"));
-        *resp.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
-        resp.headers_mut()
-            .insert("Retry-After", HeaderValue::from_static("5"));
-        return Ok(resp);
+    if current >= state.max_streams.load(Ordering::Relaxed) {
+        return Err(ApiError::too_many_streams());
     }
 
     // Increment and create drop guard (decrements on function exit)
     state.active_streams.fetch_add(1, Ordering::Relaxed);
     let _stream_guard = StreamGuard(state.clone());
 
-    // 3. Look up file path from database
-    let file_path = {
-        let db_lock = state
-            .db
-            .lock()
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        let db = db_lock.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
-        let track = db.get_track(track_id).map_err(|_| StatusCode::NOT_FOUND)?;
-        track.file_path
-    };
-
-    // 4. Validate path is within a library root folder (canonicalized)
-    let canonical_path =
-        std::fs::canonicalize(&file_path).map_err(|_| StatusCode::NOT_FOUND)?;
-    let canonical_str = canonical_path.to_string_lossy().to_string();
-
-    let is_within_library = {
-        let folders = state
-            .library_folders
-            .lock()
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        folders.iter().any(|folder| {
-            if let Ok(canonical_folder) = std::fs::canonicalize(folder) {
-                canonical_str.starts_with(&canonical_folder.to_string_lossy().to_string())
-            } else {
-                false
-            }
-        })
-    };
-
-    if !is_within_library {
-        eprintln!(
-            "[companion] Stream rejected: track {} not within library roots",
-            track_id
-        );
-        return Err(StatusCode::FORBIDDEN);
-    }
+    // 3-4. Look up file path and validate it is within a library root
+    let canonical_path = resolve_track_file(&state, track_id)?;
 
     // 5. Open file and get total size (without reading entire file into memory)
-    let mut file =
-        std::fs::File::open(&canonical_path).map_err(|_| StatusCode::NOT_FOUND)?;
+    let mut file = std::fs::File::open(&canonical_path)
+        .map_err(|_| ApiError::not_found("Audio file not found"))?;
     let metadata = file
         .metadata()
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     let total_len = metadata.len() as usize;
-    let mime = audio_mime_type(&canonical_str);
+    let mime = file_mime_type(&canonical_path);
 
     // Log without sensitive info
     eprintln!(
@@ -144,20 +128,24 @@ async fn stream_track(
 
             Response::builder()
                 .status(StatusCode::PARTIAL_CONTENT)
-                .header("Content-Type", mime)
+                .header("Content-Type", mime.as_str())
                 .header("Content-Length", read_len.to_string())
                 .header("Accept-Ranges", "bytes")
                 .header("Content-Range", content_range)
                 .header("Referrer-Policy", "no-referrer")
                 .header("Cache-Control", "no-store")
                 .body(Body::from(buf))
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into())
         }
         None => {
             // If Range header was present but unparseable → 416
             if range_header.is_some() {
-                let mut resp = Response::new(Body::empty());
-                *resp.status_mut() = StatusCode::RANGE_NOT_SATISFIABLE;
+                let mut resp = ApiError::new(
+                    StatusCode::RANGE_NOT_SATISFIABLE,
+                    "range_not_satisfiable",
+                    "Requested range is outside the file",
+                )
+                .into_response();
                 resp.headers_mut().insert(
                     "Content-Range",
                     HeaderValue::from_str(&format!("bytes */{}", total_len))
@@ -173,17 +161,126 @@ async fn stream_track(
 
             Response::builder()
                 .status(StatusCode::OK)
-                .header("Content-Type", mime)
+                .header("Content-Type", mime.as_str())
                 .header("Content-Length", total_len.to_string())
                 .header("Accept-Ranges", "bytes")
                 .header("Referrer-Policy", "no-referrer")
                 .header("Cache-Control", "no-store")
                 .body(Body::from(buf))
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into())
         }
     }
 }
 
+/// Look up a track's file and make sure it lives inside a library root folder
+/// (canonicalized, so symlinks and ".." can't escape). Returns the canonical path.
+fn resolve_track_file(
+    state: &CompanionServerState,
+    track_id: i64,
+) -> Result<std::path::PathBuf, ApiError> {
+    let file_path = {
+        let db_lock = state
+            .db
+            .lock()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let db = db_lock.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+        let track = db.get_track(track_id).map_err(|_| ApiError::not_found("Track not found"))?;
+        track.file_path
+    };
+
+    let canonical_path = std::fs::canonicalize(&file_path)
+        .map_err(|_| ApiError::not_found("Audio file not found"))?;
+    let canonical_str = canonical_path.to_string_lossy().to_string();
+
+    let is_within_library = {
+        let folders = state
+            .library_folders
+            .lock()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        folders.iter().any(|folder| {
+            if let Ok(canonical_folder) = std::fs::canonicalize(folder) {
+                canonical_str.starts_with(&canonical_folder.to_string_lossy().to_string())
+            } else {
+                false
+            }
+        })
+    };
+
+    if !is_within_library {
+        eprintln!(
+            "[companion] Access rejected: track {} not within library roots",
+            track_id
+        );
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "forbidden",
+            "Track is outside the shared library folders",
+        ));
+    }
+
+    Ok(canonical_path)
+}
+
+/// Download a whole track for offline use in the PWA.
+/// Unlike /stream, this sits under /api and requires the paired device's Bearer token
+/// (fetched with Authorization, then cached client-side). The original file is served
+/// as an attachment — no transcoding, so the cached copy is bit-identical.
+async fn download_track(
+    State(state): State<Arc<CompanionServerState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(track_id): Path<i64>,
+) -> Result<Response<Body>, ApiError> {
+    // Downloads share the concurrent stream limit
+    let current = state.active_streams.load(Ordering::Relaxed);
+    if current >= state.max_streams.load(Ordering::Relaxed) {
+        return Err(ApiError::too_many_streams());
+    }
+    state.active_streams.fetch_add(1, Ordering::Relaxed);
+    let _stream_guard = StreamGuard(state.clone());
+
+    let canonical_path = resolve_track_file(&state, track_id)?;
+    let buf = tokio::fs::read(&canonical_path)
+        .await
+        .map_err(|_| ApiError::not_found("Audio file not found"))?;
+    let mime = file_mime_type(&canonical_path);
+    let filename = canonical_path
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_else(|| format!("track-{}", track_id));
+
+    // Log without sensitive info
+    eprintln!(
+        "[companion] Download of track {} ({} bytes) by {}",
+        track_id,
+        buf.len(),
+        addr.ip()
+    );
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", mime.as_str())
+        .header("Content-Length", buf.len().to_string())
+        .header("Content-Disposition", content_disposition(&filename))
+        .header("Referrer-Policy", "no-referrer")
+        .header("Cache-Control", "private, no-store")
+        .body(Body::from(buf))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into())
+}
+
+/// Build an attachment Content-Disposition (RFC 6266): an ASCII fallback
+/// filename plus the exact UTF-8 name percent-encoded in filename*.
+fn content_disposition(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| if (c.is_ascii_graphic() && c != '"' && c != '\\') || c == ' ' { c } else { '_' })
+        .collect();
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        fallback,
+        super::percent_encode(filename)
+    )
+}
+
 /// Parse Range header (e.g. "bytes=0-1023" or "bytes=0-")
 fn parse_range(range_header: &str, total_len: usize) -> Option<(usize, usize)> {
     let range_header = range_header.trim();
@@ -210,21 +307,18 @@ fn parse_range(range_header: &str, total_len: usize) -> Option<(usize, usize)> {
     Some((start, end.min(total_len)))
 }
 
-/// Get MIME type for an audio file based on its extension
-fn audio_mime_type(path: &str) -> &'static str {
-    match std::path::Path::new(path)
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|s| s.to_lowercase())
-        .as_deref()
-    {
-        Some("mp3") => "audio/mpeg",
-        Some("flac") => "audio/flac",
-        Some("wav") => "audio/wav",
-        Some("ogg") => "audio/ogg",
-        Some("m4a") => "audio/mp4",
-        Some("aac") => "audio/aac",
-        Some("aiff") | Some("aif") => "audio/aiff",
-        _ => "application/octet-stream",
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_disposition() {
+        assert_eq!(
+            content_disposition("Track 01.mp3"),
+            "attachment; filename=\"Track 01.mp3\"; filename*=UTF-8''Track%2001.mp3"
+        );
+        let header = content_disposition("Beyoncé \"Live\".flac");
+        assert!(header.starts_with("attachment; filename=\"Beyonc_ _Live_.flac\""));
+        assert!(header.ends_with("filename*=UTF-8''Beyonc%C3%A9%20%22Live%22.flac"));
     }
 }
@@ -0,0 +1,99 @@
+// Library sync endpoints (Bearer-token auth like the rest of the API)
+// GET  /sync/snapshot — this library's metadata (see crate::sync)
+// POST /sync/snapshot — merge the caller's snapshot into this library, returns SyncStats
+//   (refused in browse mode)
+// A peer instance pairs with a pairing code first, exactly like the PWA.
+
+use axum::{
+    Json, Router,
+    extract::{DefaultBodyLimit, State},
+    http::StatusCode,
+    routing::get,
+};
+use std::sync::Arc;
+
+use super::CompanionServerState;
+use super::error::ApiError;
+use crate::sync::{self, SyncSnapshot, SyncStats};
+
+/// Snapshots of large libraries exceed axum's 2 MB default body limit
+const MAX_SNAPSHOT_BYTES: usize = 64 * 1024 * 1024;
+
+pub fn sync_routes() -> Router<Arc<CompanionServerState>> {
+    Router::new().route(
+        "/sync/snapshot",
+        get(get_snapshot)
+            .post(apply_snapshot)
+            .layer(DefaultBodyLimit::max(MAX_SNAPSHOT_BYTES)),
+    )
+}
+
+/// Run `f` on the server's database connection off the async runtime
+/// (hashing pending tracks can take a while)
+async fn with_db<T: Send + 'static>(
+    state: Arc<CompanionServerState>,
+    f: impl FnOnce(&crate::db::Database) -> Result<T, String> + Send + 'static,
+) -> Result<T, ApiError> {
+    tokio::task::spawn_blocking(move || {
+        let db_lock = state.db.lock().map_err(|_| ApiError::from(StatusCode::INTERNAL_SERVER_ERROR))?;
+        let db = db_lock.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+        f(db).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "sync_failed", e))
+    })
+    .await
+    .map_err(|_| ApiError::from(StatusCode::INTERNAL_SERVER_ERROR))?
+}
+
+async fn get_snapshot(
+    State(state): State<Arc<CompanionServerState>>,
+) -> Result<Json<SyncSnapshot>, ApiError> {
+    with_db(state, sync::build_snapshot).await.map(Json)
+}
+
+async fn apply_snapshot(
+    State(state): State<Arc<CompanionServerState>>,
+    Json(snapshot): Json<SyncSnapshot>,
+) -> Result<Json<SyncStats>, ApiError> {
+    // auth_middleware refuses mutations in browse mode already; this route must never
+    // write, whichever way it's reached
+    if state.is_read_only() {
+        return Err(ApiError::read_only());
+    }
+    if snapshot.format != sync::SYNC_FORMAT {
+        return Err(ApiError::bad_request(format!(
+            "Unsupported sync format {} (this version uses {})",
+            snapshot.format,
+            sync::SYNC_FORMAT
+        )));
+    }
+    let stats = with_db(state.clone(), move |db| sync::apply_snapshot(db, &snapshot)).await?;
+    eprintln!(
+        "[companion] Sync applied: {} tracks, {} cue sets, {} playlists created, {} updated",
+        stats.tracks_updated, stats.cues_updated, stats.playlists_created, stats.playlists_updated
+    );
+    state.notify_synced();
+    Ok(Json(stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use std::sync::atomic::Ordering;
+
+    #[tokio::test]
+    async fn test_apply_snapshot_refused_in_browse_mode() {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        let state = Arc::new(super::super::tests::test_state());
+        *state.db.lock().unwrap() = Some(db);
+        let snapshot = || SyncSnapshot { format: sync::SYNC_FORMAT, tracks: Vec::new(), playlists: Vec::new() };
+
+        state.read_only.store(true, Ordering::Relaxed);
+        let error = apply_snapshot(State(state.clone()), Json(snapshot())).await.unwrap_err();
+        assert_eq!(error.status, StatusCode::FORBIDDEN);
+        assert_eq!(error.code, "read_only");
+
+        state.read_only.store(false, Ordering::Relaxed);
+        assert!(apply_snapshot(State(state), Json(snapshot())).await.is_ok());
+    }
+}
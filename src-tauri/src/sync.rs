@@ -0,0 +1,372 @@
+// Library sync between two RecoDeck instances (e.g. studio desktop and laptop)
+// Only metadata travels, never audio: ratings, colors, comments, genre and energy per
+// track, cue points, and playlists. Tracks are matched by content hash, playlists by
+// their sync_id. For each item the side with the newer updated_at wins (see migration
+// 019). Deletions are not synced: a playlist deleted on one machine survives on the other.
+// Transport: the companion server's /sync/snapshot endpoints (server/sync.rs).
+
+use crate::db::{CuePoint, Database, PlaylistSyncState, TrackSyncState};
+use crate::scanner::Scanner;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Snapshot format version, bumped on incompatible changes
+pub const SYNC_FORMAT: u32 = 1;
+
+/// Everything one instance sends the other
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncSnapshot {
+    pub format: u32,
+    pub tracks: Vec<SyncTrack>,
+    pub playlists: Vec<SyncPlaylist>,
+}
+
+/// A track's user metadata and cues, identified by content hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncTrack {
+    pub hash: String,
+    pub rating: i32,
+    pub color: Option<String>,
+    pub comment: Option<String>,
+    pub genre: Option<String>,
+    pub genre_source: Option<String>,
+    pub energy_level: Option<i32>,
+    pub metadata_updated_at: Option<String>,
+    /// Present when cues_updated_at is (an empty list means "all cues removed")
+    pub cues: Option<Vec<SyncCue>>,
+    pub cues_updated_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncCue {
+    pub position_ms: i64,
+    pub label: Option<String>,
+    pub color: Option<String>,
+    pub cue_type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncPlaylist {
+    pub sync_id: String,
+    pub name: String,
+    pub playlist_type: String,
+    pub parent_sync_id: Option<String>,
+    pub smart_rules: Option<String>,
+    pub ai_prompt: Option<String>,
+    pub updated_at: Option<String>,
+    pub track_hashes: Vec<String>,
+}
+
+/// What applying a snapshot changed
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncStats {
+    pub tracks_updated: usize,
+    pub cues_updated: usize,
+    pub playlists_created: usize,
+    pub playlists_updated: usize,
+    /// Tracks in the snapshot that aren't in this library (their metadata is skipped)
+    pub unknown_tracks: usize,
+}
+
+/// True when `remote` is strictly newer than `local` (a timestamp beats none).
+/// Timestamps are SQLite datetime strings, which sort chronologically.
+fn is_newer(remote: Option<&str>, local: Option<&str>) -> bool {
+    match (remote, local) {
+        (Some(remote), Some(local)) => remote > local,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+/// Everything this library has to offer. Tracks never edited (no timestamps) are left out.
+pub fn build_snapshot(db: &Database) -> Result<SyncSnapshot, String> {
+    Scanner::hash_pending(db).map_err(|e| format!("Failed to hash tracks: {}", e))?;
+
+    let states = db.get_track_sync_states()
+        .map_err(|e| format!("Failed to read tracks: {}", e))?;
+    let mut tracks = Vec::new();
+    for state in states {
+        if state.metadata_updated_at.is_none() && state.cues_updated_at.is_none() {
+            continue;
+        }
+        let cues = match state.cues_updated_at {
+            Some(_) => Some(
+                db.get_cue_points(state.track_id)
+                    .map_err(|e| format!("Failed to read cues: {}", e))?
+                    .into_iter()
+                    .map(|c| SyncCue {
+                        position_ms: c.position_ms,
+                        label: c.label,
+                        color: c.color,
+                        cue_type: c.cue_type,
+                    })
+                    .collect(),
+            ),
+            None => None,
+        };
+        tracks.push(SyncTrack {
+            hash: state.file_hash,
+            rating: state.rating,
+            color: state.color,
+            comment: state.comment,
+            genre: state.genre,
+            genre_source: state.genre_source,
+            energy_level: state.energy_level,
+            metadata_updated_at: state.metadata_updated_at,
+            cues,
+            cues_updated_at: state.cues_updated_at,
+        });
+    }
+
+    let playlists = db.get_playlist_sync_states()
+        .map_err(|e| format!("Failed to read playlists: {}", e))?
+        .into_iter()
+        .map(|p| SyncPlaylist {
+            sync_id: p.sync_id,
+            name: p.name,
+            playlist_type: p.playlist_type,
+            parent_sync_id: p.parent_sync_id,
+            smart_rules: p.smart_rules,
+            ai_prompt: p.ai_prompt,
+            updated_at: p.updated_at,
+            track_hashes: p.track_hashes,
+        })
+        .collect();
+
+    Ok(SyncSnapshot {
+        format: SYNC_FORMAT,
+        tracks,
+        playlists,
+    })
+}
+
+/// Merge another instance's snapshot into this library, newest change winning.
+/// All or nothing.
+pub fn apply_snapshot(db: &Database, snapshot: &SyncSnapshot) -> Result<SyncStats, String> {
+    if snapshot.format != SYNC_FORMAT {
+        return Err(format!(
+            "Unsupported sync format {} (this version uses {})",
+            snapshot.format, SYNC_FORMAT
+        ));
+    }
+    Scanner::hash_pending(db).map_err(|e| format!("Failed to hash tracks: {}", e))?;
+
+    let mut by_hash: HashMap<String, Vec<TrackSyncState>> = HashMap::new();
+    for state in db.get_track_sync_states().map_err(|e| format!("Failed to read tracks: {}", e))? {
+        by_hash.entry(state.file_hash.clone()).or_default().push(state);
+    }
+    let mut stats = SyncStats::default();
+
+    let tx = db.begin_write().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+
+    for remote in &snapshot.tracks {
+        let locals = match by_hash.get(&remote.hash) {
+            Some(locals) => locals,
+            None => {
+                stats.unknown_tracks += 1;
+                continue;
+            }
+        };
+        // Duplicates (same content, several paths) all take the synced values
+        for local in locals {
+            if is_newer(remote.metadata_updated_at.as_deref(), local.metadata_updated_at.as_deref()) {
+                let state = TrackSyncState {
+                    track_id: local.track_id,
+                    file_hash: local.file_hash.clone(),
+                    rating: remote.rating,
+                    color: remote.color.clone(),
+                    comment: remote.comment.clone(),
+                    genre: remote.genre.clone(),
+                    genre_source: remote.genre_source.clone(),
+                    energy_level: remote.energy_level,
+                    metadata_updated_at: remote.metadata_updated_at.clone(),
+                    cues_updated_at: local.cues_updated_at.clone(),
+                };
+                db.apply_track_sync_metadata(local.track_id, &state)
+                    .map_err(|e| format!("Failed to update track {}: {}", local.track_id, e))?;
+                stats.tracks_updated += 1;
+            }
+            if let Some(cues) = &remote.cues {
+                if is_newer(remote.cues_updated_at.as_deref(), local.cues_updated_at.as_deref()) {
+                    let cues: Vec<CuePoint> = cues
+                        .iter()
+                        .map(|c| CuePoint {
+                            id: None,
+                            track_id: local.track_id,
+                            position_ms: c.position_ms,
+                            label: c.label.clone(),
+                            color: c.color.clone(),
+                            cue_type: c.cue_type.clone(),
+                        })
+                        .collect();
+                    db.apply_track_sync_cues(local.track_id, &cues, remote.cues_updated_at.as_deref())
+                        .map_err(|e| format!("Failed to update cues of track {}: {}", local.track_id, e))?;
+                    stats.cues_updated += 1;
+                }
+            }
+        }
+    }
+
+    // Playlists: parents arrive before children (see get_playlist_sync_states)
+    let local_playlists: HashMap<String, PlaylistSyncState> = db.get_playlist_sync_states()
+        .map_err(|e| format!("Failed to read playlists: {}", e))?
+        .into_iter()
+        .map(|p| (p.sync_id.clone(), p))
+        .collect();
+    // sync_id -> (local id, local parent sync_id), updated as playlists are written
+    let mut tree: HashMap<String, (i64, Option<String>)> = local_playlists
+        .values()
+        .map(|p| (p.sync_id.clone(), (p.id, p.parent_sync_id.clone())))
+        .collect();
+
+    for remote in &snapshot.playlists {
+        let local = local_playlists.get(&remote.sync_id);
+        if let Some(local) = local {
+            if !is_newer(remote.updated_at.as_deref(), local.updated_at.as_deref()) {
+                continue;
+            }
+        }
+
+        // Keep the local parent if the remote one is unknown here or would create a cycle
+        let parent_sync_id = match &remote.parent_sync_id {
+            Some(parent) if tree.contains_key(parent) && !is_descendant(&tree, parent, &remote.sync_id) => Some(parent.clone()),
+            Some(_) => local.and_then(|l| l.parent_sync_id.clone()),
+            None => None,
+        };
+        let parent_id = parent_sync_id.as_ref().and_then(|p| tree.get(p)).map(|(id, _)| *id);
+
+        let mut track_ids = Vec::new();
+        for hash in &remote.track_hashes {
+            if let Some(locals) = by_hash.get(hash) {
+                track_ids.push(locals[0].track_id);
+            }
+        }
+
+        let state = PlaylistSyncState {
+            id: local.map(|l| l.id).unwrap_or(0),
+            sync_id: remote.sync_id.clone(),
+            name: remote.name.clone(),
+            playlist_type: remote.playlist_type.clone(),
+            parent_sync_id: parent_sync_id.clone(),
+            smart_rules: remote.smart_rules.clone(),
+            ai_prompt: remote.ai_prompt.clone(),
+            updated_at: remote.updated_at.clone(),
+            track_hashes: Vec::new(),
+        };
+        let id = db.save_synced_playlist(local.map(|l| l.id), &state, parent_id, &track_ids)
+            .map_err(|e| format!("Failed to save playlist '{}': {}", remote.name, e))?;
+        tree.insert(remote.sync_id.clone(), (id, parent_sync_id));
+        if local.is_some() {
+            stats.playlists_updated += 1;
+        } else {
+            stats.playlists_created += 1;
+        }
+    }
+
+    tx.commit().map_err(|e| format!("Failed to commit sync: {}", e))?;
+    Ok(stats)
+}
+
+/// Whether `sync_id` is `ancestor` itself or one of its descendants in `tree`
+fn is_descendant(tree: &HashMap<String, (i64, Option<String>)>, sync_id: &str, ancestor: &str) -> bool {
+    let mut current = Some(sync_id.to_string());
+    let mut steps = 0;
+    while let Some(id) = current {
+        if id == ancestor {
+            return true;
+        }
+        steps += 1;
+        if steps > tree.len() {
+            return true;
+        }
+        current = tree.get(&id).and_then(|(_, parent)| parent.clone());
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{create_test_track, Track};
+
+    fn track(path: &str, hash: &str) -> Track {
+        Track { file_path: path.to_string(), file_hash: hash.to_string(), ..create_test_track() }
+    }
+
+    fn library(paths_and_hashes: &[(&str, &str)]) -> Database {
+        let db = Database::new_in_memory().unwrap();
+        db.run_migrations().unwrap();
+        for (path, hash) in paths_and_hashes {
+            db.create_track(&track(path, hash)).unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn test_sync_between_libraries() {
+        // Same files at different paths on the two machines
+        let studio = library(&[("/studio/a.mp3", "hash-a"), ("/studio/b.mp3", "hash-b")]);
+        let laptop = library(&[("/laptop/a.mp3", "hash-a"), ("/laptop/b.mp3", "hash-b")]);
+        let studio_a = studio.find_track_id_by_hash("hash-a").unwrap().unwrap();
+        let studio_b = studio.find_track_id_by_hash("hash-b").unwrap().unwrap();
+        let laptop_a = laptop.find_track_id_by_hash("hash-a").unwrap().unwrap();
+
+        studio.set_track_color(studio_a, Some("#FF0000")).unwrap();
+        studio.replace_cue_points(studio_a, &[CuePoint {
+            id: None,
+            track_id: studio_a,
+            position_ms: 32_000,
+            label: Some("Drop".to_string()),
+            color: None,
+            cue_type: "cue".to_string(),
+        }]).unwrap();
+        let folder = studio.create_playlist("Gigs", "folder", None).unwrap();
+        let set = studio.create_playlist("Warmup", "manual", Some(folder)).unwrap();
+        studio.add_track_to_playlist(set, studio_b).unwrap();
+        studio.add_track_to_playlist(set, studio_a).unwrap();
+
+        let stats = apply_snapshot(&laptop, &build_snapshot(&studio).unwrap()).unwrap();
+        assert_eq!(stats.tracks_updated, 1);
+        assert_eq!(stats.cues_updated, 1);
+        assert_eq!(stats.playlists_created, 2);
+
+        assert_eq!(laptop.get_track(laptop_a).unwrap().color.as_deref(), Some("#FF0000"));
+        assert_eq!(laptop.get_cue_points(laptop_a).unwrap()[0].label.as_deref(), Some("Drop"));
+        let playlists = laptop.get_all_playlists().unwrap();
+        let warmup = playlists.iter().find(|p| p.name == "Warmup").unwrap();
+        let gigs = playlists.iter().find(|p| p.name == "Gigs").unwrap();
+        assert_eq!(warmup.parent_id, gigs.id);
+        let paths: Vec<String> = laptop.get_playlist_tracks(warmup.id.unwrap()).unwrap()
+            .into_iter().map(|(t, ..)| t.file_path).collect();
+        assert_eq!(paths, vec!["/laptop/b.mp3", "/laptop/a.mp3"]);
+
+        // Syncing back changes nothing: timestamps match, so neither side is newer
+        let stats = apply_snapshot(&studio, &build_snapshot(&laptop).unwrap()).unwrap();
+        assert_eq!(stats, SyncStats::default());
+    }
+
+    #[test]
+    fn test_sync_newer_side_wins() {
+        let studio = library(&[("/studio/a.mp3", "hash-a")]);
+        let laptop = library(&[("/laptop/a.mp3", "hash-a")]);
+        let studio_a = studio.find_track_id_by_hash("hash-a").unwrap().unwrap();
+        let laptop_a = laptop.find_track_id_by_hash("hash-a").unwrap().unwrap();
+
+        studio.set_track_color(studio_a, Some("#FF0000")).unwrap();
+        laptop.set_track_color(laptop_a, Some("#00FF00")).unwrap();
+        // The laptop edit is later
+        studio.apply_track_sync_metadata(studio_a, &TrackSyncState {
+            metadata_updated_at: Some("2026-01-01 10:00:00".to_string()),
+            ..studio.get_track_sync_states().unwrap().remove(0)
+        }).unwrap();
+        laptop.apply_track_sync_metadata(laptop_a, &TrackSyncState {
+            metadata_updated_at: Some("2026-01-01 11:00:00".to_string()),
+            ..laptop.get_track_sync_states().unwrap().remove(0)
+        }).unwrap();
+
+        let stats = apply_snapshot(&laptop, &build_snapshot(&studio).unwrap()).unwrap();
+        assert_eq!(stats.tracks_updated, 0);
+        apply_snapshot(&studio, &build_snapshot(&laptop).unwrap()).unwrap();
+        assert_eq!(studio.get_track(studio_a).unwrap().color.as_deref(), Some("#00FF00"));
+    }
+}
@@ -17,7 +17,7 @@ import { PlayerAIChat } from "./components/ai/PlayerAIChat";
 import { Icon } from "./components/Icon";
 import { usePlayerStore } from "./store/playerStore";
 import { tauriApi } from "./lib/tauri-api";
-import type { Track, Playlist } from "./types/track";
+import type { Track, Playlist, TrackRenamedEvent } from "./types/track";
 import "./App.css";
 import "./components/TrackTable.css";
 
@@ -351,6 +351,22 @@ function AppContent() {
     };
   }, []);
 
+  // A track's file moved: reload so rows and stream URLs use the new path
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+
+    listen<TrackRenamedEvent>("track-renamed", async (event) => {
+      console.log(`Track ${event.payload.track_id} moved to ${event.payload.new_path}`);
+      await loadTracksRef.current();
+    }).then((fn) => {
+      unlisten = fn;
+    });
+
+    return () => {
+      unlisten?.();
+    };
+  }, []);
+
   function hexToRgb(hex: string): string {
     const m = hex.match(/^#?([a-f\d]{2})([a-f\d]{2})([a-f\d]{2})$/i);
     if (!m) return "99, 102, 241";
@@ -470,7 +486,9 @@ function AppContent() {
       setAnalysisCancelled(false);
       setError(null);
 
-      const folderTracks = await tauriApi.getTracksInFolder(folderPath);
+      const folderTracks = await tauriApi.getTracksInFolder(folderPath, [
+        "file_path", "title", "duration_ms", "file_size", "bpm", "musical_key",
+      ]);
       
       // Filter tracks that need analysis
       const tracksToAnalyze = folderTracks.filter(
@@ -772,10 +790,44 @@ function AppContent() {
     }
   }
 
+  // Analyze a playlist's tracks in the background, ahead of the auto-analysis backlog
+  async function handleAnalyzePlaylist(id: number, name: string) {
+    try {
+      const queued = await tauriApi.analyzePlaylist(id);
+      setNotification({
+        message:
+          queued === 0
+            ? `All tracks in "${name}" are already analyzed`
+            : `Analyzing ${queued} track${queued === 1 ? "" : "s"} from "${name}" in the background`,
+        type: "info",
+      });
+    } catch (err) {
+      setError(err instanceof Error ? err.message : String(err));
+    }
+  }
+
+  // Add a track to a playlist, asking first if another version of the song is already in it.
+  // Returns false if the user declined.
+  async function addTrackCheckingVersions(trackId: number, playlistId: number): Promise<boolean> {
+    const result = await tauriApi.addTrackToPlaylist(playlistId, trackId, true);
+    if (!result.possible_duplicate) return true;
+
+    const versions = result.duplicate_versions
+      .map((v) => `• ${v.artist ?? "Unknown"} – ${v.title ?? "Untitled"}`)
+      .join("\n");
+    const confirmed = await confirm(
+      `This playlist already has another version of this song:\n${versions}\n\nAdd it anyway?`,
+      { title: "Possible duplicate", kind: "warning" }
+    );
+    if (!confirmed) return false;
+    await tauriApi.addTrackToPlaylist(playlistId, trackId);
+    return true;
+  }
+
   // Add track to playlist
   async function handleAddToPlaylist(track: Track, playlistId: number) {
     try {
-      await tauriApi.addTrackToPlaylist(playlistId, track.id);
+      if (!(await addTrackCheckingVersions(track.id, playlistId))) return;
       await loadPlaylists(); // Refresh playlist counts
       const playlist = playlists.find((p) => p.id === playlistId);
       setHeaderNotification(`Added to ${playlist?.name ?? "playlist"}`);
@@ -1145,6 +1197,7 @@ function AppContent() {
             onFolderSelect={handleFolderSelect}
             onPlaylistSelect={handlePlaylistSelect}
             onAnalyzeFolder={handleAnalyzeFolder}
+            onAnalyzePlaylist={handleAnalyzePlaylist}
             onAnalyzeAll={handleAnalyzeAll}
             onCreatePlaylist={handleCreatePlaylist}
             onCreateFolder={handleCreateFolder}
@@ -1191,7 +1244,7 @@ function AppContent() {
         onTrackMetaClick={handleScrollToCurrentTrack}
         onAddToPlaylist={async (trackId, playlistId) => {
           try {
-            await tauriApi.addTrackToPlaylist(playlistId, trackId);
+            if (!(await addTrackCheckingVersions(trackId, playlistId))) return;
             await loadPlaylists();
             const playlist = playlists.find((p) => p.id === playlistId);
             setHeaderNotification(`Added to ${playlist?.name ?? "playlist"}`);
@@ -1212,6 +1265,7 @@ function AppContent() {
         onThemeChanged={handleThemeChanged}
         onKeyNotationChanged={handleKeyNotationChanged}
         onWaveformStyleChanged={handleWaveformStyleChanged}
+        onKeyColorsChanged={() => loadTracksRef.current()}
         onNotification={(message, type) => setNotification({ message, type })}
       />
 
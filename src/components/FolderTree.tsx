@@ -20,6 +20,7 @@ interface FolderTreeProps {
   onFolderSelect: (folderPath: string | null) => void;
   onPlaylistSelect: (playlistId: number) => void;
   onAnalyzeFolder: (folderPath: string) => void;
+  onAnalyzePlaylist: (id: number, name: string) => void;
   onAnalyzeAll: () => void;
   onCreatePlaylist: (parentId: number | null) => void;
   onCreateFolder: (parentId: number | null) => void;
@@ -136,6 +137,7 @@ export function FolderTree({
   onFolderSelect,
   onPlaylistSelect,
   onAnalyzeFolder,
+  onAnalyzePlaylist,
   onAnalyzeAll,
   onCreatePlaylist,
   onCreateFolder,
@@ -651,6 +653,19 @@ export function FolderTree({
                 <Icon name="FolderPlus" size={16} className="context-menu-icon" />
                 Create Folder
               </div>
+              <div
+                className="context-menu-item"
+                onClick={() => {
+                  onAnalyzePlaylist(
+                    contextMenu.playlistId!,
+                    contextMenu.playlistName!
+                  );
+                  closeContextMenu();
+                }}
+              >
+                <Icon name="Zap" size={16} className="context-menu-icon" />
+                Analyze Playlist
+              </div>
               <div className="context-menu-separator" />
               <div
                 className="context-menu-item"
@@ -9,6 +9,7 @@ import { check } from "@tauri-apps/plugin-updater";
 import { relaunch } from "@tauri-apps/plugin-process";
 import { QRCodeSVG } from "qrcode.react";
 import { tauriApi } from "../lib/tauri-api";
+import type { KeyColor, SymlinkMode } from "../types/track";
 import { useAIStore } from "../store/aiStore";
 import { Icon } from "./Icon";
 import "./Settings.css";
@@ -20,6 +21,7 @@ interface SettingsProps {
   onThemeChanged: (theme: string, customColors?: Record<string, string>) => void;
   onKeyNotationChanged?: (notation: string) => void;
   onWaveformStyleChanged?: (style: string) => void;
+  onKeyColorsChanged?: () => void;
   onNotification?: (message: string, type: "info" | "success" | "warning" | "error") => void;
 }
 
@@ -66,12 +68,14 @@ const WAVEFORM_STYLES = [
 
 type SettingsTab = 'library' | 'appearance' | 'audio' | 'database' | 'ai' | 'companion' | 'app';
 
-export function Settings({ isOpen, onClose, onFoldersChanged, onThemeChanged, onKeyNotationChanged, onWaveformStyleChanged, onNotification }: SettingsProps) {
+export function Settings({ isOpen, onClose, onFoldersChanged, onThemeChanged, onKeyNotationChanged, onWaveformStyleChanged, onKeyColorsChanged, onNotification }: SettingsProps) {
   const [activeTab, setActiveTab] = useState<SettingsTab>('library');
   const [folders, setFolders] = useState<string[]>([]);
+  const [symlinkModes, setSymlinkModes] = useState<Record<string, SymlinkMode>>({});
   const [currentTheme, setCurrentTheme] = useState("midnight");
   const [customColors, setCustomColors] = useState<Record<string, string>>(DEFAULT_CUSTOM_COLORS);
   const [keyNotation, setKeyNotation] = useState("camelot");
+  const [keyColors, setKeyColors] = useState<KeyColor[]>([]);
   const [waveformStyle, setWaveformStyle] = useState("traktor_rgb");
   const [crossfadeEnabled, setCrossfadeEnabled] = useState(false);
   const [crossfadeDuration, setCrossfadeDuration] = useState(8);
@@ -124,6 +128,10 @@ export function Settings({ isOpen, onClose, onFoldersChanged, onThemeChanged, on
         tauriApi.getSetting("crossfade_duration_sec").catch(() => "8"),
         tauriApi.getCustomThemeColors().catch(() => null),
       ]);
+      tauriApi.getKeyColors().then(setKeyColors).catch(() => {});
+      tauriApi.getFolderSymlinkModes()
+        .then((modes) => setSymlinkModes(Object.fromEntries(modes.map((m) => [m.folder, m.mode]))))
+        .catch(() => {});
 
       // Check AI API key status
       await checkApiKeyStatus();
@@ -230,7 +238,20 @@ export function Settings({ isOpen, onClose, onFoldersChanged, onThemeChanged, on
 
       // Clean up tracks from the removed folder (keeps playlists intact)
       try {
-        const removed = await tauriApi.cleanupStrayTracks();
+        const preview = await tauriApi.previewStrayTracksCleanup();
+        // Without any folders left, every track counts as stray: ask once more
+        const force = preview.requires_force && await ask(
+          `No library folders are left. Remove all ${preview.count} tracks from your library?`,
+          {
+            title: "Remove All Tracks",
+            kind: "warning",
+            okLabel: "Remove All",
+            cancelLabel: "Keep Tracks",
+          }
+        );
+        const removed = preview.count > 0 && (!preview.requires_force || force)
+          ? await tauriApi.cleanupStrayTracks(preview.confirm_token, force)
+          : 0;
         if (removed > 0) {
           console.log(`Removed ${removed} tracks from removed folder`);
           onNotification?.(
@@ -258,6 +279,12 @@ export function Settings({ isOpen, onClose, onFoldersChanged, onThemeChanged, on
 
       const result = await tauriApi.scanDirectory(path);
       console.log("Rescan result:", result);
+      if (result.symlinks_skipped.length > 0) {
+        onNotification?.(
+          `Skipped ${result.symlinks_skipped.length} symlink${result.symlinks_skipped.length > 1 ? "s" : ""} (not followed or already scanned)`,
+          "info"
+        );
+      }
 
       setScanningFolder(null);
       onFoldersChanged();
@@ -267,6 +294,17 @@ export function Settings({ isOpen, onClose, onFoldersChanged, onThemeChanged, on
     }
   }
 
+  async function handleToggleSymlinks(folder: string) {
+    const mode: SymlinkMode = symlinkModes[folder] === "skip" ? "follow" : "skip";
+    try {
+      setError(null);
+      await tauriApi.setFolderSymlinkMode(folder, mode);
+      setSymlinkModes({ ...symlinkModes, [folder]: mode });
+    } catch (err) {
+      setError(err instanceof Error ? err.message : String(err));
+    }
+  }
+
   async function handleRescanAll() {
     try {
       setError(null);
@@ -377,6 +415,30 @@ export function Settings({ isOpen, onClose, onFoldersChanged, onThemeChanged, on
     onNotification?.("Custom colors reset to default", "info");
   }
 
+  async function saveKeyColors(colors: Record<string, string>) {
+    try {
+      setError(null);
+      setKeyColors(await tauriApi.setKeyColors(colors));
+      onKeyColorsChanged?.();
+    } catch (err) {
+      setError(err instanceof Error ? err.message : String(err));
+    }
+  }
+
+  // The picker reports every step of a drag: show them, save when it closes
+  function handleKeyColorChange(key: string, color: string) {
+    setKeyColors(keyColors.map((k) => (k.key === key ? { ...k, color } : k)));
+  }
+
+  function handleKeyColorCommit() {
+    saveKeyColors(Object.fromEntries(keyColors.map((k) => [k.key, k.color])));
+  }
+
+  async function handleResetKeyColors() {
+    await saveKeyColors({});
+    onNotification?.("Key colors reset to default", "info");
+  }
+
   async function handleKeyNotationChange(notation: string) {
     try {
       setError(null);
@@ -675,6 +737,16 @@ export function Settings({ isOpen, onClose, onFoldersChanged, onThemeChanged, on
                     <span className="folder-scanning">Scanning...</span>
                   ) : (
                     <>
+                      <button
+                        className="btn-icon"
+                        onClick={() => handleToggleSymlinks(folder)}
+                        title={symlinkModes[folder] === "skip"
+                          ? "Symlinks are skipped — click to follow them"
+                          : "Symlinks are followed — click to skip them"}
+                        disabled={loading}
+                      >
+                        <Icon name={symlinkModes[folder] === "skip" ? "Unlink" : "Link"} size={16} />
+                      </button>
                       <button
                         className="btn-icon"
                         onClick={() => handleRescanFolder(folder)}
@@ -787,6 +859,39 @@ export function Settings({ isOpen, onClose, onFoldersChanged, onThemeChanged, on
         </div>
       </section>
 
+      <section className="settings-section">
+        <h4 className="settings-subsection-title">Key Colors</h4>
+        <p className="settings-hint settings-custom-colors-hint">
+          Shown next to keys in the track list and in exported tracklists.
+        </p>
+        <div className="custom-colors-grid">
+          {keyColors.map((keyColor) => (
+            <div key={keyColor.key} className="custom-color-row">
+              <label className="custom-color-label">{keyColor.key}</label>
+              <div className="custom-color-input-wrap">
+                <input
+                  type="color"
+                  value={keyColor.color}
+                  onChange={(e) => handleKeyColorChange(keyColor.key, e.target.value)}
+                  onBlur={handleKeyColorCommit}
+                  className="custom-color-swatch"
+                />
+              </div>
+            </div>
+          ))}
+        </div>
+        <div className="custom-colors-actions">
+          <button
+            type="button"
+            className="btn-secondary btn-small"
+            onClick={handleResetKeyColors}
+            disabled={keyColors.every((k) => k.is_default)}
+          >
+            Reset to Default
+          </button>
+        </div>
+      </section>
+
       <section className="settings-section">
         <h4 className="settings-subsection-title">Waveform Style</h4>
         <div className="key-notation-list">
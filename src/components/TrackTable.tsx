@@ -6,6 +6,7 @@ import { useVirtualizer } from "@tanstack/react-virtual";
 import { useRef, useState, useMemo, useEffect, useImperativeHandle, forwardRef } from "react";
 import type { Track, Playlist } from "../types/track";
 import { usePlayerStore } from "../store/playerStore";
+import { tauriApi } from "../lib/tauri-api";
 import { Icon } from "./Icon";
 
 // --- Sort types ---
@@ -94,6 +95,9 @@ export const TrackTable = forwardRef<TrackTableRef, TrackTableProps>(function Tr
 
   // Search state
   const [searchQuery, setSearchQuery] = useState("");
+  // Tracks the backend matches for the query by transliterated text ("Zhu" finds "Жу",
+  // "deja vu" finds "Déjà Vu"), which the plain substring filter below can't
+  const [transliteratedIds, setTransliteratedIds] = useState<Set<number>>(new Set());
 
   // Context menu (right-click on track row)
   const [contextMenu, setContextMenu] = useState<{
@@ -202,6 +206,27 @@ export const TrackTable = forwardRef<TrackTableRef, TrackTableProps>(function Tr
     direction: "asc",
   });
 
+  useEffect(() => {
+    const query = searchQuery.trim();
+    if (!query) {
+      setTransliteratedIds(new Set());
+      return;
+    }
+    let cancelled = false;
+    const timer = window.setTimeout(() => {
+      tauriApi
+        .searchTrackIds(query)
+        .then((ids) => {
+          if (!cancelled) setTransliteratedIds(new Set(ids));
+        })
+        .catch((e) => console.error("Search failed:", e));
+    }, 250);
+    return () => {
+      cancelled = true;
+      window.clearTimeout(timer);
+    };
+  }, [searchQuery]);
+
   // --- Search: filter tracks by query across all text fields ---
   const filteredTracks = useMemo(() => {
     if (!searchQuery.trim()) return tracks;
@@ -209,6 +234,7 @@ export const TrackTable = forwardRef<TrackTableRef, TrackTableProps>(function Tr
     const query = searchQuery.toLowerCase().trim();
 
     return tracks.filter((track) => {
+      if (transliteratedIds.has(track.id)) return true;
       const fields = [
         track.title,
         track.artist,
@@ -222,7 +248,7 @@ export const TrackTable = forwardRef<TrackTableRef, TrackTableProps>(function Tr
         (field) => field != null && field.toLowerCase().includes(query)
       );
     });
-  }, [tracks, searchQuery]);
+  }, [tracks, searchQuery, transliteratedIds]);
 
   // --- Sort: order filtered tracks by selected column ---
   const sortedTracks = useMemo(() => {
@@ -548,6 +574,7 @@ export const TrackTable = forwardRef<TrackTableRef, TrackTableProps>(function Tr
                   {track.bpm ? track.bpm.toFixed(2) : "—"}
                 </div>
                 <div className="table-cell cell-key" title={track.key_confidence != null ? `${track.musical_key ?? "—"} (${Math.round((track.key_confidence ?? 0) * 100)}%)` : undefined}>
+                  {track.key_color && <span className="key-swatch" style={{ background: track.key_color }} />}
                   {track.musical_key ? (keyNotation === "openkey" ? camelotToOpenKey(track.musical_key) : track.musical_key) : "—"}
                 </div>
                 <div className="table-cell cell-genre" title={track.genre}>
@@ -259,6 +259,8 @@ export class AudioPlayer {
         position_ms: number;
         duration_ms: number;
         is_end: boolean;
+        /** "i16" when the stream is configured for 16-bit samples */
+        sample_format?: 'i16';
       };
 
       if (this.mode !== 'native') return;
@@ -282,9 +284,10 @@ export class AudioPlayer {
       const buffer = this.audioCtx.createBuffer(2, frames, chunk.sample_rate || 44100);
       const left = buffer.getChannelData(0);
       const right = buffer.getChannelData(1);
+      const scale = chunk.sample_format === 'i16' ? 1 / 32767 : 1;
       for (let i = 0, j = 0; i < frames; i++, j += 2) {
-        left[i] = chunk.samples[j] ?? 0;
-        right[i] = chunk.samples[j + 1] ?? left[i];
+        left[i] = (chunk.samples[j] ?? 0) * scale;
+        right[i] = chunk.samples[j + 1] !== undefined ? chunk.samples[j + 1] * scale : left[i];
       }
 
       const source = this.audioCtx.createBufferSource();
@@ -1104,9 +1107,6 @@ export class AudioPlayer {
     await this.ensureNativeAudio();
     if (!this.audioCtx) return;
 
-    // Capture playing state before stopping sources
-    const wasPlaying = this._isPlaying;
-
     // Stop currently scheduled audio and reset scheduling.
     this.stopNativeSources();
     this.nativeNextStartTime = this.audioCtx.currentTime;
@@ -1116,15 +1116,9 @@ export class AudioPlayer {
     this.onPositionUpdate?.(this._position);
     this.nativeEndedEmitted = false;
 
-    // AWAIT seek completion (backend increments generation to cancel old task)
+    // AWAIT seek completion (backend cancels the old task and, when it was playing,
+    // restarts streaming from the new position)
     await tauriApi.playbackSeek(Math.floor(positionMs));
-
-    // Only resume if we were playing AND seek succeeded
-    if (wasPlaying) {
-      // Small delay to ensure backend task cancellation propagated
-      await new Promise(resolve => setTimeout(resolve, 100));
-      await tauriApi.playbackResume();
-    }
   }
 
   private async nativeStop(): Promise<void> {
@@ -25,6 +25,20 @@ export interface MobileTrack {
   filename: string;
   bpm?: number;
   musical_key?: string;
+  /** Volume normalization gain (dB) to -18 LUFS; null until the loudness is analyzed */
+  gain_db?: number | null;
+}
+
+/** GET /api/v1/tracks/summary — just the list view's fields (feature "track_summaries") */
+export interface MobileTrackSummary {
+  id: number;
+  title?: string;
+  artist?: string;
+  bpm?: number;
+  musical_key?: string;
+  duration_ms?: number;
+  color?: string;
+  rating: number;
 }
 
 interface ServerStatus {
@@ -39,8 +53,48 @@ interface StreamTicketResponse {
   stream_url: string;
 }
 
+/** GET /api/v1/capabilities — API version and enabled features of the desktop */
+export interface ServerCapabilities {
+  name: string;
+  version: string;
+  api_version: number;
+  api_versions: number[];
+  features: string[];
+}
+
+/** Pushed on GET /api/v1/events (feature "events"). "lagged": some events were
+ *  missed, reload what's shown; "server_stopping": the desktop is shutting the server down */
+export type CompanionEvent =
+  | { type: "track_analyzed"; track_id: number; error: string | null }
+  | { type: "waveform_ready"; track_id: number; error: string | null }
+  | { type: "lagged"; missed: number }
+  | { type: "server_stopping" };
+
+/** Wait before reconnecting to /events after the stream ended or failed */
+const EVENTS_RETRY_MS = 5000;
+
+/** Error from the server's { code, message } envelope */
+export class ApiError extends Error {
+  constructor(public status: number, public code: string, message: string) {
+    super(message);
+    this.name = "ApiError";
+  }
+}
+
+/** What desktops from before API versioning offer (they only serve the unversioned /api) */
+const LEGACY_CAPABILITIES: ServerCapabilities = {
+  name: "RecoDeck",
+  version: "unknown",
+  api_version: 0,
+  api_versions: [],
+  features: ["streaming", "downloads", "remote_control", "m3u8_playlists", "pairing"],
+};
+
 let _baseUrl = "";
 let _token = "";
+/** /api/v1, or /api against older desktops (see loadCapabilities) */
+let _apiPrefix = "/api";
+let _capabilities: ServerCapabilities = LEGACY_CAPABILITIES;
 
 /** Convert MobileTrack to Track interface (filling in missing fields with defaults) */
 function mobileTrackToTrack(mt: MobileTrack): Track {
@@ -68,8 +122,38 @@ function mobileTrackToTrack(mt: MobileTrack): Track {
   };
 }
 
+/** Convert a track summary to the Track interface; the other fields stay empty */
+function summaryToTrack(summary: MobileTrackSummary): Track {
+  return {
+    id: summary.id,
+    file_path: "",
+    file_hash: "",
+    title: summary.title,
+    artist: summary.artist,
+    duration_ms: summary.duration_ms,
+    play_count: 0,
+    rating: summary.rating,
+    color: summary.color,
+    bpm: summary.bpm,
+    musical_key: summary.musical_key,
+  };
+}
+
+/** Error thrown for a failed response, using the envelope when the server sent one */
+async function responseError(res: Response): Promise<Error> {
+  const body = await res.json().catch(() => null);
+  if (body && typeof body.code === "string" && typeof body.message === "string") {
+    return new ApiError(res.status, body.code, body.message);
+  }
+  if (res.status === 401) {
+    return new ApiError(401, "unauthorized", "Unauthorized — invalid or expired token");
+  }
+  return new ApiError(res.status, "http_error", `HTTP ${res.status}: ${res.statusText}`);
+}
+
+/** Fetch an API endpoint; `path` is relative to the API prefix (e.g. "/tracks") */
 async function authFetch(path: string, options?: RequestInit): Promise<Response> {
-  const res = await fetch(`${_baseUrl}${path}`, {
+  const res = await fetch(`${_baseUrl}${_apiPrefix}${path}`, {
     ...options,
     headers: {
       ...options?.headers,
@@ -78,15 +162,38 @@ async function authFetch(path: string, options?: RequestInit): Promise<Response>
     },
   });
 
-  if (res.status === 401) {
-    throw new Error("Unauthorized — invalid or expired token");
-  }
   if (!res.ok) {
-    throw new Error(`HTTP ${res.status}: ${res.statusText}`);
+    throw await responseError(res);
   }
   return res;
 }
 
+/** Read server-sent events from a response until the stream ends */
+async function readEvents(res: Response, onEvent: (event: CompanionEvent) => void) {
+  const reader = res.body!.pipeThrough(new TextDecoderStream()).getReader();
+  let buffer = "";
+  for (;;) {
+    const { value, done } = await reader.read();
+    if (done) return;
+    buffer += value;
+    let end;
+    while ((end = buffer.indexOf("\n\n")) >= 0) {
+      const frame = buffer.slice(0, end);
+      buffer = buffer.slice(end + 2);
+      let type = "";
+      let data = "";
+      for (const line of frame.split("\n")) {
+        // Lines starting with ":" are keep-alive comments
+        if (line.startsWith("event:")) type = line.slice(6).trim();
+        else if (line.startsWith("data:")) data += line.slice(5).trim();
+      }
+      if (type) {
+        onEvent({ type, ...(data ? JSON.parse(data) : {}) } as CompanionEvent);
+      }
+    }
+  }
+}
+
 export const httpApi = {
   /** Configure the server connection */
   configure(baseUrl: string, token: string) {
@@ -99,23 +206,86 @@ export const httpApi = {
     return _baseUrl;
   },
 
+  /**
+   * Ask the desktop which API version and features it supports. Desktops from before
+   * versioning don't have the endpoint (or answer with the PWA's index.html): fall back
+   * to the unversioned API and the features they always had.
+   */
+  async loadCapabilities(): Promise<ServerCapabilities> {
+    try {
+      const res = await fetch(`${_baseUrl}/api/v1/capabilities`);
+      const isJson = res.headers.get("content-type")?.startsWith("application/json");
+      if (res.ok && isJson) {
+        _capabilities = await res.json();
+        _apiPrefix = "/api/v1";
+        return _capabilities;
+      }
+    } catch {
+      // Unreachable server: getStatus reports it
+    }
+    _capabilities = LEGACY_CAPABILITIES;
+    _apiPrefix = "/api";
+    return _capabilities;
+  },
+
+  /** Whether the connected desktop offers a feature (e.g. "downloads", "remote_control") */
+  hasFeature(feature: string): boolean {
+    return _capabilities.features.includes(feature);
+  },
+
   /** Check connection to the server */
   async getStatus(): Promise<ServerStatus> {
-    const res = await authFetch("/api/status");
+    const res = await authFetch("/status");
     return res.json();
   },
 
   /** Get paginated tracks */
   async getTracksPaginated(limit: number, offset: number): Promise<Track[]> {
-    const res = await authFetch(`/api/tracks?limit=${limit}&offset=${offset}`);
+    const res = await authFetch(`/tracks?limit=${limit}&offset=${offset}`);
     const mobileTracks: MobileTrack[] = await res.json();
     return mobileTracks.map(mobileTrackToTrack);
   },
 
+  /** Get paginated tracks with only the list view's fields (title, artist, BPM, key,
+   *  duration, color, rating); falls back to full tracks on desktops without summaries */
+  async getTrackSummaries(limit: number, offset: number): Promise<Track[]> {
+    if (!this.hasFeature("track_summaries")) {
+      return this.getTracksPaginated(limit, offset);
+    }
+    const res = await authFetch(`/tracks/summary?limit=${limit}&offset=${offset}`);
+    const summaries: MobileTrackSummary[] = await res.json();
+    return summaries.map(summaryToTrack);
+  },
+
+  /**
+   * Listen for desktop events (analysis results etc.), reconnecting whenever the stream
+   * drops. Uses fetch rather than EventSource, which can't send the Bearer token.
+   * Does nothing on desktops without the "events" feature. Returns a function that stops.
+   */
+  watchEvents(onEvent: (event: CompanionEvent) => void): () => void {
+    const controller = new AbortController();
+    if (!this.hasFeature("events")) {
+      return () => controller.abort();
+    }
+    (async () => {
+      while (!controller.signal.aborted) {
+        try {
+          const res = await authFetch("/events", { signal: controller.signal });
+          await readEvents(res, onEvent);
+        } catch (err) {
+          if (controller.signal.aborted) return;
+          console.warn("Event stream failed:", err);
+        }
+        await new Promise((resolve) => setTimeout(resolve, EVENTS_RETRY_MS));
+      }
+    })();
+    return () => controller.abort();
+  },
+
   /** Search tracks */
   async searchTracks(query: string): Promise<Track[]> {
     const res = await authFetch(
-      `/api/tracks/search?q=${encodeURIComponent(query)}`
+      `/tracks/search?q=${encodeURIComponent(query)}`
     );
     const mobileTracks: MobileTrack[] = await res.json();
     return mobileTracks.map(mobileTrackToTrack);
@@ -123,14 +293,14 @@ export const httpApi = {
 
   /** Get a single track */
   async getTrack(id: number): Promise<Track> {
-    const res = await authFetch(`/api/tracks/${id}`);
+    const res = await authFetch(`/tracks/${id}`);
     const mt: MobileTrack = await res.json();
     return mobileTrackToTrack(mt);
   },
 
   /** Request a stream ticket for audio playback */
   async getStreamTicket(trackId: number): Promise<StreamTicketResponse> {
-    const res = await authFetch("/api/stream-ticket", {
+    const res = await authFetch("/stream-ticket", {
       method: "POST",
       body: JSON.stringify({ track_id: trackId }),
     });
@@ -142,4 +312,72 @@ export const httpApi = {
     const ticket = await this.getStreamTicket(trackId);
     return `${_baseUrl}/stream/${trackId}?ticket=${ticket.ticket}`;
   },
+
+  /** Get a ticketed m3u8 URL for a playlist, playable by LAN devices (Sonos, VLC) */
+  async getPlaylistM3uUrl(playlistId: number): Promise<string> {
+    const res = await authFetch("/playlist-ticket", {
+      method: "POST",
+      body: JSON.stringify({ playlist_id: playlistId }),
+    });
+    const ticket: { playlist_url: string } = await res.json();
+    return `${_baseUrl}${ticket.playlist_url}`;
+  },
+
+  /** Download the original audio file (e.g. to cache for offline playback) */
+  async downloadTrack(trackId: number): Promise<Blob> {
+    const res = await authFetch(`/tracks/${trackId}/download`);
+    return res.blob();
+  },
+
+  /**
+   * A track's waveform blob. Returns null while the desktop is still generating it;
+   * retry after `retryAfter` seconds.
+   */
+  async getWaveform(
+    trackId: number,
+    level: "overview" | "detail" = "overview"
+  ): Promise<{ data: ArrayBuffer | null; retryAfter: number }> {
+    const res = await authFetch(`/tracks/${trackId}/waveform?level=${level}`);
+    if (res.status === 202) {
+      const retryAfter = Number(res.headers.get("Retry-After")) || 2;
+      return { data: null, retryAfter };
+    }
+    return { data: await res.arrayBuffer(), retryAfter: 0 };
+  },
+
+  /** The desktop's audition queue, next first */
+  async getQueue(): Promise<Track[]> {
+    const res = await authFetch("/queue");
+    const mobileTracks: MobileTrack[] = await res.json();
+    return mobileTracks.map(mobileTrackToTrack);
+  },
+
+  /** Queue a track on the desktop (at the front with playNext) */
+  async enqueueTrack(trackId: number, playNext = false): Promise<Track[]> {
+    const res = await authFetch("/queue", {
+      method: "POST",
+      body: JSON.stringify({ track_id: trackId, play_next: playNext }),
+    });
+    const mobileTracks: MobileTrack[] = await res.json();
+    return mobileTracks.map(mobileTrackToTrack);
+  },
+
+  async reorderQueue(from: number, to: number): Promise<Track[]> {
+    const res = await authFetch("/queue/reorder", {
+      method: "POST",
+      body: JSON.stringify({ from, to }),
+    });
+    const mobileTracks: MobileTrack[] = await res.json();
+    return mobileTracks.map(mobileTrackToTrack);
+  },
+
+  async removeFromQueue(index: number): Promise<Track[]> {
+    const res = await authFetch(`/queue/${index}`, { method: "DELETE" });
+    const mobileTracks: MobileTrack[] = await res.json();
+    return mobileTracks.map(mobileTrackToTrack);
+  },
+
+  async clearQueue(): Promise<void> {
+    await authFetch("/queue", { method: "DELETE" });
+  },
 };
@@ -1,8 +1,8 @@
 // Tauri API wrapper for invoking backend commands
 
 import { invoke } from "@tauri-apps/api/core";
-import type { Track, ScanResult, BpmResult, KeyResult, TrackAnalysis, FolderInfo, Playlist, GenreCount, GenreDefinition } from "../types/track";
-import type { ChatMessage, GeneratedPlaylist } from "../types/ai";
+import type { Track, ScanResult, BpmResult, KeyResult, TrackAnalysis, FolderInfo, Playlist, GenreCount, GenreDefinition, GenreMergeSuggestion, OnboardingState, MusicFolderSuggestion, RekordboxImportResult, MixabilityReport, SkippedTrack, EnergyExtractor, StreamMimeConfig, InterruptedBatch, ScanHashMode, WriteConflictMode, TrackComparison, MidiMapping, HotkeyConfig, BatchAction, AnalysisQueueStatus, TracklistFormat, CompanionPairingPayload, CompanionMetrics, PlayContextType, PlayContext, ListeningSession, PlaylistDraft, GenreBpmRange, BpmRounding, GenreProfile, BpmCorrection, DuplicateCluster, VerificationResult, VerifySummary, VerificationReport, SidecarSettings, SidecarResult, WatchRule, PlaylistExportSummary, LibrarySyncResult, PerformanceStats, TempoCurve, SeedFilters, DurationPlaylist, AddTrackResult, PlaylistAudition, ProfileSummary, PruneOptions, PruneSuggestions, StreamConfig, DistinctColumn, DistinctValue, TrackGrouping, TrackFilter, TrackGroup, PolledFolder, QualityCheck, QualityFlag, QualityIssue, CustomField, CustomFieldType, TrackPurchase, PurchaseSourceCount, PurchaseFilter, PlaylistSnapshot, Gig, GigOverview, SetTracklist, BeatMarkerFormat, BeatMarkerExport, DeletedFileSettings, JournalEntry, MissingTrack, KeyColor, StrayTracksPreview, SymlinkMode, FolderSymlinkMode, AnalysisKind, AnalysisFailure, TrackField } from "../types/track";
+import type { ChatMessage, GeneratedPlaylist, RefinedPlaylist, ApiProvider, ApiKeyStatus, AiUsageMonth } from "../types/ai";
 
 export const tauriApi = {
   // Database commands
@@ -14,8 +14,9 @@ export const tauriApi = {
     return await invoke("get_all_tracks");
   },
 
-  async getTracksPaginated(limit: number, offset: number): Promise<Track[]> {
-    return await invoke("get_tracks_paginated", { limit, offset });
+  /** fields: only these Track fields (and id), for views that render a few columns */
+  async getTracksPaginated<F extends TrackField = TrackField>(limit: number, offset: number, sortBy?: "id" | "score" | "title" | "artist" | "album", fields?: F[]): Promise<Pick<Track, F | "id">[]> {
+    return await invoke("get_tracks_paginated", { limit, offset, sortBy: sortBy ?? null, fields: fields ?? null });
   },
 
   async getTrack(id: number): Promise<Track> {
@@ -26,6 +27,10 @@ export const tauriApi = {
     return await invoke("update_track", { track });
   },
 
+  async setTrackEnergy(trackId: number, energyLevel: number | null): Promise<void> {
+    return await invoke("set_track_energy", { trackId, energyLevel });
+  },
+
   async deleteTrack(id: number): Promise<void> {
     return await invoke("delete_track", { id });
   },
@@ -34,11 +39,37 @@ export const tauriApi = {
     return await invoke("count_tracks");
   },
 
+  /** Distinct values with track counts for filter dropdowns (all columns by default) */
+  async getDistinctValues(columns?: DistinctColumn[]): Promise<Partial<Record<DistinctColumn, DistinctValue[]>>> {
+    return await invoke("get_distinct_values", { columns: columns ?? null });
+  },
+
+  /** Group headers with counts and totals; fetch a group's tracks with getTrackGroupMembers */
+  async getTracksGrouped(groupBy: TrackGrouping, filters?: TrackFilter): Promise<TrackGroup[]> {
+    return await invoke("get_tracks_grouped", { groupBy, filters: filters ?? null });
+  },
+
+  /** A page of one group's tracks (`value` from its header, same groupBy/filters) */
+  async getTrackGroupMembers(
+    groupBy: TrackGrouping,
+    value: string | null,
+    limit: number,
+    offset: number,
+    filters?: TrackFilter
+  ): Promise<Track[]> {
+    return await invoke("get_track_group_members", { groupBy, value, filters: filters ?? null, limit, offset });
+  },
+
   // Search command (backend SQL search for future use with large libraries)
   async searchTracks(query: string): Promise<Track[]> {
     return await invoke("search_tracks", { query });
   },
 
+  /** IDs of the tracks matching a search, including transliterated matches ("Zhu" finds "Жу") */
+  async searchTrackIds(query: string): Promise<number[]> {
+    return await invoke("search_track_ids", { query });
+  },
+
   // Scanner commands
   async scanDirectory(path: string): Promise<ScanResult> {
     return await invoke("scan_directory", { path });
@@ -61,6 +92,16 @@ export const tauriApi = {
     return await invoke("get_library_folders");
   },
 
+  /** Show a track's file in Finder/Explorer (only for files inside library folders) */
+  async revealTrackInFileManager(trackId: number): Promise<void> {
+    return await invoke("reveal_track_in_file_manager", { trackId });
+  },
+
+  /** Open a library folder (or the folder containing a library file) in Finder/Explorer */
+  async openContainingFolder(path: string): Promise<void> {
+    return await invoke("open_containing_folder", { path });
+  },
+
   async addLibraryFolder(path: string): Promise<string[]> {
     return await invoke("add_library_folder", { path });
   },
@@ -77,6 +118,77 @@ export const tauriApi = {
     return await invoke("set_theme", { theme });
   },
 
+  async getAnalysisPriority(): Promise<"tag" | "analysis"> {
+    return await invoke("get_analysis_priority");
+  },
+
+  async setAnalysisPriority(priority: "tag" | "analysis"): Promise<void> {
+    return await invoke("set_analysis_priority", { priority });
+  },
+
+  /** Whether BPM analysis also measures a tempo curve (flags variable-tempo tracks) */
+  async getTempoCurveAnalysis(): Promise<boolean> {
+    return await invoke("get_tempo_curve_analysis");
+  },
+
+  async setTempoCurveAnalysis(enabled: boolean): Promise<void> {
+    return await invoke("set_tempo_curve_analysis", { enabled });
+  },
+
+  async getEnergyExtractor(): Promise<EnergyExtractor> {
+    return await invoke("get_energy_extractor");
+  },
+
+  async setEnergyExtractor(config: EnergyExtractor): Promise<void> {
+    return await invoke("set_energy_extractor", { config });
+  },
+
+  /** How the Content-Type of streamed files is chosen */
+  async getStreamMimeConfig(): Promise<StreamMimeConfig> {
+    return await invoke("get_stream_mime_config");
+  },
+
+  /** Returns the saved config (extensions normalized) */
+  async setStreamMimeConfig(config: StreamMimeConfig): Promise<StreamMimeConfig> {
+    return await invoke("set_stream_mime_config", { config });
+  },
+
+  /** Whether loaded tracks start after their detected leading silence */
+  async getSkipLeadingSilence(): Promise<boolean> {
+    return await invoke("get_skip_leading_silence");
+  },
+
+  async setSkipLeadingSilence(enabled: boolean): Promise<void> {
+    return await invoke("set_skip_leading_silence", { enabled });
+  },
+
+  /** Whether the player only streams files in the library folders (on by default) */
+  async getStreamRestrictToLibrary(): Promise<boolean> {
+    return await invoke("get_stream_restrict_to_library");
+  },
+
+  async setStreamRestrictToLibrary(enabled: boolean): Promise<void> {
+    return await invoke("set_stream_restrict_to_library", { enabled });
+  },
+
+  /** Sample format and chunk length of streamed audio (applies from the next play/seek) */
+  async getStreamConfig(): Promise<StreamConfig> {
+    return await invoke("get_stream_config");
+  },
+
+  async setStreamConfig(config: StreamConfig): Promise<void> {
+    return await invoke("set_stream_config", { config });
+  },
+
+  /** Alphabetical sorting ignores a leading "The"/"A"/"An" */
+  async getSortIgnoreArticles(): Promise<boolean> {
+    return await invoke("get_sort_ignore_articles");
+  },
+
+  async setSortIgnoreArticles(enabled: boolean): Promise<void> {
+    return await invoke("set_sort_ignore_articles", { enabled });
+  },
+
   async getCustomThemeColors(): Promise<Record<string, string> | null> {
     const json = await invoke<string | null>("get_setting", { key: "custom_theme_colors" });
     if (!json) return null;
@@ -99,25 +211,33 @@ export const tauriApi = {
     return await invoke("list_subdirectories", { path });
   },
 
-  async getTracksInFolder(path: string): Promise<Track[]> {
-    return await invoke("get_tracks_in_folder", { path });
+  async getTracksInFolder<F extends TrackField = TrackField>(path: string, fields?: F[]): Promise<Pick<Track, F | "id">[]> {
+    return await invoke("get_tracks_in_folder", { path, fields: fields ?? null });
   },
 
   async countTracksInFolder(path: string): Promise<number> {
     return await invoke("count_tracks_in_folder", { path });
   },
 
-  async getTracksInFolderShallow(path: string): Promise<Track[]> {
-    return await invoke("get_tracks_in_folder_shallow", { path });
+  async getTracksInFolderShallow<F extends TrackField = TrackField>(path: string, fields?: F[]): Promise<Pick<Track, F | "id">[]> {
+    return await invoke("get_tracks_in_folder_shallow", { path, fields: fields ?? null });
   },
 
   async countTracksInFolderShallow(path: string): Promise<number> {
     return await invoke("count_tracks_in_folder_shallow", { path });
   },
 
-  // Cleanup command - removes tracks not in library folders
-  async cleanupStrayTracks(): Promise<number> {
-    return await invoke("cleanup_stray_tracks");
+  // Cleanup commands - remove tracks not in library folders
+
+  /** What cleanupStrayTracks would remove, and the token to confirm it with. Changes nothing. */
+  async previewStrayTracksCleanup(): Promise<StrayTracksPreview> {
+    return await invoke<StrayTracksPreview>("preview_stray_tracks_cleanup");
+  },
+
+  /** Remove the previewed tracks; fails if the library changed since the preview.
+   *  With no library folders (requires_force) every track goes, and only with force. */
+  async cleanupStrayTracks(confirmToken: string, force = false): Promise<number> {
+    return await invoke("cleanup_stray_tracks", { confirmToken, force });
   },
 
   // Cleanup command - removes duplicate tracks (same file hash or filename)
@@ -125,6 +245,74 @@ export const tauriApi = {
     return await invoke("cleanup_duplicate_tracks");
   },
 
+  // Find duplicate tracks without removing anything
+  async findDuplicateClusters(): Promise<DuplicateCluster[]> {
+    return await invoke("find_duplicate_clusters");
+  },
+
+  // Keep one copy and remove the others, merging play counts, ratings and playlists
+  async resolveDuplicates(keepId: number, removeIds: number[], mergeMetadata = false): Promise<Track> {
+    return await invoke("resolve_duplicates", { keepId, removeIds, mergeMetadata });
+  },
+
+  // Rank tracks that look safe to delete (never played, low rated, duplicates, ...)
+  async suggestPruneCandidates(options?: PruneOptions): Promise<PruneSuggestions> {
+    return await invoke("suggest_prune_candidates", { options });
+  },
+
+  // Technical quality checks: fake lossless (transcoded) files, DC offset, mains hum
+  async analyzeQuality(trackId: number): Promise<QualityCheck> {
+    return await invoke("analyze_quality", { trackId });
+  },
+
+  /** Check all unchecked tracks; returns how many were checked */
+  async analyzeAllQuality(): Promise<number> {
+    return await invoke("analyze_all_quality");
+  },
+
+  /** Flagged tracks, optionally only those with some of the given flags */
+  async getQualityIssues(flags?: QualityFlag[]): Promise<QualityIssue[]> {
+    return await invoke("get_quality_issues", { flags: flags ?? null });
+  },
+
+  // File integrity verification (re-hash files, compare with the hash stored at scan)
+  async verifyTrack(trackId: number): Promise<VerificationResult> {
+    return await invoke("verify_track", { trackId });
+  },
+
+  /** Skips tracks verified within maxAgeDays, if given */
+  async verifyAll(maxAgeDays?: number): Promise<VerifySummary> {
+    return await invoke("verify_all", { maxAgeDays: maxAgeDays ?? null });
+  },
+
+  async getVerificationReport(): Promise<VerificationReport> {
+    return await invoke("get_verification_report");
+  },
+
+  /** Accept a changed file as correct: its new hash becomes the stored hash */
+  async acceptVerifiedHash(trackId: number): Promise<void> {
+    return await invoke("accept_verified_hash", { trackId });
+  },
+
+  // Analysis sidecars (.recodeck files next to the audio)
+  async getSidecarSettings(): Promise<SidecarSettings> {
+    return await invoke("get_sidecar_settings");
+  },
+
+  async setSidecarSettings(settings: SidecarSettings): Promise<void> {
+    return await invoke("set_sidecar_settings", { settings });
+  },
+
+  /** Write sidecars for the given tracks, or the whole library */
+  async exportSidecars(trackIds?: number[]): Promise<SidecarResult> {
+    return await invoke("export_sidecars", { trackIds: trackIds ?? null });
+  },
+
+  /** Fill analysis and cues from sidecars for the given tracks, or the whole library */
+  async importSidecars(trackIds?: number[]): Promise<SidecarResult> {
+    return await invoke("import_sidecars", { trackIds: trackIds ?? null });
+  },
+
   // Normalize file paths - removes double slashes from stored paths
   async normalizeFilePaths(): Promise<number> {
     return await invoke("normalize_file_paths");
@@ -160,19 +348,192 @@ export const tauriApi = {
     return await invoke("get_playlist_tracks", { playlistId });
   },
 
-  async addTrackToPlaylist(playlistId: number, trackId: number): Promise<void> {
-    return await invoke("add_track_to_playlist", { playlistId, trackId });
+  /**
+   * Add a track to a playlist. With `checkVersions`, a different file of a song already in the
+   * playlist is held back and returned as `possible_duplicate` so the UI can confirm.
+   */
+  async addTrackToPlaylist(playlistId: number, trackId: number, checkVersions = false): Promise<AddTrackResult> {
+    return await invoke("add_track_to_playlist", { playlistId, trackId, checkVersions });
+  },
+
+  /** Tracks already auditioned (played from this playlist as play context) */
+  async getPlaylistAuditions(playlistId: number): Promise<PlaylistAudition[]> {
+    return await invoke("get_playlist_auditions", { playlistId });
+  },
+
+  /** Clear "already reviewed" marks (all, or only `trackIds`); returns how many were cleared */
+  async resetPlaylistAuditions(playlistId: number, trackIds?: number[]): Promise<number> {
+    return await invoke("reset_playlist_auditions", { playlistId, trackIds });
   },
 
   async removeTrackFromPlaylist(playlistId: number, trackId: number): Promise<void> {
     return await invoke("remove_track_from_playlist", { playlistId, trackId });
   },
 
+  /** Snapshots of a playlist's tracks, newest first (one is taken before each change) */
+  async getPlaylistHistory(playlistId: number): Promise<PlaylistSnapshot[]> {
+    return await invoke("get_playlist_history", { playlistId });
+  },
+
+  /** Snapshot now; null if nothing changed since the last snapshot */
+  async snapshotPlaylist(playlistId: number): Promise<number | null> {
+    return await invoke("snapshot_playlist", { playlistId });
+  },
+
+  /** Roll a playlist back to a snapshot (undoable); returns the tracks restored */
+  async restorePlaylistSnapshot(snapshotId: number): Promise<number> {
+    return await invoke("restore_playlist_snapshot", { snapshotId });
+  },
+
+  async getMirroredFolders(): Promise<string[]> {
+    return await invoke("get_mirrored_folders");
+  },
+
+  async addMirroredFolder(path: string): Promise<string[]> {
+    return await invoke("add_mirrored_folder", { path });
+  },
+
+  async removeMirroredFolder(path: string): Promise<string[]> {
+    return await invoke("remove_mirrored_folder", { path });
+  },
+
+  async syncFolderPlaylists(): Promise<number> {
+    return await invoke("sync_folder_playlists");
+  },
+
+  // .m3u playlist export (kept in sync automatically while a folder is set)
+  async getPlaylistExportFolder(): Promise<string | null> {
+    return await invoke("get_playlist_export_folder");
+  },
+
+  async setPlaylistExportFolder(folder: string | null): Promise<PlaylistExportSummary | null> {
+    return await invoke("set_playlist_export_folder", { folder });
+  },
+
+  async exportPlaylistsM3u(): Promise<PlaylistExportSummary | null> {
+    return await invoke("export_playlists_m3u");
+  },
+
+  /** Pick tracks matching the filters that add up to about `targetMinutes` (nothing is saved) */
+  async buildPlaylistToDuration(seedFilters: SeedFilters, targetMinutes: number): Promise<DurationPlaylist> {
+    return await invoke("build_playlist_to_duration", { seedFilters, targetMinutes });
+  },
+
   // File watcher commands
   async startFileWatcher(folders: string[]): Promise<void> {
     return await invoke("start_file_watcher", { folders });
   },
 
+  /** Library folders watched by polling instead of OS notifications (NAS/SMB/NFS) */
+  async getPolledFolders(): Promise<PolledFolder[]> {
+    return await invoke("get_polled_folders");
+  },
+
+  /** Poll a library folder every intervalSecs (5-3600), or null to use OS notifications again */
+  async setFolderPolling(folder: string, intervalSecs: number | null): Promise<void> {
+    return await invoke("set_folder_polling", { folder, intervalSecs });
+  },
+
+  /**
+   * Watch folder rules — new files in the folder are auto-imported, tagged and added to a playlist
+   */
+  async getWatchRules(): Promise<WatchRule[]> {
+    return await invoke<WatchRule[]>("get_watch_rules");
+  },
+
+  async createWatchRule(folder: string, tag: string | null, playlistName: string | null): Promise<WatchRule> {
+    return await invoke<WatchRule>("create_watch_rule", { folder, tag, playlistName });
+  },
+
+  async updateWatchRule(rule: WatchRule): Promise<WatchRule> {
+    return await invoke<WatchRule>("update_watch_rule", {
+      id: rule.id,
+      folder: rule.folder,
+      tag: rule.tag,
+      playlistName: rule.playlist_name,
+      enabled: rule.enabled,
+    });
+  },
+
+  async deleteWatchRule(id: number): Promise<void> {
+    return await invoke("delete_watch_rule", { id });
+  },
+
+  async getDeletedFileSettings(): Promise<DeletedFileSettings> {
+    return await invoke<DeletedFileSettings>("get_deleted_file_settings");
+  },
+
+  /** Restarts the watcher; grace_period_secs at most 86400 */
+  async setDeletedFileSettings(settings: DeletedFileSettings): Promise<void> {
+    return await invoke("set_deleted_file_settings", { settings });
+  },
+
+  // Operation journal commands
+
+  /** Background library changes (e.g. tracks of deleted files removed), newest first */
+  async getOperationJournal(limit?: number): Promise<JournalEntry[]> {
+    return await invoke<JournalEntry[]>("get_operation_journal", { limit: limit ?? null });
+  },
+
+  /** Revert a journal entry; returns the number of tracks restored or unflagged */
+  async undoOperation(id: number): Promise<number> {
+    return await invoke<number>("undo_operation", { id });
+  },
+
+  async getMissingTracks(): Promise<MissingTrack[]> {
+    return await invoke<MissingTrack[]>("get_missing_tracks");
+  },
+
+  // Key color commands
+
+  /** Every Camelot key's color, in wheel order (1A, 1B, 2A, ...) */
+  async getKeyColors(): Promise<KeyColor[]> {
+    return await invoke<KeyColor[]>("get_key_colors");
+  },
+
+  /** Replace the key colors (Camelot key -> "#rrggbb"); keys left out get their default */
+  async setKeyColors(colors: Record<string, string>): Promise<KeyColor[]> {
+    return await invoke<KeyColor[]>("set_key_colors", { colors });
+  },
+
+  // Gig commands
+
+  /** All gigs, soonest first */
+  async getGigs(): Promise<Gig[]> {
+    return await invoke("get_gigs");
+  },
+
+  async createGig(name: string, venue: string | null, date: string, setLengthMinutes: number, notes: string | null): Promise<Gig> {
+    return await invoke("create_gig", { name, venue, date, setLengthMinutes, notes });
+  },
+
+  async updateGig(id: number, name: string, venue: string | null, date: string, setLengthMinutes: number, notes: string | null): Promise<Gig> {
+    return await invoke("update_gig", { id, name, venue, date, setLengthMinutes, notes });
+  },
+
+  /** Delete a gig; its playlists are kept */
+  async deleteGig(id: number): Promise<void> {
+    return await invoke("delete_gig", { id });
+  },
+
+  async linkPlaylistToGig(gigId: number, playlistId: number): Promise<void> {
+    return await invoke("link_playlist_to_gig", { gigId, playlistId });
+  },
+
+  async unlinkPlaylistFromGig(gigId: number, playlistId: number): Promise<void> {
+    return await invoke("unlink_playlist_from_gig", { gigId, playlistId });
+  },
+
+  /** Playlist lengths vs the slot, analysis coverage and missing files */
+  async getGigOverview(id: number): Promise<GigOverview> {
+    return await invoke("get_gig_overview", { id });
+  },
+
+  /** Reconstruct a recorded set's tracklist; tracks of `playlistIds` are fingerprinted first if needed */
+  async identifySetTracklist(recordingPath: string, playlistIds?: number[]): Promise<SetTracklist> {
+    return await invoke("identify_set_tracklist", { recordingPath, playlistIds });
+  },
+
   // Analysis commands
   async analyzeBpm(trackId: number): Promise<BpmResult> {
     return await invoke("analyze_bpm", { trackId });
@@ -194,16 +555,63 @@ export const tauriApi = {
     return await invoke("get_track_analysis", { trackId });
   },
 
+  /** Measure BPM over consecutive windows and store the curve */
+  async analyzeTempoCurve(trackId: number): Promise<TempoCurve> {
+    return await invoke("analyze_tempo_curve", { trackId });
+  },
+
+  /** Stored tempo curve, or null if it hasn't been analyzed */
+  async getTempoCurve(trackId: number): Promise<TempoCurve | null> {
+    return await invoke("get_tempo_curve", { trackId });
+  },
+
   async analyzeWaveform(trackId: number): Promise<void> {
     return await invoke("analyze_waveform", { trackId });
   },
 
+  /** Expected BPM range per genre; detected BPMs are moved into it by octaves */
+  async getGenreBpmRanges(): Promise<GenreBpmRange[]> {
+    return await invoke("get_genre_bpm_ranges");
+  },
+
+  async setGenreBpmRanges(ranges: GenreBpmRange[]): Promise<void> {
+    return await invoke("set_genre_bpm_ranges", { ranges });
+  },
+
+  /** Re-map stored BPMs into their genre's range; returns the corrections made */
+  async normalizeBpmOctaves(): Promise<BpmCorrection[]> {
+    return await invoke("normalize_bpm_octaves");
+  },
+
+  /** How new BPM analysis is rounded (step, snapping to common tempos) */
+  async getBpmRounding(): Promise<BpmRounding> {
+    return await invoke("get_bpm_rounding");
+  },
+
+  async setBpmRounding(rounding: BpmRounding): Promise<void> {
+    return await invoke("set_bpm_rounding", { rounding });
+  },
+
+  /** Round stored analyzed BPMs with the current setting; returns the changes made */
+  async requantizeBpms(): Promise<BpmCorrection[]> {
+    return await invoke("requantize_bpms");
+  },
+
   async getWaveform(trackId: number, level: string): Promise<Uint8Array | null> {
     const result = await invoke<number[] | null>("get_waveform", { trackId, level });
     if (result === null) return null;
     return new Uint8Array(result);
   },
 
+  /** Analyses kept for deleted tracks, restored when the same file is re-imported */
+  async getAnalysisCacheCount(): Promise<number> {
+    return await invoke("get_analysis_cache_count");
+  },
+
+  async clearAnalysisCache(): Promise<number> {
+    return await invoke("clear_analysis_cache");
+  },
+
   // Playback commands (native decode/streaming)
   async playbackLoadTrack(trackId: number): Promise<{
     is_playing: boolean;
@@ -275,6 +683,77 @@ export const tauriApi = {
     return await invoke("get_playback_status");
   },
 
+  /** Auto-advance: after the current track, play the rest of a folder/playlist or the queue.
+   *  `position` is the current track's index; each advance emits "track-changed". */
+  async setPlayContext(contextType: PlayContextType, id: string | null, position: number): Promise<PlayContext> {
+    return await invoke("set_play_context", { contextType, id, position });
+  },
+
+  async getPlayContext(): Promise<PlayContext | null> {
+    return await invoke("get_play_context");
+  },
+
+  async clearPlayContext(): Promise<void> {
+    return await invoke("clear_play_context");
+  },
+
+  /** Play only startMs..endMs of a track, optionally looping it; loads the track if needed */
+  async playSection(trackId: number, startMs: number, endMs: number, loop = true): Promise<{
+    is_playing: boolean;
+    track_id: number | null;
+    position_ms: number;
+    duration_ms: number;
+    sample_rate: number;
+  }> {
+    return await invoke("play_section", { trackId, startMs, endMs, looped: loop });
+  },
+
+  /** Stop looping the section; playback continues through the track */
+  async clearPlaySection(): Promise<void> {
+    return await invoke("clear_play_section");
+  },
+
+  // Audition queue commands (shared with the companion remote; changes emit "queue-changed")
+  async getQueue(): Promise<Track[]> {
+    return await invoke("get_queue");
+  },
+
+  async enqueueTrack(trackId: number, playNext = false): Promise<Track[]> {
+    return await invoke("enqueue_track", { trackId, playNext });
+  },
+
+  async dequeueTrack(index: number): Promise<Track[]> {
+    return await invoke("dequeue_track", { index });
+  },
+
+  async reorderQueue(from: number, to: number): Promise<Track[]> {
+    return await invoke("reorder_queue", { from, to });
+  },
+
+  async clearQueue(): Promise<void> {
+    return await invoke("clear_queue");
+  },
+
+  /** Load and play the next queued track; null when the queue is empty */
+  async playNextInQueue(): Promise<{
+    is_playing: boolean;
+    track_id: number | null;
+    position_ms: number;
+    duration_ms: number;
+    sample_rate: number;
+  } | null> {
+    return await invoke("play_next_in_queue");
+  },
+
+  // Play history commands
+  async recomputeTrackScores(): Promise<number> {
+    return await invoke("recompute_track_scores");
+  },
+
+  async getMostSkipped(limit?: number): Promise<SkippedTrack[]> {
+    return await invoke("get_most_skipped", { limit });
+  },
+
   // AI commands
   async setAIApiKey(apiKey: string): Promise<void> {
     return await invoke("set_ai_api_key", { apiKey });
@@ -288,6 +767,23 @@ export const tauriApi = {
     return await invoke("delete_ai_api_key");
   },
 
+  async setProviderApiKey(provider: ApiProvider, apiKey: string): Promise<void> {
+    return await invoke("set_provider_api_key", { provider, apiKey });
+  },
+
+  async deleteProviderApiKey(provider: ApiProvider): Promise<void> {
+    return await invoke("delete_provider_api_key", { provider });
+  },
+
+  async getApiKeyStatuses(): Promise<ApiKeyStatus[]> {
+    return await invoke("get_api_key_statuses");
+  },
+
+  /** Check a stored key with a cheap test call against the provider */
+  async validateApiKey(provider: ApiProvider): Promise<ApiKeyStatus> {
+    return await invoke("validate_api_key", { provider });
+  },
+
   async rebuildAIContext(): Promise<void> {
     return await invoke("rebuild_ai_context");
   },
@@ -296,10 +792,20 @@ export const tauriApi = {
     return await invoke("ai_generate_playlist", { prompt });
   },
 
+  /** Change an existing playlist by instruction; the playlist is snapshotted first */
+  async aiRefinePlaylist(playlistId: number, instruction: string): Promise<RefinedPlaylist> {
+    return await invoke("ai_refine_playlist", { playlistId, instruction });
+  },
+
   async aiChat(message: string, conversationHistory: ChatMessage[]): Promise<string> {
     return await invoke("ai_chat", { message, conversationHistory });
   },
 
+  /** Claude tokens used per month (newest first, default the last 12), by feature */
+  async getAiUsage(months?: number): Promise<AiUsageMonth[]> {
+    return await invoke("get_ai_usage", { months: months ?? null });
+  },
+
   // Genre commands
   async setTrackGenre(trackId: number, genre: string): Promise<void> {
     return await invoke("set_track_genre", { trackId, genre });
@@ -337,6 +843,62 @@ export const tauriApi = {
     return await invoke("bulk_set_genre", { trackIds, genre });
   },
 
+  async mergeGenres(fromNames: string[], toName: string): Promise<number> {
+    return await invoke("merge_genres", { fromNames, toName });
+  },
+
+  async suggestGenreMerges(): Promise<GenreMergeSuggestion[]> {
+    return await invoke("suggest_genre_merges");
+  },
+
+  /** Recompute each genre's typical BPM range and keys from the library */
+  async learnGenreProfiles(): Promise<GenreProfile[]> {
+    return await invoke("learn_genre_profiles");
+  },
+
+  /** Genre profiles from the last learnGenreProfiles, for pre-filling playlist filters */
+  async getGenreProfiles(): Promise<GenreProfile[]> {
+    return await invoke("get_genre_profiles");
+  },
+
+  /** Write genres, tags, smart playlists and portable settings to a JSON profile file */
+  async exportProfile(path: string): Promise<ProfileSummary> {
+    return await invoke("export_profile", { path });
+  },
+
+  /** Merge a profile file into this library (matched by name; nothing is deleted) */
+  async importProfile(path: string): Promise<ProfileSummary> {
+    return await invoke("import_profile", { path });
+  },
+
+  // Library sync with another RecoDeck instance (its companion server must be running)
+  async pairSyncPeer(url: string, code: string): Promise<string> {
+    return await invoke("pair_sync_peer", { url, code });
+  },
+
+  async getSyncPeer(): Promise<string | null> {
+    return await invoke("get_sync_peer");
+  },
+
+  async forgetSyncPeer(): Promise<void> {
+    return await invoke("forget_sync_peer");
+  },
+
+  async syncWithPeer(): Promise<LibrarySyncResult> {
+    return await invoke("sync_with_peer");
+  },
+
+  // Performance tracing commands (only record in builds with the "perf-trace" feature)
+
+  /** Per-command/query timings plus the `limit` most recent raw events (default 200) */
+  async getPerformanceStats(limit?: number): Promise<PerformanceStats> {
+    return await invoke("get_performance_stats", { limit });
+  },
+
+  async clearPerformanceStats(): Promise<void> {
+    return await invoke("clear_performance_stats");
+  },
+
   // Companion server commands
   async startCompanionServer(port?: number): Promise<{
     running: boolean;
@@ -344,6 +906,9 @@ export const tauriApi = {
     token: string | null;
     port: number | null;
     active_streams: number;
+    /** Browse mode: paired phones can't change anything */
+    read_only: boolean;
+    metrics: CompanionMetrics | null;
   }> {
     return await invoke("start_companion_server", { port: port ?? null });
   },
@@ -358,6 +923,9 @@ export const tauriApi = {
     token: string | null;
     port: number | null;
     active_streams: number;
+    /** Browse mode: paired phones can't change anything */
+    read_only: boolean;
+    metrics: CompanionMetrics | null;
   }> {
     return await invoke("get_companion_status");
   },
@@ -368,7 +936,295 @@ export const tauriApi = {
     token: string | null;
     port: number | null;
     active_streams: number;
+    /** Browse mode: paired phones can't change anything */
+    read_only: boolean;
+    metrics: CompanionMetrics | null;
   }> {
     return await invoke("regenerate_companion_token");
   },
+
+  /** Apply changed settings to the running server; restarts the listener only if the port changes */
+  async reloadCompanionConfig(options?: { port?: number; maxStreams?: number; readOnly?: boolean }): Promise<{
+    running: boolean;
+    url: string | null;
+    token: string | null;
+    port: number | null;
+    active_streams: number;
+    /** Browse mode: paired phones can't change anything */
+    read_only: boolean;
+    metrics: CompanionMetrics | null;
+  }> {
+    return await invoke("reload_companion_config", {
+      port: options?.port ?? null,
+      maxStreams: options?.maxStreams ?? null,
+      readOnly: options?.readOnly ?? null,
+    });
+  },
+
+  /** QR payload: server URL + single-use pairing code (the PWA exchanges it for the token) */
+  async getCompanionPairingPayload(): Promise<CompanionPairingPayload> {
+    return await invoke("get_companion_pairing_payload");
+  },
+
+  // Onboarding commands
+  async getOnboardingState(): Promise<OnboardingState> {
+    return await invoke("get_onboarding_state");
+  },
+
+  async completeOnboardingStep(step: string): Promise<OnboardingState> {
+    return await invoke("complete_onboarding_step", { step });
+  },
+
+  async suggestMusicFolders(): Promise<MusicFolderSuggestion[]> {
+    return await invoke("suggest_music_folders");
+  },
+
+  async runInitialScan(paths: string[]): Promise<ScanResult> {
+    return await invoke("run_initial_scan", { paths });
+  },
+
+  // Rekordbox import commands
+  async importRekordboxLibrary(path: string): Promise<RekordboxImportResult> {
+    return await invoke("import_rekordbox_library", { path });
+  },
+
+  // Report commands
+  async getMixabilityReport(playlistId?: number): Promise<MixabilityReport> {
+    return await invoke("get_mixability_report", { playlistId: playlistId ?? null });
+  },
+
+  // Batch recovery
+
+  async getInterruptedBatches(): Promise<InterruptedBatch[]> {
+    return await invoke("get_interrupted_batches");
+  },
+
+  async dismissInterruptedBatch(job: string): Promise<void> {
+    return await invoke("dismiss_interrupted_batch", { job });
+  },
+
+  async getScanHashMode(): Promise<ScanHashMode> {
+    return await invoke("get_scan_hash_mode");
+  },
+
+  async setScanHashMode(mode: ScanHashMode): Promise<void> {
+    return await invoke("set_scan_hash_mode", { mode });
+  },
+
+  /** Each library folder's symlink handling (default "follow") */
+  async getFolderSymlinkModes(): Promise<FolderSymlinkMode[]> {
+    return await invoke<FolderSymlinkMode[]>("get_folder_symlink_modes");
+  },
+
+  /** Follow or skip symlinks when scanning a library folder; applies from the next scan */
+  async setFolderSymlinkMode(folder: string, mode: SymlinkMode): Promise<void> {
+    return await invoke("set_folder_symlink_mode", { folder, mode });
+  },
+
+  async getWriteConflictMode(): Promise<WriteConflictMode> {
+    return await invoke("get_write_conflict_mode");
+  },
+
+  async setWriteConflictMode(mode: WriteConflictMode): Promise<void> {
+    return await invoke("set_write_conflict_mode", { mode });
+  },
+
+  async compareTracks(trackA: number, trackB: number): Promise<TrackComparison> {
+    return await invoke("compare_tracks", { trackA, trackB });
+  },
+
+  // MIDI controller input
+
+  async listMidiInputs(): Promise<string[]> {
+    return await invoke("list_midi_inputs");
+  },
+
+  /** Connect to a MIDI input (first available if no name). Returns the port name. */
+  async startMidi(portName?: string): Promise<string> {
+    return await invoke("start_midi", { portName });
+  },
+
+  async stopMidi(): Promise<void> {
+    return await invoke("stop_midi");
+  },
+
+  async getMidiMappings(): Promise<MidiMapping[]> {
+    return await invoke("get_midi_mappings");
+  },
+
+  async setMidiMappings(mappings: MidiMapping[]): Promise<void> {
+    return await invoke("set_midi_mappings", { mappings });
+  },
+
+  // Global hotkeys
+
+  /** Register the saved hotkeys system-wide. Returns how many were registered. */
+  async startGlobalHotkeys(): Promise<number> {
+    return await invoke("start_global_hotkeys");
+  },
+
+  async stopGlobalHotkeys(): Promise<void> {
+    return await invoke("stop_global_hotkeys");
+  },
+
+  async getHotkeyConfig(): Promise<HotkeyConfig> {
+    return await invoke("get_hotkey_config");
+  },
+
+  async setHotkeyConfig(config: HotkeyConfig): Promise<void> {
+    return await invoke("set_hotkey_config", { config });
+  },
+
+  async setTrackColor(trackId: number, color: string | null): Promise<void> {
+    return await invoke("set_track_color", { trackId, color });
+  },
+
+  /** Set or clear a "don't play again before" date (YYYY-MM-DD); cooled-down tracks are left out of built and AI playlists */
+  async setTrackCooldown(trackId: number, until: string | null): Promise<void> {
+    return await invoke("set_track_cooldown", { trackId, until });
+  },
+
+  /** Tracks currently in cooldown, as track ID -> date */
+  async getTrackCooldowns(): Promise<Record<number, string>> {
+    return await invoke("get_track_cooldowns");
+  },
+
+  /** Look again for a cover.jpg/folder.jpg next to the track's file; returns the new artwork path */
+  async refreshArtwork(trackId: number): Promise<string | null> {
+    return await invoke("refresh_artwork", { trackId });
+  },
+
+  /** Path of the track's cover thumbnail (JPEG, rendered on first use; load with convertFileSrc), null without artwork */
+  async getArtworkThumbnail(trackId: number, size: 64 | 256): Promise<string | null> {
+    return await invoke("get_artwork_thumbnail", { trackId, size });
+  },
+
+  // User-defined track fields ("Vinyl owned", "Stems available", ...)
+  async getCustomFields(): Promise<CustomField[]> {
+    return await invoke("get_custom_fields");
+  },
+
+  async createCustomField(name: string, fieldType: CustomFieldType): Promise<CustomField> {
+    return await invoke("create_custom_field", { name, fieldType });
+  },
+
+  /** Rename or reorder a field (the type can't change) */
+  async updateCustomField(id: number, name: string, sortOrder: number): Promise<CustomField> {
+    return await invoke("update_custom_field", { id, name, sortOrder });
+  },
+
+  /** Delete a field and all its values */
+  async deleteCustomField(id: number): Promise<void> {
+    return await invoke("delete_custom_field", { id });
+  },
+
+  /** A track's values as field ID -> value */
+  async getTrackCustomFields(trackId: number): Promise<Record<number, string>> {
+    return await invoke("get_track_custom_fields", { trackId });
+  },
+
+  /** Every track's values: track ID -> field ID -> value */
+  async getAllCustomFieldValues(): Promise<Record<number, Record<number, string>>> {
+    return await invoke("get_all_custom_field_values");
+  },
+
+  /** Set a value (checked against the field type); null or blank clears it. Returns the stored value. */
+  async setTrackCustomField(trackId: number, fieldId: number, value: string | null): Promise<string | null> {
+    return await invoke("set_track_custom_field", { trackId, fieldId, value });
+  },
+
+  // Purchase info
+  async getTrackPurchase(trackId: number): Promise<TrackPurchase> {
+    return await invoke("get_track_purchase", { trackId });
+  },
+
+  /** Blank fields clear; returns what was stored */
+  async setTrackPurchase(trackId: number, purchase: TrackPurchase): Promise<TrackPurchase> {
+    return await invoke("set_track_purchase", { trackId, purchase });
+  },
+
+  async getPurchaseSources(): Promise<PurchaseSourceCount[]> {
+    return await invoke("get_purchase_sources");
+  },
+
+  /** Guess purchase info from folder names for tracks without any; returns the number filled in */
+  async detectPurchaseSources(): Promise<number> {
+    return await invoke("detect_purchase_sources");
+  },
+
+  /** IDs of the matching tracks out of trackIds (or the whole library), order kept */
+  async filterTracksByPurchase(filter: PurchaseFilter, trackIds?: number[]): Promise<number[]> {
+    return await invoke("filter_tracks_by_purchase", { trackIds: trackIds ?? null, filter });
+  },
+
+  /** Run an ordered list of actions on each track; each track's DB changes are transactional */
+  async runBatchActions(trackIds: number[], actions: BatchAction[]): Promise<BatchTrackResult[]> {
+    return await invoke("run_batch_actions", { trackIds, actions });
+  },
+
+  /** Auto-analysis setting and background queue progress */
+  async getAnalysisQueueStatus(): Promise<AnalysisQueueStatus> {
+    return await invoke("get_analysis_queue_status");
+  },
+
+  /** Turn auto-analysis of new imports on/off. Enabling queues tracks still missing analysis; returns how many. */
+  async setAutoAnalysis(enabled: boolean): Promise<number> {
+    return await invoke("set_auto_analysis", { enabled });
+  },
+
+  /** Generate waveforms for the visible rows first; replaces the previous request (pass [] to cancel).
+   *  Emits "waveform-ready" per track; returns how many were queued. */
+  async requestWaveforms(trackIds: number[]): Promise<number> {
+    return await invoke("request_waveforms", { trackIds });
+  },
+
+  /** Analyze a playlist's tracks ahead of the auto-analysis backlog (all kinds if none given);
+   *  only what's missing is computed. Emits "track-analyzed" per track; returns how many were queued. */
+  async analyzePlaylist(playlistId: number, kinds?: AnalysisKind[]): Promise<number> {
+    return await invoke("analyze_playlist", { playlistId, kinds: kinds ?? null });
+  },
+
+  /** Tracks whose last analysis failed, most recent first */
+  async getAnalysisFailures(): Promise<AnalysisFailure[]> {
+    return await invoke("get_analysis_failures");
+  },
+
+  /** Forget failures (of the given tracks, or all) and queue the tracks again; returns how many were queued */
+  async retryFailedAnalyses(trackIds?: number[]): Promise<number> {
+    return await invoke("retry_failed_analyses", { trackIds: trackIds ?? null });
+  },
+
+  // Listening sessions and playlist drafts
+  async getListeningSessions(limit?: number): Promise<ListeningSession[]> {
+    return await invoke("get_listening_sessions", { limit });
+  },
+
+  /** Cluster a session's kept tracks into playlist drafts (replaces the session's earlier drafts) */
+  async suggestPlaylistFromSession(sessionId: number, aiNames = false): Promise<PlaylistDraft[]> {
+    return await invoke("suggest_playlist_from_session", { sessionId, aiNames });
+  },
+
+  async getPlaylistDrafts(): Promise<PlaylistDraft[]> {
+    return await invoke("get_playlist_drafts");
+  },
+
+  /** Create a real playlist from a draft; returns the new playlist ID */
+  async acceptPlaylistDraft(draftId: number, name?: string): Promise<number> {
+    return await invoke("accept_playlist_draft", { draftId, name });
+  },
+
+  async discardPlaylistDraft(draftId: number): Promise<void> {
+    return await invoke("discard_playlist_draft", { draftId });
+  },
+
+  /** Render a playlist's tracklist as HTML (UTF-8) or PDF; returns the file bytes */
+  async renderTracklist(playlistId: number, format: TracklistFormat): Promise<Uint8Array> {
+    const bytes: number[] = await invoke("render_tracklist", { playlistId, format });
+    return new Uint8Array(bytes);
+  },
+
+  /** Write a track's beat/bar timestamps as CSV or Resolume marker XML, for syncing visuals */
+  async exportBeatMarkers(trackId: number, format: BeatMarkerFormat, path: string): Promise<BeatMarkerExport> {
+    return await invoke("export_beat_markers", { trackId, format, path });
+  },
 };
@@ -17,6 +17,25 @@ export interface GeneratedPlaylist {
   description: string;
   track_ids: number[];
   reasoning: string;
+  /** Share of the library the AI was shown (0-100); below 100 on sampled big libraries */
+  context_coverage: number;
+}
+
+/**
+ * Playlist after an AI refinement was applied
+ */
+export interface RefinedPlaylist {
+  playlist_id: number;
+  /** Tracks after the changes, in order */
+  track_ids: number[];
+  added: number[];
+  removed: number[];
+  moved: number[];
+  /** Proposed changes that were dropped (unknown track, already in the playlist, ...) */
+  rejected: string[];
+  reasoning: string;
+  /** Share of the library the AI was shown (0-100) */
+  context_coverage: number;
 }
 
 /**
@@ -30,3 +49,46 @@ export interface AIChatState {
   pendingPlaylist: GeneratedPlaylist | null;
   error: string | null;
 }
+
+/**
+ * Service an API key is stored for (OS keychain)
+ */
+export type ApiProvider = 'anthropic' | 'openai' | 'acoustid' | 'discogs';
+
+/**
+ * Key status for one provider
+ */
+export interface ApiKeyStatus {
+  provider: ApiProvider;
+  configured: boolean;
+  /** Result of the last validation; null if not validated since the key was set */
+  valid: boolean | null;
+  message: string | null;
+  /** Unix seconds */
+  checked_at: number | null;
+}
+
+/**
+ * Claude tokens used by one feature in a month
+ */
+export interface AiUsagePurpose {
+  purpose: 'playlist_generation' | 'playlist_refinement' | 'chat' | 'session_naming';
+  requests: number;
+  input_tokens: number;
+  output_tokens: number;
+  estimated_cost_usd: number;
+}
+
+/**
+ * Claude tokens used in a month
+ */
+export interface AiUsageMonth {
+  /** "YYYY-MM" (UTC) */
+  month: string;
+  requests: number;
+  input_tokens: number;
+  output_tokens: number;
+  /** At list prices; the Anthropic console has the exact figure */
+  estimated_cost_usd: number;
+  by_purpose: AiUsagePurpose[];
+}
@@ -9,6 +9,11 @@ export interface Track {
   album?: string;
   album_artist?: string;
   track_number?: number;
+  disc_number?: number;
+  /** Tracks on the disc (or album) */
+  total_tracks?: number;
+  isrc?: string;
+  catalog_number?: string;
   year?: number;
   label?: string;
   duration_ms?: number;
@@ -24,18 +29,30 @@ export interface Track {
   artwork_path?: string;
   genre?: string;
   genre_source?: string; // 'user' | 'tag' | 'ai'
+  energy_level?: number; // 1-10
+  color?: string; // "#RRGGBB"
   // Analysis fields (from track_analysis table via LEFT JOIN)
   bpm?: number;
   bpm_confidence?: number;
   musical_key?: string;
   key_confidence?: number;
+  key_color?: string | null; // "#rrggbb", from the key color settings
 }
 
+/** A Track field a list query can be limited to (id always comes along) */
+export type TrackField = keyof Track;
+
 export interface ScanResult {
   total_files: number;
   imported: number;
+  /** Known files whose size or mtime changed and were re-read */
+  updated: number;
+  /** New paths matched to a library track whose file had moved */
+  moved: number;
   skipped: number;
   errors: ScanError[];
+  /** Symlinks not followed, or leading to a folder or file already scanned */
+  symlinks_skipped: string[];
 }
 
 export interface ScanError {
@@ -55,11 +72,12 @@ export interface FolderInfo {
 export interface Playlist {
   id: number;
   name: string;
-  playlist_type: string; // "manual" | "smart" | "folder"
+  playlist_type: string; // "manual" | "smart" | "folder" | "mirror"
   parent_id: number | null;
   track_count: number;
   created_at?: string;
   updated_at?: string;
+  source_folder?: string | null; // set for "mirror" playlists
 }
 
 // Analysis result types
@@ -87,6 +105,47 @@ export interface TrackAnalysis {
   dynamic_range?: number;
   spectral_centroid?: number;
   analyzed_at?: string;
+  /** Dead air at the start/end (ms), detected with the waveform */
+  silence_lead_ms?: number;
+  silence_tail_ms?: number;
+  /** Set once a tempo curve has been analyzed: true if the tempo drifts */
+  tempo_variable?: boolean;
+}
+
+/** Track column listed by getDistinctValues */
+export type DistinctColumn = "genre" | "key" | "year" | "label" | "format";
+
+/** A column value and how many tracks have it */
+export interface DistinctValue {
+  value: string;
+  count: number;
+}
+
+/** What getTracksGrouped groups by */
+export type TrackGrouping = "album" | "artist" | "genre" | "key" | "bpm_decade" | "month_added";
+
+/** Filters of a grouped view; empty lists and missing bounds don't filter */
+export interface TrackFilter {
+  genres?: string[];
+  keys?: string[];
+  min_bpm?: number;
+  max_bpm?: number;
+  min_rating?: number;
+  /** Text in the title, artist, album or label */
+  search?: string;
+}
+
+/** Header of a group of tracks */
+export interface TrackGroup {
+  /** "120" for 120-129 BPM, "2024-05" for a month added; null for tracks without a value */
+  value: string | null;
+  track_count: number;
+  total_duration_ms: number;
+  min_bpm: number | null;
+  avg_bpm: number | null;
+  max_bpm: number | null;
+  total_plays: number;
+  last_added: string | null;
 }
 
 // Genre types
@@ -101,3 +160,855 @@ export interface GenreDefinition {
   color?: string;
   sort_order: number;
 }
+
+export interface GenreMergeSuggestion {
+  variants: GenreCount[];
+  suggested_name: string;
+  total_tracks: number;
+}
+
+// Onboarding types
+export interface OnboardingState {
+  steps: string[]; // "welcome" | "library_folders" | "initial_scan" | "analysis"
+  completed_steps: string[];
+  current_step: string | null;
+  is_complete: boolean;
+  has_tracks: boolean;
+}
+
+export interface MusicFolderSuggestion {
+  path: string;
+  label: string;
+  audio_file_count: number;
+  count_is_estimate: boolean;
+  already_added: boolean;
+}
+
+// Rekordbox import types
+export interface RekordboxImportResult {
+  tracks_found: number;
+  tracks_matched: number;
+  unmatched_paths: string[];
+  playlists_imported: number;
+  cues_imported: number;
+  beat_grids_imported: number;
+}
+
+// Report types
+export interface BridgeTrack {
+  track_id: number;
+  title?: string;
+  artist?: string;
+  bpm?: number;
+  musical_key?: string;
+}
+
+export interface MixabilityReport {
+  total_tracks: number;
+  tracks_with_key: number;
+  tracks_with_bpm: number;
+  key_distribution: { camelot: string; count: number; compatible_count: number }[];
+  weak_keys: {
+    camelot: string;
+    count: number;
+    compatible_count: number;
+    bridge_tracks: BridgeTrack[];
+  }[];
+  bpm_gaps: { from_bpm: number; to_bpm: number; bridge_tracks: BridgeTrack[] }[];
+}
+
+export interface SkippedTrack {
+  track: Track;
+  skip_count: number;
+  play_count: number;
+}
+
+export interface EnergyExtractor {
+  /** Tag fields searched in order: "comment", "grouping", "mood", "description" or a custom field name */
+  fields: string[];
+  /** Words that introduce the level, e.g. "energy" in "Energy 7" */
+  keywords: string[];
+}
+
+export interface StreamMimeConfig {
+  /** Detect the format from the file's contents instead of trusting the extension */
+  probe: boolean;
+  /** Extension (no dot) -> Content-Type to send, e.g. { m4a: "audio/x-m4a" } */
+  overrides: Record<string, string>;
+}
+
+export interface InterruptedBatch {
+  /** "scan:<path>", "analyze_bpm" or "analyze_keys" */
+  job: string;
+  detail?: string;
+  processed: number;
+  total?: number;
+  started_at: string;
+  updated_at: string;
+}
+
+/** When the scanner hashes files: every new/changed file, or only when duplicate detection needs it */
+export type ScanHashMode = "full" | "lazy";
+
+/** Whether scans of a library folder follow symlinks (each folder/file still once) or skip them */
+export type SymlinkMode = "follow" | "skip";
+
+export interface FolderSymlinkMode {
+  folder: string;
+  mode: SymlinkMode;
+}
+
+/** Before writing to a file modified by another program: refuse, or re-read it first */
+export type WriteConflictMode = "refuse" | "reread";
+
+export interface ComparedTrack {
+  track_id: number;
+  title?: string;
+  artist?: string;
+  duration_ms?: number;
+  bpm?: number;
+  musical_key?: string;
+  loudness_lufs?: number;
+  energy_level?: number;
+  /** Peak level (0-1) resampled to a fixed length; absent until a waveform exists */
+  energy_curve?: number[];
+}
+
+export interface TrackComparison {
+  a: ComparedTrack;
+  b: ComparedTrack;
+  /** b - a */
+  bpm_difference?: number;
+  key_verdict?: "same" | "compatible" | "clash";
+  /** b - a, in LU */
+  lufs_difference?: number;
+  /** b - a */
+  duration_difference_ms?: number;
+}
+
+export type MidiAction =
+  | "load_selected"
+  | "play_pause"
+  | "seek"
+  | "seek_forward"
+  | "seek_backward"
+  | "rate_up"
+  | "rate_down"
+  | "browse";
+
+export interface MidiMapping {
+  kind: "note" | "cc";
+  /** 1-16; omit to match any channel */
+  channel?: number;
+  /** Note or CC number (0-127) */
+  number: number;
+  action: MidiAction;
+}
+
+/** Payload of the "midi-message" event (for MIDI learn) */
+export interface MidiMessage {
+  kind: "note" | "cc";
+  channel: number;
+  number: number;
+  value: number;
+}
+
+/** Payload of the "midi-action" event */
+export interface MidiActionEvent {
+  action: MidiAction;
+  /** 0-1 for seek, signed row count for browse, 1 for buttons */
+  value: number;
+}
+
+export type HotkeyAction =
+  | { type: "play_pause" }
+  | { type: "next_in_queue" }
+  | { type: "rate"; stars: number }
+  | { type: "add_to_working_playlist" };
+
+export interface HotkeyBinding {
+  /** e.g. "CommandOrControl+Alt+P" */
+  accelerator: string;
+  action: HotkeyAction;
+}
+
+export interface HotkeyConfig {
+  bindings: HotkeyBinding[];
+  working_playlist_id: number | null;
+}
+
+/** Payload of the "hotkey-action" event */
+export interface HotkeyActionEvent {
+  action: HotkeyAction;
+  /** The playing track (rate and add already applied to it) */
+  track_id: number | null;
+  /** Why a rate or add didn't happen */
+  error: string | null;
+}
+
+export type BatchAction =
+  | { type: "set_genre"; genre: string }
+  | { type: "set_color"; color: string | null }
+  | { type: "add_to_playlist"; playlist_id: number }
+  | { type: "analyze" }
+  | { type: "write_tags" };
+
+export interface BatchTrackResult {
+  track_id: number;
+  success: boolean;
+  /** Names of the actions applied, in order */
+  applied: string[];
+  error?: string;
+}
+
+
+/** Background auto-analysis queue (see "track-analyzed" events) */
+export interface AnalysisQueueStatus {
+  enabled: boolean;
+  pending: number;
+  current: number | null;
+  /** Visible-row waveforms still to generate */
+  waveforms_pending: number;
+  /** Of `pending`: tracks queued by analyzePlaylist */
+  playlist_pending: number;
+}
+
+/** What analyzePlaylist computes */
+export type AnalysisKind = "bpm" | "key" | "waveform" | "loudness";
+
+/** A track whose last analysis failed (see getAnalysisFailures) */
+export interface AnalysisFailure {
+  track_id: number;
+  file_path: string;
+  title?: string;
+  artist?: string;
+  error_class: "missing_file" | "decode" | "analysis";
+  message: string;
+  /** Failures in a row since the file last changed */
+  attempts: number;
+  last_failed_at: string;
+  /** Failed often enough to be left out of analysis until retried */
+  skipped: boolean;
+}
+
+/** Payload of the "track-analyzed" event */
+export interface TrackAnalyzedEvent {
+  track_id: number;
+  error: string | null;
+  pending: number;
+}
+
+
+/** Output format for printable tracklists */
+export type TracklistFormat = "html" | "pdf" | "csv";
+
+/** Output format for exportBeatMarkers */
+export type BeatMarkerFormat = "csv" | "resolume";
+
+/** What exportBeatMarkers wrote */
+export interface BeatMarkerExport {
+  path: string;
+  beats: number;
+  bars: number;
+  bpm: number | null;
+  /** "rekordbox", "analysis" or "estimated" (constant grid from the analyzed BPM) */
+  source: string;
+}
+
+
+/** Everything the phone needs to connect in one QR scan */
+export interface CompanionPairingPayload {
+  url: string;
+  /** Single-use, expires after expires_in seconds */
+  pairing_code: string;
+  expires_in: number;
+  /** Always null while the companion server is plain HTTP */
+  tls_fingerprint: string | null;
+  /** Encode this in the QR code */
+  qr_url: string;
+  deep_link: string;
+}
+
+/** Latency summary for one companion server endpoint (route pattern, e.g. /api/tracks/{id}) */
+export interface CompanionEndpointMetrics {
+  endpoint: string;
+  requests: number;
+  errors: number;
+  avg_ms: number;
+  max_ms: number;
+}
+
+/** Companion server counters since it started (also served at /api/metrics) */
+export interface CompanionMetrics {
+  uptime_secs: number;
+  requests: number;
+  /** Responses with a 4xx/5xx status */
+  errors: number;
+  active_streams: number;
+  bytes_served: number;
+  /** Busiest endpoints first */
+  endpoints: CompanionEndpointMetrics[];
+}
+
+/** What auto-advance plays after the current track */
+export type PlayContextType = "folder" | "playlist" | "queue";
+
+export interface PlayContext {
+  context_type: PlayContextType;
+  /** Folder path or playlist ID; null for the queue */
+  id: string | null;
+  position: number;
+  track_count: number;
+}
+
+/** Payload of the "track-changed" event */
+export interface TrackChangedEvent {
+  track_id: number;
+  context_type: PlayContextType;
+  /** Position in the folder/playlist; null for the queue */
+  position: number | null;
+}
+
+/** Payload of the "playback-position" event (~every 250ms while streaming) */
+export interface PlaybackPositionEvent {
+  track_id: number | null;
+  /** Estimated audible position */
+  position_ms: number;
+  /** Audio sent to the player but not yet heard */
+  buffered_ms: number;
+  duration_ms: number;
+  /** Times the decoder fell behind since the stream started */
+  underruns: number;
+}
+
+/** How audio is sent from the decoder to the player */
+export interface StreamConfig {
+  /** "i16" halves the payload of each chunk */
+  sample_format: "f32" | "i16";
+  /** Minimum chunk length in ms (0 = one decoded packet each, max 500) */
+  chunk_ms: number;
+}
+
+/** Payload of the "playback-underrun" event: the player (nearly) ran out of audio */
+export interface PlaybackUnderrunEvent {
+  track_id: number | null;
+  position_ms: number;
+  underruns: number;
+}
+
+/** Payload of the "waveform-ready" event (from requestWaveforms) */
+export interface WaveformReadyEvent {
+  track_id: number;
+  error: string | null;
+}
+
+/** A run of plays without a long pause */
+export interface ListeningSession {
+  /** First play-history entry of the session */
+  id: number;
+  started_at: string;
+  ended_at: string;
+  play_count: number;
+  /** Distinct tracks that weren't skipped */
+  kept_count: number;
+}
+
+/** A playlist proposed from a listening session, waiting to be accepted or discarded */
+export interface PlaylistDraft {
+  id: number;
+  session_id: number;
+  name: string;
+  tracks: Track[];
+  created_at: string;
+}
+
+/** One copy in a duplicate cluster */
+export interface DuplicateCandidate {
+  track: Track;
+  playlist_count: number;
+}
+
+/** Tracks that look like copies of each other ("hash" = same content, "filename_size" = same name and size) */
+export interface DuplicateCluster {
+  reasons: Array<"hash" | "filename_size">;
+  tracks: DuplicateCandidate[];
+}
+
+/** How much each pruning criterion counts (0 ignores it) */
+export interface PruneWeights {
+  never_played: number;
+  low_rating: number;
+  duplicate: number;
+  old_import: number;
+  not_in_playlist: number;
+  length: number;
+}
+
+/** Options for suggestPruneCandidates; omitted fields use the defaults */
+export interface PruneOptions {
+  weights?: Partial<PruneWeights>;
+  /** Imported at least this many days ago counts as old (default 365) */
+  min_age_days?: number;
+  /** Minimum score 0-1 (default 0.5) */
+  min_score?: number;
+  /** Default 200 */
+  limit?: number;
+}
+
+export type PruneReason =
+  | "never_played"
+  | "low_rating"
+  | "duplicate"
+  | "other_version"
+  | "old_import"
+  | "not_in_playlist"
+  | "long";
+
+export interface PruneCandidate {
+  track: Track;
+  /** Weighted score, 0-1 */
+  score: number;
+  reasons: PruneReason[];
+  playlist_count: number;
+}
+
+export interface PruneSuggestions {
+  candidates: PruneCandidate[];
+  total_size_bytes: number;
+}
+
+/** A saved state of a playlist's tracks */
+export interface PlaylistSnapshot {
+  id: number;
+  playlist_id: number;
+  track_ids: number[];
+  track_count: number;
+  /** auto: before a change; restore: before a restore */
+  reason: "auto" | "manual" | "restore";
+  created_at: string;
+}
+
+/** Technical problem found by the quality check */
+export type QualityFlag = "lossy_cutoff" | "low_bandwidth" | "dc_offset" | "hum";
+
+/** Result of a track's quality check */
+export interface QualityCheck {
+  track_id: number;
+  /** lossy_cutoff: a lossless file whose spectrum stops like an MP3's (fake lossless) */
+  flags: QualityFlag[];
+  /** Where the spectrum stops, if below 20 kHz */
+  cutoff_hz: number | null;
+  /** 50 or 60 when mains hum was found */
+  hum_hz: number | null;
+  dc_offset: number;
+}
+
+export interface QualityIssue {
+  track: Track;
+  check: QualityCheck;
+}
+
+/** Result of re-hashing a track's file against its stored hash */
+export type VerifyStatus = "ok" | "mismatch" | "missing" | "unreadable" | "baselined";
+
+export interface VerificationResult {
+  track_id: number;
+  status: VerifyStatus;
+  expected_hash: string;
+  actual_hash: string | null;
+}
+
+export interface SidecarSettings {
+  /** Write a track's .recodeck sidecar after it is analyzed */
+  write: boolean;
+  /** Read sidecars when scanning files in */
+  read: boolean;
+}
+
+export interface SidecarResult {
+  /** Sidecars written / tracks filled from a sidecar */
+  processed: number;
+  /** Tracks with nothing to write, or without a matching sidecar */
+  skipped: number;
+  errors: string[];
+}
+
+export interface VerifySummary {
+  checked: number;
+  ok: number;
+  mismatch: number;
+  missing: number;
+  unreadable: number;
+  baselined: number;
+}
+
+export interface VerificationProblem {
+  track_id: number;
+  file_path: string;
+  title: string | null;
+  artist: string | null;
+  status: VerifyStatus;
+  expected_hash: string;
+  actual_hash: string | null;
+  verified_at: string;
+}
+
+export interface VerificationReport {
+  total_tracks: number;
+  verified: number;
+  oldest_verified_at: string | null;
+  by_status: Partial<Record<VerifyStatus, number>>;
+  problems: VerificationProblem[];
+}
+
+/** Expected BPM range for a genre (e.g. Drum & Bass 160-180) */
+export interface GenreBpmRange {
+  genre: string;
+  min_bpm: number;
+  max_bpm: number;
+}
+
+/** How analyzed BPMs are rounded before they are stored */
+export interface BpmRounding {
+  /** 0.01, 0.1 or 1; 0 keeps the detected value */
+  step: number;
+  /** Tempos a BPM within snap_tolerance is pulled onto (e.g. 125, 128) */
+  snap_to: number[];
+  snap_tolerance: number;
+  /** Results less confident than this (0-1) are stored as detected */
+  min_confidence: number;
+}
+
+/** A stored BPM moved into its genre's range (or rounded by requantizeBpms) */
+export interface BpmCorrection {
+  track_id: number;
+  old_bpm: number;
+  new_bpm: number;
+}
+
+/** Watch folder rule: new audio files in `folder` are imported, tagged and added to a playlist */
+export interface WatchRule {
+  id: number;
+  folder: string;
+  tag: string | null;
+  playlist_name: string | null;
+  enabled: boolean;
+}
+
+/** A library folder watched by polling (network shares send no change notifications) */
+export interface PolledFolder {
+  folder: string;
+  interval_secs: number;
+}
+
+/** What the watcher does with tracks whose file is deleted, once it has stayed gone for the grace period */
+export interface DeletedFileSettings {
+  /** "remove" also drops analysis, cues and playlist entries (undoable from the journal) */
+  action: "ignore" | "mark_missing" | "remove";
+  grace_period_secs: number;
+}
+
+/** A background library change that can be undone */
+export interface JournalEntry {
+  id: number;
+  /** "mark_missing" or "remove_tracks" */
+  operation: string;
+  description: string;
+  created_at: string;
+  undone_at: string | null;
+}
+
+/** A track whose file the watcher saw deleted */
+export interface MissingTrack {
+  track_id: number;
+  file_path: string;
+  title: string | null;
+  artist: string | null;
+  missing_since: string;
+}
+
+/** What cleaning up tracks outside the library folders would remove */
+export interface StrayTracksPreview {
+  count: number;
+  total_tracks: number;
+  /** The first few paths */
+  sample_paths: string[];
+  /** No library folders are configured: every track is stray, removed only with force */
+  requires_force: boolean;
+  confirm_token: string;
+}
+
+/** A Camelot key's color (Settings > Appearance > Key Colors) */
+export interface KeyColor {
+  /** Camelot key, e.g. "8A" */
+  key: string;
+  /** "#rrggbb" */
+  color: string;
+  is_default: boolean;
+}
+
+/** Result of mirroring playlists as .m3u files into the export folder */
+export interface PlaylistExportSummary {
+  playlists: number;
+  written: number;
+  unchanged: number;
+  removed: number;
+}
+
+/** Changes made by merging one side's library sync snapshot into the other */
+export interface SyncStats {
+  tracks_updated: number;
+  cues_updated: number;
+  playlists_created: number;
+  playlists_updated: number;
+  /** Tracks the other side has that this library doesn't (matched by file hash) */
+  unknown_tracks: number;
+}
+
+/** Result of a two-way sync: `pulled` changed this library, `pushed` the peer's */
+export interface LibrarySyncResult {
+  peer_url: string;
+  pulled: SyncStats;
+  pushed: SyncStats;
+}
+
+/** What a performance trace event timed: a Tauri command, an SQL statement, or a database-lock hold */
+export type PerfKind = "command" | "query" | "db_lock";
+
+export interface PerfEvent {
+  kind: PerfKind;
+  /** Command name, normalized SQL, or the file:line that took the lock */
+  name: string;
+  duration_us: number;
+  /** Unix ms */
+  at: number;
+}
+
+export interface PerfSummary {
+  kind: PerfKind;
+  name: string;
+  count: number;
+  total_us: number;
+  avg_us: number;
+  p95_us: number;
+  max_us: number;
+}
+
+/** get_performance_stats; `enabled` is false in builds without the "perf-trace" feature */
+export interface PerformanceStats {
+  enabled: boolean;
+  capacity: number;
+  /** Most recent first */
+  recent: PerfEvent[];
+  /** Slowest in total first */
+  summary: PerfSummary[];
+}
+
+/** BPM measured over one window of a track */
+export interface TempoPoint {
+  /** Middle of the window */
+  time_ms: number;
+  bpm: number;
+}
+
+/** A track's tempo over time (analyze_tempo_curve / get_tempo_curve) */
+export interface TempoCurve {
+  track_id: number;
+  window_secs: number;
+  points: TempoPoint[];
+  min_bpm: number;
+  max_bpm: number;
+  /** BPM between the 10th and 90th percentile window */
+  spread: number;
+  /** Tempo drifts enough to need manual beatmatching */
+  variable: boolean;
+}
+
+/** Filters for build_playlist_to_duration; omitted fields don't filter */
+export interface SeedFilters {
+  genres?: string[];
+  min_bpm?: number;
+  max_bpm?: number;
+  /** Camelot keys, e.g. "8A" */
+  keys?: string[];
+  /** Also accept keys that mix harmonically with `keys` */
+  harmonic_keys?: boolean;
+  min_energy?: number;
+  max_energy?: number;
+  min_rating?: number;
+  /** Also pick tracks in cooldown (see setTrackCooldown) */
+  include_cooled_down?: boolean;
+  /** Conditions on user-defined fields (all must match) */
+  custom_fields?: CustomFieldFilter[];
+  /** Fill a missing BPM range and keys from the chosen genres' profiles */
+  use_genre_profiles?: boolean;
+}
+
+/** Typical BPM range and keys of a genre, learned from the library */
+export interface GenreProfile {
+  genre: string;
+  track_count: number;
+  min_bpm: number | null;
+  median_bpm: number | null;
+  max_bpm: number | null;
+  /** Camelot keys, most common first */
+  common_keys: string[];
+}
+
+export type CustomFieldType = "text" | "number" | "boolean" | "date";
+
+/** A user-defined track column */
+export interface CustomField {
+  id: number;
+  name: string;
+  field_type: CustomFieldType;
+  sort_order: number;
+}
+
+/** Condition on a custom field; tracks without a value never match */
+export interface CustomFieldFilter {
+  field_id: number;
+  /** Exact value (ignoring case); omitted = any value */
+  value?: string;
+  /** Bounds for number fields */
+  min?: number;
+  max?: number;
+}
+
+/** Where a track was bought; guessed from folder names on scan, editable */
+export interface TrackPurchase {
+  source: string | null;
+  /** YYYY-MM-DD */
+  date: string | null;
+  order_id: string | null;
+}
+
+export interface PurchaseSourceCount {
+  source: string;
+  count: number;
+}
+
+/** Omitted fields don't filter */
+export interface PurchaseFilter {
+  /** Any of these sources (ignoring case) */
+  sources?: string[];
+  /** true: only tracks with a source; false: only tracks without one */
+  has_source?: boolean;
+  /** Purchase date bounds (YYYY-MM-DD, inclusive) */
+  from_date?: string;
+  to_date?: string;
+}
+
+/** Candidate tracks for a fixed-length slot, ordered by BPM then energy */
+export interface DurationPlaylist {
+  tracks: Track[];
+  total_duration_ms: number;
+  target_duration_ms: number;
+  /** Tracks that matched the filters */
+  matching_tracks: number;
+}
+
+/** Another version of a song already in a playlist */
+export interface DuplicateVersion {
+  track_id: number;
+  title?: string;
+  artist?: string;
+  reason: "title_artist" | "fingerprint";
+}
+
+/** add_track_to_playlist result */
+export interface AddTrackResult {
+  /** False if already in the playlist or held back by the version check */
+  added: boolean;
+  /** The version check found other versions; the track was not added */
+  possible_duplicate: boolean;
+  duplicate_versions: DuplicateVersion[];
+}
+
+/** A playlist track already listened to while playing through the playlist */
+export interface PlaylistAudition {
+  track_id: number;
+  auditioned_at: string;
+}
+
+/** What export_profile wrote or import_profile applied */
+export interface ProfileSummary {
+  genres: number;
+  tags: number;
+  saved_views: number;
+  settings: number;
+  /** Import only: genres, tags and views that didn't exist yet */
+  created: number;
+  /** Import only: non-portable settings in the file that were ignored */
+  skipped_settings: string[];
+}
+
+/** A gig: date, venue and slot length, with the playlists prepared for it */
+export interface Gig {
+  id: number;
+  name: string;
+  venue: string | null;
+  /** YYYY-MM-DD */
+  date: string;
+  set_length_minutes: number;
+  notes: string | null;
+  playlist_ids: number[];
+}
+
+/** A playlist linked to a gig, measured against the slot */
+export interface GigPlaylistSummary {
+  playlist_id: number;
+  name: string;
+  track_count: number;
+  duration_ms: number;
+  /** Tracks with both BPM and key */
+  analyzed_tracks: number;
+  covers_slot: boolean;
+}
+
+/** How prepared a gig is */
+export interface GigOverview {
+  gig: Gig;
+  set_length_ms: number;
+  playlists: GigPlaylistSummary[];
+  /** Distinct tracks across the linked playlists */
+  total_tracks: number;
+  total_duration_ms: number;
+  analyzed_tracks: number;
+  /** 0-1 */
+  analysis_coverage: number;
+  missing_files: number;
+  /** A playlist covers the slot, every track is analyzed and no file is missing */
+  ready: boolean;
+}
+
+/** A library track heard in a recorded set */
+export interface IdentifiedTrack {
+  track_id: number;
+  title: string | null;
+  artist: string | null;
+  /** Where the track is heard in the recording; neighbours overlap during transitions */
+  start_ms: number;
+  end_ms: number;
+  /** Position in the track at start_ms */
+  track_position_ms: number;
+  /** 0-1 */
+  confidence: number;
+}
+
+/** Tracklist reconstructed from a recorded set */
+export interface SetTracklist {
+  recording_duration_ms: number;
+  tracks: IdentifiedTrack[];
+  /** Library tracks the recording was compared with */
+  compared_tracks: number;
+  /** Tracks fingerprinted during this call */
+  fingerprinted_tracks: number;
+}
+
+/** Payload of the "track-renamed" event, sent when a track's file_path changes */
+export interface TrackRenamedEvent {
+  track_id: number;
+  old_path: string;
+  new_path: string;
+}